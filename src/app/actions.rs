@@ -1,10 +1,11 @@
 use std::time::Instant;
 
 use crate::db::AuditAction;
-use crate::input::keymap::{parse_command, Action};
-use crate::ui::{components::MessageType, renderer::View};
+use crate::input::keymap::{parse_command, suggest_command, Action};
+use crate::ui::{components::MessageType, renderer::{DetailLayout, View}};
 
 use super::config::PendingAction;
+use super::reauth_handler::Guarded;
 use super::App;
 
 impl App {
@@ -18,10 +19,25 @@ impl App {
             Action::PageDown => self.page_move(|ls, h| ls.page_down(h.saturating_sub(1)))?,
             Action::HalfPageUp => self.page_move(|ls, h| ls.page_up(h / 2))?,
             Action::HalfPageDown => self.page_move(|ls, h| ls.page_down(h / 2))?,
+            Action::PreviousBlock => self.block_move(1, false)?,
+            Action::NextBlock => self.block_move(1, true)?,
+            Action::ScrollDetailUp => self.detail_scroll.scroll_up(1),
+            // Rendering keeps the offset within the content
+            Action::ScrollDetailDown => self.detail_scroll.scroll_down(1, usize::MAX),
+            Action::SetMark(mark) => self.set_mark(mark),
+            Action::JumpToMark(mark) => self.jump_to_mark(mark)?,
+            Action::Repeat(count, action) => self.repeat_action(count, *action)?,
 
             Action::ShowHelp => self.show_help(),
             Action::ShowTags => self.show_tags()?,
+            Action::ShowTypes => self.show_types()?,
+            Action::FilterExpiring => self.toggle_expiring_filter()?,
+            Action::FilterRotation => self.toggle_rotation_filter()?,
+            Action::ShowDuplicates => self.show_duplicates()?,
             Action::ShowLogs => self.show_logs()?,
+            Action::ShowHistory => self.show_history()?,
+            Action::ShowActivity => self.show_activity()?,
+            Action::ShowStats => self.show_stats()?,
             Action::ChangePassword => self.request_password_change(),
 
             Action::Select => self.select_credential()?,
@@ -31,19 +47,28 @@ impl App {
             Action::CopyUsername => self.copy_username()?,
             Action::CopyTotp => self.copy_totp()?,
             Action::CopyTotpUri => self.copy_totp_uri()?,
+            Action::CopyPublicKey => self.copy_public_key()?,
+            Action::AddToAgent => self.add_to_ssh_agent()?,
+            Action::RegenerateSecret => self.regenerate_secret()?,
+            Action::CopyUsernameThenPassword => self.copy_username_then_password()?,
             Action::TogglePasswordVisibility => self.toggle_password()?,
+            Action::ToggleDetailLayout => self.toggle_detail_layout(),
+            Action::ToggleMark => self.toggle_mark()?,
+            Action::EnterVisual => self.enter_visual(),
+            Action::ExitVisual => self.exit_visual(),
+            Action::MarkRange => self.mark_visual_range(),
 
             Action::Delete => self.initiate_delete(),
             Action::New => self.new_credential(),
             Action::Edit => self.edit_credential()?,
 
             Action::EnterCommand => self.mode_state.enter_command_mode(),
-            Action::EnterSearch => self.mode_state.enter_search_mode(),
+            Action::EnterSearch => self.begin_search(),
 
             Action::ExecuteCommand(cmd) => return self.execute_action(parse_command(&cmd)),
             Action::Search(query) => self.search_credentials(&query)?,
 
-            Action::GeneratePassword => self.generate_and_copy_password()?,
+            Action::GeneratePassword(kind) => self.generate_and_copy_password(kind.as_deref())?,
 
             Action::Confirm => self.handle_confirm()?,
             Action::Cancel => self.cancel_pending(),
@@ -53,9 +78,52 @@ impl App {
             Action::ForceQuit => return Ok(true),
             Action::Lock => self.lock(),
             Action::Export => self.export()?,
+            Action::ExportCurrent => self.export_current()?,
+            Action::Import => self.import()?,
+            Action::Share(file) => self.share(file),
+            Action::Receive(file) => self.receive(file.as_deref())?,
+            Action::Backup(name) => self.manual_backup(name.as_deref()),
+            Action::ShowRestore => self.show_restore()?,
+            Action::Restore(file) => self.request_restore(&file),
+            Action::ShowTrash => self.show_trash()?,
+            Action::Report(name) => self.show_report(name.as_deref())?,
+            Action::RestoreTrashed(id) => self.restore_trashed(&id)?,
+            Action::PurgeTrashed(id) => self.request_purge(PendingAction::PurgeTrashed(id)),
+            Action::EmptyTrash => self.request_purge(PendingAction::EmptyTrash),
+            Action::Sort(order) => self.sort_list(order.as_deref())?,
+            Action::Filter(arg) => self.filter_command(arg.as_deref())?,
+            Action::Goto(name) => self.goto_credential(name.as_deref())?,
+            Action::AddTag(tag) => self.bulk_tag(tag.as_deref(), true)?,
+            Action::RemoveTag(tag) => self.bulk_tag(tag.as_deref(), false)?,
+            Action::SetType(name) => self.bulk_set_type(name.as_deref())?,
+            Action::RenameTag(args) => self.rename_tag(args.as_deref())?,
+            Action::MergeTags(args) => self.merge_tags(args.as_deref())?,
+            Action::DeleteTag(tag) => self.request_delete_tag(tag.as_deref()),
+            Action::Set(setting) => self.set_setting(setting.as_deref(), false),
+            Action::SetAndSave(setting) => self.set_setting(Some(&setting), true),
+            Action::Keyfile(arg) => self.keyfile_command(arg.as_deref()),
+            Action::Yubikey(arg) => self.yubikey_command(arg.as_deref()),
+            Action::Tpm(arg) => self.tpm_command(arg.as_deref()),
+            Action::EncryptDb(arg) => self.encrypt_db_command(arg.as_deref()),
+            Action::EncryptMetadata(arg) => self.encrypt_metadata_command(arg.as_deref())?,
+            Action::Vacuum => self.vacuum_database()?,
+            Action::Fido2(arg) => self.fido2_command(arg.as_deref()),
+            Action::ForgetSession => self.forget_session()?,
+            Action::Hardening => self.show_hardening(),
+            Action::Wipe(arg) => self.wipe_command(arg.as_deref()),
+            Action::Duress(arg) => self.duress_command(arg.as_deref()),
+            Action::Kdf(arg) => self.kdf_command(arg.as_deref()),
+            Action::RotateKey => self.reauth_request = Some(Guarded::RotateKey),
             Action::Refresh => self.refresh_data()?,
             Action::VerifyAudit => self.verify_and_report_audit(),
-            Action::Invalid(cmd) => self.set_message(&format!("Unknown command: {}", cmd), MessageType::Error),
+            Action::Check => self.run_integrity_check(),
+            Action::Invalid(cmd) => {
+                let message = match suggest_command(&cmd) {
+                    Some(name) => format!("Unknown command: {} (did you mean :{}?)", cmd, name),
+                    None => format!("Unknown command: {}", cmd),
+                };
+                self.set_message(&message, MessageType::Error);
+            }
 
             _ => {}
         }
@@ -74,6 +142,46 @@ impl App {
         self.update_selected_detail()
     }
 
+    fn block_move(&mut self, count: usize, forward: bool) -> Result<(), Box<dyn std::error::Error>> {
+        for _ in 0..count {
+            match forward {
+                true => self.list_state.next_block(&self.credentials),
+                false => self.list_state.previous_block(&self.credentials),
+            }
+        }
+        self.update_selected_detail()
+    }
+
+    /// Counted motions move once by the whole distance, so the detail pane
+    /// decrypts only where they land
+    fn repeat_action(&mut self, count: usize, action: Action) -> Result<(), Box<dyn std::error::Error>> {
+        let n = count as isize;
+        match action {
+            Action::MoveUp => self.move_list(|ls| ls.move_by(-n)),
+            Action::MoveDown => self.move_list(|ls| ls.move_by(n)),
+            Action::MoveToTop | Action::MoveToBottom => self.move_list(|ls| ls.move_to(count - 1)),
+            Action::PageUp => self.page_move(|ls, h| ls.page_up(h.saturating_sub(1) * count)),
+            Action::PageDown => self.page_move(|ls, h| ls.page_down(h.saturating_sub(1) * count)),
+            Action::HalfPageUp => self.page_move(|ls, h| ls.page_up(h / 2 * count)),
+            Action::HalfPageDown => self.page_move(|ls, h| ls.page_down(h / 2 * count)),
+            Action::PreviousBlock => self.block_move(count, false),
+            Action::NextBlock => self.block_move(count, true),
+            Action::ScrollDetailUp => {
+                self.detail_scroll.scroll_up(count);
+                Ok(())
+            }
+            Action::ScrollDetailDown => {
+                self.detail_scroll.scroll_down(count, usize::MAX);
+                Ok(())
+            }
+            Action::Delete => {
+                self.initiate_delete_count(count);
+                Ok(())
+            }
+            action => self.execute_action(action).map(|_| ()),
+        }
+    }
+
     pub fn list_visible_height(&self) -> usize {
         (self.terminal_size.height as usize).saturating_sub(4)
     }
@@ -95,6 +203,27 @@ impl App {
         Ok(())
     }
 
+    fn show_types(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.vault.is_unlocked() {
+            self.set_message("Vault must be unlocked", MessageType::Error);
+            return Ok(());
+        }
+        self.load_types()?;
+        self.mode_state.enter_types_mode();
+        Ok(())
+    }
+
+    fn show_duplicates(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.vault.is_unlocked() {
+            self.set_message("Vault must be unlocked", MessageType::Error);
+            return Ok(());
+        }
+        self.load_duplicates()?;
+        self.duplicates_state.home();
+        self.mode_state.enter_duplicates_mode();
+        Ok(())
+    }
+
     fn show_logs(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         if !self.vault.is_unlocked() {
             self.set_message("Vault must be unlocked", MessageType::Error);
@@ -106,6 +235,48 @@ impl App {
         Ok(())
     }
 
+    fn show_history(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.vault.is_unlocked() {
+            self.set_message("Vault must be unlocked", MessageType::Error);
+            return Ok(());
+        }
+        let Some(cred) = self.list_state.selected().and_then(|i| self.credentials.get(i)) else { return Ok(()) };
+        let revisions = crate::vault::history::credential_history(self.vault.db()?.conn(), self.vault.dek()?, cred)?;
+        self.history_state.set_history(&cred.name, &revisions);
+        self.mode_state.enter_history_mode();
+        Ok(())
+    }
+
+    /// The selected credential's audit entries, leaving out views, which
+    /// every visit to its detail logs
+    fn show_activity(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.vault.is_unlocked() {
+            self.set_message("Vault must be unlocked", MessageType::Error);
+            return Ok(());
+        }
+        let Some(cred) = self.list_state.selected().and_then(|i| self.credentials.get(i)) else { return Ok(()) };
+        let logs = crate::vault::audit::get_credential_logs(self.vault.db()?.conn(), &cred.id)?
+            .into_iter()
+            .filter(|log| log.action != AuditAction::Read)
+            .collect();
+        self.logs_state.set_credential_logs(&cred.name, logs);
+        self.logs_state.scroll.pending_g = false;
+        self.mode_state.enter_logs_mode();
+        Ok(())
+    }
+
+    fn show_stats(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.vault.is_unlocked() {
+            self.set_message("Vault must be unlocked", MessageType::Error);
+            return Ok(());
+        }
+        let db = self.vault.db()?;
+        let stats = crate::vault::stats::collect(db.conn(), self.vault.dek()?, db.size().unwrap_or(0))?;
+        self.stats_state.set_stats(&stats);
+        self.mode_state.enter_stats_mode();
+        Ok(())
+    }
+
     fn request_password_change(&mut self) {
         if self.vault.is_unlocked() {
             self.wants_password_change = true;
@@ -125,7 +296,10 @@ impl App {
     }
 
     fn go_back(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        if self.view == View::Detail {
+        if !self.list_state.marked.is_empty() {
+            self.list_state.marked.clear();
+            Ok(())
+        } else if self.view == View::Detail {
             self.view = View::List;
             Ok(())
         } else if self.has_active_filters() {
@@ -135,7 +309,19 @@ impl App {
         }
     }
 
-    fn toggle_password(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    fn toggle_detail_layout(&mut self) {
+        self.detail_layout = self.detail_layout.toggle();
+        let msg = match self.detail_layout {
+            DetailLayout::Split => "Detail: side by side (on wide terminals)",
+            DetailLayout::Full => "Detail: full screen",
+        };
+        self.set_message(msg, MessageType::Info);
+    }
+
+    pub(super) fn toggle_password(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.password_visible && !self.reauthorized(Guarded::Reveal) {
+            return Ok(());
+        }
         if self.password_visible {
             self.password_visible = false;
             self.password_hide_at = None;
@@ -154,13 +340,33 @@ impl App {
     }
 
     fn initiate_delete(&mut self) {
+        if !self.list_state.marked.is_empty() {
+            self.pending_action = Some(PendingAction::DeleteCredentials(self.marked_ids()));
+            self.mode_state.enter_confirm_mode();
+            return;
+        }
         let Some(idx) = self.list_state.selected() else { return };
-        let Some(item) = self.credential_items.get(idx) else { return };
+        let Some(item) = self.credentials.get(idx) else { return };
 
         self.pending_action = Some(PendingAction::DeleteCredential(item.id.clone()));
         self.mode_state.enter_confirm_mode();
     }
 
+    /// `3dd`: the selected credential and the ones below it, up to `count`;
+    /// marks take precedence as they do for `dd`
+    fn initiate_delete_count(&mut self, count: usize) {
+        let Some(idx) = self.list_state.selected() else { return };
+        if count == 1 || !self.list_state.marked.is_empty() {
+            return self.initiate_delete();
+        }
+        let ids: Vec<String> = self.credentials.iter().skip(idx).take(count).map(|item| item.id.clone()).collect();
+        if ids.is_empty() {
+            return;
+        }
+        self.pending_action = Some(PendingAction::DeleteCredentials(ids));
+        self.mode_state.enter_confirm_mode();
+    }
+
     fn cancel_pending(&mut self) {
         self.pending_action = None;
         self.mode_state.enter_normal_mode();
@@ -172,11 +378,16 @@ impl App {
             return Ok(());
         };
 
+        self.mode_state.enter_normal_mode();
         match action {
             PendingAction::DeleteCredential(id) => self.delete_credential(&id)?,
+            PendingAction::DeleteCredentials(ids) => self.delete_credentials(&ids)?,
+            PendingAction::RestoreBackup(path) => self.restore_backup(&path),
+            PendingAction::PurgeTrashed(id) => self.purge_trashed(&id)?,
+            PendingAction::EmptyTrash => self.empty_trash()?,
+            PendingAction::DeleteTag(tag) => self.delete_tag(&tag)?,
+            PendingAction::ApplyKdf(kdf, _) => self.reauth_request = Some(Guarded::Kdf(kdf)),
         }
-
-        self.mode_state.enter_normal_mode();
         Ok(())
     }
 
@@ -0,0 +1,69 @@
+//! Automatic encrypted exports on lock and on a schedule
+
+use std::time::{Duration, Instant};
+
+use crate::db::AuditAction;
+use crate::ui::MessageType;
+use crate::vault::auto_export::{self, AutoExportConfig};
+use crate::vault::{search, VaultError};
+
+use super::App;
+
+/// How often the scheduled export check runs
+const EXPORT_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+impl App {
+    /// None while `autoexportdir` is unset
+    pub fn auto_export_config(&self) -> Option<AutoExportConfig> {
+        Some(AutoExportConfig {
+            dir: self.config.auto_export_dir.clone()?,
+            recipients: self.config.auto_export_to.clone(),
+            interval: self.config.auto_export_interval,
+            keep: self.config.auto_export_keep,
+        })
+    }
+
+    /// Export and prune if due, reporting failures in the status line
+    pub fn auto_export(&mut self, trigger: &str) {
+        if !self.vault.is_unlocked() {
+            return;
+        }
+        let Some(config) = self.auto_export_config() else { return };
+        if let Err(e) = self.try_auto_export(&config, trigger) {
+            self.set_message(&format!("Automatic export failed: {}", e), MessageType::Error);
+        }
+    }
+
+    fn try_auto_export(&mut self, config: &AutoExportConfig, trigger: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if !auto_export::is_due(config)? {
+            return Ok(());
+        }
+
+        let credentials = search::get_all(self.vault.db()?.conn())?;
+        let path = match auto_export::write_export(self.vault.dek()?, &credentials, config) {
+            Ok(path) => path,
+            // Another automatic export was made this very second
+            Err(VaultError::AlreadyExists) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        let pruned = auto_export::prune(config)?;
+
+        let details = format!(
+            "Automatic export on {}: {} credential(s) to {} ({} old export(s) pruned)",
+            trigger,
+            credentials.len(),
+            path.display(),
+            pruned
+        );
+        self.log_audit(AuditAction::Export, None, None, None, Some(&details))
+    }
+
+    /// Scheduled exports when an interval is configured
+    pub fn tick_auto_export(&mut self) {
+        if self.config.auto_export_interval.is_none() || self.last_auto_export_check.elapsed() < EXPORT_CHECK_INTERVAL {
+            return;
+        }
+        self.last_auto_export_check = Instant::now();
+        self.auto_export("schedule");
+    }
+}
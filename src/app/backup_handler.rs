@@ -0,0 +1,154 @@
+//! Automatic backups on unlock, exit and on a schedule, plus `:backup` and `:restore`
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::db::AuditAction;
+use crate::ui::MessageType;
+use crate::vault::backup::{self, BackupConfig};
+use crate::vault::VaultError;
+
+use super::config::PendingAction;
+use super::App;
+
+/// How often the scheduled backup check runs
+const BACKUP_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+impl App {
+    pub fn backup_config(&self) -> BackupConfig {
+        let mut config = BackupConfig::for_vault(&self.config.vault_path);
+        if let Some(dir) = &self.config.backup_dir {
+            config.dir = dir.clone();
+        }
+        config.keep_daily = self.config.backup_keep_daily;
+        config.keep_weekly = self.config.backup_keep_weekly;
+        config.interval = self.config.backup_interval;
+        config
+    }
+
+    /// Back up and rotate if due, reporting failures in the status line
+    pub fn auto_backup(&mut self, trigger: &str) {
+        if !self.vault.is_unlocked() {
+            return;
+        }
+        if let Err(e) = self.try_auto_backup(trigger) {
+            self.set_message(&format!("Automatic backup failed: {}", e), MessageType::Error);
+        }
+    }
+
+    fn try_auto_backup(&mut self, trigger: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let config = self.backup_config();
+        if !backup::is_due(&config)? {
+            return Ok(());
+        }
+
+        let path = match backup::create_backup(self.vault.db()?.conn(), &config.dir, None) {
+            Ok(path) => path,
+            // Another automatic backup was made this very second
+            Err(VaultError::AlreadyExists) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        let pruned = backup::prune(&config)?;
+
+        let details = format!(
+            "Automatic backup on {}: {} ({} old backup(s) pruned)",
+            trigger,
+            path.display(),
+            pruned
+        );
+        self.log_audit(AuditAction::Backup, None, None, None, Some(&details))
+    }
+
+    /// Scheduled backups when an interval is configured
+    pub fn tick_backup(&mut self) {
+        if self.config.backup_interval.is_none() || self.last_backup_check.elapsed() < BACKUP_CHECK_INTERVAL {
+            return;
+        }
+        self.last_backup_check = Instant::now();
+        self.auto_backup("schedule");
+    }
+
+    /// `:backup [name]`: a labelled backup that rotation never prunes
+    pub fn manual_backup(&mut self, name: Option<&str>) {
+        if !self.vault.is_unlocked() {
+            self.set_message("Vault must be unlocked", MessageType::Error);
+            return;
+        }
+        match self.try_manual_backup(name.unwrap_or("manual")) {
+            Ok(path) => self.set_message(&format!("Backup saved to {}", path.display()), MessageType::Success),
+            Err(e) => self.set_message(&format!("Backup failed: {}", e), MessageType::Error),
+        }
+    }
+
+    fn try_manual_backup(&mut self, label: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let path = backup::create_backup(self.vault.db()?.conn(), &self.backup_config().dir, Some(label))?;
+        let details = format!("Manual backup: {}", path.display());
+        self.log_audit(AuditAction::Backup, None, None, None, Some(&details))?;
+        Ok(path)
+    }
+
+    pub fn show_restore(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.vault.is_unlocked() {
+            self.set_message("Vault must be unlocked", MessageType::Error);
+            return Ok(());
+        }
+        let backups = backup::list_backups(&self.backup_config().dir)?;
+        self.backups_state.set_backups(backups);
+        self.mode_state.enter_restore_mode();
+        Ok(())
+    }
+
+    /// Ask for confirmation before restoring `file`, a path or a name in the backup directory
+    pub fn request_restore(&mut self, file: &str) {
+        if !self.vault.is_unlocked() {
+            self.set_message("Vault must be unlocked", MessageType::Error);
+            return;
+        }
+        let path = Path::new(file);
+        let path = if path.components().count() > 1 { path.to_path_buf() } else { self.backup_config().dir.join(path) };
+        if !path.is_file() {
+            self.set_message(&format!("No such backup: {}", path.display()), MessageType::Error);
+            self.mode_state.enter_normal_mode();
+            return;
+        }
+
+        self.pending_action = Some(PendingAction::RestoreBackup(path));
+        self.mode_state.enter_confirm_mode();
+    }
+
+    /// Snapshot the current database, swap in `path` and lock so it is unlocked afresh
+    pub fn restore_backup(&mut self, path: &Path) {
+        match self.try_restore_backup(path) {
+            Ok(()) => self.set_message("Backup restored, unlock to continue", MessageType::Success),
+            Err(e) => self.set_message(&format!("Restore failed: {}", e), MessageType::Error),
+        }
+    }
+
+    fn try_restore_backup(&mut self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        backup::validate_vault_file(path)?;
+
+        let config = self.backup_config();
+        let snapshot = match backup::create_backup(self.vault.db()?.conn(), &config.dir, Some("pre-restore")) {
+            // A pre-restore snapshot from this very second already holds the current data
+            Err(VaultError::AlreadyExists) => None,
+            result => Some(result?),
+        };
+        let details = match &snapshot {
+            Some(snapshot) => format!("Restoring {} (current data saved to {})", path.display(), snapshot.display()),
+            None => format!("Restoring {}", path.display()),
+        };
+        self.log_audit(AuditAction::Restore, None, None, None, Some(&details))?;
+
+        self.lock();
+        backup::restore_backup(&self.config.vault_path, path)?;
+        self.restored_from = Some(path.to_path_buf());
+        Ok(())
+    }
+
+    /// Record a completed restore in the restored vault's own audit log
+    pub(super) fn log_restore(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(path) = self.restored_from.take() else { return Ok(()) };
+        let details = format!("Restored from {}", path.display());
+        self.log_audit(AuditAction::Restore, None, None, None, Some(&details))
+    }
+}
@@ -0,0 +1,180 @@
+//! Bulk operations on credentials marked with Space
+
+use crate::db::{self, AuditAction, CredentialType};
+use crate::ui::MessageType;
+use crate::ui::renderer::View;
+use crate::vault::audit;
+
+use super::App;
+
+impl App {
+    /// Mark or unmark the credential under the cursor and move on to the next
+    pub fn toggle_mark(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(item) = self.list_state.selected().and_then(|i| self.credentials.get(i)) else {
+            return Ok(());
+        };
+        let id = item.id.clone();
+        self.list_state.toggle_mark(&id);
+        self.list_state.move_down();
+        self.update_selected_detail()
+    }
+
+    /// Start a visual range at the cursor
+    pub fn enter_visual(&mut self) {
+        if self.list_state.selected().is_none() {
+            return;
+        }
+        self.list_state.visual_anchor = self.list_state.selected();
+        self.mode_state.enter_visual_mode();
+    }
+
+    pub fn exit_visual(&mut self) {
+        self.list_state.visual_anchor = None;
+        self.mode_state.enter_normal_mode();
+    }
+
+    /// Add every credential in the visual range to the marks and leave visual mode
+    pub fn mark_visual_range(&mut self) {
+        if let Some(range) = self.list_state.visual_range() {
+            let ids: Vec<String> = self.credentials.get(range).unwrap_or_default().iter().map(|c| c.id.clone()).collect();
+            self.list_state.marked.extend(ids);
+        }
+        self.exit_visual();
+    }
+
+    /// Marked credential IDs in list order
+    pub fn marked_ids(&self) -> Vec<String> {
+        self.credentials
+            .iter()
+            .filter(|c| self.list_state.is_marked(&c.id))
+            .map(|c| c.id.clone())
+            .collect()
+    }
+
+    /// Marked credentials, or the one under the cursor when nothing is marked
+    fn bulk_targets(&self) -> Vec<String> {
+        if !self.list_state.marked.is_empty() {
+            return self.marked_ids();
+        }
+        self.list_state
+            .selected()
+            .and_then(|i| self.credentials.get(i))
+            .map(|c| vec![c.id.clone()])
+            .unwrap_or_default()
+    }
+
+    pub fn delete_credentials(&mut self, ids: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+        let audit_key = self.vault.keys()?.derive_audit_key()?;
+        let conn = self.vault.db()?.conn();
+        // All or nothing, under one commit however many are marked
+        db::in_transaction(conn, || -> Result<(), Box<dyn std::error::Error>> {
+            for id in ids {
+                let cred = db::get_credential(conn, id)?;
+                db::delete_credential(conn, id)?;
+                audit::log_action(
+                    conn,
+                    &audit_key,
+                    AuditAction::Delete,
+                    Some(id),
+                    Some(&cred.name),
+                    cred.username.as_deref(),
+                    Some("Moved to trash"),
+                )?;
+            }
+            Ok(())
+        })?;
+        for id in ids {
+            self.decryption_cache.remove(id);
+        }
+        self.reindex(ids.iter().map(String::as_str))?;
+
+        self.list_state.marked.clear();
+        if self.view == View::Detail {
+            self.view = View::List;
+        }
+        self.refresh_data()?;
+        self.update_selected_detail()?;
+        self.set_message(&format!("Moved {} credential(s) to trash", ids.len()), MessageType::Success);
+        Ok(())
+    }
+
+    /// `:addtag <tag>` and `:rmtag <tag>`
+    pub fn bulk_tag(&mut self, tag: Option<&str>, add: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(tag) = tag else {
+            let usage = if add { "Usage: :addtag <tag>" } else { "Usage: :rmtag <tag>" };
+            self.set_message(usage, MessageType::Error);
+            return Ok(());
+        };
+        let details = if add { format!("Added tag {}", tag) } else { format!("Removed tag {}", tag) };
+
+        let changed = self.bulk_update(&details, |cred| {
+            let has_tag = cred.tags.iter().any(|t| t == tag);
+            match (add, has_tag) {
+                (true, false) => cred.tags.push(tag.to_string()),
+                (false, true) => cred.tags.retain(|t| t != tag),
+                _ => return false,
+            }
+            true
+        })?;
+        self.set_message(&format!("{} on {} credential(s)", details, changed), MessageType::Success);
+        Ok(())
+    }
+
+    /// `:settype <type>`
+    pub fn bulk_set_type(&mut self, name: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(cred_type) = name.and_then(CredentialType::from_name) else {
+            self.set_message(
+                "Usage: :settype password|api_key|ssh_key|certificate|note|database|custom",
+                MessageType::Error,
+            );
+            return Ok(());
+        };
+
+        let details = format!("Changed type to {}", cred_type.display_name());
+        let changed = self.bulk_update(&details, |cred| {
+            let changed = cred.credential_type != cred_type;
+            cred.credential_type = cred_type;
+            changed
+        })?;
+        self.set_message(&format!("{} on {} credential(s)", details, changed), MessageType::Success);
+        Ok(())
+    }
+
+    /// Apply `change` to every target, saving and auditing those it reports as
+    /// changed, in one transaction
+    fn bulk_update(
+        &mut self,
+        details: &str,
+        mut change: impl FnMut(&mut db::Credential) -> bool,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let targets = self.bulk_targets();
+        let audit_key = self.vault.keys()?.derive_audit_key()?;
+        let conn = self.vault.db()?.conn();
+        let mut changed = 0;
+        db::in_transaction(conn, || -> Result<(), Box<dyn std::error::Error>> {
+            for id in &targets {
+                let mut cred = db::get_credential(conn, id)?;
+                if !change(&mut cred) {
+                    continue;
+                }
+                db::update_credential(conn, &cred)?;
+                audit::log_action(
+                    conn,
+                    &audit_key,
+                    AuditAction::Update,
+                    Some(id),
+                    Some(&cred.name),
+                    cred.username.as_deref(),
+                    Some(details),
+                )?;
+                changed += 1;
+            }
+            Ok(())
+        })?;
+        self.reindex(targets.iter().map(String::as_str))?;
+
+        self.refresh_data()?;
+        self.update_selected_detail()?;
+        Ok(changed)
+    }
+}
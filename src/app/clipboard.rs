@@ -1,92 +1,406 @@
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::Mutex;
 use std::time::Duration;
-use zeroize::Zeroize;
+
+use sha2::{Digest, Sha256};
+use zeroize::{Zeroize, Zeroizing};
 
 pub static CLIPBOARD_COPY_ID: AtomicU64 = AtomicU64::new(0);
 
-pub fn copy_with_timeout(text: &str, timeout: Duration) {
+/// The latest copy still waiting to be cleared
+static PENDING_CLEAR: Mutex<Option<Copied>> = Mutex::new(None);
+
+/// A copy we made, identified by a hash so the value itself isn't kept
+struct Copied {
+    id: u64,
+    digest: [u8; 32],
+    target: Target,
+    selection: Selection,
+}
+
+/// How and where to copy, from the `clipboard` and `clipselection` settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ClipboardOptions {
+    pub mode: ClipboardMode,
+    pub selection: Selection,
+}
+
+/// Where copied text goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClipboardMode {
+    /// The system clipboard, falling back to OSC 52 when there is none
+    #[default]
+    Auto,
+    /// Only the system clipboard (wl-copy, xclip or the platform API)
+    System,
+    /// OSC 52 escape sequences, which the terminal turns into a copy; works
+    /// over SSH as long as the terminal (and tmux, with `set-clipboard on`) allows it
+    Osc52,
+}
+
+impl ClipboardMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::System => "system",
+            Self::Osc52 => "osc52",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(Self::Auto),
+            "system" => Some(Self::System),
+            "osc52" => Some(Self::Osc52),
+            _ => None,
+        }
+    }
+}
+
+/// Which X11/Wayland selection receives copies; other platforms only have
+/// the clipboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Selection {
+    /// The clipboard pasted with Ctrl+V
+    #[default]
+    Clipboard,
+    /// The primary selection pasted with the middle mouse button
+    Primary,
+    Both,
+}
+
+impl Selection {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Clipboard => "clipboard",
+            Self::Primary => "primary",
+            Self::Both => "both",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "clipboard" => Some(Self::Clipboard),
+            "primary" => Some(Self::Primary),
+            "both" => Some(Self::Both),
+            _ => None,
+        }
+    }
+
+    /// Each selection as whether it is the primary one
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    fn primaries(&self) -> &'static [bool] {
+        match self {
+            Self::Clipboard => &[false],
+            Self::Primary => &[true],
+            Self::Both => &[false, true],
+        }
+    }
+}
+
+/// Which clipboard a copy landed in, so it is cleared in the same place
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Target {
+    System,
+    Osc52,
+}
+
+pub fn copy_with_timeout(text: &str, timeout: Duration, options: ClipboardOptions) {
     let copy_id = CLIPBOARD_COPY_ID.fetch_add(1, Ordering::SeqCst) + 1;
     let mut text = text.to_string();
 
-    std::thread::spawn(move || copy_thread(&mut text, timeout, copy_id));
+    std::thread::spawn(move || copy_thread(&mut text, timeout, copy_id, options));
 }
 
-#[cfg(target_os = "linux")]
-fn copy_thread(text: &mut String, timeout: Duration, copy_id: u64) {
-    let is_wayland = std::env::var("WAYLAND_DISPLAY").is_ok();
+/// Copy on the current thread and block until the clipboard is cleared
+///
+/// Used by short-lived CLI commands, which would otherwise exit before the
+/// clearing thread runs. Returns false if nothing could be copied.
+pub fn copy_and_wait(text: &str, timeout: Duration, options: ClipboardOptions) -> bool {
+    let copy_id = CLIPBOARD_COPY_ID.fetch_add(1, Ordering::SeqCst) + 1;
+    let mut text = text.to_string();
+    copy_thread(&mut text, timeout, copy_id, options)
+}
 
-    let ok = if is_wayland { set_wayland(text) } else { set_x11(text) };
-    if !ok {
-        return;
-    }
+fn copy_thread(text: &mut String, timeout: Duration, copy_id: u64, options: ClipboardOptions) -> bool {
+    let selection = options.selection;
+    let target = match options.mode {
+        ClipboardMode::System => set_system(text, selection).then_some(Target::System),
+        ClipboardMode::Osc52 => set_osc52(text, selection).then_some(Target::Osc52),
+        ClipboardMode::Auto if set_system(text, selection) => Some(Target::System),
+        ClipboardMode::Auto => set_osc52(text, selection).then_some(Target::Osc52),
+    };
+    let Some(target) = target else {
+        text.zeroize();
+        return false;
+    };
 
-    std::thread::sleep(timeout);
+    let digest = Sha256::digest(text.as_bytes()).into();
     text.zeroize();
-
-    if CLIPBOARD_COPY_ID.load(Ordering::SeqCst) != copy_id {
-        return;
+    {
+        let mut pending = PENDING_CLEAR.lock().unwrap_or_else(|e| e.into_inner());
+        // A later copy may have landed first; it is the one to clear now
+        if CLIPBOARD_COPY_ID.load(Ordering::SeqCst) == copy_id {
+            *pending = Some(Copied { id: copy_id, digest, target, selection });
+        }
     }
 
-    clear_clipboard(is_wayland);
+    std::thread::sleep(timeout);
+    clear_pending(Some(copy_id));
+    true
 }
 
+/// Copy `text` until it is pasted once, signalling the paste on the returned
+/// channel; gives up (and clears it) after `timeout`
+///
+/// Only wl-copy can tell when a paste happens, so this copies nothing and
+/// returns None anywhere else.
 #[cfg(target_os = "linux")]
-fn set_wayland(text: &str) -> bool {
+pub fn copy_until_pasted(text: &str, timeout: Duration, options: ClipboardOptions) -> Option<Receiver<()>> {
     use std::io::Write;
     use std::process::{Command, Stdio};
+    use std::time::Instant;
 
-    Command::new("wl-copy")
+    if options.mode == ClipboardMode::Osc52 || !is_wayland() || options.selection == Selection::Both {
+        return None;
+    }
+    let mut args = vec!["--foreground", "--paste-once"];
+    if options.selection == Selection::Primary {
+        args.push("--primary");
+    }
+    let mut child = Command::new("wl-copy")
+        .args(&args)
         .stdin(Stdio::piped())
         .stdout(Stdio::null())
         .stderr(Stdio::null())
         .spawn()
-        .ok()
-        .and_then(|mut c| c.stdin.take()?.write_all(text.as_bytes()).ok())
-        .is_some()
+        .ok()?;
+    child.stdin.take()?.write_all(text.as_bytes()).ok()?;
+    // Supersedes earlier copies, which no longer need clearing
+    CLIPBOARD_COPY_ID.fetch_add(1, Ordering::SeqCst);
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let deadline = Instant::now() + timeout;
+    std::thread::spawn(move || loop {
+        match child.try_wait() {
+            Ok(Some(status)) => {
+                if status.success() {
+                    let _ = tx.send(());
+                }
+                return;
+            }
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return;
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(100)),
+            Err(_) => return,
+        }
+    });
+    Some(rx)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn copy_until_pasted(_text: &str, _timeout: Duration, _options: ClipboardOptions) -> Option<Receiver<()>> {
+    None
+}
+
+/// Clear what we copied right away, e.g. when the vault locks
+pub fn clear_now() {
+    clear_pending(None);
+}
+
+/// Clear the pending copy (only if it is `copy_id`, when given), but only if
+/// the clipboard still holds it: something the user copied since is left alone
+fn clear_pending(copy_id: Option<u64>) {
+    let mut pending = PENDING_CLEAR.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(copied) = pending.as_ref() else { return };
+    if copy_id.is_some_and(|id| id != copied.id) {
+        return;
+    }
+
+    match copied.target {
+        Target::System => clear_system(copied.selection, |current| {
+            Sha256::digest(current.as_bytes()).as_slice() == copied.digest
+        }),
+        // The terminal's clipboard can't be read back, so it is always cleared
+        Target::Osc52 => {
+            set_osc52("", copied.selection);
+        }
+    }
+    *pending = None;
+}
+
+/// OSC 52 sequence setting the selection to `text`; empty text clears it
+///
+/// Built in one buffer sized up front, so the encoded secret is never left
+/// behind in a temporary or a reallocation.
+fn osc52_sequence(text: &str, selection: Selection) -> Zeroizing<String> {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+
+    let target = match selection {
+        Selection::Clipboard => "c",
+        Selection::Primary => "p",
+        Selection::Both => "cp",
+    };
+    let mut sequence = Zeroizing::new(String::with_capacity(text.len().div_ceil(3) * 4 + 16));
+    sequence.push_str("\x1b]52;");
+    sequence.push_str(target);
+    sequence.push(';');
+    STANDARD.encode_string(text, &mut sequence);
+    sequence.push('\x07');
+    sequence
+}
+
+/// Write the sequence straight to the terminal, not stdout, so it works
+/// while stdout is piped and arrives in one piece between TUI redraws
+fn set_osc52(text: &str, selection: Selection) -> bool {
+    use std::io::Write;
+
+    let sequence = osc52_sequence(text, selection);
+    match std::fs::OpenOptions::new().write(true).open(terminal_path()) {
+        Ok(mut tty) => tty.write_all(sequence.as_bytes()).and_then(|_| tty.flush()).is_ok(),
+        Err(_) => {
+            let mut stdout = std::io::stdout().lock();
+            stdout.write_all(sequence.as_bytes()).and_then(|_| stdout.flush()).is_ok()
+        }
+    }
+}
+
+#[cfg(unix)]
+fn terminal_path() -> &'static str {
+    "/dev/tty"
+}
+
+#[cfg(not(unix))]
+fn terminal_path() -> &'static str {
+    "CONOUT$"
+}
+
+#[cfg(target_os = "linux")]
+fn is_wayland() -> bool {
+    std::env::var("WAYLAND_DISPLAY").is_ok()
+}
+
+#[cfg(target_os = "linux")]
+fn set_system(text: &str, selection: Selection) -> bool {
+    if !is_wayland() && std::env::var("DISPLAY").is_err() {
+        // No display, e.g. over SSH: xclip would start and then fail
+        return false;
+    }
+    selection.primaries().iter().all(|&primary| {
+        if is_wayland() {
+            let args: &[&str] = if primary { &["--primary"] } else { &[] };
+            pipe_to("wl-copy", args, text)
+        } else {
+            pipe_to("xclip", &["-selection", x11_selection(primary)], text)
+        }
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn x11_selection(primary: bool) -> &'static str {
+    if primary { "primary" } else { "clipboard" }
 }
 
+/// Feed `text` to a clipboard tool and check that it took it
 #[cfg(target_os = "linux")]
-fn set_x11(text: &str) -> bool {
+fn pipe_to(program: &str, args: &[&str], text: &str) -> bool {
     use std::io::Write;
     use std::process::{Command, Stdio};
 
-    Command::new("xclip")
-        .args(["-selection", "clipboard"])
+    let Ok(mut child) = Command::new(program)
+        .args(args)
         .stdin(Stdio::piped())
         .stdout(Stdio::null())
         .stderr(Stdio::null())
         .spawn()
-        .ok()
-        .and_then(|mut c| c.stdin.take()?.write_all(text.as_bytes()).ok())
-        .is_some()
+    else {
+        return false;
+    };
+    let written = child.stdin.take().is_some_and(|mut stdin| stdin.write_all(text.as_bytes()).is_ok());
+    // Both tools fork to keep serving the selection after we exit, so this
+    // returns once it is set
+    written && child.wait().is_ok_and(|status| status.success())
 }
 
+/// Current text of one selection, None if it can't be read
 #[cfg(target_os = "linux")]
-fn clear_clipboard(is_wayland: bool) {
-    use std::process::{Command, Stdio};
+fn read_system(primary: bool) -> Option<String> {
+    use std::process::Command;
 
-    if is_wayland {
-        let _ = Command::new("wl-copy").arg("--clear").output();
+    let output = if is_wayland() {
+        let args: &[&str] = if primary { &["--no-newline", "--primary"] } else { &["--no-newline"] };
+        Command::new("wl-paste").args(args).output()
+    } else {
+        Command::new("xclip").args(["-selection", x11_selection(primary), "-o"]).output()
+    };
+    let output = output.ok()?;
+    if output.status.success() {
+        String::from_utf8(output.stdout).map_err(|e| e.into_bytes().zeroize()).ok()
     } else {
-        let _ = Command::new("xclip")
-            .args(["-selection", "clipboard"])
-            .stdin(Stdio::piped())
-            .output();
+        // Both tools fail on an empty selection, which holds nothing of ours
+        Some(String::new())
     }
 }
 
-#[cfg(not(target_os = "linux"))]
-fn copy_thread(text: &mut String, timeout: Duration, copy_id: u64) {
-    let Ok(mut clipboard) = arboard::Clipboard::new() else { return };
+/// Clear each selection that `is_ours` still recognizes; one that can't be
+/// read back is cleared, the safe choice
+#[cfg(target_os = "linux")]
+fn clear_system(selection: Selection, is_ours: impl Fn(&str) -> bool) {
+    use std::process::{Command, Stdio};
 
-    if clipboard.set_text(&*text).is_err() {
-        return;
+    for &primary in selection.primaries() {
+        let ours = read_system(primary).is_none_or(|mut current| {
+            let same = is_ours(&current);
+            current.zeroize();
+            same
+        });
+        if !ours {
+            continue;
+        }
+        if is_wayland() {
+            let args: &[&str] = if primary { &["--clear", "--primary"] } else { &["--clear"] };
+            let _ = Command::new("wl-copy").args(args).output();
+        } else {
+            let _ = Command::new("xclip")
+                .args(["-selection", x11_selection(primary)])
+                .stdin(Stdio::piped())
+                .output();
+        }
     }
+}
 
-    std::thread::sleep(timeout);
-    text.zeroize();
+#[cfg(not(target_os = "linux"))]
+fn set_system(text: &str, _selection: Selection) -> bool {
+    arboard::Clipboard::new().is_ok_and(|mut clipboard| clipboard.set_text(text).is_ok())
+}
 
-    if CLIPBOARD_COPY_ID.load(Ordering::SeqCst) == copy_id {
+#[cfg(not(target_os = "linux"))]
+fn clear_system(_selection: Selection, is_ours: impl Fn(&str) -> bool) {
+    let Ok(mut clipboard) = arboard::Clipboard::new() else { return };
+    let ours = clipboard.get_text().ok().is_none_or(|mut current| {
+        let same = is_ours(&current);
+        current.zeroize();
+        same
+    });
+    if ours {
         let _ = clipboard.clear();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc52_sequence() {
+        assert_eq!(osc52_sequence("hunter2", Selection::Clipboard).as_str(), "\x1b]52;c;aHVudGVyMg==\x07");
+        assert_eq!(osc52_sequence("", Selection::Clipboard).as_str(), "\x1b]52;c;\x07");
+        assert_eq!(osc52_sequence("", Selection::Both).as_str(), "\x1b]52;cp;\x07");
+        assert_eq!(ClipboardMode::from_name("osc52"), Some(ClipboardMode::Osc52));
+        assert_eq!(ClipboardMode::from_name("auto").map(|m| m.name()), Some("auto"));
+    }
+}
@@ -0,0 +1,73 @@
+//! Command-mode history and Tab completion
+
+use std::collections::BTreeSet;
+
+use crate::db::CredentialType;
+use crate::input::completion::{self, Vocabulary};
+use crate::input::history::CommandHistory;
+use crate::ui::MessageType;
+use crate::vault::search;
+
+use super::config::SETTINGS;
+use super::App;
+
+impl App {
+    /// Read the history kept in the vault, once it is unlocked
+    pub fn load_command_history(&mut self) {
+        match self.vault.command_history() {
+            Ok(entries) => self.command_history = CommandHistory::new(entries),
+            Err(e) => self.set_message(&format!("Command history unreadable: {}", e), MessageType::Error),
+        }
+    }
+
+    /// Add a submitted command to the history and save it
+    pub fn record_command(&mut self, line: &str) {
+        if !self.command_history.record(line) {
+            return;
+        }
+        if let Err(e) = self.vault.save_command_history(self.command_history.entries()) {
+            self.set_message(&format!("Saving command history failed: {}", e), MessageType::Error);
+        }
+    }
+
+    /// Up and Down in command mode
+    pub fn recall_command(&mut self, previous: bool) {
+        let recalled = match previous {
+            true => self.command_history.previous(self.mode_state.get_buffer()),
+            false => self.command_history.next(),
+        };
+        if let Some(line) = recalled.map(String::from) {
+            self.mode_state.set_buffer(&line);
+        }
+    }
+
+    /// Tab in command mode; several matches are listed in the status line
+    pub fn complete_command(&mut self) {
+        let words = self.vocabulary();
+        let Some(completion) = completion::complete(self.mode_state.get_buffer(), &words) else {
+            return;
+        };
+        self.mode_state.set_buffer(&completion.line);
+        if !completion.matches.is_empty() {
+            self.set_message(&completion.matches.join("  "), MessageType::Info);
+        }
+    }
+
+    fn vocabulary(&self) -> Vocabulary {
+        let tags: BTreeSet<String> = self
+            .vault
+            .db()
+            .ok()
+            .and_then(|db| search::get_all(db.conn()).ok())
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|cred| cred.tags)
+            .collect();
+        Vocabulary {
+            tags: tags.into_iter().collect(),
+            names: self.credentials.iter().map(|cred| cred.name.clone()).collect(),
+            types: CredentialType::ALL.iter().map(|t| t.as_str().to_string()).collect(),
+            settings: SETTINGS.iter().map(|(key, _)| key.to_string()).collect(),
+        }
+    }
+}
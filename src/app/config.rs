@@ -1,11 +1,109 @@
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
+use serde_json::{Map, Value};
+
+use crate::crypto::KdfParams;
+use crate::ui::components::tags::TagColors;
+
+use super::clipboard::{ClipboardMode, ClipboardOptions, Selection};
+use crate::ui::Theme;
+use crate::vault::search::SearchMode;
+
+/// Settings accepted by `:set` and the config file, with their units
+pub const SETTINGS: &[(&str, &str)] = &[
+    ("autolock", "seconds idle before locking, 0 never locks"),
+    ("lockwarn", "seconds before the idle lock to count down in the status line, 0 locks without warning"),
+    ("focuslock", "seconds after the terminal loses focus before locking, 0 never; needs a terminal that reports focus"),
+    ("sleeplock", "on locks the vault when the machine suspends"),
+    ("cliptimeout", "seconds before the clipboard is cleared"),
+    ("clipboard", "auto, system or osc52; auto falls back to OSC 52 when there is no system clipboard"),
+    ("clipselection", "clipboard, primary or both: where copies go on Linux and over OSC 52"),
+    ("showtimeout", "seconds a revealed password stays visible"),
+    ("reauth", "minutes idle after which copying a secret asks for the master password; while set, revealing and exporting always ask; 0 turns it off"),
+    ("backupdir", "automatic backup directory, empty for backups/ next to the vault"),
+    ("backupdaily", "daily backups to keep"),
+    ("backupweekly", "weekly backups to keep"),
+    ("backupinterval", "hours between automatic backups, 0 backs up on every unlock and exit"),
+    ("autoexportdir", "directory for automatic encrypted exports, empty turns them off"),
+    ("autoexportto", "GPG key IDs or age public keys automatic exports are encrypted to, comma separated"),
+    ("autoexportinterval", "hours between automatic exports, 0 exports on every lock"),
+    ("autoexportkeep", "automatic exports to keep, 0 keeps all"),
+    ("auditentries", "audit log entries to keep, 0 keeps all"),
+    ("auditdays", "days of audit log to keep, 0 keeps all"),
+    ("theme", "auto, dark, light, 16color or mono"),
+    ("tagcolors", "tag:color pairs, e.g. work:blue,personal/bank:#ff8800"),
+    ("search", "substring or fuzzy; a ~ prefix makes one search fuzzy"),
+    ("deepsearch", "on to also search decrypted notes and URLs"),
+    ("maxage", "days after which a secret counts as stale in :report age"),
+    ("minstrength", "strength score (0-100) below which :report weak lists a password"),
+    ("expirywarn", "days ahead to warn about expiring credentials at unlock, 0 turns the warning off"),
+    ("sshlifetime", "seconds a key added to ssh-agent stays loaded, 0 until the agent stops"),
+    ("wordlist", "passphrase wordlist file for vault generate, empty for the built-in list"),
+    ("keyfile", "keyfile read at unlock for vaults that need one; :keyfile adds or removes it"),
+    ("kdfmemory", "Argon2 memory in MiB for new password hashes; :kdf apply rehashes the vault's"),
+    ("kdftime", "Argon2 passes for new password hashes"),
+    ("kdfparallelism", "Argon2 lanes for new password hashes"),
+    ("keyring", "on reopens the vault without the password until reboot (INSECURE: any program running as you can then open it); :forget-session ends it"),
+];
+
 pub struct AppConfig {
     pub vault_path: PathBuf,
     pub auto_lock_timeout: Duration,
+    /// How long before the idle lock the countdown shows; None locks without warning
+    pub lock_warning: Option<Duration>,
+    /// How long the terminal may be out of focus before locking; None ignores focus
+    pub focus_lock_after: Option<Duration>,
+    /// Lock when the machine suspends or is found to have slept
+    pub lock_on_sleep: bool,
     pub clipboard_timeout: Duration,
+    pub clipboard: ClipboardOptions,
     pub password_visibility_timeout: Duration,
+    /// Idle time after which copies need the master password again; while
+    /// set, reveals and exports always do. None never asks
+    pub reauth_after: Option<Duration>,
+    /// Automatic backup directory; None puts `backups/` next to the vault
+    pub backup_dir: Option<PathBuf>,
+    pub backup_keep_daily: usize,
+    pub backup_keep_weekly: usize,
+    /// Minimum time between automatic backups; None backs up on every unlock and exit
+    pub backup_interval: Option<Duration>,
+    /// Directory for automatic encrypted exports; None turns them off
+    pub auto_export_dir: Option<PathBuf>,
+    /// GPG key IDs or age public keys automatic exports are encrypted to
+    pub auto_export_to: Vec<String>,
+    /// Minimum time between automatic exports; None exports on every lock
+    pub auto_export_interval: Option<Duration>,
+    pub auto_export_keep: usize,
+    /// Most audit log entries kept; None keeps all
+    pub audit_max_entries: Option<usize>,
+    /// Days of audit log kept; None keeps all
+    pub audit_max_days: Option<u64>,
+    /// Color theme; None detects one from the terminal
+    pub theme: Option<Theme>,
+    /// Tag colors; nested tags inherit their parent's
+    pub tag_colors: TagColors,
+    pub search_mode: SearchMode,
+    /// Also match decrypted notes and URLs when searching
+    pub deep_search: bool,
+    /// Secrets unchanged for longer than this many days are listed by `:report age`
+    pub max_secret_age_days: u64,
+    /// Secrets scoring below this are listed by `:report weak`; 41 is the lowest Fair score
+    pub min_strength: u32,
+    /// Credentials expiring within this many days are flagged
+    pub expiry_warning_days: u64,
+    /// How long keys added to ssh-agent stay loaded; None leaves them until the agent stops
+    pub ssh_agent_lifetime: Option<Duration>,
+    /// Passphrase wordlist file; None uses the built-in list
+    pub wordlist: Option<PathBuf>,
+    /// Keyfile mixed into the master key; a new vault is created with it
+    pub keyfile: Option<PathBuf>,
+    /// Keep a session key in the kernel keyring so the vault reopens without
+    /// the password until reboot
+    pub keyring_session: bool,
+    /// Argon2 parameters for new vaults, password changes and `:kdf apply`
+    pub kdf: KdfParams,
 }
 
 impl Default for AppConfig {
@@ -18,21 +116,389 @@ impl Default for AppConfig {
         Self {
             vault_path,
             auto_lock_timeout: Duration::from_secs(180),
+            lock_warning: Some(Duration::from_secs(30)),
+            focus_lock_after: None,
+            lock_on_sleep: true,
             clipboard_timeout: Duration::from_secs(15),
+            clipboard: ClipboardOptions::default(),
             password_visibility_timeout: Duration::from_secs(5),
+            reauth_after: None,
+            backup_dir: None,
+            backup_keep_daily: 7,
+            backup_keep_weekly: 4,
+            backup_interval: None,
+            auto_export_dir: None,
+            auto_export_to: Vec::new(),
+            auto_export_interval: None,
+            auto_export_keep: 10,
+            audit_max_entries: None,
+            audit_max_days: None,
+            theme: None,
+            tag_colors: TagColors::default(),
+            search_mode: SearchMode::default(),
+            deep_search: false,
+            max_secret_age_days: 365,
+            min_strength: 41,
+            expiry_warning_days: 30,
+            ssh_agent_lifetime: Some(Duration::from_secs(3600)),
+            wordlist: None,
+            keyfile: None,
+            keyring_session: false,
+            kdf: KdfParams::default(),
         }
     }
 }
 
+impl AppConfig {
+    /// `config.json` in the user's config directory, e.g. `~/.config/vault/config.json`
+    pub fn config_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("vault").join("config.json"))
+    }
+
+    /// Defaults overlaid with the config file, if there is one
+    pub fn load() -> Result<Self, String> {
+        let mut config = Self::default();
+        if let Some(path) = Self::config_path() {
+            config.apply_file(&path)?;
+        }
+        Ok(config)
+    }
+
+    /// Apply every setting in a config file; a missing file changes nothing
+    pub fn apply_file(&mut self, path: &Path) -> Result<(), String> {
+        let settings = match read_settings(path)? {
+            Some(settings) => settings,
+            None => return Ok(()),
+        };
+        for (key, value) in &settings {
+            let value = match value {
+                Value::String(s) => s.clone(),
+                // `{"work": "blue"}` is the file form of `work:blue`
+                Value::Object(pairs) => pairs
+                    .iter()
+                    .map(|(k, v)| format!("{}:{}", k, v.as_str().unwrap_or_default()))
+                    .collect::<Vec<_>>()
+                    .join(","),
+                other => other.to_string(),
+            };
+            self.set(key, &value).map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+        Ok(())
+    }
+
+    /// Change one setting from its `:set` form
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let value = value.trim();
+        match key {
+            "autolock" => self.auto_lock_timeout = Duration::from_secs(parse_number(key, value)?),
+            "lockwarn" => {
+                let secs = parse_number(key, value)?;
+                self.lock_warning = (secs > 0).then(|| Duration::from_secs(secs));
+            }
+            "focuslock" => {
+                let secs = parse_number(key, value)?;
+                self.focus_lock_after = (secs > 0).then(|| Duration::from_secs(secs));
+            }
+            "cliptimeout" => self.clipboard_timeout = Duration::from_secs(parse_positive(key, value)?),
+            "clipboard" => {
+                self.clipboard.mode = ClipboardMode::from_name(value).ok_or_else(|| format!("Unknown clipboard mode: {}", value))?;
+            }
+            "clipselection" => {
+                self.clipboard.selection = Selection::from_name(value).ok_or_else(|| format!("Unknown selection: {}", value))?;
+            }
+            "showtimeout" => self.password_visibility_timeout = Duration::from_secs(parse_positive(key, value)?),
+            "reauth" => {
+                let minutes = parse_number(key, value)?;
+                self.reauth_after = (minutes > 0).then(|| Duration::from_secs(minutes * 60));
+            }
+            "backupdir" => self.backup_dir = (!value.is_empty()).then(|| PathBuf::from(value)),
+            "backupdaily" => self.backup_keep_daily = parse_number(key, value)? as usize,
+            "backupweekly" => self.backup_keep_weekly = parse_number(key, value)? as usize,
+            "backupinterval" => {
+                let hours = parse_number(key, value)?;
+                self.backup_interval = (hours > 0).then(|| Duration::from_secs(hours * 3600));
+            }
+            "autoexportdir" => self.auto_export_dir = (!value.is_empty()).then(|| expand_home(value)),
+            "autoexportto" => {
+                self.auto_export_to = value
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|key| !key.is_empty())
+                    .map(String::from)
+                    .collect();
+            }
+            "autoexportinterval" => {
+                let hours = parse_number(key, value)?;
+                self.auto_export_interval = (hours > 0).then(|| Duration::from_secs(hours * 3600));
+            }
+            "autoexportkeep" => self.auto_export_keep = parse_number(key, value)? as usize,
+            "auditentries" => {
+                let entries = parse_number(key, value)? as usize;
+                self.audit_max_entries = (entries > 0).then_some(entries);
+            }
+            "auditdays" => {
+                let days = parse_number(key, value)?;
+                self.audit_max_days = (days > 0).then_some(days);
+            }
+            "theme" => {
+                self.theme = match value {
+                    "auto" => None,
+                    name => Some(Theme::from_name(name).ok_or_else(|| format!("Unknown theme: {}", name))?),
+                };
+            }
+            "tagcolors" => self.tag_colors = TagColors::parse(value)?,
+            "deepsearch" => self.deep_search = parse_switch(key, value)?,
+            "sleeplock" => self.lock_on_sleep = parse_switch(key, value)?,
+            "maxage" => self.max_secret_age_days = parse_number(key, value)?,
+            "minstrength" => self.min_strength = parse_number(key, value)?.min(100) as u32,
+            "expirywarn" => self.expiry_warning_days = parse_number(key, value)?,
+            "sshlifetime" => {
+                let secs = parse_number(key, value)?;
+                self.ssh_agent_lifetime = (secs > 0).then(|| Duration::from_secs(secs));
+            }
+            "wordlist" => self.wordlist = (!value.is_empty()).then(|| PathBuf::from(value)),
+            "keyfile" => self.keyfile = (!value.is_empty()).then(|| expand_home(value)),
+            "keyring" => self.keyring_session = parse_switch(key, value)?,
+            "kdfmemory" => self.kdf.memory_cost = parse_kdf(key, value, 8, 4096)? * 1024,
+            "kdftime" => self.kdf.time_cost = parse_kdf(key, value, 1, 100)?,
+            "kdfparallelism" => self.kdf.parallelism = parse_kdf(key, value, 1, 64)?,
+            "search" => {
+                self.search_mode = SearchMode::from_name(value).ok_or_else(|| format!("Unknown search mode: {}", value))?;
+            }
+            _ => return Err(format!("Unknown setting: {}", key)),
+        }
+        Ok(())
+    }
+
+    /// Current value of a setting in its `:set` form
+    pub fn get(&self, key: &str) -> Option<String> {
+        let value = match key {
+            "autolock" => self.auto_lock_timeout.as_secs().to_string(),
+            "lockwarn" => self.lock_warning.map_or(0, |w| w.as_secs()).to_string(),
+            "focuslock" => self.focus_lock_after.map_or(0, |f| f.as_secs()).to_string(),
+            "cliptimeout" => self.clipboard_timeout.as_secs().to_string(),
+            "clipboard" => self.clipboard.mode.name().to_string(),
+            "clipselection" => self.clipboard.selection.name().to_string(),
+            "showtimeout" => self.password_visibility_timeout.as_secs().to_string(),
+            "reauth" => self.reauth_after.map_or(0, |r| r.as_secs() / 60).to_string(),
+            "backupdir" => self.backup_dir.as_ref().map(|d| d.display().to_string()).unwrap_or_default(),
+            "backupdaily" => self.backup_keep_daily.to_string(),
+            "backupweekly" => self.backup_keep_weekly.to_string(),
+            "backupinterval" => self.backup_interval.map_or(0, |i| i.as_secs() / 3600).to_string(),
+            "autoexportdir" => self.auto_export_dir.as_ref().map(|d| d.display().to_string()).unwrap_or_default(),
+            "autoexportto" => self.auto_export_to.join(","),
+            "autoexportinterval" => self.auto_export_interval.map_or(0, |i| i.as_secs() / 3600).to_string(),
+            "autoexportkeep" => self.auto_export_keep.to_string(),
+            "auditentries" => self.audit_max_entries.unwrap_or(0).to_string(),
+            "auditdays" => self.audit_max_days.unwrap_or(0).to_string(),
+            "theme" => self.theme.map_or("auto", |t| t.name()).to_string(),
+            "tagcolors" => self.tag_colors.spec(),
+            "search" => self.search_mode.name().to_string(),
+            "deepsearch" => if self.deep_search { "on" } else { "off" }.to_string(),
+            "sleeplock" => if self.lock_on_sleep { "on" } else { "off" }.to_string(),
+            "maxage" => self.max_secret_age_days.to_string(),
+            "minstrength" => self.min_strength.to_string(),
+            "expirywarn" => self.expiry_warning_days.to_string(),
+            "sshlifetime" => self.ssh_agent_lifetime.map_or(0, |l| l.as_secs()).to_string(),
+            "wordlist" => self.wordlist.as_ref().map(|w| w.display().to_string()).unwrap_or_default(),
+            "keyfile" => self.keyfile.as_ref().map(|k| k.display().to_string()).unwrap_or_default(),
+            "keyring" => if self.keyring_session { "on" } else { "off" }.to_string(),
+            "kdfmemory" => (self.kdf.memory_cost / 1024).to_string(),
+            "kdftime" => self.kdf.time_cost.to_string(),
+            "kdfparallelism" => self.kdf.parallelism.to_string(),
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    /// The configured theme, or the one detected from the terminal
+    pub fn theme(&self) -> Theme {
+        self.theme.unwrap_or_else(Theme::detect)
+    }
+
+    /// Write the current value of a setting into a config file, keeping the others
+    pub fn persist(&self, key: &str, path: &Path) -> Result<(), String> {
+        let value = self.get(key).ok_or_else(|| format!("Unknown setting: {}", key))?;
+        let mut settings = read_settings(path)?.unwrap_or_default();
+        let value = match value.parse::<u64>() {
+            Ok(n) => Value::from(n),
+            Err(_) if key == "tagcolors" => Value::Object(
+                self.tag_colors.entries().map(|(tag, color)| (tag.to_string(), Value::from(color.to_string()))).collect(),
+            ),
+            Err(_) => Value::String(value),
+        };
+        settings.insert(key.to_string(), value);
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(&Value::Object(settings)).map_err(|e| e.to_string())?;
+        fs::write(path, json + "\n").map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+fn read_settings(path: &Path) -> Result<Option<Map<String, Value>>, String> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("{}: {}", path.display(), e)),
+    };
+    match serde_json::from_str(&content) {
+        Ok(Value::Object(settings)) => Ok(Some(settings)),
+        Ok(_) => Err(format!("{}: expected a JSON object", path.display())),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+/// A path as typed, with a leading `~/` meaning the home directory
+pub fn expand_home(value: &str) -> PathBuf {
+    match (value.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(value),
+    }
+}
+
+fn parse_number(key: &str, value: &str) -> Result<u64, String> {
+    value.parse().map_err(|_| format!("{} expects a number, got '{}'", key, value))
+}
+
+fn parse_switch(key: &str, value: &str) -> Result<bool, String> {
+    match value {
+        "on" | "true" | "1" => Ok(true),
+        "off" | "false" | "0" => Ok(false),
+        _ => Err(format!("{} expects on or off, got '{}'", key, value)),
+    }
+}
+
+fn parse_kdf(key: &str, value: &str, min: u32, max: u32) -> Result<u32, String> {
+    match parse_number(key, value)? {
+        n if n < min as u64 || n > max as u64 => Err(format!("{} must be between {} and {}", key, min, max)),
+        n => Ok(n as u32),
+    }
+}
+
+fn parse_positive(key: &str, value: &str) -> Result<u64, String> {
+    match parse_number(key, value)? {
+        0 => Err(format!("{} must be at least 1", key)),
+        n => Ok(n),
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum PendingAction {
     DeleteCredential(String),
+    DeleteCredentials(Vec<String>),
+    RestoreBackup(PathBuf),
+    PurgeTrashed(String),
+    EmptyTrash,
+    DeleteTag(String),
+    /// Parameters from `:kdf calibrate` and how long they took
+    ApplyKdf(KdfParams, Duration),
 }
 
 impl PendingAction {
-    pub fn confirm_message(&self) -> &'static str {
-        match self {
-            Self::DeleteCredential(_) => "Delete this credential?",
-        }
+    pub fn confirm_message(&self) -> String {
+        let message = match self {
+            Self::DeleteCredential(_) => "Move this credential to the trash?",
+            Self::DeleteCredentials(ids) => return format!("Move {} credentials to the trash?", ids.len()),
+            Self::RestoreBackup(_) => "Restore this backup? Current data is backed up first, then the vault locks.",
+            Self::PurgeTrashed(_) => "Delete this credential forever? It cannot be restored.",
+            Self::EmptyTrash => "Empty the trash? Its credentials cannot be restored.",
+            Self::DeleteTag(tag) => return format!("Remove tag {} from every credential?", tag),
+            Self::ApplyKdf(kdf, took) => {
+                return format!("Rehash the password with {} ({} ms per unlock here)?", kdf.summary(), took.as_millis());
+            }
+        };
+        message.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_set_and_get() {
+        let mut config = AppConfig::default();
+        config.set("autolock", "600").unwrap();
+        config.set("cliptimeout", " 10 ").unwrap();
+        config.set("backupinterval", "6").unwrap();
+        assert_eq!(config.auto_lock_timeout, Duration::from_secs(600));
+        assert_eq!(config.clipboard_timeout, Duration::from_secs(10));
+        assert_eq!(config.backup_interval, Some(Duration::from_secs(6 * 3600)));
+        assert_eq!(config.get("backupinterval").as_deref(), Some("6"));
+
+        config.set("kdfmemory", "64").unwrap();
+        assert_eq!(config.kdf.memory_cost, 64 * 1024);
+        assert_eq!(config.get("kdfmemory").as_deref(), Some("64"));
+        assert!(config.set("kdftime", "0").is_err());
+
+        config.set("focuslock", "10").unwrap();
+        assert_eq!(config.focus_lock_after, Some(Duration::from_secs(10)));
+        config.set("focuslock", "0").unwrap();
+        assert_eq!(config.focus_lock_after, None);
+        config.set("lockwarn", "0").unwrap();
+        assert_eq!(config.lock_warning, None);
+
+        config.set("backupinterval", "0").unwrap();
+        assert_eq!(config.backup_interval, None);
+        config.set("autoexportto", "ABCD1234, alice@example.com").unwrap();
+        assert_eq!(config.auto_export_to, ["ABCD1234", "alice@example.com"]);
+        assert_eq!(config.get("autoexportto").as_deref(), Some("ABCD1234,alice@example.com"));
+        config.set("autoexportinterval", "0").unwrap();
+        assert_eq!(config.auto_export_interval, None);
+        config.set("auditentries", "5000").unwrap();
+        assert_eq!(config.audit_max_entries, Some(5000));
+        config.set("auditdays", "0").unwrap();
+        assert_eq!(config.audit_max_days, None);
+        config.set("sshlifetime", "0").unwrap();
+        assert_eq!(config.ssh_agent_lifetime, None);
+        config.set("reauth", "10").unwrap();
+        assert_eq!(config.reauth_after, Some(Duration::from_secs(600)));
+        assert_eq!(config.get("reauth").as_deref(), Some("10"));
+
+        assert!(config.set("cliptimeout", "0").is_err());
+        assert!(config.set("autolock", "soon").is_err());
+        assert!(config.set("nosuch", "1").is_err());
+
+        config.set("deepsearch", "on").unwrap();
+        config.set("search", "fuzzy").unwrap();
+        assert!(config.deep_search);
+        assert_eq!(config.get("deepsearch").as_deref(), Some("on"));
+        assert_eq!(config.search_mode, SearchMode::Fuzzy);
+        assert!(config.set("deepsearch", "maybe").is_err());
+
+        config.set("theme", "light").unwrap();
+        assert_eq!(config.theme(), Theme::Light);
+        assert!(config.set("theme", "solarized").is_err());
+        config.set("theme", "auto").unwrap();
+        assert_eq!(config.theme, None);
+        assert!(SETTINGS.iter().all(|(key, _)| config.get(key).is_some()));
+    }
+
+    #[test]
+    fn test_persist_and_load() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("vault").join("config.json");
+
+        let mut config = AppConfig::default();
+        config.set("autolock", "600").unwrap();
+        config.persist("autolock", &path).unwrap();
+        config.set("backupdir", "/tmp/vault-backups").unwrap();
+        config.persist("backupdir", &path).unwrap();
+        config.set("tagcolors", "work:blue,home/iot:#ff8800").unwrap();
+        config.persist("tagcolors", &path).unwrap();
+
+        let mut loaded = AppConfig::default();
+        loaded.apply_file(&path).unwrap();
+        assert_eq!(loaded.auto_lock_timeout, Duration::from_secs(600));
+        assert_eq!(loaded.backup_dir, Some(PathBuf::from("/tmp/vault-backups")));
+        assert_eq!(loaded.clipboard_timeout, AppConfig::default().clipboard_timeout);
+        assert_eq!(loaded.tag_colors, config.tag_colors);
+        assert!(fs::read_to_string(&path).unwrap().contains(r#""work": "Blue""#));
+
+        fs::write(&path, r#"{"cliptimeout": "never"}"#).unwrap();
+        assert!(AppConfig::default().apply_file(&path).is_err());
+        assert!(AppConfig::default().apply_file(&dir.path().join("missing.json")).is_ok());
     }
 }
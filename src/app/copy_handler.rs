@@ -0,0 +1,106 @@
+//! Copying a username and then its password, for filling login forms
+
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::Instant;
+
+use crate::db::AuditAction;
+use crate::input::keymap::Action;
+use crate::ui::MessageType;
+
+use super::clipboard;
+use super::reauth_handler::Guarded;
+use super::App;
+
+/// A username copied with its password waiting to follow
+pub struct CopySequence {
+    /// Credential whose password follows
+    id: String,
+    deadline: Instant,
+    /// Signals the username was pasted, where the clipboard can tell
+    pasted: Option<Receiver<()>>,
+    /// Seconds left when the countdown was last shown
+    shown: u64,
+}
+
+impl App {
+    /// Copy the username; the password replaces it once the username is
+    /// pasted (on Wayland) or on the next keypress
+    pub fn copy_username_then_password(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.selected_credential.is_none() || !self.reauthorized(Guarded::Copy(Action::CopyUsernameThenPassword)) {
+            return Ok(());
+        }
+        let Some(cred) = &self.selected_credential else { return Ok(()) };
+        if cred.secret.is_none() {
+            return Ok(());
+        }
+        let Some(username) = cred.username.clone() else {
+            self.set_message("No username to copy; use yy for the password", MessageType::Error);
+            return Ok(());
+        };
+        let (id, name) = (cred.id.clone(), cred.name.clone());
+
+        let timeout = self.config.clipboard_timeout;
+        let pasted = clipboard::copy_until_pasted(&username, timeout, self.config.clipboard);
+        if pasted.is_none() {
+            clipboard::copy_with_timeout(&username, timeout, self.config.clipboard);
+        }
+        self.log_audit(AuditAction::Copy, Some(&id), Some(&name), Some(&username), Some("Username"))?;
+
+        self.copy_sequence = Some(CopySequence { id, deadline: Instant::now() + timeout, pasted, shown: 0 });
+        self.tick_copy_sequence();
+        Ok(())
+    }
+
+    /// Follow up with the password once the username is pasted, and count down
+    pub fn tick_copy_sequence(&mut self) {
+        let Some(sequence) = self.copy_sequence.as_mut() else { return };
+
+        let pasted = match &sequence.pasted {
+            Some(rx) => match rx.try_recv() {
+                Ok(()) => true,
+                Err(TryRecvError::Empty) => false,
+                // wl-copy gave up; a keypress still moves on
+                Err(TryRecvError::Disconnected) => {
+                    sequence.pasted = None;
+                    false
+                }
+            },
+            None => false,
+        };
+        if pasted {
+            let _ = self.finish_copy_sequence();
+            return;
+        }
+
+        let left = sequence.deadline.saturating_duration_since(Instant::now()).as_secs();
+        if left == 0 {
+            self.copy_sequence = None;
+            self.set_message("Password not copied: timed out", MessageType::Info);
+            return;
+        }
+        if left != sequence.shown {
+            sequence.shown = left;
+            let prompt = match sequence.pasted {
+                Some(_) => "paste it or press any key",
+                None => "press any key",
+            };
+            self.set_message(&format!("Username copied: {} for the password, Esc cancels ({}s)", prompt, left), MessageType::Info);
+        }
+    }
+
+    /// Replace the username with the password
+    pub fn finish_copy_sequence(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(sequence) = self.copy_sequence.take() else { return Ok(()) };
+        if self.selected_credential.as_ref().is_none_or(|c| c.id != sequence.id) {
+            self.set_message("Password not copied: selection changed", MessageType::Error);
+            return Ok(());
+        }
+        self.copy_secret()
+    }
+
+    pub fn cancel_copy_sequence(&mut self) {
+        if self.copy_sequence.take().is_some() {
+            self.set_message("Password not copied", MessageType::Info);
+        }
+    }
+}
@@ -1,73 +1,321 @@
-use secrecy::ExposeSecret;
+use chrono::{Local, Utc};
+use secrecy::{ExposeSecret, SecretString};
+use std::cmp::Reverse;
+use std::collections::HashSet;
 use std::path::Path;
 
-use crate::crypto::{totp::{self, TotpSecret}, decrypt_string};
-use crate::db::{models::Credential, AuditAction};
+use crate::crypto::totp::{self, TotpSecret};
+use crate::crypto::DataEncryptionKey;
+use crate::db::{models::{Credential, CredentialType}, AuditAction};
 use crate::ui::{
     components::{
         ExportDialog,
+        export::{ExportScope, ScopeSizes},
+        ImportDialog,
+        import::BundlePreview,
         CredentialDetail,
+        detail::DetailField,
         CredentialForm,
-        CredentialItem,
         MessageType,
-        form::EditFormParams
+        SortOrder,
+        form::{is_secret_field, EditFormParams}
     },
     renderer::View
 };
 use crate::vault::{
-    credential::DecryptedCredential,
-    export::{ExportData, ExportCredential, export_to_file, credential_to_export}
+    credential::{decrypt_credential_data, decrypt_fields, decrypt_notes, encrypt_fields, secret_strength, DecryptedCredential},
+    backup,
+    aegis::{aegis_entries, create_aegis_vault, write_aegis_vault},
+    bundle::{create_bundle, merge_bundle, read_bundle, replace_with_bundle, write_bundle, BundleImportMode},
+    export::{ExportData, ExportEncryption, ExportFormat, ExportKey, export_to_file, credential_to_export},
+    import::{apply_import, plan_import, read_import_file, ImportSummary},
+    index::IndexEntry,
+    pass::{pass_entries, write_pass_store},
+    duplicates::{find_duplicates, merge_duplicates},
+    search::{self, SearchMode, TagMatch},
+    ssh::{self, KeyAlgorithm},
+    certificate::{self, CertificateInfo},
+    expiry::{self, ExpiryStatus, EXPIRES_FIELD},
+    Vault,
 };
+use crate::input::keymap::Action;
 use crate::input::TextEditing;
 
+use super::config::expand_home;
+use super::reauth_handler::Guarded;
 use super::App;
 
 impl App {
     pub fn refresh_data(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         let db = self.vault.db()?;
-        
-        let mut results = self.fetch_base_credentials(db)?;
-        
-        if let Some(ref query) = self.search_query {
-            apply_search_filter(&mut results, query);
+        self.search_index.sync(db.conn())?;
+
+        // Lowercased once for every entry; fuzzy matching ignores case anyway
+        let query = self.search_query.as_deref().map(|q| {
+            let (mode, query) = search::parse_query(q, self.config.search_mode);
+            (mode, query.to_lowercase())
+        });
+        let query = query.as_ref().map(|(mode, query)| (*mode, query.as_str()));
+        let deep_matches = self.deep_search.as_ref().and_then(|d| d.matches_for(self.search_query.as_deref()));
+        let mut results = self.fetch_base_credentials(db, query, deep_matches)?;
+        sort_credentials(&mut results, self.list_state.sort, self.vault.dek().ok());
+        if let Some((SearchMode::Fuzzy, query)) = query.filter(|(_, q)| !q.is_empty()) {
+            // Stable, so equally good matches keep the chosen order
+            results.sort_by_cached_key(|c| Reverse(search::credential_fuzzy_score(c, query)));
         }
         
+        if !self.list_state.marked.is_empty() {
+            let ids: HashSet<&str> = results.iter().map(|c| c.id.as_str()).collect();
+            self.list_state.marked.retain(|id| ids.contains(id.as_str()));
+        }
         self.credentials = results;
-        self.credential_items = self.credentials.iter().map(credential_to_item).collect();
-        self.list_state.set_total(self.credential_items.len());
+        self.list_state.set_total(self.credentials.len());
         Ok(())
     }
 
-    fn fetch_base_credentials(&self, db: &crate::db::Database) -> Result<Vec<Credential>, Box<dyn std::error::Error>> {
-        match &self.filter_tags {
-            Some(tags) if !tags.is_empty() => Ok(crate::vault::search::filter_by_tags(db.conn(), tags)?),
-            _ => Ok(crate::vault::search::get_all(db.conn())?),
+    /// Credentials passing the filters and the search, from the search index
+    fn fetch_base_credentials(
+        &self,
+        db: &crate::db::Database,
+        query: Option<(SearchMode, &str)>,
+        deep_matches: Option<&HashSet<String>>,
+    ) -> Result<Vec<Credential>, Box<dyn std::error::Error>> {
+        let tags = self.filter_tags.as_deref().unwrap_or_default();
+        let types = self.filter_types.as_deref().unwrap_or_default();
+        let mut credentials: Vec<Credential> = self
+            .search_index
+            .filter(tags, self.tag_match, types)
+            .filter(|entry| search_matches(entry, query, deep_matches))
+            .map(|entry| entry.credential().clone())
+            .collect();
+        if self.filter_expiring {
+            let expiring: HashSet<String> = expiry::find_expiring(db.conn(), self.vault.dek()?, self.config.expiry_warning_days)?
+                .into_iter()
+                .map(|(cred, _)| cred.id)
+                .collect();
+            credentials.retain(|c| expiring.contains(&c.id));
+        }
+        if self.filter_rotation {
+            let now = Local::now();
+            credentials.retain(|c| c.rotation_due().is_some_and(|due| due <= now));
+        }
+        Ok(credentials)
+    }
+
+    /// `:expiring` narrows the list to credentials that have expired or expire soon
+    pub fn toggle_expiring_filter(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.vault.is_unlocked() {
+            self.set_message("Vault must be unlocked", MessageType::Error);
+            return Ok(());
+        }
+        self.filter_expiring = !self.filter_expiring;
+        self.refresh_data()?;
+        if self.filter_expiring {
+            let days = self.config.expiry_warning_days;
+            self.set_message(&format!("Showing credentials expiring within {} days", days), MessageType::Info);
+        }
+        self.update_selected_detail()
+    }
+
+    /// `:rotation` narrows the list to credentials whose secret is due for rotation
+    pub fn toggle_rotation_filter(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.vault.is_unlocked() {
+            self.set_message("Vault must be unlocked", MessageType::Error);
+            return Ok(());
         }
+        self.filter_rotation = !self.filter_rotation;
+        self.refresh_data()?;
+        if self.filter_rotation {
+            self.set_message(&format!("{} credential(s) due for rotation", self.credentials.len()), MessageType::Info);
+        }
+        self.update_selected_detail()
+    }
+
+    /// Point out expiring credentials and secrets due for rotation right after unlocking
+    pub fn warn_reminders(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let db = self.vault.db()?;
+        let days = self.config.expiry_warning_days;
+        let mut parts = Vec::new();
+        if days > 0 {
+            let expiring = expiry::find_expiring(db.conn(), self.vault.dek()?, days)?;
+            if !expiring.is_empty() {
+                let expired = expiring.iter().filter(|(_, expires)| *expires <= Utc::now()).count();
+                parts.push(format!(
+                    "{} credential(s) expired, {} expiring within {} days (:expiring)",
+                    expired,
+                    expiring.len() - expired,
+                    days
+                ));
+            }
+        }
+        let due = expiry::find_rotation_due(db.conn())?;
+        if !due.is_empty() {
+            parts.push(format!("{} due for rotation (:rotation)", due.len()));
+        }
+        if !parts.is_empty() {
+            self.set_message(&parts.join("; "), MessageType::Warning);
+        }
+        Ok(())
+    }
+
+    /// `:sort <order>` picks an order, `:sort` and `o` cycle to the next one
+    pub fn sort_list(&mut self, order: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+        let order = match order {
+            None => self.list_state.sort.next(),
+            Some(name) => match SortOrder::from_name(name) {
+                Some(order) => order,
+                None => {
+                    let names: Vec<&str> = SortOrder::ALL.iter().map(|o| o.name()).collect();
+                    self.set_message(&format!("Unknown sort: {} (use {})", name, names.join(", ")), MessageType::Error);
+                    return Ok(());
+                }
+            },
+        };
+
+        self.list_state.sort = order;
+        self.refresh_keeping_selection()?;
+        self.set_message(&format!("Sorted by {}", order.name()), MessageType::Info);
+        Ok(())
     }
 
     pub fn clear_credentials(&mut self) {
         self.credentials.clear();
-        self.credential_items.clear();
+        self.decryption_cache.clear();
+        self.search_index.clear();
         self.selected_credential = None;
         self.selected_detail = None;
     }
 
-    pub fn search_credentials(&mut self, query: &str) -> Result<(), Box<dyn std::error::Error>> {
-        self.search_query = if query.is_empty() { None } else { Some(query.to_string()) };
+    pub fn filter_by_tag(&mut self, tags: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+        self.filter_tags = if tags.is_empty() { None } else { Some(tags.to_vec()) };
         self.refresh_data()?;
+
+        if !tags.is_empty() {
+            self.set_message(&format_filter_message(tags, self.tag_match), MessageType::Info);
+        }
         self.update_selected_detail()
     }
 
-    pub fn filter_by_tag(&mut self, tags: &[String]) -> Result<(), Box<dyn std::error::Error>> {
-        self.filter_tags = if tags.is_empty() { None } else { Some(tags.to_vec()) };
+    /// Switch the tag filter between matching all and any of its tags
+    pub fn toggle_tag_match(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.tag_match = self.tag_match.toggled();
+        let message = match self.tag_match {
+            TagMatch::All => "Tag filter matches all selected tags",
+            TagMatch::Any => "Tag filter matches any selected tag",
+        };
+        self.set_message(message, MessageType::Info);
+        if self.filter_tags.is_some() {
+            self.refresh_data()?;
+            self.update_selected_detail()?;
+        }
+        Ok(())
+    }
+
+    pub fn filter_by_type(&mut self, types: &[CredentialType]) -> Result<(), Box<dyn std::error::Error>> {
+        self.filter_types = if types.is_empty() { None } else { Some(types.to_vec()) };
         self.refresh_data()?;
 
+        if !types.is_empty() {
+            let names: Vec<&str> = types.iter().map(|t| t.display_name()).collect();
+            self.set_message(&format!("Filtered by type: {}", names.join(", ")), MessageType::Info);
+        }
+        self.update_selected_detail()
+    }
+
+    /// `:filter type=api_key,note tag=work` filters by type and tag in one
+    /// go, replacing those filters; `:filter` alone clears every filter
+    pub fn filter_command(&mut self, arg: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(arg) = arg else {
+            self.clear_filters()?;
+            self.set_message("Filters cleared", MessageType::Info);
+            return Ok(());
+        };
+
+        let mut types = Vec::new();
+        let mut tags = Vec::new();
+        for term in arg.split_whitespace() {
+            let values = |list: &str| list.split(',').filter(|v| !v.is_empty()).map(String::from).collect::<Vec<_>>();
+            match term.split_once('=') {
+                Some(("type", list)) => {
+                    for name in values(list) {
+                        let Some(cred_type) = CredentialType::from_name(&name) else {
+                            self.set_message(&format!("Unknown type: {}", name), MessageType::Error);
+                            return Ok(());
+                        };
+                        types.push(cred_type);
+                    }
+                }
+                Some(("tag", list)) => tags.extend(values(list)),
+                _ => {
+                    self.set_message("Usage: :filter [type=name,...] [tag=name,...]", MessageType::Error);
+                    return Ok(());
+                }
+            }
+        }
+
+        let mut parts = Vec::new();
+        if !types.is_empty() {
+            let names: Vec<&str> = types.iter().map(|t| t.display_name()).collect();
+            parts.push(format!("Filtered by type: {}", names.join(", ")));
+        }
         if !tags.is_empty() {
-            self.set_message(&format_filter_message(tags), MessageType::Info);
+            parts.push(format_filter_message(&tags, self.tag_match));
+        }
+        self.filter_types = (!types.is_empty()).then_some(types);
+        self.filter_tags = (!tags.is_empty()).then_some(tags);
+        self.refresh_data()?;
+        if !parts.is_empty() {
+            self.set_message(&parts.join("; "), MessageType::Info);
         }
         self.update_selected_detail()
     }
 
+    /// `:goto name` selects the listed credential called `name`, or else the
+    /// first whose name starts with it, ignoring case
+    pub fn goto_credential(&mut self, name: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(name) = name else {
+            self.set_message("Usage: :goto <name>", MessageType::Error);
+            return Ok(());
+        };
+        let wanted = name.to_lowercase();
+        let names: Vec<String> = self.credentials.iter().map(|c| c.name.to_lowercase()).collect();
+        let found = names.iter().position(|n| *n == wanted).or_else(|| names.iter().position(|n| n.starts_with(&wanted)));
+        let Some(index) = found else {
+            self.set_message(&format!("No credential named {}", name), MessageType::Error);
+            return Ok(());
+        };
+        self.list_state.select(Some(index));
+        self.update_selected_detail()
+    }
+
+    /// `m<letter>` remembers the selected credential until vault quits
+    pub fn set_mark(&mut self, mark: char) {
+        let Some(item) = self.list_state.selected().and_then(|i| self.credentials.get(i)) else { return };
+        let message = format!("Mark '{} set on {}", mark, item.name);
+        self.list_state.jump_marks.insert(mark, item.id.clone());
+        self.set_message(&message, MessageType::Info);
+    }
+
+    /// `'<letter>` selects the marked credential, clearing the search and
+    /// filters when they hide it
+    pub fn jump_to_mark(&mut self, mark: char) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(id) = self.list_state.jump_marks.get(&mark).cloned() else {
+            self.set_message(&format!("Mark '{} not set", mark), MessageType::Error);
+            return Ok(());
+        };
+        let position = |items: &[Credential]| items.iter().position(|item| item.id == id);
+        if position(&self.credentials).is_none() {
+            self.clear_filters()?;
+        }
+        let Some(index) = position(&self.credentials) else {
+            self.list_state.jump_marks.remove(&mark);
+            self.set_message(&format!("Mark '{} was on a deleted credential", mark), MessageType::Error);
+            return Ok(());
+        };
+        self.list_state.select(Some(index));
+        self.update_selected_detail()
+    }
+
     pub fn update_selected_detail(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         let Some(idx) = self.list_state.selected() else {
             self.selected_detail = None;
@@ -80,9 +328,12 @@ impl App {
 
         let key = self.vault.dek()?;
         let db = self.vault.db()?;
-        let decrypted = crate::vault::credential::decrypt_credential(db.conn(), key, cred, false)?;
+        let decrypted = self.decryption_cache.get(db.conn(), key, cred)?;
 
-        self.selected_detail = Some(build_detail(&decrypted, self.password_visible));
+        if self.selected_credential.as_ref().is_none_or(|c| c.id != decrypted.id) {
+            self.detail_scroll.reset();
+        }
+        self.selected_detail = Some(build_detail(&decrypted, self.password_visible, self.config.expiry_warning_days));
         self.selected_credential = Some(decrypted);
         Ok(())
     }
@@ -108,7 +359,7 @@ impl App {
 
         let key = self.vault.dek()?;
         let db = self.vault.db()?;
-        let decrypted = crate::vault::credential::decrypt_credential(db.conn(), key, cred, false)?;
+        let decrypted = self.decryption_cache.get(db.conn(), key, cred)?;
         self.open_edit_form(&decrypted);
         Ok(())
     }
@@ -124,6 +375,10 @@ impl App {
             tags: cred.tags.clone(),
             totp_secret: cred.totp_secret.as_ref().map(|s| s.expose_secret().to_string()),
             notes: cred.notes.as_ref().map(|s| s.expose_secret().to_string()),
+            fields: cred.fields.iter().map(|(label, value)| (label.clone(), value.expose_secret().to_string())).collect(),
+            expires_at: cred.expires_at,
+            rotate_every: cred.rotate_every,
+            password_rule: cred.password_rule.clone(),
             previous_view: self.view,
         });
         self.credential_form = Some(form);
@@ -163,17 +418,22 @@ impl App {
         cred.username = form.get_username();
         cred.url = form.get_url();
         cred.tags = form.get_tags();
+        cred.encrypted_fields = encrypt_fields(key, &cred.id, &form.get_fields())?;
+        cred.expires_at = form.get_expires_at();
+        cred.rotate_every = form.get_rotate_every();
+        cred.password_rule = form.get_password_rule();
 
         crate::vault::credential::update_credential(
             db.conn(),
             key,
             &mut cred,
             Some(form.get_secret()),
-            form.get_notes().as_deref(),
-            form.get_totp_secret().as_deref(),
+            form.get_notes(),
+            form.get_totp_secret(),
         )?;
 
         self.log_audit(AuditAction::Update, Some(id), Some(&cred.name), cred.username.as_deref(), None)?;
+        self.reindex([id])?;
         self.set_message("Credential updated", MessageType::Success);
         Ok(())
     }
@@ -191,11 +451,15 @@ impl App {
             form.get_username(),
             form.get_url(),
             form.get_tags(),
-            form.get_notes().as_deref(),
-            form.get_totp_secret().as_deref(),
+            form.get_notes(),
+            form.get_totp_secret(),
+            &form.get_fields(),
         )?;
+        crate::db::set_reminders(db.conn(), &cred.id, form.get_expires_at(), form.get_rotate_every())?;
+        crate::db::set_password_rule(db.conn(), &cred.id, form.get_password_rule().as_deref())?;
 
         self.log_audit(AuditAction::Create, Some(&cred.id), Some(&cred.name), cred.username.as_deref(), None)?;
+        self.reindex([cred.id.as_str()])?;
         self.set_message("Credential created", MessageType::Success);
         Ok(())
     }
@@ -204,8 +468,10 @@ impl App {
         let db = self.vault.db()?;
         let cred = crate::db::get_credential(db.conn(), id)?;
         crate::db::delete_credential(db.conn(), id)?;
-        self.log_audit(AuditAction::Delete, Some(id), Some(&cred.name), cred.username.as_deref(), None)?;
-        
+        self.decryption_cache.remove(id);
+        self.log_audit(AuditAction::Delete, Some(id), Some(&cred.name), cred.username.as_deref(), Some("Moved to trash"))?;
+        self.reindex([id])?;
+
         let viewing_deleted = self.view == View::Detail
             && self.selected_credential.as_ref().is_some_and(|c| c.id == id);
         if viewing_deleted {
@@ -218,18 +484,19 @@ impl App {
             self.refresh_data()?;
         }
         self.update_selected_detail()?;
-        self.set_message("Credential deleted", MessageType::Success);
+        self.set_message("Moved to trash (:trash to restore)", MessageType::Success);
         Ok(())
     }
 
     pub fn copy_secret(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.selected_credential.is_none() || !self.reauthorized(Guarded::Copy(Action::CopyPassword)) {
+            return Ok(());
+        }
         let Some(cred) = &self.selected_credential else { return Ok(()) };
         let Some(secret) = &cred.secret else { return Ok(()) };
 
-        let text = secret.expose_secret().to_string();
+        super::clipboard::copy_with_timeout(secret.expose_secret(), self.config.clipboard_timeout, self.config.clipboard);
         let (id, name, username) = (cred.id.clone(), cred.name.clone(), cred.username.clone());
-
-        super::clipboard::copy_with_timeout(&text, self.config.clipboard_timeout);
         self.log_audit(AuditAction::Copy, Some(&id), Some(&name), username.as_deref(), Some("Secret"))?;
         self.set_message(&format!("Password copied ({}s)", self.config.clipboard_timeout.as_secs()), MessageType::Success);
         Ok(())
@@ -242,13 +509,16 @@ impl App {
         let text = username.clone();
         let (id, name, u) = (cred.id.clone(), cred.name.clone(), cred.username.clone());
 
-        super::clipboard::copy_with_timeout(&text, self.config.clipboard_timeout);
+        super::clipboard::copy_with_timeout(&text, self.config.clipboard_timeout, self.config.clipboard);
         self.log_audit(AuditAction::Copy, Some(&id), Some(&name), u.as_deref(), Some("Username"))?;
         self.set_message(&format!("Username copied ({}s)", self.config.clipboard_timeout.as_secs()), MessageType::Success);
         Ok(())
     }
 
     pub fn copy_totp(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.selected_credential.is_none() || !self.reauthorized(Guarded::Copy(Action::CopyTotp)) {
+            return Ok(());
+        }
         let Some(cred) = &self.selected_credential else { return Ok(()) };
         let Some(totp_input) = &cred.totp_secret else {
             self.set_message("No TOTP secret configured", MessageType::Error);
@@ -269,13 +539,16 @@ impl App {
         let remaining = totp::time_remaining(&totp_secret);
         let (id, name, username) = (cred.id.clone(), cred.name.clone(), cred.username.clone());
 
-        super::clipboard::copy_with_timeout(&code, self.config.clipboard_timeout);
+        super::clipboard::copy_with_timeout(&code, self.config.clipboard_timeout, self.config.clipboard);
         self.log_audit(AuditAction::Copy, Some(&id), Some(&name), username.as_deref(), Some("TOTP"))?;
         self.set_message(&format!("TOTP copied: {} ({}s remaining)", code, remaining), MessageType::Success);
         Ok(())
     }
 
     pub fn copy_totp_uri(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.selected_credential.is_none() || !self.reauthorized(Guarded::Copy(Action::CopyTotpUri)) {
+            return Ok(());
+        }
         let Some(cred) = &self.selected_credential else { return Ok(()) };
         let Some(totp_input) = &cred.totp_secret else {
             self.set_message("No TOTP secret configured", MessageType::Error);
@@ -289,34 +562,197 @@ impl App {
         };
 
         let uri = match totp_secret.to_uri() {
-            Ok(u) => u,
+            Ok(u) => zeroize::Zeroizing::new(u),
             Err(e) => { self.set_message(&format!("Failed to generate URI: {}", e), MessageType::Error); return Ok(()); }
         };
 
         let (id, name, username) = (cred.id.clone(), cred.name.clone(), cred.username.clone());
 
-        super::clipboard::copy_with_timeout(&uri, self.config.clipboard_timeout);
+        super::clipboard::copy_with_timeout(&uri, self.config.clipboard_timeout, self.config.clipboard);
         self.log_audit(AuditAction::Copy, Some(&id), Some(&name), username.as_deref(), Some("TOTP URI"))?;
         self.set_message(&format!("TOTP URI copied ({}s)", self.config.clipboard_timeout.as_secs()), MessageType::Success);
         Ok(())
     }
 
-    pub fn generate_and_copy_password(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let password = crate::crypto::generate_password(&crate::crypto::PasswordPolicy::default())?;
-        super::clipboard::copy_with_timeout(&password, self.config.clipboard_timeout);
+    /// Copy the public half of an SSH key, never touching the private key on the clipboard
+    pub fn copy_public_key(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(cred) = &self.selected_credential else { return Ok(()) };
+        let Some(public_key) = ssh_public_key(cred) else {
+            self.set_message("No SSH public key", MessageType::Error);
+            return Ok(());
+        };
+        let (id, name, username) = (cred.id.clone(), cred.name.clone(), cred.username.clone());
+
+        super::clipboard::copy_with_timeout(&public_key, self.config.clipboard_timeout, self.config.clipboard);
+        self.log_audit(AuditAction::Copy, Some(&id), Some(&name), username.as_deref(), Some("Public key"))?;
+        self.set_message(&format!("Public key copied ({}s)", self.config.clipboard_timeout.as_secs()), MessageType::Success);
+        Ok(())
+    }
+
+    /// Load the selected SSH key into the running ssh-agent without writing it to disk
+    pub fn add_to_ssh_agent(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(cred) = &self.selected_credential else { return Ok(()) };
+        if cred.credential_type != CredentialType::SshKey {
+            self.set_message("Only SSH keys can be added to ssh-agent", MessageType::Error);
+            return Ok(());
+        }
+        let Some(secret) = &cred.secret else { return Ok(()) };
+
+        let lifetime = self.config.ssh_agent_lifetime;
+        if let Err(e) = ssh::add_to_agent(secret.expose_secret(), lifetime) {
+            self.set_message(&e.to_string(), MessageType::Error);
+            return Ok(());
+        }
+
+        let (id, name, username) = (cred.id.clone(), cred.name.clone(), cred.username.clone());
+        let until = lifetime.map_or("until the agent stops".to_string(), |l| format!("for {}s", l.as_secs()));
+        let detail = format!("Added to ssh-agent {}", until);
+        self.log_audit(AuditAction::Read, Some(&id), Some(&name), username.as_deref(), Some(&detail))?;
+        self.set_message(&detail, MessageType::Success);
+        Ok(())
+    }
+
+    /// `kind` picks the generator: none for the default policy, or `pronounceable`
+    pub fn generate_and_copy_password(&mut self, kind: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+        let policy = match kind {
+            None => crate::crypto::PasswordPolicy::default(),
+            Some("pronounceable" | "pron") => crate::crypto::PasswordPolicy::pronounceable(16),
+            Some(other) => {
+                self.set_message(&format!("Unknown generator: {} (try pronounceable)", other), MessageType::Error);
+                return Ok(());
+            }
+        };
+        let password = zeroize::Zeroizing::new(crate::crypto::generate_password(&policy)?);
+        super::clipboard::copy_with_timeout(&password, self.config.clipboard_timeout, self.config.clipboard);
         self.set_message(
-            &format!("Generated: {} (copied for {}s)", password, self.config.clipboard_timeout.as_secs()),
+            &format!("Generated: {} (copied for {}s)", password.as_str(), self.config.clipboard_timeout.as_secs()),
             MessageType::Success,
         );
         Ok(())
     }
 
+    /// Rotate the selected credential's password in one step: generate one by
+    /// its password rule, save it (the old one stays in history) and copy it
+    pub fn regenerate_secret(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(cred) = &self.selected_credential else { return Ok(()) };
+        if matches!(cred.credential_type, CredentialType::Note | CredentialType::SshKey | CredentialType::Certificate) {
+            self.set_message("Only passwords can be regenerated", MessageType::Error);
+            return Ok(());
+        }
+        let policy = match crate::crypto::PasswordPolicy::from_rule(cred.password_rule.as_deref().unwrap_or_default()) {
+            Ok(policy) => policy,
+            Err(e) => {
+                self.set_message(&e.to_string(), MessageType::Error);
+                return Ok(());
+            }
+        };
+        let password = zeroize::Zeroizing::new(crate::crypto::generate_password(&policy)?);
+        let id = cred.id.clone();
+
+        let db = self.vault.db()?;
+        let key = self.vault.dek()?;
+        let mut stored = crate::db::get_credential(db.conn(), &id)?;
+        crate::vault::credential::replace_secret(db.conn(), key, &mut stored, &password)?;
+
+        super::clipboard::copy_with_timeout(&password, self.config.clipboard_timeout, self.config.clipboard);
+        self.log_audit(AuditAction::Update, Some(&id), Some(&stored.name), stored.username.as_deref(), Some("Regenerated secret"))?;
+        self.set_message(
+            &format!("Password regenerated and copied ({}s)", self.config.clipboard_timeout.as_secs()),
+            MessageType::Success,
+        );
+
+        if let Some(query) = self.search_query.clone() {
+            self.search_credentials(&query)?;
+        } else {
+            self.refresh_data()?;
+        }
+        self.update_selected_detail()
+    }
+
+    /// Replace the open form's secret with a password made by its password rule
+    pub fn generate_form_password(&mut self) {
+        let Some(form) = self.credential_form.as_mut() else { return };
+        let password = form.password_policy().and_then(|policy| crate::crypto::generate_password(&policy));
+        match password {
+            Ok(password) => {
+                let message = match form.get_password_rule() {
+                    Some(rule) => format!("Generated password ({})", rule),
+                    None => "Generated password".to_string(),
+                };
+                form.set_secret(password);
+                self.set_message(&message, MessageType::Success);
+            }
+            Err(e) => self.set_message(&e.to_string(), MessageType::Error),
+        }
+    }
+
+    /// Replace an image path in the form's TOTP field with the otpauth:// URI
+    /// of the QR code in it
+    /// Show or hide the form's password; revealing a saved one may ask for the master password
+    pub fn toggle_form_password(&mut self) {
+        let Some(form) = self.credential_form.as_ref() else { return };
+        if (form.show_password || form.editing_id.is_none() || self.reauthorized(Guarded::FormReveal))
+            && let Some(form) = self.credential_form.as_mut()
+        {
+            form.toggle_password_visibility();
+        }
+    }
+
+    pub fn read_form_totp_qr(&mut self) {
+        let Some(form) = self.credential_form.as_mut() else { return };
+        let Some(input) = form.get_totp_secret() else {
+            self.set_message("Enter the path of a QR code screenshot as the TOTP secret", MessageType::Error);
+            return;
+        };
+        match totp::read_qr_uri(&expand_home(input)) {
+            Ok(uri) => {
+                form.set_totp_secret(uri);
+                self.set_message("Read TOTP secret from QR code", MessageType::Success);
+            }
+            Err(e) => self.set_message(&e.to_string(), MessageType::Error),
+        }
+    }
+
+    /// Fill the open SSH key form with a new keypair
+    pub fn generate_ssh_keypair(&mut self, algorithm: KeyAlgorithm) {
+        let Some(form) = self.credential_form.as_mut() else { return };
+        if form.credential_type != CredentialType::SshKey {
+            self.set_message("Keypairs can only be generated for SSH keys", MessageType::Error);
+            return;
+        }
+
+        match ssh::generate_keypair(algorithm, form.get_name().trim()) {
+            Ok(pair) => {
+                form.set_keypair(pair.private_key.to_string(), pair.public_key);
+                self.set_message(&format!("Generated {} keypair", algorithm.name()), MessageType::Success);
+            }
+            Err(e) => self.set_message(&e.to_string(), MessageType::Error),
+        }
+    }
+
     pub fn export(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         if !self.vault.is_unlocked() {
             self.set_message("Vault must be unlocked", MessageType::Error);
             return Ok(());
         }
-        self.export_dialog = Some(ExportDialog::new());
+        self.search_index.sync(self.vault.db()?.conn())?;
+        let sizes = ScopeSizes {
+            all: self.search_index.entries().count(),
+            filtered: self.has_active_filters().then_some(self.credentials.len()),
+            selected: self.export_selection(ExportScope::Selected).count(),
+        };
+        self.export_dialog = Some(ExportDialog::new(sizes));
+        self.mode_state.enter_export_mode();
+        Ok(())
+    }
+
+    /// Export just the credential open in the detail view
+    pub fn export_current(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(cred) = &self.selected_credential else {
+            self.set_message("No credential selected", MessageType::Error);
+            return Ok(());
+        };
+        self.export_dialog = Some(ExportDialog::for_credential(&cred.name));
         self.mode_state.enter_export_mode();
         Ok(())
     }
@@ -328,15 +764,45 @@ impl App {
             self.set_export_error(e);
             return Ok(());
         }
+        if !self.reauthorized(Guarded::Export) {
+            return Ok(());
+        }
+        let dialog = self.export_dialog.as_ref().ok_or("No export dialog")?;
 
-        let export_creds = self.build_export_credentials()?;
-        let data = ExportData::new(export_creds);
-
-        self.write_export_file(&data, dialog)?;
-
-        let path = dialog.path.clone();
-        self.finalize_export(path.content())?;
-
+        let credentials: Vec<Credential> = self.export_selection(dialog.scope).cloned().collect();
+        // A single credential's export shows in its own audit activity
+        let single = match (dialog.scope, credentials.as_slice()) {
+            (ExportScope::Current, [cred]) => Some(cred.clone()),
+            (ExportScope::Current, _) => {
+                self.set_export_error("The credential is no longer listed".into());
+                return Ok(());
+            }
+            _ => None,
+        };
+        let path = dialog.path.content();
+        let detail = match &single {
+            Some(cred) => format!("Exported {} to {}", cred.name, path),
+            None => export_detail(dialog.scope, dialog.scope_size(), path),
+        };
+        let job = ExportJob {
+            format: dialog.format,
+            encryption: dialog.encryption,
+            passphrase: dialog.get_passphrase(),
+            recipients: dialog.get_recipients(),
+            path: path.to_string(),
+            credentials,
+            detail,
+        };
+        self.run_in_background(" Exporting ", move |vault, _| {
+            let result = job.run(vault).map_err(|e| e.to_string());
+            Box::new(move |app: &mut App| match result {
+                Ok(detail) => app.finalize_export(&detail, single.as_ref()),
+                Err(e) => {
+                    app.set_export_error(e);
+                    Ok(())
+                }
+            })
+        });
         Ok(())
     }
     
@@ -345,51 +811,28 @@ impl App {
             d.error = Some(error);
         }
     }
-    
-    fn build_export_credentials(&self) -> Result<Vec<ExportCredential>, Box<dyn std::error::Error>> {
-        let dek = self.vault.dek()?;
-        let mut export_creds = Vec::new();
-        
-        for cred in &self.credentials {
-            let secret = decrypt_string(dek.as_ref(), &cred.encrypted_secret)?;
-            let notes = self.decrypt_notes_if_present(dek.as_ref(), cred)?;
-            export_creds.push(credential_to_export(cred, secret, notes));
+
+    /// The credentials `scope` covers: the whole vault, the listed ones, the
+    /// marked ones among them, or the open one
+    fn export_selection(&self, scope: ExportScope) -> Box<dyn Iterator<Item = &Credential> + '_> {
+        let marked = &self.list_state.marked;
+        match scope {
+            ExportScope::All => Box::new(self.search_index.entries().map(IndexEntry::credential)),
+            ExportScope::Filtered => Box::new(self.credentials.iter()),
+            ExportScope::Selected => Box::new(self.credentials.iter().filter(|c| marked.contains(&c.id))),
+            ExportScope::Current => {
+                let open = self.selected_credential.as_ref().map(|c| c.id.as_str());
+                Box::new(self.credentials.iter().filter(move |c| open == Some(c.id.as_str())))
+            }
         }
-        
-        Ok(export_creds)
     }
-    
-    fn decrypt_notes_if_present(
-        &self,
-        dek: &[u8],
-        cred: &Credential,
-    ) -> Result<Option<String>, Box<dyn std::error::Error>> {
-        match &cred.encrypted_notes {
-            Some(n) => Ok(Some(decrypt_string(dek, n)?)),
-            None => Ok(None),
+
+    fn finalize_export(&mut self, detail: &str, single: Option<&Credential>) -> Result<(), Box<dyn std::error::Error>> {
+        match single {
+            Some(cred) => self.log_audit(AuditAction::Export, Some(&cred.id), Some(&cred.name), cred.username.as_deref(), Some(detail))?,
+            None => self.log_audit(AuditAction::Export, None, None, None, Some(detail))?,
         }
-    }
-    
-    fn write_export_file(
-        &self,
-        data: &ExportData,
-        dialog: &ExportDialog,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let passphrase_opt = dialog.get_passphrase();
-        let passphrase = passphrase_opt.as_ref().map(|s| s.expose_secret());
-        export_to_file(data, dialog.format, dialog.encryption, passphrase, Path::new(dialog.path.content()))?;
-        Ok(())
-    }
-    
-    fn finalize_export(&mut self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let count = self.credentials.len();
-        let detail = if self.has_active_filters() {
-            format!("Exported {} credential(s) (filtered) to {}", count, path)
-        } else {
-            format!("Exported {} credential(s) to {}", count, path)
-        };
-        self.log_audit(AuditAction::Export, None, None, None, Some(&detail))?;
-        self.set_message(&detail, MessageType::Success);
+        self.set_message(detail, MessageType::Success);
         self.export_dialog = None;
         self.mode_state.enter_normal_mode();
         Ok(())
@@ -399,6 +842,230 @@ impl App {
         self.export_dialog = None;
         self.mode_state.enter_normal_mode();
     }
+
+    pub fn import(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.vault.is_unlocked() {
+            self.set_message("Vault must be unlocked", MessageType::Error);
+            return Ok(());
+        }
+        self.import_dialog = Some(ImportDialog::new());
+        self.mode_state.enter_import_mode();
+        Ok(())
+    }
+
+    /// First Enter builds a preview; the second applies it
+    pub fn execute_import(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let dialog = self.import_dialog.as_mut().ok_or("No import dialog")?;
+
+        if let Err(e) = dialog.validate() {
+            dialog.error = Some(e);
+            return Ok(());
+        }
+
+        if dialog.is_bundle() {
+            let result = match dialog.bundle_preview() {
+                Some(_) => self.apply_bundle_import(),
+                None => self.build_bundle_preview(),
+            };
+            if let (Err(e), Some(dialog)) = (result, self.import_dialog.as_mut()) {
+                dialog.error = Some(e.to_string());
+            }
+            return Ok(());
+        }
+
+        match dialog.take_plan() {
+            Some(plan) => self.apply_import_plan(plan),
+            None => self.build_import_preview(),
+        }
+        Ok(())
+    }
+
+    fn build_import_preview(&mut self) {
+        let Some(dialog) = self.import_dialog.as_ref() else { return };
+        let path = dialog.path.content().trim().to_string();
+        let format = dialog.format;
+        let passphrase = dialog.get_passphrase();
+
+        self.import_in_background(
+            move |vault| {
+                let creds = read_import_file(Path::new(&path), format, passphrase.expose_secret())?;
+                Ok(plan_import(vault.db()?.conn(), vault.dek()?, creds)?)
+            },
+            |app, plan| {
+                if let Some(dialog) = app.import_dialog.as_mut() {
+                    dialog.set_plan(plan);
+                }
+                Ok(())
+            },
+        );
+    }
+
+    fn apply_import_plan(&mut self, plan: crate::vault::import::ImportPlan) {
+        let path = self.import_dialog.as_ref().map(|d| d.path.content().to_string()).unwrap_or_default();
+        self.import_in_background(
+            move |vault| Ok(apply_import(vault.db()?.conn(), vault.dek()?, plan)?),
+            move |app, summary| app.finalize_import(&path, summary),
+        );
+    }
+
+    /// First Enter on a bundle checks the passphrase and decrypts it for the preview
+    fn build_bundle_preview(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let dialog = self.import_dialog.as_ref().ok_or("No import dialog")?;
+        let path = dialog.path.content().trim().to_string();
+        let passphrase = dialog.get_passphrase();
+
+        self.import_in_background(
+            move |_| {
+                let bundle = read_bundle(Path::new(&path))?;
+                let opened = bundle.open(passphrase.expose_secret())?;
+                Ok(BundlePreview {
+                    created_at: bundle.created_at,
+                    credentials: opened.contents.credentials.len(),
+                    audit_logs: opened.contents.audit_logs.len(),
+                    tampered_logs: opened.tampered_logs,
+                })
+            },
+            |app, preview| {
+                if let Some(dialog) = app.import_dialog.as_mut() {
+                    dialog.set_bundle_preview(preview);
+                }
+                Ok(())
+            },
+        );
+        Ok(())
+    }
+
+    fn apply_bundle_import(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let dialog = self.import_dialog.as_ref().ok_or("No import dialog")?;
+        let path = dialog.path.content().trim().to_string();
+        let mode = dialog.bundle_mode;
+        let passphrase = dialog.get_passphrase();
+        let backup_dir = self.backup_config().dir;
+
+        self.import_in_background(
+            move |vault| {
+                let opened = read_bundle(Path::new(&path))?.open(passphrase.expose_secret())?;
+                let detail = match mode {
+                    BundleImportMode::Merge => {
+                        let report = merge_bundle(vault.db()?.conn(), vault.dek()?, &opened)?;
+                        format!(
+                            "Merged bundle {}: {} added, {} updated, {} deleted",
+                            path, report.added, report.updated, report.deleted
+                        )
+                    }
+                    BundleImportMode::Replace => {
+                        let db = vault.db()?;
+                        let snapshot = backup::create_backup(db.conn(), &backup_dir, Some("pre-import"))?;
+                        let replaced = replace_with_bundle(db, vault.keys()?.master_key(), &opened);
+                        vault.reload_keys()?;
+                        replaced?;
+                        format!(
+                            "Replaced vault with bundle {} ({} credentials; previous data saved to {})",
+                            path,
+                            opened.contents.credentials.len(),
+                            snapshot.display()
+                        )
+                    }
+                };
+                Ok(detail)
+            },
+            |app, detail| app.finish_import(&detail),
+        );
+        Ok(())
+    }
+
+    /// Run an import step with the vault on a worker thread; whatever fails
+    /// shows in the dialog, which stays open to correct the path or passphrase
+    fn import_in_background<T, F, G>(&mut self, job: F, then: G)
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Vault) -> Result<T, Box<dyn std::error::Error>> + Send + 'static,
+        G: FnOnce(&mut App, T) -> Result<(), Box<dyn std::error::Error>> + Send + 'static,
+    {
+        self.run_in_background(" Importing ", move |vault, _| {
+            let result = job(vault).map_err(|e| e.to_string());
+            Box::new(move |app: &mut App| {
+                let error = match result.map(|output| then(app, output)) {
+                    Ok(Ok(())) => return Ok(()),
+                    Ok(Err(e)) => e.to_string(),
+                    Err(e) => e,
+                };
+                match app.import_dialog.as_mut() {
+                    Some(dialog) => dialog.error = Some(error),
+                    None => return Err(error.into()),
+                }
+                Ok(())
+            })
+        });
+    }
+
+    fn finalize_import(&mut self, path: &str, summary: ImportSummary) -> Result<(), Box<dyn std::error::Error>> {
+        let detail = format!(
+            "Imported from {}: {} new, {} merged, {} skipped",
+            path, summary.created, summary.merged, summary.skipped
+        );
+        self.finish_import(&detail)
+    }
+
+    fn finish_import(&mut self, detail: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.log_audit(AuditAction::Import, None, None, None, Some(detail))?;
+        self.import_dialog = None;
+        self.mode_state.enter_normal_mode();
+        self.refresh_data()?;
+        self.update_selected_detail()?;
+        self.set_message(detail, MessageType::Success);
+        Ok(())
+    }
+
+    pub fn cancel_import(&mut self) {
+        self.import_dialog = None;
+        self.mode_state.enter_normal_mode();
+    }
+
+    pub fn load_duplicates(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let groups = {
+            let dek = self.vault.dek()?;
+            let db = self.vault.db()?;
+            find_duplicates(db.conn(), dek)?
+        };
+        self.duplicates_state.set_groups(groups);
+        Ok(())
+    }
+
+    /// Delete the entry under the cursor and rescan
+    pub fn delete_selected_duplicate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some((_, entry)) = self.duplicates_state.selected_entry() else { return Ok(()) };
+        let id = entry.id.clone();
+        self.delete_credential(&id)?;
+        self.load_duplicates()
+    }
+
+    /// Merge every entry of the selected group into the entry under the cursor
+    pub fn merge_selected_duplicates(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some((group, entry)) = self.duplicates_state.selected_entry() else { return Ok(()) };
+        let keep = entry.clone();
+        let removed: Vec<_> = group.entries.iter().filter(|e| e.id != keep.id).cloned().collect();
+        let ids: Vec<String> = removed.iter().map(|e| e.id.clone()).collect();
+
+        {
+            let dek = self.vault.dek()?;
+            let db = self.vault.db()?;
+            merge_duplicates(db.conn(), dek, &keep.id, &ids)?;
+        }
+
+        let detail = format!("Merged {} duplicate(s)", removed.len());
+        self.log_audit(AuditAction::Update, Some(&keep.id), Some(&keep.name), keep.username.as_deref(), Some(&detail))?;
+        for e in &removed {
+            let detail = format!("Merged into {}", keep.name);
+            self.log_audit(AuditAction::Delete, Some(&e.id), Some(&e.name), e.username.as_deref(), Some(&detail))?;
+        }
+
+        self.refresh_data()?;
+        self.update_selected_detail()?;
+        self.load_duplicates()?;
+        self.set_message(&detail, MessageType::Success);
+        Ok(())
+    }
 }
 
 fn parse_totp_secret(input: &str, name: &str) -> Result<TotpSecret, String> {
@@ -406,56 +1073,192 @@ fn parse_totp_secret(input: &str, name: &str) -> Result<TotpSecret, String> {
         .map_err(|e| format!("TOTP error: {}", e))
 }
 
-fn apply_search_filter(results: &mut Vec<Credential>, query: &str) {
-    if query.is_empty() {
-        return;
+/// Whether the entry matches the lowercased search, or deep search found it
+fn search_matches(entry: &IndexEntry, query: Option<(SearchMode, &str)>, deep_matches: Option<&HashSet<String>>) -> bool {
+    let Some((mode, query)) = query.filter(|(_, q)| !q.is_empty()) else { return true };
+    let cred = entry.credential();
+    if deep_matches.is_some_and(|m| m.contains(&cred.id)) {
+        return true;
+    }
+    match mode {
+        SearchMode::Fuzzy => search::credential_fuzzy_score(cred, query).is_some(),
+        SearchMode::Substring => entry.contains(query),
+    }
+}
+
+fn sort_credentials(results: &mut [Credential], order: SortOrder, dek: Option<&DataEncryptionKey>) {
+    match order {
+        SortOrder::Name => results.sort_by_cached_key(|c| c.name.to_lowercase()),
+        SortOrder::Updated => results.sort_by_key(|c| Reverse(c.updated_at)),
+        SortOrder::Created => results.sort_by_key(|c| Reverse(c.created_at)),
+        SortOrder::Type => {
+            results.sort_by_cached_key(|c| (c.credential_type.display_name(), c.name.to_lowercase()))
+        }
+        SortOrder::Strength => results.sort_by_cached_key(|c| {
+            // Passwords by strength, weakest first, then everything else
+            let strength = match c.credential_type {
+                CredentialType::Password => dek.and_then(|k| secret_strength(k, c).ok()).unwrap_or(0),
+                _ => u32::MAX,
+            };
+            (strength, c.name.to_lowercase())
+        }),
     }
-    let query_lower = query.to_lowercase();
-    results.retain(|c| {
-        c.name.to_lowercase().contains(&query_lower)
-            || c.username.as_ref().is_some_and(|u| u.to_lowercase().contains(&query_lower))
-            || c.tags.iter().any(|t| t.to_lowercase().contains(&query_lower))
-    });
 }
 
-fn format_filter_message(tags: &[String]) -> String {
+/// An export as chosen in the dialog, run away from the UI thread
+struct ExportJob {
+    format: ExportFormat,
+    encryption: ExportEncryption,
+    passphrase: Option<SecretString>,
+    /// Public keys to encrypt to instead of the passphrase
+    recipients: Vec<String>,
+    path: String,
+    credentials: Vec<Credential>,
+    /// Audit and status line text for the plain formats
+    detail: String,
+}
+
+impl ExportJob {
+    fn run(self, vault: &Vault) -> Result<String, Box<dyn std::error::Error>> {
+        let dek = vault.dek()?;
+        let path = Path::new(&self.path);
+        match self.format {
+            // Bundles always hold the whole vault, whatever filters are active
+            ExportFormat::Bundle => {
+                let passphrase = self.passphrase.ok_or("Passphrase required for vault bundle")?;
+                let bundle = create_bundle(vault.db()?.conn(), dek, passphrase.expose_secret())?;
+                write_bundle(&bundle, path)?;
+                Ok(format!("Exported vault bundle with {} credential(s) to {}", bundle.credential_count, self.path))
+            }
+            ExportFormat::Aegis => {
+                let entries = aegis_entries(dek, &self.credentials)?;
+                if entries.is_empty() {
+                    return Err("No credentials with a TOTP secret to export".into());
+                }
+                let count = entries.len();
+                let passphrase = self.passphrase.ok_or("Passphrase required for Aegis export")?;
+                write_aegis_vault(&create_aegis_vault(entries, passphrase.expose_secret())?, path)?;
+                Ok(format!("Exported {} TOTP secret(s) to {} (Aegis)", count, self.path))
+            }
+            ExportFormat::Pass => {
+                let count = write_pass_store(&pass_entries(dek, &self.credentials)?, path)?;
+                Ok(format!("Exported {} credential(s) to pass store {}", count, self.path))
+            }
+            _ => {
+                let mut export_creds = Vec::new();
+                for cred in &self.credentials {
+                    let secret = decrypt_credential_data(dek, &cred.id, &cred.encrypted_secret)?;
+                    let notes = decrypt_notes(dek, &cred.id, cred.encrypted_notes.as_ref())?;
+                    let fields = decrypt_fields(dek, &cred.id, cred.encrypted_fields.as_ref())?;
+                    export_creds.push(credential_to_export(cred, secret, notes, fields));
+                }
+                let key = match &self.passphrase {
+                    _ if !self.recipients.is_empty() => Some(ExportKey::Recipients(&self.recipients)),
+                    passphrase => passphrase.as_ref().map(|s| ExportKey::Passphrase(s.expose_secret())),
+                };
+                export_to_file(&ExportData::new(export_creds), self.format, self.encryption, key, path)?;
+                Ok(self.detail)
+            }
+        }
+    }
+}
+
+fn export_detail(scope: ExportScope, count: usize, path: &str) -> String {
+    match scope {
+        ExportScope::All => format!("Exported {} credential(s) to {}", count, path),
+        ExportScope::Filtered => format!("Exported {} credential(s) (filtered) to {}", count, path),
+        ExportScope::Selected => format!("Exported {} marked credential(s) to {}", count, path),
+        ExportScope::Current => format!("Exported {} credential to {}", count, path),
+    }
+}
+
+fn format_filter_message(tags: &[String], tag_match: TagMatch) -> String {
     if tags.len() == 1 {
         return format!("Filtered by tag: {}", tags[0]);
     }
-    format!("Filtered by tags: {}", tags.join(", "))
+    format!("Filtered by {} tags: {}", tag_match.name(), tags.join(", "))
 }
 
-pub fn credential_to_item(cred: &Credential) -> CredentialItem {
-    CredentialItem {
-        id: cred.id.clone(),
-        name: cred.name.clone(),
-        username: cred.username.clone(),
-        credential_type: cred.credential_type,
-    }
+/// The credential's SSH public key: derived from the private key, else the one stored with it
+fn ssh_public_key(cred: &DecryptedCredential) -> Option<String> {
+    cred.secret
+        .as_ref()
+        .and_then(|s| ssh::public_key_from_private(s.expose_secret()))
+        .or_else(|| {
+            let (_, value) = cred.fields.iter().find(|(label, _)| label == "Public Key")?;
+            Some(value.expose_secret().trim().to_string()).filter(|v| !v.is_empty())
+        })
+}
+
+/// The certificate a credential holds: its Certificate field, else a PEM secret
+fn certificate_info(cred: &DecryptedCredential) -> Option<CertificateInfo> {
+    cred.fields
+        .iter()
+        .find(|(label, _)| label == "Certificate")
+        .and_then(|(_, value)| certificate::parse_pem(value.expose_secret()))
+        .or_else(|| cred.secret.as_ref().and_then(|s| certificate::parse_pem(s.expose_secret())))
 }
 
-pub fn build_detail(cred: &DecryptedCredential, password_visible: bool) -> CredentialDetail {
-    let (totp_code, totp_remaining) = compute_totp(cred);
+/// Expiring soon means within `warning_days`
+pub fn build_detail(cred: &DecryptedCredential, password_visible: bool, warning_days: u64) -> CredentialDetail {
+    let (totp_code, totp_remaining, totp_next) = compute_totp(cred);
+    let public_key = ssh_public_key(cred);
+    let certificate = certificate_info(cred);
+    let expires = certificate
+        .as_ref()
+        .map(|c| c.not_after)
+        .or_else(|| cred.expires_at.map(|e| e.with_timezone(&Utc)))
+        .or_else(|| {
+            let (_, value) = cred.fields.iter().find(|(label, _)| label == EXPIRES_FIELD)?;
+            expiry::parse_date(value.expose_secret())
+        });
+    let now = Utc::now();
 
     CredentialDetail {
         name: cred.name.clone(),
         credential_type: cred.credential_type,
         username: cred.username.clone(),
-        secret: cred.secret.as_ref().map(|s| s.expose_secret().to_string()),
+        secret: cred.secret.clone(),
         secret_visible: password_visible,
         url: cred.url.clone(),
-        notes: cred.notes.as_ref().map(|s| s.expose_secret().to_string()),
+        notes: cred.notes.clone(),
         tags: cred.tags.clone(),
         created_at: cred.created_at.format("%d-%b-%Y %H:%M").to_string(),
         updated_at: cred.updated_at.format("%d-%b-%Y %H:%M").to_string(),
         totp_code,
         totp_remaining,
+        totp_next,
+        ssh_fingerprint: public_key.as_deref().and_then(ssh::fingerprint),
+        fields: cred
+            .fields
+            .iter()
+            // Shown as the dedicated public key and certificate lines instead
+            .filter(|(label, _)| public_key.is_none() || label != "Public Key")
+            .filter(|(label, _)| certificate.is_none() || label != "Certificate")
+            .filter(|(label, _)| expires.is_none() || label != EXPIRES_FIELD)
+            .map(|(label, value)| DetailField {
+                label: label.clone(),
+                value: value.clone(),
+                secret: is_secret_field(label),
+            })
+            .collect(),
+        ssh_public_key: public_key,
+        certificate,
+        expiry: expires.map(|e| (e, ExpiryStatus::of(e, now, warning_days))),
+        rotation: cred.rotate_every.zip(cred.rotation_due).map(|(days, due)| {
+            let due = due.with_timezone(&Utc);
+            (days, due, ExpiryStatus::of(due, now, warning_days))
+        }),
     }
 }
 
-pub fn compute_totp(cred: &DecryptedCredential) -> (Option<String>, Option<u64>) {
+/// Seconds left on a code at which the next one is shown beside it
+const TOTP_NEXT_PREVIEW_SECS: u64 = 5;
+
+/// The current code, its seconds left and, near expiry, the next code
+pub fn compute_totp(cred: &DecryptedCredential) -> (Option<String>, Option<u64>, Option<String>) {
     let Some(ref totp_input) = cred.totp_secret else {
-        return (None, None);
+        return (None, None, None);
     };
 
     let totp_secret = match TotpSecret::from_user_input(
@@ -464,11 +1267,17 @@ pub fn compute_totp(cred: &DecryptedCredential) -> (Option<String>, Option<u64>)
         "Vault"
     ) {
         Ok(s) => s,
-        Err(_) => return (None, None),
+        Err(_) => return (None, None, None),
     };
 
     match totp::generate_totp(&totp_secret) {
-        Ok(code) => (Some(code), Some(totp::time_remaining(&totp_secret))),
-        Err(_) => (None, None),
+        Ok(code) => {
+            let remaining = totp::time_remaining(&totp_secret);
+            let next = (remaining <= TOTP_NEXT_PREVIEW_SECS)
+                .then(|| totp::generate_next_totp(&totp_secret).ok())
+                .flatten();
+            (Some(code), Some(remaining), next)
+        }
+        Err(_) => (None, None, None),
     }
 }
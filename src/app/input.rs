@@ -1,13 +1,16 @@
 use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 
+use crate::db::CredentialType;
 use crate::input::{
-    keymap::{confirm_action, normal_mode_action, text_input_action, Action},
+    keymap::{confirm_action, count_prefix, counted_action, normal_mode_action, text_input_action, visual_mode_action, Action},
     modes::InputMode,
 };
 use crate::ui::{
-    components::{help::HelpScreen, logs::LogsScreen, tags::TagsPopup},
-    components::{CredentialForm, MessageType, export::ExportField}
+    components::{help::HelpScreen, history::HistoryScreen, logs::LogsScreen, stats::StatsScreen, tags::TagsPopup},
+    components::{CredentialForm, ImportDialog, MessageType, export::ExportField, import::ImportField}
 };
+use crate::vault::import::ConflictResolution;
+use crate::vault::ssh::KeyAlgorithm;
 
 use super::App;
 
@@ -23,6 +26,15 @@ impl App {
             return self.handle_form_key(key);
         }
 
+        // Any key sends the password after a `Y` username copy
+        if self.mode_state.mode == InputMode::Normal && self.copy_sequence.is_some() {
+            match key.code {
+                KeyCode::Esc => self.cancel_copy_sequence(),
+                _ => self.finish_copy_sequence()?,
+            }
+            return Ok(false);
+        }
+
         let action = self.resolve_action(key);
         self.execute_action(action)
     }
@@ -32,17 +44,49 @@ impl App {
             InputMode::Normal => self.resolve_normal_action(key),
             InputMode::Command | InputMode::Search => self.resolve_text_action(key),
             InputMode::Confirm => confirm_action(key),
+            InputMode::Visual => self.resolve_visual_action(key),
             InputMode::Help => self.popup_action(key, help_key_handler),
             InputMode::Logs => self.popup_action(key, logs_key_handler),
+            InputMode::History => self.popup_action(key, history_key_handler),
+            InputMode::Stats => self.popup_action(key, stats_key_handler),
             InputMode::Tags => self.popup_action(key, tags_key_handler),
+            InputMode::Types => self.popup_action(key, types_key_handler),
+            InputMode::Duplicates => self.popup_action(key, duplicates_key_handler),
+            InputMode::Report => self.popup_action(key, report_key_handler),
+            InputMode::Restore => self.popup_action(key, restore_key_handler),
+            InputMode::Trash => self.popup_action(key, trash_key_handler),
+            InputMode::Share => self.popup_action(key, share_key_handler),
             InputMode::Export => self.handle_export_key(key),
+            InputMode::Import => self.handle_import_key(key),
+            InputMode::ImportReview => self.handle_import_review_key(key),
             _ => Action::None,
         }
     }
 
+    /// A count carries across the first key of a pair, so `3dd` deletes three
     fn resolve_normal_action(&mut self, key: KeyEvent) -> Action {
+        if self.mode_state.pending.is_none() && let Some(count) = count_prefix(key, self.mode_state.count) {
+            self.mode_state.count = Some(count);
+            return Action::None;
+        }
         let (action, pending) = normal_mode_action(key, self.mode_state.pending);
         self.mode_state.pending = pending;
+        if pending.is_some() {
+            return action;
+        }
+        match self.mode_state.count.take() {
+            Some(count) => counted_action(action, count),
+            None => action,
+        }
+    }
+
+    /// Operators in visual mode first turn the range into marks, so they run
+    /// through the same bulk path as Space-marked credentials
+    fn resolve_visual_action(&mut self, key: KeyEvent) -> Action {
+        let action = visual_mode_action(key);
+        if matches!(action, Action::Delete | Action::EnterCommand) {
+            self.mark_visual_range();
+        }
         action
     }
 
@@ -63,7 +107,19 @@ impl App {
         let _ = self.execute_action(action);
     }
 
+    /// Search mode filters the list as the query changes
     fn handle_text_input(&mut self, action: Action) -> Action {
+        let searching = self.mode_state.mode == InputMode::Search;
+        let before = self.mode_state.get_buffer().to_string();
+        let result = self.apply_text_input(action);
+        let edited = searching && self.mode_state.mode == InputMode::Search && self.mode_state.get_buffer() != before;
+        if edited && let Err(e) = self.live_search() {
+            self.set_message(&format!("Search failed: {}", e), MessageType::Error);
+        }
+        result
+    }
+
+    fn apply_text_input(&mut self, action: Action) -> Action {
         match action {
             Action::InsertChar(c) => { self.mode_state.insert_char(c); Action::None }
             Action::DeleteChar => { self.mode_state.delete_char(); Action::None }
@@ -75,8 +131,13 @@ impl App {
             Action::CursorHome => { self.mode_state.cursor_home(); Action::None }
             Action::CursorEnd => { self.mode_state.cursor_end(); Action::None }
             Action::ClearToStart => { self.mode_state.clear_to_start(); Action::None }
+            Action::HistoryPrevious | Action::HistoryNext | Action::Complete if self.mode_state.mode != InputMode::Command => Action::None,
+            Action::HistoryPrevious => { self.recall_command(true); Action::None }
+            Action::HistoryNext => { self.recall_command(false); Action::None }
+            Action::Complete => { self.complete_command(); Action::None }
             Action::Submit => self.submit_text_input(),
-            Action::Cancel => { self.mode_state.enter_normal_mode(); Action::None }
+            Action::Cancel if self.mode_state.mode == InputMode::Search => { self.cancel_search(); Action::None }
+            Action::Cancel => { self.command_history.reset(); self.mode_state.enter_normal_mode(); Action::None }
             _ => action,
         }
     }
@@ -84,7 +145,10 @@ impl App {
     fn submit_text_input(&mut self) -> Action {
         let buffer = self.mode_state.get_buffer().to_string();
         let result = match self.mode_state.mode {
-            InputMode::Command => Action::ExecuteCommand(buffer),
+            InputMode::Command => {
+                self.record_command(&buffer);
+                Action::ExecuteCommand(buffer)
+            }
             InputMode::Search => Action::Search(buffer),
             _ => Action::None,
         };
@@ -107,8 +171,19 @@ impl App {
             return self.submit_form();
         }
 
-        let form = self.credential_form.as_mut().unwrap();
-        dispatch_form_key(form, key.code, key.modifiers, self.terminal_size.height);
+        match (key.code, key.modifiers) {
+            (KeyCode::Char('g'), KeyModifiers::CONTROL) if form.credential_type != CredentialType::SshKey => {
+                self.generate_form_password()
+            }
+            (KeyCode::Char('g'), KeyModifiers::CONTROL) => self.generate_ssh_keypair(KeyAlgorithm::Ed25519),
+            (KeyCode::Char('r'), KeyModifiers::CONTROL) => self.generate_ssh_keypair(KeyAlgorithm::Rsa),
+            (KeyCode::Char('o'), KeyModifiers::CONTROL) => self.read_form_totp_qr(),
+            (KeyCode::Char('s'), KeyModifiers::CONTROL) => self.toggle_form_password(),
+            _ => {
+                let form = self.credential_form.as_mut().unwrap();
+                dispatch_form_key(form, key.code, key.modifiers, self.terminal_size.height);
+            }
+        }
         Ok(false)
     }
 
@@ -139,11 +214,69 @@ impl App {
 
         Action::None
     }
+
+    fn handle_import_key(&mut self, key: KeyEvent) -> Action {
+        let Some(dialog) = self.import_dialog.as_mut() else {
+            return Action::None;
+        };
+
+        match (key.code, key.modifiers) {
+            (KeyCode::Esc, _) => self.cancel_import(),
+            (KeyCode::Enter, KeyModifiers::NONE) => { let _ = self.execute_import(); }
+            (KeyCode::Char('r'), KeyModifiers::CONTROL) if dialog.conflict_count() > 0 => {
+                self.mode_state.enter_import_review_mode();
+            }
+            (KeyCode::Tab, KeyModifiers::NONE) | (KeyCode::Down, _) => dialog.next_field(),
+            (KeyCode::BackTab, _) | (KeyCode::Up, _) => dialog.prev_field(),
+            (KeyCode::Char(' '), KeyModifiers::NONE) if dialog.active_field == ImportField::Format => dialog.cycle_format_forward(),
+            (KeyCode::Char(' '), KeyModifiers::CONTROL) if dialog.active_field == ImportField::Format => dialog.cycle_format_backward(),
+            (KeyCode::Char(' '), KeyModifiers::NONE | KeyModifiers::CONTROL) if dialog.active_field == ImportField::Mode => {
+                dialog.toggle_bundle_mode()
+            }
+            (KeyCode::Char(' '), KeyModifiers::NONE) => dialog.insert_char(' '),
+            _ => dialog.handle_text_key(key.code, key.modifiers),
+        }
+
+        Action::None
+    }
+
+    fn handle_import_review_key(&mut self, key: KeyEvent) -> Action {
+        let Some(dialog) = self.import_dialog.as_mut() else {
+            return Action::None;
+        };
+
+        match key.code {
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => self.mode_state.enter_import_mode(),
+            KeyCode::Char('j') | KeyCode::Down => dialog.review_down(),
+            KeyCode::Char('k') | KeyCode::Up => dialog.review_up(),
+            KeyCode::Char(' ') => dialog.cycle_selected_resolution(),
+            KeyCode::Char(c) => apply_review_resolution(dialog, c),
+            _ => {}
+        }
+
+        Action::None
+    }
+}
+
+fn apply_review_resolution(dialog: &mut ImportDialog, c: char) {
+    let resolution = match c.to_ascii_lowercase() {
+        's' => ConflictResolution::Skip,
+        'o' => ConflictResolution::Overwrite,
+        'b' => ConflictResolution::KeepBoth,
+        'm' => ConflictResolution::Merge,
+        _ => return,
+    };
+    if c.is_ascii_uppercase() {
+        dialog.set_all_resolutions(resolution);
+    } else {
+        dialog.set_selected_resolution(resolution);
+    }
 }
 
 fn handle_export_space(dialog: &mut crate::ui::components::export::ExportDialog) {
     match dialog.active_field {
         ExportField::Format => dialog.cycle_format(),
+        ExportField::Scope => dialog.cycle_scope(true),
         ExportField::Encryption => dialog.cycle_encryption_forward(),
         _ => dialog.insert_char(' '),
     }
@@ -152,6 +285,7 @@ fn handle_export_space(dialog: &mut crate::ui::components::export::ExportDialog)
 fn handle_export_ctrl_space(dialog: &mut crate::ui::components::export::ExportDialog) {
     match dialog.active_field {
         ExportField::Format => dialog.cycle_format(),
+        ExportField::Scope => dialog.cycle_scope(false),
         ExportField::Encryption => dialog.cycle_encryption_backward(),
         _ => {}
     }
@@ -161,7 +295,6 @@ fn dispatch_form_key(form: &mut CredentialForm, code: KeyCode, mods: KeyModifier
     match (code, mods) {
         (KeyCode::Tab, KeyModifiers::NONE) | (KeyCode::Down, _) => form.next_field(area_height),
         (KeyCode::BackTab, _) | (KeyCode::Up, _) => form.prev_field(area_height),
-        (KeyCode::Char('s'), KeyModifiers::CONTROL) => form.toggle_password_visibility(),
         (KeyCode::Char(' '), m) if form.is_select_field() => form.cycle_type(m != KeyModifiers::CONTROL),
         _ => { form.handle_text_key(code, mods, area_height); }
     }
@@ -240,6 +373,7 @@ fn logs_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<
 fn logs_exit_action(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<Option<Action>> {
     match (code, mods) {
         (KeyCode::Char('i'), KeyModifiers::NONE)
+        | (KeyCode::Char('I'), KeyModifiers::SHIFT)
         | (KeyCode::Char('q'), KeyModifiers::NONE)
         | (KeyCode::Esc, _) => {
             app.mode_state.enter_normal_mode();
@@ -270,6 +404,54 @@ fn logs_scroll_action(state: &mut crate::ui::components::logs::LogsState, code:
     }
 }
 
+fn history_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<Action> {
+    let visible = HistoryScreen::visible_height(app.terminal_size) as usize;
+    let state = &mut app.history_state;
+    let max_v = state.max_scroll(visible as u16);
+
+    let was_pending = state.scroll.pending_g;
+    state.scroll.pending_g = false;
+
+    match (code, mods) {
+        (KeyCode::Char('q'), KeyModifiers::NONE) | (KeyCode::Char('H'), KeyModifiers::SHIFT) | (KeyCode::Esc, _) => {
+            app.mode_state.enter_normal_mode();
+        }
+        (KeyCode::Char('j'), KeyModifiers::NONE) | (KeyCode::Down, _) => state.scroll_down(1, max_v),
+        (KeyCode::Char('k'), KeyModifiers::NONE) | (KeyCode::Up, _) => state.scroll_up(1),
+        (KeyCode::Char('g'), KeyModifiers::NONE) if was_pending => state.home(),
+        (KeyCode::Char('g'), KeyModifiers::NONE) => state.scroll.pending_g = true,
+        (KeyCode::Char('G'), KeyModifiers::SHIFT) => state.end(max_v),
+        (KeyCode::Char('d'), KeyModifiers::CONTROL) => state.scroll_down(visible / 2, max_v),
+        (KeyCode::Char('u'), KeyModifiers::CONTROL) => state.scroll_up(visible / 2),
+        _ => {}
+    }
+
+    None
+}
+
+fn stats_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<Action> {
+    let visible = StatsScreen::visible_height(app.terminal_size) as usize;
+    let state = &mut app.stats_state;
+    let max_v = state.max_scroll(visible as u16);
+
+    let was_pending = state.scroll.pending_g;
+    state.scroll.pending_g = false;
+
+    match (code, mods) {
+        (KeyCode::Char('q'), KeyModifiers::NONE) | (KeyCode::Esc, _) => app.mode_state.enter_normal_mode(),
+        (KeyCode::Char('j'), KeyModifiers::NONE) | (KeyCode::Down, _) => state.scroll_down(1, max_v),
+        (KeyCode::Char('k'), KeyModifiers::NONE) | (KeyCode::Up, _) => state.scroll_up(1),
+        (KeyCode::Char('g'), KeyModifiers::NONE) if was_pending => state.home(),
+        (KeyCode::Char('g'), KeyModifiers::NONE) => state.scroll.pending_g = true,
+        (KeyCode::Char('G'), KeyModifiers::SHIFT) => state.end(max_v),
+        (KeyCode::Char('d'), KeyModifiers::CONTROL) => state.scroll_down(visible / 2, max_v),
+        (KeyCode::Char('u'), KeyModifiers::CONTROL) => state.scroll_up(visible / 2),
+        _ => {}
+    }
+
+    None
+}
+
 fn tags_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<Action> {
     if let Some(action) = tags_exit_action(app, code, mods) {
         return action;
@@ -314,6 +496,20 @@ fn tags_scroll_action(app: &mut App, code: KeyCode, mods: KeyModifiers, was_pend
         (KeyCode::Char('f'), KeyModifiers::CONTROL) => state.page_down(visible.saturating_sub(1)),
         (KeyCode::Char('b'), KeyModifiers::CONTROL) => state.page_up(visible.saturating_sub(1)),
         (KeyCode::Char(' '), KeyModifiers::NONE) => tags_toggle_and_advance(state),
+        (KeyCode::Char('-'), KeyModifiers::NONE) => {
+            state.toggle_excluded();
+            state.scroll_down();
+        }
+        (KeyCode::Char('a'), KeyModifiers::NONE) => {
+            if let Err(e) = app.toggle_tag_match() {
+                app.set_message(&format!("Filter failed: {}", e), MessageType::Error);
+            }
+        }
+        (KeyCode::Char('r'), KeyModifiers::NONE) => app.prompt_tag_command("renametag"),
+        (KeyCode::Char('m'), KeyModifiers::NONE) => app.prompt_tag_command("mergetag"),
+        (KeyCode::Char('d') | KeyCode::Char('x'), KeyModifiers::NONE) => {
+            return state.tags.get(state.selected).map(|(tag, _)| Action::DeleteTag(Some(tag.clone())));
+        }
         (KeyCode::Enter, _) | (KeyCode::Char('l'), KeyModifiers::NONE) => return handle_tags_select(app),
         _ => {}
     }
@@ -321,14 +517,142 @@ fn tags_scroll_action(app: &mut App, code: KeyCode, mods: KeyModifiers, was_pend
     None
 }
 
+fn types_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<Action> {
+    let state = &mut app.types_state;
+
+    match (code, mods) {
+        (KeyCode::Char('f') | KeyCode::Char('q'), KeyModifiers::NONE) | (KeyCode::Esc, _) => app.mode_state.enter_normal_mode(),
+        (KeyCode::Char('j'), KeyModifiers::NONE) | (KeyCode::Down, _) => state.scroll_down(),
+        (KeyCode::Char('k'), KeyModifiers::NONE) | (KeyCode::Up, _) => state.scroll_up(),
+        (KeyCode::Char('g'), KeyModifiers::NONE) => state.home(),
+        (KeyCode::Char('G'), KeyModifiers::SHIFT) => state.end(),
+        (KeyCode::Char(' '), KeyModifiers::NONE) => {
+            state.toggle_selected();
+            state.scroll_down();
+        }
+        (KeyCode::Enter, _) | (KeyCode::Char('l'), KeyModifiers::NONE) => {
+            let types = state.get_selected_types();
+            app.mode_state.enter_normal_mode();
+            // Empty types will clear the filter
+            if let Err(e) = app.filter_by_type(&types) {
+                app.set_message(&format!("Filter failed: {}", e), MessageType::Error);
+            }
+        }
+        _ => {}
+    }
+
+    None
+}
+
+fn duplicates_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<Action> {
+    let state = &mut app.duplicates_state;
+
+    match (code, mods) {
+        (KeyCode::Char('q'), KeyModifiers::NONE) | (KeyCode::Esc, _) => app.mode_state.enter_normal_mode(),
+        (KeyCode::Char('j'), KeyModifiers::NONE) | (KeyCode::Down, _) => state.scroll_down(),
+        (KeyCode::Char('k'), KeyModifiers::NONE) | (KeyCode::Up, _) => state.scroll_up(),
+        (KeyCode::Char('g'), KeyModifiers::NONE) => state.home(),
+        (KeyCode::Char('G'), KeyModifiers::SHIFT) => state.end(),
+        (KeyCode::Char('d'), KeyModifiers::NONE) | (KeyCode::Char('x'), KeyModifiers::NONE) => {
+            if let Err(e) = app.delete_selected_duplicate() {
+                app.set_message(&format!("Delete failed: {}", e), MessageType::Error);
+            }
+        }
+        (KeyCode::Char('m'), KeyModifiers::NONE) => {
+            if let Err(e) = app.merge_selected_duplicates() {
+                app.set_message(&format!("Merge failed: {}", e), MessageType::Error);
+            }
+        }
+        _ => {}
+    }
+
+    None
+}
+
+fn report_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<Action> {
+    let state = &mut app.report_state;
+
+    match (code, mods) {
+        (KeyCode::Char('q'), KeyModifiers::NONE) | (KeyCode::Esc, _) => app.mode_state.enter_normal_mode(),
+        (KeyCode::Char('j'), KeyModifiers::NONE) | (KeyCode::Down, _) => state.scroll_down(),
+        (KeyCode::Char('k'), KeyModifiers::NONE) | (KeyCode::Up, _) => state.scroll_up(),
+        (KeyCode::Char('g'), KeyModifiers::NONE) => state.home(),
+        (KeyCode::Char('G'), KeyModifiers::SHIFT) => state.end(),
+        (KeyCode::Enter, _) | (KeyCode::Char('l'), KeyModifiers::NONE) => {
+            if let Err(e) = app.open_report_entry() {
+                app.set_message(&format!("Open failed: {}", e), MessageType::Error);
+            }
+        }
+        (KeyCode::Char('e'), KeyModifiers::NONE) => {
+            if let Err(e) = app.edit_report_entry() {
+                app.set_message(&format!("Edit failed: {}", e), MessageType::Error);
+            }
+        }
+        _ => {}
+    }
+
+    None
+}
+
+fn restore_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<Action> {
+    let state = &mut app.backups_state;
+
+    match (code, mods) {
+        (KeyCode::Char('q'), KeyModifiers::NONE) | (KeyCode::Esc, _) => app.mode_state.enter_normal_mode(),
+        (KeyCode::Char('j'), KeyModifiers::NONE) | (KeyCode::Down, _) => state.scroll_down(),
+        (KeyCode::Char('k'), KeyModifiers::NONE) | (KeyCode::Up, _) => state.scroll_up(),
+        (KeyCode::Char('g'), KeyModifiers::NONE) => state.home(),
+        (KeyCode::Char('G'), KeyModifiers::SHIFT) => state.end(),
+        (KeyCode::Enter, _) | (KeyCode::Char('l'), KeyModifiers::NONE) => {
+            return state.selected_backup().map(|b| Action::Restore(b.file_name()));
+        }
+        _ => {}
+    }
+
+    None
+}
+
+fn trash_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<Action> {
+    let state = &mut app.trash_state;
+
+    match (code, mods) {
+        (KeyCode::Char('q'), KeyModifiers::NONE) | (KeyCode::Esc, _) => app.mode_state.enter_normal_mode(),
+        (KeyCode::Char('j'), KeyModifiers::NONE) | (KeyCode::Down, _) => state.scroll_down(),
+        (KeyCode::Char('k'), KeyModifiers::NONE) | (KeyCode::Up, _) => state.scroll_up(),
+        (KeyCode::Char('g'), KeyModifiers::NONE) => state.home(),
+        (KeyCode::Char('G'), KeyModifiers::SHIFT) => state.end(),
+        (KeyCode::Enter, _) | (KeyCode::Char('r'), KeyModifiers::NONE) => {
+            return state.selected_item().map(|t| Action::RestoreTrashed(t.credential.id.clone()));
+        }
+        (KeyCode::Char('d') | KeyCode::Char('x'), KeyModifiers::NONE) => {
+            return state.selected_item().map(|t| Action::PurgeTrashed(t.credential.id.clone()));
+        }
+        (KeyCode::Char('D'), KeyModifiers::SHIFT) if !state.items.is_empty() => return Some(Action::EmptyTrash),
+        _ => {}
+    }
+
+    None
+}
+
+fn share_key_handler(app: &mut App, code: KeyCode, mods: KeyModifiers) -> Option<Action> {
+    match (code, mods) {
+        (KeyCode::Char('q'), KeyModifiers::NONE) | (KeyCode::Esc, _) | (KeyCode::Enter, _) => app.close_share(),
+        (KeyCode::Char('y'), KeyModifiers::NONE) => app.copy_share_block(),
+        (KeyCode::Char('p'), KeyModifiers::NONE) => app.copy_share_passphrase(),
+        _ => {}
+    }
+
+    None
+}
+
 fn tags_toggle_and_advance(state: &mut crate::ui::components::tags::TagsState) {
     state.toggle_selected();
     state.scroll_down();
 }
 
 fn handle_tags_select(app: &mut App) -> Option<Action> {
-    let tags = app.tags_state.get_selected_tags();
-    
+    let tags = app.tags_state.get_filter();
+
     app.mode_state.enter_normal_mode();
     // Empty tags will clear the filter
     let _ = app.filter_by_tag(&tags);
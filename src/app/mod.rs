@@ -3,34 +3,79 @@
 //! Core application logic tying together vault, UI, and input.
 
 mod actions;
-mod clipboard;
+mod auto_export_handler;
+mod backup_handler;
+mod bulk_handler;
+pub mod clipboard;
+mod command_handler;
 mod config;
+mod copy_handler;
 mod credentials_handler;
 mod input;
-
+mod reauth_handler;
+mod report_handler;
+mod search_handler;
+mod settings_handler;
+mod share_handler;
+mod suspend;
+mod task_handler;
+mod tags_handler;
+mod trash_handler;
+mod wipe_handler;
+
+use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
 use ratatui::{layout::Rect, Frame};
 use crossterm::event::MouseEvent;
+use secrecy::{ExposeSecret, SecretString};
 
-use crate::db::models::Credential;
+use crate::crypto::MasterKey;
+use crate::db::models::{Credential, CredentialType};
 use crate::db::AuditAction;
+use crate::input::history::CommandHistory;
 use crate::input::modes::ModeState;
 use crate::input::keymap::{mouse_action, Action};
+use crate::security::Hardening;
 use crate::ui::components::help::HelpState;
+use crate::ui::components::history::HistoryState;
+use crate::ui::components::stats::StatsState;
 use crate::ui::components::logs::LogsState;
 use crate::ui::components::tags::TagsState;
+use crate::ui::components::types::TypesState;
+use crate::ui::components::duplicates::DuplicatesState;
+use crate::ui::components::report::ReportState;
+use crate::ui::components::scroll::ScrollState;
+use crate::ui::components::backups::BackupsState;
+use crate::ui::components::trash::TrashState;
+use crate::ui::components::share::ShareView;
 use crate::ui::components::{
-    CredentialDetail, CredentialForm, CredentialItem, 
-    ExportDialog, ListViewState, MessageType,
+    CredentialDetail, CredentialForm, 
+    ExportDialog, ImportDialog, ListViewState, MessageType,
 };
-use crate::ui::renderer::{Renderer, UiState, View};
+use crate::ui::renderer::{DetailLayout, Renderer, UiState, View};
 use crate::vault::audit;
+use crate::vault::search::TagMatch;
 use crate::vault::credential::DecryptedCredential;
+use crate::vault::cache::DecryptionCache;
+use crate::vault::index::SearchIndex;
 use crate::vault::manager::VaultState;
-use crate::vault::Vault;
+use crate::vault::{Vault, VaultResult};
 
 pub use config::{AppConfig, PendingAction};
+pub use task_handler::Task;
+
+/// How often the audit retention settings are applied while unlocked
+const AUDIT_PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// What the unlock prompt was given
+pub enum UnlockMethod {
+    Password(SecretString),
+    /// The master password and the recovery code written down at setup
+    RecoveryCode(SecretString, SecretString),
+    /// An empty password with a FIDO2 key enrolled: tap it instead
+    SecurityKey,
+}
 
 #[derive(Default)]
 pub struct ClickState {
@@ -42,62 +87,151 @@ pub struct App {
     pub config: AppConfig,
     pub vault: Vault,
     pub mode_state: ModeState,
+    /// Commands entered in command mode, loaded from the vault on unlock
+    pub command_history: CommandHistory,
     pub view: View,
+    pub detail_layout: DetailLayout,
     pub list_area: Option<Rect>,
     pub click_state: ClickState,
     pub terminal_size: Rect,
     pub list_state: ListViewState,
     pub credentials: Vec<Credential>,
-    pub credential_items: Vec<CredentialItem>,
     pub selected_credential: Option<DecryptedCredential>,
+    /// Recently viewed credentials kept decrypted, emptied on lock
+    pub decryption_cache: DecryptionCache,
+    /// Every credential's metadata, lowercased for live search
+    pub search_index: SearchIndex,
     pub selected_detail: Option<CredentialDetail>,
+    pub detail_scroll: ScrollState,
     pub search_query: Option<String>,
+    pub deep_search: Option<search_handler::DeepSearch>,
+    /// Search in effect before `/`, restored if the new one is cancelled
+    pub search_before: Option<Option<String>>,
+    /// Included tags, and `-tag` for excluded ones
     pub filter_tags: Option<Vec<String>>,
+    pub tag_match: TagMatch,
+    pub filter_types: Option<Vec<CredentialType>>,
+    /// Only show credentials that have expired or expire soon
+    pub filter_expiring: bool,
+    /// Only show credentials whose secret is due for rotation
+    pub filter_rotation: bool,
     pub message: Option<(String, MessageType, Instant)>,
     pub pending_action: Option<PendingAction>,
     pub password_visible: bool,
     pub password_hide_at: Option<Instant>,
+    /// Username copied by `Y`, password still to follow
+    pub copy_sequence: Option<copy_handler::CopySequence>,
+    /// Operation waiting for the master password to be entered again
+    pub reauth_request: Option<reauth_handler::Guarded>,
+    /// Set while a confirmed operation runs, so it isn't asked for twice
+    reauthenticated: bool,
+    /// How long nothing was pressed before the latest key
+    idle_before_input: Duration,
+    /// When the terminal lost focus, while it stays out of focus
+    focus_lost_at: Option<Instant>,
+    /// The last focus change while a task had the vault, applied once it is back
+    deferred_focus: Option<bool>,
+    /// Set while the status line counts down to the idle lock
+    lock_warning: bool,
+    suspend_watch: suspend::SuspendWatch,
     pub last_totp_tick: Instant,
+    pub last_backup_check: Instant,
+    pub last_auto_export_check: Instant,
+    pub last_audit_prune: Instant,
+    /// Backup swapped in by `:restore`, logged once the restored vault is unlocked
+    pub restored_from: Option<PathBuf>,
     pub should_quit: bool,
+    /// Printed once the terminal is restored, for what the TUI can't show on the way out
+    pub exit_message: Option<String>,
     pub credential_form: Option<CredentialForm>,
     pub wants_password_change: bool,
+    /// Export, import or key rotation running with the vault on a worker thread
+    task: Option<Task<task_handler::Finish>>,
     pub help_state: HelpState,
     pub logs_state: LogsState,
+    pub history_state: HistoryState,
+    pub stats_state: StatsState,
     pub tags_state: TagsState,
+    pub types_state: TypesState,
+    pub duplicates_state: DuplicatesState,
+    pub report_state: ReportState,
+    pub backups_state: BackupsState,
+    pub trash_state: TrashState,
     pub export_dialog: Option<ExportDialog>,
+    pub import_dialog: Option<ImportDialog>,
+    /// The credential on show after `:share`
+    pub share_view: Option<ShareView>,
+    /// What startup hardening managed, for the unlock warning and `:hardening`
+    pub hardening: Hardening,
 }
 
 impl App {
     pub fn new(config: AppConfig) -> Self {
-        let vault_config = crate::vault::VaultConfig::with_path(&config.vault_path);
+        let vault_config = crate::vault::VaultConfig::with_path(&config.vault_path)
+            .with_keyfile(config.keyfile.clone())
+            .with_kdf(config.kdf.clone());
 
         Self {
             vault: Vault::new(vault_config),
             config,
             mode_state: ModeState::new(),
+            command_history: CommandHistory::default(),
             view: View::List,
+            detail_layout: DetailLayout::default(),
             list_area: None,
             click_state: ClickState::default(),
             terminal_size: Rect::default(),
             list_state: ListViewState::new(),
             credentials: Vec::new(),
-            credential_items: Vec::new(),
             selected_credential: None,
+            decryption_cache: DecryptionCache::default(),
+            search_index: SearchIndex::default(),
             selected_detail: None,
+            detail_scroll: ScrollState::default(),
             search_query: None,
+            deep_search: None,
+            search_before: None,
             filter_tags: None,
+            tag_match: TagMatch::default(),
+            filter_types: None,
+            filter_expiring: false,
+            filter_rotation: false,
             message: None,
             pending_action: None,
             password_visible: false,
             password_hide_at: None,
+            copy_sequence: None,
+            reauth_request: None,
+            reauthenticated: false,
+            idle_before_input: Duration::ZERO,
+            focus_lost_at: None,
+            deferred_focus: None,
+            lock_warning: false,
+            suspend_watch: suspend::SuspendWatch::new(),
             last_totp_tick: Instant::now(),
+            last_backup_check: Instant::now(),
+            last_auto_export_check: Instant::now(),
+            last_audit_prune: Instant::now(),
+            restored_from: None,
             should_quit: false,
+            exit_message: None,
             credential_form: None,
             wants_password_change: false,
+            task: None,
             help_state: HelpState::new(),
             logs_state: LogsState::new(),
+            history_state: HistoryState::new(),
+            stats_state: StatsState::new(),
             tags_state: TagsState::new(),
+            types_state: TypesState::new(),
+            duplicates_state: DuplicatesState::new(),
+            report_state: ReportState::new(),
+            backups_state: BackupsState::new(),
+            trash_state: TrashState::new(),
             export_dialog: None,
+            import_dialog: None,
+            share_view: None,
+            hardening: Hardening::default(),
         }
     }
 
@@ -163,11 +297,56 @@ impl App {
         self.refresh_data()
     }
 
-    pub fn unlock(&mut self, password: &str) -> Result<(), Box<dyn std::error::Error>> {
-        self.vault.unlock(password)?;
+    /// Start unlocking on a worker thread; Argon2 or a security key waiting
+    /// for its tap would otherwise freeze the screen
+    pub fn begin_unlock(&mut self, method: UnlockMethod) -> Task<VaultResult<Option<&'static str>>> {
+        Task::spawn(" Unlocking ", self.take_vault(), move |vault, _| match method {
+            UnlockMethod::Password(password) => vault.unlock(password.expose_secret()).map(|_| None),
+            // A YubiKey or TPM vault, without the token or the enrolled machine
+            UnlockMethod::RecoveryCode(password, code) => vault
+                .unlock_with_recovery_code(password.expose_secret(), code.expose_secret())
+                .map(|_| Some("Recovery code")),
+            UnlockMethod::SecurityKey => vault.unlock_with_fido2().map(|_| Some("Security key")),
+        })
+    }
+
+    /// Put the vault back from `begin_unlock` and, if it opened, load it
+    pub fn complete_unlock(
+        &mut self,
+        vault: Vault,
+        output: std::thread::Result<VaultResult<Option<&'static str>>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.vault = vault;
+        let details = output.map_err(|_| "Unlock stopped unexpectedly")??;
+        self.finish_unlock(details)
+    }
+
+    /// Unlock with the session key kept in the kernel keyring
+    pub fn unlock_with_session(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.vault.unlock_with_session()?;
+        self.finish_unlock(Some("Keyring session"))
+    }
+
+    /// Unlock with a master key obtained from a running agent
+    pub fn unlock_with_key(&mut self, master_key: MasterKey) -> Result<(), Box<dyn std::error::Error>> {
+        self.vault.unlock_with_key(master_key)?;
+        self.finish_unlock(Some("Agent"))
+    }
+
+    fn finish_unlock(&mut self, details: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+        // First, so the warnings below take the message line over it; hardening
+        // stays the same each start, so anything else outranks it
+        self.warn_hardening();
+        self.warn_reminders()?;
+        self.update_session();
+        self.suspend_watch.reset();
         self.handle_failed_attempts()?;
         self.check_audit_integrity();
-        self.log_audit(AuditAction::Unlock, None, None, None, None)?;
+        self.log_audit(AuditAction::Unlock, None, None, None, details)?;
+        self.log_restore()?;
+        self.prune_audit_log();
+        self.load_command_history();
+        self.auto_backup("unlock");
         self.refresh_data()?;
         self.update_selected_detail()
     }
@@ -193,15 +372,28 @@ impl App {
     }
 
     pub fn lock(&mut self) {
+        // Every lock without an interval, otherwise only when one is due
+        self.auto_export("lock");
         let _ = self.log_audit(AuditAction::Lock, None, None, None, None);
         self.vault.lock();
         self.clear_credentials();
+        self.command_history = CommandHistory::default();
+        self.copy_sequence = None;
+        self.share_view = None;
+        self.reauth_request = None;
+        self.focus_lost_at = None;
+        self.cancel_lock_warning();
+        clipboard::clear_now();
     }
 
     pub fn clear_filters(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         let had_filters = self.has_active_filters();
         self.search_query = None;
+        self.deep_search = None;
         self.filter_tags = None;
+        self.filter_types = None;
+        self.filter_expiring = false;
+        self.filter_rotation = false;
         if had_filters {
             self.refresh_data()?;
             self.update_selected_detail()?;
@@ -210,7 +402,7 @@ impl App {
     }
 
     pub fn has_active_filters(&self) -> bool {
-        self.search_query.is_some() || self.filter_tags.is_some()
+        self.search_query.is_some() || self.filter_tags.is_some() || self.filter_types.is_some() || self.filter_expiring || self.filter_rotation
     }
 
     pub fn log_audit(
@@ -228,6 +420,37 @@ impl App {
         Ok(())
     }
 
+    fn audit_retention(&self) -> audit::Retention {
+        audit::Retention {
+            max_entries: self.config.audit_max_entries,
+            max_age: self.config.audit_max_days.map(|days| chrono::Duration::days(days as i64)),
+        }
+    }
+
+    /// Apply the audit retention settings, reporting failures in the status line
+    pub fn prune_audit_log(&mut self) {
+        let retention = self.audit_retention();
+        if retention.is_unbounded() {
+            return;
+        }
+        let pruned = (|| -> Result<usize, Box<dyn std::error::Error>> {
+            let audit_key = self.vault.keys()?.derive_audit_key()?;
+            Ok(audit::prune(self.vault.db()?.conn(), &audit_key, retention)?)
+        })();
+        if let Err(e) = pruned {
+            self.set_message(&format!("Pruning the audit log failed: {}", e), MessageType::Error);
+        }
+    }
+
+    /// Hourly audit pruning while the vault is unlocked
+    pub fn tick_audit_prune(&mut self) {
+        if !self.vault.is_unlocked() || self.last_audit_prune.elapsed() < AUDIT_PRUNE_INTERVAL {
+            return;
+        }
+        self.last_audit_prune = Instant::now();
+        self.prune_audit_log();
+    }
+
     fn verify_audit_logs(&self) -> Result<(usize, usize), Box<dyn std::error::Error>> {
         let keys = self.vault.keys()?;
         let audit_key = keys.derive_audit_key()?;
@@ -254,6 +477,15 @@ impl App {
         Ok(())
     }
 
+    /// Types are counted within the tag filter, so the two narrow together
+    pub fn load_types(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let db = self.vault.db()?;
+        let tags = self.filter_tags.as_deref().unwrap_or_default();
+        let credentials = crate::vault::search::filter_credentials(db.conn(), tags, self.tag_match, &[])?;
+        self.types_state.set_types_from_credentials(&credentials, self.filter_types.as_deref());
+        Ok(())
+    }
+
     pub fn render(&mut self, frame: &mut Frame) {
         self.terminal_size = frame.area();
         self.check_message_expiry();
@@ -264,22 +496,43 @@ impl App {
 
         let mut state = UiState {
             view: self.view,
+            detail_layout: self.detail_layout,
             mode: self.mode_state.mode,
-            credentials: &self.credential_items,
+            credentials: &self.credentials,
             list_state: &mut self.list_state,
             list_area: &mut self.list_area,
+            detail_scroll: &mut self.detail_scroll,
             selected_detail: self.selected_detail.as_ref(),
             search_query: self.search_query.as_deref(),
+            search_progress: self.deep_search.as_ref().and_then(|d| d.progress()),
+            search_mode: self.config.search_mode,
             filter_tags: self.filter_tags.as_deref(),
+            tag_match: self.tag_match,
+            filter_types: self.filter_types.as_deref(),
+            filter_expiring: self.filter_expiring,
+            filter_rotation: self.filter_rotation,
             command_buffer,
+            pending_key: self.mode_state.pending,
             message,
-            confirm_message,
+            confirm_message: confirm_message.as_deref(),
             password_prompt: None,
             credential_form: self.credential_form.as_ref(),
             help_state: &self.help_state,
             logs_state: &self.logs_state,
+            history_state: &self.history_state,
+            stats_state: &self.stats_state,
             tags_state: &self.tags_state,
+            types_state: &self.types_state,
+            duplicates_state: &self.duplicates_state,
+            report_state: &self.report_state,
+            backups_state: &self.backups_state,
+            trash_state: &self.trash_state,
             export_dialog: self.export_dialog.as_ref(),
+            import_dialog: self.import_dialog.as_ref(),
+            share_view: self.share_view.as_ref(),
+            theme: self.config.theme(),
+            tag_colors: &self.config.tag_colors,
+            progress: self.task.as_ref().map(Task::popup),
         };
 
         Renderer::render(frame, &mut state);
@@ -311,7 +564,70 @@ impl App {
     }
 
     pub fn should_auto_lock(&self) -> bool {
-        self.vault.is_unlocked() && self.vault.time_since_activity() > self.config.auto_lock_timeout
+        if !self.vault.is_unlocked() {
+            return false;
+        }
+        let idle = !self.config.auto_lock_timeout.is_zero() && self.vault.time_since_activity() > self.config.auto_lock_timeout;
+        let unfocused = self
+            .focus_lost_at
+            .zip(self.config.focus_lock_after)
+            .is_some_and(|(lost_at, after)| lost_at.elapsed() >= after);
+        idle || unfocused
+    }
+
+    /// Count down in the status line for the last `lockwarn` seconds before the idle lock
+    pub fn tick_lock_warning(&mut self) {
+        let Some(window) = self.config.lock_warning else { return };
+        if !self.vault.is_unlocked() || self.config.auto_lock_timeout.is_zero() {
+            return;
+        }
+        let remaining = self.config.auto_lock_timeout.saturating_sub(self.vault.time_since_activity());
+        if remaining > window {
+            return;
+        }
+        self.lock_warning = true;
+        let secs = remaining.as_millis().div_ceil(1000);
+        self.set_message(&format!("Locking in {}s, press any key", secs), MessageType::Warning);
+    }
+
+    /// Dismiss the idle lock countdown; true if it was showing, so the key
+    /// that dismissed it does nothing else
+    pub fn cancel_lock_warning(&mut self) -> bool {
+        if !std::mem::take(&mut self.lock_warning) {
+            return false;
+        }
+        self.message = None;
+        true
+    }
+
+    /// Lock as the machine suspends, or on waking if that was missed
+    pub fn tick_suspend(&mut self) {
+        let slept = self.suspend_watch.poll();
+        if slept && self.config.lock_on_sleep && self.vault.is_unlocked() {
+            self.lock();
+        }
+    }
+
+    /// Start the focus-lock countdown when the terminal loses focus
+    pub fn focus_lost(&mut self) {
+        let Some(after) = self.config.focus_lock_after else { return };
+        if !self.vault.is_unlocked() {
+            return;
+        }
+        self.focus_lost_at = Some(Instant::now());
+        self.set_message(&format!("Terminal lost focus; locking in {}s", after.as_secs()), MessageType::Warning);
+    }
+
+    /// Hold a focus change until the vault is back from a task, as only an
+    /// unlocked vault starts the focus lock
+    pub fn defer_focus(&mut self, focused: bool) {
+        self.deferred_focus = Some(focused);
+    }
+
+    pub fn focus_gained(&mut self) {
+        if self.focus_lost_at.take().is_some() {
+            self.message = None;
+        }
     }
 
     pub fn tick_totp(&mut self) {
@@ -333,9 +649,10 @@ impl App {
         
         // Only update TOTP fields in the existing detail
         if let Some(ref mut detail) = self.selected_detail {
-            let (code, remaining) = credentials_handler::compute_totp(cred);
+            let (code, remaining, next) = credentials_handler::compute_totp(cred);
             detail.totp_code = code;
             detail.totp_remaining = remaining;
+            detail.totp_next = next;
         }
     }
 }
@@ -0,0 +1,105 @@
+//! Asking for the master password again before secrets are shown, copied or
+//! exported, and before the keyfile, YubiKey, TPM or security key changes
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::crypto::KdfParams;
+use crate::input::keymap::Action;
+
+use super::App;
+
+/// An operation held back until the master password is entered again
+pub enum Guarded {
+    /// Revealing the selected password
+    Reveal,
+    /// Revealing the password in the credential form
+    FormReveal,
+    /// A copy of a secret, run again once confirmed
+    Copy(Action),
+    Export,
+    /// Sharing the selected credential, optionally saved to a file
+    Share(Option<String>),
+    /// Adding, moving or removing the keyfile, which needs the password itself
+    Keyfile(Option<PathBuf>),
+    /// Requiring the YubiKey in a slot, or no longer
+    Token(Option<u8>),
+    /// Binding the vault to this machine's TPM, or releasing it
+    Tpm(bool),
+    /// Encrypting the database file under the password, or decrypting it
+    EncryptDb(bool),
+    /// Encrypting names, usernames, URLs and tags, or storing them in plaintext
+    EncryptMetadata(bool),
+    /// Showing the YubiKey or TPM recovery code again
+    RecoveryCode,
+    /// Setting up a FIDO2 security key to unlock with, or removing it
+    Fido2(bool),
+    /// Setting or removing the duress password
+    Duress(Option<String>),
+    /// Rehashing the password with new Argon2 parameters
+    Kdf(KdfParams),
+    /// Re-encrypting everything under a fresh DEK
+    RotateKey,
+}
+
+impl App {
+    /// Whether `guarded` may go ahead now; otherwise it waits for the
+    /// password prompt, which main runs next
+    pub(super) fn reauthorized(&mut self, guarded: Guarded) -> bool {
+        let Some(idle_limit) = self.config.reauth_after else { return true };
+        if std::mem::take(&mut self.reauthenticated) {
+            return true;
+        }
+        let needed = match guarded {
+            Guarded::Copy(_) => self.idle_before_input >= idle_limit,
+            Guarded::Reveal | Guarded::FormReveal | Guarded::Export | Guarded::Share(_) | Guarded::Keyfile(_) | Guarded::Token(_) | Guarded::Tpm(_) | Guarded::EncryptDb(_) | Guarded::EncryptMetadata(_) | Guarded::RecoveryCode | Guarded::Fido2(_) | Guarded::Duress(_) | Guarded::Kdf(_) | Guarded::RotateKey => true,
+        };
+        if needed {
+            self.reauth_request = Some(guarded);
+        }
+        !needed
+    }
+
+    /// Mark a keypress, remembering how long the app sat idle before it
+    pub fn record_input(&mut self) {
+        self.idle_before_input = self.vault.time_since_activity();
+        self.vault.update_activity();
+    }
+
+    /// Run `guarded` now `password` checked out
+    pub fn resume_after_reauth(&mut self, guarded: Guarded, password: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.reauthenticated = true;
+        self.idle_before_input = Duration::ZERO;
+        let result = match guarded {
+            Guarded::Reveal => self.toggle_password(),
+            Guarded::FormReveal => {
+                self.toggle_form_password();
+                Ok(())
+            }
+            Guarded::Copy(action) => self.execute_action(action).map(|_| ()),
+            Guarded::Export => self.execute_export(),
+            Guarded::Share(file) => {
+                self.share(file);
+                Ok(())
+            }
+            Guarded::Keyfile(keyfile) => self.change_keyfile(password, keyfile),
+            Guarded::Token(slot) => self.change_token(password, slot),
+            Guarded::Tpm(enable) => self.change_tpm(password, enable),
+            Guarded::EncryptDb(encrypt) => self.change_db_encryption(password, encrypt),
+            Guarded::EncryptMetadata(encrypt) => self.change_metadata_encryption(password, encrypt),
+            Guarded::RecoveryCode => {
+                self.show_recovery_code();
+                Ok(())
+            }
+            Guarded::Fido2(enroll) => self.change_fido2(password, enroll),
+            Guarded::Duress(duress) => self.change_duress_password(duress),
+            Guarded::Kdf(kdf) => self.change_kdf(password, kdf),
+            Guarded::RotateKey => {
+                self.start_key_rotation();
+                Ok(())
+            }
+        };
+        self.reauthenticated = false;
+        result
+    }
+}
@@ -0,0 +1,109 @@
+//! `:report` for the security reports: password age, reuse and strength,
+//! and `:check`, which lists damaged rows the same way
+
+use crate::ui::MessageType;
+use crate::vault::integrity::{self, IntegrityCheck};
+use crate::vault::report::{self, ReportKind};
+
+use super::App;
+
+impl App {
+    /// `:report <name>` opens a report, `:report` alone the password age one
+    pub fn show_report(&mut self, name: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.vault.is_unlocked() {
+            self.set_message("Vault must be unlocked", MessageType::Error);
+            return Ok(());
+        }
+        let kind = match name {
+            None => ReportKind::Age,
+            Some(name) => match ReportKind::from_name(name) {
+                Some(kind) => kind,
+                None => {
+                    let names: Vec<&str> = ReportKind::ALL.iter().map(|k| k.name()).collect();
+                    self.set_message(&format!("Unknown report: {} (use {})", name, names.join(", ")), MessageType::Error);
+                    return Ok(());
+                }
+            },
+        };
+        self.report_state.home();
+        self.load_report(kind)?;
+        self.mode_state.enter_report_mode();
+        Ok(())
+    }
+
+    fn load_report(&mut self, kind: ReportKind) -> Result<(), Box<dyn std::error::Error>> {
+        let conn = self.vault.db()?.conn();
+        let (report, empty_message) = match kind {
+            ReportKind::Age => {
+                let days = self.config.max_secret_age_days;
+                (report::password_age(conn, days)?, format!("No secrets older than {} days", days))
+            }
+            ReportKind::Reuse => (report::password_reuse(conn, self.vault.dek()?)?, "No reused passwords".to_string()),
+            ReportKind::Weak => {
+                let min = self.config.min_strength;
+                let report = report::weak_passwords(conn, self.vault.dek()?, min)?;
+                (report, format!("No passwords scoring below {}", min))
+            }
+            ReportKind::Integrity => (self.integrity_check()?.into_report(), "No damaged rows".to_string()),
+        };
+        self.report_state.set_report(report, empty_message);
+        Ok(())
+    }
+
+    /// `:check` reports damaged rows, or just a summary if there are none
+    pub fn run_integrity_check(&mut self) {
+        if !self.vault.is_unlocked() {
+            self.set_message("Vault must be unlocked", MessageType::Error);
+            return;
+        }
+        match self.integrity_check() {
+            Ok(check) if check.is_ok() => {
+                self.set_message(&format!("Integrity check passed: {}", check.summary()), MessageType::Success)
+            }
+            Ok(check) => {
+                let summary = check.summary();
+                self.report_state.home();
+                self.report_state.set_report(check.into_report(), "No damaged rows".to_string());
+                self.mode_state.enter_report_mode();
+                self.set_message(&summary, MessageType::Warning);
+            }
+            Err(e) => self.set_message(&format!("Integrity check failed: {}", e), MessageType::Error),
+        }
+    }
+
+    fn integrity_check(&self) -> Result<IntegrityCheck, Box<dyn std::error::Error>> {
+        let audit_key = self.vault.keys()?.derive_audit_key()?;
+        Ok(integrity::check(self.vault.db()?.conn(), self.vault.dek()?, &audit_key)?)
+    }
+
+    /// Show the credential under the report's cursor
+    pub fn open_report_entry(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.select_report_entry()? {
+            self.mode_state.enter_normal_mode();
+            self.select_credential()?;
+        }
+        Ok(())
+    }
+
+    /// Edit the credential under the report's cursor; saving returns to its detail view
+    pub fn edit_report_entry(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.select_report_entry()? {
+            self.mode_state.enter_normal_mode();
+            self.select_credential()?;
+            self.edit_credential()?;
+        }
+        Ok(())
+    }
+
+    /// Select the report's entry in the list, clearing filters that hide it
+    fn select_report_entry(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
+        let Some(id) = self.report_state.selected_entry().map(|e| e.id.clone()) else { return Ok(false) };
+        if !self.credentials.iter().any(|c| c.id == id) {
+            self.clear_filters()?;
+        }
+        let Some(index) = self.credentials.iter().position(|c| c.id == id) else { return Ok(false) };
+        self.list_state.select(Some(index));
+        self.update_selected_detail()?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,157 @@
+//! Search: live filtering as the query is typed, from the in-memory search
+//! index the app keeps current as it changes credentials, and deep search:
+//! with `deepsearch` on, a search also decrypts notes and checks URLs, a
+//! batch per tick so large vaults stay responsive
+
+use std::collections::HashSet;
+
+use crate::db::Credential;
+use crate::ui::MessageType;
+use crate::vault::credential::notes_contain;
+use crate::vault::search;
+
+use super::App;
+
+/// Credentials searched per tick
+const BATCH_SIZE: usize = 200;
+
+pub struct DeepSearch {
+    /// The search it belongs to, as typed
+    query: String,
+    needle: String,
+    /// Not yet searched, last in line first
+    pending: Vec<Credential>,
+    total: usize,
+    matched: HashSet<String>,
+}
+
+impl DeepSearch {
+    /// Searched and total credentials while the search is running
+    pub fn progress(&self) -> Option<(usize, usize)> {
+        (!self.pending.is_empty()).then(|| (self.total - self.pending.len(), self.total))
+    }
+
+    /// Matches by note or URL for `query`, if this search belongs to it
+    pub fn matches_for(&self, query: Option<&str>) -> Option<&HashSet<String>> {
+        (query == Some(self.query.as_str())).then_some(&self.matched)
+    }
+}
+
+impl App {
+    /// `/` remembers the current search so Esc can bring it back
+    pub fn begin_search(&mut self) {
+        self.search_before = Some(self.search_query.clone());
+        self.mode_state.enter_search_mode();
+    }
+
+    /// Filter by the query as it is typed; deep search waits for Enter
+    pub fn live_search(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let query = self.mode_state.get_buffer().to_string();
+        self.search_query = (!query.is_empty()).then_some(query);
+        self.deep_search = None;
+        self.refresh_data()?;
+        if self.list_state.total > 0 {
+            self.list_state.select(Some(0));
+        }
+        self.update_selected_detail()
+    }
+
+    pub fn cancel_search(&mut self) {
+        self.mode_state.enter_normal_mode();
+        let Some(previous) = self.search_before.take() else { return };
+        if previous != self.search_query {
+            self.search_query = previous;
+            let _ = self.refresh_data().and_then(|_| self.update_selected_detail());
+        }
+    }
+
+    pub fn search_credentials(&mut self, query: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.search_query = if query.is_empty() { None } else { Some(query.to_string()) };
+        self.search_before = None;
+        self.deep_search = None;
+        self.refresh_data()?;
+        self.start_deep_search()?;
+        self.update_selected_detail()
+    }
+
+    /// Queue every credential the plain search left out for a look at its notes
+    fn start_deep_search(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(query) = self.search_query.clone().filter(|_| self.config.deep_search) else {
+            return Ok(());
+        };
+        let needle = search::parse_query(&query, self.config.search_mode).1.to_lowercase();
+        if needle.is_empty() {
+            return Ok(());
+        }
+
+        let listed: HashSet<&str> = self.credentials.iter().map(|c| c.id.as_str()).collect();
+        let mut pending: Vec<Credential> = self
+            .search_index
+            .entries()
+            .map(|entry| entry.credential())
+            .filter(|c| !listed.contains(c.id.as_str()))
+            .cloned()
+            .collect();
+        pending.reverse();
+
+        self.deep_search = Some(DeepSearch {
+            query,
+            needle,
+            total: pending.len(),
+            pending,
+            matched: HashSet::new(),
+        });
+        // Small vaults finish here, before the first frame
+        self.tick_deep_search();
+        Ok(())
+    }
+
+    pub fn tick_deep_search(&mut self) {
+        let Some(deep) = self.deep_search.as_mut() else { return };
+        if deep.pending.is_empty() {
+            return;
+        }
+        let Ok(dek) = self.vault.dek() else {
+            self.deep_search = None;
+            return;
+        };
+
+        let batch = deep.pending.split_off(deep.pending.len().saturating_sub(BATCH_SIZE));
+        let mut found = false;
+        for cred in batch.iter().rev() {
+            let in_url = cred.url.as_ref().is_some_and(|u| u.to_lowercase().contains(&deep.needle));
+            if in_url || notes_contain(dek, cred, &deep.needle).unwrap_or(false) {
+                deep.matched.insert(cred.id.clone());
+                found = true;
+            }
+        }
+        let finished = deep.pending.is_empty().then_some(deep.matched.len());
+
+        if found {
+            let _ = self.refresh_keeping_selection();
+        }
+        if let Some(matched) = finished {
+            self.set_message(&format!("Deep search: {} more match(es) in notes and URLs", matched), MessageType::Info);
+        }
+    }
+
+    /// Bring the search index up to date with credentials the app just
+    /// created, changed or deleted, sparing the next search a full reload
+    pub(super) fn reindex<'a>(&mut self, ids: impl IntoIterator<Item = &'a str>) -> Result<(), Box<dyn std::error::Error>> {
+        let conn = self.vault.db()?.conn();
+        self.search_index.refresh(conn, ids)?;
+        Ok(())
+    }
+
+    /// Refresh the list without moving the cursor off the selected credential
+    pub(super) fn refresh_keeping_selection(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let selected_id = self.list_state.selected()
+            .and_then(|i| self.credentials.get(i))
+            .map(|c| c.id.clone());
+        self.refresh_data()?;
+        if let Some(index) = selected_id.and_then(|id| self.credentials.iter().position(|c| c.id == id)) {
+            self.list_state.select(Some(index));
+        }
+        self.update_selected_detail()
+    }
+}
@@ -0,0 +1,509 @@
+//! `:set` for changing settings at runtime
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::crypto::{self, KdfParams};
+use crate::db::{self, AuditAction};
+use crate::ui::MessageType;
+
+use super::config::{expand_home, AppConfig, PendingAction, SETTINGS};
+use super::reauth_handler::Guarded;
+use super::App;
+
+/// What `:kdf calibrate` aims for without a target
+const DEFAULT_UNLOCK_DELAY: Duration = Duration::from_millis(500);
+
+/// Calibration stops doubling memory here (KiB), 1 GiB
+const MAX_CALIBRATED_MEMORY: u32 = 1024 * 1024;
+
+impl App {
+    /// `:set` lists settings, `:set key` shows one, `:set key=value` changes it
+    /// and `:set! key=value` also saves it to the config file
+    pub fn set_setting(&mut self, setting: Option<&str>, persist: bool) {
+        let Some(setting) = setting else {
+            self.show_settings();
+            return;
+        };
+
+        let Some((key, value)) = setting.split_once('=') else {
+            let key = setting.trim_end_matches('?');
+            match self.config.get(key) {
+                Some(value) => self.set_message(&format!("{}={}", key, value), MessageType::Info),
+                None => self.set_message(&format!("Unknown setting: {}", key), MessageType::Error),
+            }
+            return;
+        };
+
+        let key = key.trim();
+        if let Err(e) = self.config.set(key, value) {
+            self.set_message(&e, MessageType::Error);
+            return;
+        }
+        if key == "keyfile" {
+            self.vault.set_keyfile_path(self.config.keyfile.clone());
+        }
+        if key == "keyring" {
+            self.update_session();
+        }
+        if key.starts_with("kdf") {
+            self.vault.set_kdf_params(self.config.kdf.clone());
+        }
+        let current = format!("{}={}", key, self.config.get(key).unwrap_or_default());
+        if !persist {
+            self.set_message(&current, MessageType::Success);
+            return;
+        }
+
+        let saved = AppConfig::config_path()
+            .ok_or_else(|| "No config directory".to_string())
+            .and_then(|path| self.config.persist(key, &path));
+        match saved {
+            Ok(()) => self.set_message(&format!("{} (saved)", current), MessageType::Success),
+            Err(e) => self.set_message(&format!("{} (not saved: {})", current, e), MessageType::Error),
+        }
+    }
+
+    /// `:keyfile` tells whether unlocking needs a keyfile, `:keyfile PATH`
+    /// starts requiring that file and `:keyfile off` stops; changes ask for
+    /// the master password
+    pub fn keyfile_command(&mut self, arg: Option<&str>) {
+        let Some(arg) = arg.map(str::trim) else {
+            let message = match (self.vault.has_keyfile(), &self.config.keyfile) {
+                (Ok(true), Some(path)) => format!("Unlocking needs the keyfile {}", path.display()),
+                (Ok(true), None) => "Unlocking needs a keyfile".to_string(),
+                _ => "No keyfile; :keyfile PATH adds one".to_string(),
+            };
+            self.set_message(&message, MessageType::Info);
+            return;
+        };
+
+        let keyfile = match arg {
+            "off" | "none" => None,
+            path => Some(expand_home(path)),
+        };
+        if let Some(path) = &keyfile
+            && !path.is_file()
+        {
+            self.set_message(&format!("No file at {}", path.display()), MessageType::Error);
+            return;
+        }
+        self.reauth_request = Some(Guarded::Keyfile(keyfile));
+    }
+
+    /// Rewrap the vault key for `keyfile` and remember where it lives
+    pub(super) fn change_keyfile(&mut self, password: &str, keyfile: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
+        let had_fido2 = self.vault.has_fido2();
+        self.vault.change_keyfile(password, keyfile.clone())?;
+        let mut detail = match &keyfile {
+            Some(path) => format!("Keyfile set to {}", path.display()),
+            None => "Keyfile removed".to_string(),
+        };
+        if had_fido2 {
+            detail.push_str("; :fido2 on sets up the security key again");
+        }
+        self.config.keyfile = keyfile;
+        self.log_audit(AuditAction::Update, None, None, None, Some(&detail))?;
+
+        // Without the path saved, the next unlock would not find the keyfile
+        let saved = AppConfig::config_path()
+            .ok_or_else(|| "No config directory".to_string())
+            .and_then(|path| self.config.persist("keyfile", &path));
+        match saved {
+            Ok(()) => self.set_message(&detail, MessageType::Success),
+            Err(e) => self.set_message(&format!("{}; save keyfile in the config yourself ({})", detail, e), MessageType::Error),
+        }
+        Ok(())
+    }
+
+    /// `:yubikey` tells whether unlocking needs a YubiKey, `:yubikey 1|2`
+    /// starts requiring the token's challenge-response slot, `:yubikey off`
+    /// stops and `:yubikey recovery` shows the recovery code again; all ask
+    /// for the master password
+    pub fn yubikey_command(&mut self, arg: Option<&str>) {
+        let guarded = match arg.map(str::trim) {
+            None => {
+                let message = match self.vault.token_slot() {
+                    Ok(Some(slot)) => format!("Unlocking needs the YubiKey in slot {}", slot),
+                    _ => "No YubiKey; :yubikey SLOT adds one".to_string(),
+                };
+                self.set_message(&message, MessageType::Info);
+                return;
+            }
+            Some("off" | "none") => Guarded::Token(None),
+            Some("recovery") => Guarded::RecoveryCode,
+            Some(slot @ ("1" | "2")) => Guarded::Token(slot.parse().ok()),
+            Some(other) => {
+                self.set_message(&format!("Unknown YubiKey slot: {} (use 1, 2, off or recovery)", other), MessageType::Error);
+                return;
+            }
+        };
+        if matches!(guarded, Guarded::RecoveryCode) && !matches!(self.vault.token_slot(), Ok(Some(_))) {
+            self.set_message("No YubiKey; nothing to recover", MessageType::Error);
+            return;
+        }
+        self.reauth_request = Some(guarded);
+    }
+
+    /// Rewrap the vault key for the YubiKey in `slot`, or without one
+    pub(super) fn change_token(&mut self, password: &str, slot: Option<u8>) -> Result<(), Box<dyn std::error::Error>> {
+        self.vault.change_token(password, slot)?;
+        let detail = match slot {
+            Some(slot) => format!("YubiKey slot {} required", slot),
+            None => "YubiKey removed".to_string(),
+        };
+        self.log_audit(AuditAction::Update, None, None, None, Some(&detail))?;
+        match slot {
+            Some(_) => self.show_recovery_code(),
+            None => self.set_message(&detail, MessageType::Success),
+        }
+        Ok(())
+    }
+
+    pub(super) fn show_recovery_code(&mut self) {
+        if let Some(code) = self.vault.recovery_code() {
+            let hardware = match self.vault.has_tpm() {
+                Ok(true) => "on another machine or after a firmware change",
+                _ => "without the YubiKey",
+            };
+            let message = format!("Recovery code (write it down, it opens the vault {}): {}", hardware, code.as_str());
+            self.set_message(&message, MessageType::Warning);
+        }
+    }
+
+    /// `:tpm` tells whether the vault is bound to this machine's TPM, `:tpm on`
+    /// seals a fresh secret to it, `:tpm off` releases the vault and
+    /// `:tpm recovery` shows the recovery code again; all ask for the master
+    /// password
+    pub fn tpm_command(&mut self, arg: Option<&str>) {
+        let has_tpm = self.vault.has_tpm().unwrap_or(false);
+        let guarded = match arg.map(str::trim) {
+            None => {
+                let message = match has_tpm {
+                    true => "Bound to this machine's TPM; other machines need the recovery code",
+                    false => "Not bound to a TPM; :tpm on binds the vault to this machine",
+                };
+                self.set_message(message, MessageType::Info);
+                return;
+            }
+            Some("on") => Guarded::Tpm(true),
+            Some("off" | "none") => Guarded::Tpm(false),
+            Some("recovery") => Guarded::RecoveryCode,
+            Some(other) => {
+                self.set_message(&format!("Unknown argument: {} (use on, off or recovery)", other), MessageType::Error);
+                return;
+            }
+        };
+        if matches!(guarded, Guarded::RecoveryCode) && !has_tpm {
+            self.set_message("No TPM binding; nothing to recover", MessageType::Error);
+            return;
+        }
+        self.reauth_request = Some(guarded);
+    }
+
+    /// Seal the vault key to this machine's TPM, or release it
+    pub(super) fn change_tpm(&mut self, password: &str, enable: bool) -> Result<(), Box<dyn std::error::Error>> {
+        self.vault.change_tpm(password, enable)?;
+        let detail = match enable {
+            true => "Bound to the TPM",
+            false => "TPM binding removed",
+        };
+        self.log_audit(AuditAction::Update, None, None, None, Some(detail))?;
+        match enable {
+            true => self.show_recovery_code(),
+            false => self.set_message(detail, MessageType::Success),
+        }
+        Ok(())
+    }
+
+    /// `:encrypt-db` tells whether the database file is encrypted as a whole,
+    /// `:encrypt-db on` encrypts it under the master password and
+    /// `:encrypt-db off` turns it back into plain SQLite; changes ask for
+    /// the master password
+    pub fn encrypt_db_command(&mut self, arg: Option<&str>) {
+        let encrypt = match arg.map(str::trim) {
+            None => {
+                let message = match (self.vault.is_database_encrypted(), db::ENCRYPTION_SUPPORTED) {
+                    (true, _) => "The database file is encrypted; only the master password opens it",
+                    (false, true) => "The database file is plain SQLite with encrypted secrets; :encrypt-db on encrypts all of it",
+                    (false, false) => "The database file is plain SQLite with encrypted secrets; this build cannot encrypt all of it",
+                };
+                self.set_message(message, MessageType::Info);
+                return;
+            }
+            Some("on") => true,
+            Some("off") => false,
+            Some(other) => {
+                self.set_message(&format!("Unknown argument: {} (use on or off)", other), MessageType::Error);
+                return;
+            }
+        };
+        if encrypt == self.vault.is_database_encrypted() {
+            let state = if encrypt { "already encrypted" } else { "not encrypted" };
+            self.set_message(&format!("The database file is {}", state), MessageType::Info);
+            return;
+        }
+        if !db::ENCRYPTION_SUPPORTED {
+            self.set_message("This build cannot encrypt the database; rebuild with --features sqlcipher", MessageType::Error);
+            return;
+        }
+        self.reauth_request = Some(Guarded::EncryptDb(encrypt));
+    }
+
+    /// Rewrite the database file encrypted under `password`, or in plaintext
+    pub(super) fn change_db_encryption(&mut self, password: &str, encrypt: bool) -> Result<(), Box<dyn std::error::Error>> {
+        self.vault.set_database_encryption(password, encrypt)?;
+        // A new connection, whose change counters start over
+        self.search_index.clear();
+        let detail = match encrypt {
+            true => "Database file encrypted",
+            false => "Database file decrypted",
+        };
+        self.log_audit(AuditAction::Update, None, None, None, Some(detail))?;
+        let message = match encrypt {
+            true => "Database file encrypted; security key and keyring unlocks are off while it is",
+            false => "Database file decrypted; names, usernames, URLs and tags are readable in it again",
+        };
+        self.set_message(message, MessageType::Success);
+        Ok(())
+    }
+
+    /// `:encrypt-metadata` tells whether names, usernames, URLs and tags are
+    /// stored encrypted, `:encrypt-metadata on` encrypts them (vault format
+    /// 2) and `:encrypt-metadata off` stores them in plaintext again; changes
+    /// ask for the master password
+    pub fn encrypt_metadata_command(&mut self, arg: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+        let encrypted = self.vault.is_metadata_encrypted()?;
+        let encrypt = match arg.map(str::trim) {
+            None => {
+                let message = match encrypted {
+                    true => "Names, usernames, URLs and tags are encrypted; search uses a keyed index",
+                    false => "Names, usernames, URLs and tags are stored in plaintext; :encrypt-metadata on encrypts them",
+                };
+                self.set_message(message, MessageType::Info);
+                return Ok(());
+            }
+            Some("on") => true,
+            Some("off") => false,
+            Some(other) => {
+                self.set_message(&format!("Unknown argument: {} (use on or off)", other), MessageType::Error);
+                return Ok(());
+            }
+        };
+        if encrypt == encrypted {
+            let state = if encrypt { "already encrypted" } else { "not encrypted" };
+            self.set_message(&format!("Credential metadata is {}", state), MessageType::Info);
+            return Ok(());
+        }
+        self.reauth_request = Some(Guarded::EncryptMetadata(encrypt));
+        Ok(())
+    }
+
+    /// Encrypt the metadata of every record, or store it in plaintext again
+    pub(super) fn change_metadata_encryption(&mut self, password: &str, encrypt: bool) -> Result<(), Box<dyn std::error::Error>> {
+        self.vault.set_metadata_encryption(password, encrypt)?;
+        let detail = match encrypt {
+            true => "Credential metadata encrypted",
+            false => "Credential metadata decrypted",
+        };
+        self.log_audit(AuditAction::Update, None, None, None, Some(detail))?;
+        self.refresh_data()?;
+        self.set_message(detail, MessageType::Success);
+        Ok(())
+    }
+
+    /// `:vacuum` rebuilds the database file, zeroing deleted rows
+    pub fn vacuum_database(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.vault.is_unlocked() {
+            self.set_message("Vault must be unlocked", MessageType::Error);
+            return Ok(());
+        }
+        let reclaimed = self.vault.db()?.vacuum()?;
+        let message = match reclaimed {
+            0 => "Database vacuumed; no space to reclaim".to_string(),
+            n if n < 1024 => format!("Database vacuumed; reclaimed {} bytes", n),
+            n => format!("Database vacuumed; reclaimed {} KiB", n / 1024),
+        };
+        self.set_message(&message, MessageType::Success);
+        Ok(())
+    }
+
+    /// `:fido2` tells whether a security key can unlock the vault, `:fido2 on`
+    /// sets up the one plugged in and `:fido2 off` removes it; changes ask for
+    /// the master password
+    pub fn fido2_command(&mut self, arg: Option<&str>) {
+        let enroll = match arg.map(str::trim) {
+            None => {
+                let message = match self.vault.has_fido2() {
+                    true => "A security key unlocks the vault; Enter on an empty password to tap it",
+                    false => "No security key; :fido2 on sets up the one plugged in",
+                };
+                self.set_message(message, MessageType::Info);
+                return;
+            }
+            Some("on") => true,
+            Some("off" | "none") => false,
+            Some(other) => {
+                self.set_message(&format!("Unknown argument: {} (use on or off)", other), MessageType::Error);
+                return;
+            }
+        };
+        self.reauth_request = Some(Guarded::Fido2(enroll));
+    }
+
+    pub(super) fn change_fido2(&mut self, password: &str, enroll: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let detail = match enroll {
+            true => {
+                self.vault.enroll_fido2(password)?;
+                "Security key set up; tap it at unlock instead of typing the password"
+            }
+            false => {
+                self.vault.verify_password(password)?;
+                self.vault.remove_fido2()?;
+                "Security key removed"
+            }
+        };
+        self.log_audit(AuditAction::Update, None, None, None, Some(detail))?;
+        self.set_message(detail, MessageType::Success);
+        Ok(())
+    }
+
+    /// `:kdf` compares the vault's Argon2 parameters with the configured
+    /// ones and `:kdf apply` rehashes the password with the configured ones,
+    /// asking for it first; `:kdf calibrate [ms]` proposes parameters that
+    /// take that long here
+    pub fn kdf_command(&mut self, arg: Option<&str>) {
+        if let Some(target) = arg.and_then(|a| a.trim().strip_prefix("calibrate")) {
+            self.calibrate_kdf(target.trim());
+            return;
+        }
+        match arg.map(str::trim) {
+            None => {
+                let current = self.vault.kdf_params().map(|p| p.summary()).unwrap_or_else(|e| e.to_string());
+                let message = format!("Vault: {}; configured: {} (:kdf apply switches)", current, self.config.kdf.summary());
+                self.set_message(&message, MessageType::Info);
+            }
+            Some("apply") => self.reauth_request = Some(Guarded::Kdf(self.config.kdf.clone())),
+            Some(other) => self.set_message(&format!("Unknown argument: {} (use apply or calibrate)", other), MessageType::Error),
+        }
+    }
+
+    /// Re-encrypt the vault under a fresh DEK, reporting `progress(done, total)`
+    /// Re-encrypt everything under a fresh DEK on a worker thread
+    pub fn start_key_rotation(&mut self) {
+        self.run_in_background(" Rotating Key ", |vault, progress| {
+            let rotated = vault.rotate_dek(progress).map_err(|e| e.to_string());
+            Box::new(move |app: &mut App| {
+                let rows = match rotated {
+                    Ok(rows) => rows,
+                    Err(e) => {
+                        app.set_message(&format!("Key rotation failed: {}", e), MessageType::Error);
+                        return Ok(());
+                    }
+                };
+                let detail = format!("DEK rotated; {} records re-encrypted", rows);
+                app.log_audit(AuditAction::Update, None, None, None, Some(&detail))?;
+                // The list holds ciphertext from before the rotation
+                app.refresh_data()?;
+                app.set_message(&detail, MessageType::Success);
+                Ok(())
+            })
+        });
+    }
+
+    fn calibrate_kdf(&mut self, target: &str) {
+        let target = match target {
+            "" => DEFAULT_UNLOCK_DELAY,
+            ms => match ms.trim_end_matches("ms").parse::<u64>() {
+                Ok(ms) if (100..=10_000).contains(&ms) => Duration::from_millis(ms),
+                _ => {
+                    self.set_message("Calibrate for 100 to 10000 ms, e.g. :kdf calibrate 500", MessageType::Error);
+                    return;
+                }
+            },
+        };
+        match crypto::calibrate(target, self.config.kdf.parallelism, MAX_CALIBRATED_MEMORY) {
+            Ok((kdf, took)) => {
+                self.pending_action = Some(PendingAction::ApplyKdf(kdf, took));
+                self.mode_state.enter_confirm_mode();
+            }
+            Err(e) => self.set_message(&format!("Calibration failed: {}", e), MessageType::Error),
+        }
+    }
+
+    pub(super) fn change_kdf(&mut self, password: &str, kdf: KdfParams) -> Result<(), Box<dyn std::error::Error>> {
+        let had_fido2 = self.vault.has_fido2();
+        self.vault.change_kdf(password, kdf.clone())?;
+        let mut detail = format!("Password rehashed with {}", kdf.summary());
+        self.log_audit(AuditAction::Update, None, None, None, Some(&detail))?;
+        if had_fido2 {
+            detail.push_str("; :fido2 on sets up the security key again");
+        }
+
+        // Keep later password changes on the same parameters
+        self.config.kdf = kdf;
+        let saved = AppConfig::config_path()
+            .ok_or_else(|| "No config directory".to_string())
+            .and_then(|path| ["kdfmemory", "kdftime", "kdfparallelism"].iter().try_for_each(|key| self.config.persist(key, &path)));
+        match saved {
+            Ok(()) => self.set_message(&detail, MessageType::Success),
+            Err(e) => self.set_message(&format!("{}; could not save the kdf settings ({})", detail, e), MessageType::Error),
+        }
+        Ok(())
+    }
+
+    /// Start or end the keyring session to match the `keyring` setting
+    pub(super) fn update_session(&mut self) {
+        let result = match self.config.keyring_session {
+            true if !self.vault.has_session().unwrap_or(true) => self.vault.remember_session(),
+            true => Ok(()),
+            false => self.vault.forget_session().map(|_| ()),
+        };
+        if let Err(e) = result {
+            self.set_message(&format!("Keyring session: {}", e), MessageType::Error);
+        }
+    }
+
+    /// `:forget-session` drops the keyring session, so the next start asks
+    /// for the password again
+    pub fn forget_session(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.vault.forget_session()? {
+            self.set_message("No keyring session to forget", MessageType::Info);
+            return Ok(());
+        }
+        self.log_audit(AuditAction::Update, None, None, None, Some("Keyring session forgotten"))?;
+        let message = match self.config.keyring_session {
+            true => "Keyring session forgotten; the next unlock starts another while keyring=on",
+            false => "Keyring session forgotten",
+        };
+        self.set_message(message, MessageType::Success);
+        Ok(())
+    }
+
+    /// `:hardening` shows how each startup hardening step went
+    pub fn show_hardening(&mut self) {
+        let kind = match self.hardening.is_complete() {
+            true => MessageType::Info,
+            false => MessageType::Warning,
+        };
+        self.set_message(&format!("Hardening: {}", self.hardening.summary()), kind);
+    }
+
+    /// Warn at unlock when startup hardening only partly worked
+    pub(super) fn warn_hardening(&mut self) {
+        let failed: Vec<&str> = self.hardening.failed().map(|step| step.name).collect();
+        if failed.is_empty() {
+            return;
+        }
+        let message = format!("Warning: hardening incomplete ({} failed); :hardening for details", failed.join(", "));
+        self.set_message(&message, MessageType::Warning);
+    }
+
+    fn show_settings(&mut self) {
+        let settings: Vec<String> = SETTINGS
+            .iter()
+            .map(|(key, _)| format!("{}={}", key, self.config.get(key).unwrap_or_default()))
+            .collect();
+        self.set_message(&settings.join(" "), MessageType::Info);
+    }
+}
@@ -0,0 +1,84 @@
+//! Sharing one credential as an encrypted block or QR code, and receiving one
+
+use crate::db::{self, AuditAction};
+use crate::ui::components::share::ShareView;
+use crate::ui::components::ImportDialog;
+use crate::ui::MessageType;
+use crate::vault::credential::{decrypt_credential_data, decrypt_fields, decrypt_notes};
+use crate::vault::export::credential_to_export;
+use crate::vault::{backup, share};
+
+use super::clipboard;
+use super::config::expand_home;
+use super::reauth_handler::Guarded;
+use super::App;
+
+impl App {
+    /// Encrypt the selected credential under a one-time passphrase and show
+    /// it, saving the armored block to `file` as well if given
+    pub fn share(&mut self, file: Option<String>) {
+        let Some(id) = self.selected_credential.as_ref().map(|c| c.id.clone()) else {
+            self.set_message("No credential selected", MessageType::Error);
+            return;
+        };
+        if !self.reauthorized(Guarded::Share(file.clone())) {
+            return;
+        }
+        if let Err(e) = self.try_share(&id, file) {
+            self.set_message(&format!("Share failed: {}", e), MessageType::Error);
+        }
+    }
+
+    fn try_share(&mut self, id: &str, file: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
+        let cred = db::get_credential(self.vault.db()?.conn(), id)?;
+        let shared = {
+            let dek = self.vault.dek()?;
+            let secret = decrypt_credential_data(dek, &cred.id, &cred.encrypted_secret)?;
+            let notes = decrypt_notes(dek, &cred.id, cred.encrypted_notes.as_ref())?;
+            let fields = decrypt_fields(dek, &cred.id, cred.encrypted_fields.as_ref())?;
+            share::share_credential(&credential_to_export(&cred, secret, notes, fields))?
+        };
+
+        if let Some(file) = &file {
+            let path = expand_home(file);
+            std::fs::write(&path, &shared.armored)?;
+            backup::restrict_permissions(&path);
+        }
+
+        let detail = match &file {
+            Some(file) => format!("Shared as an encrypted block, saved to {}", file),
+            None => "Shared as an encrypted block".to_string(),
+        };
+        self.log_audit(AuditAction::Export, Some(&cred.id), Some(&cred.name), cred.username.as_deref(), Some(&detail))?;
+
+        self.share_view = Some(ShareView::new(cred.name, shared, file));
+        self.mode_state.enter_share_mode();
+        Ok(())
+    }
+
+    pub fn copy_share_block(&mut self) {
+        let Some(view) = &self.share_view else { return };
+        clipboard::copy_with_timeout(&view.armored, self.config.clipboard_timeout, self.config.clipboard);
+        self.set_message("Share block copied", MessageType::Success);
+    }
+
+    pub fn copy_share_passphrase(&mut self) {
+        let Some(view) = &self.share_view else { return };
+        clipboard::copy_with_timeout(view.passphrase(), self.config.clipboard_timeout, self.config.clipboard);
+        self.set_message("Passphrase copied", MessageType::Success);
+    }
+
+    pub fn close_share(&mut self) {
+        self.share_view = None;
+        self.mode_state.enter_normal_mode();
+    }
+
+    /// The import dialog, ready for a `.share` file or a QR code screenshot
+    pub fn receive(&mut self, file: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+        self.import()?;
+        if let (Some(file), Some(dialog)) = (file, self.import_dialog.as_mut()) {
+            *dialog = ImportDialog::with_path(file);
+        }
+        Ok(())
+    }
+}
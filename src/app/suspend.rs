@@ -0,0 +1,133 @@
+//! Noticing system suspend, so an unlocked vault never survives sleep
+//!
+//! On Linux, `gdbus monitor` follows logind's PrepareForSleep signal and the
+//! vault locks as the machine goes down. Without D-Bus (or when the signal
+//! arrives too late) a wake is still spotted: the monotonic clock stops
+//! while suspended and the wall clock does not, so a gap between them means
+//! the machine slept.
+
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Wall-clock time passing beyond monotonic time that counts as a sleep
+const SLEEP_GAP: Duration = Duration::from_secs(5);
+
+pub struct SuspendWatch {
+    monitor: Option<(Child, Receiver<()>)>,
+    last_check: (Instant, SystemTime),
+}
+
+impl SuspendWatch {
+    pub fn new() -> Self {
+        Self {
+            monitor: start_monitor(),
+            last_check: (Instant::now(), SystemTime::now()),
+        }
+    }
+
+    /// Whether the machine is about to suspend or has slept since the last check
+    pub fn poll(&mut self) -> bool {
+        let (mono, wall) = std::mem::replace(&mut self.last_check, (Instant::now(), SystemTime::now()));
+        let woke = slept(mono.elapsed(), wall.elapsed().unwrap_or_default());
+
+        let suspending = match &self.monitor {
+            Some((_, rx)) => match rx.try_recv() {
+                Ok(()) => true,
+                Err(TryRecvError::Empty) => false,
+                Err(TryRecvError::Disconnected) => {
+                    self.stop_monitor();
+                    false
+                }
+            },
+            None => false,
+        };
+        woke || suspending
+    }
+
+    /// Forget sleeps so far, e.g. ones the vault spent locked
+    pub fn reset(&mut self) {
+        self.last_check = (Instant::now(), SystemTime::now());
+        if let Some((_, rx)) = &self.monitor {
+            while rx.try_recv().is_ok() {}
+        }
+    }
+
+    fn stop_monitor(&mut self) {
+        if let Some((mut child, _)) = self.monitor.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+impl Default for SuspendWatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for SuspendWatch {
+    fn drop(&mut self) {
+        self.stop_monitor();
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn start_monitor() -> Option<(Child, Receiver<()>)> {
+    let mut child = Command::new("gdbus")
+        .args(["monitor", "--system", "--dest", "org.freedesktop.login1", "--object-path", "/org/freedesktop/login1"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let stdout = child.stdout.take()?;
+
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if is_prepare_for_sleep(&line) && tx.send(()).is_err() {
+                break;
+            }
+        }
+    });
+    Some((child, rx))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn start_monitor() -> Option<(Child, Receiver<()>)> {
+    None
+}
+
+/// `gdbus monitor` prints signals as
+/// `/org/freedesktop/login1: org.freedesktop.login1.Manager.PrepareForSleep (true,)`;
+/// false is the wake-up
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn is_prepare_for_sleep(line: &str) -> bool {
+    line.contains(".PrepareForSleep") && line.contains("(true,)")
+}
+
+fn slept(monotonic: Duration, wall: Duration) -> bool {
+    wall.saturating_sub(monotonic) > SLEEP_GAP
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_prepare_for_sleep() {
+        assert!(is_prepare_for_sleep("/org/freedesktop/login1: org.freedesktop.login1.Manager.PrepareForSleep (true,)"));
+        assert!(!is_prepare_for_sleep("/org/freedesktop/login1: org.freedesktop.login1.Manager.PrepareForSleep (false,)"));
+        assert!(!is_prepare_for_sleep("/org/freedesktop/login1: org.freedesktop.login1.Manager.SessionNew ('3', '/org/freedesktop/login1/session/_33')"));
+    }
+
+    #[test]
+    fn test_slept() {
+        assert!(!slept(Duration::from_millis(100), Duration::from_millis(120)));
+        assert!(!slept(Duration::from_secs(2), Duration::from_secs(1)));
+        assert!(slept(Duration::from_millis(100), Duration::from_secs(600)));
+    }
+}
@@ -0,0 +1,108 @@
+//! Renaming, merging and deleting tags across the whole vault
+
+use crate::input::TextBuffer;
+use crate::ui::MessageType;
+use crate::vault::tags;
+
+use super::config::PendingAction;
+use super::App;
+
+impl App {
+    /// Prefill the command line with `:<command> <args> ` from the tags popup
+    pub fn prompt_tag_command(&mut self, command: &str) {
+        let args = match command {
+            "mergetag" if !self.tags_state.selected_tags.is_empty() => {
+                let mut checked = self.tags_state.get_selected_tags();
+                checked.sort();
+                checked.join(" ")
+            }
+            _ => match self.tags_state.tags.get(self.tags_state.selected) {
+                Some((tag, _)) => tag.clone(),
+                None => return,
+            },
+        };
+        self.mode_state.enter_command_mode();
+        self.mode_state.buffer = TextBuffer::with_content(format!("{} {} ", command, args));
+    }
+
+    /// `:renametag <old> <new>`
+    pub fn rename_tag(&mut self, args: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+        let parts: Vec<&str> = args.unwrap_or_default().split_whitespace().collect();
+        let [old, new] = parts[..] else {
+            self.set_message("Usage: :renametag <old> <new>", MessageType::Error);
+            return Ok(());
+        };
+        if self.tag_exists(new)? {
+            self.set_message(&format!("Tag {} already exists, use :mergetag {} {}", new, old, new), MessageType::Error);
+            return Ok(());
+        }
+        self.rewrite_tags(&[old.to_string()], Some(new), &format!("Renamed tag {} to {}", old, new))
+    }
+
+    /// `:mergetag <tag>... <into>`
+    pub fn merge_tags(&mut self, args: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+        let mut parts: Vec<String> = args.unwrap_or_default().split_whitespace().map(String::from).collect();
+        let into = parts.pop();
+        parts.retain(|t| Some(t) != into.as_ref());
+        let Some(into) = into.filter(|_| !parts.is_empty()) else {
+            self.set_message("Usage: :mergetag <tag>... <into>", MessageType::Error);
+            return Ok(());
+        };
+        let details = format!("Merged tag {} into {}", parts.join(", "), into);
+        self.rewrite_tags(&parts, Some(&into), &details)
+    }
+
+    /// `:deltag <tag>`, asking first
+    pub fn request_delete_tag(&mut self, tag: Option<&str>) {
+        let Some(tag) = tag.filter(|t| !t.contains(char::is_whitespace)) else {
+            self.set_message("Usage: :deltag <tag>", MessageType::Error);
+            return;
+        };
+        self.pending_action = Some(PendingAction::DeleteTag(tag.to_string()));
+        self.mode_state.enter_confirm_mode();
+    }
+
+    pub fn delete_tag(&mut self, tag: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.rewrite_tags(&[tag.to_string()], None, &format!("Deleted tag {}", tag))
+    }
+
+    fn tag_exists(&self, tag: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        let all = crate::vault::search::get_all(self.vault.db()?.conn())?;
+        let nested = format!("{}/", tag);
+        Ok(all.iter().any(|c| c.tags.iter().any(|t| t == tag || t.starts_with(&nested))))
+    }
+
+    /// Apply the edit, keep the tag filter in step and return to the tags popup
+    fn rewrite_tags(&mut self, from: &[String], into: Option<&str>, details: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.vault.is_unlocked() {
+            self.set_message("Vault must be unlocked", MessageType::Error);
+            return Ok(());
+        }
+        let changed = {
+            let audit_key = self.vault.keys()?.derive_audit_key()?;
+            tags::rewrite_tags(self.vault.db()?.conn(), &audit_key, from, into, details)?
+        };
+        if changed == 0 {
+            self.set_message(&format!("No credentials tagged {}", from.join(", ")), MessageType::Error);
+            return Ok(());
+        }
+
+        if let Some(filter) = self.filter_tags.take() {
+            let (mut included, mut excluded) = crate::vault::search::split_tag_filter(&filter);
+            tags::retag(&mut included, from, into);
+            tags::retag(&mut excluded, from, into);
+            included.extend(excluded.into_iter().map(|t| format!("-{}", t)));
+            self.filter_tags = (!included.is_empty()).then_some(included);
+        }
+
+        self.refresh_data()?;
+        self.update_selected_detail()?;
+        self.load_tags()?;
+        if let Some(into) = into {
+            self.tags_state.select_tag(into);
+        }
+        self.mode_state.enter_tags_mode();
+        self.set_message(&format!("{} on {} credential(s)", details, changed), MessageType::Success);
+        Ok(())
+    }
+}
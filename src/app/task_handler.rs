@@ -0,0 +1,153 @@
+//! Slow vault operations on a worker thread
+//!
+//! Unlocking (Argon2, or waiting for a security key), key rotation, exports
+//! and imports can take seconds. The vault moves onto a worker thread for the
+//! duration and comes back with the result; meanwhile the UI keeps drawing,
+//! with a spinner in place of the frozen screen, and ignores keys, since
+//! nothing else may touch the vault until it is back.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::ui::components::dialogs::ProgressPopup;
+use crate::ui::MessageType;
+use crate::vault::Vault;
+
+use super::App;
+
+/// What a background job hands back to run on the UI thread, with the vault
+/// in place again
+pub type Finish = Box<dyn FnOnce(&mut App) -> Result<(), Box<dyn std::error::Error>> + Send>;
+
+enum Update<T> {
+    Progress(usize, usize),
+    /// The vault, and the job's output unless it panicked
+    Done(Box<Vault>, thread::Result<T>),
+}
+
+/// A job running on its own thread with the vault
+pub struct Task<T> {
+    title: &'static str,
+    started: Instant,
+    /// Done and total, for jobs that count
+    progress: Option<(usize, usize)>,
+    rx: Receiver<Update<T>>,
+}
+
+impl<T: Send + 'static> Task<T> {
+    pub fn spawn<F>(title: &'static str, mut vault: Vault, job: F) -> Self
+    where
+        F: FnOnce(&mut Vault, &mut dyn FnMut(usize, usize)) -> T + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let progress_tx = tx.clone();
+            let mut progress = |done: usize, total: usize| {
+                let _ = progress_tx.send(Update::Progress(done, total));
+            };
+            // A panic must not take the vault down with the thread
+            let output = panic::catch_unwind(AssertUnwindSafe(|| job(&mut vault, &mut progress)));
+            let _ = tx.send(Update::Done(Box::new(vault), output));
+        });
+        Self { title, started: Instant::now(), progress: None, rx }
+    }
+
+    /// The vault and the job's output, once it has finished
+    pub fn poll(&mut self) -> Option<(Vault, thread::Result<T>)> {
+        while let Ok(update) = self.rx.try_recv() {
+            match update {
+                Update::Progress(done, total) => self.progress = Some((done, total)),
+                Update::Done(vault, output) => return Some((*vault, output)),
+            }
+        }
+        None
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    pub fn popup(&self) -> ProgressPopup<'static> {
+        ProgressPopup::new(self.title, self.elapsed()).progress(self.progress)
+    }
+}
+
+impl App {
+    /// Whether a job has the vault; input waits until it is back
+    pub fn is_busy(&self) -> bool {
+        self.task.is_some()
+    }
+
+    /// Run `job` with the vault on a worker thread, then what it returns
+    /// here once the vault is back
+    pub fn run_in_background<F>(&mut self, title: &'static str, job: F)
+    where
+        F: FnOnce(&mut Vault, &mut dyn FnMut(usize, usize)) -> Finish + Send + 'static,
+    {
+        let vault = self.take_vault();
+        self.task = Some(Task::spawn(title, vault, job));
+    }
+
+    /// The vault, leaving a locked one with the same settings in its place
+    pub fn take_vault(&mut self) -> Vault {
+        let placeholder = Vault::new(self.vault.config().clone());
+        std::mem::replace(&mut self.vault, placeholder)
+    }
+
+    /// Put the vault back and finish the job once it is done
+    pub fn tick_task(&mut self) {
+        let Some(task) = self.task.as_mut() else { return };
+        let Some((vault, output)) = task.poll() else { return };
+        let title = task.title.trim();
+        self.task = None;
+        self.vault = vault;
+        self.vault.update_activity();
+
+        let result = match output {
+            Ok(finish) => finish(self),
+            Err(_) => Err(format!("{} stopped unexpectedly", title).into()),
+        };
+        if let Err(e) = result {
+            self.set_message(&format!("Error: {}", e), MessageType::Error);
+        }
+        match self.deferred_focus.take() {
+            Some(true) => self.focus_gained(),
+            Some(false) => self.focus_lost(),
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wait<T: Send + 'static>(task: &mut Task<T>) -> (Vault, thread::Result<T>) {
+        loop {
+            if let Some(done) = task.poll() {
+                return done;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_task_returns_vault() {
+        let vault = Vault::new(crate::vault::VaultConfig::with_path("/nonexistent/vault.db"));
+        let mut task = Task::spawn(" Counting ", vault, |vault, progress| {
+            progress(1, 2);
+            progress(2, 2);
+            vault.is_unlocked()
+        });
+        let (vault, output) = wait(&mut task);
+        assert!(!output.unwrap());
+        assert!(!vault.is_unlocked());
+        assert_eq!(task.progress, Some((2, 2)));
+
+        let mut task = Task::spawn(" Failing ", vault, |_, _| -> () { panic!("job failed") });
+        let (_, output) = wait(&mut task);
+        assert!(output.is_err());
+    }
+}
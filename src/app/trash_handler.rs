@@ -0,0 +1,69 @@
+//! `:trash` for restoring or permanently deleting deleted credentials
+
+use crate::db::{self, AuditAction};
+use crate::ui::MessageType;
+
+use super::config::PendingAction;
+use super::App;
+
+impl App {
+    pub fn show_trash(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.vault.is_unlocked() {
+            self.set_message("Vault must be unlocked", MessageType::Error);
+            return Ok(());
+        }
+        self.load_trash()?;
+        self.mode_state.enter_trash_mode();
+        Ok(())
+    }
+
+    fn load_trash(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let trash = db::get_trash(self.vault.db()?.conn())?;
+        self.trash_state.set_items(trash);
+        Ok(())
+    }
+
+    pub fn restore_trashed(&mut self, id: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let cred = match db::restore_from_trash(self.vault.db()?.conn(), id) {
+            Ok(cred) => cred,
+            Err(e) => {
+                self.set_message(&format!("Restore failed: {}", e), MessageType::Error);
+                return Ok(());
+            }
+        };
+        self.log_audit(AuditAction::Restore, Some(id), Some(&cred.name), cred.username.as_deref(), Some("Restored from trash"))?;
+        self.reindex([id])?;
+
+        self.refresh_data()?;
+        self.update_selected_detail()?;
+        self.load_trash()?;
+        self.set_message(&format!("Restored {}", cred.name), MessageType::Success);
+        Ok(())
+    }
+
+    /// Ask before purging; confirming returns to the trash
+    pub fn request_purge(&mut self, action: PendingAction) {
+        self.pending_action = Some(action);
+        self.mode_state.enter_confirm_mode();
+    }
+
+    pub fn purge_trashed(&mut self, id: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(item) = self.trash_state.items.iter().find(|t| t.credential.id == id) else {
+            return self.show_trash();
+        };
+        let cred = item.credential.clone();
+
+        db::purge_from_trash(self.vault.db()?.conn(), id)?;
+        self.log_audit(AuditAction::Delete, Some(id), Some(&cred.name), cred.username.as_deref(), Some("Purged from trash"))?;
+        self.set_message(&format!("Deleted {} forever", cred.name), MessageType::Success);
+        self.show_trash()
+    }
+
+    pub fn empty_trash(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let purged = db::empty_trash(self.vault.db()?.conn())?;
+        let details = format!("Emptied trash: {} credential(s) purged", purged);
+        self.log_audit(AuditAction::Delete, None, None, None, Some(&details))?;
+        self.set_message(&details, MessageType::Success);
+        self.show_trash()
+    }
+}
@@ -0,0 +1,112 @@
+//! `:wipe` and the duress password, for a device that is no longer safe
+
+use std::io;
+use std::path::PathBuf;
+
+use crate::ui::MessageType;
+use crate::vault::{auto_export, backup, sync, wipe};
+
+use super::clipboard;
+use super::config::AppConfig;
+use super::reauth_handler::Guarded;
+use super::App;
+
+/// What `:wipe` must be followed by before it destroys anything
+pub const WIPE_CONFIRMATION: &str = "ERASE-EVERYTHING";
+
+impl App {
+    /// `:wipe` explains itself; only `:wipe ERASE-EVERYTHING` goes ahead
+    pub fn wipe_command(&mut self, arg: Option<&str>) {
+        if arg.map(str::trim) == Some(WIPE_CONFIRMATION) {
+            let failures = self.wipe();
+            self.exit_message = wipe_report(&failures);
+            return;
+        }
+        let message = format!(
+            "Destroys the vault, its snapshots, backups, automatic exports and the config for good; type :wipe {} to go ahead",
+            WIPE_CONFIRMATION
+        );
+        self.set_message(&message, MessageType::Warning);
+    }
+
+    /// Overwrite and delete the vault, its sync snapshots, backups, automatic
+    /// exports and the config, then quit; returns the files that survived
+    ///
+    /// Nothing is logged: the audit log goes with the vault.
+    pub fn wipe(&mut self) -> Vec<(PathBuf, io::Error)> {
+        let paths = self.wipe_paths();
+        let _ = self.vault.forget_session();
+        self.vault.lock();
+        self.clear_credentials();
+        self.copy_sequence = None;
+        self.reauth_request = None;
+        clipboard::clear_now();
+
+        let failures = wipe::shred_all(&paths);
+        let _ = std::fs::remove_dir(sync::snapshot_dir(&self.config.vault_path));
+        if self.config.backup_dir.is_none() {
+            let _ = std::fs::remove_dir(self.backup_config().dir);
+        }
+        self.should_quit = true;
+        failures
+    }
+
+    /// Whether `password` typed at unlock is the duress password
+    pub fn is_duress_password(&self, password: &str) -> bool {
+        self.vault.is_duress_password(password)
+    }
+
+    /// `:duress` tells whether a duress password is set, `:duress PHRASE`
+    /// sets one that wipes everything when typed at unlock and `:duress off`
+    /// removes it; changes ask for the master password
+    pub fn duress_command(&mut self, arg: Option<&str>) {
+        let duress = match arg.map(str::trim) {
+            None => {
+                let message = match self.vault.has_duress_password() {
+                    Ok(true) => "A duress password is set; typed at unlock, it wipes everything",
+                    _ => "No duress password; :duress PHRASE sets one",
+                };
+                self.set_message(message, MessageType::Info);
+                return;
+            }
+            Some("off" | "none") => None,
+            Some(phrase) => Some(phrase.to_string()),
+        };
+        self.reauth_request = Some(Guarded::Duress(duress));
+    }
+
+    pub(super) fn change_duress_password(&mut self, duress: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
+        self.vault.set_duress_password(duress.as_deref())?;
+        let message = match duress {
+            Some(_) => "Duress password set",
+            None => "Duress password removed",
+        };
+        self.set_message(message, MessageType::Success);
+        Ok(())
+    }
+
+    fn wipe_paths(&self) -> Vec<PathBuf> {
+        let mut paths = wipe::database_files(&self.config.vault_path);
+        paths.extend(wipe::sync_snapshots(&self.config.vault_path));
+        let backups = backup::list_backups(&self.backup_config().dir).unwrap_or_default();
+        paths.extend(backups.into_iter().map(|b| b.path));
+        if let Some(dir) = &self.config.auto_export_dir {
+            let exports = auto_export::list_exports(dir).unwrap_or_default();
+            paths.extend(exports.into_iter().map(|e| e.path));
+        }
+        paths.extend(AppConfig::config_path());
+        paths
+    }
+}
+
+/// What to tell the user after `:wipe` about files it couldn't shred
+fn wipe_report(failures: &[(PathBuf, io::Error)]) -> Option<String> {
+    if failures.is_empty() {
+        return None;
+    }
+    let mut report = format!("wipe incomplete, {} file(s) could not be shredded:", failures.len());
+    for (path, e) in failures {
+        report.push_str(&format!("\n  {}: {}", path.display(), e));
+    }
+    Some(report)
+}
@@ -0,0 +1,367 @@
+//! `vault agent` - keep the vault unlocked between commands
+//!
+//! The agent unlocks the vault once and hands its master key to later CLI
+//! commands and TUI starts over a Unix socket, until it has been idle for
+//! the timeout. The socket is only accessible to its owner and every
+//! connection is checked against the agent's user ID. Requests and replies
+//! are single lines:
+//!
+//! - `KEY <vault path>` -> `OK <hex master key>` (resets the idle timer)
+//! - `STATUS` -> `OK <seconds left> <vault path>`
+//! - `STOP` -> `OK`, then the agent locks and exits
+//!
+//! Failures are answered with `ERR <message>`.
+//!
+//! With `--http <PORT>` the agent also serves the HTTP API of `super::api` on
+//! 127.0.0.1, to clients holding a token from `vault token add`.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::crypto::MasterKey;
+
+use super::api;
+use super::password::{PasswordSource, AGENT_SOCKET_ENV};
+use super::session::Session;
+use super::GlobalOptions;
+
+/// Idle time after which the agent locks and exits
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+/// How long a client may take to send its request or read the reply
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[cfg(unix)]
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AgentAction {
+    Start,
+    Status,
+    Stop,
+}
+
+pub struct AgentArgs {
+    pub action: AgentAction,
+    pub socket: Option<PathBuf>,
+    pub timeout: Duration,
+    /// Also serve the HTTP API on this port
+    pub http: Option<u16>,
+}
+
+/// `$VAULT_AGENT_SOCK`, else `$XDG_RUNTIME_DIR/vault/agent.sock`, else a per-user temp dir
+pub fn default_socket() -> PathBuf {
+    if let Some(path) = std::env::var_os(AGENT_SOCKET_ENV) {
+        return PathBuf::from(path);
+    }
+    let dir = dirs::runtime_dir()
+        .map(|d| d.join("vault"))
+        .unwrap_or_else(|| std::env::temp_dir().join(format!("vault-{}", current_uid())));
+    dir.join("agent.sock")
+}
+
+/// The default agent socket, if an agent appears to be running
+pub fn running_socket() -> Option<PathBuf> {
+    let socket = default_socket();
+    socket.exists().then_some(socket)
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(unix)]
+fn current_uid() -> u32 {
+    unsafe { libc::getuid() }
+}
+
+#[cfg(not(unix))]
+fn current_uid() -> u32 {
+    0
+}
+
+/// Send one request line and return the payload of an `OK` reply
+#[cfg(unix)]
+fn request(socket: &Path, line: &str) -> io::Result<Zeroizing<String>> {
+    let mut stream = std::os::unix::net::UnixStream::connect(socket)?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.write_all(format!("{}\n", line).as_bytes())?;
+
+    let mut reply = Zeroizing::new(String::new());
+    BufReader::new(&stream).read_line(&mut reply)?;
+    let reply = reply.trim_end();
+
+    if let Some(payload) = reply.strip_prefix("OK") {
+        return Ok(Zeroizing::new(payload.trim_start().to_string()));
+    }
+    let message = reply.strip_prefix("ERR ").unwrap_or("malformed reply from agent");
+    Err(io::Error::other(message.to_string()))
+}
+
+#[cfg(not(unix))]
+fn request(_socket: &Path, _line: &str) -> io::Result<Zeroizing<String>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "the agent is only supported on Unix"))
+}
+
+/// Ask the agent at `socket` for the master key of the vault at `vault_path`
+pub fn request_key(socket: &Path, vault_path: &Path) -> io::Result<MasterKey> {
+    let reply = request(socket, &format!("KEY {}", canonical(vault_path).display()))?;
+
+    let mut bytes = [0u8; 32];
+    let decoded = hex::decode_to_slice(reply.as_bytes(), &mut bytes);
+    let key = decoded.map(|()| MasterKey::from_bytes(bytes));
+    bytes.zeroize();
+    key.map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "malformed key from agent"))
+}
+
+pub fn agent(global: &GlobalOptions, args: AgentArgs) -> Result<i32, Box<dyn std::error::Error>> {
+    let socket = args.socket.unwrap_or_else(default_socket);
+    match args.action {
+        AgentAction::Start => serve(global, &socket, args.timeout, args.http),
+        AgentAction::Status => {
+            let reply = request(&socket, "STATUS").map_err(|e| format!("no agent at {}: {}", socket.display(), e))?;
+            let (seconds, vault) = reply.split_once(' ').unwrap_or((reply.as_str(), ""));
+            println!("Agent at {} holds {} (locks in {}s)", socket.display(), vault, seconds);
+            Ok(0)
+        }
+        AgentAction::Stop => {
+            request(&socket, "STOP").map_err(|e| format!("no agent at {}: {}", socket.display(), e))?;
+            eprintln!("Agent stopped");
+            Ok(0)
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn serve(_global: &GlobalOptions, _socket: &Path, _timeout: Duration, _http: Option<u16>) -> Result<i32, Box<dyn std::error::Error>> {
+    Err("the agent is only supported on Unix".into())
+}
+
+#[cfg(unix)]
+fn serve(global: &GlobalOptions, socket: &Path, timeout: Duration, http: Option<u16>) -> Result<i32, Box<dyn std::error::Error>> {
+    use std::os::unix::net::UnixListener;
+
+    if request(socket, "STATUS").is_ok() {
+        return Err(format!("an agent is already running at {}", socket.display()).into());
+    }
+
+    // The agent cannot ask itself (or a stale socket) for the password
+    let source = match &global.password {
+        PasswordSource::Agent(_) => PasswordSource::Prompt,
+        other => other.clone(),
+    };
+    let session = Session::unlock(&global.vault_path, &source)?;
+    let vault_path = canonical(&global.vault_path);
+
+    let listener: UnixListener = bind(socket)?;
+    listener.set_nonblocking(true)?;
+    let api_listener = http.map(api::bind).transpose()?;
+    eprintln!("Agent listening on {}", socket.display());
+    if let Some(api_listener) = &api_listener {
+        api_listener.set_nonblocking(true)?;
+        eprintln!("HTTP API listening on http://{}", api_listener.local_addr()?);
+    }
+    eprintln!("Locks after {}s idle; stop it with: vault agent --stop", timeout.as_secs());
+
+    let mut expires = Instant::now() + timeout;
+    let result = loop {
+        if Instant::now() >= expires {
+            break Ok(0);
+        }
+        let mut idle = true;
+        match listener.accept() {
+            Ok((stream, _)) => {
+                idle = false;
+                match handle_client(stream, &session, &vault_path, expires) {
+                    Ok(Served::Key) => expires = Instant::now() + timeout,
+                    Ok(Served::Stop) => break Ok(0),
+                    Ok(Served::Other) => {}
+                    Err(e) => eprintln!("agent: {}", e),
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+            Err(e) => break Err(e.into()),
+        }
+
+        // API clients keep the agent alive like KEY requests do
+        if let Some(api_listener) = &api_listener {
+            match api_listener.accept() {
+                Ok((stream, _)) => {
+                    idle = false;
+                    let port = api_listener.local_addr().map_or(0, |a| a.port());
+                    match api::handle_client(stream, &session, port) {
+                        Ok(true) => expires = Instant::now() + timeout,
+                        Ok(false) => {}
+                        Err(e) => eprintln!("agent: http: {}", e),
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(e) => break Err(e.into()),
+            }
+        }
+
+        if idle {
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    };
+
+    let _ = std::fs::remove_file(socket);
+    drop(session);
+    eprintln!("Agent locked");
+    result
+}
+
+/// Bind the socket owner-only, replacing a stale one left by a killed agent
+#[cfg(unix)]
+fn bind(socket: &Path) -> io::Result<std::os::unix::net::UnixListener> {
+    use std::os::unix::fs::DirBuilderExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    if let Some(dir) = socket.parent().filter(|d| !d.exists()) {
+        std::fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+    }
+    if socket.exists() && UnixStream::connect(socket).is_err() {
+        std::fs::remove_file(socket)?;
+    }
+
+    let old_mask = unsafe { libc::umask(0o177) };
+    let listener = UnixListener::bind(socket);
+    unsafe { libc::umask(old_mask) };
+    listener
+}
+
+#[cfg(unix)]
+enum Served {
+    Key,
+    Stop,
+    Other,
+}
+
+#[cfg(unix)]
+fn handle_client(
+    stream: std::os::unix::net::UnixStream,
+    session: &Session,
+    vault_path: &Path,
+    expires: Instant,
+) -> io::Result<Served> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut writer = &stream;
+
+    if peer_uid(&stream)? != current_uid() {
+        writer.write_all(b"ERR permission denied\n")?;
+        return Ok(Served::Other);
+    }
+
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    let (reply, served) = respond(line.trim_end(), session, vault_path, expires);
+
+    writer.write_all(reply.as_bytes())?;
+    writer.write_all(b"\n")?;
+    Ok(served)
+}
+
+#[cfg(unix)]
+fn respond(request: &str, session: &Session, vault_path: &Path, expires: Instant) -> (Zeroizing<String>, Served) {
+    let reply = |s: String| Zeroizing::new(s);
+    let (command, argument) = request.split_once(' ').unwrap_or((request, ""));
+
+    match command {
+        "KEY" if canonical(Path::new(argument)) != vault_path => {
+            (reply(format!("ERR agent holds {}", vault_path.display())), Served::Other)
+        }
+        "KEY" => match session.vault.keys() {
+            Ok(keys) => {
+                let encoded = Zeroizing::new(hex::encode(keys.master_key().as_bytes()));
+                (reply(format!("OK {}", encoded.as_str())), Served::Key)
+            }
+            Err(e) => (reply(format!("ERR {}", e)), Served::Other),
+        },
+        "STATUS" => {
+            let left = expires.saturating_duration_since(Instant::now()).as_secs();
+            (reply(format!("OK {} {}", left, vault_path.display())), Served::Other)
+        }
+        "STOP" => (reply("OK".to_string()), Served::Stop),
+        _ => (reply("ERR unknown request".to_string()), Served::Other),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn peer_uid(stream: &std::os::unix::net::UnixStream) -> io::Result<u32> {
+    use std::os::fd::AsRawFd;
+
+    let mut cred = libc::ucred { pid: 0, uid: 0, gid: 0 };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let rc = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            (&mut cred as *mut libc::ucred).cast(),
+            &mut len,
+        )
+    };
+    if rc != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(cred.uid)
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn peer_uid(stream: &std::os::unix::net::UnixStream) -> io::Result<u32> {
+    use std::os::fd::AsRawFd;
+
+    let (mut uid, mut gid) = (0, 0);
+    if unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(uid)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    use crate::vault::{Vault, VaultConfig};
+
+    #[test]
+    fn test_agent_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("vault.db");
+        Vault::new(VaultConfig::with_path(&path)).initialize("correct horse").unwrap();
+
+        let key = dir.path().join("key");
+        std::fs::write(&key, "correct horse").unwrap();
+        std::fs::set_permissions(&key, std::fs::Permissions::from_mode(0o600)).unwrap();
+        let global = GlobalOptions { vault_path: path.clone(), password: PasswordSource::File(key) };
+
+        let socket = dir.path().join("run").join("agent.sock");
+        let server_socket = socket.clone();
+        let server = std::thread::spawn(move || serve(&global, &server_socket, Duration::from_secs(30), None).unwrap());
+        while !socket.exists() {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(std::fs::metadata(&socket).unwrap().permissions().mode() & 0o077, 0);
+
+        let mut vault = Vault::new(VaultConfig::with_path(&path));
+        vault.unlock_with_key(request_key(&socket, &path).unwrap()).unwrap();
+        assert!(vault.is_unlocked());
+
+        let status = request(&socket, "STATUS").unwrap();
+        assert!(status.ends_with(canonical(&path).to_str().unwrap()));
+        assert!(request_key(&socket, &dir.path().join("other.db")).is_err());
+        assert!(request(&socket, "BOGUS").is_err());
+
+        request(&socket, "STOP").unwrap();
+        assert_eq!(server.join().unwrap(), 0);
+        assert!(!socket.exists());
+    }
+}
@@ -0,0 +1,419 @@
+//! Local HTTP API served by `vault agent --http <PORT>`
+//!
+//! Lets programs that can't link against the vault, such as home-automation
+//! bridges and editor plugins, read credentials from a running agent. The
+//! server listens on 127.0.0.1 only, answers one request per connection and
+//! speaks JSON:
+//!
+//! - `GET /v1/credentials[?tag=TAG...]` -> `[{id, name, type, username, url, tags}]`
+//! - `GET /v1/search?q=QUERY` -> the same list, for the credentials matching QUERY
+//! - `GET /v1/credentials/{id or name}` -> one credential with its secret, notes and fields
+//! - `GET /v1/credentials/{id or name}/otp` -> `{code, remaining}`
+//!
+//! Every request needs `Authorization: Bearer <token>` with a token from
+//! `vault token add`, and every one answered is written to the audit log under
+//! the token's name. Requests naming any other `Host` than the loopback
+//! address are refused, so a web page can't reach the API by DNS rebinding.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::time::Duration;
+
+use secrecy::ExposeSecret;
+use serde::Serialize;
+use zeroize::Zeroizing;
+
+use crate::crypto::totp;
+use crate::db::{AuditAction, Credential, CredentialType};
+use crate::vault::api_tokens;
+use crate::vault::credential::decrypt_credential;
+use crate::vault::search;
+
+use super::run::find_credential;
+use super::session::Session;
+
+/// How long a client may take to send its request or read the reply
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest request line and headers read; requests have no body
+const MAX_HEAD: u64 = 8 * 1024;
+
+/// Listen on the loopback address only
+pub fn bind(port: u16) -> io::Result<TcpListener> {
+    TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+}
+
+#[derive(Debug, Default)]
+struct Request {
+    method: String,
+    /// Still percent-encoded, so an encoded `/` stays inside its segment
+    path: String,
+    query: Vec<(String, String)>,
+    host: Option<String>,
+    token: Option<Zeroizing<String>>,
+}
+
+impl Request {
+    fn query_values<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> {
+        self.query.iter().filter(move |(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+}
+
+struct Response {
+    status: u16,
+    body: Zeroizing<String>,
+}
+
+impl Response {
+    fn json(value: &impl Serialize) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self { status: 200, body: Zeroizing::new(serde_json::to_string(value)?) })
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        let body = serde_json::json!({ "error": message }).to_string();
+        Self { status, body: Zeroizing::new(body) }
+    }
+}
+
+#[derive(Serialize)]
+struct Summary<'a> {
+    id: &'a str,
+    name: &'a str,
+    #[serde(rename = "type")]
+    credential_type: CredentialType,
+    username: Option<&'a str>,
+    url: Option<&'a str>,
+    tags: &'a [String],
+}
+
+impl<'a> From<&'a Credential> for Summary<'a> {
+    fn from(cred: &'a Credential) -> Self {
+        Self {
+            id: &cred.id,
+            name: &cred.name,
+            credential_type: cred.credential_type,
+            username: cred.username.as_deref(),
+            url: cred.url.as_deref(),
+            tags: &cred.tags,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Detail<'a> {
+    #[serde(flatten)]
+    summary: Summary<'a>,
+    secret: Option<&'a str>,
+    notes: Option<&'a str>,
+    fields: Vec<Field<'a>>,
+    /// Whether `/otp` has a code to give
+    has_otp: bool,
+}
+
+#[derive(Serialize)]
+struct Field<'a> {
+    label: &'a str,
+    value: &'a str,
+}
+
+#[derive(Serialize)]
+struct Otp<'a> {
+    code: &'a str,
+    /// Seconds the code stays valid
+    remaining: u64,
+}
+
+/// Answer one connection; returns whether a client was served, which counts
+/// as activity for the agent's idle timeout
+pub fn handle_client(stream: TcpStream, session: &Session, port: u16) -> io::Result<bool> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+
+    let (response, served) = match read_request(BufReader::new((&stream).take(MAX_HEAD))) {
+        Some(request) => respond(&request, session, port),
+        None => (Response::error(400, "Malformed request"), false),
+    };
+    write_response(&stream, &response)?;
+    Ok(served)
+}
+
+/// The request line and the headers the API looks at, or None if malformed
+fn read_request(mut reader: impl BufRead) -> Option<Request> {
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let (method, target) = (parts.next()?, parts.next()?);
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            Some((percent_decode(key, true)?, percent_decode(value, true)?))
+        })
+        .collect::<Option<_>>()?;
+    let mut request = Request { method: method.to_string(), path: path.to_string(), query, ..Request::default() };
+
+    loop {
+        let mut header = Zeroizing::new(String::new());
+        if reader.read_line(&mut header).ok()? == 0 {
+            return None;
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            return Some(request);
+        }
+        let (name, value) = header.split_once(':')?;
+        match name.trim().to_ascii_lowercase().as_str() {
+            "host" => request.host = Some(value.trim().to_string()),
+            "authorization" => {
+                request.token = value.trim().strip_prefix("Bearer ").map(|t| Zeroizing::new(t.trim().to_string()));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// `%XX` escapes, and `+` as a space in query strings
+fn percent_decode(s: &str, plus_as_space: bool) -> Option<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut rest = s.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        rest = tail;
+        match b {
+            b'%' => {
+                let hex = std::str::from_utf8(rest.get(..2)?).ok()?;
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+                rest = &rest[2..];
+            }
+            b'+' if plus_as_space => bytes.push(b' '),
+            b => bytes.push(b),
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Browsers send the name they resolved; anything but loopback is a page
+/// that rebound its own domain to 127.0.0.1
+fn is_loopback_host(host: Option<&str>, port: u16) -> bool {
+    let Some(host) = host else { return false };
+    [format!("127.0.0.1:{}", port), format!("localhost:{}", port)]
+        .iter()
+        .any(|allowed| host.eq_ignore_ascii_case(allowed))
+}
+
+fn respond(request: &Request, session: &Session, port: u16) -> (Response, bool) {
+    if !is_loopback_host(request.host.as_deref(), port) {
+        return (Response::error(403, "Host must be 127.0.0.1 or localhost"), false);
+    }
+    if request.method != "GET" {
+        return (Response::error(405, "Only GET is supported"), false);
+    }
+
+    let tokens = match session.vault.db().and_then(|db| api_tokens::list_tokens(db.conn())) {
+        Ok(tokens) => tokens,
+        Err(e) => return (Response::error(500, &e.to_string()), false),
+    };
+    let client = request.token.as_deref().and_then(|token| api_tokens::find_token(&tokens, token));
+    let Some(client) = client else {
+        eprintln!("agent: refused {} {} without a valid token", request.method, request.path);
+        return (Response::error(401, "Missing or unknown API token"), false);
+    };
+
+    match route(request, session, &client.name) {
+        Ok(response) => (response, true),
+        Err(e) => (Response::error(500, &e.to_string()), true),
+    }
+}
+
+fn route(request: &Request, session: &Session, client: &str) -> Result<Response, Box<dyn std::error::Error>> {
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+    let reference = |segment: &str| percent_decode(segment, false);
+
+    match segments.as_slice() {
+        ["v1", "credentials"] => {
+            let tags: Vec<String> = request.query_values("tag").map(String::from).collect();
+            let credentials = session.credentials(&tags)?;
+            let detail = format!("Listed {} credential(s) via API for {}", credentials.len(), client);
+            list(session, &credentials, &detail)
+        }
+        ["v1", "search"] => {
+            let query = request.query_values("q").next().unwrap_or_default();
+            let credentials = search::search_credentials(session.vault.db()?.conn(), query)?;
+            let detail = format!("Searched via API for {}: {} match(es)", client, credentials.len());
+            list(session, &credentials, &detail)
+        }
+        ["v1", "credentials", id] => match reference(id) {
+            Some(id) => read(session, &id, client),
+            None => Ok(Response::error(400, "Malformed credential reference")),
+        },
+        ["v1", "credentials", id, "otp"] => match reference(id) {
+            Some(id) => otp(session, &id, client),
+            None => Ok(Response::error(400, "Malformed credential reference")),
+        },
+        _ => Ok(Response::error(404, "Unknown endpoint")),
+    }
+}
+
+fn list(session: &Session, credentials: &[Credential], detail: &str) -> Result<Response, Box<dyn std::error::Error>> {
+    session.log_audit(AuditAction::Read, None, Some(detail))?;
+    let summaries: Vec<Summary> = credentials.iter().map(Summary::from).collect();
+    Response::json(&summaries)
+}
+
+fn read(session: &Session, reference: &str, client: &str) -> Result<Response, Box<dyn std::error::Error>> {
+    let credentials = session.credentials(&[])?;
+    let cred = match find_credential(&credentials, reference) {
+        Ok(cred) => cred,
+        Err(e) => return Ok(Response::error(404, &e)),
+    };
+    let decrypted = decrypt_credential(session.vault.db()?.conn(), session.vault.dek()?, cred, true)?;
+    session.log_audit(AuditAction::Read, Some(cred), Some(&format!("Read via API for {}", client)))?;
+
+    Response::json(&Detail {
+        summary: Summary::from(cred),
+        secret: decrypted.secret.as_ref().map(|s| s.expose_secret()),
+        notes: decrypted.notes.as_ref().map(|s| s.expose_secret()),
+        fields: decrypted
+            .fields
+            .iter()
+            .map(|(label, value)| Field { label, value: value.expose_secret() })
+            .collect(),
+        has_otp: decrypted.totp_secret.is_some(),
+    })
+}
+
+fn otp(session: &Session, reference: &str, client: &str) -> Result<Response, Box<dyn std::error::Error>> {
+    let credentials = session.credentials(&[])?;
+    let cred = match find_credential(&credentials, reference) {
+        Ok(cred) => cred,
+        Err(e) => return Ok(Response::error(404, &e)),
+    };
+    let decrypted = decrypt_credential(session.vault.db()?.conn(), session.vault.dek()?, cred, true)?;
+    let Some(input) = decrypted.totp_secret.as_ref() else {
+        return Ok(Response::error(404, &format!("{} has no TOTP secret", cred.name)));
+    };
+    let secret = totp::TotpSecret::from_user_input(input.expose_secret(), &cred.name, "Vault")?;
+    let code = Zeroizing::new(totp::generate_totp(&secret)?);
+    session.log_audit(AuditAction::Read, Some(cred), Some(&format!("TOTP code read via API for {}", client)))?;
+
+    Response::json(&Otp { code: &code, remaining: totp::time_remaining(&secret) })
+}
+
+fn write_response(mut stream: &TcpStream, response: &Response) -> io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    };
+    let challenge = match response.status {
+        401 => "WWW-Authenticate: Bearer\r\n",
+        _ => "",
+    };
+    let head = format!(
+        "HTTP/1.1 {} {}\r\n{}Content-Type: application/json\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        response.status,
+        reason,
+        challenge,
+        response.body.len()
+    );
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(response.body.as_bytes())?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_request() {
+        let raw = "GET /v1/search?q=git+hub&tag=a%2Fb HTTP/1.1\r\nHost: 127.0.0.1:7878\r\nAuthorization: Bearer vault_abc\r\n\r\n";
+        let request = read_request(raw.as_bytes()).unwrap();
+        assert_eq!((request.method.as_str(), request.path.as_str()), ("GET", "/v1/search"));
+        assert_eq!(request.query_values("q").collect::<Vec<_>>(), ["git hub"]);
+        assert_eq!(request.query_values("tag").collect::<Vec<_>>(), ["a/b"]);
+        assert_eq!(request.token.as_deref().map(String::as_str), Some("vault_abc"));
+        assert!(is_loopback_host(request.host.as_deref(), 7878));
+
+        assert!(read_request("GET /v1/credentials HTTP/1.1\r\nHost: x\r\n".as_bytes()).is_none());
+        assert!(read_request("GET /v1/search?q=%zz HTTP/1.1\r\n\r\n".as_bytes()).is_none());
+        assert!(!is_loopback_host(Some("evil.example:7878"), 7878));
+        assert!(!is_loopback_host(Some("localhost:7879"), 7878));
+        assert_eq!(percent_decode("C%2B%2B+notes", true).as_deref(), Some("C++ notes"));
+        assert_eq!(percent_decode("a+b%2Fc", false).as_deref(), Some("a+b/c"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_api_round_trip() {
+        use std::os::unix::fs::PermissionsExt;
+        use tempfile::TempDir;
+
+        use crate::cli::password::PasswordSource;
+        use crate::db::{self, CredentialType};
+        use crate::vault::credential::create_credential;
+        use crate::vault::{Vault, VaultConfig};
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("vault.db");
+        let mut vault = Vault::new(VaultConfig::with_path(&path));
+        vault.initialize("correct horse").unwrap();
+        let conn = vault.db().unwrap().conn();
+        create_credential(
+            conn, vault.dek().unwrap(), "GitHub".to_string(), CredentialType::Password, "hunter2",
+            Some("alice".to_string()), None, vec!["dev".to_string()], None, Some("JBSWY3DPEHPK3PXP"), &[],
+        ).unwrap();
+        let token = api_tokens::create_token(conn, "nvim").unwrap();
+        vault.lock();
+
+        let key = dir.path().join("key");
+        std::fs::write(&key, "correct horse").unwrap();
+        std::fs::set_permissions(&key, std::fs::Permissions::from_mode(0o600)).unwrap();
+        let session = Session::unlock(&path, &PasswordSource::File(key)).unwrap();
+
+        let listener = bind(0).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let get = |target: &str, host: &str, token: &str| {
+            let mut client = TcpStream::connect(("127.0.0.1", port)).unwrap();
+            let request = format!("GET {} HTTP/1.1\r\nHost: {}\r\nAuthorization: Bearer {}\r\n\r\n", target, host, token);
+            client.write_all(request.as_bytes()).unwrap();
+            let (stream, _) = listener.accept().unwrap();
+            let served = handle_client(stream, &session, port).unwrap();
+            let mut reply = String::new();
+            client.read_to_string(&mut reply).unwrap();
+            let (head, body) = reply.split_once("\r\n\r\n").unwrap();
+            (head.split(' ').nth(1).unwrap().to_string(), body.to_string(), served)
+        };
+        let host = format!("localhost:{}", port);
+
+        let (status, body, served) = get("/v1/credentials?tag=dev", &host, &token);
+        assert_eq!((status.as_str(), served), ("200", true));
+        assert!(body.contains("\"name\":\"GitHub\"") && !body.contains("hunter2"));
+
+        let (status, body, _) = get("/v1/credentials/github", &host, &token);
+        assert_eq!(status, "200");
+        let detail: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!((detail["secret"].as_str(), detail["has_otp"].as_bool()), (Some("hunter2"), Some(true)));
+
+        let (status, body, _) = get("/v1/credentials/GitHub/otp", &host, &token);
+        assert_eq!(status, "200");
+        let otp: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(otp["code"].as_str().map(str::len), Some(6));
+
+        let (_, body, _) = get("/v1/search?q=git", &host, &token);
+        assert!(body.contains("GitHub"));
+        assert_eq!(get("/v1/credentials/missing", &host, &token).0, "404");
+        assert_eq!(get("/v1/credentials", &host, "vault_wrong"), ("401".to_string(), r#"{"error":"Missing or unknown API token"}"#.to_string(), false));
+        assert_eq!(get("/v1/credentials", "rebound.example", &token).0, "403");
+
+        let logs = db::get_all_audit_logs(session.vault.db().unwrap().conn()).unwrap();
+        let api_reads = logs.iter().filter(|l| l.details.as_deref().is_some_and(|d| d.contains("API for nvim"))).count();
+        assert_eq!(api_reads, 4);
+    }
+}
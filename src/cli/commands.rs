@@ -0,0 +1,256 @@
+//! Headless command implementations
+
+use std::io::IsTerminal;
+use std::path::Path;
+
+use crate::app::AppConfig;
+use crate::crypto::{generate_passphrase, generate_password, Wordlist};
+use crate::db::AuditAction;
+use crate::vault::aegis::{aegis_entries, create_aegis_vault, write_aegis_vault};
+use crate::vault::api_tokens;
+use crate::vault::audit;
+use crate::vault::bundle::{create_bundle, write_bundle};
+use crate::vault::credential::{decrypt_credential_data, decrypt_fields, decrypt_notes};
+use crate::vault::export::{credential_to_export, export_to_file, is_stdout, ExportData, ExportEncryption, ExportFormat, ExportKey};
+use crate::vault::pass::{pass_entries, write_pass_store};
+use crate::vault::sync::{self, SyncOutcome, SyncState};
+
+use super::password::{prompt_password, PasswordSource};
+use super::session::Session;
+use super::{Command, ExportArgs, GenerateArgs, GenerateMode, GlobalOptions, SyncArgs, TokenAction, USAGE};
+
+pub fn execute(global: &GlobalOptions, command: Command) -> Result<i32, Box<dyn std::error::Error>> {
+    match command {
+        Command::Export(args) => export(global, args),
+        Command::Verify => verify(global),
+        Command::Generate(args) => generate(args),
+        Command::Run(args) => super::run::run(global, args),
+        Command::Pick(args) => super::pick::pick(global, args),
+        Command::Agent(args) => super::agent::agent(global, args),
+        Command::Token(action) => token(global, action),
+        Command::Sync(args) => sync(global, args),
+        Command::Help => {
+            print!("{}", USAGE);
+            Ok(0)
+        }
+    }
+}
+
+fn export(global: &GlobalOptions, args: ExportArgs) -> Result<i32, Box<dyn std::error::Error>> {
+    match args.format {
+        ExportFormat::Bundle => return export_bundle(global, args),
+        ExportFormat::Aegis => return export_aegis(global, args),
+        ExportFormat::Pass => return export_pass(global, args),
+        _ => {}
+    }
+
+    // gpg and age write binary files
+    let binary = matches!(args.encryption, ExportEncryption::Gpg | ExportEncryption::Age);
+    if binary && is_stdout(&args.output) && std::io::stdout().is_terminal() {
+        return Err("refusing to write encrypted binary output to a terminal; redirect or pipe it".into());
+    }
+
+    let passphrase = match (args.encryption, &args.passphrase_file) {
+        (ExportEncryption::None, _) => None,
+        _ if !args.recipients.is_empty() => None,
+        (_, Some(path)) => Some(PasswordSource::File(path.clone()).read()?),
+        (_, None) => Some(prompt_password("Export passphrase: ")?),
+    };
+
+    let session = Session::unlock(&global.vault_path, &global.password)?;
+    let dek = session.vault.dek()?;
+
+    let mut exported = Vec::new();
+    for cred in session.credentials(&args.tags)? {
+        let secret = decrypt_credential_data(dek, &cred.id, &cred.encrypted_secret)?;
+        let notes = decrypt_notes(dek, &cred.id, cred.encrypted_notes.as_ref())?;
+        let fields = decrypt_fields(dek, &cred.id, cred.encrypted_fields.as_ref())?;
+        exported.push(credential_to_export(&cred, secret, notes, fields));
+    }
+
+    let count = exported.len();
+    let data = ExportData::new(exported);
+    let key = match passphrase.as_deref() {
+        _ if !args.recipients.is_empty() => Some(ExportKey::Recipients(&args.recipients)),
+        passphrase => passphrase.map(|p| ExportKey::Passphrase(p.as_str())),
+    };
+    export_to_file(&data, args.format, args.encryption, key, &args.output)?;
+
+    let detail = format!("Exported {} credential(s) to {} (CLI)", count, destination(&args.output));
+    session.log_audit(AuditAction::Export, None, Some(&detail))?;
+
+    if args.encryption == ExportEncryption::None {
+        eprintln!("warning: the export to {} is not encrypted", destination(&args.output));
+    }
+    eprintln!("Exported {} credential(s) to {}", count, destination(&args.output));
+    Ok(0)
+}
+
+/// Where an export went, for messages and the audit log
+fn destination(path: &Path) -> String {
+    match is_stdout(path) {
+        true => "stdout".to_string(),
+        false => path.display().to_string(),
+    }
+}
+
+fn export_bundle(global: &GlobalOptions, args: ExportArgs) -> Result<i32, Box<dyn std::error::Error>> {
+    let passphrase = match &args.passphrase_file {
+        Some(path) => PasswordSource::File(path.clone()).read()?,
+        None => prompt_password("Bundle passphrase: ")?,
+    };
+
+    let session = Session::unlock(&global.vault_path, &global.password)?;
+    let bundle = create_bundle(session.vault.db()?.conn(), session.vault.dek()?, &passphrase)?;
+    write_bundle(&bundle, &args.output)?;
+
+    let detail = format!(
+        "Exported vault bundle with {} credential(s) to {} (CLI)",
+        bundle.credential_count,
+        destination(&args.output)
+    );
+    session.log_audit(AuditAction::Export, None, Some(&detail))?;
+    eprintln!("Exported vault bundle with {} credential(s) to {}", bundle.credential_count, destination(&args.output));
+    Ok(0)
+}
+
+fn export_aegis(global: &GlobalOptions, args: ExportArgs) -> Result<i32, Box<dyn std::error::Error>> {
+    let passphrase = match &args.passphrase_file {
+        Some(path) => PasswordSource::File(path.clone()).read()?,
+        None => prompt_password("Aegis vault password: ")?,
+    };
+
+    let session = Session::unlock(&global.vault_path, &global.password)?;
+    let entries = aegis_entries(session.vault.dek()?, &session.credentials(&args.tags)?)?;
+    if entries.is_empty() {
+        return Err("no credentials with a TOTP secret to export".into());
+    }
+    let count = entries.len();
+    write_aegis_vault(&create_aegis_vault(entries, &passphrase)?, &args.output)?;
+
+    let detail = format!("Exported {} TOTP secret(s) to {} (Aegis, CLI)", count, destination(&args.output));
+    session.log_audit(AuditAction::Export, None, Some(&detail))?;
+    eprintln!("Exported {} TOTP secret(s) to {}", count, destination(&args.output));
+    Ok(0)
+}
+
+fn export_pass(global: &GlobalOptions, args: ExportArgs) -> Result<i32, Box<dyn std::error::Error>> {
+    let session = Session::unlock(&global.vault_path, &global.password)?;
+    let entries = pass_entries(session.vault.dek()?, &session.credentials(&args.tags)?)?;
+    let count = write_pass_store(&entries, &args.output)?;
+
+    let detail = format!("Exported {} credential(s) to pass store {} (CLI)", count, args.output.display());
+    session.log_audit(AuditAction::Export, None, Some(&detail))?;
+    eprintln!("Exported {} credential(s) to pass store {}", count, args.output.display());
+    Ok(0)
+}
+
+fn verify(global: &GlobalOptions) -> Result<i32, Box<dyn std::error::Error>> {
+    let session = Session::unlock(&global.vault_path, &global.password)?;
+    let audit_key = session.vault.keys()?.derive_audit_key()?;
+    let results = audit::verify_all_logs(session.vault.db()?.conn(), &audit_key)?;
+
+    let total = results.len();
+    let tampered = results.iter().filter(|(_, valid)| !valid).count();
+    if tampered == 0 {
+        println!("Audit OK: {} logs verified", total);
+        return Ok(0);
+    }
+
+    println!("Warning: {} of {} logs may be tampered!", tampered, total);
+    for (log, _) in results.iter().filter(|(_, valid)| !valid) {
+        println!("  #{} {} {}", log.id, log.timestamp.format("%Y-%m-%d %H:%M:%S"), log.action.as_str());
+    }
+    Ok(1)
+}
+
+fn generate(args: GenerateArgs) -> Result<i32, Box<dyn std::error::Error>> {
+    let wordlist = match &args.mode {
+        GenerateMode::Passphrase { wordlist, .. } => {
+            let path = wordlist.clone().or_else(|| AppConfig::load().ok().and_then(|c| c.wordlist));
+            load_wordlist(path.as_deref())?
+        }
+        GenerateMode::Password(_) => Wordlist::builtin(),
+    };
+    for _ in 0..args.count {
+        let value = match &args.mode {
+            GenerateMode::Password(policy) => generate_password(policy)?,
+            GenerateMode::Passphrase { options, .. } => generate_passphrase(options, &wordlist)?,
+        };
+        println!("{}", value);
+    }
+    Ok(0)
+}
+
+/// The wordlist file at `path`, or the built-in list without one
+fn load_wordlist(path: Option<&Path>) -> Result<Wordlist, Box<dyn std::error::Error>> {
+    let Some(path) = path else { return Ok(Wordlist::builtin()) };
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(Wordlist::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?)
+}
+
+fn token(global: &GlobalOptions, action: TokenAction) -> Result<i32, Box<dyn std::error::Error>> {
+    let session = Session::unlock(&global.vault_path, &global.password)?;
+    let conn = session.vault.db()?.conn();
+
+    match action {
+        TokenAction::Add(name) => {
+            let token = api_tokens::create_token(conn, &name)?;
+            session.log_audit(AuditAction::Update, None, Some(&format!("Created API token for {} (CLI)", name)))?;
+            println!("{}", token.as_str());
+            eprintln!("Token for {} created; it is not shown again", name);
+        }
+        TokenAction::List => {
+            let tokens = api_tokens::list_tokens(conn)?;
+            if tokens.is_empty() {
+                eprintln!("No API tokens; create one with: vault token add <NAME>");
+            }
+            for token in tokens {
+                println!("{}\tcreated {}", token.name, token.created_at.format("%Y-%m-%d %H:%M"));
+            }
+        }
+        TokenAction::Revoke(name) => {
+            if !api_tokens::revoke_token(conn, &name)? {
+                return Err(format!("no token named {}", name).into());
+            }
+            session.log_audit(AuditAction::Update, None, Some(&format!("Revoked API token for {} (CLI)", name)))?;
+            eprintln!("Revoked the token for {}", name);
+        }
+    }
+    Ok(0)
+}
+
+fn sync(global: &GlobalOptions, args: SyncArgs) -> Result<i32, Box<dyn std::error::Error>> {
+    let saved = SyncState::load(&global.vault_path)?;
+    let mut state = match (args.backend, saved) {
+        (Some(backend), Some(saved)) if saved.backend == backend => saved,
+        (Some(backend), _) => SyncState::new(backend),
+        (None, Some(saved)) => saved,
+        (None, None) => return Err("no sync backend configured; pass --folder, --webdav or --s3".into()),
+    };
+
+    let backend = state.backend.open()?;
+    let outcome = sync::sync(&global.vault_path, backend.as_ref(), &mut state)?;
+    state.save(&global.vault_path)?;
+
+    let remote = backend.describe();
+    match outcome {
+        SyncOutcome::UpToDate => eprintln!("Up to date with {}", remote),
+        SyncOutcome::Uploaded => eprintln!("Uploaded to {}", remote),
+        SyncOutcome::Downloaded => eprintln!("Downloaded from {}", remote),
+        SyncOutcome::Merged(report) if report.is_empty() => eprintln!("Merged with {} (no remote changes)", remote),
+        SyncOutcome::Merged(report) => eprintln!(
+            "Merged with {}: {} added, {} updated, {} deleted",
+            remote, report.added, report.updated, report.deleted
+        ),
+        SyncOutcome::LocalWon { snapshot } => {
+            eprintln!("Both copies changed; uploaded the newer local copy to {}", remote);
+            eprintln!("Previous remote copy saved to {}", snapshot.display());
+        }
+        SyncOutcome::RemoteWon { snapshot } => {
+            eprintln!("Both copies changed; kept the newer copy from {}", remote);
+            eprintln!("Previous local copy saved to {}", snapshot.display());
+        }
+    }
+    Ok(0)
+}
@@ -0,0 +1,745 @@
+//! Headless command line interface
+//!
+//! `vault <command> [options]` runs a single operation without the TUI so it
+//! can be used from scripts and cron jobs. Running `vault` with no command
+//! (or just a vault path) starts the TUI as before.
+
+pub mod agent;
+mod api;
+mod commands;
+pub mod password;
+mod pick;
+mod run;
+mod session;
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+use crate::crypto::{PassphraseOptions, PasswordPolicy, MAX_PASSPHRASE_WORDS};
+use crate::vault::export::{is_stdout, ExportEncryption, ExportFormat};
+use crate::vault::sync::BackendConfig;
+
+use agent::{AgentAction, AgentArgs};
+use password::PasswordSource;
+use pick::PickArgs;
+use run::{EnvBinding, RunArgs, SecretField};
+
+const COMMANDS: &[&str] = &["export", "verify", "generate", "gen", "run", "pick", "agent", "token", "sync", "help", "--help", "-h"];
+
+/// Whether the arguments name a headless command rather than a vault path
+pub fn is_cli_invocation(args: &[String]) -> bool {
+    args.first().is_some_and(|a| COMMANDS.contains(&a.as_str()))
+}
+
+/// Run a headless command and return the process exit code
+pub fn run(args: Vec<String>, default_vault: PathBuf) -> i32 {
+    let (global, command) = match parse(args, default_vault) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("vault: {}", e);
+            eprintln!("Run 'vault help' for usage.");
+            return 2;
+        }
+    };
+
+    match commands::execute(&global, command) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("vault: {}", e);
+            1
+        }
+    }
+}
+
+/// Options shared by every command
+pub struct GlobalOptions {
+    pub vault_path: PathBuf,
+    pub password: PasswordSource,
+}
+
+pub enum Command {
+    Export(ExportArgs),
+    Verify,
+    Generate(GenerateArgs),
+    Run(RunArgs),
+    Pick(PickArgs),
+    Agent(AgentArgs),
+    Token(TokenAction),
+    Sync(SyncArgs),
+    Help,
+}
+
+pub struct ExportArgs {
+    pub output: PathBuf,
+    pub format: ExportFormat,
+    pub encryption: ExportEncryption,
+    pub passphrase_file: Option<PathBuf>,
+    /// GPG key IDs or age public keys to encrypt to instead of a passphrase
+    pub recipients: Vec<String>,
+    pub tags: Vec<String>,
+}
+
+pub enum GenerateMode {
+    Password(PasswordPolicy),
+    /// `wordlist` overrides the configured one
+    Passphrase { options: PassphraseOptions, wordlist: Option<PathBuf> },
+}
+
+/// `vault token add|list|revoke`, for clients of the agent's HTTP API
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenAction {
+    Add(String),
+    List,
+    Revoke(String),
+}
+
+pub struct SyncArgs {
+    /// New backend to use; None reuses the one from the last sync
+    pub backend: Option<BackendConfig>,
+}
+
+pub struct GenerateArgs {
+    pub mode: GenerateMode,
+    pub count: usize,
+}
+
+struct Args {
+    args: VecDeque<String>,
+}
+
+impl Args {
+    fn next(&mut self) -> Option<String> {
+        self.args.pop_front()
+    }
+
+    fn value(&mut self, flag: &str) -> Result<String, String> {
+        self.args.pop_front().ok_or_else(|| format!("{} requires a value", flag))
+    }
+}
+
+fn parse(args: Vec<String>, default_vault: PathBuf) -> Result<(GlobalOptions, Command), String> {
+    let mut args = Args { args: args.into() };
+    let name = args.next().unwrap_or_default();
+    let mut vault_path = default_vault;
+    let mut password = None;
+
+    let command = match name.as_str() {
+        "export" => Command::Export(parse_export(&mut args, &mut vault_path, &mut password)?),
+        "verify" => {
+            parse_globals_only(&mut args, &mut vault_path, &mut password)?;
+            Command::Verify
+        }
+        "generate" | "gen" => Command::Generate(parse_generate(&mut args)?),
+        "run" => Command::Run(parse_run(&mut args, &mut vault_path, &mut password)?),
+        "pick" => Command::Pick(parse_pick(&mut args, &mut vault_path, &mut password)?),
+        "agent" => Command::Agent(parse_agent(&mut args, &mut vault_path, &mut password)?),
+        "token" => Command::Token(parse_token(&mut args, &mut vault_path, &mut password)?),
+        "sync" => Command::Sync(parse_sync(&mut args, &mut vault_path, &mut password)?),
+        _ => Command::Help,
+    };
+
+    let global = GlobalOptions { vault_path, password: PasswordSource::resolve(password) };
+    Ok((global, command))
+}
+
+/// Consume a global flag if `arg` is one
+fn parse_global_flag(
+    arg: &str,
+    args: &mut Args,
+    vault_path: &mut PathBuf,
+    password: &mut Option<PasswordSource>,
+) -> Result<bool, String> {
+    let source = match arg {
+        "--vault" => {
+            *vault_path = PathBuf::from(args.value(arg)?);
+            return Ok(true);
+        }
+        "--password-stdin" => PasswordSource::Stdin,
+        "--password-fd" => {
+            let fd = args.value(arg)?;
+            PasswordSource::Fd(fd.parse().map_err(|_| format!("invalid file descriptor: {}", fd))?)
+        }
+        "--password-file" => PasswordSource::File(PathBuf::from(args.value(arg)?)),
+        "--password-agent" => PasswordSource::Agent(PathBuf::from(args.value(arg)?)),
+        _ => return Ok(false),
+    };
+
+    if password.replace(source).is_some() {
+        return Err("only one password source may be given".into());
+    }
+    Ok(true)
+}
+
+fn parse_globals_only(
+    args: &mut Args,
+    vault_path: &mut PathBuf,
+    password: &mut Option<PasswordSource>,
+) -> Result<(), String> {
+    while let Some(arg) = args.next() {
+        if !parse_global_flag(&arg, args, vault_path, password)? {
+            return Err(format!("unexpected argument: {}", arg));
+        }
+    }
+    Ok(())
+}
+
+fn parse_export(
+    args: &mut Args,
+    vault_path: &mut PathBuf,
+    password: &mut Option<PasswordSource>,
+) -> Result<ExportArgs, String> {
+    let mut output = None;
+    let mut format = ExportFormat::Json;
+    let mut encryption = ExportEncryption::None;
+    let mut passphrase_file = None;
+    let mut recipients = Vec::new();
+    let mut tags = Vec::new();
+
+    while let Some(arg) = args.next() {
+        if parse_global_flag(&arg, args, vault_path, password)? {
+            continue;
+        }
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(PathBuf::from(args.value(&arg)?)),
+            "--format" => format = parse_export_format(&args.value(&arg)?)?,
+            "--encrypt" => encryption = parse_export_encryption(&args.value(&arg)?)?,
+            "--passphrase-file" => passphrase_file = Some(PathBuf::from(args.value(&arg)?)),
+            "--recipient" => recipients.push(args.value(&arg)?),
+            "--tag" => tags.push(args.value(&arg)?),
+            other => return Err(format!("unexpected argument: {}", other)),
+        }
+    }
+
+    let output = output.ok_or("export requires --output <path>")?;
+    if format == ExportFormat::Bundle && encryption != ExportEncryption::None {
+        return Err("bundles are always encrypted; drop --encrypt".into());
+    }
+    if format == ExportFormat::Aegis && encryption != ExportEncryption::None {
+        return Err("Aegis vaults are always encrypted; drop --encrypt".into());
+    }
+    if format == ExportFormat::Pass && is_stdout(&output) {
+        return Err("a pass store is a directory; it can't be written to stdout".into());
+    }
+    if format == ExportFormat::Pass && (encryption != ExportEncryption::None || passphrase_file.is_some()) {
+        return Err("pass stores are encrypted to their .gpg-id keys; drop --encrypt and --passphrase-file".into());
+    }
+    let takes_recipients = matches!(encryption, ExportEncryption::Gpg | ExportEncryption::Age);
+    if !recipients.is_empty() && (!takes_recipients || passphrase_file.is_some()) {
+        return Err("--recipient needs --encrypt gpg or age, and no --passphrase-file".into());
+    }
+    if format == ExportFormat::Bundle && !tags.is_empty() {
+        return Err("bundles hold the whole vault; drop --tag".into());
+    }
+    Ok(ExportArgs { output, format, encryption, passphrase_file, recipients, tags })
+}
+
+fn parse_export_format(value: &str) -> Result<ExportFormat, String> {
+    match value {
+        "json" => Ok(ExportFormat::Json),
+        "text" | "txt" => Ok(ExportFormat::Text),
+        "bundle" => Ok(ExportFormat::Bundle),
+        "aegis" => Ok(ExportFormat::Aegis),
+        "pass" => Ok(ExportFormat::Pass),
+        other => Err(format!("unknown format: {} (expected json, text, bundle, aegis or pass)", other)),
+    }
+}
+
+fn parse_export_encryption(value: &str) -> Result<ExportEncryption, String> {
+    match value {
+        "none" => Ok(ExportEncryption::None),
+        "gpg" => Ok(ExportEncryption::Gpg),
+        "age" => Ok(ExportEncryption::Age),
+        "internal" => Ok(ExportEncryption::Internal),
+        other => Err(format!("unknown encryption: {} (expected none, gpg, age or internal)", other)),
+    }
+}
+
+fn parse_number(flag: &str, value: &str) -> Result<usize, String> {
+    match value.parse() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("{} expects a positive number, got {}", flag, value)),
+    }
+}
+
+fn parse_generate(args: &mut Args) -> Result<GenerateArgs, String> {
+    let mut policy = PasswordPolicy::default();
+    let mut passphrase = false;
+    let mut options = PassphraseOptions::default();
+    let mut wordlist = None;
+    let mut count = 1;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-l" | "--length" => policy.length = parse_number(&arg, &args.value(&arg)?)?,
+            "--no-upper" => policy.uppercase = false,
+            "--no-lower" => policy.lowercase = false,
+            "--no-digits" => policy.digits = false,
+            "--no-symbols" => policy.symbols = false,
+            "--symbols" => policy.custom_symbols = Some(args.value(&arg)?),
+            "--no-ambiguous" => policy.exclude_ambiguous = true,
+            "--pronounceable" => policy.pronounceable = true,
+            "-p" | "--passphrase" => passphrase = true,
+            "-w" | "--words" => {
+                options.words = parse_number(&arg, &args.value(&arg)?)?;
+                passphrase = true;
+            }
+            "--separator" => {
+                options.separator = args.value(&arg)?;
+                passphrase = true;
+            }
+            "--capitalize" => {
+                options.capitalize = true;
+                passphrase = true;
+            }
+            "--wordlist" => {
+                wordlist = Some(PathBuf::from(args.value(&arg)?));
+                passphrase = true;
+            }
+            "-n" | "--count" => count = parse_number(&arg, &args.value(&arg)?)?,
+            other => return Err(format!("unexpected argument: {}", other)),
+        }
+    }
+
+    if options.words > MAX_PASSPHRASE_WORDS {
+        return Err(format!("--words must be at most {}", MAX_PASSPHRASE_WORDS));
+    }
+
+    let mode = if passphrase {
+        GenerateMode::Passphrase { options, wordlist }
+    } else {
+        GenerateMode::Password(policy)
+    };
+    Ok(GenerateArgs { mode, count })
+}
+
+/// Options up to `--` (or the first non-option), then the command to run
+fn parse_run(
+    args: &mut Args,
+    vault_path: &mut PathBuf,
+    password: &mut Option<PasswordSource>,
+) -> Result<RunArgs, String> {
+    let mut bindings = Vec::new();
+    let mut command = Vec::new();
+
+    while let Some(arg) = args.next() {
+        if parse_global_flag(&arg, args, vault_path, password)? {
+            continue;
+        }
+        match arg.as_str() {
+            "-e" | "--env" => bindings.push(EnvBinding::parse(&args.value(&arg)?)?),
+            "--" => break,
+            flag if flag.starts_with('-') => return Err(format!("unexpected argument: {}", flag)),
+            _ => {
+                command.push(arg);
+                break;
+            }
+        }
+    }
+    command.extend(args.args.drain(..));
+
+    if bindings.is_empty() {
+        return Err("run requires at least one --env VAR=CREDENTIAL".into());
+    }
+    if command.is_empty() {
+        return Err("run requires a command after --".into());
+    }
+    Ok(RunArgs { bindings, command })
+}
+
+fn parse_pick(
+    args: &mut Args,
+    vault_path: &mut PathBuf,
+    password: &mut Option<PasswordSource>,
+) -> Result<PickArgs, String> {
+    let mut pick = PickArgs { field: SecretField::Secret, menu: None, list_only: false };
+
+    while let Some(arg) = args.next() {
+        if parse_global_flag(&arg, args, vault_path, password)? {
+            continue;
+        }
+        match arg.as_str() {
+            "--list" => pick.list_only = true,
+            "--menu" => pick.menu = Some(args.value(&arg)?),
+            "-f" | "--field" => {
+                let value = args.value(&arg)?;
+                pick.field = SecretField::parse(&value).ok_or_else(|| format!("unknown field: {}", value))?;
+            }
+            other => return Err(format!("unexpected argument: {}", other)),
+        }
+    }
+    Ok(pick)
+}
+
+fn parse_agent(
+    args: &mut Args,
+    vault_path: &mut PathBuf,
+    password: &mut Option<PasswordSource>,
+) -> Result<AgentArgs, String> {
+    let mut agent = AgentArgs { action: AgentAction::Start, socket: None, timeout: agent::DEFAULT_TIMEOUT, http: None };
+
+    while let Some(arg) = args.next() {
+        if parse_global_flag(&arg, args, vault_path, password)? {
+            continue;
+        }
+        match arg.as_str() {
+            "--socket" => agent.socket = Some(PathBuf::from(args.value(&arg)?)),
+            "--timeout" => {
+                let seconds = parse_number(&arg, &args.value(&arg)?)?;
+                agent.timeout = std::time::Duration::from_secs(seconds as u64);
+            }
+            "--http" => {
+                let port = args.value(&arg)?;
+                let port = port.parse().ok().filter(|&p| p > 0).ok_or_else(|| format!("--http expects a port, got {}", port))?;
+                agent.http = Some(port);
+            }
+            "--status" => agent.action = AgentAction::Status,
+            "--stop" => agent.action = AgentAction::Stop,
+            other => return Err(format!("unexpected argument: {}", other)),
+        }
+    }
+    Ok(agent)
+}
+
+/// `add <NAME>`, `list` or `revoke <NAME>`, with global flags anywhere
+fn parse_token(
+    args: &mut Args,
+    vault_path: &mut PathBuf,
+    password: &mut Option<PasswordSource>,
+) -> Result<TokenAction, String> {
+    let mut words = Vec::new();
+    while let Some(arg) = args.next() {
+        if parse_global_flag(&arg, args, vault_path, password)? {
+            continue;
+        }
+        if arg.starts_with('-') {
+            return Err(format!("unexpected argument: {}", arg));
+        }
+        words.push(arg);
+    }
+
+    match words.as_slice() {
+        [action, name] if action == "add" => Ok(TokenAction::Add(name.clone())),
+        [action] if action == "list" => Ok(TokenAction::List),
+        [action, name] if action == "revoke" => Ok(TokenAction::Revoke(name.clone())),
+        _ => Err("token expects add <NAME>, list or revoke <NAME>".into()),
+    }
+}
+
+fn parse_sync(
+    args: &mut Args,
+    vault_path: &mut PathBuf,
+    password: &mut Option<PasswordSource>,
+) -> Result<SyncArgs, String> {
+    let mut backend = None;
+    let mut user = None;
+    let mut region = None;
+
+    while let Some(arg) = args.next() {
+        if parse_global_flag(&arg, args, vault_path, password)? {
+            continue;
+        }
+        let chosen = match arg.as_str() {
+            "--folder" => BackendConfig::Folder { path: PathBuf::from(args.value(&arg)?) },
+            "--webdav" => BackendConfig::WebDav { url: args.value(&arg)?, user: None },
+            "--s3" => BackendConfig::S3 { url: args.value(&arg)?, region: String::new() },
+            "--user" => {
+                user = Some(args.value(&arg)?);
+                continue;
+            }
+            "--region" => {
+                region = Some(args.value(&arg)?);
+                continue;
+            }
+            other => return Err(format!("unexpected argument: {}", other)),
+        };
+        if backend.replace(chosen).is_some() {
+            return Err("only one of --folder, --webdav or --s3 may be given".into());
+        }
+    }
+
+    match &mut backend {
+        Some(BackendConfig::WebDav { user: webdav_user, .. }) => *webdav_user = user,
+        Some(BackendConfig::S3 { region: s3_region, .. }) => {
+            *s3_region = region.ok_or("--s3 requires --region")?;
+        }
+        _ if user.is_some() => return Err("--user only applies to --webdav".into()),
+        _ if region.is_some() => return Err("--region only applies to --s3".into()),
+        _ => {}
+    }
+    Ok(SyncArgs { backend })
+}
+
+pub const USAGE: &str = "\
+Usage: vault [VAULT_PATH]                 Start the TUI
+       vault <COMMAND> [OPTIONS]
+
+Commands:
+  export    Export credentials to a file
+  verify    Verify audit log integrity
+  generate  Generate passwords or passphrases (no vault needed)
+  run       Run a command with credentials injected as environment variables
+  pick      Choose a credential from a menu and copy it to the clipboard
+  agent     Keep the vault unlocked for later commands and the TUI
+  token     Manage client tokens for the agent's HTTP API
+  sync      Mirror the vault to a folder, WebDAV or S3 (no password needed)
+  help      Show this message
+
+Password options (default: a running agent, then a terminal prompt):
+  --password-stdin          Read the master password from stdin
+  --password-fd <FD>        Read the master password from file descriptor FD
+  --password-file <PATH>    Read the master password from a key file (mode 600)
+  --password-agent <SOCK>   Get the key from the agent listening on SOCK
+
+Global options:
+  --vault <PATH>            Vault database to open
+
+Export options:
+  -o, --output <PATH>       Output file, - for stdout, or the store directory
+                            for pass (required)
+  --format <json|text|bundle|aegis|pass>
+                            Output format (default: json); bundle writes the
+                            whole vault encrypted with a passphrase of its own,
+                            aegis the TOTP secrets as an Aegis Authenticator vault,
+                            pass one file per credential into a password-store
+                            set up with `pass init`
+  --encrypt <none|gpg|age|internal>
+                            Encrypt the export (default: none); internal needs
+                            no gpg or age and is opened again by import
+  --passphrase-file <PATH>  Encryption passphrase for gpg/age/internal, the
+                            bundle or the Aegis vault
+  --recipient <KEY>         Encrypt to a GPG key ID or an age public key
+                            (age1...) instead of a passphrase; needs
+                            --encrypt gpg or age (repeatable)
+  --tag <TAG>               Only export credentials with this tag (repeatable);
+                            --tag -TAG leaves out credentials with it
+
+Generate options:
+  -l, --length <N>          Password length (default: 20)
+  --no-upper, --no-lower, --no-digits, --no-symbols
+                            Drop a character class
+  --symbols <CHARS>         Use CHARS as the symbol set
+  --no-ambiguous            Exclude look-alike characters (0 O 1 l I |)
+  --pronounceable           Build the password from syllables, digits and
+                            symbols at the end
+  -p, --passphrase          Generate a passphrase instead of a password
+  -w, --words <N>           Words per passphrase (default: 5)
+  --separator <SEP>         Passphrase word separator (default: -)
+  --capitalize              Capitalize each passphrase word
+  --wordlist <PATH>         Draw passphrase words from PATH, one per line or
+                            EFF dice format (default: the wordlist setting,
+                            then a small built-in list)
+  -n, --count <N>           Number of results (default: 1)
+
+Run options:
+  vault run -e VAR=CREDENTIAL[:FIELD] ... -- <COMMAND> [ARGS...]
+  -e, --env <VAR=CRED>      Export a credential (name or ID) as VAR (repeatable)
+                            FIELD is secret (default), username, url, notes or totp
+
+Pick options:
+  --menu <CMD>              Menu command, e.g. 'rofi -dmenu' or fzf (default: stdin/stdout)
+  -f, --field <FIELD>       Field to copy (default: secret)
+  --list                    Only print credential labels
+
+Agent options:
+  --socket <PATH>           Socket path (default: $VAULT_AGENT_SOCK, then
+                            $XDG_RUNTIME_DIR/vault/agent.sock)
+  --timeout <SECS>          Lock and exit after SECS idle (default: 900)
+  --http <PORT>             Also serve the HTTP API on 127.0.0.1:PORT
+  --status                  Show which vault a running agent holds
+  --stop                    Lock and stop a running agent
+
+Token options:
+  vault token add <NAME>    Create a token for an API client and print it once
+  vault token list          List the clients with a token
+  vault token revoke <NAME> Revoke a client's token
+
+Sync options (remembered after the first sync):
+  --folder <PATH>           Sync with a file in a local or mounted directory
+  --webdav <URL>            Sync with a WebDAV file, e.g. on Nextcloud
+  --user <NAME>             WebDAV user; password from $VAULT_SYNC_PASSWORD
+  --s3 <URL>                Sync with an S3 object (https://host/bucket/key);
+                            keys from $AWS_ACCESS_KEY_ID/$AWS_SECRET_ACCESS_KEY
+  --region <REGION>         S3 region (required with --s3)
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_is_cli_invocation() {
+        assert!(is_cli_invocation(&args("export -o out.json")));
+        assert!(is_cli_invocation(&args("--help")));
+        assert!(!is_cli_invocation(&args("/tmp/other.db")));
+        assert!(!is_cli_invocation(&[]));
+    }
+
+    #[test]
+    fn test_parse_export() {
+        let (global, command) = parse(
+            args("export --vault /tmp/v.db --password-fd 3 -o out.txt --format text --tag work --tag dev"),
+            PathBuf::from("default.db"),
+        )
+        .unwrap();
+
+        assert_eq!(global.vault_path, PathBuf::from("/tmp/v.db"));
+        assert_eq!(global.password, PasswordSource::Fd(3));
+        let Command::Export(export) = command else { panic!("expected export") };
+        assert_eq!(export.output, PathBuf::from("out.txt"));
+        assert_eq!(export.format, ExportFormat::Text);
+        assert_eq!(export.encryption, ExportEncryption::None);
+        assert_eq!(export.tags, vec!["work", "dev"]);
+    }
+
+    #[test]
+    fn test_parse_generate() {
+        let default = PathBuf::from("default.db");
+
+        let (_, command) = parse(args("generate -l 32 --no-symbols --pronounceable -n 3"), default.clone()).unwrap();
+        let Command::Generate(generate) = command else { panic!("expected generate") };
+        let GenerateMode::Password(policy) = generate.mode else { panic!("expected password") };
+        assert_eq!(policy.length, 32);
+        assert!(!policy.symbols);
+        assert!(policy.pronounceable);
+        assert!(policy.digits);
+        assert_eq!(generate.count, 3);
+
+        let (_, command) = parse(args("gen --words 6 --separator . --capitalize --wordlist eff.txt"), default.clone()).unwrap();
+        let Command::Generate(generate) = command else { panic!("expected generate") };
+        let GenerateMode::Passphrase { options, wordlist } = generate.mode else { panic!("expected passphrase") };
+        assert_eq!(options.words, 6);
+        assert_eq!(options.separator, ".");
+        assert!(options.capitalize);
+        assert_eq!(wordlist, Some(PathBuf::from("eff.txt")));
+
+        assert!(parse(args("generate -l 0"), default.clone()).is_err());
+        assert!(parse(args("generate --words 100000"), default.clone()).is_err());
+        assert!(parse(args("generate --password-stdin"), default).is_err());
+    }
+
+    #[test]
+    fn test_parse_run() {
+        let default = PathBuf::from("default.db");
+
+        let (global, command) = parse(
+            args("run --password-stdin --env AWS_SECRET=aws-prod -e USER=db:username -- terraform apply -auto-approve"),
+            default.clone(),
+        )
+        .unwrap();
+        assert_eq!(global.password, PasswordSource::Stdin);
+        let Command::Run(run) = command else { panic!("expected run") };
+        assert_eq!(run.bindings.len(), 2);
+        assert_eq!(run.bindings[1].var, "USER");
+        assert_eq!(run.command, vec!["terraform", "apply", "-auto-approve"]);
+
+        let (_, command) = parse(args("run -e TOKEN=gh env"), default.clone()).unwrap();
+        let Command::Run(run) = command else { panic!("expected run") };
+        assert_eq!(run.command, vec!["env"]);
+
+        assert!(parse(args("run -- env"), default.clone()).is_err());
+        assert!(parse(args("run -e TOKEN=gh --"), default).is_err());
+    }
+
+    #[test]
+    fn test_parse_pick() {
+        let default = PathBuf::from("default.db");
+
+        let (_, command) = parse(args("pick --menu fzf --field username"), default.clone()).unwrap();
+        let Command::Pick(pick) = command else { panic!("expected pick") };
+        assert_eq!(pick.menu.as_deref(), Some("fzf"));
+        assert_eq!(pick.field, SecretField::Username);
+        assert!(!pick.list_only);
+
+        let (_, command) = parse(args("pick --list"), default.clone()).unwrap();
+        let Command::Pick(pick) = command else { panic!("expected pick") };
+        assert!(pick.list_only);
+        assert_eq!(pick.field, SecretField::Secret);
+
+        assert!(parse(args("pick --field pin"), default.clone()).is_err());
+        assert!(parse(args("pick --menu"), default).is_err());
+    }
+
+    #[test]
+    fn test_parse_agent() {
+        let default = PathBuf::from("default.db");
+
+        let (_, command) = parse(args("agent --socket /tmp/a.sock --timeout 60"), default.clone()).unwrap();
+        let Command::Agent(agent) = command else { panic!("expected agent") };
+        assert_eq!(agent.action, AgentAction::Start);
+        assert_eq!(agent.socket, Some(PathBuf::from("/tmp/a.sock")));
+        assert_eq!(agent.timeout, std::time::Duration::from_secs(60));
+
+        let (_, command) = parse(args("agent --stop"), default.clone()).unwrap();
+        let Command::Agent(agent) = command else { panic!("expected agent") };
+        assert_eq!(agent.action, AgentAction::Stop);
+
+        let (_, command) = parse(args("agent --http 7878"), default.clone()).unwrap();
+        let Command::Agent(agent) = command else { panic!("expected agent") };
+        assert_eq!(agent.http, Some(7878));
+
+        assert!(parse(args("agent --timeout 0"), default.clone()).is_err());
+        assert!(parse(args("agent --http 70000"), default).is_err());
+    }
+
+    #[test]
+    fn test_parse_token() {
+        let default = PathBuf::from("default.db");
+
+        let (global, command) = parse(args("token add home-assistant --password-stdin"), default.clone()).unwrap();
+        assert_eq!(global.password, PasswordSource::Stdin);
+        let Command::Token(action) = command else { panic!("expected token") };
+        assert_eq!(action, TokenAction::Add("home-assistant".into()));
+
+        let (_, command) = parse(args("token list"), default.clone()).unwrap();
+        let Command::Token(action) = command else { panic!("expected token") };
+        assert_eq!(action, TokenAction::List);
+
+        assert!(parse(args("token"), default.clone()).is_err());
+        assert!(parse(args("token add"), default.clone()).is_err());
+        assert!(parse(args("token revoke a b"), default).is_err());
+    }
+
+    #[test]
+    fn test_parse_sync() {
+        let default = PathBuf::from("default.db");
+
+        let (_, command) = parse(args("sync --webdav https://dav/vault.db --user me"), default.clone()).unwrap();
+        let Command::Sync(sync) = command else { panic!("expected sync") };
+        assert_eq!(
+            sync.backend,
+            Some(BackendConfig::WebDav { url: "https://dav/vault.db".into(), user: Some("me".into()) })
+        );
+
+        let (_, command) = parse(args("sync --region eu-west-1 --s3 https://s3/b/v.db"), default.clone()).unwrap();
+        let Command::Sync(sync) = command else { panic!("expected sync") };
+        assert_eq!(sync.backend, Some(BackendConfig::S3 { url: "https://s3/b/v.db".into(), region: "eu-west-1".into() }));
+
+        let (_, command) = parse(args("sync"), default.clone()).unwrap();
+        let Command::Sync(sync) = command else { panic!("expected sync") };
+        assert_eq!(sync.backend, None);
+
+        assert!(parse(args("sync --s3 https://s3/b/v.db"), default.clone()).is_err());
+        assert!(parse(args("sync --folder a --webdav b"), default.clone()).is_err());
+        assert!(parse(args("sync --folder a --user me"), default).is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let default = PathBuf::from("default.db");
+        assert!(parse(args("export"), default.clone()).is_err());
+        assert!(parse(args("export -o x --format xml"), default.clone()).is_err());
+        assert!(parse(args("export -o x --format bundle --encrypt gpg"), default.clone()).is_err());
+        assert!(parse(args("export -o x --format bundle --tag work"), default.clone()).is_err());
+        assert!(parse(args("export -o x --format aegis --encrypt age"), default.clone()).is_err());
+        assert!(parse(args("export -o x --format pass --encrypt gpg"), default.clone()).is_err());
+        assert!(parse(args("export -o - --format pass"), default.clone()).is_err());
+        assert!(parse(args("export -o x --encrypt internal --recipient age1abc"), default.clone()).is_err());
+        assert!(parse(args("verify --password-stdin --password-file k"), default.clone()).is_err());
+        assert!(parse(args("verify --password-fd abc"), default.clone()).is_err());
+        assert!(parse(args("verify extra"), default).is_err());
+    }
+}
@@ -0,0 +1,173 @@
+//! Master password sources for headless commands.
+//!
+//! The password can come from stdin, an inherited file descriptor or a key
+//! file, so scripts never need it on the command line. A running `vault
+//! agent` skips the password entirely. Without any of those an interactive
+//! prompt is shown on the terminal.
+
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use zeroize::Zeroizing;
+
+use crate::input::{handle_text_key, SecureTextBuffer, TextEditing};
+
+/// Environment variable naming the agent socket
+pub const AGENT_SOCKET_ENV: &str = "VAULT_AGENT_SOCK";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PasswordSource {
+    /// Prompt on the controlling terminal
+    Prompt,
+    /// First line of standard input
+    Stdin,
+    /// First line read from an inherited file descriptor
+    Fd(i32),
+    /// First line of a key file
+    File(PathBuf),
+    /// Socket of a running `vault agent`, which hands out the master key
+    Agent(PathBuf),
+}
+
+impl PasswordSource {
+    /// Explicit source, else a running agent, else an interactive prompt
+    pub fn resolve(explicit: Option<PasswordSource>) -> Self {
+        explicit
+            .or_else(|| super::agent::running_socket().map(Self::Agent))
+            .unwrap_or(Self::Prompt)
+    }
+
+    pub fn read(&self) -> io::Result<Zeroizing<String>> {
+        let raw = match self {
+            Self::Prompt => return prompt_password("Master password: "),
+            Self::Stdin => read_all(io::stdin().lock())?,
+            Self::Fd(fd) => read_fd(*fd)?,
+            Self::File(path) => read_key_file(path)?,
+            Self::Agent(_) => {
+                return Err(io::Error::new(io::ErrorKind::Unsupported, "the agent provides a key, not a password"));
+            }
+        };
+        Ok(first_line(&raw))
+    }
+}
+
+fn read_all(mut reader: impl Read) -> io::Result<Zeroizing<String>> {
+    let mut content = Zeroizing::new(String::new());
+    reader.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+/// Strip everything from the first line break on
+fn first_line(raw: &str) -> Zeroizing<String> {
+    let line = raw.split('\n').next().unwrap_or("");
+    Zeroizing::new(line.strip_suffix('\r').unwrap_or(line).to_string())
+}
+
+#[cfg(unix)]
+fn read_fd(fd: i32) -> io::Result<Zeroizing<String>> {
+    use std::os::fd::FromRawFd;
+
+    if fd <= 2 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "password fd must be 3 or higher (use --password-stdin for stdin)"));
+    }
+    // Ownership of the descriptor is handed to us by the parent process
+    let file = unsafe { std::fs::File::from_raw_fd(fd) };
+    read_all(file)
+}
+
+#[cfg(not(unix))]
+fn read_fd(_fd: i32) -> io::Result<Zeroizing<String>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "--password-fd is only supported on Unix"))
+}
+
+fn read_key_file(path: &Path) -> io::Result<Zeroizing<String>> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(path)?.permissions().mode();
+        if mode & 0o077 != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("{} is accessible by other users (chmod 600 it)", path.display()),
+            ));
+        }
+    }
+    read_all(std::fs::File::open(path)?)
+}
+
+/// Read a password from the terminal without echoing it
+pub fn prompt_password(prompt: &str) -> io::Result<Zeroizing<String>> {
+    if !io::stdin().is_terminal() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "no terminal for password prompt; use --password-stdin, --password-fd, --password-file or --password-agent",
+        ));
+    }
+
+    eprint!("{}", prompt);
+    io::stderr().flush()?;
+
+    enable_raw_mode()?;
+    let result = read_hidden_line();
+    disable_raw_mode()?;
+    eprintln!();
+
+    result
+}
+
+fn read_hidden_line() -> io::Result<Zeroizing<String>> {
+    let mut buffer = SecureTextBuffer::new();
+    loop {
+        let Event::Key(key) = event::read()? else { continue };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match (key.code, key.modifiers) {
+            (KeyCode::Enter, _) => return Ok(Zeroizing::new(buffer.content().to_string())),
+            (KeyCode::Esc, _) | (KeyCode::Char('c'), KeyModifiers::CONTROL) => {
+                return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+            }
+            (code, mods) => {
+                handle_text_key(&mut buffer, code, mods);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_first_line() {
+        assert_eq!(first_line("hunter2\n").as_str(), "hunter2");
+        assert_eq!(first_line("hunter2\r\nignored").as_str(), "hunter2");
+        assert_eq!(first_line(" spaced ").as_str(), " spaced ");
+        assert_eq!(first_line("").as_str(), "");
+    }
+
+    #[test]
+    fn test_resolve_explicit_wins() {
+        let source = PasswordSource::resolve(Some(PasswordSource::Stdin));
+        assert_eq!(source, PasswordSource::Stdin);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_key_file_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("key");
+        std::fs::write(&path, "secret\n").unwrap();
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(PasswordSource::File(path.clone()).read().is_err());
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+        assert_eq!(PasswordSource::File(path).read().unwrap().as_str(), "secret");
+    }
+}
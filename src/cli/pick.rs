@@ -0,0 +1,178 @@
+//! `vault pick` - choose a credential from a line-oriented menu
+//!
+//! Credential labels are written one per line and a single selected line is
+//! read back, which is the contract dmenu, rofi and fzf all share. The chosen
+//! field is copied to the clipboard and cleared after the usual timeout.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Read, Write};
+use std::process::{Command, Stdio};
+
+use crate::app::clipboard::copy_and_wait;
+use crate::app::AppConfig;
+use crate::db::{AuditAction, Credential};
+
+use super::password::PasswordSource;
+use super::run::{extract_field, find_credential, SecretField};
+use super::session::Session;
+use super::GlobalOptions;
+
+pub struct PickArgs {
+    pub field: SecretField,
+    /// Menu command run through `sh -c`, e.g. `rofi -dmenu`
+    pub menu: Option<String>,
+    /// Only print the labels
+    pub list_only: bool,
+}
+
+/// One label per credential: `name (username)`, disambiguated by ID prefix
+fn build_labels(credentials: &[Credential]) -> Vec<String> {
+    let base: Vec<String> = credentials
+        .iter()
+        .map(|c| match &c.username {
+            Some(u) => format!("{} ({})", c.name, u),
+            None => c.name.clone(),
+        })
+        .collect();
+
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for label in &base {
+        *counts.entry(label.as_str()).or_default() += 1;
+    }
+
+    base.iter()
+        .zip(credentials)
+        .map(|(label, cred)| match counts[label.as_str()] {
+            1 => label.clone(),
+            _ => format!("{} #{}", label, &cred.id[..8.min(cred.id.len())]),
+        })
+        .collect()
+}
+
+fn first_line(raw: &str) -> String {
+    raw.lines().next().unwrap_or("").trim().to_string()
+}
+
+fn select_with_menu(menu: &str, labels: &[String]) -> io::Result<String> {
+    let mut child = Command::new("sh")
+        .args(["-c", menu])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // The menu may exit before reading everything; that is not an error
+        let _ = stdin.write_all(labels.join("\n").as_bytes());
+    }
+
+    let mut output = String::new();
+    if let Some(mut stdout) = child.stdout.take() {
+        stdout.read_to_string(&mut output)?;
+    }
+    child.wait()?;
+    Ok(first_line(&output))
+}
+
+fn select_with_stdio(labels: &[String]) -> io::Result<String> {
+    let mut stdout = io::stdout().lock();
+    for label in labels {
+        writeln!(stdout, "{}", label)?;
+    }
+    stdout.flush()?;
+
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line)?;
+    Ok(first_line(&line))
+}
+
+fn resolve_selection<'a>(
+    credentials: &'a [Credential],
+    labels: &[String],
+    selection: &str,
+) -> Result<&'a Credential, String> {
+    match labels.iter().position(|l| l == selection) {
+        Some(idx) => Ok(&credentials[idx]),
+        None => find_credential(credentials, selection),
+    }
+}
+
+pub fn pick(global: &GlobalOptions, args: PickArgs) -> Result<i32, Box<dyn std::error::Error>> {
+    let reads_stdin = args.menu.is_none() && !args.list_only;
+    if reads_stdin && global.password == PasswordSource::Stdin {
+        return Err("--password-stdin cannot be combined with a stdin selection; use --menu".into());
+    }
+
+    let session = Session::unlock(&global.vault_path, &global.password)?;
+    let credentials = session.credentials(&[])?;
+    let labels = build_labels(&credentials);
+
+    if args.list_only {
+        for label in &labels {
+            println!("{}", label);
+        }
+        return Ok(0);
+    }
+
+    let selection = match &args.menu {
+        Some(menu) => select_with_menu(menu, &labels)?,
+        None => select_with_stdio(&labels)?,
+    };
+    if selection.is_empty() {
+        return Ok(1);
+    }
+
+    let cred = resolve_selection(&credentials, &labels, &selection)?;
+    let value = extract_field(&session, cred, args.field)?;
+    let detail = format!("{} (CLI pick)", args.field.as_str());
+    session.log_audit(AuditAction::Copy, Some(cred), Some(&detail))?;
+    let name = cred.name.clone();
+    drop(session);
+
+    let config = AppConfig::load().unwrap_or_default();
+    let timeout = config.clipboard_timeout;
+    eprintln!("Copied {} of {} (clears in {}s)", args.field.as_str(), name, timeout.as_secs());
+    if !copy_and_wait(&value, timeout, config.clipboard) {
+        return Err("no clipboard available (install wl-clipboard or xclip, or set clipboard to osc52 in the config)".into());
+    }
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::DataEncryptionKey;
+    use crate::db::{self, CredentialType, Database};
+    use crate::vault::credential::create_credential;
+
+    #[test]
+    fn test_labels_and_selection() {
+        let db = Database::open_in_memory().unwrap();
+        let dek = DataEncryptionKey::generate();
+        let conn = db.conn();
+        let add = |name: &str, user: Option<&str>| {
+            create_credential(conn, &dek, name.to_string(), CredentialType::Password, "s", user.map(String::from), None, vec![], None, None, &[]).unwrap()
+        };
+
+        add("github", Some("alice"));
+        add("github", Some("alice"));
+        add("wifi", None);
+        let all = db::get_all_credentials(conn).unwrap();
+        let labels = build_labels(&all);
+
+        assert!(labels.contains(&"wifi".to_string()));
+        let github: Vec<_> = labels.iter().filter(|l| l.starts_with("github (alice) #")).collect();
+        assert_eq!(github.len(), 2);
+        assert_ne!(github[0], github[1]);
+
+        let picked = resolve_selection(&all, &labels, github[1]).unwrap();
+        assert_eq!(&labels[all.iter().position(|c| c.id == picked.id).unwrap()], github[1]);
+        assert_eq!(resolve_selection(&all, &labels, "WIFI").unwrap().name, "wifi");
+    }
+
+    #[test]
+    fn test_select_with_menu() {
+        let labels = vec!["one".to_string(), "two".to_string()];
+        assert_eq!(select_with_menu("tail -n 1", &labels).unwrap(), "two");
+        assert_eq!(select_with_menu("true", &labels).unwrap(), "");
+    }
+}
@@ -0,0 +1,261 @@
+//! `vault run` - inject credentials into a child process environment
+//!
+//! Each `--env VAR=REF[:field]` resolves REF to a single credential by ID or
+//! name (case-insensitive) and exports the chosen field as `VAR`. The vault
+//! is locked again before the command starts, and one audit entry per
+//! credential records which variable and command it was exposed to.
+
+use std::process::{Command, ExitStatus};
+
+use secrecy::ExposeSecret;
+use zeroize::Zeroizing;
+
+use crate::crypto::totp;
+use crate::db::{AuditAction, Credential};
+use crate::vault::credential::decrypt_credential;
+
+use super::session::Session;
+use super::GlobalOptions;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretField {
+    Secret,
+    Username,
+    Url,
+    Notes,
+    Totp,
+}
+
+impl SecretField {
+    pub(super) fn parse(s: &str) -> Option<Self> {
+        match s {
+            "secret" | "password" => Some(Self::Secret),
+            "username" | "user" => Some(Self::Username),
+            "url" => Some(Self::Url),
+            "notes" => Some(Self::Notes),
+            "totp" => Some(Self::Totp),
+            _ => None,
+        }
+    }
+
+    pub(super) fn as_str(&self) -> &'static str {
+        match self {
+            Self::Secret => "secret",
+            Self::Username => "username",
+            Self::Url => "url",
+            Self::Notes => "notes",
+            Self::Totp => "totp",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvBinding {
+    pub var: String,
+    pub reference: String,
+    pub field: SecretField,
+}
+
+impl EnvBinding {
+    /// Parse `VAR=REF[:field]`; a trailing `:word` only counts as a field if it names one
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (var, target) = spec
+            .split_once('=')
+            .ok_or_else(|| format!("--env expects VAR=CREDENTIAL, got {}", spec))?;
+
+        let valid_var = !var.is_empty()
+            && !var.starts_with(|c: char| c.is_ascii_digit())
+            && var.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid_var {
+            return Err(format!("invalid environment variable name: {}", var));
+        }
+
+        let suffix = target.rsplit_once(':').and_then(|(r, f)| Some((r, SecretField::parse(f)?)));
+        let (reference, field) = suffix.unwrap_or((target, SecretField::Secret));
+        if reference.is_empty() {
+            return Err(format!("missing credential in --env {}", spec));
+        }
+
+        Ok(Self { var: var.to_string(), reference: reference.to_string(), field })
+    }
+}
+
+/// Environment variable name and the secret value it carries
+type InjectedVar = (String, Zeroizing<String>);
+
+pub struct RunArgs {
+    pub bindings: Vec<EnvBinding>,
+    pub command: Vec<String>,
+}
+
+pub(super) fn find_credential<'a>(credentials: &'a [Credential], reference: &str) -> Result<&'a Credential, String> {
+    if let Some(cred) = credentials.iter().find(|c| c.id == reference) {
+        return Ok(cred);
+    }
+
+    let matches: Vec<_> = credentials.iter().filter(|c| c.name.eq_ignore_ascii_case(reference)).collect();
+    match matches.as_slice() {
+        [cred] => Ok(cred),
+        [] => Err(format!("no credential named {}", reference)),
+        many => {
+            let ids: Vec<_> = many
+                .iter()
+                .map(|c| format!("{} ({})", c.id, c.username.as_deref().unwrap_or("no username")))
+                .collect();
+            Err(format!("{} matches {} credentials, use an ID: {}", reference, many.len(), ids.join(", ")))
+        }
+    }
+}
+
+pub(super) fn extract_field(
+    session: &Session,
+    cred: &Credential,
+    field: SecretField,
+) -> Result<Zeroizing<String>, Box<dyn std::error::Error>> {
+    let db = session.vault.db()?;
+    let decrypted = decrypt_credential(db.conn(), session.vault.dek()?, cred, true)?;
+    let missing = || format!("{} has no {}", cred.name, field.as_str());
+
+    let value = match field {
+        SecretField::Secret => decrypted.secret.as_ref().map(|s| s.expose_secret().to_string()),
+        SecretField::Username => decrypted.username.clone(),
+        SecretField::Url => decrypted.url.clone(),
+        SecretField::Notes => decrypted.notes.as_ref().map(|s| s.expose_secret().to_string()),
+        SecretField::Totp => {
+            let input = decrypted.totp_secret.as_ref().ok_or_else(missing)?;
+            let secret = totp::TotpSecret::from_user_input(input.expose_secret(), &cred.name, "Vault")?;
+            Some(totp::generate_totp(&secret)?)
+        }
+    };
+    Ok(Zeroizing::new(value.ok_or_else(missing)?))
+}
+
+/// Resolve every binding, log the exposure, then lock the vault
+fn resolve_bindings(
+    global: &GlobalOptions,
+    args: &RunArgs,
+) -> Result<Vec<InjectedVar>, Box<dyn std::error::Error>> {
+    let session = Session::unlock(&global.vault_path, &global.password)?;
+    let credentials = session.credentials(&[])?;
+    let program = &args.command[0];
+
+    let mut env = Vec::with_capacity(args.bindings.len());
+    for binding in &args.bindings {
+        let cred = find_credential(&credentials, &binding.reference)?;
+        let value = extract_field(&session, cred, binding.field)?;
+
+        let detail = format!("Injected {} as ${} into {} (CLI run)", binding.field.as_str(), binding.var, program);
+        session.log_audit(AuditAction::Read, Some(cred), Some(&detail))?;
+        env.push((binding.var.clone(), value));
+    }
+    Ok(env)
+}
+
+fn exit_code(status: ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+    status.code().unwrap_or(1)
+}
+
+pub fn run(global: &GlobalOptions, args: RunArgs) -> Result<i32, Box<dyn std::error::Error>> {
+    let env = resolve_bindings(global, &args)?;
+
+    let mut command = Command::new(&args.command[0]);
+    command.args(&args.command[1..]);
+    for (var, value) in &env {
+        command.env(var, value.as_str());
+    }
+
+    let status = command
+        .status()
+        .map_err(|e| format!("failed to run {}: {}", args.command[0], e))?;
+    Ok(exit_code(status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{self, CredentialType, Database};
+    use crate::crypto::DataEncryptionKey;
+    use crate::vault::credential::create_credential;
+
+    #[test]
+    fn test_parse_binding() {
+        let binding = EnvBinding::parse("AWS_SECRET=aws-prod").unwrap();
+        assert_eq!(binding.var, "AWS_SECRET");
+        assert_eq!(binding.reference, "aws-prod");
+        assert_eq!(binding.field, SecretField::Secret);
+
+        let binding = EnvBinding::parse("DB_USER=postgres:prod:username").unwrap();
+        assert_eq!(binding.reference, "postgres:prod");
+        assert_eq!(binding.field, SecretField::Username);
+
+        let binding = EnvBinding::parse("HOST=server:8080").unwrap();
+        assert_eq!(binding.reference, "server:8080");
+        assert_eq!(binding.field, SecretField::Secret);
+
+        assert!(EnvBinding::parse("NOEQUALS").is_err());
+        assert!(EnvBinding::parse("1BAD=x").is_err());
+        assert!(EnvBinding::parse("BAD-NAME=x").is_err());
+        assert!(EnvBinding::parse("EMPTY=").is_err());
+    }
+
+    #[test]
+    fn test_find_credential() {
+        let db = Database::open_in_memory().unwrap();
+        let dek = DataEncryptionKey::generate();
+        let conn = db.conn();
+        let add = |name: &str, user: &str| {
+            create_credential(conn, &dek, name.to_string(), CredentialType::Password, "s", Some(user.to_string()), None, vec![], None, None, &[]).unwrap()
+        };
+
+        let prod = add("aws-prod", "admin");
+        let first = add("github", "alice");
+        add("GitHub", "bob");
+        let all = db::get_all_credentials(conn).unwrap();
+
+        assert_eq!(find_credential(&all, "AWS-PROD").unwrap().id, prod.id);
+        assert_eq!(find_credential(&all, &first.id).unwrap().id, first.id);
+        assert!(find_credential(&all, "github").unwrap_err().contains("matches 2"));
+        assert!(find_credential(&all, "missing").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_injects_env() {
+        use std::os::unix::fs::PermissionsExt;
+        use tempfile::TempDir;
+        use crate::cli::password::PasswordSource;
+        use crate::vault::{Vault, VaultConfig};
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("vault.db");
+        let mut vault = Vault::new(VaultConfig::with_path(&path));
+        vault.initialize("correct horse").unwrap();
+        create_credential(
+            vault.db().unwrap().conn(), vault.dek().unwrap(), "aws-prod".to_string(), CredentialType::ApiKey,
+            "s3cr3t", Some("admin".to_string()), None, vec![], None, None, &[],
+        ).unwrap();
+        vault.lock();
+
+        let key = dir.path().join("key");
+        std::fs::write(&key, "correct horse").unwrap();
+        std::fs::set_permissions(&key, std::fs::Permissions::from_mode(0o600)).unwrap();
+        let global = GlobalOptions { vault_path: path, password: PasswordSource::File(key) };
+
+        let script = "test \"$AWS_SECRET\" = s3cr3t && test \"$AWS_USER\" = admin";
+        let args = RunArgs {
+            bindings: vec![
+                EnvBinding::parse("AWS_SECRET=aws-prod").unwrap(),
+                EnvBinding::parse("AWS_USER=aws-prod:username").unwrap(),
+            ],
+            command: vec!["sh".into(), "-c".into(), script.into()],
+        };
+        assert_eq!(run(&global, args).unwrap(), 0);
+    }
+}
@@ -0,0 +1,115 @@
+//! Unlocked vault session for headless commands
+
+use std::io::IsTerminal;
+use std::path::Path;
+
+use crate::app::AppConfig;
+use crate::db::{AuditAction, Credential};
+use crate::vault::{audit, search, Vault, VaultConfig, VaultError};
+use crate::vault::search::TagMatch;
+
+use super::agent;
+use super::password::PasswordSource;
+
+pub struct Session {
+    pub vault: Vault,
+}
+
+impl Session {
+    /// Read the master password from `source` (or the key from the agent) and
+    /// unlock the vault at `path`
+    pub fn unlock(path: &Path, source: &PasswordSource) -> Result<Self, Box<dyn std::error::Error>> {
+        let keyfile = AppConfig::load().ok().and_then(|config| config.keyfile);
+        let mut vault = Vault::new(VaultConfig::with_path(path).with_keyfile(keyfile));
+
+        let (result, details) = match source {
+            PasswordSource::Agent(socket) => match agent::request_key(socket, path) {
+                Ok(key) => (vault.unlock_with_key(key), "CLI via agent"),
+                Err(e) if std::io::stdin().is_terminal() => {
+                    eprintln!("Agent unavailable ({}), asking for the password", e);
+                    (vault.unlock(&PasswordSource::Prompt.read()?), "CLI")
+                }
+                Err(e) => return Err(format!("agent at {}: {}", socket.display(), e).into()),
+            },
+            other => (vault.unlock(&other.read()?), "CLI"),
+        };
+
+        match result {
+            Ok(()) => {}
+            Err(VaultError::InvalidPassword) => {
+                let _ = vault.record_failed_unlock();
+                return Err("Invalid password".into());
+            }
+            Err(VaultError::NotFound) => {
+                return Err(format!("No vault at {} (run vault once to create it)", path.display()).into());
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        let session = Self { vault };
+        session.log_audit(AuditAction::Unlock, None, Some(details))?;
+        Ok(session)
+    }
+
+    pub fn log_audit(
+        &self,
+        action: AuditAction,
+        credential: Option<&Credential>,
+        details: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let audit_key = self.vault.keys()?.derive_audit_key()?;
+        let db = self.vault.db()?;
+        audit::log_action(
+            db.conn(),
+            &audit_key,
+            action,
+            credential.map(|c| c.id.as_str()),
+            credential.map(|c| c.name.as_str()),
+            credential.and_then(|c| c.username.as_deref()),
+            details,
+        )?;
+        Ok(())
+    }
+
+    pub fn credentials(&self, tags: &[String]) -> Result<Vec<Credential>, Box<dyn std::error::Error>> {
+        let conn = self.vault.db()?.conn();
+        Ok(search::filter_credentials(conn, tags, TagMatch::All, &[])?)
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        if self.vault.is_unlocked() {
+            let _ = self.log_audit(AuditAction::Lock, None, Some("CLI"));
+            self.vault.lock();
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    fn key_file(dir: &TempDir, password: &str) -> PasswordSource {
+        let path = dir.path().join("key");
+        std::fs::write(&path, format!("{}\n", password)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+        PasswordSource::File(path)
+    }
+
+    #[test]
+    fn test_unlock_with_key_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("vault.db");
+        Vault::new(VaultConfig::with_path(&path)).initialize("correct horse").unwrap();
+
+        let session = Session::unlock(&path, &key_file(&dir, "correct horse")).unwrap();
+        assert!(session.vault.is_unlocked());
+        assert!(session.credentials(&[]).unwrap().is_empty());
+        drop(session);
+
+        assert!(Session::unlock(&path, &key_file(&dir, "wrong")).is_err());
+    }
+}
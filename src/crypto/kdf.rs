@@ -2,11 +2,15 @@
 //!
 //! Argon2id password hashing for master key derivation.
 
+use std::time::{Duration, Instant};
+
 use argon2::{
     password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
     Argon2, Params,
 };
+use hmac::{Hmac, Mac};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use zeroize::Zeroize;
 
 use super::{CryptoError, CryptoResult, LockedBuffer};
@@ -27,8 +31,7 @@ impl MasterKey {
         }
     }
 
-    /// Get key bytes (used in tests; kept for type-safe 32-byte access)
-    #[allow(dead_code)]
+    /// Get key bytes
     pub fn as_bytes(&self) -> &[u8; 32] {
         &self.key
     }
@@ -47,6 +50,12 @@ impl std::fmt::Debug for MasterKey {
     }
 }
 
+/// Largest settings `check_bounds` accepts: 4 GiB, 100 passes, 64 lanes
+const MAX_MEMORY_COST: u32 = 4096 * 1024;
+const MAX_TIME_COST: u32 = 100;
+const MAX_PARALLELISM: u32 = 64;
+const MAX_OUTPUT_LEN: usize = 64;
+
 /// KDF parameters for Argon2id
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KdfParams {
@@ -72,6 +81,43 @@ impl Default for KdfParams {
 }
 
 impl KdfParams {
+    /// The parameters a PHC hash string from `derive_master_key` was made with
+    pub fn from_hash(password_hash: &str) -> CryptoResult<Self> {
+        let parsed = PasswordHash::new(password_hash).map_err(|e| CryptoError::KeyDerivationFailed(e.to_string()))?;
+        let params = Params::try_from(&parsed).map_err(|e| CryptoError::KeyDerivationFailed(e.to_string()))?;
+        Ok(Self {
+            memory_cost: params.m_cost(),
+            time_cost: params.t_cost(),
+            parallelism: params.p_cost(),
+            output_len: params.output_len().unwrap_or(32),
+        })
+    }
+
+    /// Short form for the status line, e.g. `19 MiB, 2 passes, 1 lane`
+    pub fn summary(&self) -> String {
+        format!(
+            "{} MiB, {} pass{}, {} lane{}",
+            self.memory_cost / 1024,
+            self.time_cost,
+            if self.time_cost == 1 { "" } else { "es" },
+            self.parallelism,
+            if self.parallelism == 1 { "" } else { "s" },
+        )
+    }
+
+    /// Reject settings read from a file someone else wrote that would
+    /// exhaust memory or run for hours before the passphrase is checked
+    pub fn check_bounds(&self) -> CryptoResult<()> {
+        if self.memory_cost > MAX_MEMORY_COST
+            || self.time_cost > MAX_TIME_COST
+            || self.parallelism > MAX_PARALLELISM
+            || self.output_len > MAX_OUTPUT_LEN
+        {
+            return Err(CryptoError::KeyDerivationFailed("KDF settings out of range".to_string()));
+        }
+        Ok(())
+    }
+
     /// Create params for testing (fast but insecure)
     #[cfg(test)]
     pub fn testing() -> Self {
@@ -88,7 +134,24 @@ impl KdfParams {
 /// Returns (MasterKey, password_hash_string)
 pub fn derive_master_key(password: &[u8], params: &KdfParams) -> CryptoResult<(MasterKey, String)> {
     let salt = SaltString::generate(&mut OsRng);
+    hash_with_salt(password, &salt, params)
+}
+
+/// Random salt for `derive_key_with_salt`
+pub fn generate_salt() -> String {
+    SaltString::generate(&mut OsRng).to_string()
+}
+
+/// Derive a key from a password and a stored salt
+///
+/// Unlike the PHC string from `derive_master_key`, the salt reveals nothing
+/// about the key, so it can sit next to data wrapped by that key.
+pub fn derive_key_with_salt(password: &[u8], salt: &str, params: &KdfParams) -> CryptoResult<MasterKey> {
+    let salt = SaltString::from_b64(salt).map_err(|e| CryptoError::KeyDerivationFailed(e.to_string()))?;
+    Ok(hash_with_salt(password, &salt, params)?.0)
+}
 
+fn hash_with_salt(password: &[u8], salt: &SaltString, params: &KdfParams) -> CryptoResult<(MasterKey, String)> {
     let argon2_params = Params::new(
         params.memory_cost,
         params.time_cost,
@@ -100,7 +163,7 @@ pub fn derive_master_key(password: &[u8], params: &KdfParams) -> CryptoResult<(M
     let argon2 = Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, argon2_params);
 
     let password_hash = argon2
-        .hash_password(password, &salt)
+        .hash_password(password, salt)
         .map_err(|e| CryptoError::KeyDerivationFailed(e.to_string()))?;
 
     // Extract the hash output as the key
@@ -127,6 +190,36 @@ pub fn derive_master_key(password: &[u8], params: &KdfParams) -> CryptoResult<(M
     Ok((master_key, password_hash.to_string()))
 }
 
+/// Argon2 parameters that take about `target` per derivation on this machine
+///
+/// Memory doubles first, as it is what makes cracking on GPUs expensive, up
+/// to `max_memory` KiB; passes are then added to reach the target. The
+/// result never drops below the defaults. Returns the parameters with the
+/// time they took.
+pub fn calibrate(target: Duration, parallelism: u32, max_memory: u32) -> CryptoResult<(KdfParams, Duration)> {
+    let mut params = KdfParams { parallelism, ..KdfParams::default() };
+    let mut elapsed = time_derivation(&params)?;
+    while elapsed * 2 <= target && params.memory_cost * 2 <= max_memory {
+        params.memory_cost *= 2;
+        elapsed = time_derivation(&params)?;
+    }
+
+    // Passes cost about the same each
+    let per_pass = (elapsed / params.time_cost).max(Duration::from_micros(1));
+    let passes = (target.as_nanos() / per_pass.as_nanos()).clamp(params.time_cost as u128, 100) as u32;
+    if passes != params.time_cost {
+        params.time_cost = passes;
+        elapsed = time_derivation(&params)?;
+    }
+    Ok((params, elapsed))
+}
+
+fn time_derivation(params: &KdfParams) -> CryptoResult<Duration> {
+    let start = Instant::now();
+    derive_master_key(b"calibration", params)?;
+    Ok(start.elapsed())
+}
+
 /// Verify password against stored hash and derive key
 pub fn verify_master_key(password: &[u8], password_hash: &str) -> CryptoResult<MasterKey> {
     let parsed_hash = PasswordHash::new(password_hash)
@@ -161,6 +254,35 @@ pub fn verify_master_key(password: &[u8], password_hash: &str) -> CryptoResult<M
     Ok(master_key)
 }
 
+/// Fold a keyfile into a password-derived key
+///
+/// The password hash only ever covers the password, so the stored hash
+/// reveals nothing that unwraps the DEK without the keyfile as well.
+pub fn mix_keyfile(password_key: &MasterKey, keyfile: &[u8]) -> MasterKey {
+    mix_factor(password_key, b"vault-keyfile", &Sha256::digest(keyfile))
+}
+
+/// Fold a hardware token's challenge-response answer into the master key
+pub fn mix_token_response(key: &MasterKey, response: &[u8]) -> MasterKey {
+    mix_factor(key, b"vault-yubikey", response)
+}
+
+/// Fold the secret sealed to the TPM into the master key
+pub fn mix_tpm_secret(key: &MasterKey, secret: &[u8]) -> MasterKey {
+    mix_factor(key, b"vault-tpm", secret)
+}
+
+fn mix_factor(key: &MasterKey, label: &[u8], factor: &[u8]) -> MasterKey {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(label);
+    mac.update(factor);
+
+    let mut key_bytes: [u8; 32] = mac.finalize().into_bytes().into();
+    let master_key = MasterKey::from_bytes(key_bytes);
+    key_bytes.zeroize();
+    master_key
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -177,6 +299,19 @@ mod tests {
         assert!(hash.starts_with("$argon2id$"));
     }
 
+    #[test]
+    fn test_derive_key_with_salt() {
+        let params = KdfParams::testing();
+        let salt = generate_salt();
+
+        let a = derive_key_with_salt(b"passphrase", &salt, &params).unwrap();
+        let b = derive_key_with_salt(b"passphrase", &salt, &params).unwrap();
+        let c = derive_key_with_salt(b"other", &salt, &params).unwrap();
+        assert_eq!(a.as_bytes(), b.as_bytes());
+        assert_ne!(a.as_bytes(), c.as_bytes());
+        assert!(derive_key_with_salt(b"passphrase", "not base64!", &params).is_err());
+    }
+
     #[test]
     fn test_verify_master_key() {
         let password = b"test_password_123";
@@ -225,4 +360,52 @@ mod tests {
         assert_eq!(key1.as_bytes(), key2.as_bytes());
         assert_eq!(key2.as_bytes(), key3.as_bytes());
     }
+
+    #[test]
+    fn test_params_from_hash() {
+        let params = KdfParams { memory_cost: 2048, time_cost: 3, parallelism: 2, output_len: 32 };
+        let (_, hash) = derive_master_key(b"password", &params).unwrap();
+
+        let read = KdfParams::from_hash(&hash).unwrap();
+        assert_eq!((read.memory_cost, read.time_cost, read.parallelism), (2048, 3, 2));
+        assert_eq!(read.summary(), "2 MiB, 3 passes, 2 lanes");
+        assert!(KdfParams::from_hash("not a hash").is_err());
+    }
+
+    #[test]
+    fn test_check_bounds() {
+        assert!(KdfParams::default().check_bounds().is_ok());
+        assert!(KdfParams { memory_cost: u32::MAX, ..KdfParams::default() }.check_bounds().is_err());
+        assert!(KdfParams { time_cost: 1000, ..KdfParams::default() }.check_bounds().is_err());
+        assert!(KdfParams { parallelism: 1000, ..KdfParams::default() }.check_bounds().is_err());
+    }
+
+    #[test]
+    fn test_calibrate_keeps_defaults_for_tiny_target() {
+        let (params, _) = calibrate(Duration::from_millis(1), 1, 1024 * 1024).unwrap();
+        let defaults = KdfParams::default();
+        assert_eq!((params.memory_cost, params.time_cost), (defaults.memory_cost, defaults.time_cost));
+    }
+
+    #[test]
+    fn test_mix_keyfile() {
+        let (key, _) = derive_master_key(b"password", &KdfParams::testing()).unwrap();
+
+        let a = mix_keyfile(&key, b"keyfile contents");
+        assert_eq!(a.as_bytes(), mix_keyfile(&key, b"keyfile contents").as_bytes());
+        assert_ne!(a.as_bytes(), mix_keyfile(&key, b"other contents").as_bytes());
+        assert_ne!(a.as_bytes(), key.as_bytes());
+    }
+
+    #[test]
+    fn test_mix_token_response() {
+        let (key, _) = derive_master_key(b"password", &KdfParams::testing()).unwrap();
+        let response = [7u8; 20];
+
+        let a = mix_token_response(&key, &response);
+        assert_eq!(a.as_bytes(), mix_token_response(&key, &response).as_bytes());
+        assert_ne!(a.as_bytes(), mix_token_response(&key, &[8u8; 20]).as_bytes());
+        assert_ne!(a.as_bytes(), mix_keyfile(&key, &response).as_bytes());
+        assert_ne!(a.as_bytes(), mix_tpm_secret(&key, &response).as_bytes());
+    }
 }
@@ -87,11 +87,22 @@ impl KeyHierarchy {
         Ok(new_wrapped_dek)
     }
 
+    /// A hierarchy under the same master key with a freshly generated DEK,
+    /// for DEK rotation; data must be re-encrypted before it replaces this one
+    pub fn with_fresh_dek(&self) -> CryptoResult<Self> {
+        Self::new(self.master_key.clone())
+    }
+
     /// Get the wrapped DEK for storage
     pub fn wrapped_dek(&self) -> &str {
         &self.wrapped_dek
     }
 
+    /// Get the master key, e.g. to hand to the agent
+    pub fn master_key(&self) -> &MasterKey {
+        &self.master_key
+    }
+
     /// Get the DEK for credential encryption
     pub fn dek(&self) -> &DataEncryptionKey {
         &self.dek
@@ -103,6 +114,19 @@ impl KeyHierarchy {
     }
 }
 
+/// Derive the key for one credential's data from the DEK
+///
+/// The credential id is the HKDF info, so every record has its own key and
+/// handing out one record's key reveals neither the DEK nor other records.
+pub fn derive_credential_key(dek: &DataEncryptionKey, credential_id: &str) -> CryptoResult<DerivedKey> {
+    derive_key(dek.as_bytes(), "credential", credential_id)
+}
+
+/// Derive the key the search index tokens of encrypted metadata are keyed with
+pub fn derive_search_key(dek: &DataEncryptionKey) -> CryptoResult<DerivedKey> {
+    derive_key(dek.as_bytes(), "search", "index")
+}
+
 /// Core HKDF key derivation
 fn derive_key(ikm: &[u8], context: &str, info: &str) -> CryptoResult<DerivedKey> {
     let salt = format!("vault-{}", context);
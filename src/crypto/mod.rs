@@ -191,9 +191,12 @@ impl<const N: usize> std::fmt::Debug for LockedBuffer<N> {
 // Re-exports
 pub use dek::DataEncryptionKey;
 pub use encryption::{decrypt_string, encrypt_string};
-pub use kdf::{derive_master_key, verify_master_key, KdfParams, MasterKey};
-pub use key_hierarchy::{DerivedKey, KeyHierarchy};
-pub use password_gen::{generate_password, password_strength, strength_label, PasswordPolicy};
+pub use kdf::{calibrate, derive_key_with_salt, derive_master_key, generate_salt, mix_keyfile, mix_token_response, mix_tpm_secret, verify_master_key, KdfParams, MasterKey};
+pub use key_hierarchy::{derive_credential_key, derive_search_key, DerivedKey, KeyHierarchy};
+pub use password_gen::{
+    generate_passphrase, generate_password, password_strength, strength_label, PassphraseOptions,
+    PasswordError, PasswordPolicy, Wordlist, MAX_PASSPHRASE_WORDS,
+};
 // pub use totp::{generate_totp, time_remaining, TotpSecret};
 
 #[cfg(test)]
@@ -5,6 +5,7 @@
 use rand::rngs::OsRng;
 use rand::prelude::IteratorRandom;
 use rand::seq::SliceRandom;
+use rand::Rng;
 
 /// Password generation policy
 #[derive(Debug, Clone)]
@@ -22,6 +23,9 @@ pub struct PasswordPolicy {
     pub custom_symbols: Option<String>,
     /// Exclude ambiguous characters (0, O, l, 1, I, |)
     pub exclude_ambiguous: bool,
+    /// Build the password from consonant-vowel syllables so it can be read
+    /// aloud; digits and symbols, if enabled, go at the end
+    pub pronounceable: bool,
 }
 
 impl Default for PasswordPolicy {
@@ -34,6 +38,7 @@ impl Default for PasswordPolicy {
             symbols: true,
             custom_symbols: None,
             exclude_ambiguous: false,
+            pronounceable: false,
         }
     }
 }
@@ -50,6 +55,7 @@ impl PasswordPolicy {
             symbols: false,
             custom_symbols: None,
             exclude_ambiguous: false,
+            pronounceable: false,
         }
     }
 
@@ -64,9 +70,47 @@ impl PasswordPolicy {
             symbols: false,
             custom_symbols: None,
             exclude_ambiguous: true,
+            pronounceable: false,
         }
     }
 
+    /// Create a policy for passwords that are read aloud or typed on a
+    /// console keyboard: syllables, a capital and a digit, no symbols
+    pub fn pronounceable(length: usize) -> Self {
+        Self {
+            length,
+            uppercase: true,
+            lowercase: true,
+            digits: true,
+            symbols: false,
+            custom_symbols: None,
+            exclude_ambiguous: true,
+            pronounceable: true,
+        }
+    }
+
+    /// Parse a per-credential rule such as `16 nosymbols`, `12 symbols=!@#`
+    /// or `14 pronounceable`; anything the rule doesn't mention keeps its default
+    pub fn from_rule(rule: &str) -> Result<Self, PasswordError> {
+        let mut policy = Self::default();
+        for token in rule.split_whitespace() {
+            match token {
+                "nosymbols" => policy.symbols = false,
+                "noambiguous" => policy.exclude_ambiguous = true,
+                "pronounceable" => policy.pronounceable = true,
+                _ if token.starts_with("symbols=") => {
+                    policy.symbols = true;
+                    policy.custom_symbols = Some(token["symbols=".len()..].to_string());
+                }
+                _ => match token.parse::<usize>() {
+                    Ok(length) if length > 0 => policy.length = length,
+                    _ => return Err(PasswordError::InvalidRule(token.to_string())),
+                },
+            }
+        }
+        Ok(policy)
+    }
+
     /// Create a quantum-resistant policy.
     ///
     /// Generates 40-character passwords (~262 bits entropy with full charset),
@@ -84,6 +128,7 @@ impl PasswordPolicy {
             symbols: true,
             custom_symbols: None,
             exclude_ambiguous: false,
+            pronounceable: false,
         }
     }
 }
@@ -93,9 +138,11 @@ const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
 const DIGITS: &str = "0123456789";
 const SYMBOLS: &str = "!@#$%^&*()-_=+[]{}|;:,.<>?";
 const AMBIGUOUS: &str = "0O1lI|";
+// Letters for pronounceable passwords; q, x and y read too ambiguously aloud
+const CONSONANTS: &str = "bcdfghjklmnprstvwz";
+const VOWELS: &str = "aeiou";
 
 // Word list for passphrase generation (EFF short wordlist subset)
-#[allow(dead_code)]
 const WORDLIST: &[&str] = &[
     "acid", "acorn", "acre", "acts", "afar", "affix", "aged", "agent", "agile", "aging",
     "agony", "ahead", "aide", "aids", "aim", "ajar", "alarm", "album", "alert", "alike",
@@ -119,17 +166,82 @@ const WORDLIST: &[&str] = &[
     "coast", "coat", "cocoa", "code", "coil", "cola", "cold", "colon", "color", "comet",
 ];
 
+/// Most words a passphrase may have, whatever the size of the wordlist
+pub const MAX_PASSPHRASE_WORDS: usize = 64;
+
+/// Words to draw passphrases from
+///
+/// The built-in list is small; a full list such as the EFF long wordlist can
+/// be loaded from a file with `Wordlist::parse`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Wordlist {
+    words: Vec<String>,
+}
+
+impl Wordlist {
+    pub fn builtin() -> Self {
+        Self { words: WORDLIST.iter().map(|w| w.to_string()).collect() }
+    }
+
+    /// Parse a wordlist file: one word per line, blank lines and `#` comments
+    /// skipped. EFF-style lines (`11111<TAB>abacus`) use their last column.
+    /// Duplicates are dropped so every word adds the same entropy.
+    pub fn parse(text: &str) -> Result<Self, PasswordError> {
+        let mut seen = std::collections::HashSet::new();
+        let words: Vec<String> = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| line.split_whitespace().last())
+            .filter(|word| seen.insert(*word))
+            .map(str::to_string)
+            .collect();
+        if words.is_empty() {
+            return Err(PasswordError::EmptyWordlist);
+        }
+        Ok(Self { words })
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+}
+
+/// Passphrase shape
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PassphraseOptions {
+    pub words: usize,
+    pub separator: String,
+    /// Upper-case the first letter of each word
+    pub capitalize: bool,
+}
+
+impl Default for PassphraseOptions {
+    fn default() -> Self {
+        Self { words: 5, separator: "-".to_string(), capitalize: false }
+    }
+}
+
 /// Error type for password generation
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PasswordError {
     /// No characters available after applying policy filters
     EmptyCharset,
+    /// Part of a password rule that isn't a length or known option
+    InvalidRule(String),
+    /// Wordlist file contains no words
+    EmptyWordlist,
+    /// More words requested than the wordlist holds (words are not repeated)
+    NotEnoughWords(usize),
 }
 
 impl std::fmt::Display for PasswordError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             PasswordError::EmptyCharset => { write!(f, "No characters available with current policy settings") }
+            PasswordError::InvalidRule(token) => write!(f, "Unknown password rule: {}", token),
+            PasswordError::EmptyWordlist => write!(f, "Wordlist contains no words"),
+            PasswordError::NotEnoughWords(n) => write!(f, "Wordlist only has {} words", n),
         }
     }
 }
@@ -143,6 +255,9 @@ impl std::error::Error for PasswordError {}
 /// # Errors
 /// Returns `PasswordError::EmptyCharset` if the policy results in no available characters.
 pub fn generate_password(policy: &PasswordPolicy) -> Result<String, PasswordError> {
+    if policy.pronounceable {
+        return generate_pronounceable(policy);
+    }
     let mut rng = OsRng;
     let mut charset = String::new();
     let mut required: Vec<char> = Vec::new();
@@ -215,16 +330,82 @@ pub fn generate_password(policy: &PasswordPolicy) -> Result<String, PasswordErro
     Ok(password.into_iter().collect())
 }
 
-/// Generate a passphrase from random words.
+/// Syllables (consonant, vowel, sometimes a closing consonant) up to the
+/// policy length, with one capital, then a digit and a symbol if enabled.
+fn generate_pronounceable(policy: &PasswordPolicy) -> Result<String, PasswordError> {
+    if !policy.uppercase && !policy.lowercase {
+        return Err(PasswordError::EmptyCharset);
+    }
+    let mut rng = OsRng;
+    let pick = |chars: &str, rng: &mut OsRng| -> Option<char> {
+        chars.chars().filter(|c| !policy.exclude_ambiguous || !AMBIGUOUS.contains(*c)).choose(rng)
+    };
+
+    let mut tail = Vec::new();
+    if policy.digits {
+        tail.extend(pick(DIGITS, &mut rng));
+    }
+    if policy.symbols {
+        tail.extend(pick(policy.custom_symbols.as_deref().unwrap_or(SYMBOLS), &mut rng));
+    }
+    // Very short passwords are all syllable
+    while !tail.is_empty() && tail.len() >= policy.length {
+        tail.pop();
+    }
+
+    let body_len = policy.length - tail.len();
+    let mut body: Vec<char> = Vec::with_capacity(body_len + 2);
+    while body.len() < body_len {
+        body.extend(pick(CONSONANTS, &mut rng));
+        body.extend(pick(VOWELS, &mut rng));
+        if rng.gen_bool(0.3) {
+            body.extend(pick(CONSONANTS, &mut rng));
+        }
+    }
+    body.truncate(body_len);
+
+    if !policy.lowercase {
+        body.iter_mut().for_each(|c| *c = c.to_ascii_uppercase());
+    } else if policy.uppercase {
+        // Capitalize one syllable start, avoiding look-alikes when asked
+        let starts: Vec<usize> = (0..body.len())
+            .filter(|&i| CONSONANTS.contains(body[i]) && (i == 0 || VOWELS.contains(body[i - 1])))
+            .filter(|&i| !policy.exclude_ambiguous || !AMBIGUOUS.contains(body[i].to_ascii_uppercase()))
+            .collect();
+        if let Some(&i) = starts.choose(&mut rng) {
+            body[i] = body[i].to_ascii_uppercase();
+        }
+    }
+
+    body.extend(tail);
+    Ok(body.into_iter().collect())
+}
+
+/// Generate a passphrase from random words of `wordlist`.
 /// Uses `OsRng` for cryptographically secure randomness.
-#[allow(dead_code)]
-pub fn generate_passphrase(word_count: usize, separator: &str) -> String {
+///
+/// # Errors
+/// Returns `PasswordError::NotEnoughWords` if the list is shorter than the
+/// requested word count.
+pub fn generate_passphrase(options: &PassphraseOptions, wordlist: &Wordlist) -> Result<String, PasswordError> {
+    if options.words > wordlist.len() {
+        return Err(PasswordError::NotEnoughWords(wordlist.len()));
+    }
     let mut rng = OsRng;
-    let words: Vec<&str> = WORDLIST
-        .choose_multiple(&mut rng, word_count)
-        .copied()
+    let words: Vec<String> = wordlist
+        .words
+        .choose_multiple(&mut rng, options.words)
+        .map(|w| if options.capitalize { capitalize(w) } else { w.clone() })
         .collect();
-    words.join(separator)
+    Ok(words.join(&options.separator))
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
 }
 
 /// Calculate password strength based on entropy (0-100).
@@ -330,13 +511,71 @@ mod tests {
 
     #[test]
     fn test_generate_passphrase() {
-        let passphrase = generate_passphrase(4, "-");
+        let options = PassphraseOptions { words: 4, ..Default::default() };
+        let passphrase = generate_passphrase(&options, &Wordlist::builtin()).unwrap();
         let words: Vec<&str> = passphrase.split('-').collect();
 
         assert_eq!(words.len(), 4);
         assert!(words.iter().all(|w| WORDLIST.contains(w)));
     }
 
+    #[test]
+    fn test_custom_wordlist() {
+        let wordlist = Wordlist::parse("# dice list\n11111\tabacus\n11112\tabdomen\n\nabacus\nzebra\n").unwrap();
+        assert_eq!(wordlist.len(), 3);
+
+        let options = PassphraseOptions { words: 3, separator: " ".to_string(), capitalize: true };
+        let passphrase = generate_passphrase(&options, &wordlist).unwrap();
+        let mut words: Vec<&str> = passphrase.split(' ').collect();
+        words.sort();
+        assert_eq!(words, ["Abacus", "Abdomen", "Zebra"]);
+
+        let options = PassphraseOptions { words: 4, ..Default::default() };
+        assert_eq!(generate_passphrase(&options, &wordlist), Err(PasswordError::NotEnoughWords(3)));
+        assert_eq!(Wordlist::parse("# nothing\n\n"), Err(PasswordError::EmptyWordlist));
+    }
+
+    #[test]
+    fn test_generate_pronounceable() {
+        let policy = PasswordPolicy::pronounceable(14);
+        for _ in 0..20 {
+            let password = generate_password(&policy).unwrap();
+            assert_eq!(password.len(), 14);
+            assert!(password.ends_with(|c: char| c.is_ascii_digit()));
+            assert!(password.chars().any(|c| c.is_ascii_uppercase()));
+            assert!(!password.chars().any(|c| AMBIGUOUS.contains(c)));
+
+            // Never more than two consonants in a row
+            let letters: String = password.chars().filter(|c| c.is_ascii_alphabetic()).collect();
+            let mut run = 0;
+            for c in letters.to_ascii_lowercase().chars() {
+                run = if VOWELS.contains(c) { 0 } else { run + 1 };
+                assert!(run <= 2, "'{}' is hard to pronounce", password);
+            }
+        }
+
+        let policy = PasswordPolicy { symbols: true, ..PasswordPolicy::pronounceable(2) };
+        assert_eq!(generate_password(&policy).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_password_rule() {
+        let policy = PasswordPolicy::from_rule("16 nosymbols").unwrap();
+        assert_eq!(policy.length, 16);
+        assert!(!policy.symbols);
+        let password = generate_password(&policy).unwrap();
+        assert!(password.chars().all(|c| c.is_ascii_alphanumeric()));
+
+        let policy = PasswordPolicy::from_rule("symbols=_- 12").unwrap();
+        let password = generate_password(&policy).unwrap();
+        assert_eq!(password.len(), 12);
+        assert!(password.chars().all(|c| c.is_ascii_alphanumeric() || "_-".contains(c)));
+
+        assert!(PasswordPolicy::from_rule("").is_ok());
+        assert!(PasswordPolicy::from_rule("0").is_err());
+        assert_eq!(PasswordPolicy::from_rule("16 short").unwrap_err(), PasswordError::InvalidRule("short".into()));
+    }
+
     #[test]
     fn test_password_strength_short_complex() {
         // Short password with full variety should still be weak
@@ -381,6 +620,7 @@ mod tests {
             symbols: false,
             custom_symbols: None,
             exclude_ambiguous: false,
+            pronounceable: false,
         };
 
         assert_eq!(generate_password(&policy), Err(PasswordError::EmptyCharset));
@@ -396,6 +636,7 @@ mod tests {
             symbols: true,
             custom_symbols: Some("|!@#".to_string()),
             exclude_ambiguous: true,
+            pronounceable: false,
         };
 
         let password = generate_password(&policy).unwrap();
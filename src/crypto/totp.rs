@@ -2,9 +2,14 @@
 //!
 //! Implements RFC 6238 for 2FA code generation.
 
+use std::path::Path;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use serde::{Deserialize, Serialize};
 use totp_rs::{Algorithm, Secret, TOTP};
 
+use crate::qr;
+
 use super::{CryptoError, CryptoResult};
 
 /// TOTP secret configuration
@@ -30,6 +35,8 @@ pub enum TotpAlgorithm {
     SHA1,
     SHA256,
     SHA512,
+    /// Steam Guard: SHA1, written as 5 characters from Steam's own alphabet
+    Steam,
 }
 
 impl From<TotpAlgorithm> for Algorithm {
@@ -38,10 +45,17 @@ impl From<TotpAlgorithm> for Algorithm {
             TotpAlgorithm::SHA1 => Algorithm::SHA1,
             TotpAlgorithm::SHA256 => Algorithm::SHA256,
             TotpAlgorithm::SHA512 => Algorithm::SHA512,
+            TotpAlgorithm::Steam => Algorithm::Steam,
         }
     }
 }
 
+/// Prefix marking a raw secret as a Steam Guard one
+const STEAM_PREFIX: &str = "steam:";
+
+/// Steam Guard codes always have 5 characters
+const STEAM_DIGITS: usize = 5;
+
 impl TotpSecret {
     /// Create a new TOTP secret with defaults
     pub fn new(secret: String, account: String, issuer: String) -> Self {
@@ -55,7 +69,8 @@ impl TotpSecret {
         }
     }
 
-    /// Parse from user input - handles both raw secret and otpauth:// URI
+    /// Parse from user input - handles a raw secret, a `steam:` prefixed
+    /// Steam Guard secret and an otpauth:// URI
     pub fn from_user_input(input: &str, fallback_account: &str, fallback_issuer: &str) -> CryptoResult<Self> {
         let trimmed = input.trim();
         
@@ -63,13 +78,37 @@ impl TotpSecret {
             return Err(CryptoError::TotpFailed("TOTP secret cannot be empty".to_string()));
         }
         
-        if trimmed.to_lowercase().starts_with("otpauth://") {
+        let lower = trimmed.to_lowercase();
+        if lower.starts_with("otpauth://") {
             Self::from_uri(trimmed)
+        } else if lower.starts_with(STEAM_PREFIX) {
+            Self::from_steam_secret(&trimmed[STEAM_PREFIX.len()..], fallback_account)
         } else {
             Self::from_raw_secret(trimmed, fallback_account, fallback_issuer)
         }
     }
 
+    /// Create a Steam Guard secret, given in base32 or as the base64
+    /// `shared_secret` of a Steam Desktop Authenticator maFile
+    fn from_steam_secret(secret: &str, account: &str) -> CryptoResult<Self> {
+        let secret = secret.trim();
+        let cleaned = match looks_like_base64(secret) {
+            true => {
+                let bytes = BASE64.decode(secret)
+                    .map_err(|e| CryptoError::TotpFailed(format!("Invalid Steam shared secret: {}", e)))?;
+                Secret::Raw(bytes).to_encoded().to_string()
+            }
+            false => normalize_base32(secret),
+        };
+        validate_base32(&cleaned)?;
+
+        Ok(Self {
+            digits: STEAM_DIGITS,
+            algorithm: TotpAlgorithm::Steam,
+            ..Self::new(cleaned, account.to_string(), "Steam".to_string())
+        })
+    }
+
     /// Create from raw base32 secret
     fn from_raw_secret(secret: &str, account: &str, issuer: &str) -> CryptoResult<Self> {
         let cleaned = normalize_base32(secret);
@@ -80,23 +119,36 @@ impl TotpSecret {
 
     /// Parse from otpauth:// URI
     fn from_uri(uri: &str) -> CryptoResult<Self> {
-        let totp = TOTP::from_url(normalize_otpauth_uri(uri))
+        // Aegis and andOTP export Steam entries as totp URIs with an encoder
+        let steam = extract_uri_param(uri, "encoder").is_some_and(|e| e.eq_ignore_ascii_case("steam"));
+        let uri = normalize_otpauth_uri(uri);
+        let totp = TOTP::from_url_unchecked(&uri)
             .map_err(|e| CryptoError::TotpFailed(e.to_string()))?;
+        // Steam's 5 digits would fail the RFC 6238 checks other codes must pass
+        if !steam && totp.algorithm != Algorithm::Steam {
+            TOTP::from_url(&uri).map_err(|e| CryptoError::TotpFailed(e.to_string()))?;
+        }
 
         let algorithm = match totp.algorithm {
             Algorithm::SHA1 => TotpAlgorithm::SHA1,
             Algorithm::SHA256 => TotpAlgorithm::SHA256,
             Algorithm::SHA512 => TotpAlgorithm::SHA512,
+            Algorithm::Steam => TotpAlgorithm::Steam,
         };
 
-        Ok(Self {
+        let mut secret = Self {
             secret: normalize_base32(&totp.get_secret_base32()),
             account: totp.account_name.clone(),
             issuer: totp.issuer.clone().unwrap_or_default(),
             digits: totp.digits,
             period: totp.step,
             algorithm,
-        })
+        };
+        if steam {
+            secret.algorithm = TotpAlgorithm::Steam;
+            secret.digits = STEAM_DIGITS;
+        }
+        Ok(secret)
     }
 
     /// Export as otpauth:// URI for transferring to other apps
@@ -182,6 +234,13 @@ fn extract_uri_param<'a>(uri: &'a str, key: &str) -> Option<&'a str> {
     Some(&uri[value_start..value_end])
 }
 
+/// Whether a Steam secret is base64 rather than base32: base32 has no
+/// lowercase next to uppercase, no `+` or `/` and no 0, 1, 8 or 9
+fn looks_like_base64(secret: &str) -> bool {
+    let mixed_case = secret.chars().any(|c| c.is_ascii_lowercase()) && secret.chars().any(|c| c.is_ascii_uppercase());
+    mixed_case || secret.chars().any(|c| matches!(c, '+' | '/' | '0' | '1' | '8' | '9'))
+}
+
 /// Normalize base32 input (remove spaces, dashes, convert to uppercase)
 fn normalize_base32(input: &str) -> String {
     input
@@ -216,6 +275,17 @@ fn validate_base32(secret: &str) -> CryptoResult<()> {
     Ok(())
 }
 
+/// Read the otpauth:// URI from a screenshot of a provider's QR code
+pub fn read_qr_uri(path: &Path) -> CryptoResult<String> {
+    let text = qr::read_file(path).map_err(|e| CryptoError::TotpFailed(e.to_string()))?;
+    let uri = text.trim();
+    if !uri.to_lowercase().starts_with("otpauth://") {
+        return Err(CryptoError::TotpFailed("QR code does not hold an otpauth:// URI".to_string()));
+    }
+    TotpSecret::from_uri(uri)?;
+    Ok(uri.to_string())
+}
+
 /// Generate current TOTP code
 pub fn generate_totp(secret: &TotpSecret) -> CryptoResult<String> {
     let totp = secret.build_totp()?;
@@ -223,13 +293,26 @@ pub fn generate_totp(secret: &TotpSecret) -> CryptoResult<String> {
         .map_err(|e| CryptoError::TotpFailed(e.to_string()))
 }
 
+/// Generate the code that follows the current one
+pub fn generate_next_totp(secret: &TotpSecret) -> CryptoResult<String> {
+    let totp = secret.build_totp()?;
+    Ok(totp.generate(next_period_start(unix_now(), secret.period)))
+}
+
 /// Get remaining seconds until code expires
 pub fn time_remaining(secret: &TotpSecret) -> u64 {
-    let now = std::time::SystemTime::now()
+    secret.period - (unix_now() % secret.period)
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap_or_default()
-        .as_secs();
-    secret.period - (now % secret.period)
+        .as_secs()
+}
+
+fn next_period_start(now: u64, period: u64) -> u64 {
+    now - now % period + period
 }
 
 #[cfg(test)]
@@ -308,6 +391,56 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_steam_prefix() {
+        let secret = TotpSecret::from_user_input("steam:JBSWY3DPEHPK3PXP", "gabe", "Vault").unwrap();
+        assert!(matches!(secret.algorithm, TotpAlgorithm::Steam));
+        assert_eq!(secret.issuer, "Steam");
+
+        let code = generate_totp(&secret).unwrap();
+        assert_eq!(code.len(), 5);
+        assert!(code.chars().all(|c| "23456789BCDFGHJKMNPQRTVWXY".contains(c)));
+    }
+
+    #[test]
+    fn test_steam_base64_shared_secret() {
+        // base64 of the bytes behind JBSWY3DPEHPK3PXP
+        let secret = TotpSecret::from_user_input("steam:SGVsbG8h3q2+7w==", "gabe", "Vault").unwrap();
+        assert_eq!(secret.secret, "JBSWY3DPEHPK3PXP");
+    }
+
+    #[test]
+    fn test_steam_uris() {
+        let aegis = "otpauth://totp/Steam:gabe?secret=JBSWY3DPEHPK3PXP&digits=5&encoder=steam";
+        let secret = TotpSecret::from_user_input(aegis, "fallback", "Fallback").unwrap();
+        assert!(matches!(secret.algorithm, TotpAlgorithm::Steam));
+        assert_eq!(secret.digits, 5);
+
+        let native = "otpauth://steam/Steam:gabe?secret=JBSWY3DPEHPK3PXP";
+        let secret = TotpSecret::from_user_input(native, "fallback", "Fallback").unwrap();
+        assert!(matches!(secret.algorithm, TotpAlgorithm::Steam));
+
+        // Exported URIs read back as Steam
+        let again = TotpSecret::from_user_input(&secret.to_uri().unwrap(), "fallback", "Fallback").unwrap();
+        assert!(matches!(again.algorithm, TotpAlgorithm::Steam));
+        assert_eq!(generate_totp(&again).unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_read_qr_uri_missing_file() {
+        let result = read_qr_uri(Path::new("/nonexistent/qr.png"));
+        assert!(result.unwrap_err().to_string().contains("Could not read image"));
+    }
+
+    #[test]
+    fn test_next_totp() {
+        assert_eq!(next_period_start(59, 30), 60);
+        assert_eq!(next_period_start(60, 30), 90);
+
+        let secret = TotpSecret::from_user_input("JBSWY3DPEHPK3PXP", "test", "Test").unwrap();
+        assert_eq!(generate_next_totp(&secret).unwrap().len(), 6);
+    }
+
     #[test]
     fn test_time_remaining() {
         let secret = TotpSecret::from_user_input(
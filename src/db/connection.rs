@@ -1,12 +1,29 @@
 //! Database Connection Management
 //!
 //! Handles SQLite database connections and configuration.
+//!
+//! Built with the `sqlcipher` feature, a database can also be encrypted as a
+//! whole: SQLCipher derives the page key from a passphrase given right after
+//! opening, so table and column names, timestamps and everything else stored
+//! in plaintext columns are unreadable without it.
 
+use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use rusqlite::{Connection, OpenFlags};
 
-use super::{schema::init_schema, DbResult};
+use super::{
+    functions::{self, CipherSlot, ColumnCipher},
+    schema::init_schema,
+    DbError, DbResult,
+};
+
+/// Whether this build can encrypt whole database files
+pub const ENCRYPTION_SUPPORTED: bool = cfg!(feature = "sqlcipher");
+
+/// How every plaintext SQLite file starts; SQLCipher files start with a random salt
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
 
 /// Database configuration
 #[derive(Debug, Clone)]
@@ -30,8 +47,7 @@ impl Default for DatabaseConfig {
 }
 
 impl DatabaseConfig {
-    /// Create config for in-memory database (testing)
-    #[cfg(test)]
+    /// Create config for in-memory database (tests and staging imports)
     pub fn in_memory() -> Self {
         Self {
             path: PathBuf::from(":memory:"),
@@ -61,20 +77,53 @@ fn default_db_path() -> PathBuf {
 pub struct Database {
     conn: Connection,
     config: DatabaseConfig,
+    cipher: CipherSlot,
 }
 
 impl Database {
     /// Open or create a database with the given config
     pub fn open(config: DatabaseConfig) -> DbResult<Self> {
+        Self::open_with_key(config, None)
+    }
+
+    /// Open or create a database encrypted under the passphrase `key`, or a
+    /// plain one with None; a wrong key fails here
+    pub fn open_with_key(config: DatabaseConfig, key: Option<&str>) -> DbResult<Self> {
         ensure_parent_dir(&config)?;
         let conn = open_connection(&config)?;
+        if let Some(key) = key {
+            set_key(&conn, "key", key)?;
+        }
         configure_connection(&conn, &config)?;
+        let cipher = functions::install(&conn)?;
         init_schema(&conn)?;
-        Ok(Self { conn, config })
+        Ok(Self { conn, config, cipher })
     }
 
-    /// Open in-memory database for testing
-    #[cfg(test)]
+    /// Read and write credential metadata through `cipher` from now on
+    pub fn set_column_cipher(&self, cipher: Arc<dyn ColumnCipher>) {
+        self.cipher.set(cipher);
+    }
+
+    /// Re-encrypt an encrypted database under the passphrase `key`
+    pub fn rekey(&self, key: &str) -> DbResult<()> {
+        set_key(&self.conn, "rekey", key)
+    }
+
+    /// Copy the database to a new file at `dest`, encrypted under `key`, or
+    /// in plaintext with an empty key
+    pub fn export_to(&self, dest: &Path, key: &str) -> DbResult<()> {
+        if !ENCRYPTION_SUPPORTED {
+            return Err(DbError::EncryptionUnsupported);
+        }
+        let dest = dest.to_str().ok_or_else(|| DbError::MigrationFailed("non UTF-8 path".into()))?;
+        self.conn.execute("ATTACH DATABASE ?1 AS export KEY ?2", [dest, key])?;
+        let exported = self.conn.query_row("SELECT sqlcipher_export('export')", [], |_| Ok(()));
+        self.conn.execute_batch("DETACH DATABASE export")?;
+        exported.map_err(Into::into)
+    }
+
+    /// Open in-memory database for tests and staging imports
     pub fn open_in_memory() -> DbResult<Self> {
         Self::open(DatabaseConfig::in_memory())
     }
@@ -105,15 +154,23 @@ impl Database {
         self.config.path.exists()
     }
 
-    /// Vacuum the database to reclaim space
-    #[allow(dead_code)]
-    pub fn vacuum(&self) -> DbResult<()> {
-        self.conn.execute("VACUUM", [])?;
-        Ok(())
+    /// Rebuild the file to reclaim space, with `secure_delete` on so deleted
+    /// rows are zeroed rather than left in free pages, and empty the WAL into
+    /// it. Returns the bytes reclaimed.
+    pub fn vacuum(&self) -> DbResult<u64> {
+        let before = self.allocated()?;
+        self.conn.execute_batch("PRAGMA secure_delete = ON; VACUUM; PRAGMA wal_checkpoint(TRUNCATE);")?;
+        Ok(before.saturating_sub(self.allocated()?))
+    }
+
+    /// Bytes of pages in the database, free ones included
+    fn allocated(&self) -> DbResult<u64> {
+        let pages: u64 = self.conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+        let page_size: u64 = self.conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+        Ok(pages * page_size)
     }
 
     /// Get database size in bytes
-    #[allow(dead_code)]
     pub fn size(&self) -> std::io::Result<u64> {
         if self.config.path.to_str() == Some(":memory:") {
             return Ok(0);
@@ -134,6 +191,41 @@ impl Database {
     }
 }
 
+/// Run `f` as one transaction, so a batch of writes costs a single commit
+/// and a failure anywhere leaves none of them behind.
+///
+/// A savepoint rather than `BEGIN`, so a batch can run inside another one
+/// (a bulk delete logging to the audit trail, say) and only the outermost
+/// commits.
+pub fn in_transaction<T, E: From<DbError>>(conn: &Connection, f: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+    conn.execute_batch("SAVEPOINT batch").map_err(DbError::from)?;
+    let result = f().and_then(|value| {
+        conn.execute_batch("RELEASE batch").map_err(DbError::from)?;
+        Ok(value)
+    });
+    if result.is_err() {
+        let _ = conn.execute_batch("ROLLBACK TO batch; RELEASE batch");
+    }
+    result
+}
+
+/// Whether the file at `path` is an encrypted database rather than plain SQLite
+pub fn is_encrypted_file(path: &Path) -> bool {
+    let mut header = [0u8; 16];
+    let read = std::fs::File::open(path).and_then(|mut file| file.read_exact(&mut header));
+    read.is_ok() && &header != SQLITE_HEADER
+}
+
+/// Give SQLCipher the passphrase through `pragma` ("key" or "rekey"); a
+/// plain SQLite build would ignore the pragma and leave the file readable
+fn set_key(conn: &Connection, pragma: &str, key: &str) -> DbResult<()> {
+    if !ENCRYPTION_SUPPORTED {
+        return Err(DbError::EncryptionUnsupported);
+    }
+    conn.pragma_update(None, pragma, key)?;
+    Ok(())
+}
+
 fn ensure_parent_dir(config: &DatabaseConfig) -> DbResult<()> {
     if config.path.to_str() == Some(":memory:") {
         return Ok(());
@@ -188,6 +280,33 @@ mod tests {
         assert!(db.exists());
     }
 
+    #[test]
+    fn test_encrypted_database() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("vault.db");
+        let config = DatabaseConfig::with_path(&path);
+        insert_test_credential(Database::open(config.clone()).unwrap().conn()).unwrap();
+        assert!(!is_encrypted_file(&path));
+        assert!(!is_encrypted_file(&dir.path().join("missing.db")));
+
+        if !ENCRYPTION_SUPPORTED {
+            assert!(matches!(Database::open_with_key(config, Some("key")), Err(DbError::EncryptionUnsupported)));
+            return;
+        }
+        let encrypted = dir.path().join("encrypted.db");
+        Database::open(config).unwrap().export_to(&encrypted, "secret").unwrap();
+        assert!(is_encrypted_file(&encrypted));
+        assert!(!std::fs::read(&encrypted).unwrap().windows(4).any(|w| w == b"Test"));
+        assert!(Database::open_with_key(DatabaseConfig::with_path(&encrypted), Some("wrong")).is_err());
+
+        let db = Database::open_with_key(DatabaseConfig::with_path(&encrypted), Some("secret")).unwrap();
+        assert_eq!(count_test_credentials(&db), 1);
+        db.rekey("changed").unwrap();
+        drop(db);
+        let db = Database::open_with_key(DatabaseConfig::with_path(&encrypted), Some("changed")).unwrap();
+        assert_eq!(count_test_credentials(&db), 1);
+    }
+
     #[test]
     fn test_transaction() {
         let mut db = Database::open_in_memory().unwrap();
@@ -211,6 +330,51 @@ mod tests {
             .unwrap()
     }
 
+    #[test]
+    fn test_in_transaction() {
+        let db = Database::open_in_memory().unwrap();
+        let failed: DbResult<()> = in_transaction(db.conn(), || {
+            insert_test_credential(db.conn())?;
+            Err(DbError::NotFound("later row".into()))
+        });
+        assert!(failed.is_err());
+        assert_eq!(count_test_credentials(&db), 0);
+
+        // An inner batch joins the outer one and rolls back with it
+        let failed: DbResult<()> = in_transaction(db.conn(), || {
+            in_transaction(db.conn(), || insert_test_credential(db.conn()))?;
+            Err(DbError::NotFound("later row".into()))
+        });
+        assert!(failed.is_err());
+        assert_eq!(count_test_credentials(&db), 0);
+
+        assert_eq!(in_transaction(db.conn(), || insert_test_credential(db.conn())).unwrap(), 42);
+        assert_eq!(count_test_credentials(&db), 1);
+        assert!(db.conn().is_autocommit());
+    }
+
+    #[test]
+    fn test_vacuum() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("vault.db");
+        let db = Database::open(DatabaseConfig::with_path(&path)).unwrap();
+        let notes = "Lingering ".repeat(2000);
+        db.conn()
+            .execute(
+                "INSERT INTO credentials (id, name, credential_type, encrypted_secret, encrypted_notes, created_at, updated_at)
+                VALUES ('test', 'Test', 'password', 'encrypted', ?1, datetime('now'), datetime('now'))",
+                [&notes],
+            )
+            .unwrap();
+        db.conn().execute_batch("PRAGMA wal_checkpoint(TRUNCATE)").unwrap();
+        db.conn().execute("DELETE FROM credentials", []).unwrap();
+
+        assert!(db.vacuum().unwrap() > 0);
+        let file = std::fs::read(&path).unwrap();
+        assert!(!file.windows(9).any(|w| w == b"Lingering"));
+        assert_eq!(std::fs::metadata(dir.path().join("vault.db-wal")).map(|m| m.len()).unwrap_or(0), 0);
+    }
+
     #[test]
     fn test_foreign_keys_enabled() {
         let db = Database::open_in_memory().unwrap();
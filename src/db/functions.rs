@@ -0,0 +1,150 @@
+//! SQL Functions for Encrypted Metadata
+//!
+//! Queries pass credential names, usernames, URLs and tags through
+//! `vault_seal(id, value)` on the way in and `vault_open(id, value)` on the
+//! way out, and keep the search index with `vault_tokens(text)` (a JSON
+//! array) and `vault_token(term)`. The functions are registered once per
+//! connection and call whichever `ColumnCipher` its slot holds: plaintext at
+//! first, a keyed one once the vault is unlocked. Swapping the cipher works
+//! inside transactions, where SQLite refuses to replace functions, and the
+//! queries stay the same for either vault format.
+
+use std::sync::{Arc, RwLock};
+
+use rusqlite::functions::FunctionFlags;
+use rusqlite::Connection;
+
+use super::DbResult;
+
+/// What the metadata SQL functions do to values
+pub trait ColumnCipher: Send + Sync {
+    /// Value of credential `id` as stored
+    fn seal(&self, id: &str, value: &str) -> Result<String, String>;
+
+    /// Stored value of credential `id` as read
+    fn open(&self, id: &str, value: &str) -> String;
+
+    /// Search index tokens for a credential's text
+    fn tokens(&self, text: &str) -> Vec<String>;
+
+    /// The token a search term is looked up by, if the index is kept
+    fn token(&self, term: &str) -> Option<String>;
+}
+
+/// Stores and reads everything as-is, keeping no search index
+pub struct Plaintext;
+
+impl ColumnCipher for Plaintext {
+    fn seal(&self, _id: &str, value: &str) -> Result<String, String> {
+        Ok(value.to_string())
+    }
+
+    fn open(&self, _id: &str, value: &str) -> String {
+        value.to_string()
+    }
+
+    fn tokens(&self, _text: &str) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn token(&self, _term: &str) -> Option<String> {
+        None
+    }
+}
+
+/// The cipher behind one connection's metadata functions
+#[derive(Clone)]
+pub struct CipherSlot(Arc<RwLock<Arc<dyn ColumnCipher>>>);
+
+impl CipherSlot {
+    /// Use `cipher` from the next function call on
+    pub fn set(&self, cipher: Arc<dyn ColumnCipher>) {
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = cipher;
+    }
+
+    fn get(&self) -> Arc<dyn ColumnCipher> {
+        Arc::clone(&self.0.read().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+/// Register the metadata SQL functions on `conn`, starting out plaintext
+pub fn install(conn: &Connection) -> DbResult<CipherSlot> {
+    let slot = CipherSlot(Arc::new(RwLock::new(Arc::new(Plaintext))));
+    let flags = FunctionFlags::SQLITE_UTF8;
+
+    let sealer = slot.clone();
+    conn.create_scalar_function("vault_seal", 2, flags, move |ctx| {
+        let (id, value): (String, Option<String>) = (ctx.get(0)?, ctx.get(1)?);
+        value
+            .map(|v| sealer.get().seal(&id, &v))
+            .transpose()
+            .map_err(|e| rusqlite::Error::UserFunctionError(e.into()))
+    })?;
+
+    let opener = slot.clone();
+    conn.create_scalar_function("vault_open", 2, flags, move |ctx| {
+        let (id, value): (String, Option<String>) = (ctx.get(0)?, ctx.get(1)?);
+        Ok(value.map(|v| opener.get().open(&id, &v)))
+    })?;
+
+    let indexer = slot.clone();
+    conn.create_scalar_function("vault_tokens", 1, flags, move |ctx| {
+        let text: String = ctx.get(0)?;
+        Ok(serde_json::to_string(&indexer.get().tokens(&text)).unwrap_or_else(|_| "[]".to_string()))
+    })?;
+
+    let tokenizer = slot.clone();
+    conn.create_scalar_function("vault_token", 1, flags, move |ctx| {
+        let term: String = ctx.get(0)?;
+        Ok(tokenizer.get().token(&term))
+    })?;
+    Ok(slot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Reversed;
+
+    impl ColumnCipher for Reversed {
+        fn seal(&self, _id: &str, value: &str) -> Result<String, String> {
+            Ok(value.chars().rev().collect())
+        }
+
+        fn open(&self, _id: &str, value: &str) -> String {
+            value.chars().rev().collect()
+        }
+
+        fn tokens(&self, text: &str) -> Vec<String> {
+            text.split(' ').map(str::to_string).collect()
+        }
+
+        fn token(&self, term: &str) -> Option<String> {
+            Some(term.to_string())
+        }
+    }
+
+    #[test]
+    fn test_cipher_slot() {
+        let conn = Connection::open_in_memory().unwrap();
+        let slot = install(&conn).unwrap();
+        let plain: (String, Option<String>, String) = conn
+            .query_row("SELECT vault_seal('id', 'abc'), vault_token('abc'), vault_tokens('abc')", [], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(plain, ("abc".to_string(), None, "[]".to_string()));
+
+        conn.execute_batch("BEGIN").unwrap();
+        slot.set(Arc::new(Reversed));
+        let keyed: (String, String, Option<String>, String) = conn
+            .query_row(
+                "SELECT vault_seal('id', 'abc'), vault_open('id', vault_seal('id', 'abc')), vault_seal('id', NULL), vault_tokens('a b')",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .unwrap();
+        assert_eq!(keyed, ("cba".to_string(), "abc".to_string(), None, r#"["a","b"]"#.to_string()));
+    }
+}
@@ -3,6 +3,7 @@
 //! SQLite database layer with FTS5 full-text search.
 
 pub mod connection;
+pub mod functions;
 pub mod models;
 pub mod queries;
 pub mod schema;
@@ -18,6 +19,9 @@ pub enum DbError {
     #[error("Not found: {0}")]
     NotFound(String),
 
+    #[error("This build cannot encrypt the database; rebuild with --features sqlcipher")]
+    EncryptionUnsupported,
+
     #[error("Migration failed: {0}")]
     #[allow(dead_code)]
     MigrationFailed(String),
@@ -26,6 +30,6 @@ pub enum DbError {
 pub type DbResult<T> = Result<T, DbError>;
 
 // Re-exports
-pub use connection::{Database, DatabaseConfig};
-pub use models::{AuditAction, AuditLog, Credential, CredentialType};
+pub use connection::{in_transaction, is_encrypted_file, Database, DatabaseConfig, ENCRYPTION_SUPPORTED};
+pub use models::{AuditAction, AuditLog, Credential, CredentialType, TrashedCredential};
 pub use queries::*;
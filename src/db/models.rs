@@ -6,7 +6,7 @@ use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
 
 /// Credential type enum
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum CredentialType {
     Password,
@@ -19,6 +19,8 @@ pub enum CredentialType {
 }
 
 impl CredentialType {
+    pub const ALL: [Self; 7] = [Self::Password, Self::ApiKey, Self::SshKey, Self::Certificate, Self::Note, Self::Database, Self::Custom];
+
     pub fn as_str(&self) -> &'static str {
         match self {
             Self::Password => "password",
@@ -43,6 +45,13 @@ impl CredentialType {
         }
     }
 
+    /// A type as typed in a command: `api_key`, `api-key` or `apikey`
+    pub fn from_name(name: &str) -> Option<Self> {
+        let squash = |s: &str| s.to_lowercase().replace(['_', '-'], "");
+        let name = squash(name);
+        Self::ALL.into_iter().find(|t| squash(t.as_str()) == name)
+    }
+
     pub fn icon(&self) -> &'static str {
         match self {
             Self::Password => "󰌋",
@@ -78,6 +87,21 @@ pub struct Credential {
     pub encrypted_secret: String,
     pub encrypted_notes: Option<String>,
     pub encrypted_totp_secret: Option<String>,
+    /// Type-specific fields such as host or port, as encrypted JSON label/value pairs
+    #[serde(default)]
+    pub encrypted_fields: Option<String>,
+    /// When the credential stops working, e.g. the end of an API key's validity
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Local>>,
+    /// Days between secret changes before rotation is due
+    #[serde(default)]
+    pub rotate_every: Option<u32>,
+    /// When the secret last changed, `None` if not since the credential was created
+    #[serde(default)]
+    pub secret_changed_at: Option<DateTime<Local>>,
+    /// How to generate a new secret, e.g. `16 nosymbols` for a site with length limits
+    #[serde(default)]
+    pub password_rule: Option<String>,
     pub url: Option<String>,
     pub tags: Vec<String>,
     pub created_at: DateTime<Local>,
@@ -101,6 +125,11 @@ impl Credential {
             encrypted_secret,
             encrypted_notes: None,
             encrypted_totp_secret: None,
+            encrypted_fields: None,
+            expires_at: None,
+            rotate_every: None,
+            secret_changed_at: None,
+            password_rule: None,
             url: None,
             tags: Vec::new(),
             created_at: now,
@@ -108,6 +137,31 @@ impl Credential {
             accessed_at: None,
         }
     }
+
+    /// When the secret was last set, by its change or the credential's creation
+    pub fn secret_set_at(&self) -> DateTime<Local> {
+        self.secret_changed_at.unwrap_or(self.created_at)
+    }
+
+    /// When the secret is next due for rotation, if a rotation interval is set
+    pub fn rotation_due(&self) -> Option<DateTime<Local>> {
+        let days = self.rotate_every?;
+        Some(self.secret_set_at() + chrono::Duration::days(days as i64))
+    }
+}
+
+/// A deleted credential waiting in the trash, still encrypted
+#[derive(Debug, Clone)]
+pub struct TrashedCredential {
+    pub credential: Credential,
+    pub deleted_at: DateTime<Local>,
+}
+
+/// A credential as it was before an update, still encrypted
+#[derive(Debug, Clone)]
+pub struct CredentialRevision {
+    pub credential: Credential,
+    pub revised_at: DateTime<Local>,
 }
 
 /// Audit action types
@@ -124,6 +178,10 @@ pub enum AuditAction {
     Unlock,
     Lock,
     FailedUnlock,
+    Backup,
+    Restore,
+    /// Old entries removed by the retention policy
+    Prune,
 }
 
 impl AuditAction {
@@ -139,6 +197,9 @@ impl AuditAction {
             Self::Unlock => "unlock",
             Self::Lock => "lock",
             Self::FailedUnlock => "failed_unlock",
+            Self::Backup => "backup",
+            Self::Restore => "restore",
+            Self::Prune => "prune",
         }
     }
 
@@ -154,6 +215,9 @@ impl AuditAction {
             "unlock" => Self::Unlock,
             "lock" => Self::Lock,
             "failed_unlock" => Self::FailedUnlock,
+            "backup" => Self::Backup,
+            "restore" => Self::Restore,
+            "prune" => Self::Prune,
             _ => Self::Read,
         }
     }
@@ -6,7 +6,7 @@ use chrono::{DateTime, Local};
 use rusqlite::{params, Connection, Row};
 
 use super::{
-    models::{AuditAction, AuditLog, Credential, CredentialType},
+    models::{AuditAction, AuditLog, Credential, CredentialRevision, CredentialType, TrashedCredential},
     DbError, DbResult,
 };
 
@@ -18,42 +18,47 @@ use super::{
 pub fn create_credential(conn: &Connection, credential: &Credential) -> DbResult<()> {
     let tags_json = serde_json::to_string(&credential.tags).unwrap_or_else(|_| "[]".to_string());
 
-    conn.execute(
+    conn.prepare_cached(
         r#"
-        INSERT INTO credentials (id, name, credential_type, username, encrypted_secret, encrypted_notes, encrypted_totp_secret, url, tags, created_at, updated_at, accessed_at)
-        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+        INSERT INTO credentials (id, name, credential_type, username, encrypted_secret, encrypted_notes, encrypted_totp_secret, url, tags, created_at, updated_at, accessed_at, encrypted_fields, expires_at, rotate_every, secret_changed_at, password_rule)
+        VALUES (?1, vault_seal(?1, ?2), ?3, vault_seal(?1, ?4), ?5, ?6, ?7, vault_seal(?1, ?8), vault_seal(?1, ?9), ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)
         "#,
-        params![
-            credential.id,
-            credential.name,
-            credential.credential_type.as_str(),
-            credential.username,
-            credential.encrypted_secret,
-            credential.encrypted_notes,
-            credential.encrypted_totp_secret,
-            credential.url,
-            tags_json,
-            credential.created_at.to_rfc3339(),
-            credential.updated_at.to_rfc3339(),
-            credential.accessed_at.map(|dt| dt.to_rfc3339()),
-        ],
-    )?;
+    )?
+    .execute(params![
+        credential.id,
+        credential.name,
+        credential.credential_type.as_str(),
+        credential.username,
+        credential.encrypted_secret,
+        credential.encrypted_notes,
+        credential.encrypted_totp_secret,
+        credential.url,
+        tags_json,
+        credential.created_at.to_rfc3339(),
+        credential.updated_at.to_rfc3339(),
+        credential.accessed_at.map(|dt| dt.to_rfc3339()),
+        credential.encrypted_fields,
+        credential.expires_at.map(|dt| dt.to_rfc3339()),
+        credential.rotate_every,
+        credential.secret_changed_at.map(|dt| dt.to_rfc3339()),
+        credential.password_rule,
+    ])?;
+    conn.prepare_cached("DELETE FROM tombstones WHERE id = ?1")?.execute([&credential.id])?;
+
+    index_credential(conn, credential)
+}
 
-    Ok(())
+/// Insert or overwrite a credential as-is, keeping its timestamps (used by sync)
+pub fn replace_credential(conn: &Connection, credential: &Credential) -> DbResult<()> {
+    conn.prepare_cached("DELETE FROM credentials WHERE id = ?1")?.execute([&credential.id])?;
+    create_credential(conn, credential)
 }
 
 /// Get a credential by ID
 pub fn get_credential(conn: &Connection, id: &str) -> DbResult<Credential> {
-    conn.query_row(
-        r#"
-        SELECT id, name, credential_type, username, encrypted_secret, encrypted_notes, encrypted_totp_secret, url, tags, created_at, updated_at, accessed_at
-        FROM credentials
-        WHERE id = ?1
-        "#,
-        [id],
-        row_to_credential,
-    )
-    .map_err(|e| match e {
+    conn.prepare_cached(&format!("SELECT {} FROM credentials WHERE id = ?1", OPENED_COLUMNS))?
+        .query_row([id], row_to_credential)
+        .map_err(|e| match e {
         rusqlite::Error::QueryReturnedNoRows => DbError::NotFound(format!("Credential: {}", id)),
         _ => e.into(),
     })
@@ -61,13 +66,10 @@ pub fn get_credential(conn: &Connection, id: &str) -> DbResult<Credential> {
 
 /// Get all credentials
 pub fn get_all_credentials(conn: &Connection) -> DbResult<Vec<Credential>> {
-    let mut stmt = conn.prepare(
-        r#"
-        SELECT id, name, credential_type, username, encrypted_secret, encrypted_notes, encrypted_totp_secret, url, tags, created_at, updated_at, accessed_at
-        FROM credentials
-        ORDER BY name
-        "#,
-    )?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM credentials ORDER BY vault_open(id, name)",
+        OPENED_COLUMNS
+    ))?;
 
     let credentials = stmt
         .query_map([], row_to_credential)?
@@ -78,6 +80,8 @@ pub fn get_all_credentials(conn: &Connection) -> DbResult<Vec<Credential>> {
 }
 
 /// Get credentials by tags (AND logic - must have all tags)
+///
+/// A parent tag also matches its nested tags, so `work` finds `work/aws`.
 pub fn get_credentials_by_tag(conn: &Connection, tags: &[String]) -> DbResult<Vec<Credential>> {
     if tags.is_empty() {
         return get_all_credentials(conn);
@@ -87,22 +91,21 @@ pub fn get_credentials_by_tag(conn: &Connection, tags: &[String]) -> DbResult<Ve
     let conditions: Vec<String> = tags
         .iter()
         .enumerate()
-        .map(|(i, _)| format!("tags LIKE ?{}", i + 1))
+        .map(|(i, _)| format!("(vault_open(id, tags) LIKE ?{} OR vault_open(id, tags) LIKE ?{})", 2 * i + 1, 2 * i + 2))
         .collect();
     
     let query = format!(
-        r#"
-        SELECT id, name, credential_type, username, encrypted_secret, encrypted_notes, encrypted_totp_secret, url, tags, created_at, updated_at, accessed_at
-        FROM credentials
-        WHERE {}
-        ORDER BY name
-        "#,
+        "SELECT {} FROM credentials WHERE {} ORDER BY vault_open(id, name)",
+        OPENED_COLUMNS,
         conditions.join(" AND ")
     );
 
     let mut stmt = conn.prepare(&query)?;
     
-    let patterns: Vec<String> = tags.iter().map(|t| format!("%\"{}\"%", t)).collect();
+    let patterns: Vec<String> = tags
+        .iter()
+        .flat_map(|t| [format!("%\"{}\"%", t), format!("%\"{}/%", t)])
+        .collect();
     let params: Vec<&dyn rusqlite::ToSql> = patterns.iter().map(|p| p as &dyn rusqlite::ToSql).collect();
     
     let credentials = stmt
@@ -113,8 +116,12 @@ pub fn get_credentials_by_tag(conn: &Connection, tags: &[String]) -> DbResult<Ve
     Ok(credentials)
 }
 
-/// Search credentials using FTS5
+/// Search credentials using FTS5, or the search index when metadata is encrypted
 pub fn search_credentials(conn: &Connection, query: &str) -> DbResult<Vec<Credential>> {
+    if keeps_search_index(conn) {
+        return search_index(conn, query);
+    }
+
     // Escape special FTS5 characters
     let escaped_query = query
         .replace('"', "\"\"")
@@ -129,7 +136,7 @@ pub fn search_credentials(conn: &Connection, query: &str) -> DbResult<Vec<Creden
 
     let mut stmt = conn.prepare(
         r#"
-        SELECT c.id, c.name, c.credential_type, c.username, c.encrypted_secret, c.encrypted_notes, c.encrypted_totp_secret, c.url, c.tags, c.created_at, c.updated_at, c.accessed_at
+        SELECT c.id, c.name, c.credential_type, c.username, c.encrypted_secret, c.encrypted_notes, c.encrypted_totp_secret, c.url, c.tags, c.created_at, c.updated_at, c.accessed_at, c.encrypted_fields, c.expires_at, c.rotate_every, c.secret_changed_at, c.password_rule
         FROM credentials c
         INNER JOIN credentials_fts fts ON c.rowid = fts.rowid
         WHERE credentials_fts MATCH ?1
@@ -145,37 +152,128 @@ pub fn search_credentials(conn: &Connection, query: &str) -> DbResult<Vec<Creden
     Ok(credentials)
 }
 
+/// Credentials with, for every word of `query`, a word starting with it
+fn search_index(conn: &Connection, query: &str) -> DbResult<Vec<Credential>> {
+    let words: Vec<&str> = query.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).collect();
+    if words.is_empty() {
+        return get_all_credentials(conn);
+    }
+
+    let conditions: Vec<String> = (1..=words.len())
+        .map(|i| format!("id IN (SELECT id FROM search_tokens WHERE token = vault_token(?{}))", i))
+        .collect();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM credentials WHERE {} ORDER BY vault_open(id, name)",
+        OPENED_COLUMNS,
+        conditions.join(" AND ")
+    ))?;
+    let credentials = stmt
+        .query_map(rusqlite::params_from_iter(words), row_to_credential)?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(credentials)
+}
+
+/// Whether the installed column cipher keeps the search index
+fn keeps_search_index(conn: &Connection) -> bool {
+    conn.query_row("SELECT vault_token('') IS NOT NULL", [], |row| row.get(0)).unwrap_or(false)
+}
+
+/// Replace the search index tokens of a credential
+fn index_credential(conn: &Connection, credential: &Credential) -> DbResult<()> {
+    conn.prepare_cached("DELETE FROM search_tokens WHERE id = ?1")?.execute([&credential.id])?;
+    let mut text = vec![credential.name.as_str()];
+    text.extend(credential.username.as_deref());
+    text.extend(credential.url.as_deref());
+    text.extend(credential.tags.iter().map(String::as_str));
+    conn.prepare_cached("INSERT OR IGNORE INTO search_tokens (id, token) SELECT ?1, value FROM json_each(vault_tokens(?2))")?
+        .execute(params![credential.id, text.join(" ")])?;
+    Ok(())
+}
+
+/// Index the credentials without search tokens, or with `all` every
+/// credential again (after the search key changed)
+pub fn update_search_index(conn: &Connection, all: bool) -> DbResult<()> {
+    if all {
+        conn.execute("DELETE FROM search_tokens", [])?;
+    }
+    let indexed: std::collections::HashSet<String> = conn
+        .prepare("SELECT DISTINCT id FROM search_tokens")?
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    for credential in get_all_credentials(conn)?.iter().filter(|c| !indexed.contains(&c.id)) {
+        index_credential(conn, credential)?;
+    }
+    Ok(())
+}
+
 /// Update a credential
 pub fn update_credential(conn: &Connection, credential: &Credential) -> DbResult<()> {
+    record_revision(conn, &credential.id)?;
     let tags_json = serde_json::to_string(&credential.tags).unwrap_or_else(|_| "[]".to_string());
 
-    let rows = conn.execute(
+    let rows = conn.prepare_cached(
         r#"
         UPDATE credentials
-        SET name = ?2, credential_type = ?3, username = ?4, encrypted_secret = ?5, encrypted_notes = ?6, encrypted_totp_secret = ?7, url = ?8, tags = ?9, updated_at = ?10
+        SET name = vault_seal(?1, ?2), credential_type = ?3, username = vault_seal(?1, ?4), encrypted_secret = ?5, encrypted_notes = ?6, encrypted_totp_secret = ?7,
+            url = vault_seal(?1, ?8), tags = vault_seal(?1, ?9), updated_at = ?10, encrypted_fields = ?11,
+            expires_at = ?12, rotate_every = ?13, secret_changed_at = ?14, password_rule = ?15
         WHERE id = ?1
         "#,
-        params![
-            credential.id,
-            credential.name,
-            credential.credential_type.as_str(),
-            credential.username,
-            credential.encrypted_secret,
-            credential.encrypted_notes,
-            credential.encrypted_totp_secret,
-            credential.url,
-            tags_json,
-            Local::now().to_rfc3339(),
-        ],
-    )?;
+    )?
+    .execute(params![
+        credential.id,
+        credential.name,
+        credential.credential_type.as_str(),
+        credential.username,
+        credential.encrypted_secret,
+        credential.encrypted_notes,
+        credential.encrypted_totp_secret,
+        credential.url,
+        tags_json,
+        Local::now().to_rfc3339(),
+        credential.encrypted_fields,
+        credential.expires_at.map(|dt| dt.to_rfc3339()),
+        credential.rotate_every,
+        credential.secret_changed_at.map(|dt| dt.to_rfc3339()),
+        credential.password_rule,
+    ])?;
 
     if rows == 0 {
         return Err(DbError::NotFound(format!("Credential: {}", credential.id)));
     }
 
+    index_credential(conn, credential)
+}
+
+/// Keep the stored version of a credential as a revision before it changes
+fn record_revision(conn: &Connection, id: &str) -> DbResult<()> {
+    conn.prepare_cached(&format!(
+        "INSERT INTO revisions ({cols}, revised_at) SELECT {cols}, ?2 FROM credentials WHERE id = ?1",
+        cols = CREDENTIAL_COLUMNS
+    ))?
+    .execute(params![id, Local::now().to_rfc3339()])?;
     Ok(())
 }
 
+/// Earlier versions of a credential, oldest first
+pub fn get_revisions(conn: &Connection, id: &str) -> DbResult<Vec<CredentialRevision>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}, revised_at FROM revisions WHERE id = ?1 ORDER BY revision",
+        OPENED_COLUMNS
+    ))?;
+    let revisions = stmt
+        .query_map([id], |row| {
+            Ok(CredentialRevision {
+                credential: row_to_credential(row)?,
+                revised_at: parse_datetime(row.get(17)?),
+            })
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(revisions)
+}
+
 /// Update credential access time
 pub fn touch_credential(conn: &Connection, id: &str) -> DbResult<()> {
     conn.execute(
@@ -185,22 +283,133 @@ pub fn touch_credential(conn: &Connection, id: &str) -> DbResult<()> {
     Ok(())
 }
 
-/// Delete a credential
+/// Set a credential's expiry date and rotation interval without recording a revision
+pub fn set_reminders(conn: &Connection, id: &str, expires_at: Option<DateTime<Local>>, rotate_every: Option<u32>) -> DbResult<()> {
+    conn.execute(
+        "UPDATE credentials SET expires_at = ?2, rotate_every = ?3 WHERE id = ?1",
+        params![id, expires_at.map(|dt| dt.to_rfc3339()), rotate_every],
+    )?;
+    Ok(())
+}
+
+/// Set a credential's password rule without recording a revision
+pub fn set_password_rule(conn: &Connection, id: &str, rule: Option<&str>) -> DbResult<()> {
+    conn.execute("UPDATE credentials SET password_rule = ?2 WHERE id = ?1", params![id, rule])?;
+    Ok(())
+}
+
+/// Move a credential to the trash, leaving a tombstone for sync
 pub fn delete_credential(conn: &Connection, id: &str) -> DbResult<()> {
-    let rows = conn.execute("DELETE FROM credentials WHERE id = ?1", [id])?;
+    let now = Local::now();
+    let rows = conn
+        .prepare_cached(&format!(
+            "INSERT OR REPLACE INTO trash ({cols}, deleted_at) SELECT {cols}, ?2 FROM credentials WHERE id = ?1",
+            cols = CREDENTIAL_COLUMNS
+        ))?
+        .execute(params![id, now.to_rfc3339()])?;
 
     if rows == 0 {
         return Err(DbError::NotFound(format!("Credential: {}", id)));
     }
+    conn.prepare_cached("DELETE FROM credentials WHERE id = ?1")?.execute([id])?;
+    conn.prepare_cached("DELETE FROM search_tokens WHERE id = ?1")?.execute([id])?;
+
+    record_tombstone(conn, id, now)
+}
 
+/// Remember that a credential was deleted at `deleted_at`, keeping the latest time
+pub fn record_tombstone(conn: &Connection, id: &str, deleted_at: DateTime<Local>) -> DbResult<()> {
+    conn.prepare_cached(
+        r#"
+        INSERT INTO tombstones (id, deleted_at) VALUES (?1, ?2)
+        ON CONFLICT(id) DO UPDATE SET deleted_at = MAX(deleted_at, excluded.deleted_at)
+        "#,
+    )?
+    .execute(params![id, deleted_at.to_rfc3339()])?;
     Ok(())
 }
 
+/// All tombstones as (credential ID, deletion time)
+pub fn get_tombstones(conn: &Connection) -> DbResult<Vec<(String, DateTime<Local>)>> {
+    let mut stmt = conn.prepare("SELECT id, deleted_at FROM tombstones")?;
+    let tombstones = stmt
+        .query_map([], |row| Ok((row.get(0)?, parse_datetime(row.get(1)?))))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(tombstones)
+}
+
+// ============================================================================
+// Trash Queries
+// ============================================================================
+
+/// Trashed credentials, most recently deleted first
+pub fn get_trash(conn: &Connection) -> DbResult<Vec<TrashedCredential>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}, deleted_at FROM trash ORDER BY deleted_at DESC",
+        OPENED_COLUMNS
+    ))?;
+    let trash = stmt
+        .query_map([], |row| {
+            Ok(TrashedCredential {
+                credential: row_to_credential(row)?,
+                deleted_at: parse_datetime(row.get(17)?),
+            })
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(trash)
+}
+
+/// Move a credential back out of the trash; it counts as updated now, so sync
+/// keeps it over the tombstone its deletion left behind
+pub fn restore_from_trash(conn: &Connection, id: &str) -> DbResult<Credential> {
+    let mut credential = conn
+        .query_row(
+            &format!("SELECT {} FROM trash WHERE id = ?1", OPENED_COLUMNS),
+            [id],
+            row_to_credential,
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => DbError::NotFound(format!("Trashed credential: {}", id)),
+            _ => e.into(),
+        })?;
+
+    credential.updated_at = Local::now();
+    create_credential(conn, &credential)?;
+    conn.execute("DELETE FROM trash WHERE id = ?1", [id])?;
+    Ok(credential)
+}
+
+/// Permanently delete one trashed credential
+pub fn purge_from_trash(conn: &Connection, id: &str) -> DbResult<()> {
+    let rows = conn.execute("DELETE FROM trash WHERE id = ?1", [id])?;
+    if rows == 0 {
+        return Err(DbError::NotFound(format!("Trashed credential: {}", id)));
+    }
+    conn.execute("DELETE FROM revisions WHERE id = ?1", [id])?;
+    Ok(())
+}
+
+/// Permanently delete everything in the trash, returning how many were purged
+pub fn empty_trash(conn: &Connection) -> DbResult<usize> {
+    conn.execute("DELETE FROM revisions WHERE id IN (SELECT id FROM trash)", [])?;
+    Ok(conn.execute("DELETE FROM trash", [])?)
+}
+
+/// Credential columns as stored, for copying rows between tables
+const CREDENTIAL_COLUMNS: &str = "id, name, credential_type, username, encrypted_secret, encrypted_notes, encrypted_totp_secret, url, tags, created_at, updated_at, accessed_at, encrypted_fields, expires_at, rotate_every, secret_changed_at, password_rule";
+
+/// Columns read by `row_to_credential`, in order, with metadata opened
+const OPENED_COLUMNS: &str = "id, vault_open(id, name), credential_type, vault_open(id, username), encrypted_secret, encrypted_notes, encrypted_totp_secret, vault_open(id, url), vault_open(id, tags), created_at, updated_at, accessed_at, encrypted_fields, expires_at, rotate_every, secret_changed_at, password_rule";
+
 fn row_to_credential(row: &Row) -> rusqlite::Result<Credential> {
     let tags_json: String = row.get(8)?;
     let tags: Vec<String> = serde_json::from_str(&tags_json).unwrap_or_default();
 
     let accessed_at: Option<String> = row.get(11)?;
+    let expires_at: Option<String> = row.get(13)?;
+    let secret_changed_at: Option<String> = row.get(15)?;
 
     Ok(Credential {
         id: row.get(0)?,
@@ -210,6 +419,11 @@ fn row_to_credential(row: &Row) -> rusqlite::Result<Credential> {
         encrypted_secret: row.get(4)?,
         encrypted_notes: row.get(5)?,
         encrypted_totp_secret: row.get(6)?,
+        encrypted_fields: row.get(12)?,
+        expires_at: expires_at.map(parse_datetime),
+        rotate_every: row.get(14)?,
+        secret_changed_at: secret_changed_at.map(parse_datetime),
+        password_rule: row.get(16)?,
         url: row.get(7)?,
         tags,
         created_at: parse_datetime(row.get::<_, String>(9)?),
@@ -224,35 +438,28 @@ fn row_to_credential(row: &Row) -> rusqlite::Result<Credential> {
 
 /// Create an audit log entry
 pub fn create_audit_log(conn: &Connection, log: &AuditLog) -> DbResult<i64> {
-    conn.execute(
+    conn.prepare_cached(
         r#"
         INSERT INTO audit_log (timestamp, action, credential_id, credential_name, username, details, hmac)
-        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+        VALUES (?1, ?2, ?3, vault_seal(COALESCE(?3, ''), ?4), vault_seal(COALESCE(?3, ''), ?5), ?6, ?7)
         "#,
-        params![
-            log.timestamp.to_rfc3339(),
-            log.action.as_str(),
-            log.credential_id,
-            log.credential_name,
-            log.username,
-            log.details,
-            log.hmac,
-        ],
-    )?;
+    )?
+    .execute(params![
+        log.timestamp.to_rfc3339(),
+        log.action.as_str(),
+        log.credential_id,
+        log.credential_name,
+        log.username,
+        log.details,
+        log.hmac,
+    ])?;
 
     Ok(conn.last_insert_rowid())
 }
 
 /// Get recent audit logs
 pub fn get_recent_audit_logs(conn: &Connection, limit: usize) -> DbResult<Vec<AuditLog>> {
-    let mut stmt = conn.prepare(
-        r#"
-        SELECT id, timestamp, action, credential_id, credential_name, username, details, hmac
-        FROM audit_log
-        ORDER BY timestamp DESC
-        LIMIT ?1
-        "#,
-    )?;
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM audit_log ORDER BY timestamp DESC LIMIT ?1", AUDIT_COLUMNS))?;
 
     let logs = stmt
         .query_map([limit], row_to_audit_log)?
@@ -262,16 +469,24 @@ pub fn get_recent_audit_logs(conn: &Connection, limit: usize) -> DbResult<Vec<Au
     Ok(logs)
 }
 
+/// Get every audit log, oldest first
+pub fn get_all_audit_logs(conn: &Connection) -> DbResult<Vec<AuditLog>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM audit_log ORDER BY id ASC", AUDIT_COLUMNS))?;
+
+    let logs = stmt
+        .query_map([], row_to_audit_log)?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(logs)
+}
+
 /// Get audit logs for a credential
 pub fn get_credential_audit_logs(conn: &Connection, credential_id: &str) -> DbResult<Vec<AuditLog>> {
-    let mut stmt = conn.prepare(
-        r#"
-        SELECT id, timestamp, action, credential_id, credential_name, username, details, hmac
-        FROM audit_log
-        WHERE credential_id = ?1
-        ORDER BY timestamp DESC
-        "#,
-    )?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM audit_log WHERE credential_id = ?1 ORDER BY timestamp DESC",
+        AUDIT_COLUMNS
+    ))?;
 
     let logs = stmt
         .query_map([credential_id], row_to_audit_log)?
@@ -281,6 +496,31 @@ pub fn get_credential_audit_logs(conn: &Connection, credential_id: &str) -> DbRe
     Ok(logs)
 }
 
+/// Number of audit logs
+pub fn count_audit_logs(conn: &Connection) -> DbResult<usize> {
+    Ok(conn.query_row("SELECT COUNT(*) FROM audit_log", [], |row| row.get(0))?)
+}
+
+/// Id and time of every audit log, oldest first
+pub fn get_audit_log_times(conn: &Connection) -> DbResult<Vec<(i64, DateTime<Local>)>> {
+    let mut stmt = conn.prepare("SELECT id, timestamp FROM audit_log ORDER BY id ASC")?;
+
+    let times = stmt
+        .query_map([], |row| Ok((row.get(0)?, parse_datetime(row.get(1)?))))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(times)
+}
+
+/// Delete the audit logs up to and including `id`
+pub fn delete_audit_logs_through(conn: &Connection, id: i64) -> DbResult<usize> {
+    Ok(conn.execute("DELETE FROM audit_log WHERE id <= ?1", [id])?)
+}
+
+/// Columns read by `row_to_audit_log`, in order, with the credential's name and username opened
+const AUDIT_COLUMNS: &str = "id, timestamp, action, credential_id, vault_open(COALESCE(credential_id, ''), credential_name), vault_open(COALESCE(credential_id, ''), username), details, hmac";
+
 fn row_to_audit_log(row: &Row) -> rusqlite::Result<AuditLog> {
     Ok(AuditLog {
         id: row.get(0)?,
@@ -328,8 +568,50 @@ mod tests {
         assert_eq!(fetched.name, "GitHub Token");
         assert_eq!(fetched.tags.len(), 2);
 
+        let mut renamed = fetched.clone();
+        renamed.name = "GitHub PAT".to_string();
+        update_credential(conn, &renamed).unwrap();
+        let revisions = get_revisions(conn, &cred.id).unwrap();
+        assert_eq!(revisions.len(), 1);
+        assert_eq!(revisions[0].credential.name, "GitHub Token");
+        assert_eq!(get_credential(conn, &cred.id).unwrap().name, "GitHub PAT");
+
         delete_credential(conn, &cred.id).unwrap();
         assert!(get_credential(conn, &cred.id).is_err());
+        assert_eq!(get_tombstones(conn).unwrap()[0].0, cred.id);
+
+        create_credential(conn, &cred).unwrap();
+        assert!(get_tombstones(conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_trash_restore_and_purge() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+
+        let keep = Credential::new("GitHub".to_string(), CredentialType::Password, "secret".to_string());
+        let purge = Credential::new("GitLab".to_string(), CredentialType::Password, "secret".to_string());
+        create_credential(conn, &keep).unwrap();
+        create_credential(conn, &purge).unwrap();
+
+        delete_credential(conn, &keep.id).unwrap();
+        delete_credential(conn, &purge.id).unwrap();
+        assert!(get_all_credentials(conn).unwrap().is_empty());
+
+        let trash = get_trash(conn).unwrap();
+        assert_eq!(trash.len(), 2);
+        assert_eq!(trash.iter().find(|t| t.credential.id == keep.id).unwrap().credential.encrypted_secret, "secret");
+
+        let restored = restore_from_trash(conn, &keep.id).unwrap();
+        assert!(restored.updated_at > keep.updated_at);
+        assert_eq!(get_credential(conn, &keep.id).unwrap().name, "GitHub");
+        assert!(get_tombstones(conn).unwrap().iter().all(|(id, _)| *id != keep.id));
+
+        purge_from_trash(conn, &purge.id).unwrap();
+        assert!(get_trash(conn).unwrap().is_empty());
+        assert!(get_revisions(conn, &purge.id).unwrap().is_empty());
+        assert!(restore_from_trash(conn, &purge.id).is_err());
+        assert_eq!(empty_trash(conn).unwrap(), 0);
     }
 
     #[test]
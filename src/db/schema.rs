@@ -8,7 +8,7 @@ use super::DbResult;
 
 /// Current schema version
 #[allow(dead_code)]
-pub const SCHEMA_VERSION: i32 = 3;
+pub const SCHEMA_VERSION: i32 = 10;
 
 /// Initialize the database schema
 pub fn init_schema(conn: &Connection) -> DbResult<()> {
@@ -31,10 +31,31 @@ pub fn init_schema(conn: &Connection) -> DbResult<()> {
 
 fn migrate_schema(conn: &Connection) -> DbResult<()> {
     let version = get_schema_version(conn).unwrap_or(0);
-    if version >= 3 {
-        return Ok(());
+    if version < 3 {
+        migrate_to_v3(conn)?;
     }
-    migrate_to_v3(conn)
+    if version < 4 {
+        migrate_to_v4(conn)?;
+    }
+    if version < 5 {
+        migrate_to_v5(conn)?;
+    }
+    if version < 6 {
+        migrate_to_v6(conn)?;
+    }
+    if version < 7 {
+        migrate_to_v7(conn)?;
+    }
+    if version < 8 {
+        migrate_to_v8(conn)?;
+    }
+    if version < 9 {
+        migrate_to_v9(conn)?;
+    }
+    if version < 10 {
+        migrate_to_v10(conn)?;
+    }
+    Ok(())
 }
 
 fn migrate_to_v3(conn: &Connection) -> DbResult<()> {
@@ -45,6 +66,115 @@ fn migrate_to_v3(conn: &Connection) -> DbResult<()> {
     Ok(())
 }
 
+fn migrate_to_v4(conn: &Connection) -> DbResult<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS tombstones (
+            id TEXT PRIMARY KEY,
+            deleted_at TEXT NOT NULL
+        );
+        INSERT OR REPLACE INTO metadata (key, value) VALUES ('schema_version', '4');
+        "#,
+    )?;
+    Ok(())
+}
+
+fn migrate_to_v5(conn: &Connection) -> DbResult<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS trash (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            credential_type TEXT NOT NULL,
+            username TEXT,
+            encrypted_secret TEXT NOT NULL,
+            encrypted_notes TEXT,
+            encrypted_totp_secret TEXT,
+            url TEXT,
+            tags TEXT NOT NULL DEFAULT '[]',
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            accessed_at TEXT,
+            deleted_at TEXT NOT NULL
+        );
+        INSERT OR REPLACE INTO metadata (key, value) VALUES ('schema_version', '5');
+        "#,
+    )?;
+    Ok(())
+}
+
+fn migrate_to_v6(conn: &Connection) -> DbResult<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS revisions (
+            revision INTEGER PRIMARY KEY AUTOINCREMENT,
+            id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            credential_type TEXT NOT NULL,
+            username TEXT,
+            encrypted_secret TEXT NOT NULL,
+            encrypted_notes TEXT,
+            encrypted_totp_secret TEXT,
+            url TEXT,
+            tags TEXT NOT NULL DEFAULT '[]',
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            accessed_at TEXT,
+            revised_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_revisions_id ON revisions(id);
+        INSERT OR REPLACE INTO metadata (key, value) VALUES ('schema_version', '6');
+        "#,
+    )?;
+    Ok(())
+}
+
+fn migrate_to_v7(conn: &Connection) -> DbResult<()> {
+    for table in ["credentials", "trash", "revisions"] {
+        if !has_column(conn, table, "encrypted_fields") {
+            conn.execute(&format!("ALTER TABLE {} ADD COLUMN encrypted_fields TEXT", table), [])?;
+        }
+    }
+    conn.execute("INSERT OR REPLACE INTO metadata (key, value) VALUES ('schema_version', '7')", [])?;
+    Ok(())
+}
+
+fn migrate_to_v8(conn: &Connection) -> DbResult<()> {
+    for table in ["credentials", "trash", "revisions"] {
+        for (column, sql_type) in [("expires_at", "TEXT"), ("rotate_every", "INTEGER"), ("secret_changed_at", "TEXT")] {
+            if !has_column(conn, table, column) {
+                conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, sql_type), [])?;
+            }
+        }
+    }
+    conn.execute("INSERT OR REPLACE INTO metadata (key, value) VALUES ('schema_version', '8')", [])?;
+    Ok(())
+}
+
+fn migrate_to_v9(conn: &Connection) -> DbResult<()> {
+    for table in ["credentials", "trash", "revisions"] {
+        if !has_column(conn, table, "password_rule") {
+            conn.execute(&format!("ALTER TABLE {} ADD COLUMN password_rule TEXT", table), [])?;
+        }
+    }
+    conn.execute("INSERT OR REPLACE INTO metadata (key, value) VALUES ('schema_version', '9')", [])?;
+    Ok(())
+}
+
+fn migrate_to_v10(conn: &Connection) -> DbResult<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS search_tokens (
+            id TEXT NOT NULL,
+            token TEXT NOT NULL,
+            PRIMARY KEY (token, id)
+        ) WITHOUT ROWID;
+        INSERT OR REPLACE INTO metadata (key, value) VALUES ('schema_version', '10');
+        "#,
+    )?;
+    Ok(())
+}
+
 fn has_column(conn: &Connection, table: &str, column: &str) -> bool {
     let sql = format!(
         "SELECT COUNT(*) > 0 FROM pragma_table_info('{}') WHERE name='{}'",
@@ -76,7 +206,12 @@ fn create_schema(conn: &Connection) -> DbResult<()> {
             tags TEXT NOT NULL DEFAULT '[]',
             created_at TEXT NOT NULL,
             updated_at TEXT NOT NULL,
-            accessed_at TEXT
+            accessed_at TEXT,
+            encrypted_fields TEXT,
+            expires_at TEXT,
+            rotate_every INTEGER,
+            secret_changed_at TEXT,
+            password_rule TEXT
         );
 
         -- FTS5 virtual table for full-text search
@@ -107,6 +242,65 @@ fn create_schema(conn: &Connection) -> DbResult<()> {
             VALUES (new.rowid, new.name, new.username, new.url, new.tags);
         END;
 
+        -- Deleted credential IDs, so sync can tell deletions from additions
+        CREATE TABLE IF NOT EXISTS tombstones (
+            id TEXT PRIMARY KEY,
+            deleted_at TEXT NOT NULL
+        );
+
+        -- Deleted credentials, still encrypted, until restored or purged
+        CREATE TABLE IF NOT EXISTS trash (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            credential_type TEXT NOT NULL,
+            username TEXT,
+            encrypted_secret TEXT NOT NULL,
+            encrypted_notes TEXT,
+            encrypted_totp_secret TEXT,
+            url TEXT,
+            tags TEXT NOT NULL DEFAULT '[]',
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            accessed_at TEXT,
+            encrypted_fields TEXT,
+            expires_at TEXT,
+            rotate_every INTEGER,
+            secret_changed_at TEXT,
+            password_rule TEXT,
+            deleted_at TEXT NOT NULL
+        );
+
+        -- Earlier versions of credentials, still encrypted, one row per update
+        CREATE TABLE IF NOT EXISTS revisions (
+            revision INTEGER PRIMARY KEY AUTOINCREMENT,
+            id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            credential_type TEXT NOT NULL,
+            username TEXT,
+            encrypted_secret TEXT NOT NULL,
+            encrypted_notes TEXT,
+            encrypted_totp_secret TEXT,
+            url TEXT,
+            tags TEXT NOT NULL DEFAULT '[]',
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            accessed_at TEXT,
+            encrypted_fields TEXT,
+            expires_at TEXT,
+            rotate_every INTEGER,
+            secret_changed_at TEXT,
+            password_rule TEXT,
+            revised_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_revisions_id ON revisions(id);
+
+        -- Keyed word-prefix tokens of encrypted metadata, for searching it
+        CREATE TABLE IF NOT EXISTS search_tokens (
+            id TEXT NOT NULL,
+            token TEXT NOT NULL,
+            PRIMARY KEY (token, id)
+        ) WITHOUT ROWID;
+
         -- Audit log table
         CREATE TABLE IF NOT EXISTS audit_log (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -125,7 +319,7 @@ fn create_schema(conn: &Connection) -> DbResult<()> {
         CREATE INDEX IF NOT EXISTS idx_audit_timestamp ON audit_log(timestamp DESC);
 
         -- Store schema version
-        INSERT OR REPLACE INTO metadata (key, value) VALUES ('schema_version', '3');
+        INSERT OR REPLACE INTO metadata (key, value) VALUES ('schema_version', '10');
         "#,
     )?;
 
@@ -166,6 +360,45 @@ mod tests {
         assert!(tables.contains(&"credentials".to_string()));
         assert!(tables.contains(&"audit_log".to_string()));
         assert!(tables.contains(&"metadata".to_string()));
+        assert!(tables.contains(&"tombstones".to_string()));
+        assert!(tables.contains(&"trash".to_string()));
+        assert!(tables.contains(&"search_tokens".to_string()));
+    }
+
+    #[test]
+    fn test_migrate_v3_adds_tombstones() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_schema(&conn).unwrap();
+        conn.execute_batch("DROP TABLE tombstones; UPDATE metadata SET value = '3' WHERE key = 'schema_version';").unwrap();
+
+        init_schema(&conn).unwrap();
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        conn.execute("INSERT INTO tombstones (id, deleted_at) VALUES ('x', datetime('now'))", []).unwrap();
+    }
+
+    #[test]
+    fn test_migrate_v4_adds_trash() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_schema(&conn).unwrap();
+        conn.execute_batch("DROP TABLE trash; UPDATE metadata SET value = '4' WHERE key = 'schema_version';").unwrap();
+
+        init_schema(&conn).unwrap();
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        assert!(has_column(&conn, "trash", "deleted_at"));
+    }
+
+    #[test]
+    fn test_migrate_v5_adds_revisions() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_schema(&conn).unwrap();
+        conn.execute_batch("DROP TABLE revisions; UPDATE metadata SET value = '5' WHERE key = 'schema_version';").unwrap();
+
+        init_schema(&conn).unwrap();
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        assert!(has_column(&conn, "revisions", "revised_at"));
+        assert!(has_column(&conn, "revisions", "encrypted_fields"));
+        assert!(has_column(&conn, "revisions", "secret_changed_at"));
+        assert!(has_column(&conn, "trash", "password_rule"));
     }
 
     #[test]
@@ -0,0 +1,116 @@
+//! Tab completion in command mode
+//!
+//! The first word completes to a command name; after it the word under the
+//! cursor completes to whatever that command takes: tag names for the tag
+//! commands and `:filter tag=`, type names, setting names for `:set`, and
+//! credential names for `:goto`. One match is filled in whole, several up
+//! to the prefix they share.
+
+use super::keymap::{find_command, COMMANDS};
+
+/// Words arguments complete to, gathered from the unlocked vault
+#[derive(Debug, Default)]
+pub struct Vocabulary {
+    pub tags: Vec<String>,
+    pub names: Vec<String>,
+    pub types: Vec<String>,
+    pub settings: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Completion {
+    pub line: String,
+    /// Every candidate that matched, when there was more than one
+    pub matches: Vec<String>,
+}
+
+/// Complete the last word of `line`, or None when nothing matches
+pub fn complete(line: &str, words: &Vocabulary) -> Option<Completion> {
+    let Some((command, arg)) = line.split_once(' ') else {
+        let names = COMMANDS.iter().flat_map(|c| c.names.iter().copied());
+        return extend("", line, names, " ");
+    };
+    let name = find_command(command)?.names[0];
+
+    if name == "goto" {
+        let arg = arg.trim_start();
+        let stem = &line[..line.len() - arg.len()];
+        return extend(stem, arg, words.names.iter().map(String::as_str), "");
+    }
+
+    let token = line.rsplit(' ').next().unwrap_or_default();
+    let word = token.rsplit([',', '=']).next().unwrap_or_default();
+    let stem = &line[..line.len() - word.len()];
+    let key = token.split_once('=').map(|(key, _)| key);
+    match (name, key) {
+        ("addtag" | "rmtag" | "renametag" | "mergetags" | "deltag", None) | ("filter", Some("tag")) => {
+            extend(stem, word, words.tags.iter().map(String::as_str), " ")
+        }
+        ("settype", None) | ("filter", Some("type")) => extend(stem, word, words.types.iter().map(String::as_str), " "),
+        ("filter", None) => extend(stem, word, ["tag=", "type="], ""),
+        ("set" | "set!", None) => extend(stem, word, words.settings.iter().map(String::as_str), "="),
+        _ => None,
+    }
+}
+
+/// `stem` and `word` completed against `candidates`, `suffix` following a
+/// single match
+fn extend<'a>(stem: &str, word: &str, candidates: impl IntoIterator<Item = &'a str>, suffix: &str) -> Option<Completion> {
+    let lower = word.to_lowercase();
+    let mut matches: Vec<&str> = candidates.into_iter().filter(|c| c.to_lowercase().starts_with(&lower)).collect();
+    matches.sort_unstable();
+    matches.dedup();
+
+    match matches.as_slice() {
+        [] => None,
+        [only] => Some(Completion { line: format!("{}{}{}", stem, only, suffix), matches: Vec::new() }),
+        [first, rest @ ..] => {
+            let shared = rest.iter().fold(first.chars().count(), |n, m| common_prefix(first, m).min(n));
+            let prefix: String = first.chars().take(shared).collect();
+            Some(Completion { line: format!("{}{}", stem, prefix), matches: matches.iter().map(|m| m.to_string()).collect() })
+        }
+    }
+}
+
+/// Characters `a` and `b` share at the start, ignoring case
+fn common_prefix(a: &str, b: &str) -> usize {
+    a.chars().zip(b.chars()).take_while(|(x, y)| x.to_lowercase().eq(y.to_lowercase())).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words() -> Vocabulary {
+        Vocabulary {
+            tags: vec!["work".into(), "work/aws".into(), "home".into()],
+            names: vec!["GitHub".into(), "GitLab Work".into(), "Bank".into()],
+            types: vec!["password".into(), "api_key".into()],
+            settings: vec!["autolock".into(), "cliptimeout".into()],
+        }
+    }
+
+    fn line(input: &str) -> Option<String> {
+        complete(input, &words()).map(|c| c.line)
+    }
+
+    #[test]
+    fn test_complete() {
+        assert_eq!(line("sta"), Some("stats ".into()));
+        assert_eq!(line("addt"), Some("addtag ".into()));
+        assert_eq!(line("zzz"), None);
+        assert_eq!(line("addtag h"), Some("addtag home ".into()));
+        assert_eq!(line("renametag work/aws ho"), Some("renametag work/aws home ".into()));
+        assert_eq!(line("filter ty"), Some("filter type=".into()));
+        assert_eq!(line("filter type=api,pa"), Some("filter type=api,password ".into()));
+        assert_eq!(line("filter tag=h"), Some("filter tag=home ".into()));
+        assert_eq!(line("set clip"), Some("set cliptimeout=".into()));
+        assert_eq!(line("goto b"), Some("goto Bank".into()));
+        assert_eq!(line("stats x"), None);
+
+        let several = complete("goto git", &words()).unwrap();
+        assert_eq!(several.line, "goto Git");
+        assert_eq!(several.matches, vec!["GitHub", "GitLab Work"]);
+        assert_eq!(line("addtag wo"), Some("addtag work".into()));
+    }
+}
@@ -0,0 +1,119 @@
+//! Command-mode history
+//!
+//! Up and Down walk back and forth through earlier commands; walking past
+//! the newest brings back what was typed before the walk began.
+
+/// Commands kept; the oldest go first
+pub const MAX_ENTRIES: usize = 100;
+
+/// Commands never recorded: `:duress` takes a password, and `:wipe` with its
+/// confirmation should not be one keypress from running again
+const UNRECORDED: &[&str] = &["duress", "wipe"];
+
+#[derive(Debug, Clone, Default)]
+pub struct CommandHistory {
+    entries: Vec<String>,
+    /// Entry shown while walking the history
+    position: Option<usize>,
+    /// Buffer as it was when the walk began
+    draft: String,
+}
+
+impl CommandHistory {
+    pub fn new(mut entries: Vec<String>) -> Self {
+        let excess = entries.len().saturating_sub(MAX_ENTRIES);
+        entries.drain(..excess);
+        Self { entries, ..Self::default() }
+    }
+
+    /// Oldest first
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Add `line` as the newest entry, moving it there if it was already
+    /// recorded; returns whether the history changed
+    pub fn record(&mut self, line: &str) -> bool {
+        self.reset();
+        let line = line.trim();
+        let name = line.split_whitespace().next().unwrap_or_default();
+        if line.is_empty() || UNRECORDED.contains(&name) || self.entries.last().is_some_and(|last| last == line) {
+            return false;
+        }
+        self.entries.retain(|entry| entry != line);
+        self.entries.push(line.to_string());
+        let excess = self.entries.len().saturating_sub(MAX_ENTRIES);
+        self.entries.drain(..excess);
+        true
+    }
+
+    /// The entry before the one shown, keeping `current` to come back to
+    pub fn previous(&mut self, current: &str) -> Option<&str> {
+        let index = match self.position {
+            Some(0) => return None,
+            Some(i) => i - 1,
+            None => {
+                self.draft = current.to_string();
+                self.entries.len().checked_sub(1)?
+            }
+        };
+        self.position = Some(index);
+        Some(&self.entries[index])
+    }
+
+    /// The entry after the one shown, or the draft past the newest
+    pub fn next(&mut self) -> Option<&str> {
+        let index = self.position? + 1;
+        if index < self.entries.len() {
+            self.position = Some(index);
+            return Some(&self.entries[index]);
+        }
+        self.position = None;
+        Some(&self.draft)
+    }
+
+    /// Stop walking; the next Up starts from the newest entry
+    pub fn reset(&mut self) {
+        self.position = None;
+        self.draft.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_walk() {
+        let mut history = CommandHistory::new(vec!["stats".into(), "filter tag=work".into()]);
+        assert_eq!(history.previous("sor"), Some("filter tag=work"));
+        assert_eq!(history.previous(""), Some("stats"));
+        assert_eq!(history.previous(""), None);
+        assert_eq!(history.next(), Some("filter tag=work"));
+        assert_eq!(history.next(), Some("sor"));
+        assert_eq!(history.next(), None);
+
+        assert!(CommandHistory::default().previous("x").is_none());
+    }
+
+    #[test]
+    fn test_record() {
+        let mut history = CommandHistory::default();
+        assert!(history.record(" stats "));
+        assert!(history.record("trash"));
+        assert!(!history.record("trash"));
+        assert!(history.record("stats"));
+        assert_eq!(history.entries(), ["trash", "stats"]);
+
+        assert!(!history.record(""));
+        assert!(!history.record("duress hunter2"));
+        assert!(!history.record("wipe ERASE"));
+        assert_eq!(history.entries().len(), 2);
+
+        for i in 0..MAX_ENTRIES {
+            history.record(&format!("sort {}", i));
+        }
+        assert_eq!(history.entries().len(), MAX_ENTRIES);
+        assert_eq!(history.entries()[0], "sort 0");
+    }
+}
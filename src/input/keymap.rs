@@ -16,6 +16,14 @@ pub enum Action {
     PageDown,
     HalfPageUp,
     HalfPageDown,
+    PreviousBlock,
+    NextBlock,
+    ScrollDetailUp,
+    ScrollDetailDown,
+    SetMark(char),
+    JumpToMark(char),
+    /// An action typed after a count, e.g. `5j`; `5G` goes to item 5
+    Repeat(usize, Box<Action>),
     Click(u16, u16),
 
     // Selection
@@ -32,23 +40,43 @@ pub enum Action {
     CopyUsername,
     CopyTotp,
     CopyTotpUri,
+    CopyPublicKey,
+    AddToAgent,
 
     // View
     TogglePasswordVisibility,
+    ToggleDetailLayout,
+    ToggleMark,
+    EnterVisual,
+    ExitVisual,
+    MarkRange,
     
     // Mode changes
     EnterCommand,
     EnterSearch,
     ShowHelp,
     ShowTags,
+    ShowTypes,
+    FilterExpiring,
+    FilterRotation,
+    ShowDuplicates,
+    Report(Option<String>),
+    ShowRestore,
+    ShowTrash,
 
     // Commands
     ExecuteCommand(String),
     Search(String),
-    GeneratePassword,
+    CopyUsernameThenPassword,
+    GeneratePassword(Option<String>),
+    RegenerateSecret,
     ChangePassword,
     VerifyAudit,
+    Check,
     ShowLogs,
+    ShowHistory,
+    ShowActivity,
+    ShowStats,
     
     // Confirmation
     Confirm,
@@ -61,6 +89,39 @@ pub enum Action {
     Refresh,
     Lock,
     Export,
+    ExportCurrent,
+    Import,
+    Share(Option<String>),
+    Receive(Option<String>),
+    Backup(Option<String>),
+    Restore(String),
+    RestoreTrashed(String),
+    PurgeTrashed(String),
+    EmptyTrash,
+    Set(Option<String>),
+    Keyfile(Option<String>),
+    Yubikey(Option<String>),
+    Tpm(Option<String>),
+    EncryptDb(Option<String>),
+    EncryptMetadata(Option<String>),
+    Vacuum,
+    Fido2(Option<String>),
+    ForgetSession,
+    Hardening,
+    Wipe(Option<String>),
+    Duress(Option<String>),
+    Kdf(Option<String>),
+    RotateKey,
+    Sort(Option<String>),
+    Filter(Option<String>),
+    Goto(Option<String>),
+    AddTag(Option<String>),
+    RemoveTag(Option<String>),
+    SetType(Option<String>),
+    RenameTag(Option<String>),
+    MergeTags(Option<String>),
+    DeleteTag(Option<String>),
+    SetAndSave(String),
 
     // Text input
     InsertChar(char),
@@ -74,6 +135,9 @@ pub enum Action {
     CursorWordRight,
     CursorHome,
     CursorEnd,
+    HistoryPrevious,
+    HistoryNext,
+    Complete,
     Submit,
 
     // No action
@@ -81,16 +145,51 @@ pub enum Action {
     Invalid(String),
 }
 
+/// The second key of a two-key binding in normal mode, after `prefix`
+pub struct Continuation {
+    pub prefix: char,
+    /// The keys as the hint panel shows them
+    pub keys: &'static str,
+    pub description: &'static str,
+    accepts: fn(char) -> bool,
+    build: fn(char) -> Action,
+}
+
+/// Every two-key binding; the hint panel lists a prefix's entries while it
+/// waits for the second key
+pub const CONTINUATIONS: &[Continuation] = &[
+    Continuation { prefix: 'g', keys: "g", description: "Go to top", accepts: |c| c == 'g', build: |_| Action::MoveToTop },
+    Continuation { prefix: 'd', keys: "d", description: "Move to trash", accepts: |c| c == 'd', build: |_| Action::Delete },
+    Continuation { prefix: 'y', keys: "y c", description: "Copy password", accepts: |c| matches!(c, 'y' | 'c'), build: |_| Action::CopyPassword },
+    Continuation { prefix: 'm', keys: "a-z", description: "Mark selected credential", accepts: |c| c.is_ascii_alphabetic(), build: Action::SetMark },
+    Continuation { prefix: '\'', keys: "a-z", description: "Jump to mark", accepts: |c| c.is_ascii_alphabetic(), build: Action::JumpToMark },
+];
+
+/// The bindings waiting on `prefix`
+pub fn continuations(prefix: char) -> impl Iterator<Item = &'static Continuation> {
+    CONTINUATIONS.iter().filter(move |c| c.prefix == prefix)
+}
+
 /// Map key event to action in normal mode
 pub fn normal_mode_action(key: KeyEvent, pending: Option<char>) -> (Action, Option<char>) {
+    // A second key completing a pair comes first, as a mark letter would
+    // otherwise press its own binding
+    if let (Some(prefix), KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) = (pending, key.code, key.modifiers)
+        && let Some(pair) = continuations(prefix).find(|pair| (pair.accepts)(c))
+    {
+        return ((pair.build)(c), None);
+    }
+
     match (key.code, key.modifiers, pending) {
+        (KeyCode::Char('m'), KeyModifiers::NONE, None) => (Action::None, Some('m')),
+        (KeyCode::Char('\''), KeyModifiers::NONE, None) => (Action::None, Some('\'')),
+
         // Navigation
         (KeyCode::Char('j'), KeyModifiers::NONE, _) => (Action::MoveDown, None),
         (KeyCode::Down, _, _) => (Action::MoveDown, None),
         (KeyCode::Char('k'), KeyModifiers::NONE, _) => (Action::MoveUp, None),
         (KeyCode::Up, _, _) => (Action::MoveUp, None),
         (KeyCode::Char('g'), KeyModifiers::NONE, None) => (Action::None, Some('g')),
-        (KeyCode::Char('g'), KeyModifiers::NONE, Some('g')) => (Action::MoveToTop, None),
         (KeyCode::Char('G'), KeyModifiers::SHIFT, _) => (Action::MoveToBottom, None),
         (KeyCode::Char('d'), KeyModifiers::CONTROL, _) => (Action::HalfPageDown, None),
         (KeyCode::Char('u'), KeyModifiers::CONTROL, _) => (Action::HalfPageUp, None),
@@ -98,6 +197,10 @@ pub fn normal_mode_action(key: KeyEvent, pending: Option<char>) -> (Action, Opti
         (KeyCode::Char('b'), KeyModifiers::CONTROL, _) => (Action::PageUp, None),
         (KeyCode::PageDown, _, _) => (Action::PageDown, None),
         (KeyCode::PageUp, _, _) => (Action::PageUp, None),
+        (KeyCode::Char('{'), KeyModifiers::NONE | KeyModifiers::SHIFT, _) => (Action::PreviousBlock, None),
+        (KeyCode::Char('}'), KeyModifiers::NONE | KeyModifiers::SHIFT, _) => (Action::NextBlock, None),
+        (KeyCode::Char('J'), KeyModifiers::SHIFT, _) | (KeyCode::Char('e'), KeyModifiers::CONTROL, _) => (Action::ScrollDetailDown, None),
+        (KeyCode::Char('K'), KeyModifiers::SHIFT, _) | (KeyCode::Char('y'), KeyModifiers::CONTROL, _) => (Action::ScrollDetailUp, None),
 
         // Selection
         (KeyCode::Char('l'), KeyModifiers::CONTROL, _) => (Action::Clear, None),
@@ -112,26 +215,34 @@ pub fn normal_mode_action(key: KeyEvent, pending: Option<char>) -> (Action, Opti
         (KeyCode::Char('n'), KeyModifiers::NONE, _) => (Action::New, None),
         (KeyCode::Char('e'), KeyModifiers::NONE, _) => (Action::Edit, None),
         (KeyCode::Char('d'), KeyModifiers::NONE, None) => (Action::None, Some('d')),
-        (KeyCode::Char('d'), KeyModifiers::NONE, Some('d')) => (Action::Delete, None),
         (KeyCode::Char('x'), KeyModifiers::NONE, _) => (Action::Delete, None),
 
         // Clipboard
         (KeyCode::Char('c'), KeyModifiers::NONE, None) => (Action::None, Some('y')),
         (KeyCode::Char('y'), KeyModifiers::NONE, None) => (Action::None, Some('y')),
-        (KeyCode::Char('y'), KeyModifiers::NONE, Some('y')) => (Action::CopyPassword, None),
-        (KeyCode::Char('c'), KeyModifiers::NONE, Some('y')) => (Action::CopyPassword, None),
         (KeyCode::Char('u'), KeyModifiers::NONE, None) => (Action::CopyUsername, None),
+        (KeyCode::Char('Y'), KeyModifiers::SHIFT, _) => (Action::CopyUsernameThenPassword, None),
         (KeyCode::Char('T'), KeyModifiers::SHIFT, _) => (Action::CopyTotp, None),
         (KeyCode::Char('t'), KeyModifiers::CONTROL, _) => (Action::CopyTotpUri, None),
+        (KeyCode::Char('P'), KeyModifiers::SHIFT, _) => (Action::CopyPublicKey, None),
+        (KeyCode::Char('A'), KeyModifiers::SHIFT, _) => (Action::AddToAgent, None),
+        (KeyCode::Char('R'), KeyModifiers::SHIFT, _) => (Action::RegenerateSecret, None),
+        (KeyCode::Char('E'), KeyModifiers::SHIFT, _) => (Action::ExportCurrent, None),
 
         // View
         (KeyCode::Char('s'), KeyModifiers::CONTROL, _) => (Action::TogglePasswordVisibility, None),
+        (KeyCode::Char('z'), KeyModifiers::NONE, _) => (Action::ToggleDetailLayout, None),
+        (KeyCode::Char('o'), KeyModifiers::NONE, _) => (Action::Sort(None), None),
+        (KeyCode::Char(' '), KeyModifiers::NONE, _) => (Action::ToggleMark, None),
+        (KeyCode::Char('V'), KeyModifiers::SHIFT, _) => (Action::EnterVisual, None),
+        (KeyCode::Char('v'), KeyModifiers::NONE, _) => (Action::EnterVisual, None),
 
         // Mode changes
         (KeyCode::Char(':'), KeyModifiers::NONE | KeyModifiers::SHIFT, _) => (Action::EnterCommand, None),
         (KeyCode::Char('/'), KeyModifiers::NONE, _) => (Action::EnterSearch, None),
         (KeyCode::Char('?'), KeyModifiers::NONE | KeyModifiers::SHIFT, _) => (Action::ShowHelp, None),
         (KeyCode::Char('t'), KeyModifiers::NONE, _) => (Action::ShowTags, None),
+        (KeyCode::Char('f'), KeyModifiers::NONE, _) => (Action::ShowTypes, None),
 
         // Application
         (KeyCode::Char('q'), KeyModifiers::NONE, _) => (Action::Quit, None),
@@ -139,12 +250,49 @@ pub fn normal_mode_action(key: KeyEvent, pending: Option<char>) -> (Action, Opti
         (KeyCode::Char('r'), KeyModifiers::CONTROL, _) => (Action::Refresh, None),
         (KeyCode::Char('p'), KeyModifiers::CONTROL, _) => (Action::ChangePassword, None),
         (KeyCode::Char('i'), KeyModifiers::NONE, _) => (Action::ShowLogs, None),
+        (KeyCode::Char('H'), KeyModifiers::SHIFT, _) => (Action::ShowHistory, None),
+        (KeyCode::Char('I'), KeyModifiers::SHIFT, _) => (Action::ShowActivity, None),
         (KeyCode::Char('L'), KeyModifiers::SHIFT, _) => (Action::Lock, None),
 
         _ => (Action::None, None),
     }
 }
 
+/// Largest count taken before a motion
+const MAX_COUNT: usize = 9999;
+
+/// The count after typing `key`, if it is a digit continuing one: `1`-`9`
+/// start a count and `0` only extends it, as in vim
+pub fn count_prefix(key: KeyEvent, count: Option<usize>) -> Option<usize> {
+    let KeyCode::Char(c @ '0'..='9') = key.code else { return None };
+    if key.modifiers != KeyModifiers::NONE || (c == '0' && count.is_none()) {
+        return None;
+    }
+    let digit = c as usize - '0' as usize;
+    Some((count.unwrap_or(0) * 10 + digit).min(MAX_COUNT))
+}
+
+/// `action` typed after `count`; a count means nothing to most actions,
+/// which run once
+pub fn counted_action(action: Action, count: usize) -> Action {
+    match action {
+        Action::MoveUp
+        | Action::MoveDown
+        | Action::MoveToTop
+        | Action::MoveToBottom
+        | Action::PageUp
+        | Action::PageDown
+        | Action::HalfPageUp
+        | Action::HalfPageDown
+        | Action::PreviousBlock
+        | Action::NextBlock
+        | Action::ScrollDetailUp
+        | Action::ScrollDetailDown
+        | Action::Delete => Action::Repeat(count, Box::new(action)),
+        _ => action,
+    }
+}
+
 /// Map key event to action in text input modes
 pub fn text_input_action(key: KeyEvent) -> Action {
     match (key.code, key.modifiers) {
@@ -160,11 +308,32 @@ pub fn text_input_action(key: KeyEvent) -> Action {
         (KeyCode::Home, _) | (KeyCode::Char('a'), KeyModifiers::CONTROL) => Action::CursorHome,
         (KeyCode::End, _) | (KeyCode::Char('e'), KeyModifiers::CONTROL) => Action::CursorEnd,
         (KeyCode::Char('u'), KeyModifiers::CONTROL) => Action::ClearToStart,
+        (KeyCode::Up, _) => Action::HistoryPrevious,
+        (KeyCode::Down, _) => Action::HistoryNext,
+        (KeyCode::Tab, _) => Action::Complete,
         (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => Action::InsertChar(c),
         _ => Action::None,
     }
 }
 
+/// Map key event to action in visual (range selection) mode
+pub fn visual_mode_action(key: KeyEvent) -> Action {
+    match (key.code, key.modifiers) {
+        (KeyCode::Char('j'), KeyModifiers::NONE) | (KeyCode::Down, _) => Action::MoveDown,
+        (KeyCode::Char('k'), KeyModifiers::NONE) | (KeyCode::Up, _) => Action::MoveUp,
+        (KeyCode::Char('g'), KeyModifiers::NONE) => Action::MoveToTop,
+        (KeyCode::Char('G'), KeyModifiers::SHIFT) => Action::MoveToBottom,
+        (KeyCode::Char('d'), KeyModifiers::CONTROL) => Action::HalfPageDown,
+        (KeyCode::Char('u'), KeyModifiers::CONTROL) => Action::HalfPageUp,
+        (KeyCode::Char(' '), KeyModifiers::NONE) | (KeyCode::Enter, _) => Action::MarkRange,
+        (KeyCode::Char('d') | KeyCode::Char('x'), KeyModifiers::NONE) => Action::Delete,
+        (KeyCode::Char(':'), KeyModifiers::NONE | KeyModifiers::SHIFT) => Action::EnterCommand,
+        (KeyCode::Esc, _) | (KeyCode::Char('v'), KeyModifiers::NONE) => Action::ExitVisual,
+        (KeyCode::Char('V'), KeyModifiers::SHIFT) => Action::ExitVisual,
+        _ => Action::None,
+    }
+}
+
 /// Map key event to action in confirm mode
 pub fn confirm_action(key: KeyEvent) -> Action {
     match key.code {
@@ -185,32 +354,133 @@ pub fn mouse_action(event: MouseEvent) -> Action {
     }
 }
 
+/// A command-mode verb: its names, the first one canonical, and how it
+/// builds its action from the argument
+pub struct Command {
+    pub names: &'static [&'static str],
+    build: fn(Option<&str>) -> Action,
+}
+
+/// Every command `parse_command` accepts
+pub const COMMANDS: &[Command] = &[
+    Command { names: &["clear", "cls"], build: |_| Action::Clear },
+    Command { names: &["quit", "q", "wq"], build: |_| Action::Quit },
+    Command { names: &["quit!", "q!"], build: |_| Action::ForceQuit },
+    // Every change is saved as it is made
+    Command { names: &["write", "w"], build: |_| Action::None },
+    Command { names: &["new", "n"], build: |_| Action::New },
+    Command { names: &["edit", "e"], build: |_| Action::Edit },
+    Command { names: &["delete", "del"], build: |_| Action::Delete },
+    Command { names: &["generate", "gen"], build: |arg| Action::GeneratePassword(arg.map(String::from)) },
+    Command { names: &["sshadd"], build: |_| Action::AddToAgent },
+    Command { names: &["regenerate", "regen"], build: |_| Action::RegenerateSecret },
+    Command { names: &["copyboth"], build: |_| Action::CopyUsernameThenPassword },
+    Command { names: &["help", "h"], build: |_| Action::ShowHelp },
+    Command { names: &["changepw", "passwd", "password"], build: |_| Action::ChangePassword },
+    Command { names: &["lock"], build: |_| Action::Lock },
+    Command { names: &["refresh", "ref", "resh"], build: |_| Action::Refresh },
+    Command { names: &["log", "logs"], build: |_| Action::ShowLogs },
+    Command { names: &["history", "hist"], build: |_| Action::ShowHistory },
+    Command { names: &["activity"], build: |_| Action::ShowActivity },
+    Command { names: &["stats"], build: |_| Action::ShowStats },
+    Command { names: &["audit", "aud", "verify"], build: |_| Action::VerifyAudit },
+    Command { names: &["check"], build: |_| Action::Check },
+    Command { names: &["tags", "tag"], build: |_| Action::ShowTags },
+    Command { names: &["type", "types"], build: |_| Action::ShowTypes },
+    Command { names: &["filter"], build: |arg| Action::Filter(arg.map(String::from)) },
+    Command { names: &["goto", "go"], build: |arg| Action::Goto(arg.map(String::from)) },
+    Command { names: &["expiring"], build: |_| Action::FilterExpiring },
+    Command { names: &["rotation"], build: |_| Action::FilterRotation },
+    Command { names: &["export", "exp"], build: |_| Action::Export },
+    Command { names: &["exportone", "exp1"], build: |_| Action::ExportCurrent },
+    Command { names: &["import", "imp"], build: |_| Action::Import },
+    Command { names: &["share"], build: |arg| Action::Share(arg.map(String::from)) },
+    Command { names: &["receive"], build: |arg| Action::Receive(arg.map(String::from)) },
+    Command { names: &["duplicates", "dup", "dups"], build: |_| Action::ShowDuplicates },
+    Command { names: &["report"], build: |arg| Action::Report(arg.map(String::from)) },
+    Command { names: &["backup"], build: |arg| Action::Backup(arg.map(String::from)) },
+    Command {
+        names: &["restore"],
+        build: |arg| match arg {
+            Some(file) => Action::Restore(file.to_string()),
+            None => Action::ShowRestore,
+        },
+    },
+    Command { names: &["trash"], build: |_| Action::ShowTrash },
+    Command { names: &["set"], build: |arg| Action::Set(arg.map(String::from)) },
+    Command {
+        names: &["set!"],
+        build: |arg| match arg {
+            Some(setting) => Action::SetAndSave(setting.to_string()),
+            None => Action::Set(None),
+        },
+    },
+    Command { names: &["keyfile"], build: |arg| Action::Keyfile(arg.map(String::from)) },
+    Command { names: &["yubikey"], build: |arg| Action::Yubikey(arg.map(String::from)) },
+    Command { names: &["tpm"], build: |arg| Action::Tpm(arg.map(String::from)) },
+    Command { names: &["encrypt-db"], build: |arg| Action::EncryptDb(arg.map(String::from)) },
+    Command { names: &["encrypt-metadata"], build: |arg| Action::EncryptMetadata(arg.map(String::from)) },
+    Command { names: &["vacuum"], build: |_| Action::Vacuum },
+    Command { names: &["fido2"], build: |arg| Action::Fido2(arg.map(String::from)) },
+    Command { names: &["forget-session"], build: |_| Action::ForgetSession },
+    Command { names: &["hardening"], build: |_| Action::Hardening },
+    Command { names: &["wipe"], build: |arg| Action::Wipe(arg.map(String::from)) },
+    Command { names: &["duress"], build: |arg| Action::Duress(arg.map(String::from)) },
+    Command { names: &["kdf"], build: |arg| Action::Kdf(arg.map(String::from)) },
+    Command { names: &["rotate-key"], build: |_| Action::RotateKey },
+    Command { names: &["sort"], build: |arg| Action::Sort(arg.map(String::from)) },
+    Command { names: &["addtag"], build: |arg| Action::AddTag(arg.map(String::from)) },
+    Command { names: &["rmtag"], build: |arg| Action::RemoveTag(arg.map(String::from)) },
+    Command { names: &["settype"], build: |arg| Action::SetType(arg.map(String::from)) },
+    Command { names: &["renametag"], build: |arg| Action::RenameTag(arg.map(String::from)) },
+    Command { names: &["mergetags", "mergetag"], build: |arg| Action::MergeTags(arg.map(String::from)) },
+    Command { names: &["deltag"], build: |arg| Action::DeleteTag(arg.map(String::from)) },
+];
+
+/// The command called `name`, by any of its names
+pub fn find_command(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|c| c.names.contains(&name))
+}
+
+/// The command name closest to a mistyped `name`, if one is close enough
+/// to be what was meant: at most two edits away and shorter than the typo
+pub fn suggest_command(name: &str) -> Option<&'static str> {
+    COMMANDS
+        .iter()
+        .flat_map(|c| c.names.iter().copied())
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= 2 && *distance < name.chars().count())
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance between `a` and `b`, in characters
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
 /// Parse command string into action
 pub fn parse_command(cmd: &str) -> Action {
     let cmd = cmd.trim();
     let parts: Vec<&str> = cmd.splitn(2, ' ').collect();
     let command = parts[0];
+    let arg = parts.get(1).map(|a| a.trim()).filter(|a| !a.is_empty());
 
-    match command {
-        "cls" | "clear" => Action::Clear,
-        "q" | "quit" => Action::Quit,
-        "q!" | "quit!" => Action::ForceQuit,
-        "w" | "write" => Action::None, // Auto-save, no action needed
-        "wq" => Action::Quit,
-        "n" | "new" => Action::New,
-        "e" | "edit" => Action::Edit,
-        "del" | "delete" => Action::Delete,
-        "gen" | "generate" => Action::GeneratePassword,
-        "h" | "help" => Action::ShowHelp,
-        "passwd" | "password" | "changepw" => Action::ChangePassword,
-        "lock" => Action::Lock,
-        "ref" | "resh" => Action::Refresh,
-        "log" | "logs" => Action::ShowLogs,
-        "aud" | "audit" | "verify" => Action::VerifyAudit,
-        "tag" | "tags" => Action::ShowTags,
-        "exp" | "export" => Action::Export,
-        "" => Action::None,
-        other => Action::Invalid(other.to_string()),
+    match find_command(command) {
+        Some(found) => (found.build)(arg),
+        None if command.is_empty() => Action::None,
+        None => Action::Invalid(command.to_string()),
     }
 }
 
@@ -231,6 +501,13 @@ mod tests {
         assert_eq!(normal_mode_action(key(KeyCode::Char('j')), None).0, Action::MoveDown);
         assert_eq!(normal_mode_action(key(KeyCode::Char('k')), None).0, Action::MoveUp);
         assert_eq!(normal_mode_action(KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT), None).0, Action::MoveToBottom);
+        assert_eq!(normal_mode_action(key(KeyCode::Char('z')), None).0, Action::ToggleDetailLayout);
+        assert_eq!(normal_mode_action(key(KeyCode::Char(' ')), None).0, Action::ToggleMark);
+        assert_eq!(normal_mode_action(KeyEvent::new(KeyCode::Char('P'), KeyModifiers::SHIFT), None).0, Action::CopyPublicKey);
+        assert_eq!(normal_mode_action(KeyEvent::new(KeyCode::Char('E'), KeyModifiers::SHIFT), None).0, Action::ExportCurrent);
+        assert_eq!(normal_mode_action(KeyEvent::new(KeyCode::Char('A'), KeyModifiers::SHIFT), None).0, Action::AddToAgent);
+        assert_eq!(normal_mode_action(KeyEvent::new(KeyCode::Char('R'), KeyModifiers::SHIFT), None).0, Action::RegenerateSecret);
+        assert_eq!(normal_mode_action(KeyEvent::new(KeyCode::Char('Y'), KeyModifiers::SHIFT), None).0, Action::CopyUsernameThenPassword);
     }
 
     #[test]
@@ -255,12 +532,47 @@ mod tests {
         assert_eq!(pending2, None);
     }
 
+    #[test]
+    fn test_counts() {
+        assert_eq!(count_prefix(key(KeyCode::Char('5')), None), Some(5));
+        assert_eq!(count_prefix(key(KeyCode::Char('0')), Some(1)), Some(10));
+        assert_eq!(count_prefix(key(KeyCode::Char('0')), None), None);
+        assert_eq!(count_prefix(key(KeyCode::Char('j')), Some(3)), None);
+        assert_eq!(count_prefix(key(KeyCode::Char('9')), Some(MAX_COUNT)), Some(MAX_COUNT));
+
+        assert_eq!(counted_action(Action::MoveDown, 5), Action::Repeat(5, Box::new(Action::MoveDown)));
+        assert_eq!(counted_action(Action::Delete, 3), Action::Repeat(3, Box::new(Action::Delete)));
+        assert_eq!(counted_action(Action::CopyPassword, 2), Action::CopyPassword);
+        let (action, _) = normal_mode_action(key(KeyCode::Char('}')), None);
+        assert_eq!(action, Action::NextBlock);
+    }
+
+    #[test]
+    fn test_continuations() {
+        for c in ['g', 'd', 'y', 'c', 'm', '\'', 'j'] {
+            let (_, pending) = normal_mode_action(key(KeyCode::Char(c)), None);
+            assert!(pending.is_none_or(|p| continuations(p).count() > 0), "{} waits with no hints", c);
+        }
+        assert_eq!(continuations('y').next().map(|c| c.description), Some("Copy password"));
+    }
+
+    #[test]
+    fn test_marks() {
+        let (action, pending) = normal_mode_action(key(KeyCode::Char('m')), None);
+        assert_eq!((action, pending), (Action::None, Some('m')));
+        assert_eq!(normal_mode_action(key(KeyCode::Char('j')), Some('m')), (Action::SetMark('j'), None));
+        assert_eq!(normal_mode_action(key(KeyCode::Char('j')), Some('\'')), (Action::JumpToMark('j'), None));
+        assert_eq!(normal_mode_action(key(KeyCode::Char('1')), Some('m')), (Action::None, None));
+    }
+
     #[test]
     fn test_text_input() {
         assert_eq!(text_input_action(key(KeyCode::Char('a'))), Action::InsertChar('a'));
         assert_eq!(text_input_action(key(KeyCode::Backspace)), Action::DeleteChar);
         assert_eq!(text_input_action(key(KeyCode::Enter)), Action::Submit);
         assert_eq!(text_input_action(key(KeyCode::Esc)), Action::Cancel);
+        assert_eq!(text_input_action(key(KeyCode::Up)), Action::HistoryPrevious);
+        assert_eq!(text_input_action(key(KeyCode::Tab)), Action::Complete);
     }
 
     #[test]
@@ -277,6 +589,75 @@ mod tests {
         assert_eq!(parse_command("new"), Action::New);
         assert_eq!(parse_command("help"), Action::ShowHelp);
         assert_eq!(parse_command("tags"), Action::ShowTags);
+        assert_eq!(parse_command("types"), Action::ShowTypes);
+        assert_eq!(parse_command("expiring"), Action::FilterExpiring);
+        assert_eq!(parse_command("rotation"), Action::FilterRotation);
+        assert_eq!(parse_command("history"), Action::ShowHistory);
+        assert_eq!(parse_command("activity"), Action::ShowActivity);
+        assert_eq!(parse_command("stats"), Action::ShowStats);
+        assert_eq!(parse_command("import"), Action::Import);
+        assert_eq!(parse_command("share ~/wifi.share"), Action::Share(Some("~/wifi.share".into())));
+        assert_eq!(parse_command("receive"), Action::Receive(None));
+        assert_eq!(parse_command("duplicates"), Action::ShowDuplicates);
+        assert_eq!(parse_command("gen"), Action::GeneratePassword(None));
+        assert_eq!(parse_command("regen"), Action::RegenerateSecret);
+        assert_eq!(parse_command("copyboth"), Action::CopyUsernameThenPassword);
+        assert_eq!(parse_command("gen pronounceable"), Action::GeneratePassword(Some("pronounceable".into())));
+        assert_eq!(parse_command("report age"), Action::Report(Some("age".into())));
+        assert_eq!(parse_command("check"), Action::Check);
+        assert_eq!(parse_command("backup"), Action::Backup(None));
+        assert_eq!(parse_command("backup before-move"), Action::Backup(Some("before-move".into())));
+        assert_eq!(parse_command("restore"), Action::ShowRestore);
+        assert_eq!(parse_command("restore vault-20240115-093000.db"), Action::Restore("vault-20240115-093000.db".into()));
+        assert_eq!(parse_command("trash"), Action::ShowTrash);
+        assert_eq!(parse_command("addtag work"), Action::AddTag(Some("work".into())));
+        assert_eq!(parse_command("renametag dev code"), Action::RenameTag(Some("dev code".into())));
+        assert_eq!(parse_command("mergetag"), Action::MergeTags(None));
+        assert_eq!(parse_command("deltag old"), Action::DeleteTag(Some("old".into())));
+        assert_eq!(parse_command("settype"), Action::SetType(None));
+        assert_eq!(parse_command("set"), Action::Set(None));
+        assert_eq!(parse_command("keyfile off"), Action::Keyfile(Some("off".into())));
+        assert_eq!(parse_command("yubikey 2"), Action::Yubikey(Some("2".into())));
+        assert_eq!(parse_command("tpm on"), Action::Tpm(Some("on".into())));
+        assert_eq!(parse_command("encrypt-db off"), Action::EncryptDb(Some("off".into())));
+        assert_eq!(parse_command("encrypt-metadata on"), Action::EncryptMetadata(Some("on".into())));
+        assert_eq!(parse_command("vacuum"), Action::Vacuum);
+        assert_eq!(parse_command("fido2 on"), Action::Fido2(Some("on".into())));
+        assert_eq!(parse_command("forget-session"), Action::ForgetSession);
+        assert_eq!(parse_command("hardening"), Action::Hardening);
+        assert_eq!(parse_command("wipe ERASE-EVERYTHING"), Action::Wipe(Some("ERASE-EVERYTHING".into())));
+        assert_eq!(parse_command("kdf apply"), Action::Kdf(Some("apply".into())));
+        assert_eq!(parse_command("rotate-key"), Action::RotateKey);
+        assert_eq!(parse_command("sort updated"), Action::Sort(Some("updated".into())));
+        assert_eq!(parse_command("set autolock=600"), Action::Set(Some("autolock=600".into())));
+        assert_eq!(parse_command("set! cliptimeout=10"), Action::SetAndSave("cliptimeout=10".into()));
+        assert_eq!(parse_command("filter type=apikey"), Action::Filter(Some("type=apikey".into())));
+        assert_eq!(parse_command("goto GitLab Work"), Action::Goto(Some("GitLab Work".into())));
+        assert_eq!(parse_command("stast"), Action::Invalid("stast".into()));
+    }
+
+    #[test]
+    fn test_command_table() {
+        let mut names: Vec<&str> = COMMANDS.iter().flat_map(|c| c.names.iter().copied()).collect();
+        let count = names.len();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), count);
+
+        assert_eq!(suggest_command("stast"), Some("stats"));
+        assert_eq!(suggest_command("expotr"), Some("export"));
+        assert_eq!(suggest_command("vacum"), Some("vacuum"));
+        assert_eq!(suggest_command("xy"), None);
+        assert_eq!(suggest_command("frobnicate"), None);
+    }
+
+    #[test]
+    fn test_visual_action() {
+        assert_eq!(normal_mode_action(KeyEvent::new(KeyCode::Char('V'), KeyModifiers::SHIFT), None).0, Action::EnterVisual);
+        assert_eq!(visual_mode_action(key(KeyCode::Char('j'))), Action::MoveDown);
+        assert_eq!(visual_mode_action(key(KeyCode::Char('d'))), Action::Delete);
+        assert_eq!(visual_mode_action(key(KeyCode::Char(' '))), Action::MarkRange);
+        assert_eq!(visual_mode_action(key(KeyCode::Esc)), Action::ExitVisual);
     }
 
     #[test]
@@ -297,5 +678,8 @@ mod tests {
     fn test_show_tags() {
         let (action, _) = normal_mode_action(KeyEvent::new(KeyCode::Char('t'), KeyModifiers::NONE), None);
         assert_eq!(action, Action::ShowTags);
+
+        let (action, _) = normal_mode_action(KeyEvent::new(KeyCode::Char('f'), KeyModifiers::NONE), None);
+        assert_eq!(action, Action::ShowTypes);
     }
 }
@@ -2,10 +2,12 @@
 //!
 //! Handles keyboard input with vim-style modal editing.
 
+pub mod completion;
+pub mod history;
 pub mod keymap;
 pub mod modes;
 pub mod text_buffer;
 
 // Re-exports
 pub use modes::InputMode;
-pub use text_buffer::{handle_text_key, SecureTextBuffer, TextBuffer, TextEditing};
+pub use text_buffer::{cursor_window, display_width, handle_text_key, mask, SecureTextBuffer, TextBuffer, TextEditing};
@@ -14,8 +14,19 @@ pub enum InputMode {
     Confirm,
     Help,
     Logs,
+    History,
+    Stats,
     Tags,
+    Types,
     Export,
+    Import,
+    ImportReview,
+    Share,
+    Duplicates,
+    Report,
+    Restore,
+    Trash,
+    Visual,
 }
 
 impl InputMode {
@@ -28,8 +39,19 @@ impl InputMode {
             Self::Confirm => "CONFIRM",
             Self::Help => "HELP",
             Self::Logs => "LOG",
+            Self::History => "HIST",
+            Self::Stats => "STATS",
             Self::Tags => "TAG",
+            Self::Types => "TYPE",
             Self::Export => "EXPORT",
+            Self::Import => "IMPORT",
+            Self::ImportReview => "REVIEW",
+            Self::Share => "SHARE",
+            Self::Duplicates => "DUPES",
+            Self::Report => "REPORT",
+            Self::Restore => "RESTORE",
+            Self::Trash => "TRASH",
+            Self::Visual => "VISUAL",
         }
     }
 
@@ -44,6 +66,8 @@ pub struct ModeState {
     pub mode: InputMode,
     pub buffer: TextBuffer,
     pub pending: Option<char>,
+    /// Count typed before a normal-mode key, e.g. the 5 of `5j`
+    pub count: Option<usize>,
 }
 
 impl Default for ModeState {
@@ -52,6 +76,7 @@ impl Default for ModeState {
             mode: InputMode::Normal,
             buffer: TextBuffer::new(),
             pending: None,
+            count: None,
         }
     }
 }
@@ -65,6 +90,7 @@ impl ModeState {
         self.mode = mode;
         self.buffer.clear();
         self.pending = None;
+        self.count = None;
     }
 
     pub fn enter_normal_mode(&mut self) {
@@ -95,14 +121,58 @@ impl ModeState {
         self.mode = InputMode::Tags;
     }
 
+    pub fn enter_types_mode(&mut self) {
+        self.mode = InputMode::Types;
+    }
+
     pub fn enter_logs_mode(&mut self) {
         self.mode = InputMode::Logs;
     }
 
+    pub fn enter_history_mode(&mut self) {
+        self.mode = InputMode::History;
+    }
+
+    pub fn enter_stats_mode(&mut self) {
+        self.mode = InputMode::Stats;
+    }
+
+    pub fn enter_duplicates_mode(&mut self) {
+        self.mode = InputMode::Duplicates;
+    }
+
+    pub fn enter_report_mode(&mut self) {
+        self.mode = InputMode::Report;
+    }
+
+    pub fn enter_restore_mode(&mut self) {
+        self.mode = InputMode::Restore;
+    }
+
+    pub fn enter_visual_mode(&mut self) {
+        self.set_mode(InputMode::Visual);
+    }
+
+    pub fn enter_trash_mode(&mut self) {
+        self.mode = InputMode::Trash;
+    }
+
     pub fn enter_export_mode(&mut self) {
         self.set_mode(InputMode::Export);
     }
 
+    pub fn enter_import_mode(&mut self) {
+        self.set_mode(InputMode::Import);
+    }
+
+    pub fn enter_import_review_mode(&mut self) {
+        self.mode = InputMode::ImportReview;
+    }
+
+    pub fn enter_share_mode(&mut self) {
+        self.set_mode(InputMode::Share);
+    }
+
     // Convenience methods that delegate to buffer
     pub fn insert_char(&mut self, c: char) {
         self.buffer.insert_char(c);
@@ -147,6 +217,11 @@ impl ModeState {
     pub fn get_buffer(&self) -> &str {
         self.buffer.content()
     }
+
+    /// Replace the buffer, cursor at the end
+    pub fn set_buffer(&mut self, content: &str) {
+        self.buffer.set_content(content);
+    }
 }
 
 #[cfg(test)]
@@ -1,14 +1,21 @@
 //! Text Buffer
 //!
 //! Reusable text editing buffer with cursor management.
+//!
+//! Editing works on grapheme clusters, so an emoji, a CJK character or a
+//! letter with combining accents moves and deletes as one; rendering goes by
+//! display width, where such characters may take two columns or none.
 
 use crossterm::event::{KeyCode, KeyModifiers};
-use zeroize::Zeroizing;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+use zeroize::{Zeroize, Zeroizing};
 
 /// Trait for text editing operations
 #[allow(dead_code)]
 pub trait TextEditing {
     fn content(&self) -> &str;
+    /// Byte offset into `content`, always on a grapheme boundary
     fn cursor(&self) -> usize;
     fn set_cursor(&mut self, pos: usize);
     fn set_content(&mut self, content: &str);
@@ -24,6 +31,7 @@ pub trait TextEditing {
     fn cursor_word_right(&mut self);
     fn cursor_home(&mut self);
     fn cursor_end(&mut self);
+    /// Length in graphemes, the characters as a user counts them
     fn len(&self) -> usize;
     fn is_empty(&self) -> bool;
 }
@@ -47,61 +55,175 @@ pub fn handle_text_key<T: TextEditing>(buf: &mut T, code: KeyCode, mods: KeyModi
     true
 }
 
+/// Whether a grapheme starts a word, going by its first character
+fn is_word(g: &str) -> bool {
+    g.chars().next().is_some_and(|c| c.is_alphanumeric() || c == '_')
+}
+
+fn is_space(g: &str) -> bool {
+    g.chars().next().is_some_and(char::is_whitespace)
+}
+
+/// Byte offset where the word before `from` starts
 pub fn find_word_boundary_back(s: &str, from: usize) -> usize {
-    let chars: Vec<char> = s.chars().take(from).collect();
-    let mut pos = chars.len();
-    let is_word = |c: char| c.is_alphanumeric() || c == '_';
-    let is_punct = |c: char| !c.is_whitespace() && !is_word(c);
+    let graphemes: Vec<(usize, &str)> = s[..from].grapheme_indices(true).collect();
+    let mut pos = graphemes.len();
+    let is_punct = |g: &str| !is_space(g) && !is_word(g);
 
     // Skip trailing whitespace
-    while pos > 0 && chars[pos - 1].is_whitespace() {
+    while pos > 0 && is_space(graphemes[pos - 1].1) {
         pos -= 1;
     }
     if pos == 0 { return 0; }
 
-    if is_word(chars[pos - 1]) {
+    if is_word(graphemes[pos - 1].1) {
         // Delete word chars only
-        while pos > 0 && is_word(chars[pos - 1]) {
+        while pos > 0 && is_word(graphemes[pos - 1].1) {
             pos -= 1;
         }
     } else {
         // Delete punctuation, then preceding word chars
-        while pos > 0 && is_punct(chars[pos - 1]) {
+        while pos > 0 && is_punct(graphemes[pos - 1].1) {
             pos -= 1;
         }
-        while pos > 0 && is_word(chars[pos - 1]) {
+        while pos > 0 && is_word(graphemes[pos - 1].1) {
             pos -= 1;
         }
     }
-    pos
+    graphemes.get(pos).map_or(from, |(i, _)| *i)
 }
 
+/// Byte offset where the word after `from` starts
 pub fn find_word_boundary_forward(s: &str, from: usize) -> usize {
-    let chars: Vec<char> = s.chars().collect();
-    let len = chars.len();
-    let mut pos = from;
-    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let graphemes: Vec<(usize, &str)> = s[from..].grapheme_indices(true).collect();
+    let len = graphemes.len();
+    let mut pos = 0;
 
     if pos >= len {
-        return len;
+        return s.len();
     }
 
     // Skip current word/punct chars, then whitespace
-    if is_word(chars[pos]) {
-        while pos < len && is_word(chars[pos]) {
+    if is_word(graphemes[pos].1) {
+        while pos < len && is_word(graphemes[pos].1) {
             pos += 1;
         }
-    } else if !chars[pos].is_whitespace() {
-        while pos < len && !chars[pos].is_whitespace() && !is_word(chars[pos]) {
+    } else if !is_space(graphemes[pos].1) {
+        while pos < len && !is_space(graphemes[pos].1) && !is_word(graphemes[pos].1) {
             pos += 1;
         }
     }
 
-    while pos < len && chars[pos].is_whitespace() {
+    while pos < len && is_space(graphemes[pos].1) {
         pos += 1;
     }
 
-    pos
+    graphemes.get(pos).map_or(s.len(), |(i, _)| from + i)
+}
+
+/// Start of the grapheme before `at`
+fn previous_grapheme(s: &str, at: usize) -> usize {
+    s[..at].grapheme_indices(true).next_back().map_or(0, |(i, _)| i)
+}
+
+/// End of the grapheme starting at `at`
+fn next_grapheme(s: &str, at: usize) -> usize {
+    s[at..].graphemes(true).next().map_or(at, |g| at + g.len())
+}
+
+/// The grapheme boundary at or before `pos`
+fn floor_boundary(s: &str, pos: usize) -> usize {
+    if pos >= s.len() {
+        return s.len();
+    }
+    s.grapheme_indices(true).take_while(|(i, _)| *i <= pos).last().map_or(0, |(i, _)| i)
+}
+
+/// The grapheme boundary at or after `pos`
+fn ceil_boundary(s: &str, pos: usize) -> usize {
+    match floor_boundary(s, pos) {
+        start if start == pos => pos,
+        start => next_grapheme(s, start),
+    }
+}
+
+/// Columns `s` takes up in the terminal
+pub fn display_width(s: &str) -> usize {
+    s.width()
+}
+
+/// `text` with every grapheme shown as a bullet, and `cursor` moved to match
+pub fn mask(text: &str, cursor: usize) -> (String, usize) {
+    let bullets = |s: &str| s.graphemes(true).count();
+    let before = bullets(&text[..cursor.min(text.len())]);
+    ("•".repeat(bullets(text)), before * '•'.len_utf8())
+}
+
+/// The part of `text` that fits in `width` columns with the cursor in view,
+/// and the column the cursor sits at within it
+///
+/// Scrolls once the cursor reaches the last column; a wide character is
+/// never cut in half at either edge.
+pub fn cursor_window(text: &str, cursor: usize, width: usize) -> (String, usize) {
+    let column = display_width(&text[..cursor.min(text.len())]);
+    let scroll = if column >= width.saturating_sub(1) { column.saturating_sub(width.saturating_sub(2)) } else { 0 };
+
+    let mut skipped = 0;
+    let mut visible = String::new();
+    let mut used = 0;
+    for g in text.graphemes(true) {
+        let w = g.width();
+        if skipped < scroll {
+            skipped += w;
+            continue;
+        }
+        if used + w > width {
+            break;
+        }
+        visible.push_str(g);
+        used += w;
+    }
+    (visible, column.saturating_sub(skipped))
+}
+
+// ============================================================================
+// Editing shared by both buffers; the cursor is a byte offset that always
+// sits on a grapheme boundary
+// ============================================================================
+
+fn insert(content: &mut String, cursor: &mut usize, c: char) {
+    content.insert(*cursor, c);
+    // What is typed can fuse with the grapheme after it (a letter before a
+    // stray combining mark, a joiner between emoji); step past the cluster
+    *cursor = ceil_boundary(content, *cursor + c.len_utf8());
+}
+
+fn delete_back(content: &mut String, cursor: &mut usize) {
+    if *cursor == 0 {
+        return;
+    }
+    let start = previous_grapheme(content, *cursor);
+    content.drain(start..*cursor);
+    *cursor = start;
+}
+
+fn delete_forward(content: &mut String, cursor: usize) {
+    let end = next_grapheme(content, cursor);
+    content.drain(cursor..end);
+}
+
+fn delete_word(content: &mut String, cursor: &mut usize) {
+    if *cursor == 0 {
+        return;
+    }
+    let start = find_word_boundary_back(content, *cursor);
+    content.drain(start..*cursor);
+    *cursor = start;
+}
+
+fn clear_to_start(content: &mut String, cursor: &mut usize) {
+    content.drain(..*cursor);
+    *cursor = 0;
 }
 
 // ============================================================================
@@ -136,7 +258,7 @@ impl TextEditing for TextBuffer {
     }
 
     fn set_cursor(&mut self, pos: usize) {
-        self.cursor = pos.min(self.content.len());
+        self.cursor = floor_boundary(&self.content, pos);
     }
 
     fn set_content(&mut self, content: &str) {
@@ -145,7 +267,7 @@ impl TextEditing for TextBuffer {
     }
 
     fn len(&self) -> usize {
-        self.content.len()
+        self.content.graphemes(true).count()
     }
 
     fn is_empty(&self) -> bool {
@@ -158,52 +280,31 @@ impl TextEditing for TextBuffer {
     }
 
     fn insert_char(&mut self, c: char) {
-        self.content.insert(self.cursor, c);
-        self.cursor += 1;
+        insert(&mut self.content, &mut self.cursor, c);
     }
 
     fn delete_char(&mut self) {
-        if self.cursor == 0 {
-            return;
-        }
-        self.cursor -= 1;
-        self.content.remove(self.cursor);
+        delete_back(&mut self.content, &mut self.cursor);
     }
 
     fn delete_char_forward(&mut self) {
-        if self.cursor >= self.content.len() {
-            return;
-        }
-        self.content.remove(self.cursor);
+        delete_forward(&mut self.content, self.cursor);
     }
 
     fn delete_word(&mut self) {
-        if self.cursor == 0 {
-            return;
-        }
-        let new_cursor = find_word_boundary_back(&self.content, self.cursor);
-        self.content.drain(new_cursor..self.cursor);
-        self.cursor = new_cursor;
+        delete_word(&mut self.content, &mut self.cursor);
     }
 
     fn clear_to_start(&mut self) {
-        if self.cursor == 0 {
-            return;
-        }
-        self.content.drain(..self.cursor);
-        self.cursor = 0;
+        clear_to_start(&mut self.content, &mut self.cursor);
     }
 
     fn cursor_left(&mut self) {
-        if self.cursor > 0 {
-            self.cursor -= 1;
-        }
+        self.cursor = previous_grapheme(&self.content, self.cursor);
     }
 
     fn cursor_right(&mut self) {
-        if self.cursor < self.content.len() {
-            self.cursor += 1;
-        }
+        self.cursor = next_grapheme(&self.content, self.cursor);
     }
 
     fn cursor_word_left(&mut self) {
@@ -246,6 +347,32 @@ impl SecureTextBuffer {
             cursor: 0,
         }
     }
+
+    /// The text, handed back without a copy
+    pub fn into_content(self) -> Zeroizing<String> {
+        self.content
+    }
+
+    /// Make room for `additional` bytes; growing in place would leave the
+    /// old allocation behind unzeroized, so the text moves to a bigger one
+    /// and the old is wiped as it drops
+    fn reserve(&mut self, additional: usize) {
+        let needed = self.content.len() + additional;
+        if needed <= self.content.capacity() {
+            return;
+        }
+        let mut grown = String::with_capacity(needed.max(self.content.capacity() * 2).max(32));
+        grown.push_str(&self.content);
+        self.content = Zeroizing::new(grown);
+    }
+}
+
+/// Take over `content` as it is, with the cursor at its end
+impl From<Zeroizing<String>> for SecureTextBuffer {
+    fn from(content: Zeroizing<String>) -> Self {
+        let cursor = content.len();
+        Self { content, cursor }
+    }
 }
 
 impl TextEditing for SecureTextBuffer {
@@ -258,16 +385,18 @@ impl TextEditing for SecureTextBuffer {
     }
 
     fn set_cursor(&mut self, pos: usize) {
-        self.cursor = pos.min(self.content.len());
+        self.cursor = floor_boundary(&self.content, pos);
     }
 
     fn set_content(&mut self, content: &str) {
-        *self.content = content.to_string();
+        self.content.zeroize();
+        self.reserve(content.len());
+        self.content.push_str(content);
         self.cursor = self.content.len();
     }
 
     fn len(&self) -> usize {
-        self.content.len()
+        self.content.graphemes(true).count()
     }
 
     fn is_empty(&self) -> bool {
@@ -275,60 +404,37 @@ impl TextEditing for SecureTextBuffer {
     }
 
     fn clear(&mut self) {
-        self.content.clear();
+        self.content.zeroize();
         self.cursor = 0;
     }
 
     fn insert_char(&mut self, c: char) {
-        self.content.insert(self.cursor, c);
-        self.cursor += 1;
+        self.reserve(c.len_utf8());
+        insert(&mut self.content, &mut self.cursor, c);
     }
 
     fn delete_char(&mut self) {
-        if self.cursor == 0 {
-            return;
-        }
-        self.cursor -= 1;
-        self.content.remove(self.cursor);
+        delete_back(&mut self.content, &mut self.cursor);
     }
 
     fn delete_char_forward(&mut self) {
-        if self.cursor >= self.content.len() {
-            return;
-        }
-        self.content.remove(self.cursor);
+        delete_forward(&mut self.content, self.cursor);
     }
 
     fn delete_word(&mut self) {
-        if self.cursor == 0 {
-            return;
-        }
-        let before: String = self.content.chars().take(self.cursor).collect();
-        let after: String = self.content.chars().skip(self.cursor).collect();
-        let new_cursor = find_word_boundary_back(&before, self.cursor);
-        *self.content = format!("{}{}", &before[..new_cursor], after);
-        self.cursor = new_cursor;
+        delete_word(&mut self.content, &mut self.cursor);
     }
 
     fn clear_to_start(&mut self) {
-        if self.cursor == 0 {
-            return;
-        }
-        let after: String = self.content.chars().skip(self.cursor).collect();
-        *self.content = after;
-        self.cursor = 0;
+        clear_to_start(&mut self.content, &mut self.cursor);
     }
 
     fn cursor_left(&mut self) {
-        if self.cursor > 0 {
-            self.cursor -= 1;
-        }
+        self.cursor = previous_grapheme(&self.content, self.cursor);
     }
 
     fn cursor_right(&mut self) {
-        if self.cursor < self.content.len() {
-            self.cursor += 1;
-        }
+        self.cursor = next_grapheme(&self.content, self.cursor);
     }
 
     fn cursor_word_left(&mut self) {
@@ -430,6 +536,63 @@ mod tests {
         assert!(!handle_text_key(&mut buf, KeyCode::Enter, KeyModifiers::NONE));
     }
 
+    #[test]
+    fn test_graphemes() {
+        // "e" with a combining acute, a flag, and a family joined with ZWJs
+        let mut buf = TextBuffer::with_content("ae\u{301}🇫🇷👨\u{200d}👩\u{200d}👧");
+        assert_eq!(buf.len(), 4);
+        buf.cursor_left();
+        buf.delete_char();
+        assert_eq!(buf.content(), "ae\u{301}👨\u{200d}👩\u{200d}👧");
+        buf.cursor_home();
+        buf.cursor_right();
+        buf.delete_char_forward();
+        assert_eq!(buf.content(), "a👨\u{200d}👩\u{200d}👧");
+
+        buf.set_cursor(3);
+        assert_eq!(buf.cursor(), 1);
+        buf.insert_char('é');
+        buf.insert_char('\u{301}');
+        assert_eq!(buf.cursor(), 5);
+        assert_eq!(buf.len(), 3);
+    }
+
+    #[test]
+    fn test_wide_words() {
+        let mut buf = SecureTextBuffer::new();
+        buf.set_content("密码 パス-word");
+        buf.cursor_word_left();
+        assert_eq!(&buf.content()[buf.cursor()..], "word");
+        buf.cursor_word_left();
+        assert_eq!(&buf.content()[buf.cursor()..], "パス-word");
+        buf.cursor_word_left();
+        assert_eq!(buf.cursor(), 0);
+        buf.cursor_word_right();
+        assert_eq!(&buf.content()[buf.cursor()..], "パス-word");
+        buf.cursor_end();
+        buf.delete_word();
+        assert_eq!(buf.content(), "密码 パス-");
+        buf.delete_word();
+        assert_eq!(buf.content(), "密码 ");
+    }
+
+    #[test]
+    fn test_cursor_window() {
+        assert_eq!(display_width("日本a"), 5);
+        assert_eq!(cursor_window("abc", 1, 10), ("abc".to_string(), 1));
+
+        // Scrolled so the cursor at the end stays in view, no half characters
+        let text = "日本語のパスワード";
+        let (visible, column) = cursor_window(text, text.len(), 8);
+        assert_eq!(visible, "ワード");
+        assert_eq!(column, 6);
+        assert!(display_width(&visible) <= 8);
+
+        let (masked, cursor) = mask("e\u{301}🇫🇷x", 3);
+        assert_eq!(masked, "•••");
+        assert_eq!(cursor, '•'.len_utf8());
+    }
+
     #[test]
     fn test_secure_buffer_basic() {
         let mut buf = SecureTextBuffer::new();
@@ -457,4 +620,14 @@ mod tests {
         buf.delete_word();
         assert_eq!(buf.content(), "hello ");
     }
+
+    #[test]
+    fn test_secure_buffer_round_trip() {
+        let mut buf = SecureTextBuffer::from(Zeroizing::new("key".to_string()));
+        assert_eq!(buf.cursor(), 3);
+        for c in "-that-outgrows-its-first-allocation".chars() {
+            buf.insert_char(c);
+        }
+        assert_eq!(buf.into_content().as_str(), "key-that-outgrows-its-first-allocation");
+    }
 }
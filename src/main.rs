@@ -6,7 +6,9 @@ use std::io;
 use std::path::PathBuf;
 use std::time::Duration;
 
-use crossterm::event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind};
+use crossterm::event::{
+    self, DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
+};
 use crossterm::execute;
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
 use ratatui::backend::CrosstermBackend;
@@ -15,9 +17,12 @@ use ratatui::Terminal;
 use crate::input::{handle_text_key, SecureTextBuffer, TextEditing};
 
 mod app;
+mod cli;
 mod crypto;
 mod db;
 mod input;
+mod qr;
+mod security;
 mod ui;
 mod vault;
 
@@ -26,27 +31,34 @@ use app::{App, AppConfig};
 type Term = Terminal<CrosstermBackend<io::Stdout>>;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    harden_process();
+    let hardening = security::harden_process();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if cli::is_cli_invocation(&args) {
+        std::process::exit(cli::run(args, AppConfig::default().vault_path));
+    }
 
     let config = parse_config();
     ensure_vault_dir(&config)?;
 
     let mut terminal = setup_terminal()?;
     let mut app = App::new(config);
+    app.hardening = hardening;
 
     let result = run_with_auth(&mut terminal, &mut app);
 
     cleanup_terminal(&mut terminal)?;
+    if let Some(message) = &app.exit_message {
+        eprintln!("vault: {}", message);
+    }
     result
 }
 
-fn harden_process() {
-    #[cfg(unix)]
-    unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 0); }
-}
-
 fn parse_config() -> AppConfig {
-    let mut config = AppConfig::default();
+    let mut config = AppConfig::load().unwrap_or_else(|e| {
+        eprintln!("vault: ignoring config file: {}", e);
+        AppConfig::default()
+    });
     if let Some(path) = std::env::args().nth(1) {
         config.vault_path = PathBuf::from(path);
     }
@@ -63,19 +75,41 @@ fn ensure_vault_dir(config: &AppConfig) -> Result<(), Box<dyn std::error::Error>
 fn setup_terminal() -> Result<Term, Box<dyn std::error::Error>> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture, EnableFocusChange)?;
     let backend = CrosstermBackend::new(stdout);
     Ok(Terminal::new(backend)?)
 }
 
 fn cleanup_terminal(terminal: &mut Term) -> Result<(), Box<dyn std::error::Error>> {
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture)?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture, DisableFocusChange)?;
     terminal.show_cursor()?;
     Ok(())
 }
 
+/// Skip the password prompt when a running agent holds this vault's key
+fn try_agent_unlock(app: &mut App) {
+    let Some(socket) = cli::agent::running_socket() else { return };
+    let Ok(key) = cli::agent::request_key(&socket, &app.config.vault_path) else { return };
+    let _ = app.unlock_with_key(key);
+}
+
+/// Skip the password prompt when the kernel keyring still holds a session
+/// key for this vault
+fn try_session_unlock(app: &mut App) {
+    if app.config.keyring_session {
+        let _ = app.unlock_with_session();
+    }
+}
+
 fn run_with_auth(terminal: &mut Term, app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
+    if app.is_locked() {
+        try_agent_unlock(app);
+    }
+    if app.is_locked() {
+        try_session_unlock(app);
+    }
+
     if app.needs_init() {
         run_init(terminal, app)?;
     } else if app.is_locked() {
@@ -84,6 +118,7 @@ fn run_with_auth(terminal: &mut Term, app: &mut App) -> Result<(), Box<dyn std::
 
     if !app.should_quit {
         run_app(terminal, app)?;
+        app.auto_backup("exit");
     }
     Ok(())
 }
@@ -91,6 +126,8 @@ fn run_with_auth(terminal: &mut Term, app: &mut App) -> Result<(), Box<dyn std::
 enum AppEvent {
     Key(KeyEvent),
     Mouse(crossterm::event::MouseEvent),
+    /// The terminal gained (true) or lost focus
+    Focus(bool),
 }
 
 fn poll_event() -> Result<Option<AppEvent>, Box<dyn std::error::Error>> {
@@ -100,6 +137,8 @@ fn poll_event() -> Result<Option<AppEvent>, Box<dyn std::error::Error>> {
     match event::read()? {
         Event::Key(key) if key.kind == KeyEventKind::Press => Ok(Some(AppEvent::Key(key))),
         Event::Mouse(mouse) => Ok(Some(AppEvent::Mouse(mouse))),
+        Event::FocusGained => Ok(Some(AppEvent::Focus(true))),
+        Event::FocusLost => Ok(Some(AppEvent::Focus(false))),
         _ => Ok(None),
     }
 }
@@ -110,10 +149,12 @@ fn draw_password_dialog(
     prompt: &str,
     field: &SecureTextBuffer,
     error: Option<&str>,
+    theme: ui::Theme,
 ) -> Result<(), Box<dyn std::error::Error>> {
     terminal.draw(|frame| {
-        let dialog = build_password_dialog(title, prompt, field, error);
+        let dialog = build_password_dialog(title, prompt, field, error).monochrome(theme.is_monochrome());
         frame.render_widget(dialog, frame.area());
+        theme.apply(frame.buffer_mut());
     })?;
     Ok(())
 }
@@ -152,7 +193,7 @@ struct InitState {
 
 fn init_iteration(terminal: &mut Term, app: &mut App, state: &mut InitState) -> Result<(), Box<dyn std::error::Error>> {
     let (title, prompt, field) = init_dialog_params(state.confirming, &state.password, &state.confirm);
-    draw_password_dialog(terminal, title, prompt, field, state.error.as_deref())?;
+    draw_password_dialog(terminal, title, prompt, field, state.error.as_deref(), app.config.theme())?;
 
     let Some(AppEvent::Key(key)) = poll_event()? else { return Ok(()) };
 
@@ -226,7 +267,7 @@ fn finalize_init(state: &mut InitState, app: &mut App) -> Option<String> {
 }
 
 fn run_unlock(terminal: &mut Term, app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
-    let mut state = UnlockState::default();
+    let mut state = UnlockState { fido2: app.vault.has_fido2(), ..Default::default() };
 
     while !state.done {
         unlock_iteration(terminal, app, &mut state)?;
@@ -237,22 +278,55 @@ fn run_unlock(terminal: &mut Term, app: &mut App) -> Result<(), Box<dyn std::err
 #[derive(Default)]
 struct UnlockState {
     password: SecureTextBuffer,
+    /// Asked for when the YubiKey or the TPM cannot answer, in place of it
+    recovery: Option<SecureTextBuffer>,
+    /// A security key is set up, so an empty password means tapping it
+    fido2: bool,
     error: Option<String>,
     attempts: u32,
+    /// The unlock running on its worker thread; keys wait until it is done
+    pending: Option<app::Task<vault::VaultResult<Option<&'static str>>>>,
     done: bool,
 }
 
 
 fn unlock_iteration(terminal: &mut Term, app: &mut App, state: &mut UnlockState) -> Result<(), Box<dyn std::error::Error>> {
-    draw_password_dialog(terminal, "  Unlock Vault ", "Enter master password:", &state.password, state.error.as_deref())?;
+    let (prompt, field) = match &state.recovery {
+        Some(code) => ("Enter recovery code:", code),
+        None if state.fido2 => ("Enter master password, or nothing to tap the security key:", &state.password),
+        None => ("Enter master password:", &state.password),
+    };
+    let Some(task) = state.pending.as_mut() else {
+        draw_password_dialog(terminal, "  Unlock Vault ", prompt, field, state.error.as_deref(), app.config.theme())?;
+        let Some(AppEvent::Key(key)) = poll_event()? else { return Ok(()) };
+        handle_unlock_key(key, state, app);
+        return Ok(());
+    };
 
-    let Some(AppEvent::Key(key)) = poll_event()? else { return Ok(()) };
+    let status = format!("{} Unlocking...", ui::components::dialogs::spinner(task.elapsed()));
+    let theme = app.config.theme();
+    terminal.draw(|frame| {
+        let dialog = build_password_dialog("  Unlock Vault ", prompt, field, None).status(&status).monochrome(theme.is_monochrome());
+        frame.render_widget(dialog, frame.area());
+        theme.apply(frame.buffer_mut());
+    })?;
+    // Drained so nothing typed meanwhile lands in the vault once it opens
+    poll_event()?;
 
-    handle_unlock_key(key, state, app);
+    if let Some((vault, output)) = task.poll() {
+        state.pending = None;
+        let result = app.complete_unlock(vault, output);
+        process_unlock_result(result, state, app);
+    }
     Ok(())
 }
 
 fn handle_unlock_key(key: KeyEvent, state: &mut UnlockState, app: &mut App) {
+    if key.code == KeyCode::Esc && state.recovery.is_some() {
+        state.recovery = None;
+        state.error = None;
+        return;
+    }
     if key.code == KeyCode::Esc {
         app.should_quit = true;
         state.done = true;
@@ -264,17 +338,55 @@ fn handle_unlock_key(key: KeyEvent, state: &mut UnlockState, app: &mut App) {
         return;
     }
 
-    handle_text_key(&mut state.password, key.code, key.modifiers);
+    let field = state.recovery.as_mut().unwrap_or(&mut state.password);
+    handle_text_key(field, key.code, key.modifiers);
 }
 
 fn process_unlock_attempt(state: &mut UnlockState, app: &mut App) {
-    if app.unlock(state.password.content()).is_ok() {
+    // Looks like any other exit to whoever forced the password out
+    if state.recovery.is_none() && app.is_duress_password(state.password.content()) {
+        // Silent whatever survives, for the same reason
+        let _ = app.wipe();
         state.done = true;
         return;
     }
 
+    let method = match &state.recovery {
+        Some(code) => app::UnlockMethod::RecoveryCode(secret(&state.password), secret(code)),
+        None if state.fido2 && state.password.is_empty() => app::UnlockMethod::SecurityKey,
+        None => app::UnlockMethod::Password(secret(&state.password)),
+    };
+    state.error = None;
+    state.pending = Some(app.begin_unlock(method));
+}
+
+fn secret(field: &SecureTextBuffer) -> secrecy::SecretString {
+    secrecy::SecretString::from(field.content().to_string())
+}
+
+fn process_unlock_result(result: Result<(), Box<dyn std::error::Error>>, state: &mut UnlockState, app: &mut App) {
+    match result {
+        Ok(()) => {
+            state.done = true;
+            return;
+        }
+        // No token or TPM to ask; the recovery code written down at setup stands in for it
+        Err(e) if token_missing(e.as_ref()) => {
+            state.recovery = Some(SecureTextBuffer::default());
+            state.error = Some(format!(" {}; Esc to retry", e));
+            return;
+        }
+        // A missing or unreadable keyfile, or an absent security key, is no wrong guess
+        Err(e) if keyfile_error(e.as_ref()) => {
+            state.error = Some(format!(" {}", e));
+            return;
+        }
+        Err(_) => {}
+    }
+
     state.attempts += 1;
     state.password.clear();
+    state.recovery = None;
     let _ = app.vault.record_failed_unlock();
     state.error = Some(format!(" Invalid password ({}/5)", state.attempts));
 
@@ -284,6 +396,25 @@ fn process_unlock_attempt(state: &mut UnlockState, app: &mut App) {
     }
 }
 
+fn keyfile_error(e: &(dyn std::error::Error + 'static)) -> bool {
+    matches!(
+        e.downcast_ref::<vault::VaultError>(),
+        Some(
+            vault::VaultError::KeyfileRequired
+                | vault::VaultError::IoError(_)
+                | vault::VaultError::OperationFailed(_)
+                | vault::VaultError::SecurityKeyUnavailable(_)
+        )
+    )
+}
+
+fn token_missing(e: &(dyn std::error::Error + 'static)) -> bool {
+    matches!(
+        e.downcast_ref::<vault::VaultError>(),
+        Some(vault::VaultError::TokenUnavailable(_) | vault::VaultError::TpmUnavailable(_))
+    )
+}
+
 #[derive(Default)]
 struct PasswordChangeState {
     current: SecureTextBuffer,
@@ -321,6 +452,8 @@ fn change_reset(state: &mut PasswordChangeState) {
 enum ChangeResult {
     Continue,
     Cancel,
+    /// Both new passwords match; rehash and rewrap now
+    Apply,
     Success,
 }
 
@@ -336,12 +469,42 @@ fn run_password_change(terminal: &mut Term, app: &mut App) -> Result<bool, Box<d
 }
 
 fn change_iteration(terminal: &mut Term, app: &mut App, state: &mut PasswordChangeState) -> Result<ChangeResult, Box<dyn std::error::Error>> {
-    let (prompt, field) = change_prompt_and_field(state);
-    draw_password_dialog(terminal, "  Change Master Key ", prompt, field, state.error.as_deref())?;
+    draw_change_dialog(terminal, state, None, app.config.theme())?;
 
     let Some(AppEvent::Key(key)) = poll_event()? else { return Ok(ChangeResult::Continue) };
 
-    Ok(handle_change_key(key, state, &mut app.vault))
+    match handle_change_key(key, state, &mut app.vault) {
+        ChangeResult::Apply => {
+            // Argon2 and the rewrap take a moment; say so before blocking
+            draw_change_dialog(terminal, state, Some("Hashing and re-wrapping the key..."), app.config.theme())?;
+            Ok(apply_password_change(state, &mut app.vault))
+        }
+        result => Ok(result),
+    }
+}
+
+/// The change dialog, with the new password's strength from the second step on
+fn draw_change_dialog(
+    terminal: &mut Term,
+    state: &PasswordChangeState,
+    status: Option<&str>,
+    theme: ui::Theme,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (prompt, field) = change_prompt_and_field(state);
+    let strength = (state.step > 0).then(|| crypto::password_strength(state.new_pass.content()));
+    terminal.draw(|frame| {
+        let mut dialog = build_password_dialog("  Change Master Key ", prompt, field, state.error.as_deref())
+            .monochrome(theme.is_monochrome());
+        if let Some(score) = strength {
+            dialog = dialog.strength(score);
+        }
+        if let Some(status) = status {
+            dialog = dialog.status(status);
+        }
+        frame.render_widget(dialog, frame.area());
+        theme.apply(frame.buffer_mut());
+    })?;
+    Ok(())
 }
 
 fn handle_change_key(key: KeyEvent, state: &mut PasswordChangeState, vault: &mut vault::Vault) -> ChangeResult {
@@ -361,7 +524,7 @@ fn process_change_step(state: &mut PasswordChangeState, vault: &mut vault::Vault
     match state.step {
         0 => process_change_verify(state, vault),
         1 => process_change_new(state),
-        _ => process_change_confirm(state, vault),
+        _ => process_change_confirm(state),
     }
 }
 
@@ -402,19 +565,21 @@ fn process_change_new(state: &mut PasswordChangeState) -> ChangeResult {
     ChangeResult::Continue
 }
 
-fn process_change_confirm(state: &mut PasswordChangeState, vault: &mut vault::Vault) -> ChangeResult {
+fn process_change_confirm(state: &mut PasswordChangeState) -> ChangeResult {
     if state.new_pass.content() != state.confirm.content() {
         state.confirm.clear();
         state.error = Some("Passwords do not match".into());
         return ChangeResult::Continue;
     }
+    ChangeResult::Apply
+}
 
+fn apply_password_change(state: &mut PasswordChangeState, vault: &mut vault::Vault) -> ChangeResult {
     if let Err(e) = vault.change_password(state.current.content(), state.new_pass.content()) {
-        state.error = Some(change_confirm_error_msg(e));
         change_reset(state);
+        state.error = Some(change_confirm_error_msg(e));
         return ChangeResult::Continue;
     }
-
     ChangeResult::Success
 }
 
@@ -431,7 +596,24 @@ fn run_app(terminal: &mut Term, app: &mut App) -> Result<(), Box<dyn std::error:
 }
 
 fn app_iteration(terminal: &mut Term, app: &mut App) -> Result<bool, Box<dyn std::error::Error>> {
+    app.tick_task();
+    // The vault is on a worker thread: keep drawing, and drop input until it
+    // is back; focus changes are kept, or leaving now would never lock
+    if app.is_busy() {
+        terminal.draw(|frame| app.render(frame))?;
+        if let Some(AppEvent::Focus(focused)) = poll_event()? {
+            app.defer_focus(focused);
+        }
+        return Ok(false);
+    }
     app.tick_totp();
+    app.tick_backup();
+    app.tick_auto_export();
+    app.tick_audit_prune();
+    app.tick_deep_search();
+    app.tick_copy_sequence();
+    app.tick_suspend();
+    app.tick_lock_warning();
     terminal.draw(|frame| app.render(frame))?;
     if process_app_input(terminal, app)? { return Ok(true); }
     app.check_password_timeout();
@@ -442,16 +624,32 @@ fn app_iteration(terminal: &mut Term, app: &mut App) -> Result<bool, Box<dyn std
 fn process_app_input(terminal: &mut Term, app: &mut App) -> Result<bool, Box<dyn std::error::Error>> {
     let Some(ev) = poll_event()? else { return Ok(false) };
 
-    app.vault.update_activity();
-
     let quit = match ev {
-        AppEvent::Key(key) => app.handle_key_event(key)?,
-        AppEvent::Mouse(mouse) => app.handle_mouse_event(mouse),
+        AppEvent::Key(key) => {
+            let dismissed = app.cancel_lock_warning();
+            app.record_input();
+            if dismissed { false } else { app.handle_key_event(key)? }
+        }
+        AppEvent::Mouse(mouse) => {
+            app.cancel_lock_warning();
+            app.record_input();
+            app.handle_mouse_event(mouse)
+        }
+        // Focus changes are no sign of anyone at the keyboard
+        AppEvent::Focus(true) => {
+            app.focus_gained();
+            false
+        }
+        AppEvent::Focus(false) => {
+            app.focus_lost();
+            false
+        }
     };
 
     if quit { return Ok(true); }
 
     handle_password_change_request(terminal, app)?;
+    handle_reauth_request(terminal, app)?;
     Ok(false)
 }
 
@@ -461,14 +659,97 @@ fn handle_password_change_request(terminal: &mut Term, app: &mut App) -> Result<
     }
 
     app.wants_password_change = false;
+    let had_fido2 = app.vault.has_fido2();
     match run_password_change(terminal, app) {
-        Ok(true) => app.set_message("Password changed successfully", ui::MessageType::Success),
+        Ok(true) => {
+            let detail = match app.vault.kdf_params() {
+                Ok(kdf) => format!("Master password changed; DEK re-wrapped, hashed with {}", kdf.summary()),
+                Err(_) => "Master password changed; DEK re-wrapped".to_string(),
+            };
+            app.log_audit(db::AuditAction::Update, None, None, None, Some(&detail))?;
+            // The security key held a copy of the old master key
+            let message = if had_fido2 {
+                "Password changed successfully; :fido2 on sets up the security key again"
+            } else {
+                "Password changed successfully"
+            };
+            app.set_message(message, ui::MessageType::Success);
+        }
         Ok(false) => {}
         Err(e) => app.set_message(&format!("Error: {}", e), ui::MessageType::Error),
     }
     Ok(())
 }
 
+#[derive(Default)]
+struct ReauthState {
+    password: SecureTextBuffer,
+    error: Option<String>,
+    attempts: u32,
+}
+
+enum ReauthResult {
+    Continue,
+    Cancel,
+    Confirmed,
+    /// Too many wrong passwords; the vault locks
+    Failed,
+}
+
+fn handle_reauth_request(terminal: &mut Term, app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(guarded) = app.reauth_request.take() else { return Ok(()) };
+
+    let mut state = ReauthState::default();
+    let mut result = ReauthResult::Continue;
+    while matches!(result, ReauthResult::Continue) {
+        result = reauth_iteration(terminal, app, &mut state)?;
+    }
+
+    match result {
+        ReauthResult::Confirmed => {
+            app.vault.update_activity();
+            if let Err(e) = app.resume_after_reauth(guarded, state.password.content()) {
+                app.set_message(&format!("Error: {}", e), ui::MessageType::Error);
+            }
+        }
+        ReauthResult::Failed => app.lock(),
+        _ => app.set_message("Cancelled", ui::MessageType::Info),
+    }
+    Ok(())
+}
+
+fn reauth_iteration(terminal: &mut Term, app: &mut App, state: &mut ReauthState) -> Result<ReauthResult, Box<dyn std::error::Error>> {
+    // Leave the prompt to the auto-lock rather than keep it up forever
+    if app.should_auto_lock() {
+        return Ok(ReauthResult::Cancel);
+    }
+    draw_password_dialog(terminal, "  Confirm Master Key ", "Master password:", &state.password, state.error.as_deref(), app.config.theme())?;
+
+    let Some(AppEvent::Key(key)) = poll_event()? else { return Ok(ReauthResult::Continue) };
+
+    match key.code {
+        KeyCode::Esc => Ok(ReauthResult::Cancel),
+        KeyCode::Enter => Ok(process_reauth_attempt(state, app)),
+        _ => {
+            handle_text_key(&mut state.password, key.code, key.modifiers);
+            Ok(ReauthResult::Continue)
+        }
+    }
+}
+
+fn process_reauth_attempt(state: &mut ReauthState, app: &mut App) -> ReauthResult {
+    if app.vault.verify_password(state.password.content()).is_ok() {
+        return ReauthResult::Confirmed;
+    }
+
+    state.attempts += 1;
+    state.password.clear();
+    let _ = app.log_audit(db::AuditAction::FailedUnlock, None, None, None, Some("Re-authentication"));
+    state.error = Some(format!(" Invalid password ({}/3)", state.attempts));
+
+    if state.attempts >= 3 { ReauthResult::Failed } else { ReauthResult::Continue }
+}
+
 fn check_auto_lock(terminal: &mut Term, app: &mut App) -> Result<(), Box<dyn std::error::Error>> {
     if app.should_auto_lock() { app.lock(); }
     while app.is_locked() && !app.should_quit {
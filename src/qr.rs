@@ -0,0 +1,1035 @@
+//! QR Code Reader
+//!
+//! Reads the QR codes sites show when setting up 2FA, from a PNG screenshot.
+//! Codes may be scaled or rotated, but are expected to be seen straight-on:
+//! the perspective of a photo taken at an angle is not corrected.
+
+use std::cmp::Reverse;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Error type for reading QR codes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QrError {
+    /// File missing or not a PNG
+    Image(String),
+    /// No three finder patterns that form a code
+    NotFound,
+    /// A code was found but could not be decoded
+    Unreadable,
+    /// The code holds Kanji or another encoding this reader lacks
+    Unsupported,
+}
+
+impl std::fmt::Display for QrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QrError::Image(e) => write!(f, "Could not read image: {}", e),
+            QrError::NotFound => write!(f, "No QR code found in image"),
+            QrError::Unreadable => write!(f, "QR code is damaged or too blurry to read"),
+            QrError::Unsupported => write!(f, "QR code holds data that isn't text"),
+        }
+    }
+}
+
+impl std::error::Error for QrError {}
+
+/// A grayscale image, one byte per pixel
+pub struct LumaImage {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl LumaImage {
+    pub fn new(width: usize, height: usize, pixels: Vec<u8>) -> Self {
+        assert_eq!(pixels.len(), width * height, "pixel buffer does not match image size");
+        Self { width, height, pixels }
+    }
+
+    /// Load a PNG, flattening color and transparency onto white
+    pub fn open(path: &Path) -> Result<Self, QrError> {
+        let file = File::open(path).map_err(|e| QrError::Image(e.to_string()))?;
+        let mut decoder = png::Decoder::new(BufReader::new(file));
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(|e| QrError::Image(e.to_string()))?;
+        let size = reader.output_buffer_size().ok_or_else(|| QrError::Image("image too large".into()))?;
+        let mut buf = vec![0; size];
+        let info = reader.next_frame(&mut buf).map_err(|e| QrError::Image(e.to_string()))?;
+
+        let channels = info.color_type.samples();
+        let (width, height) = (info.width as usize, info.height as usize);
+        let mut pixels = Vec::with_capacity(width * height);
+        for row in buf.chunks(info.line_size).take(height) {
+            for px in row.chunks(channels).take(width) {
+                pixels.push(to_luma(px));
+            }
+        }
+        Ok(Self::new(width, height, pixels))
+    }
+}
+
+/// Luma of a gray, gray+alpha, RGB or RGBA pixel, composited onto white
+fn to_luma(px: &[u8]) -> u8 {
+    let (luma, alpha) = match *px {
+        [l] => (l as u32, 255),
+        [l, a] => (l as u32, a as u32),
+        [r, g, b] => ((r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000, 255),
+        [r, g, b, a] => ((r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000, a as u32),
+        _ => (255, 255),
+    };
+    ((luma * alpha + 255 * (255 - alpha)) / 255) as u8
+}
+
+/// Read the text of the QR code in a PNG file
+pub fn read_file(path: &Path) -> Result<String, QrError> {
+    decode(&LumaImage::open(path)?)
+}
+
+/// Read the text of the QR code in an image
+pub fn decode(image: &LumaImage) -> Result<String, QrError> {
+    let bitmap = Bitmap::threshold(image);
+    let finders = find_finders(&bitmap);
+
+    let mut result = Err(QrError::NotFound);
+    for [tl, tr, bl] in finder_triples(&finders) {
+        for version in candidate_versions(&tl, &tr, &bl) {
+            let Some(grid) = Grid::sample(&bitmap, &tl, &tr, &bl, version) else { continue };
+            match grid.decode() {
+                Ok(text) => return Ok(text),
+                Err(QrError::Unsupported) => return Err(QrError::Unsupported),
+                Err(e) => result = Err(e),
+            }
+        }
+    }
+    result
+}
+
+// ============================================================================
+// Locating the code
+// ============================================================================
+
+/// Image split into dark and light pixels
+struct Bitmap {
+    width: usize,
+    height: usize,
+    dark: Vec<bool>,
+}
+
+impl Bitmap {
+    /// Split at the midpoint between the darkest and lightest pixel
+    fn threshold(image: &LumaImage) -> Self {
+        let min = image.pixels.iter().copied().min().unwrap_or(0) as u16;
+        let max = image.pixels.iter().copied().max().unwrap_or(0) as u16;
+        let mid = ((min + max) / 2) as u8;
+        // A flat image has nothing to find
+        let dark = match max - min {
+            0..32 => vec![false; image.pixels.len()],
+            _ => image.pixels.iter().map(|&p| p < mid).collect(),
+        };
+        Self { width: image.width, height: image.height, dark }
+    }
+
+    fn is_dark(&self, x: f32, y: f32) -> Option<bool> {
+        if x < 0.0 || y < 0.0 || x >= self.width as f32 || y >= self.height as f32 {
+            return None;
+        }
+        Some(self.dark[y as usize * self.width + x as usize])
+    }
+
+    fn row_runs(&self, y: usize) -> Vec<Run> {
+        runs((0..self.width).map(|x| self.dark[y * self.width + x]))
+    }
+
+    fn column_runs(&self, x: usize) -> Vec<Run> {
+        runs((0..self.height).map(|y| self.dark[y * self.width + x]))
+    }
+}
+
+/// A stretch of same-colored pixels along a row or column
+#[derive(Clone, Copy)]
+struct Run {
+    start: usize,
+    len: usize,
+    dark: bool,
+}
+
+impl Run {
+    fn center(&self) -> f32 {
+        self.start as f32 + self.len as f32 / 2.0
+    }
+
+    fn contains(&self, pos: usize) -> bool {
+        (self.start..self.start + self.len).contains(&pos)
+    }
+}
+
+fn runs(line: impl Iterator<Item = bool>) -> Vec<Run> {
+    let mut runs: Vec<Run> = Vec::new();
+    for (pos, dark) in line.enumerate() {
+        match runs.last_mut() {
+            Some(run) if run.dark == dark => run.len += 1,
+            _ => runs.push(Run { start: pos, len: 1, dark }),
+        }
+    }
+    runs
+}
+
+/// Whether five runs starting with a dark one read 1:1:3:1:1, a line
+/// through the middle of a finder pattern
+fn is_finder_line(runs: &[Run]) -> bool {
+    if runs.len() != 5 || !runs[0].dark {
+        return false;
+    }
+    let total: usize = runs.iter().map(|r| r.len).sum();
+    if total < 7 {
+        return false;
+    }
+    let module = total as f32 / 7.0;
+    runs.iter().zip([1.0, 1.0, 3.0, 1.0, 1.0]).all(|(run, width)| {
+        (run.len as f32 - module * width).abs() < module * width / 2.0
+    })
+}
+
+/// Check a finder line through `pos` and return its center and length
+fn finder_line_at(runs: &[Run], pos: usize) -> Option<(f32, usize)> {
+    let i = runs.iter().position(|r| r.contains(pos))?;
+    let window = runs.get(i.checked_sub(2)?..i + 3)?;
+    is_finder_line(window).then(|| (window[2].center(), window.iter().map(|r| r.len).sum()))
+}
+
+/// Center of one of the three corner squares
+#[derive(Debug, Clone, Copy)]
+struct Finder {
+    x: f32,
+    y: f32,
+    module: f32,
+    /// Rows it was seen on
+    count: usize,
+}
+
+impl Finder {
+    fn distance(&self, other: &Finder) -> f32 {
+        (self.x - other.x).hypot(self.y - other.y)
+    }
+}
+
+/// Scan every row for finder lines, confirm each across the column and row
+/// through its center, and merge the hits into patterns
+fn find_finders(bitmap: &Bitmap) -> Vec<Finder> {
+    let mut finders: Vec<Finder> = Vec::new();
+    for y in 0..bitmap.height {
+        let row = bitmap.row_runs(y);
+        for window in row.windows(5) {
+            if !is_finder_line(window) {
+                continue;
+            }
+            let Some((cy, v_len)) = finder_line_at(&bitmap.column_runs(window[2].center() as usize), y) else {
+                continue;
+            };
+            let Some((cx, h_len)) = finder_line_at(&bitmap.row_runs(cy as usize), window[2].center() as usize) else {
+                continue;
+            };
+            if v_len > h_len * 2 || h_len > v_len * 2 {
+                continue;
+            }
+            let found = Finder { x: cx, y: cy, module: (h_len + v_len) as f32 / 14.0, count: 1 };
+            merge_finder(&mut finders, found);
+        }
+    }
+    finders.sort_by_key(|f| Reverse(f.count));
+    finders
+}
+
+fn merge_finder(finders: &mut Vec<Finder>, found: Finder) {
+    let same = finders.iter_mut().find(|f| {
+        f.distance(&found) < f.module * 3.5 && (f.module / found.module - 1.0).abs() < 0.5
+    });
+    match same {
+        Some(f) => {
+            let n = f.count as f32;
+            f.x = (f.x * n + found.x) / (n + 1.0);
+            f.y = (f.y * n + found.y) / (n + 1.0);
+            f.module = (f.module * n + found.module) / (n + 1.0);
+            f.count += 1;
+        }
+        None => finders.push(found),
+    }
+}
+
+/// Sets of three finders that could be the corners of one code, as
+/// top-left, top-right and bottom-left, the most square first
+fn finder_triples(finders: &[Finder]) -> Vec<[Finder; 3]> {
+    let finders = &finders[..finders.len().min(8)];
+    let mut triples = Vec::new();
+    for i in 0..finders.len() {
+        for j in i + 1..finders.len() {
+            for k in j + 1..finders.len() {
+                if let Some(triple) = corner_order(finders[i], finders[j], finders[k]) {
+                    triples.push(triple);
+                }
+            }
+        }
+    }
+    triples.sort_by(|a, b| squareness(a).total_cmp(&squareness(b)));
+    triples
+}
+
+/// Put three finders in corner order, if they form a right angle
+fn corner_order(a: Finder, b: Finder, c: Finder) -> Option<[Finder; 3]> {
+    // The top-left corner is opposite the longest side
+    let (tl, p, q) = match (b.distance(&c), a.distance(&c), a.distance(&b)) {
+        (bc, ac, ab) if bc >= ac && bc >= ab => (a, b, c),
+        (_, ac, ab) if ac >= ab => (b, a, c),
+        _ => (c, a, b),
+    };
+    let modules = [tl.module, p.module, q.module];
+    let (min, max) = modules.iter().fold((f32::MAX, 0f32), |(lo, hi), &m| (lo.min(m), hi.max(m)));
+    if max > min * 1.5 || tl.distance(&p) < tl.module * 10.0 {
+        return None;
+    }
+
+    // Image y runs down, so top-right then bottom-left turns clockwise
+    let cross = (p.x - tl.x) * (q.y - tl.y) - (p.y - tl.y) * (q.x - tl.x);
+    let triple = if cross > 0.0 { [tl, p, q] } else { [tl, q, p] };
+    (squareness(&triple) < 0.5).then_some(triple)
+}
+
+/// How far the corners are from a square: 0 is perfect
+fn squareness([tl, tr, bl]: &[Finder; 3]) -> f32 {
+    let (top, left) = (tl.distance(tr), tl.distance(bl));
+    let dot = (tr.x - tl.x) * (bl.x - tl.x) + (tr.y - tl.y) * (bl.y - tl.y);
+    (top / left - 1.0).abs() + (dot / (top * left)).abs()
+}
+
+/// Versions whose size fits the distance between the finders, the
+/// estimate first
+fn candidate_versions(tl: &Finder, tr: &Finder, bl: &Finder) -> Vec<usize> {
+    let module = (tl.module + tr.module + bl.module) / 3.0;
+    let span = (tl.distance(tr) + tl.distance(bl)) / 2.0 / module;
+    // Finder centers sit 3.5 modules in from each edge
+    let estimate = ((span + 7.0 - 17.0) / 4.0).round().clamp(1.0, 40.0) as isize;
+    [0, -1, 1, -2, 2]
+        .iter()
+        .map(|offset| estimate + offset)
+        .filter(|v| (1..=40).contains(v))
+        .map(|v| v as usize)
+        .collect()
+}
+
+// ============================================================================
+// Reading the modules
+// ============================================================================
+
+/// Error correction levels in table order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EcLevel {
+    L,
+    M,
+    Q,
+    H,
+}
+
+impl EcLevel {
+    /// From the two bits stored in the format information
+    fn from_bits(bits: u16) -> Self {
+        match bits {
+            0 => EcLevel::M,
+            1 => EcLevel::L,
+            2 => EcLevel::H,
+            _ => EcLevel::Q,
+        }
+    }
+}
+
+/// Error correction codewords per block, by level and version
+const ECC_PER_BLOCK: [[u8; 41]; 4] = [
+    [0, 7, 10, 15, 20, 26, 18, 20, 24, 30, 18, 20, 24, 26, 30, 22, 24, 28, 30, 28, 28, 28, 28, 30, 30, 26, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+    [0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26, 30, 22, 22, 24, 24, 28, 28, 26, 26, 26, 26, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28],
+    [0, 13, 22, 18, 26, 18, 24, 18, 22, 20, 24, 28, 26, 24, 20, 30, 24, 28, 28, 26, 30, 28, 30, 30, 30, 30, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+    [0, 17, 28, 22, 16, 22, 28, 26, 26, 24, 28, 24, 28, 22, 24, 24, 30, 28, 28, 26, 28, 30, 24, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+];
+
+/// Error correction blocks, by level and version
+const BLOCKS: [[u8; 41]; 4] = [
+    [0, 1, 1, 1, 1, 1, 2, 2, 2, 2, 4, 4, 4, 4, 4, 6, 6, 6, 6, 7, 8, 8, 9, 9, 10, 12, 12, 12, 13, 14, 15, 16, 17, 18, 19, 19, 20, 21, 22, 24, 25],
+    [0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5, 5, 8, 9, 9, 10, 10, 11, 13, 14, 16, 17, 17, 18, 20, 21, 23, 25, 26, 28, 29, 31, 33, 35, 37, 38, 40, 43, 45, 47, 49],
+    [0, 1, 1, 2, 2, 4, 4, 6, 6, 8, 8, 8, 10, 12, 16, 12, 17, 16, 18, 21, 20, 23, 23, 25, 27, 29, 34, 34, 35, 38, 40, 43, 45, 48, 51, 53, 56, 59, 62, 65, 68],
+    [0, 1, 1, 2, 4, 4, 4, 5, 6, 8, 8, 11, 11, 16, 16, 18, 16, 19, 21, 25, 25, 25, 34, 30, 32, 35, 37, 40, 42, 45, 48, 51, 54, 57, 60, 63, 66, 70, 74, 77, 81],
+];
+
+/// Format information is masked so it is never all light
+const FORMAT_MASK: u16 = 0x5412;
+
+/// The 15 format bits for a level's two bits and a mask, with BCH check bits
+fn format_bits(data: u16) -> u16 {
+    let mut rem = data;
+    for _ in 0..10 {
+        rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+    }
+    ((data << 10) | (rem & 0x3ff)) ^ FORMAT_MASK
+}
+
+/// Centers of the alignment patterns along each axis
+fn alignment_positions(version: usize) -> Vec<usize> {
+    if version == 1 {
+        return Vec::new();
+    }
+    let count = version / 7 + 2;
+    let step = (version * 8 + count * 3 + 5) / (count * 4 - 4) * 2;
+    let mut positions: Vec<usize> = (0..count - 1).map(|i| version * 4 + 10 - i * step).collect();
+    positions.push(6);
+    positions.reverse();
+    positions
+}
+
+/// Whether data is inverted at a module under one of the eight masks
+fn masked(mask: u16, x: usize, y: usize) -> bool {
+    match mask {
+        0 => (x + y).is_multiple_of(2),
+        1 => y.is_multiple_of(2),
+        2 => x.is_multiple_of(3),
+        3 => (x + y).is_multiple_of(3),
+        4 => (x / 3 + y / 2).is_multiple_of(2),
+        5 => x * y % 2 + x * y % 3 == 0,
+        6 => (x * y % 2 + x * y % 3).is_multiple_of(2),
+        _ => ((x + y) % 2 + x * y % 3).is_multiple_of(2),
+    }
+}
+
+/// The code's modules, sampled from the image
+struct Grid {
+    size: usize,
+    dark: Vec<bool>,
+}
+
+impl Grid {
+    /// Sample the center of every module of a code of `version`, mapping
+    /// module coordinates onto the image through the three finder centers
+    fn sample(bitmap: &Bitmap, tl: &Finder, tr: &Finder, bl: &Finder, version: usize) -> Option<Self> {
+        let size = version * 4 + 17;
+        let span = (size - 7) as f32;
+        let (ux, uy) = ((tr.x - tl.x) / span, (tr.y - tl.y) / span);
+        let (vx, vy) = ((bl.x - tl.x) / span, (bl.y - tl.y) / span);
+
+        let mut dark = Vec::with_capacity(size * size);
+        for row in 0..size {
+            for col in 0..size {
+                let (mx, my) = (col as f32 - 3.0, row as f32 - 3.0);
+                dark.push(bitmap.is_dark(tl.x + ux * mx + vx * my, tl.y + uy * mx + vy * my)?);
+            }
+        }
+        Some(Self { size, dark })
+    }
+
+    fn get(&self, x: usize, y: usize) -> bool {
+        self.dark[y * self.size + x]
+    }
+
+    fn version(&self) -> usize {
+        (self.size - 17) / 4
+    }
+
+    fn decode(&self) -> Result<String, QrError> {
+        let (level, mask) = self.read_format().ok_or(QrError::Unreadable)?;
+        let codewords = self.read_codewords(mask);
+        let data = correct_blocks(&codewords, self.version(), level).ok_or(QrError::Unreadable)?;
+        parse_segments(&data, self.version())
+    }
+
+    /// Level and mask from whichever format copy is nearest a valid one
+    fn read_format(&self) -> Option<(EcLevel, u16)> {
+        let size = self.size;
+        let bit = |x, y, i| (self.get(x, y) as u16) << i;
+
+        let mut first = 0;
+        for i in 0..6 {
+            first |= bit(8, i, i);
+        }
+        first |= bit(8, 7, 6) | bit(8, 8, 7) | bit(7, 8, 8);
+        for i in 9..15 {
+            first |= bit(14 - i, 8, i);
+        }
+
+        let mut second = 0;
+        for i in 0..8 {
+            second |= bit(size - 1 - i, 8, i);
+        }
+        for i in 8..15 {
+            second |= bit(8, size - 15 + i, i);
+        }
+
+        (0..32u16)
+            .map(|data| {
+                let expected = format_bits(data);
+                let distance = ((first ^ expected).count_ones()).min((second ^ expected).count_ones());
+                (distance, data)
+            })
+            .min()
+            .filter(|(distance, _)| *distance <= 3)
+            .map(|(_, data)| (EcLevel::from_bits(data >> 3), data & 7))
+    }
+
+    /// Modules holding finders, timing, alignment, format and version
+    fn function_modules(&self) -> Vec<bool> {
+        let size = self.size;
+        let mut function = vec![false; size * size];
+        let mut mark = |x0: usize, y0: usize, w: usize, h: usize| {
+            for y in y0..y0 + h {
+                for x in x0..x0 + w {
+                    function[y * size + x] = true;
+                }
+            }
+        };
+
+        // Finders with their separators and format information
+        mark(0, 0, 9, 9);
+        mark(size - 8, 0, 8, 9);
+        mark(0, size - 8, 9, 8);
+        // Timing patterns
+        mark(6, 0, 1, size);
+        mark(0, 6, size, 1);
+
+        let positions = alignment_positions(self.version());
+        let last = positions.len().saturating_sub(1);
+        for (i, &x) in positions.iter().enumerate() {
+            for (j, &y) in positions.iter().enumerate() {
+                // The finders take these corners
+                if (i, j) == (0, 0) || (i, j) == (0, last) || (i, j) == (last, 0) {
+                    continue;
+                }
+                mark(x - 2, y - 2, 5, 5);
+            }
+        }
+
+        if self.version() >= 7 {
+            mark(size - 11, 0, 3, 6);
+            mark(0, size - 11, 6, 3);
+        }
+        function
+    }
+
+    /// Unmask the data modules and read them as bytes, in the two-column
+    /// zigzag from the bottom-right corner
+    fn read_codewords(&self, mask: u16) -> Vec<u8> {
+        let size = self.size;
+        let function = self.function_modules();
+        let mut bytes = Vec::new();
+        let (mut current, mut bits) = (0u8, 0);
+
+        let mut right = size - 1;
+        loop {
+            // The vertical timing pattern is skipped as a whole column
+            if right == 6 {
+                right = 5;
+            }
+            let upward = (right + 1) & 2 == 0;
+            for vert in 0..size {
+                let y = if upward { size - 1 - vert } else { vert };
+                for x in [right, right - 1] {
+                    if function[y * size + x] {
+                        continue;
+                    }
+                    current = (current << 1) | (self.get(x, y) ^ masked(mask, x, y)) as u8;
+                    bits += 1;
+                    if bits == 8 {
+                        bytes.push(current);
+                        (current, bits) = (0, 0);
+                    }
+                }
+            }
+            if right < 3 {
+                break;
+            }
+            right -= 2;
+        }
+        bytes
+    }
+}
+
+/// Split interleaved codewords into blocks, correct each and join their data
+fn correct_blocks(codewords: &[u8], version: usize, level: EcLevel) -> Option<Vec<u8>> {
+    let blocks = BLOCKS[level as usize][version] as usize;
+    let ecc = ECC_PER_BLOCK[level as usize][version] as usize;
+    let total = codewords.len();
+    // Later blocks hold one more data codeword when the count doesn't divide
+    let short_blocks = blocks - total % blocks;
+    let short_len = total / blocks;
+    let short_data = short_len.checked_sub(ecc)?;
+
+    let mut split = vec![Vec::with_capacity(short_len + 1); blocks];
+    let mut next = codewords.iter();
+    for i in 0..=short_len {
+        for (j, block) in split.iter_mut().enumerate() {
+            if i == short_data && j < short_blocks {
+                continue;
+            }
+            block.push(*next.next()?);
+        }
+    }
+
+    let mut data = Vec::new();
+    for mut block in split {
+        correct_errors(&mut block, ecc)?;
+        data.extend_from_slice(&block[..block.len() - ecc]);
+    }
+    Some(data)
+}
+
+// ============================================================================
+// Reed-Solomon error correction over GF(256)
+// ============================================================================
+
+const GF_EXP: [u8; 512] = gf_exp();
+const GF_LOG: [u8; 256] = gf_log();
+
+const fn gf_exp() -> [u8; 512] {
+    let mut table = [0u8; 512];
+    let mut x: u16 = 1;
+    let mut i = 0;
+    while i < 512 {
+        table[i] = x as u8;
+        x <<= 1;
+        if x & 0x100 != 0 {
+            x ^= 0x11d;
+        }
+        i += 1;
+    }
+    table
+}
+
+const fn gf_log() -> [u8; 256] {
+    let exp = gf_exp();
+    let mut table = [0u8; 256];
+    let mut i = 0;
+    while i < 255 {
+        table[exp[i] as usize] = i as u8;
+        i += 1;
+    }
+    table
+}
+
+fn gf_mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        return 0;
+    }
+    GF_EXP[GF_LOG[a as usize] as usize + GF_LOG[b as usize] as usize]
+}
+
+fn gf_div(a: u8, b: u8) -> u8 {
+    if a == 0 {
+        return 0;
+    }
+    GF_EXP[GF_LOG[a as usize] as usize + 255 - GF_LOG[b as usize] as usize]
+}
+
+/// Evaluate a polynomial stored lowest power first
+fn poly_eval(poly: &[u8], x: u8) -> u8 {
+    poly.iter().rev().fold(0, |acc, &c| gf_mul(acc, x) ^ c)
+}
+
+/// Correct up to `ecc / 2` wrong bytes in a block whose last `ecc` bytes
+/// are check bytes; `None` when there are more
+fn correct_errors(block: &mut [u8], ecc: usize) -> Option<()> {
+    let n = block.len();
+    // The first byte is the highest power
+    let syndromes: Vec<u8> = (0..ecc)
+        .map(|j| block.iter().fold(0, |acc, &c| gf_mul(acc, GF_EXP[j]) ^ c))
+        .collect();
+    if syndromes.iter().all(|&s| s == 0) {
+        return Some(());
+    }
+
+    // Berlekamp-Massey finds the error locator
+    let mut locator = vec![1u8];
+    let mut previous = vec![1u8];
+    let (mut errors, mut shift, mut last_discrepancy) = (0, 1, 1u8);
+    for step in 0..ecc {
+        let discrepancy = (1..=errors).fold(syndromes[step], |d, i| {
+            d ^ gf_mul(locator.get(i).copied().unwrap_or(0), syndromes[step - i])
+        });
+        if discrepancy == 0 {
+            shift += 1;
+            continue;
+        }
+        let scale = gf_div(discrepancy, last_discrepancy);
+        let mut updated = locator.clone();
+        updated.resize(updated.len().max(previous.len() + shift), 0);
+        for (i, &c) in previous.iter().enumerate() {
+            updated[i + shift] ^= gf_mul(scale, c);
+        }
+        if 2 * errors <= step {
+            errors = step + 1 - errors;
+            previous = std::mem::replace(&mut locator, updated);
+            last_discrepancy = discrepancy;
+            shift = 1;
+        } else {
+            locator = updated;
+            shift += 1;
+        }
+    }
+    if 2 * errors > ecc {
+        return None;
+    }
+
+    // Error evaluator: syndromes times locator, cut to the check length
+    let mut evaluator = vec![0u8; ecc];
+    for (i, &s) in syndromes.iter().enumerate() {
+        for (j, &l) in locator.iter().enumerate().take(ecc - i) {
+            evaluator[i + j] ^= gf_mul(s, l);
+        }
+    }
+    // Formal derivative keeps only the odd powers in GF(2^8)
+    let derivative: Vec<u8> = locator.iter().enumerate().skip(1).map(|(i, &c)| if i % 2 == 1 { c } else { 0 }).collect();
+
+    // Chien search tries every position, Forney gives the error value
+    let mut found = 0;
+    for (pos, byte) in block.iter_mut().enumerate() {
+        let power = (n - 1 - pos) % 255;
+        let x_inv = GF_EXP[255 - power];
+        if poly_eval(&locator, x_inv) != 0 {
+            continue;
+        }
+        let denominator = poly_eval(&derivative, x_inv);
+        if denominator == 0 {
+            return None;
+        }
+        let magnitude = gf_mul(GF_EXP[power], gf_div(poly_eval(&evaluator, x_inv), denominator));
+        *byte ^= magnitude;
+        found += 1;
+    }
+    (found == errors).then_some(())
+}
+
+// ============================================================================
+// Decoding the data
+// ============================================================================
+
+const ALPHANUMERIC: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl BitReader<'_> {
+    fn remaining(&self) -> usize {
+        self.data.len() * 8 - self.pos
+    }
+
+    fn read(&mut self, count: usize) -> Option<u32> {
+        if count > self.remaining() {
+            return None;
+        }
+        let mut value = 0;
+        for _ in 0..count {
+            let bit = (self.data[self.pos / 8] >> (7 - self.pos % 8)) & 1;
+            value = (value << 1) | bit as u32;
+            self.pos += 1;
+        }
+        Some(value)
+    }
+}
+
+/// Bits in a segment's length field, by mode's sizes for versions 1-9,
+/// 10-26 and 27-40
+fn count_bits(sizes: [usize; 3], version: usize) -> usize {
+    match version {
+        1..=9 => sizes[0],
+        10..=26 => sizes[1],
+        _ => sizes[2],
+    }
+}
+
+/// Join the text of every segment up to the terminator
+fn parse_segments(data: &[u8], version: usize) -> Result<String, QrError> {
+    let mut reader = BitReader { data, pos: 0 };
+    let mut bytes = Vec::new();
+    let bad = || QrError::Unreadable;
+
+    while reader.remaining() >= 4 {
+        match reader.read(4).ok_or_else(bad)? {
+            0 => break,
+            // Numeric: three digits per 10 bits
+            1 => {
+                let mut count = reader.read(count_bits([10, 12, 14], version)).ok_or_else(bad)? as usize;
+                while count > 0 {
+                    let digits = count.min(3);
+                    let value = reader.read([0, 4, 7, 10][digits]).ok_or_else(bad)?;
+                    if value >= [1, 10, 100, 1000][digits] {
+                        return Err(bad());
+                    }
+                    bytes.extend(format!("{:0width$}", value, width = digits).bytes());
+                    count -= digits;
+                }
+            }
+            // Alphanumeric: two characters per 11 bits
+            2 => {
+                let mut count = reader.read(count_bits([9, 11, 13], version)).ok_or_else(bad)? as usize;
+                while count >= 2 {
+                    let value = reader.read(11).ok_or_else(bad)? as usize;
+                    bytes.push(*ALPHANUMERIC.get(value / 45).ok_or_else(bad)?);
+                    bytes.push(ALPHANUMERIC[value % 45]);
+                    count -= 2;
+                }
+                if count == 1 {
+                    let value = reader.read(6).ok_or_else(bad)? as usize;
+                    bytes.push(*ALPHANUMERIC.get(value).ok_or_else(bad)?);
+                }
+            }
+            4 => {
+                let count = reader.read(count_bits([8, 16, 16], version)).ok_or_else(bad)?;
+                for _ in 0..count {
+                    bytes.push(reader.read(8).ok_or_else(bad)? as u8);
+                }
+            }
+            // Structured append header, skipped
+            3 => {
+                reader.read(16).ok_or_else(bad)?;
+            }
+            // FNC1 markers carry no text of their own
+            5 => {}
+            9 => {
+                reader.read(8).ok_or_else(bad)?;
+            }
+            // ECI designator; the text is taken as UTF-8 regardless
+            7 => {
+                let first = reader.read(8).ok_or_else(bad)?;
+                let extra = match first {
+                    0x00..=0x7f => 0,
+                    0x80..=0xbf => 8,
+                    _ => 16,
+                };
+                reader.read(extra).ok_or_else(bad)?;
+            }
+            8 => return Err(QrError::Unsupported),
+            _ => return Err(QrError::Unreadable),
+        }
+    }
+
+    // Byte mode is meant to be Latin-1 but nearly every encoder writes UTF-8
+    Ok(String::from_utf8(bytes).unwrap_or_else(|e| e.into_bytes().iter().map(|&b| b as char).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// RS check bytes for `data`, as an encoder computes them
+    fn rs_check_bytes(data: &[u8], ecc: usize) -> Vec<u8> {
+        let mut generator = vec![1u8];
+        for &root in &GF_EXP[..ecc] {
+            let mut next = vec![0u8; generator.len() + 1];
+            for (j, &c) in generator.iter().enumerate() {
+                next[j] ^= c;
+                next[j + 1] ^= gf_mul(c, root);
+            }
+            generator = next;
+        }
+        let mut rem = vec![0u8; ecc];
+        for &byte in data {
+            let factor = byte ^ rem[0];
+            rem.remove(0);
+            rem.push(0);
+            for (r, &g) in rem.iter_mut().zip(&generator[1..]) {
+                *r ^= gf_mul(g, factor);
+            }
+        }
+        rem
+    }
+
+    const URI: &str = "otpauth://totp/Example:alice@example.com?secret=JBSWY3DPEHPK3PXP&issuer=Example";
+
+    /// `URI` as a version 7-Q code with mask 5, from a separate encoder:
+    /// uneven block lengths and version information both come into play
+    const URI_CODE: &[&str] = &[
+        "#######.##..########...##.#.###..#..#.#######",
+        "#.....#.##.##.##...####..###...###.#..#.....#",
+        "#.###.#..#..####.#...####......#.#.#..#.###.#",
+        "#.###.#...###...#####....####...##.##.#.###.#",
+        "#.###.#..#.#..#.....#####..#.###.####.#.###.#",
+        "#.....#..#.#...###..#...##.#..........#.....#",
+        "#######.#.#.#.#.#.#.#.#.#.#.#.#.#.#.#.#######",
+        ".........#..#.....###...######.##.##.........",
+        ".#....#####.#...##.#########...#.#.#.#.....##",
+        "#..#......#..####..#.....##############.###.#",
+        "##.#.####.#.#....#..#..####.#...#..##.##.###.",
+        ".##.#...###.##..##.#..###...####.###..#.####.",
+        "##..#.#..#..#..#..#..#.####.#...###..##.##..#",
+        ".##....###.#.....##..##..#.#.#####.###.#....#",
+        "#.#######.#######...#.###..####..####.##..#..",
+        "#..#...##.####..###....#..##..#..###...#.##.#",
+        "...#..#..##...#..#.###.#..#..#...#.#...#.##..",
+        "...###.#.###..##.##.#.##...#####.#.###.####.#",
+        "##..#.###.##.#.#..#####.#.......##..##.#..#.#",
+        ".#.#.#.#.#...###.####....##..#.##..###..#####",
+        "#...######.###.....######.#.#....#..######.##",
+        "#####...##..###..#..#...##...#.######...##..#",
+        "###.#.#.####.#.##.###.#.#...#.##.####.#.##.#.",
+        "#.#.#...#.#...#....##...##..##..#...#...####.",
+        "#...######.#..#.#########.#.#..##########....",
+        ".#..#..#.#..######...####.##.##.##..##.#.#...",
+        "###...####.#....###.#.#.#.#..#..###..#.###.#.",
+        "######.####...##.#..###..##.#.#.#....#..#####",
+        ".#..###.####..###.#.#..##.#..######.#...#.##.",
+        "..####.##.....###...#..#..#####.##..####..#.#",
+        "..#.#.###.#.#####..###.#..####...###..#.###.#",
+        "...###.#..#...#.#.#...###.#.#.#.#..###.#.####",
+        "#.#..##.###.###..##..#...#...#.#.#..###.#....",
+        "###.#..###.#.#...#####..#..#.##...#..####.#..",
+        "....#.#..##.#.##....#....#..#..#.##.##...#.#.",
+        ".####.....##.#..##..##.#.##.#..#.#.#.###.####",
+        "#..##.#..##.##.##########.#.#.#.#.#######..##",
+        "........#.#.#######.#...###...#.#..##...#.###",
+        "#######.###..##....##.#.###.##...#..#.#.#.##.",
+        "#.....#..######..#..#...#.....#.....#...###.#",
+        "#.###.#.....#.####.######....#.#.#..#####.##.",
+        "#.###.#..##...###.#.#....#...##........##....",
+        "#.###.#....#...#..#..##..#..#.......#.#.###.#",
+        "#.....#.####.#.#....#.##.###..#.######...##..",
+        "#######..####.#.####..###..#.###.#..###....#.",
+    ];
+
+    /// Render a grid of `#`/space rows as an image with a quiet zone
+    fn render(rows: &[&str], scale: usize) -> LumaImage {
+        let size = rows.len();
+        let border = 4 * scale;
+        let width = size * scale + border * 2;
+        let mut pixels = vec![255u8; width * width];
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                if c != '#' {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        pixels[(border + y * scale + dy) * width + border + x * scale + dx] = 0;
+                    }
+                }
+            }
+        }
+        LumaImage::new(width, width, pixels)
+    }
+
+    fn rotate(image: &LumaImage) -> LumaImage {
+        let (w, h) = (image.width, image.height);
+        let mut pixels = vec![0; w * h];
+        for y in 0..h {
+            for x in 0..w {
+                pixels[x * h + (h - 1 - y)] = image.pixels[y * w + x];
+            }
+        }
+        LumaImage::new(h, w, pixels)
+    }
+
+    #[test]
+    fn test_decode_code() {
+        assert_eq!(decode(&render(URI_CODE, 1)).unwrap(), URI);
+        assert_eq!(decode(&render(URI_CODE, 3)).unwrap(), URI);
+    }
+
+    #[test]
+    fn test_decode_rotated_code() {
+        let image = render(URI_CODE, 4);
+        assert_eq!(decode(&rotate(&image)).unwrap(), URI);
+        assert_eq!(decode(&rotate(&rotate(&image))).unwrap(), URI);
+    }
+
+    #[test]
+    fn test_decode_damaged_code() {
+        let mut rows: Vec<String> = URI_CODE.iter().map(|r| r.to_string()).collect();
+        for (x, y) in [(20, 20), (21, 20), (30, 12), (12, 30), (40, 40), (25, 33)] {
+            let flipped = if &rows[y][x..=x] == "#" { "." } else { "#" };
+            rows[y].replace_range(x..=x, flipped);
+        }
+        let rows: Vec<&str> = rows.iter().map(String::as_str).collect();
+        assert_eq!(decode(&render(&rows, 2)).unwrap(), URI);
+    }
+
+    #[test]
+    fn test_read_png() {
+        let image = render(URI_CODE, 2);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("qr.png");
+
+        // Gray on a transparent background reads as dark on white
+        let rgba: Vec<u8> = image.pixels.iter().flat_map(|&p| if p == 0 { [40, 40, 40, 255] } else { [0, 0, 0, 0] }).collect();
+        let mut encoder = png::Encoder::new(File::create(&path).unwrap(), image.width as u32, image.height as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header().unwrap().write_image_data(&rgba).unwrap();
+
+        assert_eq!(read_file(&path).unwrap(), URI);
+    }
+
+    #[test]
+    fn test_read_missing_file() {
+        assert!(matches!(read_file(Path::new("/nonexistent/qr.png")), Err(QrError::Image(_))));
+    }
+
+    #[test]
+    fn test_thonky_check_bytes() {
+        let data = [32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236];
+        assert_eq!(rs_check_bytes(&data, 13), [168, 72, 22, 82, 217, 54, 156, 0, 46, 15, 180, 122, 16]);
+    }
+
+    #[test]
+    fn test_corrects_errors_up_to_half_the_check_bytes() {
+        let data: Vec<u8> = (0..40u8).map(|i| i.wrapping_mul(37)).collect();
+        let mut block = data.clone();
+        block.extend(rs_check_bytes(&data, 16));
+
+        let mut damaged = block.clone();
+        for pos in [0, 5, 17, 23, 39, 41, 50, 55] {
+            damaged[pos] ^= 0x5a;
+        }
+        assert_eq!(correct_errors(&mut damaged, 16), Some(()));
+        assert_eq!(damaged, block);
+
+        let mut too_damaged = block.clone();
+        for pos in 0..9 {
+            too_damaged[pos * 6] ^= 0xff;
+        }
+        assert!(correct_errors(&mut too_damaged, 16).is_none() || too_damaged != block);
+    }
+
+    #[test]
+    fn test_alignment_positions() {
+        assert!(alignment_positions(1).is_empty());
+        assert_eq!(alignment_positions(2), [6, 18]);
+        assert_eq!(alignment_positions(7), [6, 22, 38]);
+        assert_eq!(alignment_positions(32), [6, 34, 60, 86, 112, 138]);
+    }
+
+    #[test]
+    fn test_format_bits() {
+        // Level M, mask 0
+        assert_eq!(format_bits(0), 0b101010000010010);
+        // Level L, mask 4
+        assert_eq!(format_bits(0b01100), 0b110011000101111);
+    }
+
+    #[test]
+    fn test_parse_segments() {
+        // Alphanumeric "AB1" then numeric "42", version 1
+        let mut bits = String::new();
+        bits += "0010000000011";
+        bits += &format!("{:011b}", 10 * 45 + 11);
+        bits += &format!("{:06b}", 1);
+        bits += "00010000000010";
+        bits += &format!("{:07b}", 42);
+        bits += "0000";
+        while !bits.len().is_multiple_of(8) {
+            bits.push('0');
+        }
+        let data: Vec<u8> = bits.as_bytes().chunks(8).map(|c| u8::from_str_radix(std::str::from_utf8(c).unwrap(), 2).unwrap()).collect();
+        assert_eq!(parse_segments(&data, 1).unwrap(), "AB142");
+    }
+
+    #[test]
+    fn test_flat_image_has_no_code() {
+        let image = LumaImage::new(50, 50, vec![200; 2500]);
+        assert_eq!(decode(&image), Err(QrError::NotFound));
+    }
+}
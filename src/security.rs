@@ -0,0 +1,134 @@
+//! Process hardening run first thing at startup
+//!
+//! Core dumps are turned off and the process marked non-dumpable, so a crash
+//! or a debugger running as the same user cannot copy decrypted secrets out
+//! of memory, and all memory is locked so none of it is swapped to disk.
+//! Each step may fail on its own (locking memory usually needs a raised
+//! `RLIMIT_MEMLOCK`); the report says which did, for the status line and
+//! `:hardening`.
+
+/// One hardening step and how it went
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub name: &'static str,
+    pub result: Result<(), String>,
+}
+
+/// What `harden_process` managed to do
+#[derive(Debug, Clone, Default)]
+pub struct Hardening {
+    pub steps: Vec<Step>,
+}
+
+impl Hardening {
+    /// Whether every step succeeded
+    pub fn is_complete(&self) -> bool {
+        self.steps.iter().all(|step| step.result.is_ok())
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &Step> {
+        self.steps.iter().filter(|step| step.result.is_err())
+    }
+
+    /// Every step on one line, e.g. "core dumps off; memory lock failed: ..."
+    pub fn summary(&self) -> String {
+        let parts: Vec<String> = self
+            .steps
+            .iter()
+            .map(|step| match &step.result {
+                Ok(()) => format!("{} ok", step.name),
+                Err(e) => format!("{} failed: {}", step.name, e),
+            })
+            .collect();
+        parts.join("; ")
+    }
+}
+
+/// Disable core dumps, make the process non-dumpable and lock its memory,
+/// reporting each step
+pub fn harden_process() -> Hardening {
+    Hardening {
+        steps: vec![
+            Step { name: "core dumps off", result: disable_core_dumps() },
+            Step { name: "non-dumpable", result: set_non_dumpable() },
+            Step { name: "memory lock", result: lock_memory() },
+        ],
+    }
+}
+
+#[cfg(unix)]
+fn disable_core_dumps() -> Result<(), String> {
+    let limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    // SAFETY: limit is a valid rlimit for the duration of the call
+    match unsafe { libc::setrlimit(libc::RLIMIT_CORE, &limit) } {
+        0 => Ok(()),
+        _ => Err(std::io::Error::last_os_error().to_string()),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn set_non_dumpable() -> Result<(), String> {
+    // SAFETY: PR_SET_DUMPABLE takes a plain integer argument
+    match unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 0) } {
+        0 => Ok(()),
+        _ => Err(std::io::Error::last_os_error().to_string()),
+    }
+}
+
+/// Lock current and future pages; only tried when RLIMIT_MEMLOCK is
+/// unlimited, as under a finite limit every allocation past it would fail
+/// (Argon2 alone takes tens of megabytes). Key buffers are still locked one
+/// by one when this is skipped.
+#[cfg(unix)]
+fn lock_memory() -> Result<(), String> {
+    let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    // SAFETY: limit is a valid rlimit to write into
+    if unsafe { libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut limit) } != 0 {
+        return Err(std::io::Error::last_os_error().to_string());
+    }
+    if limit.rlim_cur != libc::RLIM_INFINITY {
+        return Err(format!(
+            "RLIMIT_MEMLOCK is {} KiB; raise it to unlimited (ulimit -l unlimited) to lock all memory",
+            limit.rlim_cur / 1024
+        ));
+    }
+    // SAFETY: mlockall takes only flags
+    match unsafe { libc::mlockall(libc::MCL_CURRENT | libc::MCL_FUTURE) } {
+        0 => Ok(()),
+        _ => Err(std::io::Error::last_os_error().to_string()),
+    }
+}
+
+#[cfg(not(unix))]
+fn disable_core_dumps() -> Result<(), String> {
+    Err("not supported on this platform".into())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_non_dumpable() -> Result<(), String> {
+    Err("not supported on this platform".into())
+}
+
+#[cfg(not(unix))]
+fn lock_memory() -> Result<(), String> {
+    Err("not supported on this platform".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let hardening = Hardening {
+            steps: vec![
+                Step { name: "core dumps off", result: Ok(()) },
+                Step { name: "memory lock", result: Err("no".into()) },
+            ],
+        };
+        assert!(!hardening.is_complete());
+        assert_eq!(hardening.failed().count(), 1);
+        assert_eq!(hardening.summary(), "core dumps off ok; memory lock failed: no");
+        assert!(Hardening::default().is_complete());
+    }
+}
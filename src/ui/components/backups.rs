@@ -0,0 +1,118 @@
+//! Backup picker popup and state for `:restore`
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style},
+    widgets::{Clear, Widget},
+};
+
+use crate::vault::backup::BackupFile;
+
+use super::layout::{
+    centered_rect_fixed, create_popup_block, highlight_row, render_empty_message,
+    truncate_with_ellipsis,
+};
+use super::scroll::render_v_scroll_indicator;
+
+#[derive(Default)]
+pub struct BackupsState {
+    /// Newest first
+    pub backups: Vec<BackupFile>,
+    pub selected: usize,
+}
+
+impl BackupsState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_backups(&mut self, backups: Vec<BackupFile>) {
+        self.backups = backups;
+        self.selected = 0;
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn scroll_down(&mut self) {
+        if self.selected + 1 < self.backups.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn home(&mut self) {
+        self.selected = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.selected = self.backups.len().saturating_sub(1);
+    }
+
+    pub fn selected_backup(&self) -> Option<&BackupFile> {
+        self.backups.get(self.selected)
+    }
+}
+
+pub struct BackupsPopup<'a> {
+    state: &'a BackupsState,
+}
+
+impl<'a> BackupsPopup<'a> {
+    pub fn new(state: &'a BackupsState) -> Self {
+        Self { state }
+    }
+}
+
+impl Widget for BackupsPopup<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let backups = &self.state.backups;
+        let available = area.height.saturating_sub(2);
+        let height = (backups.len() as u16).saturating_add(2).min((available * 75) / 100).max(6);
+        let popup = centered_rect_fixed(70, height, area, true);
+        Clear.render(popup, buf);
+
+        let title = format!(" Restore backup ({}) ", backups.len());
+        let block = create_popup_block(&title, Color::Red);
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        if backups.is_empty() {
+            render_empty_message(inner, buf, "No backups found");
+            return;
+        }
+
+        let visible = inner.height as usize;
+        let selected = self.state.selected;
+        let scroll_offset = if selected >= visible { selected - visible + 1 } else { 0 };
+
+        for (i, backup) in backups.iter().enumerate().skip(scroll_offset).take(visible) {
+            let y = inner.y + (i - scroll_offset) as u16;
+            render_backup_row(inner, buf, y, backup, i == selected);
+        }
+
+        let max_v = backups.len().saturating_sub(visible);
+        if max_v > 0 {
+            render_v_scroll_indicator(buf, &popup, scroll_offset, max_v, Color::Red);
+        }
+    }
+}
+
+fn render_backup_row(inner: Rect, buf: &mut Buffer, y: u16, backup: &BackupFile, is_cursor: bool) {
+    if is_cursor {
+        highlight_row(buf, inner.x, y, inner.width);
+    }
+    let bg = if is_cursor { Color::DarkGray } else { Color::Reset };
+
+    let date = backup.created_at.format("%Y-%m-%d %H:%M:%S").to_string();
+    buf.set_string(inner.x + 1, y, &date, Style::default().fg(Color::White).bg(bg));
+
+    let (label, color) = match &backup.label {
+        Some(label) => (label.as_str(), Color::Yellow),
+        None => ("automatic", Color::DarkGray),
+    };
+    let max_width = (inner.width as usize).saturating_sub(date.len() + 3);
+    let display = truncate_with_ellipsis(label, max_width);
+    buf.set_string(inner.x + date.len() as u16 + 3, y, &display, Style::default().fg(color).bg(bg));
+}
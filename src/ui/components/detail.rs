@@ -1,52 +1,313 @@
 //! Detail View Component
 //!
-//! Displays credential details in a panel.
+//! Displays credential details in a panel. Long values wrap under their
+//! label, notes are shown in full, and content taller than the panel
+//! scrolls.
 
 use ratatui::{
     buffer::Buffer,
     layout::Rect,
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, BorderType, Paragraph, Widget, Wrap},
+    widgets::{Block, Borders, BorderType, Widget},
 };
 
+use chrono::{DateTime, Utc};
+use secrecy::{ExposeSecret, SecretString};
+
 use crate::db::models::CredentialType;
+use crate::vault::certificate::CertificateInfo;
+use crate::vault::expiry::ExpiryStatus;
+
+use super::scroll::render_v_scroll_indicator;
+use super::tags::TagColors;
+
+/// Column values start at, after their label
+const VALUE_X: usize = 13;
 
 #[derive(Debug, Clone)]
 pub struct CredentialDetail {
     pub name: String,
     pub credential_type: CredentialType,
     pub username: Option<String>,
-    pub secret: Option<String>,
+    pub secret: Option<SecretString>,
     pub secret_visible: bool,
     pub url: Option<String>,
-    pub notes: Option<String>,
+    pub notes: Option<SecretString>,
     pub tags: Vec<String>,
     pub created_at: String,
     pub updated_at: String,
     pub totp_code: Option<String>,
     pub totp_remaining: Option<u64>,
+    /// The following code, once the current one is about to expire
+    pub totp_next: Option<String>,
+    pub fields: Vec<DetailField>,
+    pub ssh_public_key: Option<String>,
+    pub ssh_fingerprint: Option<String>,
+    pub certificate: Option<CertificateInfo>,
+    /// Certificate or user-set expiry, with how close it is
+    pub expiry: Option<(DateTime<Utc>, ExpiryStatus)>,
+    /// Rotation interval in days and when the secret is next due
+    pub rotation: Option<(u32, DateTime<Utc>, ExpiryStatus)>,
+}
+
+/// A type-specific field such as a database host
+#[derive(Debug, Clone)]
+pub struct DetailField {
+    pub label: String,
+    pub value: SecretString,
+    pub secret: bool,
 }
 
 pub struct DetailView<'a> {
     detail: &'a CredentialDetail,
+    monochrome: bool,
+    tag_colors: Option<&'a TagColors>,
+    scroll: usize,
 }
 
 impl<'a> DetailView<'a> {
     pub fn new(detail: &'a CredentialDetail) -> Self {
-        Self { detail }
+        Self { detail, monochrome: false, tag_colors: None, scroll: 0 }
+    }
+
+    pub fn monochrome(mut self, monochrome: bool) -> Self {
+        self.monochrome = monochrome;
+        self
+    }
+
+    pub fn tag_colors(mut self, colors: &'a TagColors) -> Self {
+        self.tag_colors = Some(colors);
+        self
+    }
+
+    /// Lines scrolled past at the top
+    pub fn scroll(mut self, offset: usize) -> Self {
+        self.scroll = offset;
+        self
+    }
+
+    /// How far the content can scroll in a panel of `area`
+    pub fn max_scroll(&self, area: Rect) -> usize {
+        let inner = Block::default().borders(Borders::ALL).inner(area);
+        self.layout(inner).0.len().saturating_sub(inner.height as usize)
+    }
+
+    /// The lines to show in `inner`; the timestamps stay pinned at the bottom
+    /// while everything fits, and otherwise follow the body to scroll with it
+    fn layout(&self, inner: Rect) -> (Vec<Line<'a>>, Option<Vec<Line<'a>>>) {
+        let (mut body, footer) = self.lines(inner.width as usize);
+        if body.len() + footer.len() <= inner.height as usize {
+            return (body, Some(footer));
+        }
+        body.push(Line::default());
+        body.extend(footer);
+        (body, None)
+    }
+
+    /// The fields and notes, and the timestamps kept at the bottom
+    fn lines(&self, width: usize) -> (Vec<Line<'a>>, Vec<Line<'a>>) {
+        let detail = self.detail;
+        let mut lines = Vec::new();
+        let mut out = Lines { lines: &mut lines, width };
+
+        out.type_field(detail);
+        if let Some(ref username) = detail.username {
+            out.field("Username", vec![Span::styled(username.as_str(), Style::default().fg(Color::White))]);
+        }
+        if let Some(ref secret) = detail.secret {
+            out.secret_and_strength(secret.expose_secret(), detail, self.monochrome);
+        }
+        if let Some(ref public_key) = detail.ssh_public_key {
+            out.ssh_fields(public_key, detail.ssh_fingerprint.as_deref());
+        }
+        if let Some(ref cert) = detail.certificate {
+            out.certificate_fields(cert);
+        }
+        if let Some((expires, status)) = detail.expiry {
+            out.expiry_field(expires, status, self.monochrome);
+        }
+        if let Some(rotation) = detail.rotation {
+            out.rotation_field(rotation, self.monochrome);
+        }
+        for field in &detail.fields {
+            out.extra_field(field, detail.secret_visible);
+        }
+        if let (Some(code), Some(remaining)) = (&detail.totp_code, detail.totp_remaining) {
+            out.totp_field(code, remaining, detail.totp_next.as_deref());
+        }
+        if let Some(ref url) = detail.url {
+            out.field("URL", vec![Span::styled(url.as_str(), Style::default().fg(Color::Blue))]);
+        }
+        if !detail.tags.is_empty() {
+            out.tags_field(&detail.tags, self.tag_colors);
+        }
+        out.lines.push(Line::default());
+        if let Some(ref notes) = detail.notes {
+            out.notes_section(notes.expose_secret());
+        }
+
+        (lines, timestamps(&detail.created_at, &detail.updated_at))
     }
 }
 
-fn render_field(buf: &mut Buffer, x: u16, y: &mut u16, _width: u16, label: &str, value: &[Span]) {
-    let label_style = Style::default().fg(Color::White);
-    buf.set_string(x, *y, format!("{}:", label), label_style);
+/// Where the lines of a detail panel collect, wrapped to `width`
+struct Lines<'l, 'a> {
+    lines: &'l mut Vec<Line<'a>>,
+    width: usize,
+}
+
+impl<'a> Lines<'_, 'a> {
+    /// `label:` and its value, continued under the value column when too long
+    fn field(&mut self, label: &str, value: Vec<Span<'a>>) {
+        let label: String = format!("{}:", label).chars().take(VALUE_X - 1).collect();
+        let rows = wrap_spans(value, self.width.saturating_sub(VALUE_X).max(1));
+        for (i, row) in rows.into_iter().enumerate() {
+            let lead = match i {
+                0 => Span::styled(format!("{:<width$}", label, width = VALUE_X), Style::default().fg(Color::White)),
+                _ => Span::raw(" ".repeat(VALUE_X)),
+            };
+            self.lines.push(Line::from([vec![lead], row].concat()));
+        }
+    }
+
+    fn type_field(&mut self, detail: &CredentialDetail) {
+        let color = type_color(detail.credential_type);
+        self.field("Type", vec![
+            Span::styled(detail.credential_type.icon(), Style::default().fg(color)),
+            Span::raw(" "),
+            Span::styled(detail.credential_type.display_name(), Style::default().fg(Color::White)),
+        ]);
+    }
+
+    fn secret_field(&mut self, secret: &'a str, visible: bool) {
+        // Borrowed when shown, so no copy of the secret outlives the frame
+        let display_secret = if visible {
+            Span::styled(secret, Style::default().fg(Color::Yellow))
+        } else {
+            Span::styled("•".repeat(secret.len().min(20)), Style::default().fg(Color::Yellow))
+        };
+        self.field("Secret", vec![display_secret]);
+    }
 
-    let value_x = x + 12;
-    let line = Line::from(value.to_vec());
-    buf.set_line(value_x, *y, &line, 60);
+    fn strength_field(&mut self, secret: &str, monochrome: bool) {
+        let strength = crate::crypto::password_strength(secret);
+        let label = crate::crypto::strength_label(strength);
+        let text = format!("{} ({}%)", label, strength);
+        if !monochrome {
+            let color = strength_color(strength);
+            self.field("Strength", vec![Span::styled(text, Style::default().fg(color))]);
+            return;
+        }
+        let style = if strength <= 40 { Style::default().add_modifier(Modifier::BOLD) } else { Style::default() };
+        self.field("Strength", vec![
+            Span::raw(strength_meter(strength)),
+            Span::raw(" "),
+            Span::styled(text, style),
+        ]);
+    }
 
-    *y += 1;
+    fn secret_and_strength(&mut self, secret: &'a str, detail: &CredentialDetail, monochrome: bool) {
+        if secret.is_empty() {
+            return;
+        }
+        self.secret_field(secret, detail.secret_visible);
+        if detail.credential_type == CredentialType::Password {
+            self.strength_field(secret, monochrome);
+        }
+    }
+
+    fn extra_field(&mut self, field: &DetailField, secret_visible: bool) {
+        let value = field.value.expose_secret();
+        let (text, style) = if field.secret && !secret_visible {
+            ("•".repeat(value.len().min(20)), Style::default().fg(Color::Yellow))
+        } else {
+            // Multi-line values (certificates, chains) show their first line
+            let mut lines = value.lines();
+            let first = lines.next().unwrap_or_default();
+            let text = if lines.next().is_some() { format!("{} …", first) } else { first.to_string() };
+            let color = if field.secret { Color::Yellow } else { Color::White };
+            (text, Style::default().fg(color))
+        };
+        self.field(&field.label, vec![Span::styled(text, style)]);
+    }
+
+    fn ssh_fields(&mut self, public_key: &str, fingerprint: Option<&str>) {
+        self.field("Public Key", vec![Span::styled(public_key.to_string(), Style::default().fg(Color::Cyan))]);
+        if let Some(fingerprint) = fingerprint {
+            self.field("Fingerprint", vec![Span::styled(fingerprint.to_string(), Style::default().fg(Color::White))]);
+        }
+    }
+
+    fn certificate_fields(&mut self, cert: &CertificateInfo) {
+        let value_style = Style::default().fg(Color::White);
+        self.field("Subject", vec![Span::styled(cert.subject.clone(), value_style)]);
+        self.field("Issuer", vec![Span::styled(cert.issuer.clone(), value_style)]);
+        if !cert.sans.is_empty() {
+            self.field("SANs", vec![Span::styled(cert.sans.join(", "), value_style)]);
+        }
+    }
+
+    fn expiry_field(&mut self, expires: DateTime<Utc>, status: ExpiryStatus, monochrome: bool) {
+        let days = (expires - Utc::now()).num_days();
+        let note = match status {
+            ExpiryStatus::Expired => format!("expired {} days ago", -days),
+            ExpiryStatus::ExpiringSoon => format!("expires in {} days", days),
+            ExpiryStatus::Valid => format!("in {} days", days),
+        };
+        let date = expires.format("%d-%b-%Y").to_string();
+        self.field("Expires", vec![
+            Span::styled(date, Style::default().fg(Color::White)),
+            Span::raw(" "),
+            Span::styled(format!("({})", note), status_style(status, monochrome)),
+        ]);
+    }
+
+    fn rotation_field(&mut self, rotation: (u32, DateTime<Utc>, ExpiryStatus), monochrome: bool) {
+        let (every, due, status) = rotation;
+        let days = (due - Utc::now()).num_days();
+        let note = match status {
+            ExpiryStatus::Expired => format!("overdue by {} days", -days),
+            _ => format!("due in {} days", days),
+        };
+        self.field("Rotate", vec![
+            Span::styled(format!("every {} days", every), Style::default().fg(Color::White)),
+            Span::raw(" "),
+            Span::styled(format!("({})", note), status_style(status, monochrome)),
+        ]);
+    }
+
+    fn totp_field(&mut self, code: &str, remaining: u64, next: Option<&str>) {
+        let mut spans = vec![
+            Span::styled(code.to_string(), Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+            Span::styled(format!(" ({}s)", remaining), Style::default().fg(Color::DarkGray)),
+        ];
+        if let Some(next) = next {
+            spans.push(Span::styled("  next ", Style::default().fg(Color::DarkGray)));
+            spans.push(Span::styled(next.to_string(), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
+        }
+        self.field("TOTP", spans);
+    }
+
+    fn tags_field(&mut self, tags: &[String], colors: Option<&TagColors>) {
+        let tag_spans: Vec<Span> = tags
+            .iter()
+            .flat_map(|tag| vec![
+                Span::styled(
+                    format!("#{}", tag),
+                    Style::default().fg(colors.and_then(|c| c.color(tag)).unwrap_or(Color::Magenta)),
+                ),
+                Span::raw(" "),
+            ])
+            .collect();
+        self.field("Tags", tag_spans);
+    }
+
+    fn notes_section(&mut self, notes: &str) {
+        self.lines.push(Line::styled("Notes:", Style::default().fg(Color::Yellow)));
+        let style = Style::default().fg(Color::Gray);
+        self.lines.extend(wrap_text(notes, self.width).into_iter().map(|row| Line::styled(row, style)));
+    }
 }
 
 fn type_color(cred_type: CredentialType) -> Color {
@@ -61,7 +322,7 @@ fn type_color(cred_type: CredentialType) -> Color {
     }
 }
 
-fn strength_color(strength: u32) -> Color {
+pub(super) fn strength_color(strength: u32) -> Color {
     match strength {
         0..=20 => Color::Red,
         21..=40 => Color::LightRed,
@@ -71,97 +332,81 @@ fn strength_color(strength: u32) -> Color {
     }
 }
 
-fn render_type_field(buf: &mut Buffer, x: u16, y: &mut u16, width: u16, detail: &CredentialDetail) {
-    let color = type_color(detail.credential_type);
-    let value_style = Style::default().fg(Color::White);
-    render_field(buf, x, y, width, "Type", &[
-        Span::styled(detail.credential_type.icon(), Style::default().fg(color)),
-        Span::raw(" "),
-        Span::styled(detail.credential_type.display_name(), value_style),
-    ]);
-}
-
-fn render_username_field(buf: &mut Buffer, x: u16, y: &mut u16, width: u16, username: &str) {
-    let value_style = Style::default().fg(Color::White);
-    render_field(buf, x, y, width, "Username", &[Span::styled(username, value_style)]);
+/// Ten-step text meter standing in for the strength color
+pub(super) fn strength_meter(strength: u32) -> String {
+    let filled = (strength.min(100) as usize).div_ceil(10);
+    format!("[{}{}]", "#".repeat(filled), "-".repeat(10 - filled))
 }
 
-fn render_secret_field(buf: &mut Buffer, x: u16, y: &mut u16, width: u16, secret: &str, visible: bool) {
-    let secret_style = Style::default().fg(Color::Yellow);
-    let display_secret = if visible {
-        secret.to_string()
-    } else {
-        "•".repeat(secret.len().min(20))
-    };
-    render_field(buf, x, y, width, "Secret", &[Span::styled(display_secret, secret_style)]);
-}
-
-fn render_strength_field(buf: &mut Buffer, x: u16, y: &mut u16, width: u16, secret: &str) {
-    let strength = crate::crypto::password_strength(secret);
-    let label = crate::crypto::strength_label(strength);
-    let color = strength_color(strength);
-    render_field(buf, x, y, width, "Strength", &[
-        Span::styled(format!("{} ({}%)", label, strength), Style::default().fg(color)),
-    ]);
-}
-
-fn render_secret_and_strength(buf: &mut Buffer, x: u16, y: &mut u16, width: u16, secret: &str, detail: &CredentialDetail) {
-    if secret.is_empty() {
-        return;
-    }
-    render_secret_field(buf, x, y, width, secret, detail.secret_visible);
-    if detail.credential_type == CredentialType::Password {
-        render_strength_field(buf, x, y, width, secret);
+fn status_style(status: ExpiryStatus, monochrome: bool) -> Style {
+    match (monochrome, status) {
+        (false, ExpiryStatus::Expired) => Style::default().fg(Color::Red),
+        (false, ExpiryStatus::ExpiringSoon) => Style::default().fg(Color::Yellow),
+        (false, ExpiryStatus::Valid) => Style::default().fg(Color::Green),
+        (true, ExpiryStatus::Valid) => Style::default(),
+        (true, _) => Style::default().add_modifier(Modifier::BOLD),
     }
 }
 
-fn render_totp_field(buf: &mut Buffer, x: u16, y: &mut u16, width: u16, code: &str, remaining: u64) {
-    render_field(buf, x, y, width, "TOTP", &[
-        Span::styled(code, Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
-        Span::styled(format!(" ({}s)", remaining), Style::default().fg(Color::DarkGray)),
-    ]);
-}
-
-fn render_url_field(buf: &mut Buffer, x: u16, y: &mut u16, width: u16, url: &str) {
-    render_field(buf, x, y, width, "URL", &[
-        Span::styled(url, Style::default().fg(Color::Blue)),
-    ]);
-}
-
-fn render_tags_field(buf: &mut Buffer, x: u16, y: &mut u16, width: u16, tags: &[String]) {
-    let tag_spans: Vec<Span> = tags
-        .iter()
-        .flat_map(|tag| vec![
-            Span::styled(format!("#{}", tag), Style::default().fg(Color::Magenta)),
-            Span::raw(" "),
-        ])
-        .collect();
-    render_field(buf, x, y, width, "Tags", &tag_spans);
+fn timestamps<'a>(created: &str, updated: &str) -> Vec<Line<'a>> {
+    let label_style = Style::default().fg(Color::Green);
+    let value_style = Style::default().fg(Color::White);
+    vec![
+        Line::from(vec![Span::styled("Created: ", label_style), Span::styled(created.to_string(), value_style)]),
+        Line::from(vec![Span::styled("Updated: ", label_style), Span::styled(updated.to_string(), value_style)]),
+    ]
 }
 
-fn render_notes_section(buf: &mut Buffer, inner: &Rect, y: &mut u16, notes: &str) {
-    let label_style = Style::default().fg(Color::Yellow);
-    buf.set_string(inner.x, *y, "Notes:", label_style);
-    *y += 1;
-
-    let note_area = Rect::new(inner.x, *y, inner.width, inner.height.saturating_sub(*y - inner.y));
-    let note_widget = Paragraph::new(notes)
-        .style(Style::default().fg(Color::Gray))
-        .wrap(Wrap { trim: true });
-    note_widget.render(note_area, buf);
+/// `spans` cut into rows of at most `width` characters, keeping their styles
+fn wrap_spans(spans: Vec<Span<'_>>, width: usize) -> Vec<Vec<Span<'_>>> {
+    let mut rows = vec![Vec::new()];
+    let mut used = 0;
+    for span in spans {
+        let mut rest: &str = &span.content;
+        while !rest.is_empty() {
+            if used == width {
+                rows.push(Vec::new());
+                used = 0;
+            }
+            let take = rest.char_indices().nth(width - used).map_or(rest.len(), |(i, _)| i);
+            let (head, tail) = rest.split_at(take);
+            used += head.chars().count();
+            rows.last_mut().unwrap().push(Span::styled(head.to_string(), span.style));
+            rest = tail;
+        }
+    }
+    rows
 }
 
-fn render_timestamps(buf: &mut Buffer, inner: &Rect, y: u16, created: &str, updated: &str) {
-    let footer_y = inner.y + inner.height.saturating_sub(2);
-    if footer_y <= y {
-        return;
+/// `text` wrapped at spaces to `width` characters, words longer than a line
+/// split, blank lines kept
+fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut rows = Vec::new();
+    for line in text.lines() {
+        let mut row = String::new();
+        for word in line.split_whitespace() {
+            let mut word = word.to_string();
+            let fits = row.is_empty() || row.chars().count() + 1 + word.chars().count() <= width;
+            if !fits {
+                rows.push(std::mem::take(&mut row));
+            }
+            while word.chars().count() > width {
+                if !row.is_empty() {
+                    rows.push(std::mem::take(&mut row));
+                }
+                let split = word.char_indices().nth(width).map_or(word.len(), |(i, _)| i);
+                rows.push(word[..split].to_string());
+                word = word[split..].to_string();
+            }
+            if !row.is_empty() {
+                row.push(' ');
+            }
+            row.push_str(&word);
+        }
+        rows.push(row);
     }
-    let label_style = Style::default().fg(Color::Green);
-    let value_style = Style::default().fg(Color::White);
-    buf.set_string(inner.x, footer_y, "Created: ", label_style);
-    buf.set_string(inner.x + 9, footer_y, created, value_style);
-    buf.set_string(inner.x, footer_y + 1, "Updated: ", label_style);
-    buf.set_string(inner.x + 9, footer_y + 1, updated, value_style);
+    rows
 }
 
 fn render_detail_block(area: Rect, buf: &mut Buffer, name: &str) -> Rect {
@@ -179,36 +424,78 @@ fn render_detail_block(area: Rect, buf: &mut Buffer, name: &str) -> Rect {
 impl<'a> Widget for DetailView<'a> {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let inner = render_detail_block(area, buf, &self.detail.name);
-        let mut y = inner.y;
-
-        render_type_field(buf, inner.x, &mut y, inner.width, self.detail);
-
-        if let Some(ref username) = self.detail.username {
-            render_username_field(buf, inner.x, &mut y, inner.width, username);
-        }
-
-        if let Some(ref secret) = self.detail.secret {
-            render_secret_and_strength(buf, inner.x, &mut y, inner.width, secret, self.detail);
+        let (content, footer) = self.layout(inner);
+        let height = inner.height as usize;
+        let draw = |buf: &mut Buffer, row: usize, line: &Line| {
+            buf.set_line(inner.x, inner.y + row as u16, line, inner.width);
+        };
+
+        let max = content.len().saturating_sub(height);
+        let offset = self.scroll.min(max);
+        for (row, line) in content.iter().skip(offset).take(height).enumerate() {
+            draw(buf, row, line);
         }
-
-        if let (Some(code), Some(remaining)) = (&self.detail.totp_code, self.detail.totp_remaining) {
-            render_totp_field(buf, inner.x, &mut y, inner.width, code, remaining);
-        }
-
-        if let Some(ref url) = self.detail.url {
-            render_url_field(buf, inner.x, &mut y, inner.width, url);
+        let footer = footer.unwrap_or_default();
+        for (i, line) in footer.iter().enumerate() {
+            draw(buf, height - footer.len() + i, line);
         }
+        render_v_scroll_indicator(buf, &area, offset, max, Color::Magenta);
+    }
+}
 
-        if !self.detail.tags.is_empty() {
-            render_tags_field(buf, inner.x, &mut y, inner.width, &self.detail.tags);
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        y += 1;
+    #[test]
+    fn test_wrap_spans() {
+        let rows = wrap_spans(vec![Span::raw("https://"), Span::styled("example.com/a", Style::default().fg(Color::Blue))], 8);
+        let texts: Vec<String> = rows.iter().map(|row| row.iter().map(|s| s.content.as_ref()).collect()).collect();
+        assert_eq!(texts, ["https://", "example.", "com/a"]);
+        assert_eq!(rows[1][0].style.fg, Some(Color::Blue));
+    }
 
-        if let Some(ref notes) = self.detail.notes {
-            render_notes_section(buf, &inner, &mut y, notes);
-        }
+    #[test]
+    fn test_scroll() {
+        let mut detail = CredentialDetail {
+            name: "Bank".into(),
+            credential_type: CredentialType::Note,
+            username: Some("alice".into()),
+            secret: None,
+            secret_visible: false,
+            url: None,
+            notes: None,
+            tags: vec![],
+            created_at: "2026-01-01".into(),
+            updated_at: "2026-01-02".into(),
+            totp_code: None,
+            totp_remaining: None,
+            totp_next: None,
+            fields: vec![],
+            ssh_public_key: None,
+            ssh_fingerprint: None,
+            certificate: None,
+            expiry: None,
+            rotation: None,
+        };
+        let area = Rect::new(0, 0, 30, 10);
+        assert_eq!(DetailView::new(&detail).max_scroll(area), 0);
+
+        // Type, username, blank, "Notes:", 12 note lines, blank, 2 timestamps
+        detail.notes = Some((1..=12).map(|i| format!("line {}", i)).collect::<Vec<_>>().join("\n").into());
+        let view = DetailView::new(&detail);
+        assert_eq!(view.max_scroll(area), 19 - 8);
+
+        let mut buf = Buffer::empty(area);
+        view.scroll(99).render(area, &mut buf);
+        let row = |y: u16| (0..area.width).map(|x| buf[(x, y)].symbol()).collect::<String>();
+        assert!(row(8).contains("Updated: 2026-01-02"));
+        assert!(row(1).contains("line 8"));
+    }
 
-        render_timestamps(buf, &inner, y, &self.detail.created_at, &self.detail.updated_at);
+    #[test]
+    fn test_wrap_text() {
+        assert_eq!(wrap_text("one two three\n\nfour", 8), ["one two", "three", "", "four"]);
+        assert_eq!(wrap_text("ab abcdefghij", 4), ["ab", "abcd", "efgh", "ij"]);
     }
 }
@@ -1,4 +1,6 @@
-//! Dialog popups (confirm, message, password)
+//! Dialog popups (confirm, message, password, progress)
+
+use std::time::Duration;
 
 use ratatui::{
     buffer::Buffer,
@@ -8,6 +10,9 @@ use ratatui::{
     widgets::{Block, BorderType, Borders, Clear, Paragraph, Widget, Wrap},
 };
 
+use crate::input::{cursor_window, mask};
+
+use super::detail::{strength_color, strength_meter};
 use super::layout::{centered_rect_fixed, create_popup_block};
 
 pub struct ConfirmDialog<'a> {
@@ -92,29 +97,101 @@ impl Widget for MessagePopup<'_> {
     }
 }
 
+/// Frames of the spinner shown while something runs in the background
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// The spinner frame for `elapsed`, turning ten times a second
+pub fn spinner(elapsed: Duration) -> char {
+    SPINNER[(elapsed.as_millis() / 100) as usize % SPINNER.len()]
+}
+
+/// Shown over the screen while a background job has the vault
+#[derive(Debug, Clone, Copy)]
+pub struct ProgressPopup<'a> {
+    title: &'a str,
+    elapsed: Duration,
+    progress: Option<(usize, usize)>,
+}
+
+impl<'a> ProgressPopup<'a> {
+    pub fn new(title: &'a str, elapsed: Duration) -> Self {
+        Self { title, elapsed, progress: None }
+    }
+
+    /// Done and total, for a bar in place of the bare spinner
+    pub fn progress(mut self, progress: Option<(usize, usize)>) -> Self {
+        self.progress = progress;
+        self
+    }
+}
+
+impl Widget for ProgressPopup<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let popup_area = centered_rect_fixed(44, 5, area, true);
+        Clear.render(popup_area, buf);
+
+        let block = create_popup_block(self.title, Color::Magenta);
+        let inner = block.inner(popup_area);
+        block.render(popup_area, buf);
+
+        let text = match self.progress {
+            Some((done, total)) => format!("{} {}/{}", spinner(self.elapsed), done, total),
+            None => format!("{} Working... {}s", spinner(self.elapsed), self.elapsed.as_secs()),
+        };
+        buf.set_stringn(inner.x + 1, inner.y + 1, text, inner.width.saturating_sub(2) as usize, Style::default().fg(Color::White));
+
+        let Some((done, total)) = self.progress.filter(|(_, total)| *total > 0) else { return };
+        let width = inner.width.saturating_sub(2) as usize;
+        let filled = (width * done.min(total) / total).min(width);
+        let bar = format!("{}{}", "█".repeat(filled), "░".repeat(width - filled));
+        buf.set_string(inner.x + 1, inner.y + 2, bar, Style::default().fg(Color::Magenta));
+    }
+}
+
 pub struct PasswordDialog<'a> {
     title: &'a str,
     prompt: &'a str,
     value: &'a str,
     cursor: usize,
     error: Option<&'a str>,
+    status: Option<&'a str>,
+    strength: Option<u32>,
+    monochrome: bool,
 }
 
 impl<'a> PasswordDialog<'a> {
     pub fn new(title: &'a str, prompt: &'a str, value: &'a str, cursor: usize) -> Self {
-        Self { title, prompt, value, cursor, error: None }
+        Self { title, prompt, value, cursor, error: None, status: None, strength: None, monochrome: false }
     }
 
     pub fn error(mut self, err: &'a str) -> Self {
         self.error = Some(err);
         self
     }
+
+    /// What is happening right now, e.g. while a slow key derivation runs;
+    /// an error takes its place
+    pub fn status(mut self, status: &'a str) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    /// Show a strength line for a new password
+    pub fn strength(mut self, score: u32) -> Self {
+        self.strength = Some(score);
+        self
+    }
+
+    pub fn monochrome(mut self, monochrome: bool) -> Self {
+        self.monochrome = monochrome;
+        self
+    }
 }
 
 impl Widget for PasswordDialog<'_> {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let dialog_width = 40;
-        let height = 6;
+        let height = if self.strength.is_some() { 7 } else { 6 };
         let popup_area = centered_rect_fixed(dialog_width, height, area, false);
         Clear.render(popup_area, buf);
 
@@ -124,15 +201,8 @@ impl Widget for PasswordDialog<'_> {
 
         buf.set_string(inner.x, inner.y, self.prompt, Style::default().fg(Color::White));
 
-        let field_width = inner.width as usize;
-        let masked: String = "•".repeat(self.value.chars().count());
-        let scroll = if self.cursor >= field_width.saturating_sub(1) {
-            self.cursor.saturating_sub(field_width.saturating_sub(2))
-        } else {
-            0
-        };
-        let visible: String = masked.chars().skip(scroll).take(field_width).collect();
-        let adjusted_cursor = self.cursor.saturating_sub(scroll);
+        let (masked, cursor) = mask(self.value, self.cursor);
+        let (visible, adjusted_cursor) = cursor_window(&masked, cursor, inner.width as usize);
 
         let value_y = inner.y + 2;
         fill_password_background(buf, inner.x, value_y, inner.width);
@@ -140,12 +210,37 @@ impl Widget for PasswordDialog<'_> {
 
         render_password_cursor(buf, inner.x + adjusted_cursor as u16, value_y, inner.x + inner.width);
 
-        if let Some(err) = self.error {
-            buf.set_string(inner.x, inner.y + 3, err, Style::default().fg(Color::Red));
+        let mut message_y = inner.y + 3;
+        if let Some(score) = self.strength {
+            render_password_strength(buf, inner.x, message_y, score, self.monochrome);
+            message_y += 1;
+        }
+
+        match (self.error, self.status) {
+            (Some(err), _) if self.monochrome => {
+                let text = format!("[error] {}", err);
+                buf.set_string(inner.x, message_y, text, Style::default().add_modifier(Modifier::BOLD));
+            }
+            (Some(err), _) => buf.set_string(inner.x, message_y, err, Style::default().fg(Color::Red)),
+            (None, Some(status)) => buf.set_string(inner.x, message_y, status, Style::default().fg(Color::Cyan)),
+            (None, None) => {}
         }
     }
 }
 
+fn render_password_strength(buf: &mut Buffer, x: u16, y: u16, score: u32, monochrome: bool) {
+    let text = format!("{} ({}%)", crate::crypto::strength_label(score), score);
+    let line = if monochrome {
+        Line::from(vec![Span::raw(strength_meter(score)), Span::raw(" "), Span::raw(text)])
+    } else {
+        Line::from(vec![
+            Span::styled("Strength: ", Style::default().fg(Color::Gray)),
+            Span::styled(text, Style::default().fg(strength_color(score))),
+        ])
+    };
+    buf.set_line(x, y, &line, 38);
+}
+
 fn fill_password_background(buf: &mut Buffer, x: u16, y: u16, width: u16) {
     for cx in x..x + width {
         if let Some(cell) = buf.cell_mut((cx, y)) {
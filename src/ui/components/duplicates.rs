@@ -0,0 +1,173 @@
+//! Duplicate credentials popup and state
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Clear, Widget},
+};
+
+use crate::vault::duplicates::{DuplicateEntry, DuplicateGroup, DuplicateKind};
+
+use super::layout::{
+    centered_rect_fixed, create_popup_block, highlight_row, render_empty_message,
+    truncate_with_ellipsis,
+};
+use super::scroll::render_v_scroll_indicator;
+
+#[derive(Default)]
+pub struct DuplicatesState {
+    pub groups: Vec<DuplicateGroup>,
+    /// Index into the flattened list of entries across all groups
+    pub selected: usize,
+}
+
+impl DuplicatesState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_groups(&mut self, groups: Vec<DuplicateGroup>) {
+        self.groups = groups;
+        self.selected = self.selected.min(self.entry_count().saturating_sub(1));
+    }
+
+    fn entry_count(&self) -> usize {
+        self.groups.iter().map(|g| g.entries.len()).sum()
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn scroll_down(&mut self) {
+        if self.selected + 1 < self.entry_count() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn home(&mut self) {
+        self.selected = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.selected = self.entry_count().saturating_sub(1);
+    }
+
+    /// Group and entry under the cursor
+    pub fn selected_entry(&self) -> Option<(&DuplicateGroup, &DuplicateEntry)> {
+        let mut remaining = self.selected;
+        for group in &self.groups {
+            if remaining < group.entries.len() {
+                return Some((group, &group.entries[remaining]));
+            }
+            remaining -= group.entries.len();
+        }
+        None
+    }
+}
+
+enum Row<'a> {
+    Header(&'a DuplicateGroup),
+    Entry(&'a DuplicateEntry, usize),
+}
+
+fn build_rows(groups: &[DuplicateGroup]) -> Vec<Row<'_>> {
+    let mut rows = Vec::new();
+    let mut entry_idx = 0;
+    for group in groups {
+        rows.push(Row::Header(group));
+        for entry in &group.entries {
+            rows.push(Row::Entry(entry, entry_idx));
+            entry_idx += 1;
+        }
+    }
+    rows
+}
+
+pub struct DuplicatesPopup<'a> {
+    state: &'a DuplicatesState,
+}
+
+impl<'a> DuplicatesPopup<'a> {
+    pub fn new(state: &'a DuplicatesState) -> Self {
+        Self { state }
+    }
+}
+
+impl Widget for DuplicatesPopup<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let rows = build_rows(&self.state.groups);
+        let height = calculate_duplicates_height(rows.len(), area.height);
+        let popup = centered_rect_fixed(80, height, area, true);
+        Clear.render(popup, buf);
+
+        let title = format!(" Duplicates ({}) ", self.state.groups.len());
+        let block = create_popup_block(&title, Color::Yellow);
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        if rows.is_empty() {
+            render_empty_message(inner, buf, "No duplicates found");
+            return;
+        }
+
+        let visible = inner.height as usize;
+        let cursor_row = rows
+            .iter()
+            .position(|r| matches!(r, Row::Entry(_, i) if *i == self.state.selected))
+            .unwrap_or(0);
+        let scroll_offset = if cursor_row >= visible { cursor_row - visible + 1 } else { 0 };
+
+        for (i, row) in rows.iter().enumerate().skip(scroll_offset).take(visible) {
+            let y = inner.y + (i - scroll_offset) as u16;
+            match row {
+                Row::Header(group) => render_group_header(inner, buf, y, group),
+                Row::Entry(entry, idx) => render_entry_row(inner, buf, y, entry, *idx == self.state.selected),
+            }
+        }
+
+        let max_v = rows.len().saturating_sub(visible);
+        if max_v > 0 {
+            render_v_scroll_indicator(buf, &popup, scroll_offset, max_v, Color::Yellow);
+        }
+    }
+}
+
+fn calculate_duplicates_height(rows: usize, area_height: u16) -> u16 {
+    let available = area_height.saturating_sub(2);
+    let desired = (rows as u16).saturating_add(2);
+    desired.min((available * 75) / 100).max(6)
+}
+
+fn render_group_header(inner: Rect, buf: &mut Buffer, y: u16, group: &DuplicateGroup) {
+    let color = match group.kind {
+        DuplicateKind::Exact => Color::Red,
+        DuplicateKind::Near => Color::Yellow,
+    };
+    let style = Style::default().fg(color).add_modifier(Modifier::BOLD);
+    let text = format!("{} ({})", group.kind.display_name(), group.entries.len());
+    buf.set_string(inner.x, y, &text, style);
+}
+
+fn render_entry_row(inner: Rect, buf: &mut Buffer, y: u16, entry: &DuplicateEntry, is_cursor: bool) {
+    if is_cursor {
+        highlight_row(buf, inner.x, y, inner.width);
+    }
+    let bg = if is_cursor { Color::DarkGray } else { Color::Reset };
+
+    let date = entry.updated_at.format("%Y-%m-%d").to_string();
+    let date_x = inner.x + inner.width.saturating_sub(date.len() as u16);
+    buf.set_string(date_x, y, &date, Style::default().fg(Color::DarkGray).bg(bg));
+
+    let mut text = entry.name.clone();
+    if let Some(username) = &entry.username {
+        text.push_str(&format!(" [{}]", username));
+    }
+    if let Some(url) = &entry.url {
+        text.push_str(&format!(" {}", url));
+    }
+    let max_width = (inner.width as usize).saturating_sub(date.len() + 3);
+    let display = truncate_with_ellipsis(&text, max_width);
+    buf.set_string(inner.x + 2, y, &display, Style::default().fg(Color::White).bg(bg));
+}
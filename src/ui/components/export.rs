@@ -1,6 +1,8 @@
 //! Export Dialog Component
 //!
-//! Dialog for selecting export format, encryption, and passphrase.
+//! Dialog for selecting export format, scope, encryption, recipients and passphrase.
+
+use std::path::Path;
 
 use ratatui::{
     buffer::Buffer,
@@ -11,50 +13,57 @@ use ratatui::{
 use secrecy::SecretString;
 use crossterm::event::{KeyCode, KeyModifiers};
 
-use crate::vault::export::{ExportEncryption, ExportFormat};
-use crate::input::{handle_text_key, SecureTextBuffer, TextBuffer, TextEditing};
+use crate::vault::aegis::AEGIS_EXTENSION;
+use crate::vault::bundle::BUNDLE_EXTENSION;
+use crate::vault::export::{is_stdout, parse_age_recipients, ExportEncryption, ExportFormat};
+use crate::vault::pass::default_store_dir;
+use crate::vault::sealed::SEALED_EXTENSION;
+use crate::input::{cursor_window, handle_text_key, mask, SecureTextBuffer, TextBuffer, TextEditing};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ExportField {
     Format,
+    Scope,
     Encryption,
+    Recipients,
     Passphrase,
     Path,
 }
 
 impl ExportField {
-    fn next(self, needs_passphrase: bool) -> Self {
-        match self {
-            Self::Format => Self::Encryption,
-            Self::Encryption => next_after_encryption(needs_passphrase),
-            Self::Passphrase => Self::Path,
-            Self::Path => Self::Format,
-        }
-    }
+    const ALL: [Self; 6] = [Self::Format, Self::Scope, Self::Encryption, Self::Recipients, Self::Passphrase, Self::Path];
+}
 
-    fn prev(self, needs_passphrase: bool) -> Self {
-        match self {
-            Self::Format => Self::Path,
-            Self::Encryption => Self::Format,
-            Self::Passphrase => Self::Encryption,
-            Self::Path => prev_before_path(needs_passphrase),
-        }
-    }
+/// Which credentials an export covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportScope {
+    /// Every credential in the vault
+    All,
+    /// The credentials the list currently shows
+    Filtered,
+    /// The marked credentials
+    Selected,
+    /// Only the credential open in the detail view, named here
+    Current,
 }
 
-fn next_after_encryption(needs_passphrase: bool) -> ExportField {
-    if needs_passphrase {
-        ExportField::Passphrase
-    } else {
-        ExportField::Path
-    }
+/// How many credentials each scope would export
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScopeSizes {
+    pub all: usize,
+    /// None while no search or filter is active
+    pub filtered: Option<usize>,
+    pub selected: usize,
 }
 
-fn prev_before_path(needs_passphrase: bool) -> ExportField {
-    if needs_passphrase {
-        ExportField::Passphrase
-    } else {
-        ExportField::Encryption
+impl ScopeSizes {
+    fn of(&self, scope: ExportScope) -> Option<usize> {
+        match scope {
+            ExportScope::All => Some(self.all),
+            ExportScope::Filtered => self.filtered,
+            ExportScope::Selected => Some(self.selected).filter(|n| *n > 0),
+            ExportScope::Current => Some(1),
+        }
     }
 }
 
@@ -62,7 +71,13 @@ fn prev_before_path(needs_passphrase: bool) -> ExportField {
 pub struct ExportDialog {
     pub active_field: ExportField,
     pub format: ExportFormat,
+    pub scope: ExportScope,
+    sizes: ScopeSizes,
+    /// Name of the credential a `Current` export holds
+    current: Option<String>,
     pub encryption: ExportEncryption,
+    /// GPG key IDs or age public keys, used instead of the passphrase when set
+    pub recipients: TextBuffer,
     passphrase: SecureTextBuffer,
     pub path: TextBuffer,
     pub error: Option<String>,
@@ -70,35 +85,76 @@ pub struct ExportDialog {
 
 impl Default for ExportDialog {
     fn default() -> Self {
-        Self::new()
+        Self::new(ScopeSizes::default())
     }
 }
 
 impl ExportDialog {
-    pub fn new() -> Self {
+    /// Starts on the narrowest scope there is: the marked credentials, else
+    /// the filtered list, else the whole vault
+    pub fn new(sizes: ScopeSizes) -> Self {
         let default_encryption = ExportEncryption::Gpg;
+        let scope = [ExportScope::Selected, ExportScope::Filtered]
+            .into_iter()
+            .find(|s| sizes.of(*s).is_some())
+            .unwrap_or(ExportScope::All);
         Self {
             active_field: ExportField::Format,
             format: ExportFormat::Json,
+            scope,
+            sizes,
+            current: None,
             encryption: default_encryption,
+            recipients: TextBuffer::new(),
             passphrase: SecureTextBuffer::new(),
             path: TextBuffer::with_content(default_export_path(ExportFormat::Json, default_encryption)),
             error: None,
         }
     }
 
+    /// An export of just the credential called `name`, as JSON or text,
+    /// named after it
+    pub fn for_credential(name: &str) -> Self {
+        let mut dialog = Self::new(ScopeSizes::default());
+        dialog.scope = ExportScope::Current;
+        dialog.path.set_content(&export_path(&file_stem(name), dialog.format, dialog.encryption));
+        dialog.current = Some(name.to_string());
+        dialog
+    }
+
     pub fn next_field(&mut self) {
-        self.active_field = self.active_field.next(self.needs_passphrase());
-        self.update_cursor_to_end();
+        self.step_field(1);
     }
 
     pub fn prev_field(&mut self) {
-        self.active_field = self.active_field.prev(self.needs_passphrase());
+        self.step_field(ExportField::ALL.len() - 1);
+    }
+
+    /// Move by `step` places around the fields, skipping ones that don't apply
+    fn step_field(&mut self, step: usize) {
+        let all = ExportField::ALL;
+        let mut pos = all.iter().position(|f| *f == self.active_field).unwrap_or(0);
+        loop {
+            pos = (pos + step) % all.len();
+            if self.is_editable(all[pos]) {
+                break;
+            }
+        }
+        self.active_field = all[pos];
         self.update_cursor_to_end();
     }
 
+    fn is_editable(&self, field: ExportField) -> bool {
+        match field {
+            ExportField::Recipients => self.takes_recipients(),
+            ExportField::Passphrase => self.needs_passphrase(),
+            _ => true,
+        }
+    }
+
     fn update_cursor_to_end(&mut self) {
         match self.active_field {
+            ExportField::Recipients => self.recipients.cursor_end(),
             ExportField::Passphrase => self.passphrase.cursor_end(),
             ExportField::Path => self.path.cursor_end(),
             _ => {}
@@ -106,65 +162,132 @@ impl ExportDialog {
     }
 
     pub fn cycle_format(&mut self) {
+        // One credential goes out as a file to read, not a vault to import
+        if self.scope == ExportScope::Current {
+            self.format = match self.format {
+                ExportFormat::Json => ExportFormat::Text,
+                _ => ExportFormat::Json,
+            };
+            self.update_path_extension();
+            return;
+        }
         self.format = match self.format {
             ExportFormat::Json => ExportFormat::Text,
-            ExportFormat::Text => ExportFormat::Json,
+            ExportFormat::Text => ExportFormat::Bundle,
+            ExportFormat::Bundle => ExportFormat::Aegis,
+            ExportFormat::Aegis => ExportFormat::Pass,
+            ExportFormat::Pass => ExportFormat::Json,
         };
         self.update_path_extension();
     }
 
+    /// Step through the scopes that have something to export; bundles
+    /// always hold the whole vault
+    pub fn cycle_scope(&mut self, forward: bool) {
+        if self.format == ExportFormat::Bundle || self.scope == ExportScope::Current {
+            return;
+        }
+        let order = [ExportScope::All, ExportScope::Filtered, ExportScope::Selected];
+        let current = order.iter().position(|s| *s == self.scope).unwrap_or(0);
+        let step = if forward { 1 } else { order.len() - 1 };
+        self.scope = (1..order.len())
+            .map(|i| order[(current + i * step) % order.len()])
+            .find(|s| self.sizes.of(*s).is_some())
+            .unwrap_or(self.scope);
+    }
+
+    /// How many credentials the export will hold
+    pub fn scope_size(&self) -> usize {
+        self.sizes.of(self.scope).unwrap_or_default()
+    }
+
+    fn scope_display(&self) -> String {
+        if self.format == ExportFormat::Bundle {
+            return "Whole vault (bundle)".into();
+        }
+        let name = match self.scope {
+            ExportScope::All => "All",
+            ExportScope::Filtered => "Filtered",
+            ExportScope::Selected => "Selected",
+            ExportScope::Current => return format!("Only {}", self.current.as_deref().unwrap_or_default()),
+        };
+        format!("{} ({})", name, self.scope_size())
+    }
+
     pub fn cycle_encryption_forward(&mut self) {
+        if self.format.is_self_encrypted() {
+            return;
+        }
         self.encryption = match self.encryption {
             ExportEncryption::None => ExportEncryption::Gpg,
             ExportEncryption::Gpg => ExportEncryption::Age,
-            ExportEncryption::Age => ExportEncryption::None,
+            ExportEncryption::Age => ExportEncryption::Internal,
+            ExportEncryption::Internal => ExportEncryption::None,
         };
         self.handle_encryption_change();
     }
 
     pub fn cycle_encryption_backward(&mut self) {
+        if self.format.is_self_encrypted() {
+            return;
+        }
         self.encryption = match self.encryption {
-            ExportEncryption::None => ExportEncryption::Age,
+            ExportEncryption::None => ExportEncryption::Internal,
             ExportEncryption::Gpg => ExportEncryption::None,
             ExportEncryption::Age => ExportEncryption::Gpg,
+            ExportEncryption::Internal => ExportEncryption::Age,
         };
         self.handle_encryption_change();
     }
 
     fn handle_encryption_change(&mut self) {
         self.update_path_extension();
-        
-        if self.needs_passphrase() {
-            return;
-        }
-        
-        if self.active_field == ExportField::Passphrase {
+
+        if !self.is_editable(self.active_field) {
             self.active_field = ExportField::Path;
             self.path.cursor_end();
         }
     }
 
     fn update_path_extension(&mut self) {
+        // A pass store is a directory of its own, not a file to rename
+        let store = default_store_dir().to_string_lossy().into_owned();
+        if self.format == ExportFormat::Pass {
+            self.path.set_content(&store);
+            return;
+        }
+        if self.path.content() == store {
+            self.path.set_content(&default_export_path(self.format, self.encryption));
+            return;
+        }
+
         let base = self
             .path
             .content()
             .trim_end_matches(".gpg")
             .trim_end_matches(".age")
+            .trim_end_matches(SEALED_EXTENSION)
+            .trim_end_matches(BUNDLE_EXTENSION)
+            .trim_end_matches(AEGIS_EXTENSION)
             .trim_end_matches(".json")
             .trim_end_matches(".txt");
 
         let format_ext = match self.format {
             ExportFormat::Json => ".json",
             ExportFormat::Text => ".txt",
+            ExportFormat::Bundle => BUNDLE_EXTENSION,
+            ExportFormat::Aegis => AEGIS_EXTENSION,
+            ExportFormat::Pass => "",
         };
 
-        let enc_ext = self.encryption.file_extension();
+        let enc_ext = self.encryption_extension();
 
         self.path.set_content(&format!("{}{}{}", base, format_ext, enc_ext));
     }
 
     pub fn insert_char(&mut self, c: char) {
         match self.active_field {
+            ExportField::Recipients if self.takes_recipients() => self.recipients.insert_char(c),
             ExportField::Passphrase if self.needs_passphrase() => self.passphrase.insert_char(c),
             ExportField::Path => self.path.insert_char(c),
             _ => {}
@@ -172,15 +295,58 @@ impl ExportDialog {
     }
 
     pub fn handle_text_key(&mut self, code: KeyCode, mods: KeyModifiers) {
-        if self.active_field == ExportField::Passphrase && self.needs_passphrase() {
+        if self.active_field == ExportField::Recipients && self.takes_recipients() {
+            handle_text_key(&mut self.recipients, code, mods);
+        } else if self.active_field == ExportField::Passphrase && self.needs_passphrase() {
             handle_text_key(&mut self.passphrase, code, mods);
         } else if self.active_field == ExportField::Path {
             handle_text_key(&mut self.path, code, mods);
         }
     }
 
+    /// pass stores are encrypted to the store's GPG keys, and GPG or age
+    /// exports to recipients' keys, without a passphrase
     pub fn needs_passphrase(&self) -> bool {
-        self.encryption != ExportEncryption::None
+        match self.format {
+            ExportFormat::Pass => false,
+            _ if !self.get_recipients().is_empty() => false,
+            format => format.is_self_encrypted() || self.encryption != ExportEncryption::None,
+        }
+    }
+
+    /// GPG and age can encrypt to public keys instead of a passphrase
+    pub fn takes_recipients(&self) -> bool {
+        !self.format.is_self_encrypted() && matches!(self.encryption, ExportEncryption::Gpg | ExportEncryption::Age)
+    }
+
+    /// The recipients typed in, split on commas and whitespace
+    pub fn get_recipients(&self) -> Vec<String> {
+        if !self.takes_recipients() {
+            return Vec::new();
+        }
+        self.recipients
+            .content()
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|r| !r.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    /// Bundles, Aegis vaults and pass stores encrypt themselves and are never wrapped again
+    fn encryption_extension(&self) -> &'static str {
+        match self.format.is_self_encrypted() {
+            true => "",
+            false => self.encryption.file_extension(),
+        }
+    }
+
+    fn encryption_display(&self) -> &'static str {
+        match self.format {
+            ExportFormat::Bundle => "Built-in (Argon2id + ChaCha20)",
+            ExportFormat::Aegis => "Built-in (scrypt + AES-256-GCM)",
+            ExportFormat::Pass => "GPG to the store's .gpg-id keys",
+            _ => self.encryption.display_name(),
+        }
     }
 
     // Only expose when needed for export
@@ -193,32 +359,64 @@ impl ExportDialog {
     }
 
     pub fn validate(&self) -> Result<(), String> {
+        let recipients = self.get_recipients();
+        if self.encryption == ExportEncryption::Age && !recipients.is_empty() {
+            parse_age_recipients(&recipients).map_err(|e| e.to_string())?;
+        }
         if self.needs_passphrase() && self.passphrase.is_empty() {
             return Err("Passphrase required for encrypted export".into());
         }
         if self.path.content().trim().is_empty() {
             return Err("Output path is required".into());
         }
+        // stdout is the screen this dialog is drawn on
+        if is_stdout(Path::new(self.path.content().trim())) {
+            return Err("Exporting to stdout (-) needs the CLI: vault export -o -".into());
+        }
         Ok(())
     }
 }
 
 fn default_export_path(format: ExportFormat, encryption: ExportEncryption) -> String {
+    export_path("vault_export", format, encryption)
+}
+
+/// `stem` with the extensions for `format` and `encryption`, in the home directory
+fn export_path(stem: &str, format: ExportFormat, encryption: ExportEncryption) -> String {
     let format_ext = match format {
         ExportFormat::Json => ".json",
         ExportFormat::Text => ".txt",
+        ExportFormat::Bundle => BUNDLE_EXTENSION,
+        ExportFormat::Aegis => AEGIS_EXTENSION,
+        ExportFormat::Pass => return default_store_dir().to_string_lossy().into_owned(),
     };
     let enc_ext = encryption.file_extension();
 
     let home_path = dirs::home_dir();
     match home_path {
-        Some(p) => build_export_path_from_home(p, format_ext, enc_ext),
-        None => format!("./vault_export{}{}", format_ext, enc_ext),
+        Some(p) => build_export_path_from_home(p, stem, format_ext, enc_ext),
+        None => format!("./{}{}{}", stem, format_ext, enc_ext),
+    }
+}
+
+/// `name` as a file name: letters, digits, `-` and `_`, other runs as one `_`
+fn file_stem(name: &str) -> String {
+    let mut stem = String::new();
+    for c in name.trim().chars() {
+        match c.is_alphanumeric() || c == '-' {
+            true => stem.push(c),
+            false if !stem.ends_with('_') => stem.push('_'),
+            false => {}
+        }
+    }
+    match stem.trim_matches('_') {
+        "" => "credential".to_string(),
+        stem => stem.to_string(),
     }
 }
 
-fn build_export_path_from_home(home: std::path::PathBuf, format_ext: &str, enc_ext: &str) -> String {
-    home.join(format!("vault_export{}{}", format_ext, enc_ext))
+fn build_export_path_from_home(home: std::path::PathBuf, stem: &str, format_ext: &str, enc_ext: &str) -> String {
+    home.join(format!("{}{}{}", stem, format_ext, enc_ext))
         .to_string_lossy()
         .into_owned()
 }
@@ -244,7 +442,9 @@ impl Widget for ExportDialogWidget<'_> {
         let mut y = inner.y;
 
         y = render_format_field(self.dialog, buf, inner.x, y, label_width, value_width);
+        y = render_scope_field(self.dialog, buf, inner.x, y, label_width, value_width);
         y = render_encryption_field(self.dialog, buf, inner.x, y, label_width, value_width);
+        y = render_recipients_field(self.dialog, buf, inner.x, y, label_width, value_width);
         y = render_passphrase_field(self.dialog, buf, inner.x, y, label_width, value_width);
         y = render_path_field(self.dialog, buf, inner.x, y, label_width, value_width);
 
@@ -273,6 +473,27 @@ fn render_format_field(
     y + 2
 }
 
+fn render_scope_field(
+    dialog: &ExportDialog,
+    buf: &mut Buffer,
+    x: u16,
+    y: u16,
+    label_width: u16,
+    value_width: u16,
+) -> u16 {
+    render_select_field(
+        buf,
+        x,
+        y,
+        "Scope:",
+        &dialog.scope_display(),
+        dialog.active_field == ExportField::Scope,
+        label_width,
+        value_width,
+    );
+    y + 2
+}
+
 fn render_encryption_field(
     dialog: &ExportDialog,
     buf: &mut Buffer,
@@ -286,7 +507,7 @@ fn render_encryption_field(
         x,
         y,
         "Encryption:",
-        dialog.encryption.display_name(),
+        dialog.encryption_display(),
         dialog.active_field == ExportField::Encryption,
         label_width,
         value_width,
@@ -294,6 +515,45 @@ fn render_encryption_field(
     y + 2
 }
 
+fn render_recipients_field(
+    dialog: &ExportDialog,
+    buf: &mut Buffer,
+    x: u16,
+    y: u16,
+    label_width: u16,
+    value_width: u16,
+) -> u16 {
+    if !dialog.takes_recipients() {
+        buf.set_string(x, y, "Recipients:", Style::default().fg(Color::DarkGray));
+        fill_background(buf, x + label_width, y, value_width, Color::Black);
+        render_disabled_input(buf, x + label_width, y, Color::Black, "(N/A - GPG or age only)");
+        return y + 2;
+    }
+
+    let is_active = dialog.active_field == ExportField::Recipients;
+    render_input_field(
+        buf,
+        x,
+        y,
+        "Recipients:",
+        dialog.recipients.content(),
+        dialog.recipients.cursor(),
+        is_active,
+        false,
+        label_width,
+        value_width,
+        true,
+    );
+    if !is_active && dialog.recipients.content().is_empty() {
+        let hint = match dialog.encryption {
+            ExportEncryption::Age => "(none: passphrase; or age1... keys)",
+            _ => "(none: passphrase; or key IDs)",
+        };
+        buf.set_string(x + label_width, y, hint, Style::default().fg(Color::DarkGray).bg(Color::Black));
+    }
+    y + 2
+}
+
 fn render_passphrase_field(
     dialog: &ExportDialog,
     buf: &mut Buffer,
@@ -352,7 +612,7 @@ fn render_error_if_present(dialog: &ExportDialog, buf: &mut Buffer, x: u16, y: u
 fn calculate_form_area(area: Rect, has_error: bool) -> Rect {
     let content_area = Rect::new(area.x, area.y, area.width, area.height.saturating_sub(2));
     let form_width = 60u16.min(content_area.width.saturating_sub(4));
-    let content_height = if has_error { 12u16 } else { 11u16 };
+    let content_height = if has_error { 16u16 } else { 15u16 };
     let remainder = (content_area.height.saturating_sub(content_height)) % 2;
     let form_height = (content_height + remainder).min(content_area.height);
     let form_x = content_area.x + (content_area.width.saturating_sub(form_width)) / 2;
@@ -379,6 +639,9 @@ fn format_display(format: ExportFormat) -> String {
     match format {
         ExportFormat::Json => "JSON".into(),
         ExportFormat::Text => "Plain Text".into(),
+        ExportFormat::Bundle => "Vault bundle (whole vault)".into(),
+        ExportFormat::Aegis => "Aegis vault (TOTP only)".into(),
+        ExportFormat::Pass => "pass store (file per credential)".into(),
     }
 }
 
@@ -393,7 +656,7 @@ fn label_style(is_active: bool) -> Style {
 }
 
 #[allow(clippy::too_many_arguments)]
-fn render_select_field(
+pub(super) fn render_select_field(
     buf: &mut Buffer,
     x: u16,
     y: u16,
@@ -420,7 +683,7 @@ fn render_select_field(
 }
 
 #[allow(clippy::too_many_arguments)]
-fn render_input_field(
+pub(super) fn render_input_field(
     buf: &mut Buffer,
     x: u16,
     y: u16,
@@ -445,7 +708,7 @@ fn render_input_field(
     fill_background(buf, value_x, y, value_width, bg_color);
 
     if !enabled {
-        render_disabled_input(buf, value_x, y, bg_color);
+        render_disabled_input(buf, value_x, y, bg_color, "(N/A - no passphrase needed)");
         return;
     }
 
@@ -460,8 +723,7 @@ fn compute_input_bg_color(is_active: bool, enabled: bool) -> Color {
     }
 }
 
-fn render_disabled_input(buf: &mut Buffer, x: u16, y: u16, bg_color: Color) {
-    let disabled_text = "(N/A - no encryption)";
+fn render_disabled_input(buf: &mut Buffer, x: u16, y: u16, bg_color: Color, disabled_text: &str) {
     buf.set_string(
         x,
         y,
@@ -482,15 +744,13 @@ fn render_enabled_input(
     value_width: u16,
     bg_color: Color,
 ) {
-    let display_value = if masked {
-        "•".repeat(value.len())
+    let (display_value, cursor) = if masked {
+        mask(value, cursor)
     } else {
-        value.to_string()
+        (value.to_string(), cursor)
     };
 
-    let scroll = compute_scroll_offset(cursor, value_width);
-    let visible = extract_visible_text(&display_value, scroll, value_width);
-    let adjusted_cursor = cursor.saturating_sub(scroll);
+    let (visible, adjusted_cursor) = cursor_window(&display_value, cursor, value_width as usize);
 
     let fg_color = if masked { Color::Green } else { Color::Blue };
     let value_style = Style::default().fg(fg_color).bg(bg_color);
@@ -501,21 +761,6 @@ fn render_enabled_input(
     }
 }
 
-fn compute_scroll_offset(cursor: usize, value_width: u16) -> usize {
-    if cursor >= (value_width as usize).saturating_sub(1) {
-        cursor.saturating_sub((value_width as usize).saturating_sub(2))
-    } else {
-        0
-    }
-}
-
-fn extract_visible_text(text: &str, scroll: usize, width: u16) -> String {
-    text.chars()
-        .skip(scroll)
-        .take(width as usize)
-        .collect()
-}
-
 fn render_cursor(buf: &mut Buffer, x: u16, y: u16, cursor: usize, width: u16) {
     let cursor_x = x + cursor as u16;
     if cursor_x >= x + width {
@@ -533,3 +778,67 @@ fn fill_background(buf: &mut Buffer, x: u16, y: u16, width: u16, color: Color) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope_skips_empty() {
+        let sizes = ScopeSizes { all: 10, filtered: None, selected: 2 };
+        let mut dialog = ExportDialog::new(sizes);
+        assert_eq!(dialog.scope, ExportScope::Selected);
+        dialog.cycle_scope(true);
+        assert_eq!((dialog.scope, dialog.scope_size()), (ExportScope::All, 10));
+        dialog.cycle_scope(true);
+        assert_eq!(dialog.scope, ExportScope::Selected);
+        dialog.cycle_scope(false);
+        assert_eq!(dialog.scope, ExportScope::All);
+
+        let dialog = ExportDialog::new(ScopeSizes { all: 10, filtered: Some(0), selected: 0 });
+        assert_eq!(dialog.scope, ExportScope::Filtered);
+        assert_eq!(ExportDialog::new(ScopeSizes { all: 3, ..Default::default() }).scope, ExportScope::All);
+    }
+
+    #[test]
+    fn test_recipients_replace_passphrase() {
+        let mut dialog = ExportDialog::new(ScopeSizes { all: 3, ..Default::default() });
+        assert!(dialog.takes_recipients() && dialog.needs_passphrase());
+        dialog.active_field = ExportField::Encryption;
+        dialog.next_field();
+        assert_eq!(dialog.active_field, ExportField::Recipients);
+        for c in "ABCD1234, bob@example.com".chars() {
+            dialog.insert_char(c);
+        }
+        assert_eq!(dialog.get_recipients(), ["ABCD1234", "bob@example.com"]);
+        assert!(!dialog.needs_passphrase() && dialog.get_passphrase().is_none());
+        dialog.next_field();
+        assert_eq!(dialog.active_field, ExportField::Path);
+
+        dialog.cycle_encryption_forward();
+        assert_eq!(dialog.encryption, ExportEncryption::Age);
+        assert!(dialog.validate().is_err());
+        dialog.cycle_encryption_forward();
+        assert!(dialog.get_recipients().is_empty() && dialog.needs_passphrase());
+        dialog.prev_field();
+        assert_eq!(dialog.active_field, ExportField::Passphrase);
+        dialog.prev_field();
+        assert_eq!(dialog.active_field, ExportField::Encryption);
+    }
+
+    #[test]
+    fn test_single_credential() {
+        let mut dialog = ExportDialog::for_credential("GitHub (work)");
+        assert!(dialog.path.content().ends_with("GitHub_work.json.gpg"));
+        dialog.cycle_scope(true);
+        assert_eq!((dialog.scope, dialog.scope_size()), (ExportScope::Current, 1));
+        dialog.cycle_format();
+        assert!(dialog.path.content().ends_with("GitHub_work.txt.gpg"));
+        dialog.cycle_format();
+        assert_eq!(dialog.format, ExportFormat::Json);
+        dialog.cycle_encryption_backward();
+        dialog.cycle_encryption_backward();
+        assert!(dialog.path.content().ends_with("GitHub_work.json.sealed"));
+        assert_eq!(file_stem("../.."), "credential");
+    }
+}
@@ -2,6 +2,10 @@
 //!
 //! Multi-field form for creating and editing credentials.
 
+use std::collections::HashMap;
+
+use chrono::{DateTime, Local};
+
 use ratatui::{
     buffer::Buffer,
     layout::Rect,
@@ -9,17 +13,41 @@ use ratatui::{
     widgets::{Block, Borders, BorderType, Clear, Widget},
 };
 
+use crate::crypto::{PasswordError, PasswordPolicy};
 use crate::db::models::CredentialType;
 use crate::ui::renderer::View;
 use crossterm::event::{KeyCode, KeyModifiers};
-use crate::input::{handle_text_key, TextBuffer, TextEditing};
+use unicode_segmentation::UnicodeSegmentation;
+use zeroize::Zeroizing;
+use crate::input::{cursor_window, display_width, handle_text_key, mask, SecureTextBuffer, TextEditing};
+use crate::vault::expiry::{self, EXPIRES_FIELD};
 
 use super::scroll::render_v_scroll_indicator;
 
+/// What a form field holds, independent of where the type's layout puts it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FieldKey {
+    Name,
+    Type,
+    Username,
+    Secret,
+    Url,
+    Tags,
+    Totp,
+    Expires,
+    Rotation,
+    /// Generation rule for the secret, e.g. `16 nosymbols`
+    Rule,
+    Notes,
+    /// A type-specific field, stored under its label
+    Extra(&'static str),
+}
+
 #[derive(Debug, Clone)]
 pub struct FormField {
+    pub key: FieldKey,
     pub label: &'static str,
-    pub value: String,
+    pub value: Zeroizing<String>,
     pub required: bool,
     pub masked: bool,
     pub field_type: FieldType,
@@ -34,40 +62,44 @@ pub enum FieldType {
 }
 
 impl FormField {
-    pub fn text(label: &'static str, required: bool) -> Self {
+    pub fn text(key: FieldKey, label: &'static str, required: bool) -> Self {
         Self {
+            key,
             label,
-            value: String::new(),
+            value: Zeroizing::default(),
             required,
             masked: false,
             field_type: FieldType::Text,
         }
     }
 
-    pub fn secret(label: &'static str, required: bool) -> Self {
+    pub fn secret(key: FieldKey, label: &'static str, required: bool) -> Self {
         Self {
+            key,
             label,
-            value: String::new(),
+            value: Zeroizing::default(),
             required,
             masked: true,
             field_type: FieldType::Password,
         }
     }
 
-    pub fn select(label: &'static str) -> Self {
+    pub fn select(key: FieldKey, label: &'static str) -> Self {
         Self {
+            key,
             label,
-            value: String::new(),
+            value: Zeroizing::default(),
             required: true,
             masked: false,
             field_type: FieldType::Select,
         }
     }
 
-    pub fn multiline(label: &'static str) -> Self {
+    pub fn multiline(key: FieldKey, label: &'static str) -> Self {
         Self {
+            key,
             label,
-            value: String::new(),
+            value: Zeroizing::default(),
             required: false,
             masked: false,
             field_type: FieldType::MultiLine,
@@ -75,7 +107,7 @@ impl FormField {
     }
 
     pub fn with_value(mut self, value: impl Into<String>) -> Self {
-        self.value = value.into();
+        self.value = Zeroizing::new(value.into());
         self
     }
 }
@@ -91,6 +123,8 @@ pub struct CredentialForm {
     pub scroll_offset: usize,
     pub multiline_scroll: usize,
     pub previous_view: View,
+    /// Values of fields the current type's layout doesn't show, kept across type changes
+    stash: HashMap<FieldKey, Zeroizing<String>>,
 }
 
 impl Default for CredentialForm {
@@ -99,17 +133,66 @@ impl Default for CredentialForm {
     }
 }
 
-fn default_fields() -> Vec<FormField> {
-    vec![
-        FormField::text("Name", true),
-        FormField::select("Type").with_value(CredentialType::Password.display_name()),
-        FormField::text("Username", false),
-        FormField::secret("Password/Secret", true),
-        FormField::text("URL", false),
-        FormField::text("Tags (multiple)", false),
-        FormField::secret("TOTP Secret", false),
-        FormField::multiline("Notes"),
-    ]
+/// The form layout for a credential type
+fn template(cred_type: CredentialType) -> Vec<FormField> {
+    let secret_required = is_secret_required(cred_type);
+    let tags = FormField::text(FieldKey::Tags, "Tags (multiple)", false);
+
+    let mut fields = vec![
+        FormField::text(FieldKey::Name, "Name", true),
+        FormField::select(FieldKey::Type, "Type").with_value(cred_type.display_name()),
+    ];
+    fields.extend(match cred_type {
+        CredentialType::Database => vec![
+            FormField::text(FieldKey::Extra("Host"), "Host", false),
+            FormField::text(FieldKey::Extra("Port"), "Port", false),
+            FormField::text(FieldKey::Extra("Database"), "Database", false),
+            FormField::text(FieldKey::Username, "User", false),
+            FormField::secret(FieldKey::Secret, "Password", secret_required),
+            FormField::text(FieldKey::Rule, "Password rule", false),
+            tags,
+        ],
+        CredentialType::SshKey => vec![
+            FormField::secret(FieldKey::Secret, "Private Key", secret_required),
+            FormField::text(FieldKey::Extra("Public Key"), "Public Key", false),
+            FormField::secret(FieldKey::Extra("Passphrase"), "Passphrase", false),
+            tags,
+        ],
+        CredentialType::Certificate => vec![
+            FormField::multiline(FieldKey::Extra("Certificate"), "Certificate"),
+            FormField::secret(FieldKey::Secret, "Private Key", secret_required),
+            FormField::multiline(FieldKey::Extra("Chain"), "Chain"),
+            tags,
+        ],
+        CredentialType::ApiKey => vec![
+            FormField::text(FieldKey::Username, "Username", false),
+            FormField::secret(FieldKey::Secret, "Password/Secret", secret_required),
+            FormField::text(FieldKey::Rule, "Password rule", false),
+            FormField::text(FieldKey::Url, "URL", false),
+            tags,
+            FormField::secret(FieldKey::Totp, "TOTP Secret", false),
+        ],
+        _ => vec![
+            FormField::text(FieldKey::Username, "Username", false),
+            FormField::secret(FieldKey::Secret, "Password/Secret", secret_required),
+            FormField::text(FieldKey::Rule, "Password rule", false),
+            FormField::text(FieldKey::Url, "URL", false),
+            tags,
+            FormField::secret(FieldKey::Totp, "TOTP Secret", false),
+        ],
+    });
+    fields.push(FormField::text(FieldKey::Expires, "Expires", false));
+    fields.push(FormField::text(FieldKey::Rotation, "Rotate (days)", false));
+    fields.push(FormField::multiline(FieldKey::Notes, "Notes"));
+    fields
+}
+
+/// Whether a type-specific field holds a secret and should be masked
+pub fn is_secret_field(label: &str) -> bool {
+    [CredentialType::Database, CredentialType::SshKey, CredentialType::Certificate]
+        .into_iter()
+        .flat_map(template)
+        .any(|f| matches!(f.key, FieldKey::Extra(l) if l == label) && f.masked)
 }
 
 fn is_secret_required(cred_type: CredentialType) -> bool {
@@ -141,12 +224,12 @@ fn cycle_type_backward(cred_type: CredentialType) -> CredentialType {
 }
 
 fn trim_to_option(val: &str) -> Option<String> {
-    let trimmed = val.trim();
-    if trimmed.is_empty() {
-        None
-    } else {
-        Some(trimmed.to_string())
-    }
+    trim_to_str(val).map(str::to_string)
+}
+
+/// Like `trim_to_option`, borrowed so secret values aren't copied out
+fn trim_to_str(val: &str) -> Option<&str> {
+    Some(val.trim()).filter(|v| !v.is_empty())
 }
 
 pub struct EditFormParams {
@@ -159,13 +242,17 @@ pub struct EditFormParams {
     pub tags: Vec<String>,
     pub totp_secret: Option<String>,
     pub notes: Option<String>,
+    pub fields: Vec<(String, String)>,
+    pub expires_at: Option<DateTime<Local>>,
+    pub rotate_every: Option<u32>,
+    pub password_rule: Option<String>,
     pub previous_view: View,
 }
 
 impl CredentialForm {
     pub fn new() -> Self {
         Self {
-            fields: default_fields(),
+            fields: template(CredentialType::Password),
             active_field: 0,
             cursor: 0,
             credential_type: CredentialType::Password,
@@ -174,6 +261,7 @@ impl CredentialForm {
             scroll_offset: 0,
             multiline_scroll: 0,
             previous_view: View::List,
+            stash: HashMap::new(),
         }
     }
 
@@ -181,21 +269,54 @@ impl CredentialForm {
         let mut form = Self::new();
         form.editing_id = Some(params.id);
         form.credential_type = params.cred_type;
+        form.fields = template(params.cred_type);
         form.previous_view = params.previous_view;
 
-        form.fields[0].value = params.name;
-        form.fields[1].value = params.cred_type.display_name().to_string();
-        form.fields[2].value = params.username.unwrap_or_default();
-        form.fields[3].value = params.secret;
-        form.fields[3].required = is_secret_required(params.cred_type);
-        form.fields[4].value = params.url.unwrap_or_default();
-        form.fields[5].value = params.tags.join(" ");
-        form.fields[6].value = params.totp_secret.unwrap_or_default();
-        form.fields[7].value = params.notes.unwrap_or_default();
+        form.set_value(FieldKey::Name, params.name);
+        form.set_value(FieldKey::Username, params.username.unwrap_or_default());
+        form.set_value(FieldKey::Secret, params.secret);
+        form.set_value(FieldKey::Url, params.url.unwrap_or_default());
+        form.set_value(FieldKey::Tags, params.tags.join(" "));
+        form.set_value(FieldKey::Totp, params.totp_secret.unwrap_or_default());
+        form.set_value(FieldKey::Notes, params.notes.unwrap_or_default());
+        form.set_value(FieldKey::Expires, params.expires_at.map(expiry::format_date).unwrap_or_default());
+        form.set_value(FieldKey::Rotation, params.rotate_every.map(|d| d.to_string()).unwrap_or_default());
+        form.set_value(FieldKey::Rule, params.password_rule.unwrap_or_default());
+        for (label, value) in params.fields {
+            // API keys kept their expiry date in a field; saving moves it to `expires_at`
+            if label == EXPIRES_FIELD && form.value(FieldKey::Expires).is_empty() {
+                form.set_value(FieldKey::Expires, value);
+                continue;
+            }
+            let key = form.fields.iter().map(|f| f.key).find(|k| matches!(k, FieldKey::Extra(l) if *l == label));
+            if let Some(key) = key {
+                form.set_value(key, value);
+            }
+        }
 
         form
     }
 
+    /// A field's value, whether or not the current layout shows it
+    fn value(&self, key: FieldKey) -> &str {
+        self.fields
+            .iter()
+            .find(|f| f.key == key)
+            .map(|f| f.value.as_str())
+            .or_else(|| self.stash.get(&key).map(|v| v.as_str()))
+            .unwrap_or_default()
+    }
+
+    fn set_value(&mut self, key: FieldKey, value: String) {
+        let value = Zeroizing::new(value);
+        match self.fields.iter_mut().find(|f| f.key == key) {
+            Some(field) => field.value = value,
+            None => {
+                self.stash.insert(key, value);
+            }
+        }
+    }
+
     pub fn is_editing(&self) -> bool {
         self.editing_id.is_some()
     }
@@ -245,32 +366,27 @@ impl CredentialForm {
         form_height.saturating_sub(2) // block borders
     }
 
-    fn active_buffer(&self) -> TextBuffer {
-        let field = &self.fields[self.active_field];
-        let mut buf = TextBuffer::with_content(&field.value);
-        buf.cursor_home();
-        for _ in 0..self.cursor {
-            buf.cursor_right();
-        }
+    /// The active field's value, moved into a buffer for editing so no copy is left behind
+    fn take_buffer(&mut self) -> SecureTextBuffer {
+        let value = std::mem::take(&mut self.fields[self.active_field].value);
+        let mut buf = SecureTextBuffer::from(value);
+        buf.set_cursor(self.cursor);
         buf
     }
 
-    fn apply_buffer(&mut self, buf: TextBuffer) {
-        self.fields[self.active_field].value = buf.content().to_string();
+    fn apply_buffer(&mut self, buf: SecureTextBuffer) {
         self.cursor = buf.cursor();
+        self.fields[self.active_field].value = buf.into_content();
     }
 
     pub fn handle_text_key(&mut self, code: KeyCode, mods: KeyModifiers, area_height: u16) {
         if self.active_field().field_type == FieldType::Select {
             return;
         }
-        let mut buf = self.active_buffer();
-        if !handle_text_key(&mut buf, code, mods) {
-            return;
-        }
-        let is_multiline = self.active_field().field_type == FieldType::MultiLine;
+        let mut buf = self.take_buffer();
+        let handled = handle_text_key(&mut buf, code, mods);
         self.apply_buffer(buf);
-        if is_multiline {
+        if handled && self.active_field().field_type == FieldType::MultiLine {
             self.ensure_visible(Self::form_inner_height(area_height));
         }
     }
@@ -284,8 +400,20 @@ impl CredentialForm {
         } else {
             cycle_type_backward(self.credential_type)
         };
-        self.fields[1].value = self.credential_type.display_name().to_string();
-        self.fields[3].required = is_secret_required(self.credential_type);
+        self.apply_template();
+    }
+
+    /// Swap in the current type's layout, carrying values over by what they hold
+    fn apply_template(&mut self) {
+        let old = std::mem::replace(&mut self.fields, template(self.credential_type));
+        for field in old.into_iter().filter(|f| f.key != FieldKey::Type) {
+            self.stash.insert(field.key, field.value);
+        }
+        for field in &mut self.fields {
+            if let Some(value) = self.stash.remove(&field.key) {
+                field.value = value;
+            }
+        }
     }
 
     pub fn toggle_password_visibility(&mut self) {
@@ -297,40 +425,99 @@ impl CredentialForm {
             let is_empty_required = field.required && field.value.trim().is_empty();
             if is_empty_required { return Err(format!("{} is required", field.label)); }
         }
+        let expires = self.value(FieldKey::Expires).trim();
+        if !expires.is_empty() && expiry::parse_date(expires).is_none() {
+            return Err("Expires must be a date like 2030-12-31".into());
+        }
+        let rotation = self.value(FieldKey::Rotation).trim();
+        if !rotation.is_empty() && !rotation.parse::<u32>().is_ok_and(|d| d > 0) {
+            return Err("Rotate must be a number of days".into());
+        }
+        self.password_policy().map_err(|e| e.to_string())?;
         Ok(())
     }
 
     pub fn get_name(&self) -> &str {
-        &self.fields[0].value
+        self.value(FieldKey::Name)
     }
 
     pub fn get_username(&self) -> Option<String> {
-        trim_to_option(&self.fields[2].value)
+        trim_to_option(self.value(FieldKey::Username))
     }
 
     pub fn get_secret(&self) -> &str {
-        &self.fields[3].value
+        self.value(FieldKey::Secret)
     }
 
     pub fn get_url(&self) -> Option<String> {
-        trim_to_option(&self.fields[4].value)
+        trim_to_option(self.value(FieldKey::Url))
     }
 
     pub fn get_tags(&self) -> Vec<String> {
-        self.fields[5]
-            .value
+        self.value(FieldKey::Tags)
             .split(' ')
             .map(|s| s.trim().to_string())
             .filter(|s| !s.is_empty())
             .collect()
     }
 
-    pub fn get_totp_secret(&self) -> Option<String> {
-        trim_to_option(&self.fields[6].value)
+    pub fn get_totp_secret(&self) -> Option<&str> {
+        trim_to_str(self.value(FieldKey::Totp))
+    }
+
+    pub fn get_notes(&self) -> Option<&str> {
+        trim_to_str(self.value(FieldKey::Notes))
+    }
+
+    pub fn get_expires_at(&self) -> Option<DateTime<Local>> {
+        expiry::parse_date(self.value(FieldKey::Expires)).map(|date| date.with_timezone(&Local))
+    }
+
+    pub fn get_rotate_every(&self) -> Option<u32> {
+        self.value(FieldKey::Rotation).trim().parse().ok().filter(|days| *days > 0)
     }
 
-    pub fn get_notes(&self) -> Option<String> {
-        trim_to_option(&self.fields[7].value)
+    pub fn get_password_rule(&self) -> Option<String> {
+        trim_to_option(self.value(FieldKey::Rule))
+    }
+
+    /// The policy the password rule describes, the default policy without one
+    pub fn password_policy(&self) -> Result<PasswordPolicy, PasswordError> {
+        PasswordPolicy::from_rule(self.value(FieldKey::Rule))
+    }
+
+    /// Replace the secret with a generated one
+    pub fn set_secret(&mut self, secret: String) {
+        self.set_value(FieldKey::Secret, secret);
+        self.cursor = self.fields[self.active_field].value.len();
+    }
+
+    /// Replace the TOTP field, e.g. with the URI read from a QR code
+    pub fn set_totp_secret(&mut self, secret: String) {
+        self.set_value(FieldKey::Totp, secret);
+        self.cursor = self.fields[self.active_field].value.len();
+    }
+
+    /// Fill in a generated SSH keypair; it has no passphrase of its own
+    pub fn set_keypair(&mut self, private_key: String, public_key: String) {
+        self.set_value(FieldKey::Secret, private_key);
+        self.set_value(FieldKey::Extra("Public Key"), public_key);
+        self.set_value(FieldKey::Extra("Passphrase"), String::new());
+        self.cursor = self.fields[self.active_field].value.len();
+    }
+
+    /// The current type's own fields as label/value pairs
+    ///
+    /// Fields of other types are left behind when the type changes; the common
+    /// fields above are kept even when the layout hides them.
+    pub fn get_fields(&self) -> Vec<(&str, &str)> {
+        self.fields
+            .iter()
+            .filter_map(|f| match f.key {
+                FieldKey::Extra(label) => Some((label, f.value.as_str())),
+                _ => None,
+            })
+            .collect()
     }
 }
 
@@ -425,27 +612,22 @@ struct DisplayValue {
 fn compute_select_display(form: &CredentialForm, field: &FormField) -> DisplayValue {
     let icon = form.credential_type.icon();
     DisplayValue {
-        text: format!("{} {}  [Space/Ctrl+Space]", icon, field.value),
+        text: format!("{} {}  [Space/Ctrl+Space]", icon, field.value.as_str()),
         cursor: 0,
     }
 }
 
 fn compute_text_display(form: &CredentialForm, field: &FormField, value_width: usize, is_active: bool) -> DisplayValue {
-    let text = if field.masked && !form.show_password {
-        "•".repeat(field.value.len())
-    } else {
-        field.value.clone()
-    };
-
     let cursor_pos = if is_active { form.cursor } else { 0 };
-    let scroll = if cursor_pos >= value_width.saturating_sub(1) {
-        cursor_pos.saturating_sub(value_width.saturating_sub(2))
+    let (text, cursor_pos) = if field.masked && !form.show_password {
+        mask(&field.value, cursor_pos)
     } else {
-        0
+        // Keys pasted or generated into one-line fields keep their line breaks
+        let before = field.value[..cursor_pos.min(field.value.len())].replace('\n', "↵");
+        (field.value.replace('\n', "↵"), before.len())
     };
 
-    let visible: String = text.chars().skip(scroll).take(value_width).collect();
-    let adjusted_cursor = cursor_pos.saturating_sub(scroll);
+    let (visible, adjusted_cursor) = cursor_window(&text, cursor_pos, value_width);
 
     DisplayValue {
         text: visible,
@@ -547,27 +729,26 @@ fn render_multiline_field(
 
     let text = &field.value;
 
-    // Soft-wrap into visual lines, tracking byte offsets
+    // Soft-wrap into visual lines by display width, tracking byte offsets
     let mut lines: Vec<String> = Vec::new();
     let mut line_starts: Vec<usize> = vec![0];
     let mut current_line = String::new();
-    let mut byte_idx: usize = 0;
 
-    for ch in text.chars() {
-        if ch == '\n' {
-            lines.push(current_line.clone());
-            current_line.clear();
-            byte_idx += ch.len_utf8();
-            line_starts.push(byte_idx);
+    for (byte_idx, g) in text.grapheme_indices(true) {
+        if g == "\n" || g == "\r\n" {
+            lines.push(std::mem::take(&mut current_line));
+            line_starts.push(byte_idx + g.len());
             continue;
         }
-        current_line.push(ch);
-        byte_idx += ch.len_utf8();
-        if current_line.chars().count() >= w {
-            lines.push(current_line.clone());
-            current_line.clear();
+        if display_width(&current_line) + display_width(g) > w {
+            lines.push(std::mem::take(&mut current_line));
             line_starts.push(byte_idx);
         }
+        current_line.push_str(g);
+        if display_width(&current_line) >= w {
+            lines.push(std::mem::take(&mut current_line));
+            line_starts.push(byte_idx + g.len());
+        }
     }
     lines.push(current_line);
 
@@ -606,7 +787,7 @@ fn render_multiline_field(
     // Cursor
     if is_active {
         let line_start = line_starts.get(cursor_line).copied().unwrap_or(0);
-        let cursor_in_line = cursor_pos.saturating_sub(line_start);
+        let cursor_in_line = text.get(line_start..cursor_pos).map_or(0, display_width);
         let cursor_row = cursor_line.saturating_sub(scroll);
         if (cursor_row as u16) < visible_lines {
             let cx = x + cursor_in_line as u16;
@@ -659,3 +840,80 @@ impl<'a> Widget for CredentialFormWidget<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(form: &CredentialForm) -> Vec<&'static str> {
+        form.fields.iter().map(|f| f.label).collect()
+    }
+
+    #[test]
+    fn test_type_templates() {
+        let mut form = CredentialForm::new();
+        form.set_value(FieldKey::Username, "admin".into());
+        form.set_value(FieldKey::Url, "https://db.local".into());
+        form.active_field = 1;
+
+        // Password -> ApiKey -> SshKey -> Certificate -> Note -> Database
+        for _ in 0..5 {
+            form.cycle_type(true);
+        }
+        assert_eq!(form.credential_type, CredentialType::Database);
+        assert_eq!(
+            labels(&form),
+            ["Name", "Type", "Host", "Port", "Database", "User", "Password", "Password rule", "Tags (multiple)", "Expires", "Rotate (days)", "Notes"]
+        );
+        form.set_value(FieldKey::Extra("Host"), "db.local".into());
+
+        // Hidden common fields are kept, fields of other types are not saved
+        assert_eq!(form.get_url().as_deref(), Some("https://db.local"));
+        assert_eq!(form.get_username().as_deref(), Some("admin"));
+        assert_eq!(form.get_fields()[0], ("Host", "db.local"));
+
+        form.cycle_type(false);
+        assert!(form.get_fields().is_empty());
+        form.cycle_type(true);
+        assert_eq!(form.get_fields()[0].1, "db.local");
+
+        assert!(is_secret_field("Passphrase"));
+        assert!(!is_secret_field("Host"));
+    }
+
+    #[test]
+    fn test_expiry_and_rotation() {
+        let mut form = CredentialForm::for_edit(EditFormParams {
+            id: "id".into(),
+            name: "Stripe".into(),
+            cred_type: CredentialType::ApiKey,
+            username: None,
+            secret: "sk_live".into(),
+            url: None,
+            tags: vec![],
+            totp_secret: None,
+            notes: None,
+            fields: vec![(EXPIRES_FIELD.to_string(), "2030-12-31".to_string())],
+            expires_at: None,
+            rotate_every: Some(90),
+            password_rule: Some("16 nosymbols".into()),
+            previous_view: View::List,
+        });
+
+        // The old Expires field moves to the expiry date
+        assert_eq!(form.get_expires_at().map(expiry::format_date).as_deref(), Some("2030-12-31"));
+        assert!(form.get_fields().is_empty());
+        assert_eq!(form.get_rotate_every(), Some(90));
+
+        form.set_value(FieldKey::Rotation, "0".into());
+        assert!(form.validate().is_err());
+        form.set_value(FieldKey::Rotation, String::new());
+        form.set_value(FieldKey::Expires, "31/12/2030".into());
+        assert!(form.validate().is_err());
+        form.set_value(FieldKey::Expires, String::new());
+
+        assert_eq!(form.password_policy().unwrap().length, 16);
+        form.set_value(FieldKey::Rule, "short".into());
+        assert!(form.validate().is_err());
+    }
+}
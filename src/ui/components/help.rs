@@ -289,40 +289,138 @@ fn help_sections() -> Vec<(&'static str, Vec<(&'static str, &'static str)>)> {
             ("k / ↑", "Move up"),
             ("gg", "Go to top"),
             ("G", "Go to bottom"),
+            ("{ / }", "Previous / next block"),
+            ("5j / 3dd / 12G", "Counts: repeat, delete, go to"),
+            ("m<a-z> / '<a-z>", "Mark credential / jump to mark"),
             ("Ctrl+d", "Half page down"),
             ("Ctrl+u", "Half page up"),
             ("Ctrl+f", "Page down"),
             ("Ctrl+b", "Page up"),
+            ("J / K", "Scroll details down / up"),
         ]),
         ("Actions", vec![
             ("l / Enter", "View details"),
             ("n", "New credential"),
             ("e", "Edit credential"),
             ("dd / x", "Delete credential"),
+            ("E", "Export just this credential"),
+            ("Ctrl+g / Ctrl+r", "Generate ed25519/RSA keypair (SSH key form)"),
+            ("Ctrl+g", "Generate password by the password rule (other forms)"),
+            ("Ctrl+o", "Read TOTP secret from the QR code image path in its field"),
         ]),
         ("Clipboard", vec![
             ("yy / c", "Copy password/secret"),
             ("u", "Copy username"),
+            ("Y", "Copy username, then password"),
             ("T", "Copy TOTP code"),
             ("Ctrl+t", "Copy TOTP secret"),
+            ("P", "Copy SSH public key"),
+            ("A", "Add SSH key to ssh-agent"),
+            ("R", "Regenerate password by its rule and copy it"),
         ]),
         ("View", vec![
             ("Ctrl+s", "Toggle password"),
-            ("/", "Search"),
+            ("z", "Toggle split/full detail"),
+            ("o", "Cycle sort order"),
+            ("Space", "Mark for bulk actions"),
+            ("V", "Visual mode: select a range"),
+            ("/", "Search (/~ for fuzzy)"),
             ("i", "Show logs"),
+            ("H", "Show change history"),
+            ("I", "Show credential's audit activity"),
             ("t", "Show tags"),
+            ("f", "Filter by type"),
         ]),
         ("Commands", vec![
             (":", "Command mode"),
+            ("Tab / Up / Down", "Complete / recall commands"),
             (":q", "Quit"),
             (":clear", "Clear message"),
             (":changepw", "Change master key"),
             (":audit", "Verify audit log integrity"),
+            (":check", "Check database integrity, ciphertexts and audit HMACs"),
             (":log", "View logs"),
+            (":history", "Changes to selected credential"),
+            (":activity", "Audit entries of selected credential"),
+            (":stats", "Vault statistics"),
             (":tag", "View tags"),
+            (":type", "Filter by type"),
+            (":filter [type=..] [tag=..]", "Filter by type and tag; alone clears filters"),
+            (":goto NAME", "Select credential by name"),
+            (":expiring", "Show expired or expiring"),
+            (":rotation", "Show due for rotation"),
             (":new", "New credential"),
-            (":gen", "Generate password"),
+            (":delete", "Move credential to trash"),
+            (":lock", "Lock vault"),
+            (":gen [pronounceable]", "Generate password"),
+            (":sshadd", "Add SSH key to ssh-agent"),
+            (":regen", "Regenerate password by its rule and copy it"),
+            (":copyboth", "Copy username, then password"),
             (":export", "Export Credentials"),
+            (":exportone", "Export the selected credential"),
+            (":import", "Import browser CSV or vault bundle"),
+            (":share [file]", "Share the selected credential as a QR code"),
+            (":receive [file]", "Import a shared credential"),
+            (":duplicates", "Find duplicate credentials"),
+            (":report [age|reuse|weak]", "Security reports"),
+            (":backup [name]", "Create a named backup"),
+            (":restore [file]", "Restore a backup"),
+            (":trash", "Restore or purge deleted credentials"),
+            (":addtag <tag>", "Tag marked credentials"),
+            (":rmtag <tag>", "Untag marked credentials"),
+            (":renametag", "Rename a tag everywhere: <old> <new>"),
+            (":mergetag", "Merge tags: <tag>... <into>"),
+            (":deltag <tag>", "Remove a tag everywhere"),
+            (":settype <type>", "Change type of marked credentials"),
+            (":sort <order>", "Sort by name/updated/created/type/strength"),
+            (":set [key=value]", "Show or change a setting"),
+            (":set! key=value", "Change a setting and save it"),
+            (":keyfile [path|off]", "Require a keyfile to unlock, or stop"),
+            (":yubikey [1|2|off|recovery]", "Require a YubiKey to unlock, or show its recovery code"),
+            (":tpm [on|off|recovery]", "Bind the vault to this machine's TPM"),
+            (":encrypt-db [on|off]", "Encrypt the whole database file (SQLCipher)"),
+            (":encrypt-metadata [on|off]", "Encrypt names, usernames, URLs and tags"),
+            (":vacuum", "Compact the database, zeroing deleted rows"),
+            (":fido2 [on|off]", "Unlock with a tap on a FIDO2 security key"),
+            (":forget-session", "Ask for the password again on the next start"),
+            (":hardening", "Show which process hardening steps worked"),
+            (":wipe", "Destroy the vault, backups and config"),
+            (":duress [phrase|off]", "Password that wipes everything at unlock"),
+            (":kdf [apply]", "Show or apply the Argon2 settings"),
+            (":kdf calibrate [ms]", "Tune Argon2 to this machine"),
+            (":rotate-key", "Re-encrypt everything under a new DEK"),
+        ]),
+        ("Tags", vec![
+            ("Space", "Select tag for filter or merge"),
+            ("-", "Exclude tag from filter"),
+            ("a", "Match all or any selected tags"),
+            ("Enter", "Filter by selected tags"),
+            ("r", "Rename tag"),
+            ("m", "Merge selected tags into another"),
+            ("d / x", "Delete tag from all credentials"),
+        ]),
+        ("Types", vec![
+            ("Space", "Select type for filter"),
+            ("Enter", "Filter by selected types"),
+        ]),
+        ("Trash", vec![
+            ("Enter / r", "Restore selected credential"),
+            ("d / x", "Delete forever"),
+            ("D", "Empty trash"),
+        ]),
+        ("Duplicates", vec![
+            ("d / x", "Delete selected entry"),
+            ("m", "Merge group into selected"),
+        ]),
+        ("Report", vec![
+            ("Enter / l", "Open selected credential"),
+            ("e", "Edit selected credential"),
+        ]),
+        ("Import Review", vec![
+            ("Ctrl+r", "Review conflicts"),
+            ("Space", "Cycle action"),
+            ("s/o/b/m", "Skip/Overwrite/Both/Merge"),
+            ("S/O/B/M", "Apply to all conflicts"),
         ]),
         ("Other", vec![
             ("?", "Show this help"),
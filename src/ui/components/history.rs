@@ -0,0 +1,128 @@
+//! Credential history screen and state
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Clear, Widget},
+};
+
+use crate::vault::history::{ChangeKind, Revision};
+
+use super::layout::{centered_rect, create_popup_block, render_empty_message, truncate_with_ellipsis};
+use super::scroll::{render_v_scroll_indicator, ScrollState};
+
+/// A revision's date line followed by one line per change
+enum HistoryRow {
+    Date(String),
+    Change(ChangeKind, String),
+}
+
+#[derive(Default)]
+pub struct HistoryState {
+    pub scroll: ScrollState,
+    pub name: String,
+    rows: Vec<HistoryRow>,
+}
+
+impl HistoryState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_history(&mut self, name: &str, revisions: &[Revision]) {
+        self.name = name.to_string();
+        self.rows = revisions
+            .iter()
+            .flat_map(|rev| {
+                let date = HistoryRow::Date(rev.changed_at.format("%d-%b-%Y %H:%M").to_string());
+                let changes = rev.changes.iter().map(|c| HistoryRow::Change(c.kind, c.describe()));
+                std::iter::once(date).chain(changes)
+            })
+            .collect();
+        self.scroll.reset();
+    }
+
+    pub fn scroll_up(&mut self, amount: usize) {
+        self.scroll.scroll_up(amount);
+    }
+
+    pub fn scroll_down(&mut self, amount: usize, max: usize) {
+        self.scroll.scroll_down(amount, max);
+    }
+
+    pub fn home(&mut self) {
+        self.scroll.home();
+    }
+
+    pub fn end(&mut self, max: usize) {
+        self.scroll.end(max);
+    }
+
+    pub fn max_scroll(&self, visible_height: u16) -> usize {
+        self.rows.len().saturating_sub(visible_height as usize)
+    }
+}
+
+pub struct HistoryScreen<'a> {
+    state: &'a HistoryState,
+}
+
+impl<'a> HistoryScreen<'a> {
+    pub fn new(state: &'a HistoryState) -> Self {
+        Self { state }
+    }
+
+    pub fn visible_height(area: Rect) -> u16 {
+        let popup = centered_rect(70, 75, area);
+        popup.height.saturating_sub(2)
+    }
+}
+
+impl Widget for HistoryScreen<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let popup = centered_rect(70, 75, area);
+        Clear.render(popup, buf);
+
+        let title = format!(" History: {} ", self.state.name);
+        let block = create_popup_block(&title, Color::Blue);
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        if self.state.rows.is_empty() {
+            render_empty_message(inner, buf, "No changes recorded");
+            return;
+        }
+
+        let visible = inner.height as usize;
+        let offset = self.state.scroll.v_scroll;
+        for (i, row) in self.state.rows.iter().enumerate().skip(offset).take(visible) {
+            render_history_row(inner, buf, inner.y + (i - offset) as u16, row);
+        }
+
+        let max_v = self.state.max_scroll(inner.height);
+        if max_v > 0 {
+            render_v_scroll_indicator(buf, &popup, offset, max_v, Color::Blue);
+        }
+    }
+}
+
+fn render_history_row(inner: Rect, buf: &mut Buffer, y: u16, row: &HistoryRow) {
+    let max_width = (inner.width as usize).saturating_sub(4);
+    match row {
+        HistoryRow::Date(date) => {
+            let style = Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD);
+            buf.set_string(inner.x + 1, y, date, style);
+        }
+        HistoryRow::Change(kind, text) => {
+            let (marker, color) = match kind {
+                ChangeKind::Added => ("+", Color::Green),
+                ChangeKind::Removed => ("-", Color::Red),
+                ChangeKind::Changed => ("~", Color::Yellow),
+            };
+            buf.set_string(inner.x + 2, y, marker, Style::default().fg(color));
+            let text = truncate_with_ellipsis(text, max_width.saturating_sub(2));
+            buf.set_string(inner.x + 4, y, &text, Style::default().fg(Color::White));
+        }
+    }
+}
@@ -0,0 +1,596 @@
+//! Import Dialog Component
+//!
+//! Dialog for selecting an import source file and previewing the result.
+//! Vault bundles (by extension) also take a passphrase and a merge/replace mode;
+//! encrypted exports (sealed, gpg or age) and shared credentials, as a `.share`
+//! file or a QR code screenshot, take just the passphrase.
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Block, BorderType, Borders, Clear, Widget},
+};
+use chrono::{DateTime, Local};
+use crossterm::event::{KeyCode, KeyModifiers};
+use secrecy::SecretString;
+
+use crate::vault::bundle::{BundleImportMode, BUNDLE_EXTENSION};
+use crate::vault::export::ExportEncryption;
+use crate::vault::import::{ConflictResolution, ImportAction, ImportFormat, ImportPlan, ImportSummary};
+use crate::vault::share::SHARE_EXTENSION;
+use crate::input::{handle_text_key, SecureTextBuffer, TextBuffer, TextEditing};
+
+use super::export::{render_input_field, render_select_field};
+use super::layout::{
+    centered_rect_fixed, create_popup_block, highlight_row, render_separator_line,
+    truncate_with_ellipsis,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportField {
+    Format,
+    Path,
+    Passphrase,
+    Mode,
+}
+
+impl ImportField {
+    const CSV: &'static [Self] = &[Self::Format, Self::Path];
+    const ENCRYPTED: &'static [Self] = &[Self::Format, Self::Path, Self::Passphrase];
+    const BUNDLE: &'static [Self] = &[Self::Format, Self::Path, Self::Passphrase, Self::Mode];
+
+    fn step(self, fields: &[Self], forward: bool) -> Self {
+        let Some(pos) = fields.iter().position(|f| *f == self) else { return Self::Path };
+        let len = fields.len();
+        fields[if forward { (pos + 1) % len } else { (pos + len - 1) % len }]
+    }
+}
+
+/// What a verified bundle holds, shown before it is applied
+#[derive(Debug, Clone)]
+pub struct BundlePreview {
+    pub created_at: DateTime<Local>,
+    pub credentials: usize,
+    pub audit_logs: usize,
+    pub tampered_logs: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportDialog {
+    pub active_field: ImportField,
+    /// `None` means auto-detect from the CSV header
+    pub format: Option<ImportFormat>,
+    pub path: TextBuffer,
+    passphrase: SecureTextBuffer,
+    pub bundle_mode: BundleImportMode,
+    pub error: Option<String>,
+    plan: Option<ImportPlan>,
+    bundle_preview: Option<BundlePreview>,
+    /// Cursor within the conflict review list
+    pub review_selected: usize,
+}
+
+impl Default for ImportDialog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ImportDialog {
+    pub fn new() -> Self {
+        Self {
+            active_field: ImportField::Path,
+            format: None,
+            path: TextBuffer::with_content(default_import_path()),
+            passphrase: SecureTextBuffer::new(),
+            bundle_mode: BundleImportMode::Merge,
+            error: None,
+            plan: None,
+            bundle_preview: None,
+            review_selected: 0,
+        }
+    }
+
+    /// Opened on `path` rather than the default CSV
+    pub fn with_path(path: &str) -> Self {
+        Self { path: TextBuffer::with_content(path.to_string()), ..Self::new() }
+    }
+
+    pub fn next_field(&mut self) {
+        self.active_field = self.active_field.step(self.fields(), true);
+        self.update_cursor_to_end();
+    }
+
+    pub fn prev_field(&mut self) {
+        self.active_field = self.active_field.step(self.fields(), false);
+        self.update_cursor_to_end();
+    }
+
+    fn fields(&self) -> &'static [ImportField] {
+        if self.is_bundle() {
+            ImportField::BUNDLE
+        } else if self.takes_passphrase() {
+            ImportField::ENCRYPTED
+        } else {
+            ImportField::CSV
+        }
+    }
+
+    fn update_cursor_to_end(&mut self) {
+        match self.active_field {
+            ImportField::Path => self.path.cursor_end(),
+            ImportField::Passphrase => self.passphrase.cursor_end(),
+            _ => {}
+        }
+    }
+
+    /// Bundles are recognised by extension; everything else is a browser CSV
+    /// or a vault JSON export
+    pub fn is_bundle(&self) -> bool {
+        self.path.content().trim().ends_with(BUNDLE_EXTENSION)
+    }
+
+    /// How an encrypted export was encrypted, by extension; these take the
+    /// export passphrase
+    pub fn encryption(&self) -> Option<ExportEncryption> {
+        let path = self.path.content().trim();
+        [ExportEncryption::Internal, ExportEncryption::Gpg, ExportEncryption::Age]
+            .into_iter()
+            .find(|e| path.ends_with(e.file_extension()))
+    }
+
+    /// A credential shared with `:share`, saved as a file or a QR code screenshot
+    pub fn is_share(&self) -> bool {
+        let path = self.path.content().trim();
+        path.ends_with(SHARE_EXTENSION) || path.ends_with(".png")
+    }
+
+    /// Encrypted exports and shares; bundles have fields of their own
+    fn takes_passphrase(&self) -> bool {
+        self.encryption().is_some() || self.is_share()
+    }
+
+    pub fn toggle_bundle_mode(&mut self) {
+        self.bundle_mode = self.bundle_mode.toggle();
+    }
+
+    pub fn get_passphrase(&self) -> SecretString {
+        SecretString::from(self.passphrase.content().to_string())
+    }
+
+    pub fn cycle_format_forward(&mut self) {
+        if self.is_bundle() || self.takes_passphrase() {
+            return;
+        }
+        self.format = match self.format {
+            None => Some(ImportFormat::Chrome),
+            Some(ImportFormat::Chrome) => Some(ImportFormat::Firefox),
+            Some(ImportFormat::Firefox) => None,
+        };
+        self.invalidate_preview();
+    }
+
+    pub fn cycle_format_backward(&mut self) {
+        if self.is_bundle() || self.takes_passphrase() {
+            return;
+        }
+        self.format = match self.format {
+            None => Some(ImportFormat::Firefox),
+            Some(ImportFormat::Chrome) => None,
+            Some(ImportFormat::Firefox) => Some(ImportFormat::Chrome),
+        };
+        self.invalidate_preview();
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        match self.active_field {
+            ImportField::Path => self.path.insert_char(c),
+            ImportField::Passphrase => self.passphrase.insert_char(c),
+            _ => return,
+        }
+        self.invalidate_preview();
+    }
+
+    pub fn handle_text_key(&mut self, code: KeyCode, mods: KeyModifiers) {
+        let changed = match self.active_field {
+            ImportField::Path => {
+                let before = self.path.content().to_string();
+                handle_text_key(&mut self.path, code, mods);
+                self.path.content() != before
+            }
+            ImportField::Passphrase => {
+                let before = self.passphrase.len();
+                handle_text_key(&mut self.passphrase, code, mods);
+                self.passphrase.len() != before
+            }
+            _ => false,
+        };
+        if changed {
+            self.invalidate_preview();
+        }
+    }
+
+    /// Any edit makes the preview stale, so the next Enter re-plans
+    fn invalidate_preview(&mut self) {
+        self.plan = None;
+        self.bundle_preview = None;
+        self.error = None;
+    }
+
+    pub fn set_bundle_preview(&mut self, preview: BundlePreview) {
+        self.error = None;
+        self.bundle_preview = Some(preview);
+    }
+
+    pub fn bundle_preview(&self) -> Option<&BundlePreview> {
+        self.bundle_preview.as_ref()
+    }
+
+    pub fn set_plan(&mut self, plan: ImportPlan) {
+        self.error = None;
+        self.plan = Some(plan);
+        self.review_selected = 0;
+    }
+
+    pub fn plan(&self) -> Option<&ImportPlan> {
+        self.plan.as_ref()
+    }
+
+    pub fn conflict_count(&self) -> usize {
+        self.plan.as_ref().map_or(0, |p| p.conflict_indices().len())
+    }
+
+    pub fn review_down(&mut self) {
+        if self.review_selected + 1 < self.conflict_count() {
+            self.review_selected += 1;
+        }
+    }
+
+    pub fn review_up(&mut self) {
+        self.review_selected = self.review_selected.saturating_sub(1);
+    }
+
+    fn selected_item_index(&self) -> Option<usize> {
+        self.plan.as_ref()?.conflict_indices().get(self.review_selected).copied()
+    }
+
+    fn selected_resolution(&self) -> Option<ConflictResolution> {
+        let idx = self.selected_item_index()?;
+        match &self.plan.as_ref()?.items.get(idx)?.1 {
+            ImportAction::Conflict(c) => Some(c.resolution),
+            _ => None,
+        }
+    }
+
+    pub fn set_selected_resolution(&mut self, resolution: ConflictResolution) {
+        let Some(idx) = self.selected_item_index() else { return };
+        if let Some(plan) = self.plan.as_mut() {
+            plan.set_resolution(idx, resolution);
+        }
+    }
+
+    pub fn cycle_selected_resolution(&mut self) {
+        let Some(current) = self.selected_resolution() else { return };
+        self.set_selected_resolution(current.next());
+    }
+
+    pub fn set_all_resolutions(&mut self, resolution: ConflictResolution) {
+        if let Some(plan) = self.plan.as_mut() {
+            plan.set_all_resolutions(resolution);
+        }
+    }
+
+    pub fn take_plan(&mut self) -> Option<ImportPlan> {
+        self.plan.take()
+    }
+
+    pub fn preview(&self) -> Option<ImportSummary> {
+        self.plan.as_ref().map(|p| p.summary())
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.path.content().trim().is_empty() {
+            return Err("Input path is required".into());
+        }
+        if self.is_bundle() && self.passphrase.is_empty() {
+            return Err("Passphrase required for vault bundle".into());
+        }
+        // gpg can decrypt with a secret key gpg-agent already holds
+        let needs_passphrase = self.encryption().is_some_and(|e| e != ExportEncryption::Gpg);
+        if needs_passphrase && self.passphrase.is_empty() {
+            return Err("Passphrase required for encrypted export".into());
+        }
+        if self.is_share() && self.passphrase.is_empty() {
+            return Err("Passphrase required for shared credential".into());
+        }
+        Ok(())
+    }
+}
+
+fn default_import_path() -> String {
+    match dirs::home_dir() {
+        Some(home) => home.join("passwords.csv").to_string_lossy().into_owned(),
+        None => "./passwords.csv".to_string(),
+    }
+}
+
+fn format_display(format: Option<ImportFormat>) -> &'static str {
+    match format {
+        None => "Auto-detect",
+        Some(f) => f.display_name(),
+    }
+}
+
+pub struct ImportDialogWidget<'a> {
+    dialog: &'a ImportDialog,
+}
+
+impl<'a> ImportDialogWidget<'a> {
+    pub fn new(dialog: &'a ImportDialog) -> Self {
+        Self { dialog }
+    }
+}
+
+impl Widget for ImportDialogWidget<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let dialog = self.dialog;
+        // Two rows for each field past Format and Path
+        let extra_rows = (dialog.fields().len() as u16 - 2) * 2;
+        let form_area = calculate_form_area(area, dialog.error.is_some(), extra_rows);
+        let inner = render_form_block(buf, form_area);
+
+        let label_width = 14u16;
+        let value_width = inner.width.saturating_sub(label_width + 1);
+
+        let format = if dialog.is_bundle() {
+            "Vault bundle"
+        } else if dialog.is_share() {
+            "Shared credential"
+        } else if let Some(encryption) = dialog.encryption() {
+            match encryption {
+                ExportEncryption::Gpg => "GPG export",
+                ExportEncryption::Age => "age export",
+                _ => "Sealed export",
+            }
+        } else {
+            format_display(dialog.format)
+        };
+        render_select_field(
+            buf,
+            inner.x,
+            inner.y,
+            "Format:",
+            format,
+            dialog.active_field == ImportField::Format,
+            label_width,
+            value_width,
+        );
+        render_input_field(
+            buf,
+            inner.x,
+            inner.y + 2,
+            "Path:",
+            dialog.path.content(),
+            dialog.path.cursor(),
+            dialog.active_field == ImportField::Path,
+            false,
+            label_width,
+            value_width,
+            true,
+        );
+
+        let y = if dialog.is_bundle() {
+            let y = render_bundle_fields(dialog, buf, inner.x, inner.y + 4, label_width, value_width);
+            render_bundle_preview(dialog, buf, inner.x, y, label_width)
+        } else if dialog.takes_passphrase() {
+            render_passphrase(dialog, buf, inner.x, inner.y + 4, label_width, value_width);
+            render_preview(dialog, buf, inner.x, inner.y + 6, label_width)
+        } else {
+            render_preview(dialog, buf, inner.x, inner.y + 4, label_width)
+        };
+        if let Some(err) = &dialog.error {
+            buf.set_string(inner.x, y, err, Style::default().fg(Color::Red));
+        }
+    }
+}
+
+fn render_preview(dialog: &ImportDialog, buf: &mut Buffer, x: u16, y: u16, label_width: u16) -> u16 {
+    let label_style = Style::default().fg(Color::Gray);
+    buf.set_string(x, y, "Preview:", label_style);
+
+    let Some(summary) = dialog.preview() else {
+        let hint = "Press Enter to scan the file";
+        buf.set_string(x + label_width, y, hint, Style::default().fg(Color::DarkGray));
+        return y + 2;
+    };
+
+    let counts = [
+        (summary.created, "new", Color::Green),
+        (summary.merged, "merged", Color::Yellow),
+        (summary.overwritten, "overwritten", Color::Red),
+        (summary.skipped, "skipped", Color::DarkGray),
+    ];
+    let mut cx = x + label_width;
+    for (count, label, color) in counts {
+        let text = format!("{} {}  ", count, label);
+        buf.set_string(cx, y, &text, Style::default().fg(color).add_modifier(Modifier::BOLD));
+        cx += text.len() as u16;
+    }
+
+    let confirm = if summary.conflicts > 0 {
+        format!("{} conflict(s), Ctrl+r to review, Enter to import", summary.conflicts)
+    } else {
+        "Press Enter again to import".to_string()
+    };
+    buf.set_string(x + label_width, y + 1, &confirm, Style::default().fg(Color::DarkGray));
+    y + 2
+}
+
+fn render_passphrase(dialog: &ImportDialog, buf: &mut Buffer, x: u16, y: u16, label_width: u16, value_width: u16) {
+    render_input_field(
+        buf,
+        x,
+        y,
+        "Passphrase:",
+        dialog.passphrase.content(),
+        dialog.passphrase.cursor(),
+        dialog.active_field == ImportField::Passphrase,
+        true,
+        label_width,
+        value_width,
+        true,
+    );
+}
+
+fn render_bundle_fields(dialog: &ImportDialog, buf: &mut Buffer, x: u16, y: u16, label_width: u16, value_width: u16) -> u16 {
+    render_passphrase(dialog, buf, x, y, label_width, value_width);
+    render_select_field(
+        buf,
+        x,
+        y + 2,
+        "Mode:",
+        dialog.bundle_mode.display_name(),
+        dialog.active_field == ImportField::Mode,
+        label_width,
+        value_width,
+    );
+    y + 4
+}
+
+fn render_bundle_preview(dialog: &ImportDialog, buf: &mut Buffer, x: u16, y: u16, label_width: u16) -> u16 {
+    buf.set_string(x, y, "Preview:", Style::default().fg(Color::Gray));
+
+    let Some(preview) = dialog.bundle_preview() else {
+        let hint = "Press Enter to verify the bundle";
+        buf.set_string(x + label_width, y, hint, Style::default().fg(Color::DarkGray));
+        return y + 2;
+    };
+
+    let summary = format!(
+        "{} credentials, {} log entries ({})",
+        preview.credentials,
+        preview.audit_logs,
+        preview.created_at.format("%Y-%m-%d")
+    );
+    let bold = |color| Style::default().fg(color).add_modifier(Modifier::BOLD);
+    buf.set_string(x + label_width, y, &summary, bold(Color::Green));
+
+    let (confirm, color) = match (preview.tampered_logs, dialog.bundle_mode) {
+        (0, BundleImportMode::Merge) => ("Press Enter again to merge".to_string(), Color::DarkGray),
+        (0, BundleImportMode::Replace) => ("Enter again replaces this vault (backed up first)".to_string(), Color::Yellow),
+        (n, _) => (format!("Warning: {} log entries fail verification", n), Color::Red),
+    };
+    buf.set_string(x + label_width, y + 1, &confirm, Style::default().fg(color));
+    y + 2
+}
+
+fn calculate_form_area(area: Rect, has_error: bool, extra_rows: u16) -> Rect {
+    let content_area = Rect::new(area.x, area.y, area.width, area.height.saturating_sub(2));
+    let form_width = 60u16.min(content_area.width.saturating_sub(4));
+    let content_height = if has_error { 9u16 } else { 8u16 } + extra_rows;
+    let remainder = (content_area.height.saturating_sub(content_height)) % 2;
+    let form_height = (content_height + remainder).min(content_area.height);
+    let form_x = content_area.x + (content_area.width.saturating_sub(form_width)) / 2;
+    let form_y = content_area.y + (content_area.height.saturating_sub(form_height)) / 2;
+    Rect::new(form_x, form_y, form_width, form_height)
+}
+
+fn render_form_block(buf: &mut Buffer, form_area: Rect) -> Rect {
+    Clear.render(form_area, buf);
+
+    let block = Block::default()
+        .title(" Import Credentials ")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(form_area);
+    block.render(form_area, buf);
+    inner
+}
+
+/// Per-item conflict resolution screen
+pub struct ImportReviewWidget<'a> {
+    dialog: &'a ImportDialog,
+}
+
+impl<'a> ImportReviewWidget<'a> {
+    pub fn new(dialog: &'a ImportDialog) -> Self {
+        Self { dialog }
+    }
+}
+
+impl Widget for ImportReviewWidget<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let Some(plan) = self.dialog.plan() else { return };
+        let conflicts = plan.conflict_indices();
+
+        let height = (conflicts.len() as u16 + 4).min(area.height.saturating_sub(2).min(20));
+        let width = 90u16.min(area.width.saturating_sub(4));
+        let popup = centered_rect_fixed(width, height, area, true);
+        Clear.render(popup, buf);
+
+        let title = format!(" Resolve Conflicts ({}) ", conflicts.len());
+        let block = create_popup_block(&title, Color::Cyan);
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        render_review_header(inner, buf);
+        render_separator_line(buf, inner.x, inner.y + 1, inner.width);
+
+        let visible = inner.height.saturating_sub(2) as usize;
+        let selected = self.dialog.review_selected;
+        let offset = if selected >= visible { selected - visible + 1 } else { 0 };
+
+        for (row, &item_idx) in conflicts.iter().enumerate().skip(offset).take(visible) {
+            let y = inner.y + 2 + (row - offset) as u16;
+            render_review_row(plan, item_idx, inner, buf, y, row == selected);
+        }
+    }
+}
+
+fn render_review_header(inner: Rect, buf: &mut Buffer) {
+    let style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+    buf.set_string(inner.x, inner.y, "ACTION", style);
+    buf.set_string(inner.x + 12, inner.y, "IMPORTED -> EXISTING", style);
+}
+
+fn render_review_row(plan: &ImportPlan, item_idx: usize, inner: Rect, buf: &mut Buffer, y: u16, is_cursor: bool) {
+    let Some((cred, ImportAction::Conflict(conflict))) = plan.items.get(item_idx) else { return };
+
+    if is_cursor {
+        highlight_row(buf, inner.x, y, inner.width);
+    }
+    let bg = if is_cursor { Color::DarkGray } else { Color::Reset };
+
+    let action_style = Style::default().fg(resolution_color(conflict.resolution)).bg(bg);
+    buf.set_string(inner.x, y, conflict.resolution.display_name(), action_style);
+
+    let text = format!(
+        "{} -> {} ({})",
+        with_username(&cred.name, cred.username.as_deref()),
+        with_username(&conflict.existing_name, conflict.existing_username.as_deref()),
+        conflict.reason.display_name(),
+    );
+    let max_width = (inner.width as usize).saturating_sub(12);
+    let style = Style::default().fg(Color::White).bg(bg);
+    buf.set_string(inner.x + 12, y, truncate_with_ellipsis(&text, max_width), style);
+}
+
+fn with_username(name: &str, username: Option<&str>) -> String {
+    match username {
+        Some(u) => format!("{} [{}]", name, u),
+        None => name.to_string(),
+    }
+}
+
+fn resolution_color(resolution: ConflictResolution) -> Color {
+    match resolution {
+        ConflictResolution::Skip => Color::DarkGray,
+        ConflictResolution::Overwrite => Color::Red,
+        ConflictResolution::KeepBoth => Color::Green,
+        ConflictResolution::Merge => Color::Yellow,
+    }
+}
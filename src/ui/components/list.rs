@@ -1,6 +1,11 @@
 //! List View Component
 //!
-//! Displays credentials in a scrollable list.
+//! Displays credentials in a scrollable list. Rows are built only for the
+//! window on screen, so scrolling costs the same with ten credentials or
+//! ten thousand.
+
+use std::collections::{HashMap, HashSet};
+use std::ops::RangeInclusive;
 
 use ratatui::{
     buffer::Buffer,
@@ -10,15 +15,69 @@ use ratatui::{
     widgets::{Block, List, ListItem, ListState, StatefulWidget, Widget},
 };
 
-use crate::db::models::CredentialType;
+use crate::db::models::{Credential, CredentialType};
 use crate::ui::renderer::Renderer;
+use crate::vault::search::{self, SearchMode};
 
-#[derive(Debug, Clone)]
-pub struct CredentialItem {
-    pub id: String,
-    pub name: String,
-    pub username: Option<String>,
+use super::tags::TagColors;
+
+/// One row of the list, borrowed from its credential for the frame it is drawn in
+#[derive(Debug, Clone, Copy)]
+pub struct CredentialItem<'a> {
+    pub id: &'a str,
+    pub name: &'a str,
+    pub username: Option<&'a str>,
     pub credential_type: CredentialType,
+    pub tags: &'a [String],
+}
+
+impl<'a> From<&'a Credential> for CredentialItem<'a> {
+    fn from(cred: &'a Credential) -> Self {
+        Self {
+            id: &cred.id,
+            name: &cred.name,
+            username: cred.username.as_deref(),
+            credential_type: cred.credential_type,
+            tags: &cred.tags,
+        }
+    }
+}
+
+/// Order of the credential list, chosen with `:sort` or cycled with `o`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    #[default]
+    Name,
+    /// Most recently updated first
+    Updated,
+    /// Most recently created first
+    Created,
+    Type,
+    /// Weakest passwords first, other credential types last
+    Strength,
+}
+
+impl SortOrder {
+    pub const ALL: [SortOrder; 5] = [Self::Name, Self::Updated, Self::Created, Self::Type, Self::Strength];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::Updated => "updated",
+            Self::Created => "created",
+            Self::Type => "type",
+            Self::Strength => "strength",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|order| order.name() == name)
+    }
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|o| *o == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -26,6 +85,13 @@ pub struct CredentialItem {
 pub struct ListViewState {
     pub selected: Option<usize>,
     pub total: usize,
+    pub sort: SortOrder,
+    /// IDs of credentials marked for a bulk operation
+    pub marked: HashSet<String>,
+    /// Where visual mode started; the range runs from here to the cursor
+    pub visual_anchor: Option<usize>,
+    /// Credential IDs set with `m<letter>`, for `'<letter>` to jump back to
+    pub jump_marks: HashMap<char, String>,
     list_state: ListState,
 }
 
@@ -48,6 +114,35 @@ impl ListViewState {
         self.selected
     }
 
+    pub fn toggle_mark(&mut self, id: &str) {
+        if !self.marked.remove(id) {
+            self.marked.insert(id.to_string());
+        }
+    }
+
+    pub fn is_marked(&self, id: &str) -> bool {
+        self.marked.contains(id)
+    }
+
+    /// Indices covered by the visual range, if visual mode is active
+    pub fn visual_range(&self) -> Option<RangeInclusive<usize>> {
+        let anchor = self.visual_anchor?;
+        let cursor = self.selected?;
+        Some(anchor.min(cursor)..=anchor.max(cursor))
+    }
+
+    fn in_selection(&self, index: usize, id: &str) -> bool {
+        self.is_marked(id) || self.visual_range().is_some_and(|r| r.contains(&index))
+    }
+
+    /// Marked items plus any unmarked ones in the visual range
+    pub fn selection_count(&self, items: &[Credential]) -> usize {
+        let in_range = self
+            .visual_range()
+            .map_or(0, |r| items.get(r).unwrap_or_default().iter().filter(|i| !self.is_marked(&i.id)).count());
+        self.marked.len() + in_range
+    }
+
     pub fn set_total(&mut self, total: usize) {
         let changed = self.total != total;
         self.total = total;
@@ -85,6 +180,39 @@ impl ListViewState {
         }
     }
 
+    /// Move `delta` items down, or up when negative, stopping at either end
+    pub fn move_by(&mut self, delta: isize) {
+        if self.total == 0 {
+            return;
+        }
+        let from = self.selected.unwrap_or(0);
+        self.select(Some(from.saturating_add_signed(delta).min(self.total - 1)));
+    }
+
+    /// Select item `index`, or the last one when there are fewer
+    pub fn move_to(&mut self, index: usize) {
+        if self.total > 0 {
+            self.select(Some(index.min(self.total - 1)));
+        }
+    }
+
+    /// `}`: the first item of the next block, or the last item
+    pub fn next_block(&mut self, items: &[Credential]) {
+        let Some(from) = self.selected else { return };
+        let key = |i: usize| block_key(&items[i], self.sort);
+        let next = (from + 1..items.len()).find(|&i| key(i) != key(from));
+        self.move_to(next.unwrap_or(items.len().saturating_sub(1)));
+    }
+
+    /// `{`: the first item of this block, or of the one before when already there
+    pub fn previous_block(&mut self, items: &[Credential]) {
+        let Some(from) = self.selected.filter(|&i| i > 0 && i < items.len()) else { return };
+        let key = |i: usize| block_key(&items[i], self.sort);
+        let block = key(from - 1);
+        let start = (0..from).rev().find(|&i| key(i) != block).map_or(0, |i| i + 1);
+        self.move_to(start);
+    }
+
     pub fn page_up(&mut self, page_size: usize) {
         if self.total == 0 {
             return;
@@ -100,9 +228,14 @@ impl ListViewState {
         let new_index = self.selected.map_or(0, |i| (i + page_size).min(self.total - 1));
         self.select(Some(new_index));
     }
+}
 
-    pub fn list_state_mut(&mut self) -> &mut ListState {
-        &mut self.list_state
+/// What `{` and `}` jump between: runs of names with the same first letter
+/// when sorted by name, and of the same type otherwise
+fn block_key(item: &Credential, sort: SortOrder) -> String {
+    match sort {
+        SortOrder::Name => item.name.chars().next().map(|c| c.to_uppercase().collect()).unwrap_or_default(),
+        _ => item.credential_type.as_str().to_string(),
     }
 }
 
@@ -118,19 +251,25 @@ fn compute_selection_after_total_change(selected: Option<usize>, total: usize) -
 }
 
 pub struct CredentialList<'a> {
-    items: &'a [CredentialItem],
+    credentials: &'a [Credential],
     block: Option<Block<'a>>,
     highlight_style: Style,
     show_username: bool,
+    monochrome: bool,
+    tag_colors: Option<&'a TagColors>,
+    search: Option<(SearchMode, &'a str)>,
 }
 
 impl<'a> CredentialList<'a> {
-    pub fn new(items: &'a [CredentialItem]) -> Self {
+    pub fn new(credentials: &'a [Credential]) -> Self {
         Self {
-            items,
+            credentials,
             block: None,
             highlight_style: Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD),
             show_username: true,
+            monochrome: false,
+            tag_colors: None,
+            search: None,
         }
     }
 
@@ -138,6 +277,75 @@ impl<'a> CredentialList<'a> {
         self.block = Some(block);
         self
     }
+
+    pub fn monochrome(mut self, monochrome: bool) -> Self {
+        self.monochrome = monochrome;
+        self
+    }
+
+    /// Show tags after each name in their configured colors
+    pub fn tag_colors(mut self, colors: &'a TagColors) -> Self {
+        self.tag_colors = Some(colors);
+        self
+    }
+
+    /// Highlight what `query` matches in names and usernames
+    pub fn search(mut self, mode: SearchMode, query: &'a str) -> Self {
+        self.search = Some((mode, query));
+        self
+    }
+
+    fn build_item(&self, item: CredentialItem<'a>, index: usize, state: &ListViewState) -> ListItem<'a> {
+        let is_selected = Some(index) == state.selected();
+        let is_marked = state.in_selection(index, item.id);
+        let base_style = if is_selected { self.highlight_style } else { Style::default() };
+        let name_color = if is_marked { Color::Yellow } else { Color::White };
+        let icon = item.credential_type.icon();
+
+        let mut spans = vec![
+            build_selection_symbol(is_selected, is_marked, self.monochrome),
+            Span::styled(format!("{} ", icon), base_style.fg(type_color(item.credential_type))),
+        ];
+        spans.extend(self.highlight_matches(item.name, base_style.fg(name_color)));
+        if let Some(username) = item.username.filter(|_| self.show_username) {
+            let style = base_style.fg(Renderer::hex_color(0x4C566A));
+            spans.push(Span::styled(" (", style));
+            spans.extend(self.highlight_matches(username, style));
+            spans.push(Span::styled(")", style));
+        }
+        if let Some(colors) = self.tag_colors {
+            append_tag_spans(&mut spans, item, base_style, colors);
+        }
+
+        let mut list_item = ListItem::new(Line::from(spans));
+        if is_selected {
+            list_item = list_item.style(self.highlight_style);
+        }
+        list_item
+    }
+
+    /// Split `text` into spans with the search matches underlined in yellow
+    fn highlight_matches(&self, text: &'a str, style: Style) -> Vec<Span<'a>> {
+        let matched = self.search.map(|(mode, query)| search::match_indices(text, mode, query)).unwrap_or_default();
+        if matched.is_empty() {
+            return vec![Span::styled(text, style)];
+        }
+        let match_style = style.fg(Color::Yellow).add_modifier(Modifier::UNDERLINED);
+
+        let mut spans = Vec::new();
+        let mut run_start = 0;
+        let mut run_matched = false;
+        for (i, (offset, _)) in text.char_indices().enumerate() {
+            let is_match = matched.binary_search(&i).is_ok();
+            if is_match != run_matched && offset > run_start {
+                spans.push(Span::styled(&text[run_start..offset], if run_matched { match_style } else { style }));
+                run_start = offset;
+            }
+            run_matched = is_match;
+        }
+        spans.push(Span::styled(&text[run_start..], if run_matched { match_style } else { style }));
+        spans
+    }
 }
 
 fn type_color(cred_type: CredentialType) -> Color {
@@ -152,7 +360,20 @@ fn type_color(cred_type: CredentialType) -> Color {
     }
 }
 
-fn build_selection_symbol(is_selected: bool) -> Span<'static> {
+fn build_selection_symbol(is_selected: bool, is_marked: bool, monochrome: bool) -> Span<'static> {
+    if monochrome {
+        let marker = match (is_selected, is_marked) {
+            (true, true) => ">*",
+            (true, false) => "> ",
+            (false, true) => " *",
+            (false, false) => "  ",
+        };
+        return Span::styled(marker, Style::default().add_modifier(Modifier::BOLD));
+    }
+    let bg = if is_selected { Color::DarkGray } else { Color::Reset };
+    if is_marked {
+        return Span::styled("● ", Style::default().fg(Color::Yellow).bg(bg));
+    }
     if is_selected {
         Span::styled(" ", Style::default().fg(Color::Magenta).bg(Color::DarkGray))
     } else {
@@ -160,71 +381,39 @@ fn build_selection_symbol(is_selected: bool) -> Span<'static> {
     }
 }
 
-fn build_item_spans<'a>(
-    item: &'a CredentialItem,
-    is_selected: bool,
-    highlight_style: Style,
-    show_username: bool,
-) -> Vec<Span<'a>> {
-    let base_style = if is_selected { highlight_style } else { Style::default() };
-    let icon = item.credential_type.icon();
-    let color = type_color(item.credential_type);
-    let mut spans = vec![
-        build_selection_symbol(is_selected),
-        Span::styled(format!("{} ", icon), base_style.fg(color)),
-        Span::styled(item.name.as_str(), base_style.fg(Color::White)),
-    ];
-    append_username_span(&mut spans, item, base_style, show_username);
-    spans
-}
-
-fn append_username_span<'a>(spans: &mut Vec<Span<'a>>, item: &'a CredentialItem, base_style: Style, show_username: bool) {
-    if !show_username { return }
-    let Some(ref username) = item.username else { return };
-    spans.push(Span::styled(
-        format!(" ({})", username),
-        base_style.fg(Renderer::hex_color(0x4C566A)),
-    ));
-}
-
-fn build_list_item<'a>(
-    item: &'a CredentialItem,
-    index: usize,
-    selected: Option<usize>,
-    highlight_style: Style,
-    show_username: bool,
-) -> ListItem<'a> {
-    let is_selected = Some(index) == selected;
-    let spans = build_item_spans(item, is_selected, highlight_style, show_username);
-    let mut list_item = ListItem::new(Line::from(spans));
-
-    if is_selected {
-        list_item = list_item.style(highlight_style);
+fn append_tag_spans<'a>(spans: &mut Vec<Span<'a>>, item: CredentialItem<'a>, base_style: Style, colors: &TagColors) {
+    for tag in item.tags {
+        spans.push(Span::styled(format!(" #{}", tag), base_style.fg(colors.color_or(tag, Color::Magenta))));
     }
-
-    list_item
 }
 
 impl<'a> StatefulWidget for CredentialList<'a> {
     type State = ListViewState;
 
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
-        let selected = state.selected();
+        let inner = render_optional_block(area, buf, &self.block);
+        let height = inner.height as usize;
+        let offset = window_offset(state.list_state.offset(), state.selected, height, self.credentials.len());
+        *state.list_state.offset_mut() = offset;
 
-        let items: Vec<ListItem> = self
-            .items
+        let items: Vec<ListItem> = self.credentials[offset..]
             .iter()
+            .take(height)
             .enumerate()
-            .map(|(i, item)| build_list_item(item, i, selected, self.highlight_style, self.show_username))
+            .map(|(i, cred)| self.build_item(CredentialItem::from(cred), offset + i, state))
             .collect();
+        Widget::render(List::new(items), inner, buf);
+    }
+}
 
-        let list = List::new(items);
-        let list = match self.block {
-            Some(block) => list.block(block),
-            None => list,
-        };
-
-        StatefulWidget::render(list, area, buf, state.list_state_mut());
+/// First row on screen: where it was, moved only as far as it takes to keep
+/// the selection in view and the window full
+fn window_offset(offset: usize, selected: Option<usize>, height: usize, total: usize) -> usize {
+    let offset = offset.min(total.saturating_sub(height));
+    match selected {
+        Some(sel) if sel < offset => sel,
+        Some(sel) if height > 0 && sel >= offset + height => sel + 1 - height,
+        _ => offset,
     }
 }
 
@@ -303,6 +492,106 @@ mod tests {
         assert_eq!(state.selected(), Some(0));
     }
 
+    #[test]
+    fn test_toggle_mark() {
+        let mut state = ListViewState::new();
+        state.toggle_mark("a");
+        state.toggle_mark("b");
+        state.toggle_mark("a");
+        assert!(!state.is_marked("a"));
+        assert!(state.is_marked("b"));
+    }
+
+    #[test]
+    fn test_visual_range() {
+        let mut state = ListViewState::new();
+        state.set_total(5);
+        state.select(Some(3));
+        assert_eq!(state.visual_range(), None);
+
+        state.visual_anchor = Some(3);
+        state.move_up();
+        state.move_up();
+        assert_eq!(state.visual_range(), Some(1..=3));
+    }
+
+    #[test]
+    fn test_highlight_matches() {
+        let list = CredentialList::new(&[]).search(SearchMode::Substring, "hub");
+        let spans = list.highlight_matches("GitHub", Style::default());
+        let texts: Vec<&str> = spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(texts, ["Git", "Hub"]);
+        assert!(spans[1].style.add_modifier.contains(Modifier::UNDERLINED));
+
+        let list = CredentialList::new(&[]);
+        assert_eq!(list.highlight_matches("GitHub", Style::default()).len(), 1);
+    }
+
+    #[test]
+    fn test_sort_order_cycle() {
+        assert_eq!(SortOrder::Name.next(), SortOrder::Updated);
+        assert_eq!(SortOrder::Strength.next(), SortOrder::Name);
+        assert_eq!(SortOrder::from_name("created"), Some(SortOrder::Created));
+        assert_eq!(SortOrder::from_name("size"), None);
+    }
+
+    #[test]
+    fn test_counts_and_blocks() {
+        let item = |name: &str| Credential::new(name.to_string(), CredentialType::Password, String::new());
+        let items: Vec<Credential> = ["Apple", "azure", "Bank", "Bitbucket", "Cloud"].into_iter().map(item).collect();
+        let mut state = ListViewState::new();
+        state.set_total(items.len());
+
+        state.move_by(3);
+        assert_eq!(state.selected(), Some(3));
+        state.move_by(-10);
+        assert_eq!(state.selected(), Some(0));
+        state.move_to(99);
+        assert_eq!(state.selected(), Some(4));
+
+        state.move_to(0);
+        state.next_block(&items);
+        assert_eq!(state.selected(), Some(2));
+        state.next_block(&items);
+        assert_eq!(state.selected(), Some(4));
+        state.move_to(3);
+        state.previous_block(&items);
+        assert_eq!(state.selected(), Some(2));
+        state.previous_block(&items);
+        assert_eq!(state.selected(), Some(0));
+
+        // Other orders group by type, all one block here
+        state.sort = SortOrder::Updated;
+        state.next_block(&items);
+        assert_eq!(state.selected(), Some(4));
+    }
+
+    #[test]
+    fn test_renders_only_the_window() {
+        let credentials: Vec<Credential> = (0..10_000)
+            .map(|i| Credential::new(format!("cred {:05}", i), CredentialType::Password, String::new()))
+            .collect();
+        let mut state = ListViewState::new();
+        state.set_total(credentials.len());
+        let area = Rect::new(0, 0, 30, 5);
+        let row = |buf: &Buffer, y: u16| (0..30).map(|x| buf[(x, y)].symbol()).collect::<String>();
+
+        state.move_to(5_000);
+        let mut buf = Buffer::empty(area);
+        CredentialList::new(&credentials).render(area, &mut buf, &mut state);
+        assert_eq!(state.list_state_ref().offset(), 4_996);
+        assert!(row(&buf, 4).contains("cred 05000"));
+
+        // Moving within the window leaves it where it is
+        state.move_by(-2);
+        CredentialList::new(&credentials).render(area, &mut buf, &mut state);
+        assert_eq!(state.list_state_ref().offset(), 4_996);
+        assert!(row(&buf, 0).contains("cred 04996"));
+
+        assert_eq!(window_offset(9_998, Some(9_999), 5, 10_000), 9_995);
+        assert_eq!(window_offset(0, None, 0, 3), 0);
+    }
+
     #[test]
     fn test_list_state_empty() {
         let mut state = ListViewState::new();
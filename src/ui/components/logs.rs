@@ -74,6 +74,8 @@ impl LogsColumns {
 pub struct LogsState {
     pub scroll: ScrollState,
     pub logs: Vec<AuditLog>,
+    /// Name of the credential whose activity is shown; None for the whole log
+    pub credential: Option<String>,
     columns: Option<LogsColumns>,
 }
 
@@ -83,11 +85,18 @@ impl LogsState {
     }
 
     pub fn set_logs(&mut self, logs: Vec<AuditLog>) {
+        self.credential = None;
         self.columns = Some(LogsColumns::from_logs(&logs));
         self.logs = logs;
         self.scroll.reset();
     }
 
+    /// Show one credential's entries instead of the whole log
+    pub fn set_credential_logs(&mut self, name: &str, logs: Vec<AuditLog>) {
+        self.set_logs(logs);
+        self.credential = Some(name.to_string());
+    }
+
     pub fn scroll_up(&mut self, amount: usize) {
         self.scroll.scroll_up(amount);
     }
@@ -167,12 +176,16 @@ impl Widget for LogsScreen<'_> {
         let popup = centered_rect(85, 75, area);
         Clear.render(popup, buf);
 
-        let block = create_popup_block(" Audit Logs (last 500) ", Color::Magenta);
+        let (title, empty_message) = match &self.state.credential {
+            Some(name) => (format!(" Activity: {} ", name), "No activity recorded"),
+            None => (" Audit Logs (last 500) ".to_string(), "No audit logs found"),
+        };
+        let block = create_popup_block(&title, Color::Magenta);
         let inner = block.inner(popup);
         block.render(popup, buf);
 
         if self.state.logs.is_empty() {
-            render_empty_message(inner, buf, "No audit logs found");
+            render_empty_message(inner, buf, empty_message);
             return;
         }
 
@@ -296,5 +309,8 @@ fn action_display(action: &AuditAction) -> (&'static str, Color) {
         AuditAction::Unlock => ("UNLOCK", Color::Cyan),
         AuditAction::Lock => ("LOCK", Color::Yellow),
         AuditAction::FailedUnlock => ("FAILED", Color::Red),
+        AuditAction::Backup => ("BACKUP", Color::Green),
+        AuditAction::Restore => ("RESTORE", Color::Red),
+        AuditAction::Prune => ("PRUNE", Color::Gray),
     }
 }
@@ -8,17 +8,28 @@ pub mod list;
 pub mod statusline;
 pub mod dialogs;
 pub mod help;
+pub mod history;
 pub mod layout;
 pub mod logs;
 pub mod scroll;
 pub mod tags;
 pub mod export;
+pub mod import;
+pub mod share;
+pub mod duplicates;
+pub mod report;
+pub mod stats;
+pub mod backups;
+pub mod trash;
+pub mod types;
+pub mod which_key;
 
 // Re-exports
 pub use detail::{CredentialDetail, DetailView};
 pub use form::{CredentialForm, CredentialFormWidget};
-pub use list::{CredentialItem, CredentialList, EmptyState, ListViewState};
+pub use list::{CredentialList, EmptyState, ListViewState, SortOrder};
 pub use statusline::{HelpBar, MessageType, StatusLine};
 pub use dialogs::{ConfirmDialog, PasswordDialog};
 pub use help::{HelpScreen};
 pub use export::ExportDialog;
+pub use import::ImportDialog;
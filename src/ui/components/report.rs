@@ -0,0 +1,164 @@
+//! Security report popup and state
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Clear, Widget},
+};
+
+use crate::vault::report::{Report, ReportEntry, ReportGroup};
+
+use super::layout::{
+    centered_rect_fixed, create_popup_block, highlight_row, render_empty_message,
+    truncate_with_ellipsis,
+};
+use super::scroll::render_v_scroll_indicator;
+
+#[derive(Default)]
+pub struct ReportState {
+    pub report: Option<Report>,
+    /// Index into the flattened list of entries across all groups
+    pub selected: usize,
+    /// What the report lists, shown when it is empty, e.g. "No secrets older than 365 days"
+    pub empty_message: String,
+}
+
+impl ReportState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_report(&mut self, report: Report, empty_message: String) {
+        self.report = Some(report);
+        self.empty_message = empty_message;
+        self.selected = self.selected.min(self.entry_count().saturating_sub(1));
+    }
+
+    fn entry_count(&self) -> usize {
+        self.report.as_ref().map_or(0, Report::entry_count)
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn scroll_down(&mut self) {
+        if self.selected + 1 < self.entry_count() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn home(&mut self) {
+        self.selected = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.selected = self.entry_count().saturating_sub(1);
+    }
+
+    /// Entry under the cursor
+    pub fn selected_entry(&self) -> Option<&ReportEntry> {
+        self.report.as_ref()?.groups.iter().flat_map(|g| &g.entries).nth(self.selected)
+    }
+}
+
+enum Row<'a> {
+    Header(&'a ReportGroup, &'a str),
+    Entry(&'a ReportEntry, usize),
+}
+
+fn build_rows(groups: &[ReportGroup]) -> Vec<Row<'_>> {
+    let mut rows = Vec::new();
+    let mut entry_idx = 0;
+    for group in groups {
+        if let Some(title) = &group.title {
+            rows.push(Row::Header(group, title));
+        }
+        for entry in &group.entries {
+            rows.push(Row::Entry(entry, entry_idx));
+            entry_idx += 1;
+        }
+    }
+    rows
+}
+
+pub struct ReportPopup<'a> {
+    state: &'a ReportState,
+}
+
+impl<'a> ReportPopup<'a> {
+    pub fn new(state: &'a ReportState) -> Self {
+        Self { state }
+    }
+}
+
+impl Widget for ReportPopup<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let Some(report) = &self.state.report else { return };
+        let rows = build_rows(&report.groups);
+        let height = calculate_report_height(rows.len(), area.height);
+        let popup = centered_rect_fixed(80, height, area, true);
+        Clear.render(popup, buf);
+
+        let title = format!(" {} ({}) ", report.kind.title(), report.entry_count());
+        let block = create_popup_block(&title, Color::Red);
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        if rows.is_empty() {
+            render_empty_message(inner, buf, &self.state.empty_message);
+            return;
+        }
+
+        let visible = inner.height as usize;
+        let cursor_row = rows
+            .iter()
+            .position(|r| matches!(r, Row::Entry(_, i) if *i == self.state.selected))
+            .unwrap_or(0);
+        let scroll_offset = if cursor_row >= visible { cursor_row - visible + 1 } else { 0 };
+
+        for (i, row) in rows.iter().enumerate().skip(scroll_offset).take(visible) {
+            let y = inner.y + (i - scroll_offset) as u16;
+            match row {
+                Row::Header(group, title) => render_group_header(inner, buf, y, group, title),
+                Row::Entry(entry, idx) => render_entry_row(inner, buf, y, entry, *idx == self.state.selected),
+            }
+        }
+
+        let max_v = rows.len().saturating_sub(visible);
+        if max_v > 0 {
+            render_v_scroll_indicator(buf, &popup, scroll_offset, max_v, Color::Red);
+        }
+    }
+}
+
+fn calculate_report_height(rows: usize, area_height: u16) -> u16 {
+    let available = area_height.saturating_sub(2);
+    let desired = (rows as u16).saturating_add(2);
+    desired.min((available * 75) / 100).max(6)
+}
+
+fn render_group_header(inner: Rect, buf: &mut Buffer, y: u16, group: &ReportGroup, title: &str) {
+    let style = Style::default().fg(Color::Red).add_modifier(Modifier::BOLD);
+    let text = format!("{} ({})", title, group.entries.len());
+    buf.set_string(inner.x, y, &text, style);
+}
+
+fn render_entry_row(inner: Rect, buf: &mut Buffer, y: u16, entry: &ReportEntry, is_cursor: bool) {
+    if is_cursor {
+        highlight_row(buf, inner.x, y, inner.width);
+    }
+    let bg = if is_cursor { Color::DarkGray } else { Color::Reset };
+
+    let detail_x = inner.x + inner.width.saturating_sub(entry.detail.len() as u16);
+    buf.set_string(detail_x, y, &entry.detail, Style::default().fg(Color::Yellow).bg(bg));
+
+    let mut text = entry.name.clone();
+    if let Some(username) = &entry.username {
+        text.push_str(&format!(" [{}]", username));
+    }
+    let max_width = (inner.width as usize).saturating_sub(entry.detail.len() + 3);
+    let display = truncate_with_ellipsis(&text, max_width);
+    buf.set_string(inner.x + 2, y, &display, Style::default().fg(Color::White).bg(bg));
+}
@@ -0,0 +1,127 @@
+//! Share popup for `:share`
+//!
+//! Shows a shared credential as a QR code drawn with half-block characters,
+//! two modules rows per line, next to the one-time passphrase that opens it.
+//! When the terminal is too small for the code, the armored block can still
+//! be copied.
+
+use qrcode::{EcLevel, QrCode};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Clear, Widget},
+};
+use zeroize::Zeroizing;
+
+use crate::vault::share::Share;
+
+use super::layout::{centered_rect_fixed, create_popup_block, truncate_with_ellipsis};
+
+/// Light modules around the code, which scanners need to find it
+const QUIET_ZONE: usize = 2;
+/// Lines under the code: blank, passphrase label, passphrase, blank, note
+const TEXT_ROWS: u16 = 5;
+const MIN_WIDTH: u16 = 56;
+
+pub struct ShareView {
+    pub name: String,
+    pub armored: String,
+    passphrase: Zeroizing<String>,
+    /// None when the block is too long for a QR code
+    qr: Option<QrCode>,
+    /// Where the block was saved, if it was
+    pub saved_to: Option<String>,
+}
+
+impl ShareView {
+    pub fn new(name: String, share: Share, saved_to: Option<String>) -> Self {
+        // The lowest level holds the most, and a terminal isn't a smudged label
+        let qr = QrCode::with_error_correction_level(&share.armored, EcLevel::L).ok();
+        Self { name, armored: share.armored, passphrase: share.passphrase, qr, saved_to }
+    }
+
+    pub fn passphrase(&self) -> &str {
+        &self.passphrase
+    }
+}
+
+pub struct SharePopup<'a> {
+    view: &'a ShareView,
+}
+
+impl<'a> SharePopup<'a> {
+    pub fn new(view: &'a ShareView) -> Self {
+        Self { view }
+    }
+}
+
+impl Widget for SharePopup<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let view = self.view;
+        let modules = view.qr.as_ref().map_or(0, |code| code.width() + QUIET_ZONE * 2) as u16;
+        let qr_rows = modules.div_ceil(2);
+        let fits = view.qr.is_some() && modules + 4 <= area.width && qr_rows + TEXT_ROWS + 4 <= area.height;
+
+        let width = if fits { (modules + 4).max(MIN_WIDTH) } else { MIN_WIDTH };
+        let height = if fits { qr_rows + TEXT_ROWS + 2 } else { TEXT_ROWS + 3 };
+        let popup = centered_rect_fixed(width, height, area, true);
+        Clear.render(popup, buf);
+
+        let title = format!(" Share: {} ", truncate_with_ellipsis(&view.name, width.saturating_sub(12) as usize));
+        let block = create_popup_block(&title, Color::Red);
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        let mut y = inner.y;
+        match view.qr.as_ref().filter(|_| fits) {
+            Some(code) => {
+                let x = inner.x + inner.width.saturating_sub(modules) / 2;
+                render_code(code, buf, x, y);
+                y += qr_rows;
+            }
+            None => {
+                let reason = match view.qr {
+                    Some(_) => "Terminal too small for the QR code",
+                    None => "Too long for a QR code",
+                };
+                buf.set_string(inner.x + 1, y, reason, Style::default().fg(Color::DarkGray));
+                y += 1;
+            }
+        }
+
+        let label = Style::default().fg(Color::Gray);
+        buf.set_string(inner.x + 1, y + 1, "One-time passphrase:", label);
+        let passphrase = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+        buf.set_string(inner.x + 1, y + 2, view.passphrase(), passphrase);
+
+        let note = match &view.saved_to {
+            Some(path) => format!("Saved to {}; send the passphrase separately", path),
+            None => "Send the passphrase separately from the code".to_string(),
+        };
+        let note = truncate_with_ellipsis(&note, inner.width.saturating_sub(2) as usize);
+        buf.set_string(inner.x + 1, y + 4, note, Style::default().fg(Color::DarkGray));
+    }
+}
+
+/// Dark modules on white whatever the theme, two module rows per line
+fn render_code(code: &QrCode, buf: &mut Buffer, x: u16, y: u16) {
+    let size = code.width() + QUIET_ZONE * 2;
+    let dark = |col: usize, row: usize| {
+        let inside = |i: usize| i >= QUIET_ZONE && i < code.width() + QUIET_ZONE;
+        inside(col) && inside(row) && code[(col - QUIET_ZONE, row - QUIET_ZONE)] == qrcode::Color::Dark
+    };
+    let style = Style::default().fg(Color::Black).bg(Color::White);
+
+    for (line, row) in (0..size).step_by(2).enumerate() {
+        let text: String = (0..size)
+            .map(|col| match (dark(col, row), dark(col, row + 1)) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            })
+            .collect();
+        buf.set_string(x, y + line as u16, text, style);
+    }
+}
@@ -0,0 +1,160 @@
+//! Vault statistics screen and state
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Clear, Widget},
+};
+
+use crate::crypto::strength_label;
+use crate::vault::stats::{Dated, VaultStats};
+
+use super::layout::{centered_rect, create_popup_block, truncate_with_ellipsis};
+use super::scroll::{render_v_scroll_indicator, ScrollState};
+
+/// Width of the label column
+const LABEL_WIDTH: usize = 20;
+
+/// A section heading, or a label and its value
+enum StatsRow {
+    Heading(&'static str),
+    Item(String, String),
+}
+
+#[derive(Default)]
+pub struct StatsState {
+    pub scroll: ScrollState,
+    rows: Vec<StatsRow>,
+}
+
+impl StatsState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_stats(&mut self, stats: &VaultStats) {
+        let item = |label: &str, value: String| StatsRow::Item(label.to_string(), value);
+        let dated = |entry: &Option<Dated>| match entry {
+            Some((name, at)) => format!("{} ({})", name, at.format("%d-%b-%Y")),
+            None => "-".to_string(),
+        };
+        let strength = match stats.average_strength {
+            Some(score) => format!("{} ({}) over {} passwords", strength_label(score), score, stats.scored),
+            None => "-".to_string(),
+        };
+
+        let mut rows = vec![
+            StatsRow::Heading("Vault"),
+            item("Credentials", stats.total.to_string()),
+            item("In trash", stats.trashed.to_string()),
+            item("Oldest", dated(&stats.oldest)),
+            item("Newest", dated(&stats.newest)),
+            item("Average strength", strength),
+            item("Audit entries", stats.audit_entries.to_string()),
+            item("File size", format_size(stats.file_size)),
+            StatsRow::Heading("Types"),
+        ];
+        rows.extend(stats.by_type.iter().map(|(kind, count)| item(kind.display_name(), count.to_string())));
+        rows.push(StatsRow::Heading("Tags"));
+        rows.extend(stats.by_tag.iter().map(|(tag, count)| item(tag, count.to_string())));
+        rows.push(item("(untagged)", stats.untagged.to_string()));
+
+        self.rows = rows;
+        self.scroll.reset();
+    }
+
+    pub fn scroll_up(&mut self, amount: usize) {
+        self.scroll.scroll_up(amount);
+    }
+
+    pub fn scroll_down(&mut self, amount: usize, max: usize) {
+        self.scroll.scroll_down(amount, max);
+    }
+
+    pub fn home(&mut self) {
+        self.scroll.home();
+    }
+
+    pub fn end(&mut self, max: usize) {
+        self.scroll.end(max);
+    }
+
+    pub fn max_scroll(&self, visible_height: u16) -> usize {
+        self.rows.len().saturating_sub(visible_height as usize)
+    }
+}
+
+/// Bytes in a readable unit, e.g. `3.2 MiB`
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{} bytes", bytes),
+        1024..1_048_576 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MiB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+pub struct StatsScreen<'a> {
+    state: &'a StatsState,
+}
+
+impl<'a> StatsScreen<'a> {
+    pub fn new(state: &'a StatsState) -> Self {
+        Self { state }
+    }
+
+    pub fn visible_height(area: Rect) -> u16 {
+        let popup = centered_rect(60, 75, area);
+        popup.height.saturating_sub(2)
+    }
+}
+
+impl Widget for StatsScreen<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let popup = centered_rect(60, 75, area);
+        Clear.render(popup, buf);
+
+        let block = create_popup_block(" Statistics ", Color::Cyan);
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        let visible = inner.height as usize;
+        let offset = self.state.scroll.v_scroll;
+        for (i, row) in self.state.rows.iter().enumerate().skip(offset).take(visible) {
+            render_stats_row(inner, buf, inner.y + (i - offset) as u16, row);
+        }
+
+        let max_v = self.state.max_scroll(inner.height);
+        if max_v > 0 {
+            render_v_scroll_indicator(buf, &popup, offset, max_v, Color::Cyan);
+        }
+    }
+}
+
+fn render_stats_row(inner: Rect, buf: &mut Buffer, y: u16, row: &StatsRow) {
+    let max_width = (inner.width as usize).saturating_sub(4);
+    match row {
+        StatsRow::Heading(title) => {
+            let style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+            buf.set_string(inner.x + 1, y, title, style);
+        }
+        StatsRow::Item(label, value) => {
+            let label = truncate_with_ellipsis(label, LABEL_WIDTH - 1);
+            buf.set_string(inner.x + 2, y, &label, Style::default().fg(Color::DarkGray));
+            let value = truncate_with_ellipsis(value, max_width.saturating_sub(LABEL_WIDTH));
+            buf.set_string(inner.x + 2 + LABEL_WIDTH as u16, y, &value, Style::default().fg(Color::White));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 bytes");
+        assert_eq!(format_size(2048), "2.0 KiB");
+        assert_eq!(format_size(3 * 1_048_576 + 200_000), "3.2 MiB");
+    }
+}
@@ -16,7 +16,6 @@ use crate::input::InputMode;
 pub enum MessageType {
     Info,
     Success,
-    #[allow(dead_code)]
     Warning,
     Error,
 }
@@ -30,6 +29,16 @@ impl MessageType {
             Self::Error => Color::Red,
         }
     }
+
+    /// Text marker shown in place of the color in monochrome mode
+    pub fn marker(&self) -> &'static str {
+        match self {
+            Self::Info => "[info]",
+            Self::Success => "[ok]",
+            Self::Warning => "[warn]",
+            Self::Error => "[error]",
+        }
+    }
 }
 
 pub struct StatusLine<'a> {
@@ -38,7 +47,13 @@ pub struct StatusLine<'a> {
     message: Option<(&'a str, MessageType)>,
     item_count: Option<(usize, usize)>,
     search_query: Option<&'a str>,
-    filter_tags: Option<&'a [String]>,
+    filter_tags: Option<(&'a [String], &'a str)>,
+    filter_types: Option<&'a str>,
+    expiring: bool,
+    rotation: bool,
+    sort: Option<&'a str>,
+    marked: usize,
+    monochrome: bool,
 }
 
 impl<'a> StatusLine<'a> {
@@ -50,6 +65,12 @@ impl<'a> StatusLine<'a> {
             item_count: None,
             search_query: None,
             filter_tags: None,
+            filter_types: None,
+            expiring: false,
+            rotation: false,
+            sort: None,
+            marked: 0,
+            monochrome: false,
         }
     }
 
@@ -73,8 +94,39 @@ impl<'a> StatusLine<'a> {
         self
     }
 
-    pub fn filter_tags(mut self, tags: &'a [String]) -> Self {
-        self.filter_tags = Some(tags);
+    /// Active tag filter and whether it matches all or any of its tags
+    pub fn filter_tags(mut self, tags: &'a [String], tag_match: &'a str) -> Self {
+        self.filter_tags = Some((tags, tag_match));
+        self
+    }
+
+    pub fn filter_types(mut self, types: &'a str) -> Self {
+        self.filter_types = Some(types);
+        self
+    }
+
+    pub fn expiring(mut self, expiring: bool) -> Self {
+        self.expiring = expiring;
+        self
+    }
+
+    pub fn rotation(mut self, rotation: bool) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn sort(mut self, sort: &'a str) -> Self {
+        self.sort = Some(sort);
+        self
+    }
+
+    pub fn marked(mut self, count: usize) -> Self {
+        self.marked = count;
+        self
+    }
+
+    pub fn monochrome(mut self, monochrome: bool) -> Self {
+        self.monochrome = monochrome;
         self
     }
 }
@@ -89,8 +141,19 @@ fn mode_style(mode: InputMode) -> Style {
         InputMode::Confirm => base.bg(Color::Red),
         InputMode::Help => base.bg(Color::Yellow),
         InputMode::Logs => base.bg(Color::Green),
+        InputMode::History => base.bg(Color::Blue),
+        InputMode::Stats => base.bg(Color::Cyan),
         InputMode::Tags => base.bg(Color::Magenta),
+        InputMode::Types => base.bg(Color::Cyan),
         InputMode::Export => base.bg(Color::Red),
+        InputMode::Import => base.bg(Color::Cyan),
+        InputMode::ImportReview => base.bg(Color::Cyan),
+        InputMode::Share => base.bg(Color::Red),
+        InputMode::Duplicates => base.bg(Color::Yellow),
+        InputMode::Report => base.bg(Color::Red),
+        InputMode::Restore => base.bg(Color::Red),
+        InputMode::Trash => base.bg(Color::Red),
+        InputMode::Visual => base.bg(Color::Yellow),
     }
 }
 
@@ -116,6 +179,7 @@ fn render_command_or_message(
     mode: InputMode,
     command_buffer: Option<&str>,
     message: Option<(&str, MessageType)>,
+    monochrome: bool,
 ) {
     let style_base = Style::default().bg(Color::DarkGray);
 
@@ -125,37 +189,69 @@ fn render_command_or_message(
         return;
     }
 
-    if let Some((msg, msg_type)) = message {
+    let Some((msg, msg_type)) = message else { return };
+    if !monochrome {
         buf.set_string(x, y, msg, style_base.fg(msg_type.color()));
+        return;
     }
+    let style = match msg_type {
+        MessageType::Error | MessageType::Warning => style_base.add_modifier(Modifier::BOLD),
+        _ => style_base,
+    };
+    buf.set_string(x, y, format!("{} {}", msg_type.marker(), msg), style);
 }
 
-fn render_right_section(
-    buf: &mut Buffer,
-    area: Rect,
-    search_query: Option<&str>,
-    filter_tags: Option<&[String]>,
-    item_count: Option<(usize, usize)>,
-) {
+fn render_right_section(buf: &mut Buffer, area: Rect, status: &StatusLine) {
+    let StatusLine { search_query, filter_tags, filter_types, expiring, rotation, sort, marked, item_count, .. } = *status;
     let mut spans: Vec<Span> = Vec::new();
     let sep = Span::styled(" | ", Style::default().fg(Color::White)); // opts: |, │
     
-    if let Some(tags) = filter_tags {
+    if let Some((tags, tag_match)) = filter_tags {
         let tags_display = if tags.len() > 2 {
             format!("{}+{}", tags[..2].join(","), tags.len() - 2)
         } else {
             tags.join(", ")
         };
-        spans.push(Span::styled("Tags: ", Style::default().fg(Color::Green).bg(Color::DarkGray)));
+        spans.push(Span::styled(format!("Tags ({}): ", tag_match), Style::default().fg(Color::Green).bg(Color::DarkGray)));
         spans.push(Span::styled(tags_display, Style::default().fg(Color::Magenta).bg(Color::DarkGray).add_modifier(Modifier::BOLD)));
     }
+
+    if let Some(types) = filter_types {
+        if !spans.is_empty() { spans.push(sep.clone()); }
+        spans.push(Span::styled("Type: ", Style::default().fg(Color::Cyan).bg(Color::DarkGray)));
+        spans.push(Span::styled(types, Style::default().fg(Color::Magenta).bg(Color::DarkGray).add_modifier(Modifier::BOLD)));
+    }
     
+    if expiring {
+        if !spans.is_empty() { spans.push(sep.clone()); }
+        spans.push(Span::styled("Expiring", Style::default().fg(Color::Red).bg(Color::DarkGray).add_modifier(Modifier::BOLD)));
+    }
+
+    if rotation {
+        if !spans.is_empty() { spans.push(sep.clone()); }
+        spans.push(Span::styled("Rotation due", Style::default().fg(Color::Red).bg(Color::DarkGray).add_modifier(Modifier::BOLD)));
+    }
+
     if let Some(query) = search_query {
         if !spans.is_empty() { spans.push(sep.clone()); }
         spans.push(Span::styled("Search: ", Style::default().fg(Color::Yellow).bg(Color::DarkGray)));
         spans.push(Span::styled(query, Style::default().fg(Color::Magenta).bg(Color::DarkGray).add_modifier(Modifier::BOLD)));
     }
     
+    if let Some(sort) = sort {
+        if !spans.is_empty() { spans.push(sep.clone()); }
+        spans.push(Span::styled("Sort: ", Style::default().fg(Color::Blue).bg(Color::DarkGray)));
+        spans.push(Span::styled(sort, Style::default().fg(Color::White).bg(Color::DarkGray)));
+    }
+
+    if marked > 0 {
+        if !spans.is_empty() { spans.push(sep.clone()); }
+        spans.push(Span::styled(
+            format!("{} marked", marked),
+            Style::default().fg(Color::Yellow).bg(Color::DarkGray).add_modifier(Modifier::BOLD),
+        ));
+    }
+
     if let Some((selected, total)) = item_count {
         if !spans.is_empty() { spans.push(sep.clone()); }
         spans.push(Span::styled(
@@ -185,9 +281,9 @@ impl<'a> Widget for StatusLine<'a> {
         buf.set_string(x, area.y, " ", Style::default().bg(Color::DarkGray));
         let x = x + 1;
 
-        render_command_or_message(buf, x, area.y, self.mode, self.command_buffer, self.message);
+        render_command_or_message(buf, x, area.y, self.mode, self.command_buffer, self.message, self.monochrome);
 
-        render_right_section(buf, area, self.search_query, self.filter_tags, self.item_count);
+        render_right_section(buf, area, &self);
     }
 }
 
@@ -233,10 +329,24 @@ fn hints_for_mode(mode: InputMode) -> Vec<(&'static str, &'static str)> {
             ("0/$", "start/end"),
             ("gg/G", "top/bottom"),
         ],
+        InputMode::History | InputMode::Stats => vec![
+            ("esc", "close"),
+            ("j/k", "scroll"),
+            ("ctrl+[d/u]", "page"),
+            ("gg/G", "top/bottom"),
+        ],
         InputMode::Tags => vec![
             ("esc", "close"),
             ("j/k", "scroll"),
             ("ctrl+[d/u]", "page"),
+            ("space/-", "include/exclude"),
+            ("a", "all/any"),
+            ("enter", "filter"),
+            ("r/m/d", "rename/merge/delete"),
+        ],
+        InputMode::Types => vec![
+            ("esc", "close"),
+            ("j/k", "move"),
             ("space", "select"),
             ("enter", "filter"),
         ],
@@ -245,7 +355,57 @@ fn hints_for_mode(mode: InputMode) -> Vec<(&'static str, &'static str)> {
             ("space/ctrl+space", "cycle option"),
             ("enter", "export"),
             ("esc", "cancel"),
-        ]
+        ],
+        InputMode::Import => vec![
+            ("tab/shift+tab", "cycle field"),
+            ("space/ctrl+space", "cycle format"),
+            ("enter", "preview/import"),
+            ("ctrl+r", "review conflicts"),
+            ("esc", "cancel"),
+        ],
+        InputMode::ImportReview => vec![
+            ("j/k", "move"),
+            ("space", "cycle action"),
+            ("s/o/b/m", "skip/overwrite/both/merge"),
+            ("S/O/B/M", "apply to all"),
+            ("enter/esc", "back"),
+        ],
+        InputMode::Share => vec![
+            ("y", "copy block"),
+            ("p", "copy passphrase"),
+            ("esc", "close"),
+        ],
+        InputMode::Duplicates => vec![
+            ("esc", "close"),
+            ("j/k", "move"),
+            ("d", "delete"),
+            ("m", "merge group into selected"),
+        ],
+        InputMode::Report => vec![
+            ("esc", "close"),
+            ("j/k", "move"),
+            ("enter", "open"),
+            ("e", "edit"),
+        ],
+        InputMode::Restore => vec![
+            ("esc", "close"),
+            ("j/k", "move"),
+            ("enter", "restore"),
+        ],
+        InputMode::Visual => vec![
+            ("esc", "cancel"),
+            ("j/k", "extend"),
+            ("space", "mark"),
+            ("d", "delete"),
+            (":", "command on range"),
+        ],
+        InputMode::Trash => vec![
+            ("esc", "close"),
+            ("j/k", "move"),
+            ("enter/r", "restore"),
+            ("d", "delete forever"),
+            ("D", "empty trash"),
+        ],
     }
 }
 
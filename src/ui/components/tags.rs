@@ -1,6 +1,11 @@
 //! Tags popup and state
+//!
+//! Tags nest with `parent/child` paths: the popup lists them as a tree and a
+//! child takes its parent's configured color unless it has one of its own.
 
-use std::collections::{HashMap, HashSet};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::str::FromStr;
 
 use ratatui::{
     buffer::Buffer,
@@ -10,6 +15,7 @@ use ratatui::{
 };
 
 use crate::db::Credential;
+use crate::vault::search::TagMatch;
 
 use super::layout::{
     centered_rect_fixed, create_popup_block, highlight_row, render_empty_message,
@@ -17,12 +23,72 @@ use super::layout::{
 };
 use super::scroll::{render_v_scroll_indicator, ScrollState};
 
+/// Tag colors from the `tagcolors` setting, keyed by tag path
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TagColors(BTreeMap<String, Color>);
+
+impl TagColors {
+    /// Parse the `:set` form, `tag:color` pairs separated by commas,
+    /// e.g. `work:blue,personal/bank:#ff8800`
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut colors = BTreeMap::new();
+        for pair in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let Some((tag, color)) = pair.rsplit_once(':') else {
+                return Err(format!("Expected tag:color, got '{}'", pair));
+            };
+            let color = Color::from_str(color.trim()).map_err(|_| format!("Unknown color: {}", color.trim()))?;
+            colors.insert(tag.trim().to_string(), color);
+        }
+        Ok(Self(colors))
+    }
+
+    /// The `:set` form, parsed back by `parse`
+    pub fn spec(&self) -> String {
+        self.entries().map(|(tag, color)| format!("{}:{}", tag, color)).collect::<Vec<_>>().join(",")
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&str, Color)> {
+        self.0.iter().map(|(tag, color)| (tag.as_str(), *color))
+    }
+
+    /// Color of `tag`, falling back to its nearest colored parent
+    pub fn color(&self, tag: &str) -> Option<Color> {
+        tag_ancestry(tag).rev().find_map(|path| self.0.get(path).copied())
+    }
+
+    /// Color of `tag`, or `default` when neither it nor a parent has one
+    pub fn color_or(&self, tag: &str, default: Color) -> Color {
+        self.color(tag).unwrap_or(default)
+    }
+}
+
+/// `a`, `a/b` and `a/b/c` for the tag `a/b/c`
+fn tag_ancestry(tag: &str) -> impl DoubleEndedIterator<Item = &str> {
+    tag.match_indices('/')
+        .map(|(i, _)| &tag[..i])
+        .chain(std::iter::once(tag))
+        .filter(|path| !path.is_empty())
+        .collect::<Vec<_>>()
+        .into_iter()
+}
+
+/// Nesting depth of a tag path, 0 for top-level tags
+pub fn tag_depth(tag: &str) -> usize {
+    tag.trim_matches('/').matches('/').count()
+}
+
+/// Last segment of a tag path
+pub fn tag_leaf(tag: &str) -> &str {
+    tag.trim_end_matches('/').rsplit('/').next().unwrap_or(tag)
+}
+
 #[derive(Default)]
 pub struct TagsState {
     pub scroll: ScrollState,
     pub tags: Vec<(String, usize)>,
     pub selected: usize,
     pub selected_tags: HashSet<String>,
+    pub excluded_tags: HashSet<String>,
 }
 
 impl TagsState {
@@ -35,10 +101,14 @@ impl TagsState {
         self.scroll.reset();
         self.selected = 0;
         self.selected_tags.clear();
-        
+        self.excluded_tags.clear();
+
         let Some(filter_tags) = active_filter else { return };
         for tag in filter_tags {
-            self.selected_tags.insert(tag.clone());
+            match tag.strip_prefix('-') {
+                Some(excluded) => self.excluded_tags.insert(excluded.to_string()),
+                None => self.selected_tags.insert(tag.clone()),
+            };
         }
     }
 
@@ -72,6 +142,7 @@ impl TagsState {
 
     pub fn toggle_selected(&mut self) {
         let Some((tag, _)) = self.tags.get(self.selected) else { return };
+        self.excluded_tags.remove(tag);
         if self.selected_tags.contains(tag) {
             self.selected_tags.remove(tag);
         } else {
@@ -79,30 +150,81 @@ impl TagsState {
         }
     }
 
+    /// Mark the tag under the cursor to be left out of the filter, or unmark it
+    pub fn toggle_excluded(&mut self) {
+        let Some((tag, _)) = self.tags.get(self.selected) else { return };
+        self.selected_tags.remove(tag);
+        if !self.excluded_tags.remove(tag) {
+            self.excluded_tags.insert(tag.clone());
+        }
+    }
+
+    /// Move the cursor to `tag` if it is listed
+    pub fn select_tag(&mut self, tag: &str) {
+        if let Some(index) = self.tags.iter().position(|(t, _)| t == tag) {
+            self.selected = index;
+        }
+    }
+
     pub fn get_selected_tags(&self) -> Vec<String> {
         self.selected_tags.iter().cloned().collect()
     }
+
+    /// Selected tags and `-tag` exclusions, in list order
+    pub fn get_filter(&self) -> Vec<String> {
+        self.tags
+            .iter()
+            .filter_map(|(tag, _)| {
+                if self.selected_tags.contains(tag) {
+                    Some(tag.clone())
+                } else if self.excluded_tags.contains(tag) {
+                    Some(format!("-{}", tag))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
 }
 
+/// Count credentials per tag, parents included, ordered as a tree with
+/// the most used siblings first
 fn aggregate_tags(credentials: &[Credential]) -> Vec<(String, usize)> {
     let mut counts: HashMap<String, usize> = HashMap::new();
     for cred in credentials {
-        for tag in &cred.tags {
-            *counts.entry(tag.clone()).or_insert(0) += 1;
+        let paths: HashSet<&str> = cred.tags.iter().flat_map(|tag| tag_ancestry(tag)).collect();
+        for path in paths {
+            *counts.entry(path.to_string()).or_insert(0) += 1;
         }
     }
-    let mut tags: Vec<_> = counts.into_iter().collect();
-    tags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let mut tags: Vec<_> = counts.iter().map(|(tag, count)| (tag.clone(), *count)).collect();
+    tags.sort_by_cached_key(|(tag, _)| {
+        tag_ancestry(tag)
+            .map(|path| (Reverse(counts.get(path).copied().unwrap_or(0)), path.to_string()))
+            .collect::<Vec<_>>()
+    });
     tags
 }
 
 pub struct TagsPopup<'a> {
     state: &'a TagsState,
+    colors: Option<&'a TagColors>,
+    tag_match: TagMatch,
 }
 
 impl<'a> TagsPopup<'a> {
     pub fn new(state: &'a TagsState) -> Self {
-        Self { state }
+        Self { state, colors: None, tag_match: TagMatch::default() }
+    }
+
+    pub fn tag_colors(mut self, colors: &'a TagColors) -> Self {
+        self.colors = Some(colors);
+        self
+    }
+
+    pub fn tag_match(mut self, tag_match: TagMatch) -> Self {
+        self.tag_match = tag_match;
+        self
     }
 
     pub fn visible_height(area: Rect) -> u16 {
@@ -133,7 +255,7 @@ impl Widget for TagsPopup<'_> {
         let needs_v_scroll = max_v > 0;
 
         // Render header (always at top)
-        render_tags_header(inner, buf);
+        render_tags_header(inner, buf, self.tag_match);
         render_separator_line(buf, inner.x, inner.y + 1, inner.width);
 
         // Calculate list area that reserves bottom line for scroll indicator
@@ -142,7 +264,7 @@ impl Widget for TagsPopup<'_> {
         // Calculate list area that reserves bottom line for scroll indicator
         let scroll_offset = calculate_scroll_offset(self.state.selected, list_area_height);
 
-        render_tags_list(inner, buf, list_start_y, list_area_height, scroll_offset, self.state);
+        render_tags_list(inner, buf, list_start_y, list_area_height, scroll_offset, self.state, self.colors);
 
         // Render scroll indicator
         if needs_v_scroll {
@@ -158,9 +280,11 @@ fn calculate_tags_height(count: usize, area_height: u16) -> u16 {
     desired.min((available * 75) / 100).max(8)
 }
 
-fn render_tags_header(inner: Rect, buf: &mut Buffer) {
+fn render_tags_header(inner: Rect, buf: &mut Buffer, tag_match: TagMatch) {
     let style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
     buf.set_string(inner.x, inner.y, "TAG", style);
+    let logic = format!("match {}", tag_match.name());
+    buf.set_string(inner.x + 5, inner.y, &logic, Style::default().fg(Color::DarkGray));
     buf.set_string(inner.x + inner.width - 5, inner.y, "COUNT", style);
 }
 
@@ -171,13 +295,15 @@ fn render_tags_list(
     visible_count: usize,
     scroll_offset: usize,
     state: &TagsState,
+    colors: Option<&TagColors>,
 ) {
     for (i, (tag, count)) in state.tags.iter().enumerate().skip(scroll_offset) {
         let row = i - scroll_offset;
         if row >= visible_count {
             break;
         }
-        render_tag_row(inner, buf, start_y + row as u16, i, tag, *count, state);
+        let color = colors.and_then(|c| c.color(tag)).unwrap_or(Color::White);
+        render_tag_row(inner, buf, start_y + row as u16, i, (tag, color), *count, state);
     }
 }
 
@@ -190,33 +316,51 @@ fn render_tag_row(
     buf: &mut Buffer,
     y: u16,
     idx: usize,
-    tag: &str,
+    (tag, color): (&str, Color),
     count: usize,
     state: &TagsState,
 ) {
     let is_cursor = idx == state.selected;
-    let is_checked = state.selected_tags.contains(tag);
+    let check = if state.excluded_tags.contains(tag) {
+        TagCheck::Excluded
+    } else if state.selected_tags.contains(tag) {
+        TagCheck::Selected
+    } else {
+        TagCheck::None
+    };
 
     if is_cursor {
         highlight_row(buf, inner.x, y, inner.width);
     }
 
-    render_tag_checkbox(buf, inner.x, y, is_checked, is_cursor);
-    render_tag_name(buf, inner.x + 2, y, inner.width, tag, is_cursor);
+    render_tag_checkbox(buf, inner.x, y, check, is_cursor);
+    render_tag_name(buf, inner.x + 2, y, inner.width, tag, color, is_cursor);
     render_tag_count(buf, inner.x + inner.width - 5, y, count, is_cursor);
 }
 
-fn render_tag_checkbox(buf: &mut Buffer, x: u16, y: u16, checked: bool, highlight: bool) {
-    let icon = if checked { "󰗠 " } else { "󰄰 " };
-    let style = Style::default().fg(Color::Green);
+enum TagCheck {
+    None,
+    Selected,
+    Excluded,
+}
+
+fn render_tag_checkbox(buf: &mut Buffer, x: u16, y: u16, check: TagCheck, highlight: bool) {
+    let (icon, color) = match check {
+        TagCheck::None => ("󰄰 ", Color::Green),
+        TagCheck::Selected => ("󰗠 ", Color::Green),
+        TagCheck::Excluded => ("󰅙 ", Color::Red),
+    };
+    let style = Style::default().fg(color);
     let style = if highlight { style.bg(Color::DarkGray) } else { style };
     buf.set_string(x, y, icon, style);
 }
 
-fn render_tag_name(buf: &mut Buffer, x: u16, y: u16, inner_width: u16, tag: &str, highlight: bool) {
+/// Nested tags show only their last segment, indented under the parent
+fn render_tag_name(buf: &mut Buffer, x: u16, y: u16, inner_width: u16, tag: &str, color: Color, highlight: bool) {
     let max_width = (inner_width as usize).saturating_sub(8);
-    let display = truncate_with_ellipsis(tag, max_width);
-    let style = Style::default().fg(Color::White);
+    let indented = format!("{}{}", "  ".repeat(tag_depth(tag)), tag_leaf(tag));
+    let display = truncate_with_ellipsis(&indented, max_width);
+    let style = Style::default().fg(color);
     let style = if highlight { style.bg(Color::DarkGray) } else { style };
     buf.set_string(x, y, &display, style);
 }
@@ -226,3 +370,51 @@ fn render_tag_count(buf: &mut Buffer, x: u16, y: u16, count: usize, highlight: b
     let style = if highlight { style.bg(Color::DarkGray) } else { style };
     buf.set_string(x, y, format!("{:>5}", count), style);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::CredentialType;
+
+    fn tagged(tags: &[&str]) -> Credential {
+        let mut cred = Credential::new("c".into(), CredentialType::Password, "enc".into());
+        cred.tags = tags.iter().map(|t| t.to_string()).collect();
+        cred
+    }
+
+    #[test]
+    fn test_tag_colors() {
+        let colors = TagColors::parse("work:blue, personal/bank:#ff8800").unwrap();
+        assert_eq!(colors.color("work"), Some(Color::Blue));
+        assert_eq!(colors.color("work/ci"), Some(Color::Blue));
+        assert_eq!(colors.color("personal/bank/joint"), Some(Color::Rgb(0xff, 0x88, 0x00)));
+        assert_eq!(colors.color("personal"), None);
+        assert_eq!(TagColors::parse(&colors.spec()).unwrap(), colors);
+        assert!(TagColors::parse("work:nocolor").is_err());
+    }
+
+    #[test]
+    fn test_aggregate_tags_nests() {
+        let creds = [tagged(&["work/ci", "work/aws"]), tagged(&["work/aws"]), tagged(&["home"])];
+        let tags = aggregate_tags(&creds);
+        let names: Vec<&str> = tags.iter().map(|(t, _)| t.as_str()).collect();
+        assert_eq!(names, ["work", "work/aws", "work/ci", "home"]);
+        assert_eq!(tags[0].1, 2);
+        assert_eq!((tag_depth("work/aws"), tag_leaf("work/aws")), (1, "aws"));
+    }
+
+    #[test]
+    fn test_filter_with_exclusions() {
+        let creds = [tagged(&["work", "home"]), tagged(&["work"])];
+        let filter = vec!["-home".to_string()];
+        let mut state = TagsState::new();
+        state.set_tags_from_credentials(&creds, Some(&filter));
+        assert_eq!(state.get_filter(), filter);
+
+        state.select_tag("work");
+        state.toggle_selected();
+        assert_eq!(state.get_filter(), ["work", "-home"]);
+        state.toggle_excluded();
+        assert_eq!(state.get_filter(), ["-work", "-home"]);
+    }
+}
@@ -0,0 +1,123 @@
+//! Trash popup and state for `:trash`
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style},
+    widgets::{Clear, Widget},
+};
+
+use crate::db::TrashedCredential;
+
+use super::layout::{
+    centered_rect_fixed, create_popup_block, highlight_row, render_empty_message,
+    truncate_with_ellipsis,
+};
+use super::scroll::render_v_scroll_indicator;
+
+#[derive(Default)]
+pub struct TrashState {
+    /// Most recently deleted first
+    pub items: Vec<TrashedCredential>,
+    pub selected: usize,
+}
+
+impl TrashState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the items, keeping the cursor in range
+    pub fn set_items(&mut self, items: Vec<TrashedCredential>) {
+        self.items = items;
+        self.selected = self.selected.min(self.items.len().saturating_sub(1));
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn scroll_down(&mut self) {
+        if self.selected + 1 < self.items.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn home(&mut self) {
+        self.selected = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.selected = self.items.len().saturating_sub(1);
+    }
+
+    pub fn selected_item(&self) -> Option<&TrashedCredential> {
+        self.items.get(self.selected)
+    }
+}
+
+pub struct TrashPopup<'a> {
+    state: &'a TrashState,
+}
+
+impl<'a> TrashPopup<'a> {
+    pub fn new(state: &'a TrashState) -> Self {
+        Self { state }
+    }
+}
+
+impl Widget for TrashPopup<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let items = &self.state.items;
+        let available = area.height.saturating_sub(2);
+        let height = (items.len() as u16).saturating_add(2).min((available * 75) / 100).max(6);
+        let popup = centered_rect_fixed(70, height, area, true);
+        Clear.render(popup, buf);
+
+        let title = format!(" Trash ({}) ", items.len());
+        let block = create_popup_block(&title, Color::Red);
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        if items.is_empty() {
+            render_empty_message(inner, buf, "Trash is empty");
+            return;
+        }
+
+        let visible = inner.height as usize;
+        let selected = self.state.selected;
+        let scroll_offset = if selected >= visible { selected - visible + 1 } else { 0 };
+
+        for (i, item) in items.iter().enumerate().skip(scroll_offset).take(visible) {
+            let y = inner.y + (i - scroll_offset) as u16;
+            render_trash_row(inner, buf, y, item, i == selected);
+        }
+
+        let max_v = items.len().saturating_sub(visible);
+        if max_v > 0 {
+            render_v_scroll_indicator(buf, &popup, scroll_offset, max_v, Color::Red);
+        }
+    }
+}
+
+fn render_trash_row(inner: Rect, buf: &mut Buffer, y: u16, item: &TrashedCredential, is_cursor: bool) {
+    if is_cursor {
+        highlight_row(buf, inner.x, y, inner.width);
+    }
+    let bg = if is_cursor { Color::DarkGray } else { Color::Reset };
+    let cred = &item.credential;
+
+    let date = item.deleted_at.format("%Y-%m-%d %H:%M").to_string();
+    buf.set_string(inner.x + 1, y, &date, Style::default().fg(Color::DarkGray).bg(bg));
+
+    let x = inner.x + date.len() as u16 + 3;
+    buf.set_string(x, y, cred.credential_type.icon(), Style::default().fg(Color::Red).bg(bg));
+
+    let label = match &cred.username {
+        Some(username) => format!("{} ({})", cred.name, username),
+        None => cred.name.clone(),
+    };
+    let max_width = (inner.width as usize).saturating_sub(date.len() + 6);
+    let display = truncate_with_ellipsis(&label, max_width);
+    buf.set_string(x + 2, y, &display, Style::default().fg(Color::White).bg(bg));
+}
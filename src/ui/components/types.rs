@@ -0,0 +1,145 @@
+//! Credential types popup and state
+
+use std::collections::{HashMap, HashSet};
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Clear, Widget},
+};
+
+use crate::db::{Credential, CredentialType};
+
+use super::layout::{
+    centered_rect_fixed, create_popup_block, highlight_row, render_empty_message, render_separator_line,
+};
+
+#[derive(Default)]
+pub struct TypesState {
+    pub types: Vec<(CredentialType, usize)>,
+    pub selected: usize,
+    pub selected_types: HashSet<CredentialType>,
+}
+
+impl TypesState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Types in use with their counts, the most used first
+    pub fn set_types_from_credentials(&mut self, credentials: &[Credential], active_filter: Option<&[CredentialType]>) {
+        let mut counts: HashMap<CredentialType, usize> = HashMap::new();
+        for cred in credentials {
+            *counts.entry(cred.credential_type).or_insert(0) += 1;
+        }
+        self.types = counts.into_iter().collect();
+        self.types.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.display_name().cmp(b.0.display_name())));
+        self.selected = 0;
+        self.selected_types = active_filter.unwrap_or_default().iter().copied().collect();
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn scroll_down(&mut self) {
+        if self.selected < self.types.len().saturating_sub(1) {
+            self.selected += 1;
+        }
+    }
+
+    pub fn home(&mut self) {
+        self.selected = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.selected = self.types.len().saturating_sub(1);
+    }
+
+    pub fn toggle_selected(&mut self) {
+        let Some((cred_type, _)) = self.types.get(self.selected) else { return };
+        if !self.selected_types.remove(cred_type) {
+            self.selected_types.insert(*cred_type);
+        }
+    }
+
+    /// Checked types in the order they are listed
+    pub fn get_selected_types(&self) -> Vec<CredentialType> {
+        self.types.iter().map(|(t, _)| *t).filter(|t| self.selected_types.contains(t)).collect()
+    }
+}
+
+pub struct TypesPopup<'a> {
+    state: &'a TypesState,
+}
+
+impl<'a> TypesPopup<'a> {
+    pub fn new(state: &'a TypesState) -> Self {
+        Self { state }
+    }
+}
+
+impl Widget for TypesPopup<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        // 2 border + 2 header, one row per type
+        let height = (self.state.types.len() as u16 + 4).max(6);
+        let popup = centered_rect_fixed(40, height, area, true);
+        Clear.render(popup, buf);
+
+        let block = create_popup_block(" Types ", Color::Cyan);
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        if self.state.types.is_empty() {
+            render_empty_message(inner, buf, "No credentials");
+            return;
+        }
+
+        let header = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+        buf.set_string(inner.x, inner.y, "TYPE", header);
+        buf.set_string(inner.x + inner.width - 5, inner.y, "COUNT", header);
+        render_separator_line(buf, inner.x, inner.y + 1, inner.width);
+
+        let visible = inner.height.saturating_sub(2) as usize;
+        for (i, (cred_type, count)) in self.state.types.iter().enumerate().take(visible) {
+            let y = inner.y + 2 + i as u16;
+            render_type_row(inner, buf, y, (*cred_type, *count), i == self.state.selected, self.state.selected_types.contains(cred_type));
+        }
+    }
+}
+
+fn render_type_row(inner: Rect, buf: &mut Buffer, y: u16, (cred_type, count): (CredentialType, usize), is_cursor: bool, is_checked: bool) {
+    if is_cursor {
+        highlight_row(buf, inner.x, y, inner.width);
+    }
+    let bg = if is_cursor { Color::DarkGray } else { Color::Reset };
+
+    let checkbox = if is_checked { "󰗠 " } else { "󰄰 " };
+    buf.set_string(inner.x, y, checkbox, Style::default().fg(Color::Green).bg(bg));
+    buf.set_string(inner.x + 2, y, cred_type.icon(), Style::default().fg(Color::Cyan).bg(bg));
+    buf.set_string(inner.x + 4, y, cred_type.display_name(), Style::default().fg(Color::White).bg(bg));
+    buf.set_string(inner.x + inner.width - 5, y, format!("{:>5}", count), Style::default().fg(Color::Cyan).bg(bg));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cred(cred_type: CredentialType) -> Credential {
+        Credential::new("c".into(), cred_type, "enc".into())
+    }
+
+    #[test]
+    fn test_types_from_credentials() {
+        let creds = [cred(CredentialType::Note), cred(CredentialType::ApiKey), cred(CredentialType::ApiKey)];
+        let mut state = TypesState::new();
+        state.set_types_from_credentials(&creds, Some(&[CredentialType::Note]));
+        assert_eq!(state.types, vec![(CredentialType::ApiKey, 2), (CredentialType::Note, 1)]);
+
+        state.toggle_selected();
+        assert_eq!(state.get_selected_types(), vec![CredentialType::ApiKey, CredentialType::Note]);
+        state.toggle_selected();
+        assert_eq!(state.get_selected_types(), vec![CredentialType::Note]);
+    }
+}
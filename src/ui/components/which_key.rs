@@ -0,0 +1,58 @@
+//! Hint panel listing what can follow a pending prefix key
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Clear, Widget},
+};
+
+use crate::input::keymap::continuations;
+
+use super::layout::create_popup_block;
+
+/// Width of the key column
+const KEY_WIDTH: u16 = 6;
+
+/// Sits at the bottom right of the content, above the status line
+pub struct WhichKeyPanel {
+    prefix: char,
+}
+
+impl WhichKeyPanel {
+    pub fn new(prefix: char) -> Self {
+        Self { prefix }
+    }
+}
+
+impl Widget for WhichKeyPanel {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let entries: Vec<_> = continuations(self.prefix).collect();
+        if entries.is_empty() {
+            return;
+        }
+        let text_width = entries.iter().map(|e| e.description.len() as u16).max().unwrap_or(0);
+        let width = (KEY_WIDTH + text_width + 4).min(area.width);
+        let height = (entries.len() as u16 + 2).min(area.height.saturating_sub(2));
+        let popup = Rect::new(
+            area.x + area.width - width,
+            area.y + area.height.saturating_sub(2 + height),
+            width,
+            height,
+        );
+        Clear.render(popup, buf);
+
+        let title = format!(" {} ", self.prefix);
+        let block = create_popup_block(&title, Color::Yellow);
+        let inner = block.inner(popup);
+        block.render(popup, buf);
+
+        let key_style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+        for (i, entry) in entries.iter().take(inner.height as usize).enumerate() {
+            let y = inner.y + i as u16;
+            buf.set_stringn(inner.x + 1, y, entry.keys, KEY_WIDTH as usize, key_style);
+            let max = inner.width.saturating_sub(KEY_WIDTH + 1) as usize;
+            buf.set_stringn(inner.x + 1 + KEY_WIDTH, y, entry.description, max, Style::default().fg(Color::White));
+        }
+    }
+}
@@ -4,9 +4,11 @@
 
 pub mod components;
 pub mod renderer;
+pub mod theme;
 
 // Re-exports
 pub use components::{
     MessageType,
     PasswordDialog,
 };
+pub use theme::Theme;
@@ -9,16 +9,31 @@ use ratatui::{
     Frame,
 };
 
+use super::theme::Theme;
 use super::components::{
-    ConfirmDialog, CredentialDetail, CredentialForm, CredentialFormWidget, CredentialItem,
+    ConfirmDialog, CredentialDetail, CredentialForm, CredentialFormWidget,
     CredentialList, DetailView, EmptyState, HelpBar, HelpScreen, ListViewState, MessageType,
     PasswordDialog, StatusLine,
 };
+use super::components::dialogs::ProgressPopup;
+use crate::db::{Credential, CredentialType};
 use crate::input::InputMode;
 use crate::ui::components::help::HelpState;
+use crate::ui::components::history::{HistoryScreen, HistoryState};
+use crate::ui::components::stats::{StatsScreen, StatsState};
 use crate::ui::components::logs::{LogsScreen, LogsState};
-use crate::ui::components::tags::{TagsPopup, TagsState};
+use crate::ui::components::tags::{TagColors, TagsPopup, TagsState};
+use crate::ui::components::types::{TypesPopup, TypesState};
+use crate::ui::components::duplicates::{DuplicatesPopup, DuplicatesState};
+use crate::ui::components::report::{ReportPopup, ReportState};
+use crate::ui::components::backups::{BackupsPopup, BackupsState};
+use crate::ui::components::scroll::ScrollState;
+use crate::ui::components::trash::{TrashPopup, TrashState};
+use crate::ui::components::which_key::WhichKeyPanel;
 use crate::ui::components::export::{ExportDialog, ExportDialogWidget};
+use crate::ui::components::import::{ImportDialog, ImportDialogWidget, ImportReviewWidget};
+use crate::ui::components::share::{SharePopup, ShareView};
+use crate::vault::search::{self, SearchMode, TagMatch};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum View {
@@ -27,24 +42,70 @@ pub enum View {
     Form,
 }
 
+/// How the detail view shares the screen with the credential list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DetailLayout {
+    /// List and detail side by side when the terminal is wide enough
+    #[default]
+    Split,
+    Full,
+}
+
+impl DetailLayout {
+    pub fn toggle(self) -> Self {
+        match self {
+            Self::Split => Self::Full,
+            Self::Full => Self::Split,
+        }
+    }
+}
+
+/// Narrowest terminal that still shows list and detail side by side
+const MIN_SPLIT_WIDTH: u16 = 100;
+
 pub struct UiState<'a> {
     pub view: View,
+    pub detail_layout: DetailLayout,
     pub mode: InputMode,
-    pub credentials: &'a [CredentialItem],
+    pub credentials: &'a [Credential],
     pub list_state: &'a mut ListViewState,
     pub list_area: &'a mut Option<Rect>,
+    /// Offset of the detail panel, kept within its content as it renders
+    pub detail_scroll: &'a mut ScrollState,
     pub selected_detail: Option<&'a CredentialDetail>,
     pub search_query: Option<&'a str>,
+    /// Credentials searched so far by a running deep search, and the total
+    pub search_progress: Option<(usize, usize)>,
+    pub search_mode: SearchMode,
     pub filter_tags: Option<&'a [String]>,
+    pub tag_match: TagMatch,
+    pub filter_types: Option<&'a [CredentialType]>,
+    pub filter_expiring: bool,
+    pub filter_rotation: bool,
     pub command_buffer: Option<&'a str>,
+    /// First key of a two-key binding, waiting for the second
+    pub pending_key: Option<char>,
     pub message: Option<(&'a str, MessageType)>,
     pub confirm_message: Option<&'a str>,
     pub password_prompt: Option<PasswordPrompt<'a>>,
     pub credential_form: Option<&'a CredentialForm>,
     pub help_state: &'a HelpState,
     pub logs_state: &'a LogsState,
+    pub history_state: &'a HistoryState,
+    pub stats_state: &'a StatsState,
     pub tags_state: &'a TagsState,
+    pub types_state: &'a TypesState,
+    pub duplicates_state: &'a DuplicatesState,
+    pub report_state: &'a ReportState,
+    pub backups_state: &'a BackupsState,
+    pub trash_state: &'a TrashState,
     pub export_dialog: Option<&'a ExportDialog>,
+    pub import_dialog: Option<&'a ImportDialog>,
+    pub share_view: Option<&'a ShareView>,
+    pub theme: Theme,
+    pub tag_colors: &'a TagColors,
+    /// Spinner for a job that has the vault on a worker thread
+    pub progress: Option<ProgressPopup<'a>>,
 }
 
 pub struct PasswordPrompt<'a> {
@@ -74,6 +135,8 @@ impl Renderer {
         render_status_line(frame, chunks[1], state);
         render_help_bar(frame, chunks[2], state.mode);
         render_overlays(frame, size, state);
+        render_progress_overlay(frame, size, state);
+        state.theme.apply(frame.buffer_mut());
     }
 }
 
@@ -97,7 +160,7 @@ fn render_content(frame: &mut Frame, area: Rect, state: &mut UiState) {
 }
 
 fn render_status_line(frame: &mut Frame, area: Rect, state: &UiState) {
-    let mut status = StatusLine::new(state.mode);
+    let mut status = StatusLine::new(state.mode).monochrome(state.theme.is_monochrome());
 
     if let Some(buffer) = state.command_buffer {
         status = status.command_buffer(buffer);
@@ -106,11 +169,30 @@ fn render_status_line(frame: &mut Frame, area: Rect, state: &UiState) {
     }
 
     if let Some(tags) = state.filter_tags {
-        status = status.filter_tags(tags);
+        status = status.filter_tags(tags, state.tag_match.name());
+    }
+
+    let types_label;
+    if let Some(types) = state.filter_types {
+        types_label = types.iter().map(|t| t.display_name()).collect::<Vec<_>>().join(",");
+        status = status.filter_types(&types_label);
     }
 
+    status = status.expiring(state.filter_expiring).rotation(state.filter_rotation);
+
+    let search_label;
     if let Some(query) = state.search_query {
-        status = status.search_query(query);
+        search_label = match state.search_progress {
+            Some((done, total)) => format!("{} (notes {}%)", query, done * 100 / total.max(1)),
+            None => query.to_string(),
+        };
+        status = status.search_query(&search_label);
+    }
+
+    if state.view != View::Form {
+        status = status
+            .sort(state.list_state.sort.name())
+            .marked(state.list_state.selection_count(state.credentials));
     }
 
     if let Some(selected) = state.list_state.selected() {
@@ -135,12 +217,16 @@ fn render_list(frame: &mut Frame, area: Rect, state: &mut UiState) {
         return;
     }
 
-    let block = create_credentials_block(Color::Magenta);
-    let list = CredentialList::new(state.credentials).block(block);
-    frame.render_stateful_widget(list, area, state.list_state);
+    render_credential_list(frame, area, state, Color::Magenta);
 }
 
 fn render_detail(frame: &mut Frame, area: Rect, state: &mut UiState) {
+    if state.detail_layout == DetailLayout::Full || area.width < MIN_SPLIT_WIDTH {
+        *state.list_area = None;
+        render_detail_panel(frame, area, state);
+        return;
+    }
+
     let half = area.width / 2;
     let chunks = Layout::default()
         .direction(Direction::Horizontal)
@@ -148,22 +234,36 @@ fn render_detail(frame: &mut Frame, area: Rect, state: &mut UiState) {
         .split(area);
 
     render_detail_list(frame, chunks[0], state);
-    render_detail_panel(frame, chunks[1], state.selected_detail);
+    render_detail_panel(frame, chunks[1], state);
 }
 
 fn render_detail_list(frame: &mut Frame, area: Rect, state: &mut UiState) {
     *state.list_area = Some(area);
 
-    let block = create_credentials_block(Color::DarkGray);
-    let list = CredentialList::new(state.credentials).block(block);
-    frame.render_stateful_widget(list, area, state.list_state);
+    render_credential_list(frame, area, state, Color::DarkGray);
 }
 
-fn render_detail_panel(frame: &mut Frame, area: Rect, detail: Option<&CredentialDetail>) {
-    match detail {
-        Some(d) => frame.render_widget(DetailView::new(d), area),
-        None => render_empty_detail_panel(frame, area),
+fn render_credential_list(frame: &mut Frame, area: Rect, state: &mut UiState, border: Color) {
+    let mut list = CredentialList::new(state.credentials)
+        .block(create_credentials_block(border))
+        .monochrome(state.theme.is_monochrome())
+        .tag_colors(state.tag_colors);
+    if let Some(query) = state.search_query {
+        let (mode, query) = search::parse_query(query, state.search_mode);
+        list = list.search(mode, query);
     }
+    frame.render_stateful_widget(list, area, state.list_state);
+}
+
+fn render_detail_panel(frame: &mut Frame, area: Rect, state: &mut UiState) {
+    let Some(d) = state.selected_detail else {
+        render_empty_detail_panel(frame, area);
+        return;
+    };
+    let view = DetailView::new(d).monochrome(state.theme.is_monochrome()).tag_colors(state.tag_colors);
+    let offset = state.detail_scroll.v_scroll.min(view.max_scroll(area));
+    state.detail_scroll.v_scroll = offset;
+    frame.render_widget(view.scroll(offset), area);
 }
 
 fn render_empty_detail_panel(frame: &mut Frame, area: Rect) {
@@ -208,9 +308,19 @@ fn render_overlays(frame: &mut Frame, area: Rect, state: &UiState) {
         return;
     }
 
+    render_which_key_overlay(frame, area, state);
     render_tags_overlay(frame, state);
+    render_types_overlay(frame, state);
+    render_duplicates_overlay(frame, state);
+    render_report_overlay(frame, state);
+    render_backups_overlay(frame, state);
+    render_trash_overlay(frame, state);
     render_logs_overlay(frame, state);
+    render_history_overlay(frame, state);
+    render_stats_overlay(frame, state);
     render_export_overlay(frame, area, state);
+    render_import_overlay(frame, area, state);
+    render_share_overlay(frame, area, state);
 
     if render_confirm_overlay(frame, area, state) {
         return;
@@ -219,6 +329,12 @@ fn render_overlays(frame: &mut Frame, area: Rect, state: &UiState) {
     render_password_overlay(frame, area, state);
 }
 
+fn render_progress_overlay(frame: &mut Frame, area: Rect, state: &UiState) {
+    if let Some(popup) = state.progress {
+        frame.render_widget(popup, area);
+    }
+}
+
 fn render_help_overlay(frame: &mut Frame, area: Rect, state: &UiState) -> bool {
     if state.mode != InputMode::Help {
         return false;
@@ -231,7 +347,66 @@ fn render_tags_overlay(frame: &mut Frame, state: &UiState) {
     if state.mode != InputMode::Tags {
         return;
     }
-    TagsPopup::new(state.tags_state).render(frame.area(), frame.buffer_mut());
+    TagsPopup::new(state.tags_state)
+        .tag_colors(state.tag_colors)
+        .tag_match(state.tag_match)
+        .render(frame.area(), frame.buffer_mut());
+}
+
+fn render_types_overlay(frame: &mut Frame, state: &UiState) {
+    if state.mode != InputMode::Types {
+        return;
+    }
+    TypesPopup::new(state.types_state).render(frame.area(), frame.buffer_mut());
+}
+
+fn render_duplicates_overlay(frame: &mut Frame, state: &UiState) {
+    if state.mode != InputMode::Duplicates {
+        return;
+    }
+    DuplicatesPopup::new(state.duplicates_state).render(frame.area(), frame.buffer_mut());
+}
+
+fn render_report_overlay(frame: &mut Frame, state: &UiState) {
+    if state.mode != InputMode::Report {
+        return;
+    }
+    ReportPopup::new(state.report_state).render(frame.area(), frame.buffer_mut());
+}
+
+fn render_backups_overlay(frame: &mut Frame, state: &UiState) {
+    if state.mode != InputMode::Restore {
+        return;
+    }
+    BackupsPopup::new(state.backups_state).render(frame.area(), frame.buffer_mut());
+}
+
+fn render_trash_overlay(frame: &mut Frame, state: &UiState) {
+    if state.mode != InputMode::Trash {
+        return;
+    }
+    TrashPopup::new(state.trash_state).render(frame.area(), frame.buffer_mut());
+}
+
+fn render_history_overlay(frame: &mut Frame, state: &UiState) {
+    if state.mode != InputMode::History {
+        return;
+    }
+    HistoryScreen::new(state.history_state).render(frame.area(), frame.buffer_mut());
+}
+
+fn render_which_key_overlay(frame: &mut Frame, area: Rect, state: &UiState) {
+    let Some(prefix) = state.pending_key.filter(|_| state.mode == InputMode::Normal) else {
+        return;
+    };
+    WhichKeyPanel::new(prefix).render(area, frame.buffer_mut());
+}
+
+fn render_stats_overlay(frame: &mut Frame, state: &UiState) {
+    if state.mode != InputMode::Stats {
+        return;
+    }
+    StatsScreen::new(state.stats_state).render(frame.area(), frame.buffer_mut());
 }
 
 fn render_logs_overlay(frame: &mut Frame, state: &UiState) {
@@ -250,6 +425,24 @@ fn render_export_overlay(frame: &mut Frame, area: Rect, state: &UiState) {
     }
 }
 
+fn render_import_overlay(frame: &mut Frame, area: Rect, state: &UiState) {
+    let Some(dialog) = state.import_dialog else { return };
+    match state.mode {
+        InputMode::Import => ImportDialogWidget::new(dialog).render(area, frame.buffer_mut()),
+        InputMode::ImportReview => ImportReviewWidget::new(dialog).render(area, frame.buffer_mut()),
+        _ => {}
+    }
+}
+
+fn render_share_overlay(frame: &mut Frame, area: Rect, state: &UiState) {
+    if state.mode != InputMode::Share {
+        return;
+    }
+    if let Some(view) = state.share_view {
+        SharePopup::new(view).render(area, frame.buffer_mut());
+    }
+}
+
 fn render_confirm_overlay(frame: &mut Frame, area: Rect, state: &UiState) -> bool {
     if state.mode != InputMode::Confirm {
         return false;
@@ -267,7 +460,8 @@ fn render_password_overlay(frame: &mut Frame, area: Rect, state: &UiState) {
         None => return,
     };
 
-    let mut dialog = PasswordDialog::new(prompt.title, prompt.prompt, prompt.value, prompt.cursor);
+    let mut dialog = PasswordDialog::new(prompt.title, prompt.prompt, prompt.value, prompt.cursor)
+        .monochrome(state.theme.is_monochrome());
     if let Some(err) = prompt.error {
         dialog = dialog.error(err);
     }
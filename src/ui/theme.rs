@@ -0,0 +1,225 @@
+//! Color themes
+//!
+//! Components draw with the dark palette; other themes remap the finished
+//! frame so the same widgets stay readable on light backgrounds and in
+//! terminals limited to the 16 basic colors. The monochrome theme drops
+//! color entirely, and components add text markers where color carried meaning.
+
+use ratatui::{
+    buffer::{Buffer, Cell},
+    style::{Color, Modifier},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+    /// Only the 16 basic ANSI colors, for terminals without 256/true color
+    Basic,
+    /// No color, with reverse video and underline for highlights (`NO_COLOR`)
+    Mono,
+}
+
+/// Approximate RGB values of the 16 ANSI colors, for mapping down true colors
+const ANSI_PALETTE: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+impl Theme {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Dark => "dark",
+            Self::Light => "light",
+            Self::Basic => "16color",
+            Self::Mono => "mono",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::Dark),
+            "light" => Some(Self::Light),
+            "16color" | "basic" => Some(Self::Basic),
+            "mono" | "nocolor" => Some(Self::Mono),
+            _ => None,
+        }
+    }
+
+    /// Whether components should mark state with text instead of color
+    pub fn is_monochrome(&self) -> bool {
+        *self == Self::Mono
+    }
+
+    /// Guess from the environment: `NO_COLOR`, `COLORFGBG` for the background,
+    /// then `COLORTERM` and `TERM` for color support
+    pub fn detect() -> Self {
+        if std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+            return Self::Mono;
+        }
+        let colorfgbg = std::env::var("COLORFGBG").ok();
+        let colorterm = std::env::var("COLORTERM").ok();
+        let term = std::env::var("TERM").ok();
+        Self::detect_from(colorfgbg.as_deref(), colorterm.as_deref(), term.as_deref())
+    }
+
+    fn detect_from(colorfgbg: Option<&str>, colorterm: Option<&str>, term: Option<&str>) -> Self {
+        let background = colorfgbg
+            .and_then(|v| v.rsplit(';').next())
+            .and_then(|bg| bg.parse::<u8>().ok());
+        if matches!(background, Some(7 | 9..=15)) {
+            return Self::Light;
+        }
+
+        let rich = colorterm.is_some_and(|c| !c.is_empty())
+            || term.is_some_and(|t| t.contains("256color") || t.contains("direct"));
+        match term {
+            Some(t) if !rich && !t.is_empty() => Self::Basic,
+            _ => Self::Dark,
+        }
+    }
+
+    /// Remap every cell drawn with the dark palette into this theme
+    pub fn apply(&self, buf: &mut Buffer) {
+        if *self == Self::Dark {
+            return;
+        }
+        if *self == Self::Mono {
+            buf.content.iter_mut().for_each(strip_color);
+            return;
+        }
+        for cell in buf.content.iter_mut() {
+            cell.fg = self.fg(cell.fg);
+            cell.bg = self.bg(cell.bg);
+        }
+    }
+
+    fn fg(&self, color: Color) -> Color {
+        match self {
+            Self::Dark => color,
+            Self::Light => match color {
+                Color::White => Color::Black,
+                Color::Black => Color::White,
+                Color::Gray => Color::DarkGray,
+                Color::LightRed => Color::Red,
+                Color::LightGreen => Color::Green,
+                other => other,
+            },
+            Self::Basic => to_ansi(color),
+            Self::Mono => Color::Reset,
+        }
+    }
+
+    fn bg(&self, color: Color) -> Color {
+        match self {
+            Self::Dark => color,
+            Self::Light => match color {
+                Color::Black => Color::White,
+                Color::White => Color::Black,
+                Color::DarkGray => Color::Gray,
+                other => other,
+            },
+            Self::Basic => to_ansi(color),
+            Self::Mono => Color::Reset,
+        }
+    }
+}
+
+/// Turn background highlights into modifiers: the cursor and colored badges
+/// become reverse video, gray bars and selected rows are underlined
+fn strip_color(cell: &mut Cell) {
+    let modifier = match cell.bg {
+        Color::Reset | Color::Black => Modifier::empty(),
+        Color::DarkGray | Color::Gray => Modifier::UNDERLINED,
+        _ => Modifier::REVERSED,
+    };
+    cell.modifier.insert(modifier);
+    cell.fg = Color::Reset;
+    cell.bg = Color::Reset;
+}
+
+/// Nearest of the 16 basic colors for true and indexed colors
+fn to_ansi(color: Color) -> Color {
+    let (r, g, b) = match color {
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Indexed(i) if i < 16 => return ANSI_PALETTE[i as usize].0,
+        Color::Indexed(i) => indexed_rgb(i),
+        other => return other,
+    };
+    ANSI_PALETTE
+        .iter()
+        .min_by_key(|(_, (pr, pg, pb))| {
+            let dr = r as i32 - *pr as i32;
+            let dg = g as i32 - *pg as i32;
+            let db = b as i32 - *pb as i32;
+            dr * dr + dg * dg + db * db
+        })
+        .map(|(c, _)| *c)
+        .unwrap_or(Color::Reset)
+}
+
+/// RGB of a 256-color index above 15: the 6x6x6 cube, then the gray ramp
+fn indexed_rgb(i: u8) -> (u8, u8, u8) {
+    if i >= 232 {
+        let level = 8 + (i - 232) * 10;
+        return (level, level, level);
+    }
+    let i = i - 16;
+    let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+    (level(i / 36), level((i / 6) % 6), level(i % 6))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::layout::Rect;
+    use ratatui::style::Style;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Theme::detect_from(Some("0;15"), None, Some("xterm-256color")), Theme::Light);
+        assert_eq!(Theme::detect_from(Some("15;default;0"), None, Some("xterm-256color")), Theme::Dark);
+        assert_eq!(Theme::detect_from(None, Some("truecolor"), Some("xterm")), Theme::Dark);
+        assert_eq!(Theme::detect_from(None, None, Some("linux")), Theme::Basic);
+        assert_eq!(Theme::detect_from(None, None, None), Theme::Dark);
+        assert_eq!(Theme::from_name("mono"), Some(Theme::Mono));
+    }
+
+    #[test]
+    fn test_apply() {
+        let area = Rect::new(0, 0, 2, 1);
+        let mut buf = Buffer::empty(area);
+        buf.set_string(0, 0, "a", Style::default().fg(Color::White).bg(Color::DarkGray));
+        buf.set_string(1, 0, "b", Style::default().fg(Color::Rgb(0x4C, 0x56, 0x6A)));
+
+        let mut mono = buf.clone();
+        Theme::Mono.apply(&mut mono);
+        assert_eq!(mono[(0, 0)].bg, Color::Reset);
+        assert!(mono[(0, 0)].modifier.contains(Modifier::UNDERLINED));
+
+        let mut light = buf.clone();
+        Theme::Light.apply(&mut light);
+        assert_eq!(light[(0, 0)].fg, Color::Black);
+        assert_eq!(light[(0, 0)].bg, Color::Gray);
+
+        Theme::Basic.apply(&mut buf);
+        assert_eq!(buf[(0, 0)].fg, Color::White);
+        assert_eq!(buf[(1, 0)].fg, Color::DarkGray);
+        assert_eq!(to_ansi(Color::Indexed(196)), Color::LightRed);
+    }
+}
@@ -0,0 +1,320 @@
+//! Aegis Authenticator export
+//!
+//! Writes the TOTP settings of credentials as an encrypted Aegis vault, which
+//! Aegis opens with "Import from file" and its own password prompt. Only
+//! credentials with a TOTP secret are written, and of those only the OTP
+//! settings, issuer and account name leave the vault.
+//!
+//! The format follows Aegis' vault documentation: a random master key
+//! encrypts the entry database with AES-256-GCM, and a password slot holds
+//! the master key encrypted with a scrypt-derived key.
+
+use std::path::Path;
+
+use aes_gcm::aead::AeadInPlace;
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::crypto::totp::{TotpAlgorithm, TotpSecret};
+use crate::crypto::DataEncryptionKey;
+use crate::db::Credential;
+
+use super::credential::decrypt_credential_data;
+use super::export::write_output;
+use super::{VaultError, VaultResult};
+
+pub const AEGIS_EXTENSION: &str = ".aegis.json";
+
+/// The cost Aegis itself derives password slots with: n = 2^15, r = 8, p = 1
+const AEGIS_LOG_N: u8 = 15;
+const AEGIS_R: u32 = 8;
+const AEGIS_P: u32 = 1;
+
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+/// Slot type for a password-derived key
+const PASSWORD_SLOT: u8 = 1;
+
+/// On-disk Aegis vault
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AegisVault {
+    pub version: u32,
+    pub header: AegisHeader,
+    /// `AegisDb` as JSON, AES-256-GCM encrypted with the master key, base64
+    pub db: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AegisHeader {
+    pub slots: Vec<AegisSlot>,
+    /// Nonce and tag of `db`
+    pub params: AegisKeyParams,
+}
+
+/// The master key, encrypted with a key derived from the password
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AegisSlot {
+    #[serde(rename = "type")]
+    pub slot_type: u8,
+    pub uuid: String,
+    /// Encrypted master key, hex
+    pub key: String,
+    pub key_params: AegisKeyParams,
+    pub n: u32,
+    pub r: u32,
+    pub p: u32,
+    /// scrypt salt, hex
+    pub salt: String,
+    /// Marks keys derived from the password's UTF-8 bytes, as all are here
+    pub repaired: bool,
+    pub is_backup: bool,
+}
+
+/// AES-GCM nonce and tag, hex
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AegisKeyParams {
+    pub nonce: String,
+    pub tag: String,
+}
+
+/// Decrypted entry database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AegisDb {
+    pub version: u32,
+    pub entries: Vec<AegisEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AegisEntry {
+    /// `totp`, or `steam` for Steam Guard
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub uuid: String,
+    /// Account name
+    pub name: String,
+    pub issuer: String,
+    pub note: String,
+    pub favorite: bool,
+    pub icon: Option<String>,
+    pub info: AegisInfo,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AegisInfo {
+    /// Base32 secret
+    pub secret: String,
+    pub algo: String,
+    pub digits: usize,
+    pub period: u64,
+}
+
+impl AegisEntry {
+    /// An entry for `totp`, named after the credential where the secret
+    /// carries no issuer or account of its own
+    fn new(cred: &Credential, totp: &TotpSecret) -> Self {
+        let (kind, algo) = match totp.algorithm {
+            TotpAlgorithm::SHA1 => ("totp", "SHA1"),
+            TotpAlgorithm::SHA256 => ("totp", "SHA256"),
+            TotpAlgorithm::SHA512 => ("totp", "SHA512"),
+            TotpAlgorithm::Steam => ("steam", "SHA1"),
+        };
+        let issuer = match totp.issuer.is_empty() {
+            true => cred.name.clone(),
+            false => totp.issuer.clone(),
+        };
+        Self {
+            kind: kind.to_string(),
+            uuid: uuid::Uuid::new_v4().to_string(),
+            name: totp.account.clone(),
+            issuer,
+            note: String::new(),
+            favorite: false,
+            icon: None,
+            info: AegisInfo {
+                secret: totp.secret.clone(),
+                algo: algo.to_string(),
+                digits: totp.digits,
+                period: totp.period,
+            },
+        }
+    }
+}
+
+/// Entries for every credential with a usable TOTP secret; raw secrets
+/// take the username as account and the credential name as issuer
+pub fn aegis_entries<'a>(
+    dek: &DataEncryptionKey,
+    credentials: impl IntoIterator<Item = &'a Credential>,
+) -> VaultResult<Vec<AegisEntry>> {
+    let mut entries = Vec::new();
+    for cred in credentials {
+        let Some(encrypted) = &cred.encrypted_totp_secret else { continue };
+        let input = Zeroizing::new(decrypt_credential_data(dek, &cred.id, encrypted)?);
+        let account = cred.username.as_deref().unwrap_or(&cred.name);
+        if let Ok(totp) = TotpSecret::from_user_input(&input, account, "") {
+            entries.push(AegisEntry::new(cred, &totp));
+        }
+    }
+    Ok(entries)
+}
+
+fn random<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+fn key_params(nonce: &[u8; NONCE_LEN], tag: &[u8; TAG_LEN]) -> AegisKeyParams {
+    AegisKeyParams { nonce: hex::encode(nonce), tag: hex::encode(tag) }
+}
+
+/// AES-256-GCM with the tag kept apart, as Aegis stores it
+fn encrypt(key: &[u8; 32], nonce: &[u8; NONCE_LEN], plaintext: &[u8]) -> VaultResult<(Vec<u8>, [u8; TAG_LEN])> {
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let mut buffer = plaintext.to_vec();
+    let tag = cipher
+        .encrypt_in_place_detached(Nonce::from_slice(nonce), b"", &mut buffer)
+        .map_err(|_| VaultError::CryptoError("Encryption failed".into()))?;
+    Ok((buffer, tag.into()))
+}
+
+/// Encrypt `entries` behind `password`
+pub fn create_aegis_vault(entries: Vec<AegisEntry>, password: &str) -> VaultResult<AegisVault> {
+    seal(entries, password, AEGIS_LOG_N)
+}
+
+fn seal(entries: Vec<AegisEntry>, password: &str, log_n: u8) -> VaultResult<AegisVault> {
+    if password.is_empty() {
+        return Err(VaultError::OperationFailed("Passphrase required for Aegis export".into()));
+    }
+
+    let master_key = Zeroizing::new(random::<32>());
+    let salt = random::<32>();
+    let mut slot_key = Zeroizing::new([0u8; 32]);
+    let cost = scrypt::Params::new(log_n, AEGIS_R, AEGIS_P, slot_key.len())
+        .map_err(|e| VaultError::CryptoError(e.to_string()))?;
+    scrypt::scrypt(password.as_bytes(), &salt, &cost, slot_key.as_mut())
+        .map_err(|e| VaultError::CryptoError(e.to_string()))?;
+
+    let key_nonce = random::<NONCE_LEN>();
+    let (wrapped_key, key_tag) = encrypt(&slot_key, &key_nonce, master_key.as_ref())?;
+
+    let json = Zeroizing::new(
+        serde_json::to_vec(&AegisDb { version: 2, entries })
+            .map_err(|e| VaultError::OperationFailed(format!("JSON serialization failed: {}", e)))?,
+    );
+    let db_nonce = random::<NONCE_LEN>();
+    let (db, db_tag) = encrypt(&master_key, &db_nonce, &json)?;
+
+    use base64::Engine;
+    Ok(AegisVault {
+        version: 1,
+        header: AegisHeader {
+            slots: vec![AegisSlot {
+                slot_type: PASSWORD_SLOT,
+                uuid: uuid::Uuid::new_v4().to_string(),
+                key: hex::encode(wrapped_key),
+                key_params: key_params(&key_nonce, &key_tag),
+                n: 1 << log_n,
+                r: AEGIS_R,
+                p: AEGIS_P,
+                salt: hex::encode(salt),
+                repaired: true,
+                is_backup: false,
+            }],
+            params: key_params(&db_nonce, &db_tag),
+        },
+        db: base64::engine::general_purpose::STANDARD.encode(db),
+    })
+}
+
+/// Write `vault` to `path` (or stdout for `-`), readable only by the owner
+pub fn write_aegis_vault(vault: &AegisVault, path: &Path) -> VaultResult<()> {
+    let json = serde_json::to_string_pretty(vault)
+        .map_err(|e| VaultError::OperationFailed(format!("JSON serialization failed: {}", e)))?;
+    write_output(path, json.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+
+    use crate::crypto::encrypt_string;
+    use crate::db::CredentialType;
+
+    /// Decrypt the way Aegis does on import
+    fn open(vault: &AegisVault, password: &str) -> AegisDb {
+        let slot = &vault.header.slots[0];
+        let unhex = |s: &str| hex::decode(s).unwrap();
+        let mut slot_key = [0u8; 32];
+        let cost = scrypt::Params::new(slot.n.ilog2() as u8, slot.r, slot.p, slot_key.len()).unwrap();
+        scrypt::scrypt(password.as_bytes(), &unhex(&slot.salt), &cost, &mut slot_key).unwrap();
+
+        let decrypt = |key: &[u8], params: &AegisKeyParams, mut buffer: Vec<u8>| {
+            Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key))
+                .decrypt_in_place_detached(
+                    Nonce::from_slice(&unhex(&params.nonce)),
+                    b"",
+                    &mut buffer,
+                    aes_gcm::Tag::from_slice(&unhex(&params.tag)),
+                )
+                .unwrap();
+            buffer
+        };
+        let master_key = decrypt(&slot_key, &slot.key_params, unhex(&slot.key));
+        let db = base64::engine::general_purpose::STANDARD.decode(&vault.db).unwrap();
+        let json = decrypt(&master_key, &vault.header.params, db);
+        serde_json::from_slice(&json).unwrap()
+    }
+
+    fn credential(dek: &DataEncryptionKey, name: &str, username: Option<&str>, totp: Option<&str>) -> Credential {
+        let mut cred = Credential::new(name.into(), CredentialType::Password, encrypt_string(dek.as_bytes(), "pw").unwrap());
+        cred.username = username.map(String::from);
+        cred.encrypted_totp_secret = totp.map(|t| encrypt_string(dek.as_bytes(), t).unwrap());
+        cred
+    }
+
+    #[test]
+    fn test_entries_skip_credentials_without_totp() {
+        let dek = DataEncryptionKey::generate();
+        let credentials = [
+            credential(&dek, "GitHub", Some("alice"), Some("JBSWY3DPEHPK3PXP")),
+            credential(&dek, "Bank", Some("alice"), None),
+            credential(&dek, "Example", None, Some("otpauth://totp/Example:bob?secret=JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP&issuer=Example&period=60")),
+            credential(&dek, "Steam", Some("gabe"), Some("steam:JBSWY3DPEHPK3PXP")),
+        ];
+
+        let entries = aegis_entries(&dek, &credentials).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!((entries[0].issuer.as_str(), entries[0].name.as_str()), ("GitHub", "alice"));
+        assert_eq!((entries[1].issuer.as_str(), entries[1].name.as_str()), ("Example", "bob"));
+        assert_eq!(entries[1].info.period, 60);
+        assert_eq!((entries[2].kind.as_str(), entries[2].info.digits), ("steam", 5));
+    }
+
+    #[test]
+    fn test_vault_opens_with_password() {
+        let dek = DataEncryptionKey::generate();
+        let entries = aegis_entries(&dek, &[credential(&dek, "GitHub", Some("alice"), Some("JBSWY3DPEHPK3PXP"))]).unwrap();
+
+        // A cheap cost keeps the test fast; the format is the same
+        let vault = seal(entries, "correct horse", 10).unwrap();
+        let db = open(&vault, "correct horse");
+        assert_eq!(db.entries.len(), 1);
+        assert_eq!(db.entries[0].info.secret, "JBSWY3DPEHPK3PXP");
+
+        let json = serde_json::to_value(&vault).unwrap();
+        assert_eq!(json["header"]["slots"][0]["type"], 1);
+        assert_eq!(json["header"]["slots"][0]["key"].as_str().unwrap().len(), 64);
+    }
+
+    #[test]
+    fn test_empty_password_rejected() {
+        assert!(create_aegis_vault(Vec::new(), "").is_err());
+    }
+}
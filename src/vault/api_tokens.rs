@@ -0,0 +1,126 @@
+//! Client tokens for the local HTTP API
+//!
+//! Every client of `vault agent --http` (a home-automation bridge, an editor
+//! plugin) gets a token of its own under a name, so its requests show in the
+//! audit log under that name and it can be revoked without touching the
+//! others. The vault keeps only a SHA-256 hash of each token, in its metadata
+//! table; the token itself is shown once, when it is created.
+
+use chrono::{DateTime, Local};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zeroize::{Zeroize, Zeroizing};
+
+use super::{VaultError, VaultResult};
+
+/// Metadata key holding the token list as JSON
+const TOKENS_KEY: &str = "api_tokens";
+/// Marks a string as a vault API token, e.g. in secret scanners
+const TOKEN_PREFIX: &str = "vault_";
+const TOKEN_BYTES: usize = 32;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub name: String,
+    /// SHA-256 of the token, hex
+    hash: String,
+    pub created_at: DateTime<Local>,
+}
+
+/// Every token, oldest first
+pub fn list_tokens(conn: &Connection) -> VaultResult<Vec<ApiToken>> {
+    let stored: Option<String> = conn
+        .query_row("SELECT value FROM metadata WHERE key = ?1", [TOKENS_KEY], |row| row.get(0))
+        .ok();
+    let Some(json) = stored else { return Ok(Vec::new()) };
+    serde_json::from_str(&json).map_err(|e| VaultError::OperationFailed(format!("Invalid API token list: {}", e)))
+}
+
+fn save_tokens(conn: &Connection, tokens: &[ApiToken]) -> VaultResult<()> {
+    let json = serde_json::to_string(tokens).map_err(|e| VaultError::OperationFailed(e.to_string()))?;
+    conn.execute("INSERT OR REPLACE INTO metadata (key, value) VALUES (?1, ?2)", [TOKENS_KEY, &json])?;
+    Ok(())
+}
+
+/// Create a token for the client `name`, returning the token to hand over
+pub fn create_token(conn: &Connection, name: &str) -> VaultResult<Zeroizing<String>> {
+    let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c));
+    if !valid {
+        return Err(VaultError::OperationFailed(format!(
+            "Invalid token name {:?}: use letters, digits, '-', '_' and '.'",
+            name
+        )));
+    }
+    let mut tokens = list_tokens(conn)?;
+    if tokens.iter().any(|t| t.name == name) {
+        return Err(VaultError::OperationFailed(format!("A token named {} already exists", name)));
+    }
+
+    let mut bytes = [0u8; TOKEN_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    let token = Zeroizing::new(format!("{}{}", TOKEN_PREFIX, hex::encode(bytes)));
+    bytes.zeroize();
+
+    tokens.push(ApiToken { name: name.to_string(), hash: hash(&token), created_at: Local::now() });
+    save_tokens(conn, &tokens)?;
+    Ok(token)
+}
+
+/// Remove the token named `name`; returns whether there was one
+pub fn revoke_token(conn: &Connection, name: &str) -> VaultResult<bool> {
+    let mut tokens = list_tokens(conn)?;
+    let before = tokens.len();
+    tokens.retain(|t| t.name != name);
+    if tokens.len() == before {
+        return Ok(false);
+    }
+    save_tokens(conn, &tokens)?;
+    Ok(true)
+}
+
+/// The token `presented` belongs to, if any. Only hashes are compared, so
+/// timing says nothing about how much of a guess was right
+pub fn find_token<'a>(tokens: &'a [ApiToken], presented: &str) -> Option<&'a ApiToken> {
+    let presented = hash(presented);
+    tokens.iter().find(|t| t.hash == presented)
+}
+
+fn hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    #[test]
+    fn test_token_lifecycle() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        assert!(list_tokens(conn).unwrap().is_empty());
+
+        let token = create_token(conn, "home-assistant").unwrap();
+        let other = create_token(conn, "nvim").unwrap();
+        assert!(token.starts_with(TOKEN_PREFIX) && token.len() == TOKEN_PREFIX.len() + TOKEN_BYTES * 2);
+        assert_ne!(token, other);
+        assert!(create_token(conn, "nvim").is_err());
+        assert!(create_token(conn, "bad name").is_err());
+
+        let tokens = list_tokens(conn).unwrap();
+        assert_eq!(find_token(&tokens, &token).unwrap().name, "home-assistant");
+        assert_eq!(find_token(&tokens, &other).unwrap().name, "nvim");
+        assert!(find_token(&tokens, "vault_guess").is_none());
+        let stored: String = conn.query_row("SELECT value FROM metadata WHERE key = ?1", [TOKENS_KEY], |r| r.get(0)).unwrap();
+        assert!(!stored.contains(token.as_str()));
+
+        assert!(revoke_token(conn, "home-assistant").unwrap());
+        assert!(!revoke_token(conn, "home-assistant").unwrap());
+        let tokens = list_tokens(conn).unwrap();
+        assert!(find_token(&tokens, &token).is_none());
+        assert!(find_token(&tokens, &other).is_some());
+    }
+}
@@ -2,6 +2,7 @@
 //!
 //! HMAC-signed audit logging for tamper detection.
 
+use chrono::{Duration, Local};
 use hmac::{Hmac, Mac};
 use sha2::Sha256;
 
@@ -49,6 +50,12 @@ pub fn log_action(
 
 /// Verify an audit log entry's HMAC
 pub fn verify_log(audit_key: &DerivedKey, log: &AuditLog) -> bool {
+    sign_log(audit_key, log) == log.hmac
+}
+
+/// The HMAC an existing entry should carry under `audit_key`, e.g. to sign
+/// it again after a DEK rotation
+pub fn sign_log(audit_key: &DerivedKey, log: &AuditLog) -> String {
     // Must match the format used in log_action
     let message = format!(
         "{}:{}:{}:{}:{}",
@@ -58,9 +65,7 @@ pub fn verify_log(audit_key: &DerivedKey, log: &AuditLog) -> bool {
         log.username.as_deref().unwrap_or(""),
         log.details.as_deref().unwrap_or(""),
     );
-
-    let expected_hmac = compute_hmac(audit_key.as_bytes(), &message);
-    expected_hmac == log.hmac
+    compute_hmac(audit_key.as_bytes(), &message)
 }
 
 /// Get recent audit logs
@@ -68,8 +73,7 @@ pub fn get_recent_logs(conn: &rusqlite::Connection, limit: usize) -> VaultResult
     Ok(db::get_recent_audit_logs(conn, limit)?)
 }
 
-/// Get audit logs for a specific credential
-#[allow(dead_code)]
+/// Get audit logs for a specific credential, newest first
 pub fn get_credential_logs(conn: &rusqlite::Connection, credential_id: &str) -> VaultResult<Vec<AuditLog>> {
     Ok(db::get_credential_audit_logs(conn, credential_id)?)
 }
@@ -87,6 +91,46 @@ pub fn verify_all_logs(conn: &rusqlite::Connection, audit_key: &DerivedKey) -> V
     Ok(results)
 }
 
+/// How much of the audit log to keep; `None` sets no bound
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Retention {
+    pub max_entries: Option<usize>,
+    pub max_age: Option<Duration>,
+}
+
+impl Retention {
+    pub fn is_unbounded(&self) -> bool {
+        self.max_entries.is_none() && self.max_age.is_none()
+    }
+}
+
+/// Delete the oldest entries beyond `retention` and log a signed marker
+/// saying how many went and up to when, together in one transaction. The
+/// marker counts towards `max_entries`. Returns how many were pruned.
+pub fn prune(conn: &rusqlite::Connection, audit_key: &DerivedKey, retention: Retention) -> VaultResult<usize> {
+    let times = db::get_audit_log_times(conn)?;
+    let by_count = match retention.max_entries {
+        Some(max) if times.len() > max => times.len() - max.saturating_sub(1),
+        _ => 0,
+    };
+    let by_age = retention.max_age.map_or(0, |age| {
+        let cutoff = Local::now() - age;
+        times.iter().rposition(|(_, time)| *time < cutoff).map_or(0, |i| i + 1)
+    });
+    let count = by_count.max(by_age);
+    let Some((last_id, last_time)) = count.checked_sub(1).map(|i| times[i]) else {
+        return Ok(0);
+    };
+
+    db::in_transaction(conn, || -> VaultResult<()> {
+        db::delete_audit_logs_through(conn, last_id)?;
+        let details = format!("pruned {} entries up to {}", count, last_time.format("%Y-%m-%d %H:%M:%S"));
+        log_action(conn, audit_key, AuditAction::Prune, None, None, None, Some(&details))?;
+        Ok(())
+    })?;
+    Ok(count)
+}
+
 fn compute_hmac(key: &[u8], message: &str) -> String {
     let mut mac = HmacSha256::new_from_slice(key)
         .expect("HMAC can take key of any size");
@@ -268,4 +312,33 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_prune() -> CryptoResult<()> {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let key = test_audit_key()?;
+        for _ in 0..5 {
+            log_action(conn, &key, AuditAction::Unlock, None, None, None, None).unwrap();
+        }
+        assert_eq!(prune(conn, &key, Retention::default()).unwrap(), 0);
+
+        let by_count = Retention { max_entries: Some(3), max_age: None };
+        assert_eq!(prune(conn, &key, by_count).unwrap(), 3);
+        assert_eq!(prune(conn, &key, by_count).unwrap(), 0);
+        let logs = get_recent_logs(conn, 10).unwrap();
+        assert_eq!(logs.len(), 3);
+        assert_eq!(logs[0].action, AuditAction::Prune);
+        assert!(logs[0].details.as_deref().unwrap().starts_with("pruned 3 entries up to "));
+
+        let old = (Local::now() - Duration::days(40)).to_rfc3339();
+        conn.execute("UPDATE audit_log SET timestamp = ?1 WHERE id = 4", [&old]).unwrap();
+        let by_age = Retention { max_entries: None, max_age: Some(Duration::days(30)) };
+        assert_eq!(prune(conn, &key, by_age).unwrap(), 1);
+        let ids: Vec<i64> = db::get_all_audit_logs(conn).unwrap().iter().map(|log| log.id).collect();
+        assert_eq!(ids, vec![5, 6, 7]);
+        assert!(verify_all_logs(conn, &key).unwrap().iter().all(|(_, ok)| *ok));
+
+        Ok(())
+    }
 }
@@ -0,0 +1,191 @@
+//! Automatic encrypted exports
+//!
+//! Writes the whole vault as a JSON export into a directory of the user's
+//! choosing, on every lock or on a schedule. Unlike backups, the files don't
+//! need the vault or its master password to read back: they are encrypted to
+//! GPG key IDs or age public keys, so no passphrase has to be kept around for
+//! them to run unattended. Files are named `vault-export-YYYYMMDD-HHMMSS.json`
+//! plus `.gpg` or `.age`, and only the newest `keep` are kept.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+
+use crate::crypto::DataEncryptionKey;
+use crate::db::Credential;
+
+use super::credential::{decrypt_credential_data, decrypt_fields, decrypt_notes};
+use super::export::{credential_to_export, export_to_file, ExportData, ExportEncryption, ExportFormat, ExportKey};
+use super::{VaultError, VaultResult};
+
+const PREFIX: &str = "vault-export-";
+const EXTENSION: &str = ".json";
+const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// Where automatic exports go, who can read them and how many to keep
+#[derive(Debug, Clone)]
+pub struct AutoExportConfig {
+    pub dir: PathBuf,
+    /// GPG key IDs, or age public keys (`age1...`)
+    pub recipients: Vec<String>,
+    /// Minimum time between exports; None exports on every lock
+    pub interval: Option<Duration>,
+    /// Exports to keep; 0 keeps all
+    pub keep: usize,
+}
+
+impl AutoExportConfig {
+    /// age when every recipient is an age public key, GPG otherwise
+    pub fn encryption(&self) -> ExportEncryption {
+        match self.recipients.iter().all(|r| r.starts_with("age1")) {
+            true => ExportEncryption::Age,
+            false => ExportEncryption::Gpg,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportFile {
+    pub path: PathBuf,
+    pub created_at: DateTime<Local>,
+}
+
+impl ExportFile {
+    fn parse(path: PathBuf) -> Option<Self> {
+        let name = path.file_name()?.to_str()?;
+        let stem = [ExportEncryption::Gpg, ExportEncryption::Age]
+            .iter()
+            .find_map(|e| name.strip_suffix(e.file_extension()))?;
+        let stamp = stem.strip_prefix(PREFIX)?.strip_suffix(EXTENSION)?;
+        let naive = NaiveDateTime::parse_from_str(stamp, TIMESTAMP_FORMAT).ok()?;
+        let created_at = Local.from_local_datetime(&naive).earliest()?;
+        Some(Self { path, created_at })
+    }
+}
+
+/// All automatic exports in `dir`, newest first
+pub fn list_exports(dir: &Path) -> VaultResult<Vec<ExportFile>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(VaultError::IoError(e.to_string())),
+    };
+
+    let mut exports: Vec<_> = entries.filter_map(|e| ExportFile::parse(e.ok()?.path())).collect();
+    exports.sort_by_key(|e| std::cmp::Reverse(e.created_at));
+    Ok(exports)
+}
+
+/// Whether an automatic export is due under `config`
+pub fn is_due(config: &AutoExportConfig) -> VaultResult<bool> {
+    let Some(interval) = config.interval else { return Ok(true) };
+    let latest = list_exports(&config.dir)?.into_iter().next();
+    Ok(latest.is_none_or(|e| {
+        let age = Local::now().signed_duration_since(e.created_at);
+        age.to_std().is_ok_and(|age| age >= interval)
+    }))
+}
+
+/// Export every credential into `config.dir`, encrypted to its recipients
+pub fn write_export(dek: &DataEncryptionKey, credentials: &[Credential], config: &AutoExportConfig) -> VaultResult<PathBuf> {
+    if config.recipients.is_empty() {
+        return Err(VaultError::OperationFailed("No keys to encrypt to; set autoexportto".into()));
+    }
+
+    let encryption = config.encryption();
+    let stamp = Local::now().format(TIMESTAMP_FORMAT);
+    let path = config.dir.join(format!("{}{}{}{}", PREFIX, stamp, EXTENSION, encryption.file_extension()));
+    if path.exists() {
+        return Err(VaultError::AlreadyExists);
+    }
+
+    let mut exported = Vec::with_capacity(credentials.len());
+    for cred in credentials {
+        let secret = decrypt_credential_data(dek, &cred.id, &cred.encrypted_secret)?;
+        let notes = decrypt_notes(dek, &cred.id, cred.encrypted_notes.as_ref())?;
+        let fields = decrypt_fields(dek, &cred.id, cred.encrypted_fields.as_ref())?;
+        exported.push(credential_to_export(cred, secret, notes, fields));
+    }
+
+    let key = ExportKey::Recipients(&config.recipients);
+    export_to_file(&ExportData::new(exported), ExportFormat::Json, encryption, Some(key), &path)?;
+    Ok(path)
+}
+
+/// Delete all but the newest `config.keep` exports, returning how many were removed
+pub fn prune(config: &AutoExportConfig) -> VaultResult<usize> {
+    if config.keep == 0 {
+        return Ok(0);
+    }
+    let exports = list_exports(&config.dir)?;
+    let expired = exports.get(config.keep..).unwrap_or_default();
+    for export in expired {
+        std::fs::remove_file(&export.path).map_err(|e| VaultError::IoError(e.to_string()))?;
+    }
+    Ok(expired.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    use crate::crypto::encrypt_string;
+    use crate::db::CredentialType;
+
+    fn config(dir: &Path, recipients: &[&str]) -> AutoExportConfig {
+        AutoExportConfig {
+            dir: dir.to_path_buf(),
+            recipients: recipients.iter().map(|r| r.to_string()).collect(),
+            interval: Some(Duration::from_secs(3600)),
+            keep: 2,
+        }
+    }
+
+    #[test]
+    fn test_parse_names() {
+        let parsed = ExportFile::parse(PathBuf::from("vault-export-20240115-093000.json.age")).unwrap();
+        assert_eq!(parsed.created_at.format("%Y-%m-%d %H:%M").to_string(), "2024-01-15 09:30");
+        assert!(ExportFile::parse(PathBuf::from("vault-export-20240115-093000.json.gpg")).is_some());
+        assert!(ExportFile::parse(PathBuf::from("vault-export-20240115-093000.json")).is_none());
+        assert!(ExportFile::parse(PathBuf::from("vault-20240115-093000.db")).is_none());
+    }
+
+    #[test]
+    fn test_export_due_and_pruned() {
+        let dir = TempDir::new().unwrap();
+        let identity = age::x25519::Identity::generate();
+        let recipient = identity.to_public().to_string();
+        let config = config(dir.path(), &[&recipient]);
+        assert_eq!(config.encryption(), ExportEncryption::Age);
+        assert!(is_due(&config).unwrap());
+
+        let dek = DataEncryptionKey::generate();
+        let cred = Credential::new("GitHub".into(), CredentialType::Password, encrypt_string(dek.as_bytes(), "hunter2").unwrap());
+        let path = write_export(&dek, &[cred], &config).unwrap();
+        assert!(!is_due(&config).unwrap());
+
+        let encrypted = std::fs::read(&path).unwrap();
+        let decryptor = age::Decryptor::new(&encrypted[..]).unwrap();
+        let mut reader = decryptor.decrypt(std::iter::once(&identity as &dyn age::Identity)).unwrap();
+        let mut json = String::new();
+        std::io::Read::read_to_string(&mut reader, &mut json).unwrap();
+        assert!(json.contains("hunter2"));
+
+        for stamp in ["20240101-000000", "20240102-000000"] {
+            std::fs::write(dir.path().join(format!("{}{}.json.age", PREFIX, stamp)), "").unwrap();
+        }
+        assert_eq!(prune(&config).unwrap(), 1);
+        let left: Vec<_> = list_exports(dir.path()).unwrap().into_iter().map(|e| e.path).collect();
+        assert_eq!(left, [path, dir.path().join(format!("{}20240102-000000.json.age", PREFIX))]);
+    }
+
+    #[test]
+    fn test_needs_recipients() {
+        let dir = TempDir::new().unwrap();
+        let config = config(dir.path(), &[]);
+        assert!(write_export(&DataEncryptionKey::generate(), &[], &config).is_err());
+        assert_eq!(self::config(dir.path(), &["ABCD1234", "age1x"]).encryption(), ExportEncryption::Gpg);
+    }
+}
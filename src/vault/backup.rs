@@ -0,0 +1,304 @@
+//! Timed backups of the encrypted vault database with rotation
+//!
+//! Backups are consistent `VACUUM INTO` copies named
+//! `vault-YYYYMMDD-HHMMSS[-label].db`, so they stay encrypted exactly like
+//! the live database. Rotation keeps the newest automatic backup of each of
+//! the last `keep_daily` days and `keep_weekly` ISO weeks; labelled backups
+//! are made on request and never pruned. Restoring swaps a backup in place
+//! of the vault file after checking that it is an intact vault.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, TimeZone};
+use rusqlite::{Connection, OpenFlags};
+
+use crate::db;
+
+use super::{VaultError, VaultResult};
+
+const PREFIX: &str = "vault-";
+const EXTENSION: &str = "db";
+const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// Where backups go and how many to keep
+#[derive(Debug, Clone)]
+pub struct BackupConfig {
+    pub dir: PathBuf,
+    pub keep_daily: usize,
+    pub keep_weekly: usize,
+    /// Minimum time between automatic backups; None backs up on every unlock and exit
+    pub interval: Option<Duration>,
+}
+
+impl BackupConfig {
+    /// `backups/` next to the vault, a week of dailies and a month of weeklies
+    pub fn for_vault(vault_path: &Path) -> Self {
+        Self {
+            dir: vault_path.with_file_name("backups"),
+            keep_daily: 7,
+            keep_weekly: 4,
+            interval: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupFile {
+    pub path: PathBuf,
+    pub created_at: DateTime<Local>,
+    /// Name given to a manual backup
+    pub label: Option<String>,
+}
+
+impl BackupFile {
+    fn parse(path: PathBuf) -> Option<Self> {
+        if path.extension()? != EXTENSION {
+            return None;
+        }
+        let stem = path.file_stem()?.to_str()?.strip_prefix(PREFIX)?;
+        let (stamp, label) = match stem.get(15..) {
+            Some("") | None => (stem, None),
+            Some(rest) => (&stem[..15], Some(rest.strip_prefix('-')?.to_string())),
+        };
+        let naive = NaiveDateTime::parse_from_str(stamp, TIMESTAMP_FORMAT).ok()?;
+        let created_at = Local.from_local_datetime(&naive).earliest()?;
+        Some(Self { path, created_at, label })
+    }
+
+    pub fn file_name(&self) -> String {
+        self.path.file_name().unwrap_or_default().to_string_lossy().into_owned()
+    }
+}
+
+/// Keep labels safe to use in a file name
+fn sanitize_label(label: &str) -> String {
+    label
+        .trim()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+/// Write a consistent copy of the open database into `dir`
+pub fn create_backup(conn: &Connection, dir: &Path, label: Option<&str>) -> VaultResult<PathBuf> {
+    std::fs::create_dir_all(dir).map_err(|e| VaultError::IoError(e.to_string()))?;
+
+    let stamp = Local::now().format(TIMESTAMP_FORMAT);
+    let name = match label.map(sanitize_label).filter(|l| !l.is_empty()) {
+        Some(label) => format!("{}{}-{}.{}", PREFIX, stamp, label, EXTENSION),
+        None => format!("{}{}.{}", PREFIX, stamp, EXTENSION),
+    };
+    let path = dir.join(name);
+    if path.exists() {
+        return Err(VaultError::AlreadyExists);
+    }
+
+    let target = path.to_str().ok_or_else(|| VaultError::IoError("non UTF-8 backup path".into()))?;
+    conn.execute("VACUUM INTO ?1", [target])?;
+    restrict_permissions(&path);
+    Ok(path)
+}
+
+/// Make `path` readable only by the owner
+#[cfg(unix)]
+pub(crate) fn restrict_permissions(path: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+    let _ = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600));
+}
+
+#[cfg(not(unix))]
+pub(crate) fn restrict_permissions(_path: &Path) {}
+
+/// All backups in `dir`, newest first
+pub fn list_backups(dir: &Path) -> VaultResult<Vec<BackupFile>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(VaultError::IoError(e.to_string())),
+    };
+
+    let mut backups: Vec<_> = entries.filter_map(|e| BackupFile::parse(e.ok()?.path())).collect();
+    backups.sort_by_key(|b| std::cmp::Reverse(b.created_at));
+    Ok(backups)
+}
+
+/// Whether an automatic backup is due under `config`
+pub fn is_due(config: &BackupConfig) -> VaultResult<bool> {
+    let Some(interval) = config.interval else { return Ok(true) };
+    let latest = list_backups(&config.dir)?.into_iter().next();
+    Ok(latest.is_none_or(|b| {
+        let age = Local::now().signed_duration_since(b.created_at);
+        age.to_std().is_ok_and(|age| age >= interval)
+    }))
+}
+
+/// Automatic backups to delete: everything but the newest per recent day and week
+fn select_expired(backups: &[BackupFile], keep_daily: usize, keep_weekly: usize) -> Vec<&BackupFile> {
+    let mut days = HashSet::new();
+    let mut weeks = HashSet::new();
+
+    // `backups` is newest first, so the first of each day or week is the one kept
+    backups
+        .iter()
+        .filter(|b| b.label.is_none())
+        .filter(|b| {
+            let day = b.created_at.date_naive();
+            let week = b.created_at.iso_week();
+            let keep_day = days.len() < keep_daily && days.insert(day);
+            let keep_week = weeks.len() < keep_weekly && weeks.insert((week.year(), week.week()));
+            !(keep_day || keep_week)
+        })
+        .collect()
+}
+
+/// Delete expired automatic backups, returning how many were removed
+pub fn prune(config: &BackupConfig) -> VaultResult<usize> {
+    let backups = list_backups(&config.dir)?;
+    let expired = select_expired(&backups, config.keep_daily, config.keep_weekly);
+    for backup in &expired {
+        std::fs::remove_file(&backup.path).map_err(|e| VaultError::IoError(e.to_string()))?;
+    }
+    Ok(expired.len())
+}
+
+/// Check that `path` is an intact vault database
+pub fn validate_vault_file(path: &Path) -> VaultResult<()> {
+    // Nothing in an encrypted file can be read without the password; unlocking checks it
+    if db::is_encrypted_file(path) {
+        return Ok(());
+    }
+    let invalid = |detail: String| VaultError::OperationFailed(format!("{} is not a valid vault: {}", path.display(), detail));
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(|e| invalid(e.to_string()))?;
+    let check: String = conn.query_row("PRAGMA quick_check", [], |row| row.get(0)).map_err(|e| invalid(e.to_string()))?;
+    if check != "ok" {
+        return Err(invalid(check));
+    }
+    conn.query_row("SELECT value FROM metadata WHERE key = 'wrapped_dek'", [], |row| row.get::<_, String>(0))
+        .map_err(|_| invalid("missing key material".into()))?;
+    Ok(())
+}
+
+/// Replace the vault file with `new`, folding and removing the old WAL first
+///
+/// The vault must not be open while this runs.
+pub fn replace_database(vault_path: &Path, new: &Path) -> VaultResult<()> {
+    // An encrypted database needs its key even to checkpoint; its WAL goes unread
+    if !db::is_encrypted_file(vault_path) {
+        let conn = Connection::open(vault_path)?;
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+    }
+
+    for suffix in ["-wal", "-shm"] {
+        let mut side = vault_path.as_os_str().to_os_string();
+        side.push(suffix);
+        let _ = std::fs::remove_file(PathBuf::from(side));
+    }
+    std::fs::rename(new, vault_path).map_err(|e| VaultError::IoError(e.to_string()))
+}
+
+/// Put the contents of `backup` in place of the (closed) vault at `vault_path`
+pub fn restore_backup(vault_path: &Path, backup: &Path) -> VaultResult<()> {
+    validate_vault_file(backup)?;
+
+    // Copy next to the vault first so the final swap is a same-directory rename
+    let mut name = std::ffi::OsString::from(".");
+    name.push(vault_path.file_name().unwrap_or_default());
+    name.push(".restore");
+    let staging = vault_path.with_file_name(name);
+    std::fs::copy(backup, &staging).map_err(|e| VaultError::IoError(e.to_string()))?;
+    restrict_permissions(&staging);
+
+    let result = replace_database(vault_path, &staging);
+    if result.is_err() {
+        let _ = std::fs::remove_file(&staging);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration as ChronoDuration;
+    use tempfile::TempDir;
+
+    use crate::db::Database;
+
+    fn backup_at(days_ago: i64, hour: u32, label: Option<&str>) -> BackupFile {
+        let date = Local::now().date_naive() - ChronoDuration::days(days_ago);
+        let created_at = Local.from_local_datetime(&date.and_hms_opt(hour, 0, 0).unwrap()).earliest().unwrap();
+        BackupFile { path: PathBuf::from(format!("{}-{}", days_ago, hour)), created_at, label: label.map(String::from) }
+    }
+
+    #[test]
+    fn test_parse_file_name() {
+        let plain = BackupFile::parse(PathBuf::from("/b/vault-20240115-093000.db")).unwrap();
+        assert_eq!(plain.created_at.format("%Y-%m-%d %H:%M").to_string(), "2024-01-15 09:30");
+        assert_eq!(plain.label, None);
+
+        let named = BackupFile::parse(PathBuf::from("/b/vault-20240115-093000-before_upgrade.db")).unwrap();
+        assert_eq!(named.label.as_deref(), Some("before_upgrade"));
+
+        assert!(BackupFile::parse(PathBuf::from("/b/vault-2024.db")).is_none());
+        assert!(BackupFile::parse(PathBuf::from("/b/notes.txt")).is_none());
+    }
+
+    #[test]
+    fn test_rotation() {
+        // Newest first: two today, one daily for the past 20 days, one labelled
+        let mut backups = vec![backup_at(0, 12, None), backup_at(0, 8, None)];
+        backups.extend((1..=20).map(|d| backup_at(d, 8, None)));
+        backups.push(backup_at(30, 8, Some("keep")));
+
+        let expired = select_expired(&backups, 3, 0);
+        let kept: Vec<_> = backups.iter().filter(|b| !expired.contains(b)).collect();
+        assert_eq!(kept, vec![&backups[0], &backups[2], &backups[3], &backups[22]]);
+
+        let expired = select_expired(&backups, 0, 2);
+        let kept: Vec<_> = backups.iter().filter(|b| !expired.contains(b) && b.label.is_none()).collect();
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[0], &backups[0]);
+        assert_ne!(kept[0].created_at.iso_week(), kept[1].created_at.iso_week());
+    }
+
+    #[test]
+    fn test_create_and_prune() {
+        let dir = TempDir::new().unwrap();
+        let db = Database::open(crate::db::DatabaseConfig::with_path(dir.path().join("vault.db"))).unwrap();
+        let config = BackupConfig::for_vault(&dir.path().join("vault.db"));
+
+        let named = create_backup(db.conn(), &config.dir, Some("before move!")).unwrap();
+        assert!(named.to_string_lossy().ends_with("-before_move_.db"));
+        assert!(Connection::open(&named).is_ok());
+
+        let backups = list_backups(&config.dir).unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(prune(&config).unwrap(), 0);
+        assert!(is_due(&config).unwrap());
+        assert!(!is_due(&BackupConfig { interval: Some(Duration::from_secs(3600)), ..config }).unwrap());
+    }
+
+    #[test]
+    fn test_restore() {
+        let dir = TempDir::new().unwrap();
+        let vault_path = dir.path().join("vault.db");
+        let backups = dir.path().join("backups");
+
+        let db = Database::open(crate::db::DatabaseConfig::with_path(&vault_path)).unwrap();
+        db.conn().execute("INSERT INTO metadata (key, value) VALUES ('wrapped_dek', 'old')", []).unwrap();
+        let saved = create_backup(db.conn(), &backups, Some("before")).unwrap();
+        db.conn().execute("UPDATE metadata SET value = 'new' WHERE key = 'wrapped_dek'", []).unwrap();
+        drop(db);
+
+        let junk = dir.path().join("junk.db");
+        std::fs::write(&junk, b"not a database").unwrap();
+        assert!(restore_backup(&vault_path, &junk).is_err());
+
+        restore_backup(&vault_path, &saved).unwrap();
+        let conn = Connection::open(&vault_path).unwrap();
+        let dek: String = conn.query_row("SELECT value FROM metadata WHERE key = 'wrapped_dek'", [], |row| row.get(0)).unwrap();
+        assert_eq!(dek, "old");
+        assert!(saved.exists());
+    }
+}
@@ -0,0 +1,330 @@
+//! Portable encrypted vault bundles
+//!
+//! A bundle is a single JSON file holding everything needed to move a vault
+//! to another machine. The data encryption key is wrapped with a key derived
+//! from the bundle passphrase (Argon2id, as for the master key), and the
+//! credentials, tombstones and audit log are encrypted with that DEK.
+//! Credential secrets stay encrypted exactly as stored, and audit HMACs
+//! still verify because the audit key derives from the same DEK.
+//!
+//! Importing either merges the bundle into the open vault record by record,
+//! re-encrypting secrets under the local DEK, or replaces the vault's
+//! contents outright, adopting the bundle's DEK under the local password.
+
+use std::path::Path;
+
+use chrono::{DateTime, Local};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::{
+    decrypt_string, derive_key_with_salt, encrypt_string, generate_salt, DataEncryptionKey, KdfParams,
+    KeyHierarchy, MasterKey,
+};
+use crate::db::{self, AuditLog, Credential, Database};
+
+use super::credential::{decrypt_credential_data, encrypt_credential_data};
+use super::sync::merge::{merge_into, MergeReport};
+use super::export::write_output;
+use super::{audit, metadata, VaultError, VaultResult};
+
+pub const BUNDLE_FORMAT: &str = "vault-bundle";
+pub const BUNDLE_VERSION: u32 = 1;
+pub const BUNDLE_EXTENSION: &str = ".vault";
+
+/// On-disk bundle; only the counts and timestamps are readable without the passphrase
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultBundle {
+    pub format: String,
+    pub version: u32,
+    pub created_at: DateTime<Local>,
+    pub credential_count: usize,
+    pub kdf: KdfParams,
+    /// Salt for deriving the passphrase key
+    pub salt: String,
+    /// DEK wrapped with the passphrase key
+    pub wrapped_dek: String,
+    /// `BundleContents` as JSON, encrypted with the DEK
+    pub payload: String,
+}
+
+/// How an imported bundle is applied to the open vault
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BundleImportMode {
+    /// Fold records in; the later edit of each credential wins
+    Merge,
+    /// Swap the vault's credentials and audit log for the bundle's
+    Replace,
+}
+
+impl BundleImportMode {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Merge => "Merge into this vault",
+            Self::Replace => "Replace this vault",
+        }
+    }
+
+    pub fn toggle(self) -> Self {
+        match self {
+            Self::Merge => Self::Replace,
+            Self::Replace => Self::Merge,
+        }
+    }
+}
+
+/// Decrypted bundle payload
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BundleContents {
+    pub credentials: Vec<Credential>,
+    pub tombstones: Vec<(String, DateTime<Local>)>,
+    pub audit_logs: Vec<AuditLog>,
+}
+
+impl BundleContents {
+    pub fn from_database(conn: &Connection) -> VaultResult<Self> {
+        Ok(Self {
+            credentials: db::get_all_credentials(conn)?,
+            tombstones: db::get_tombstones(conn)?,
+            audit_logs: db::get_all_audit_logs(conn)?,
+        })
+    }
+}
+
+fn crypto_err(e: crate::crypto::CryptoError) -> VaultError {
+    VaultError::CryptoError(e.to_string())
+}
+
+/// Seal the whole vault behind `passphrase`
+pub fn create_bundle(conn: &Connection, dek: &DataEncryptionKey, passphrase: &str) -> VaultResult<VaultBundle> {
+    seal(&BundleContents::from_database(conn)?, dek, passphrase, KdfParams::default())
+}
+
+fn seal(contents: &BundleContents, dek: &DataEncryptionKey, passphrase: &str, kdf: KdfParams) -> VaultResult<VaultBundle> {
+    if passphrase.is_empty() {
+        return Err(VaultError::OperationFailed("Passphrase required for vault bundle".into()));
+    }
+
+    let salt = generate_salt();
+    let bundle_key = derive_key_with_salt(passphrase.as_bytes(), &salt, &kdf).map_err(crypto_err)?;
+    let json = serde_json::to_string(contents)
+        .map_err(|e| VaultError::OperationFailed(format!("JSON serialization failed: {}", e)))?;
+
+    Ok(VaultBundle {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        created_at: Local::now(),
+        credential_count: contents.credentials.len(),
+        kdf,
+        salt,
+        wrapped_dek: dek.wrap(&bundle_key).map_err(crypto_err)?,
+        payload: encrypt_string(dek.as_bytes(), &json).map_err(crypto_err)?,
+    })
+}
+
+/// A bundle whose passphrase checked out, with its records decrypted
+pub struct OpenedBundle {
+    pub keys: KeyHierarchy,
+    pub contents: BundleContents,
+    /// Audit entries whose HMAC doesn't verify under the bundle's key
+    pub tampered_logs: usize,
+}
+
+impl VaultBundle {
+    /// Unwrap the DEK with `passphrase` and decrypt the payload
+    pub fn open(&self, passphrase: &str) -> VaultResult<OpenedBundle> {
+        // The settings come from the file; don't let a crafted one exhaust memory
+        self.kdf.check_bounds().map_err(crypto_err)?;
+        let bundle_key = derive_key_with_salt(passphrase.as_bytes(), &self.salt, &self.kdf).map_err(crypto_err)?;
+        let keys = KeyHierarchy::from_wrapped_dek(bundle_key, self.wrapped_dek.clone())
+            .map_err(|_| VaultError::InvalidPassword)?;
+
+        let damaged = |detail: &str| VaultError::OperationFailed(format!("Bundle is damaged: {}", detail));
+        let json = decrypt_string(keys.dek().as_bytes(), &self.payload).map_err(|_| damaged("payload does not decrypt"))?;
+        let contents: BundleContents = serde_json::from_str(&json).map_err(|e| damaged(&e.to_string()))?;
+        if contents.credentials.len() != self.credential_count {
+            return Err(damaged("credential count mismatch"));
+        }
+
+        let audit_key = keys.derive_audit_key().map_err(crypto_err)?;
+        let tampered_logs = contents.audit_logs.iter().filter(|log| !audit::verify_log(&audit_key, log)).count();
+        Ok(OpenedBundle { keys, contents, tampered_logs })
+    }
+}
+
+/// Read a bundle file, checking that it is one this version understands
+pub fn read_bundle(path: &Path) -> VaultResult<VaultBundle> {
+    let json = std::fs::read_to_string(path).map_err(|e| VaultError::IoError(e.to_string()))?;
+    let bundle: VaultBundle = serde_json::from_str(&json)
+        .map_err(|_| VaultError::OperationFailed(format!("{} is not a vault bundle", path.display())))?;
+    if bundle.format != BUNDLE_FORMAT {
+        return Err(VaultError::OperationFailed(format!("{} is not a vault bundle", path.display())));
+    }
+    if bundle.version > BUNDLE_VERSION {
+        return Err(VaultError::OperationFailed(format!(
+            "Bundle version {} is newer than this vault supports ({})",
+            bundle.version, BUNDLE_VERSION
+        )));
+    }
+    Ok(bundle)
+}
+
+/// Re-encrypt a credential's secrets from one DEK to another
+fn reencrypt(cred: &Credential, from: &DataEncryptionKey, to: &DataEncryptionKey) -> VaultResult<Credential> {
+    let convert = |blob: &String| -> VaultResult<String> {
+        let plain = zeroize::Zeroizing::new(decrypt_credential_data(from, &cred.id, blob)?);
+        encrypt_credential_data(to, &cred.id, &plain)
+    };
+
+    let mut cred = cred.clone();
+    cred.encrypted_secret = convert(&cred.encrypted_secret)?;
+    cred.encrypted_notes = cred.encrypted_notes.as_ref().map(convert).transpose()?;
+    cred.encrypted_totp_secret = cred.encrypted_totp_secret.as_ref().map(convert).transpose()?;
+    cred.encrypted_fields = cred.encrypted_fields.as_ref().map(convert).transpose()?;
+    Ok(cred)
+}
+
+/// Merge the bundle's credentials and tombstones into `conn`, encrypted with `dek`
+///
+/// Audit logs are not merged; the local vault keeps its own.
+pub fn merge_bundle(conn: &Connection, dek: &DataEncryptionKey, bundle: &OpenedBundle) -> VaultResult<MergeReport> {
+    let staging = Database::open_in_memory()?;
+    let same_key = dek.as_bytes() == bundle.keys.dek().as_bytes();
+    for cred in &bundle.contents.credentials {
+        let cred = if same_key { cred.clone() } else { reencrypt(cred, bundle.keys.dek(), dek)? };
+        db::create_credential(staging.conn(), &cred)?;
+    }
+    for (id, deleted_at) in &bundle.contents.tombstones {
+        db::record_tombstone(staging.conn(), id, *deleted_at)?;
+    }
+    merge_into(conn, staging.conn())
+}
+
+/// Replace everything in `conn` with the bundle, re-wrapping its DEK with `master_key`
+///
+/// The caller must reload its keys afterwards (`Vault::reload_keys`), even
+/// on failure, as encrypted metadata is written under the bundle's DEK.
+pub fn replace_with_bundle(db: &Database, master_key: &MasterKey, bundle: &OpenedBundle) -> VaultResult<()> {
+    let wrapped_dek = bundle.keys.dek().wrap(master_key).map_err(crypto_err)?;
+    let conn = db.conn();
+
+    db::in_transaction(conn, || -> VaultResult<()> {
+        metadata::bind(db, bundle.keys.dek())?;
+        // Trashed entries and revisions are encrypted with the old DEK, so they go too
+        conn.execute_batch(
+            "DELETE FROM credentials; DELETE FROM tombstones; DELETE FROM audit_log; DELETE FROM trash; DELETE FROM revisions; DELETE FROM search_tokens;",
+        )?;
+        for cred in &bundle.contents.credentials {
+            db::create_credential(conn, cred)?;
+        }
+        for (id, deleted_at) in &bundle.contents.tombstones {
+            db::record_tombstone(conn, id, *deleted_at)?;
+        }
+        for log in &bundle.contents.audit_logs {
+            db::create_audit_log(conn, log)?;
+        }
+        conn.execute("INSERT OR REPLACE INTO metadata (key, value) VALUES ('wrapped_dek', ?1)", [&wrapped_dek])?;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+/// Write `bundle` to `path` (or stdout for `-`), readable only by the owner
+pub fn write_bundle(bundle: &VaultBundle, path: &Path) -> VaultResult<()> {
+    let json = serde_json::to_string_pretty(bundle)
+        .map_err(|e| VaultError::OperationFailed(format!("JSON serialization failed: {}", e)))?;
+    write_output(path, json.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    use crate::crypto::{decrypt_string, DataEncryptionKey};
+    use crate::db::{CredentialType, Database};
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let db = Database::open_in_memory().unwrap();
+        let dek = DataEncryptionKey::generate();
+        let secret = encrypt_string(dek.as_bytes(), "hunter2").unwrap();
+        db::create_credential(db.conn(), &Credential::new("Bank".into(), CredentialType::Password, secret)).unwrap();
+
+        let contents = BundleContents::from_database(db.conn()).unwrap();
+        let bundle = seal(&contents, &dek, "bundle pass", KdfParams::testing()).unwrap();
+        assert_eq!(bundle.credential_count, 1);
+        assert!(!bundle.payload.contains("Bank"));
+        assert!(seal(&contents, &dek, "", KdfParams::testing()).is_err());
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("out").join("vault.vault");
+        write_bundle(&bundle, &path).unwrap();
+        let json = std::fs::read_to_string(&path).unwrap();
+        assert!(!json.contains("Bank"));
+        assert!(!json.contains("$argon2"));
+
+        // The passphrase alone recovers the DEK, and with it every record
+        let read: VaultBundle = serde_json::from_str(&json).unwrap();
+        let key = derive_key_with_salt(b"bundle pass", &read.salt, &read.kdf).unwrap();
+        let recovered = DataEncryptionKey::unwrap(&read.wrapped_dek, &key).unwrap();
+        let payload: BundleContents = serde_json::from_str(&decrypt_string(recovered.as_bytes(), &read.payload).unwrap()).unwrap();
+        assert_eq!(payload.credentials[0].name, "Bank");
+        assert_eq!(decrypt_string(recovered.as_bytes(), &payload.credentials[0].encrypted_secret).unwrap(), "hunter2");
+
+        let wrong = derive_key_with_salt(b"wrong", &read.salt, &read.kdf).unwrap();
+        assert!(DataEncryptionKey::unwrap(&read.wrapped_dek, &wrong).is_err());
+    }
+
+    #[test]
+    fn test_oversized_kdf_rejected() {
+        let db = Database::open_in_memory().unwrap();
+        let contents = BundleContents::from_database(db.conn()).unwrap();
+        let mut bundle = seal(&contents, &DataEncryptionKey::generate(), "bundle pass", KdfParams::testing()).unwrap();
+        bundle.kdf.memory_cost = u32::MAX;
+        assert!(matches!(bundle.open("bundle pass"), Err(VaultError::CryptoError(_))));
+    }
+
+    #[test]
+    fn test_open_merge_and_replace() {
+        let source = Database::open_in_memory().unwrap();
+        let source_keys = KeyHierarchy::new(MasterKey::from_bytes([1; 32])).unwrap();
+        let secret = encrypt_string(source_keys.dek().as_bytes(), "hunter2").unwrap();
+        let mut bank = Credential::new("Bank".into(), CredentialType::Password, secret);
+        bank.encrypted_notes = Some(encrypt_string(source_keys.dek().as_bytes(), "pin 1234").unwrap());
+        db::create_credential(source.conn(), &bank).unwrap();
+        let audit_key = source_keys.derive_audit_key().unwrap();
+        audit::log_action(source.conn(), &audit_key, db::AuditAction::Create, Some(&bank.id), Some("Bank"), None, None).unwrap();
+
+        let contents = BundleContents::from_database(source.conn()).unwrap();
+        let bundle = seal(&contents, source_keys.dek(), "bundle pass", KdfParams::testing()).unwrap();
+        assert!(matches!(bundle.open("wrong"), Err(VaultError::InvalidPassword)));
+        let opened = bundle.open("bundle pass").unwrap();
+        assert_eq!(opened.tampered_logs, 0);
+
+        // Merging re-encrypts under the local DEK
+        let local = Database::open_in_memory().unwrap();
+        let local_master = MasterKey::from_bytes([2; 32]);
+        let local_keys = KeyHierarchy::new(local_master.clone()).unwrap();
+        let old = Credential::new("Old".into(), CredentialType::Password, encrypt_string(local_keys.dek().as_bytes(), "x").unwrap());
+        db::create_credential(local.conn(), &old).unwrap();
+
+        let report = merge_bundle(local.conn(), local_keys.dek(), &opened).unwrap();
+        assert_eq!(report, MergeReport { added: 1, updated: 0, deleted: 0 });
+        let merged = db::get_credential(local.conn(), &bank.id).unwrap();
+        assert_eq!(decrypt_credential_data(local_keys.dek(), &merged.id, &merged.encrypted_secret).unwrap(), "hunter2");
+        assert_eq!(decrypt_credential_data(local_keys.dek(), &merged.id, merged.encrypted_notes.as_ref().unwrap()).unwrap(), "pin 1234");
+        assert_eq!(db::get_all_credentials(local.conn()).unwrap().len(), 2);
+
+        // Replacing adopts the bundle's DEK under the local master key
+        replace_with_bundle(&local, &local_master, &opened).unwrap();
+        let names: Vec<_> = db::get_all_credentials(local.conn()).unwrap().into_iter().map(|c| c.name).collect();
+        assert_eq!(names, vec!["Bank"]);
+        let wrapped: String = local.conn().query_row("SELECT value FROM metadata WHERE key = 'wrapped_dek'", [], |row| row.get(0)).unwrap();
+        let keys = KeyHierarchy::from_wrapped_dek(local_master, wrapped).unwrap();
+        assert_eq!(keys.dek().as_bytes(), source_keys.dek().as_bytes());
+        let logs = db::get_all_audit_logs(local.conn()).unwrap();
+        assert_eq!(logs.len(), 1);
+        assert!(audit::verify_log(&keys.derive_audit_key().unwrap(), &logs[0]));
+    }
+}
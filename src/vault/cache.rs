@@ -0,0 +1,135 @@
+//! Decryption Cache
+//!
+//! Keeps the most recently viewed credentials decrypted, so moving back and
+//! forth through the list does not derive keys and decrypt the same record
+//! again on every step. Entries are keyed by id and `updated_at`: an edit,
+//! a sync or a restore changes the timestamp, and the stale entry is simply
+//! never hit again. Secrets are `SecretString`s, zeroized when an entry is
+//! evicted or the cache is cleared on lock.
+
+use std::collections::VecDeque;
+
+use crate::crypto::DataEncryptionKey;
+use crate::db::Credential;
+
+use super::credential::{decrypt_credential, DecryptedCredential};
+use super::VaultResult;
+
+/// Credentials kept decrypted by default
+pub const DEFAULT_CAPACITY: usize = 32;
+
+pub struct DecryptionCache {
+    /// Least recently used first
+    entries: VecDeque<DecryptedCredential>,
+    capacity: usize,
+}
+
+impl Default for DecryptionCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl DecryptionCache {
+    pub fn new(capacity: usize) -> Self {
+        Self { entries: VecDeque::with_capacity(capacity), capacity: capacity.max(1) }
+    }
+
+    /// `cred` decrypted, from the cache when it holds this version of it
+    pub fn get(
+        &mut self,
+        conn: &rusqlite::Connection,
+        dek: &DataEncryptionKey,
+        cred: &Credential,
+    ) -> VaultResult<DecryptedCredential> {
+        if let Some(index) = self.entries.iter().position(|c| c.id == cred.id) {
+            let entry = self.entries.remove(index).expect("index is in range");
+            if entry.updated_at == cred.updated_at {
+                // Reminders and the password rule change without a new
+                // timestamp, so only the decrypted parts come from the cache
+                let fresh = DecryptedCredential {
+                    secret: entry.secret.clone(),
+                    notes: entry.notes.clone(),
+                    totp_secret: entry.totp_secret.clone(),
+                    fields: entry.fields.clone(),
+                    ..DecryptedCredential::from_credential(cred, None, None, None, Vec::new())
+                };
+                self.entries.push_back(entry);
+                return Ok(fresh);
+            }
+        }
+
+        let decrypted = decrypt_credential(conn, dek, cred, false)?;
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(decrypted.clone());
+        Ok(decrypted)
+    }
+
+    /// Drop one credential, e.g. once it is deleted
+    pub fn remove(&mut self, id: &str) {
+        self.entries.retain(|c| c.id != id);
+    }
+
+    /// Drop everything, zeroizing the secrets
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.entries.shrink_to_fit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use secrecy::ExposeSecret;
+
+    use crate::db::{CredentialType, Database};
+    use crate::vault::credential::create_credential;
+
+    fn create(conn: &rusqlite::Connection, dek: &DataEncryptionKey, name: &str) -> Credential {
+        create_credential(conn, dek, name.to_string(), CredentialType::Password, name, None, None, vec![], None, None, &[])
+            .unwrap()
+    }
+
+    #[test]
+    fn test_hits_and_invalidation() {
+        let db = Database::open_in_memory().unwrap();
+        let dek = DataEncryptionKey::generate();
+        let cred = create(db.conn(), &dek, "alpha");
+        let mut cache = DecryptionCache::default();
+        assert_eq!(cache.get(db.conn(), &dek, &cred).unwrap().secret.unwrap().expose_secret(), "alpha");
+
+        // Same version: served from the cache without touching the blob
+        let mut tampered = cred.clone();
+        tampered.encrypted_secret = "not a blob".into();
+        tampered.password_rule = Some("12 nosymbols".into());
+        let hit = cache.get(db.conn(), &dek, &tampered).unwrap();
+        assert_eq!(hit.secret.unwrap().expose_secret(), "alpha");
+        assert_eq!(hit.password_rule.as_deref(), Some("12 nosymbols"));
+
+        // A newer version is decrypted afresh
+        tampered.updated_at += Duration::seconds(1);
+        assert!(cache.get(db.conn(), &dek, &tampered).is_err());
+        assert!(cache.entries.is_empty());
+    }
+
+    #[test]
+    fn test_bounded() {
+        let db = Database::open_in_memory().unwrap();
+        let dek = DataEncryptionKey::generate();
+        let creds: Vec<Credential> = ["a", "b", "c"].iter().map(|name| create(db.conn(), &dek, name)).collect();
+        let mut cache = DecryptionCache::new(2);
+        for cred in &creds {
+            cache.get(db.conn(), &dek, cred).unwrap();
+        }
+        assert_eq!(cache.entries.len(), 2);
+        assert!(cache.entries.iter().all(|c| c.id != creds[0].id));
+
+        cache.remove(&creds[1].id);
+        assert_eq!(cache.entries.len(), 1);
+        cache.clear();
+        assert!(cache.entries.is_empty());
+    }
+}
@@ -0,0 +1,201 @@
+//! X.509 certificates: subject, issuer, SANs and expiry read from PEM
+//!
+//! Only the handful of fields the detail view shows are decoded; the DER
+//! reader below walks the certificate structure without validating it.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificateInfo {
+    pub subject: String,
+    pub issuer: String,
+    pub sans: Vec<String>,
+    pub not_after: DateTime<Utc>,
+}
+
+/// The first certificate in PEM text, e.g. the leaf of a pasted chain
+pub fn parse_pem(pem: &str) -> Option<CertificateInfo> {
+    let start = pem.find("-----BEGIN CERTIFICATE-----")? + "-----BEGIN CERTIFICATE-----".len();
+    let end = start + pem[start..].find("-----END CERTIFICATE-----")?;
+    let body: String = pem[start..end].split_whitespace().collect();
+    parse_der(&STANDARD.decode(body).ok()?)
+}
+
+fn parse_der(der: &[u8]) -> Option<CertificateInfo> {
+    let mut cert = Der(der).expect(SEQUENCE)?;
+    let mut tbs = cert.expect(SEQUENCE)?;
+
+    if tbs.peek_tag() == Some(CONTEXT_0) {
+        tbs.next()?; // version
+    }
+    tbs.expect(INTEGER)?; // serial
+    tbs.expect(SEQUENCE)?; // signature algorithm
+    let issuer = format_name(tbs.expect(SEQUENCE)?);
+    let mut validity = tbs.expect(SEQUENCE)?;
+    validity.next()?; // not before
+    let not_after = parse_time(validity.next()?)?;
+    let subject = format_name(tbs.expect(SEQUENCE)?);
+    tbs.expect(SEQUENCE)?; // public key
+
+    let mut sans = Vec::new();
+    while let Some((tag, value)) = tbs.next() {
+        if tag == CONTEXT_3 {
+            sans = find_sans(Der(value)).unwrap_or_default();
+        }
+    }
+
+    Some(CertificateInfo { subject, issuer, sans, not_after })
+}
+
+const INTEGER: u8 = 0x02;
+const OCTET_STRING: u8 = 0x04;
+const OID: u8 = 0x06;
+const SEQUENCE: u8 = 0x30;
+const SET: u8 = 0x31;
+const UTC_TIME: u8 = 0x17;
+const GENERALIZED_TIME: u8 = 0x18;
+const CONTEXT_0: u8 = 0xa0;
+const CONTEXT_3: u8 = 0xa3;
+
+const OID_SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+
+/// `C=US, O=Example, CN=example.com`, in the order the certificate lists them
+fn format_name(mut name: Der) -> String {
+    let mut parts = Vec::new();
+    while let Some(mut rdn) = name.expect(SET) {
+        while let Some(mut attr) = rdn.expect(SEQUENCE) {
+            let (Some(oid), Some((_, value))) = (attr.expect(OID), attr.next()) else { continue };
+            let Some(label) = attribute_label(oid.0) else { continue };
+            parts.push(format!("{}={}", label, String::from_utf8_lossy(value)));
+        }
+    }
+    parts.join(", ")
+}
+
+fn attribute_label(oid: &[u8]) -> Option<&'static str> {
+    match oid {
+        [0x55, 0x04, 0x03] => Some("CN"),
+        [0x55, 0x04, 0x06] => Some("C"),
+        [0x55, 0x04, 0x07] => Some("L"),
+        [0x55, 0x04, 0x08] => Some("ST"),
+        [0x55, 0x04, 0x0a] => Some("O"),
+        [0x55, 0x04, 0x0b] => Some("OU"),
+        _ => None,
+    }
+}
+
+/// DNS names, emails and IP addresses from the subjectAltName extension
+fn find_sans(mut extensions: Der) -> Option<Vec<String>> {
+    let mut list = extensions.expect(SEQUENCE)?;
+    while let Some(mut ext) = list.expect(SEQUENCE) {
+        if ext.expect(OID)?.0 != OID_SUBJECT_ALT_NAME {
+            continue;
+        }
+        let mut value = ext.next()?;
+        if value.0 != OCTET_STRING {
+            value = ext.next()?; // skip the critical flag
+        }
+        let mut names = Der(value.1).expect(SEQUENCE)?;
+        let mut sans = Vec::new();
+        while let Some((tag, name)) = names.next() {
+            match tag {
+                0x81 | 0x82 => sans.push(String::from_utf8_lossy(name).into_owned()),
+                0x87 => sans.push(format_ip(name)),
+                _ => {}
+            }
+        }
+        return Some(sans);
+    }
+    None
+}
+
+fn format_ip(bytes: &[u8]) -> String {
+    match bytes.len() {
+        4 => bytes.iter().map(u8::to_string).collect::<Vec<_>>().join("."),
+        16 => bytes.chunks(2).map(|p| format!("{:x}", u16::from_be_bytes([p[0], p[1]]))).collect::<Vec<_>>().join(":"),
+        _ => hex::encode(bytes),
+    }
+}
+
+fn parse_time((tag, value): (u8, &[u8])) -> Option<DateTime<Utc>> {
+    let text = std::str::from_utf8(value).ok()?.strip_suffix('Z')?;
+    let text = match tag {
+        // Two-digit years: 50-99 are 19xx, 00-49 are 20xx
+        UTC_TIME => format!("{}{}", if text.get(..2)?.parse::<u8>().ok()? >= 50 { "19" } else { "20" }, text),
+        GENERALIZED_TIME => text.to_string(),
+        _ => return None,
+    };
+    NaiveDateTime::parse_from_str(text.get(..14)?, "%Y%m%d%H%M%S").ok().map(|t| t.and_utc())
+}
+
+/// A cursor over DER tag-length-value items
+struct Der<'a>(&'a [u8]);
+
+impl<'a> Der<'a> {
+    fn peek_tag(&self) -> Option<u8> {
+        self.0.first().copied()
+    }
+
+    fn next(&mut self) -> Option<(u8, &'a [u8])> {
+        let (&tag, rest) = self.0.split_first()?;
+        let (&first, mut rest) = rest.split_first()?;
+        let len = if first < 0x80 {
+            first as usize
+        } else {
+            let count = (first & 0x7f) as usize;
+            if count == 0 || count > 4 || rest.len() < count {
+                return None;
+            }
+            let len = rest[..count].iter().fold(0usize, |len, b| (len << 8) | *b as usize);
+            rest = &rest[count..];
+            len
+        };
+        if len > rest.len() {
+            return None;
+        }
+        let (value, rest) = rest.split_at(len);
+        self.0 = rest;
+        Some((tag, value))
+    }
+
+    /// The next item's contents if it has the given tag
+    fn expect(&mut self, tag: u8) -> Option<Der<'a>> {
+        match self.next()? {
+            (t, value) if t == tag => Some(Der(value)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // openssl req -x509 -subj "/C=US/O=Example Org/CN=example.com" -days 3650
+    //   -addext "subjectAltName=DNS:example.com,DNS:www.example.com,IP:10.0.0.1"
+    const PEM: &str = "-----BEGIN CERTIFICATE-----
+MIIB+TCCAZ6gAwIBAgIUBcfldtBDkdbVQXyVm9b2dSoMrBQwCgYIKoZIzj0EAwIw
+OTELMAkGA1UEBhMCVVMxFDASBgNVBAoMC0V4YW1wbGUgT3JnMRQwEgYDVQQDDAtl
+eGFtcGxlLmNvbTAeFw0yNjEwMTYwMjQ4MjJaFw0zNjEwMTMwMjQ4MjJaMDkxCzAJ
+BgNVBAYTAlVTMRQwEgYDVQQKDAtFeGFtcGxlIE9yZzEUMBIGA1UEAwwLZXhhbXBs
+ZS5jb20wWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAATWEwK/Io79+icEVeCIgjPy
+tEujNAK3lz93pn6O11IoagVayzcMC/if8RtrTRtTkRWF8Bil2lwzltx/kH+smk4D
+o4GDMIGAMB0GA1UdDgQWBBTzk1YYxu9BxglFxPwhzPd6wDE1OjAfBgNVHSMEGDAW
+gBTzk1YYxu9BxglFxPwhzPd6wDE1OjAPBgNVHRMBAf8EBTADAQH/MC0GA1UdEQQm
+MCSCC2V4YW1wbGUuY29tgg93d3cuZXhhbXBsZS5jb22HBAoAAAEwCgYIKoZIzj0E
+AwIDSQAwRgIhAOvclMIeZ3zDxbspLca0kzytEYYkFf1g1hlFhuLul7J7AiEA2NJj
+kJGvyuUG/m3VtBJ4ff3UOBbI+d2iumX8yrl1iz4=
+-----END CERTIFICATE-----";
+
+    #[test]
+    fn test_parse_pem() {
+        let info = parse_pem(PEM).unwrap();
+        assert_eq!(info.subject, "C=US, O=Example Org, CN=example.com");
+        assert_eq!(info.issuer, info.subject);
+        assert_eq!(info.sans, ["example.com", "www.example.com", "10.0.0.1"]);
+        assert_eq!(info.not_after.to_rfc3339(), "2036-10-13T02:48:22+00:00");
+
+        assert_eq!(parse_pem("not a certificate"), None);
+    }
+}
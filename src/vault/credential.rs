@@ -3,12 +3,14 @@
 //! Encrypted CRUD operations for credentials.
 //!
 //! Credentials are encrypted with a Data Encryption Key (DEK), not the
-//! master key directly.
+//! master key directly; each credential's data uses its own key derived
+//! from the DEK and the credential id.
 
 use chrono::{DateTime, Local};
 use secrecy::SecretString;
+use zeroize::Zeroizing;
 
-use crate::crypto::{decrypt_string, encrypt_string, DataEncryptionKey};
+use crate::crypto::{decrypt_string, derive_credential_key, encrypt_string, DataEncryptionKey};
 use crate::db::{self, Credential, CredentialType};
 
 use super::{VaultError, VaultResult};
@@ -24,6 +26,12 @@ pub struct DecryptedCredential {
     pub totp_secret: Option<SecretString>,
     pub url: Option<String>,
     pub tags: Vec<String>,
+    /// Type-specific fields as label/value pairs, in form order
+    pub fields: Vec<(String, SecretString)>,
+    pub expires_at: Option<DateTime<Local>>,
+    pub rotate_every: Option<u32>,
+    pub rotation_due: Option<DateTime<Local>>,
+    pub password_rule: Option<String>,
     pub created_at: DateTime<Local>,
     pub updated_at: DateTime<Local>,
 }
@@ -34,6 +42,7 @@ impl DecryptedCredential {
         secret: Option<String>,
         notes: Option<String>,
         totp_secret: Option<String>,
+        fields: Vec<(String, String)>,
     ) -> Self {
         Self {
             id: cred.id.clone(),
@@ -45,69 +54,115 @@ impl DecryptedCredential {
             totp_secret: totp_secret.map(SecretString::from),
             url: cred.url.clone(),
             tags: cred.tags.clone(),
+            fields: fields.into_iter().map(|(label, value)| (label, SecretString::from(value))).collect(),
+            expires_at: cred.expires_at,
+            rotate_every: cred.rotate_every,
+            rotation_due: cred.rotation_due(),
+            password_rule: cred.password_rule.clone(),
             created_at: cred.created_at,
             updated_at: cred.updated_at,
         }
     }
 }
 
-fn encrypt_secret(dek: &DataEncryptionKey, secret: &str) -> VaultResult<String> {
-    encrypt_string(dek.as_ref(), secret).map_err(|e| VaultError::CryptoError(e.to_string()))
+/// Marks blobs encrypted with the credential's own key; blobs without it
+/// predate per-credential keys and open with the DEK itself
+const CREDENTIAL_KEY_PREFIX: &str = "k1:";
+
+/// Encrypt data of credential `id` with the key derived for it
+pub fn encrypt_credential_data(dek: &DataEncryptionKey, id: &str, plaintext: &str) -> VaultResult<String> {
+    let key = derive_credential_key(dek, id).map_err(|e| VaultError::CryptoError(e.to_string()))?;
+    let blob = encrypt_string(key.as_bytes(), plaintext).map_err(|e| VaultError::CryptoError(e.to_string()))?;
+    Ok(format!("{}{}", CREDENTIAL_KEY_PREFIX, blob))
 }
 
-fn encrypt_notes(dek: &DataEncryptionKey, notes: Option<&str>) -> VaultResult<Option<String>> {
-    let Some(n) = notes else {
-        return Ok(None);
+/// Decrypt data of credential `id`, with its own key or, for blobs from
+/// before per-credential keys, the DEK
+pub fn decrypt_credential_data(dek: &DataEncryptionKey, id: &str, blob: &str) -> VaultResult<String> {
+    let decrypted = match blob.strip_prefix(CREDENTIAL_KEY_PREFIX) {
+        Some(blob) => {
+            let key = derive_credential_key(dek, id).map_err(|e| VaultError::CryptoError(e.to_string()))?;
+            decrypt_string(key.as_bytes(), &blob.to_string())
+        }
+        None => decrypt_string(dek.as_ref(), &blob.to_string()),
     };
-    let encrypted = encrypt_string(dek.as_ref(), n)
-        .map_err(|e| VaultError::CryptoError(e.to_string()))?;
-    Ok(Some(encrypted))
+    decrypted.map_err(|e| VaultError::CryptoError(e.to_string()))
 }
 
-fn decrypt_secret(dek: &DataEncryptionKey, encrypted: &str) -> VaultResult<String> {
-    decrypt_string(dek.as_ref(), &encrypted.to_string()).map_err(|e| VaultError::CryptoError(e.to_string()))
+fn encrypt_secret(dek: &DataEncryptionKey, id: &str, secret: &str) -> VaultResult<String> {
+    encrypt_credential_data(dek, id, secret)
 }
 
-fn decrypt_notes(dek: &DataEncryptionKey, encrypted: Option<&String>) -> VaultResult<Option<String>> {
-    let Some(n) = encrypted else {
-        return Ok(None);
-    };
-    let decrypted = decrypt_string(dek.as_ref(), n)
-        .map_err(|e| VaultError::CryptoError(e.to_string()))?;
-    Ok(Some(decrypted))
+fn encrypt_notes(dek: &DataEncryptionKey, id: &str, notes: Option<&str>) -> VaultResult<Option<String>> {
+    notes.map(|n| encrypt_credential_data(dek, id, n)).transpose()
 }
 
-fn decrypt_totp_secret(dek: &DataEncryptionKey, encrypted: Option<&String>) -> VaultResult<Option<String>> {
-    let Some(t) = encrypted else {
-        return Ok(None);
+pub(crate) fn decrypt_secret(dek: &DataEncryptionKey, id: &str, encrypted: &str) -> VaultResult<String> {
+    decrypt_credential_data(dek, id, encrypted)
+}
+
+/// Strength score of a credential's secret, for sorting without keeping it around
+pub fn secret_strength(dek: &DataEncryptionKey, cred: &Credential) -> VaultResult<u32> {
+    let secret = zeroize::Zeroizing::new(decrypt_secret(dek, &cred.id, &cred.encrypted_secret)?);
+    Ok(crate::crypto::password_strength(&secret))
+}
+
+/// Whether a credential's notes contain `needle` (lowercase), without keeping them around
+pub fn notes_contain(dek: &DataEncryptionKey, cred: &Credential, needle: &str) -> VaultResult<bool> {
+    let Some(notes) = decrypt_notes(dek, &cred.id, cred.encrypted_notes.as_ref())? else {
+        return Ok(false);
     };
-    let decrypted = decrypt_string(dek.as_ref(), t)
-        .map_err(|e| VaultError::CryptoError(e.to_string()))?;
-    Ok(Some(decrypted))
+    let notes = zeroize::Zeroizing::new(notes);
+    let lower = zeroize::Zeroizing::new(notes.to_lowercase());
+    Ok(lower.contains(needle))
+}
+
+pub(crate) fn decrypt_notes(dek: &DataEncryptionKey, id: &str, encrypted: Option<&String>) -> VaultResult<Option<String>> {
+    encrypted.map(|n| decrypt_credential_data(dek, id, n)).transpose()
+}
+
+pub(crate) fn decrypt_totp_secret(dek: &DataEncryptionKey, id: &str, encrypted: Option<&String>) -> VaultResult<Option<String>> {
+    encrypted.map(|t| decrypt_credential_data(dek, id, t)).transpose()
 }
 
-fn encrypt_totp_secret(dek: &DataEncryptionKey, totp: Option<&str>) -> VaultResult<Option<String>> {
+fn encrypt_totp_secret(dek: &DataEncryptionKey, id: &str, totp: Option<&str>) -> VaultResult<Option<String>> {
     let Some(t) = totp else {
         return Ok(None);
     };
     if t.is_empty() {
         return Ok(None);
     }
-    let encrypted = encrypt_string(dek.as_ref(), t)
-        .map_err(|e| VaultError::CryptoError(e.to_string()))?;
-    Ok(Some(encrypted))
+    encrypt_credential_data(dek, id, t).map(Some)
 }
 
-fn encrypt_notes_for_update(dek: &DataEncryptionKey, notes: Option<&str>) -> VaultResult<Option<String>> {
+/// Encrypt type-specific fields as JSON label/value pairs, or `None` when all are empty
+pub fn encrypt_fields(dek: &DataEncryptionKey, id: &str, fields: &[(&str, &str)]) -> VaultResult<Option<String>> {
+    let filled: Vec<&(&str, &str)> = fields.iter().filter(|(_, value)| !value.trim().is_empty()).collect();
+    if filled.is_empty() {
+        return Ok(None);
+    }
+    let json = zeroize::Zeroizing::new(
+        serde_json::to_string(&filled).map_err(|e| VaultError::OperationFailed(e.to_string()))?,
+    );
+    encrypt_credential_data(dek, id, &json).map(Some)
+}
+
+pub fn decrypt_fields(dek: &DataEncryptionKey, id: &str, encrypted: Option<&String>) -> VaultResult<Vec<(String, String)>> {
+    let Some(f) = encrypted else {
+        return Ok(Vec::new());
+    };
+    let json = zeroize::Zeroizing::new(decrypt_credential_data(dek, id, f)?);
+    serde_json::from_str(&json).map_err(|e| VaultError::OperationFailed(format!("Invalid fields: {}", e)))
+}
+
+fn encrypt_notes_for_update(dek: &DataEncryptionKey, id: &str, notes: Option<&str>) -> VaultResult<Option<String>> {
     let Some(n) = notes else {
         return Ok(None);
     };
     if n.is_empty() {
         return Ok(None);
     }
-    let encrypted = encrypt_string(dek.as_ref(), n)
-        .map_err(|e| VaultError::CryptoError(e.to_string()))?;
-    Ok(Some(encrypted))
+    encrypt_credential_data(dek, id, n).map(Some)
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -122,17 +177,17 @@ pub fn create_credential(
     tags: Vec<String>,
     notes: Option<&str>,
     totp_secret: Option<&str>,
+    fields: &[(&str, &str)],
 ) -> VaultResult<Credential> {
-    let encrypted_secret = encrypt_secret(dek, secret)?;
-    let encrypted_notes = encrypt_notes(dek, notes)?;
-    let encrypted_totp = encrypt_totp_secret(dek, totp_secret)?;
-
-    let mut cred = Credential::new(name, credential_type, encrypted_secret);
+    // The id comes first: it picks the key everything is encrypted with
+    let mut cred = Credential::new(name, credential_type, String::new());
+    cred.encrypted_secret = encrypt_secret(dek, &cred.id, secret)?;
+    cred.encrypted_notes = encrypt_notes(dek, &cred.id, notes)?;
+    cred.encrypted_totp_secret = encrypt_totp_secret(dek, &cred.id, totp_secret)?;
+    cred.encrypted_fields = encrypt_fields(dek, &cred.id, fields)?;
     cred.username = username;
     cred.url = url;
     cred.tags = tags;
-    cred.encrypted_notes = encrypted_notes;
-    cred.encrypted_totp_secret = encrypted_totp;
 
     db::create_credential(conn, &cred)?;
     Ok(cred)
@@ -144,15 +199,31 @@ pub fn decrypt_credential(
     cred: &Credential,
     log_access: bool,
 ) -> VaultResult<DecryptedCredential> {
-    let secret = decrypt_secret(dek, &cred.encrypted_secret)?;
-    let notes = decrypt_notes(dek, cred.encrypted_notes.as_ref())?;
-    let totp_secret = decrypt_totp_secret(dek, cred.encrypted_totp_secret.as_ref())?;
+    let secret = decrypt_secret(dek, &cred.id, &cred.encrypted_secret)?;
+    let notes = decrypt_notes(dek, &cred.id, cred.encrypted_notes.as_ref())?;
+    let totp_secret = decrypt_totp_secret(dek, &cred.id, cred.encrypted_totp_secret.as_ref())?;
+    let fields = decrypt_fields(dek, &cred.id, cred.encrypted_fields.as_ref())?;
 
     if log_access {
         db::touch_credential(conn, &cred.id)?;
     }
 
-    Ok(DecryptedCredential::from_credential(cred, Some(secret), notes, totp_secret))
+    Ok(DecryptedCredential::from_credential(cred, Some(secret), notes, totp_secret, fields))
+}
+
+/// Replace only the secret, keeping notes, TOTP and fields as they are
+///
+/// The previous secret stays in the credential's history like any other edit.
+pub fn replace_secret(
+    conn: &rusqlite::Connection,
+    dek: &DataEncryptionKey,
+    cred: &mut Credential,
+    secret: &str,
+) -> VaultResult<()> {
+    cred.encrypted_secret = encrypt_secret(dek, &cred.id, secret)?;
+    cred.secret_changed_at = Some(Local::now());
+    db::update_credential(conn, cred)?;
+    Ok(())
 }
 
 pub fn update_credential(
@@ -164,11 +235,15 @@ pub fn update_credential(
     new_totp_secret: Option<&str>,
 ) -> VaultResult<()> {
     if let Some(secret) = new_secret {
-        cred.encrypted_secret = encrypt_secret(dek, secret)?;
+        // Re-encrypting alone doesn't restart the rotation clock
+        if *Zeroizing::new(decrypt_secret(dek, &cred.id, &cred.encrypted_secret)?) != secret {
+            cred.secret_changed_at = Some(Local::now());
+        }
+        cred.encrypted_secret = encrypt_secret(dek, &cred.id, secret)?;
     }
 
-    cred.encrypted_notes = encrypt_notes_for_update(dek, new_notes)?;
-    cred.encrypted_totp_secret = encrypt_totp_secret(dek, new_totp_secret)?;
+    cred.encrypted_notes = encrypt_notes_for_update(dek, &cred.id, new_notes)?;
+    cred.encrypted_totp_secret = encrypt_totp_secret(dek, &cred.id, new_totp_secret)?;
     db::update_credential(conn, cred)?;
     Ok(())
 }
@@ -205,6 +280,7 @@ mod tests {
             vec![],
             None,
             None,
+            &[],
         )
         .unwrap()
     }
@@ -226,6 +302,7 @@ mod tests {
             vec!["test".to_string()],
             Some("These are notes"),
             None,
+            &[],
         )
         .unwrap();
 
@@ -241,6 +318,30 @@ mod tests {
             Some("These are notes")
         );
         assert_eq!(decrypted.username, Some("testuser".to_string()));
+
+        assert!(notes_contain(&dek, &cred, "are notes").unwrap());
+        assert!(!notes_contain(&dek, &cred, "password").unwrap());
+    }
+
+    #[test]
+    fn test_replace_secret_keeps_notes() {
+        let db = setup_test_db();
+        let conn = db.conn();
+        let dek = test_dek();
+
+        let mut cred = create_credential(
+            conn, &dek, "Site".into(), CredentialType::Password, "old", None, None, vec![], Some("notes"), None, &[],
+        )
+        .unwrap();
+        replace_secret(conn, &dek, &mut cred, "new").unwrap();
+
+        let decrypted = decrypt_credential(conn, &dek, &db::get_credential(conn, &cred.id).unwrap(), false).unwrap();
+        assert_eq!(decrypted.secret.as_ref().map(|s| s.expose_secret()), Some("new"));
+        assert_eq!(decrypted.notes.as_ref().map(|s| s.expose_secret()), Some("notes"));
+        assert!(cred.secret_changed_at.is_some());
+
+        let revisions = db::get_revisions(conn, &cred.id).unwrap();
+        assert_eq!(decrypt_secret(&dek, &cred.id, &revisions[0].credential.encrypted_secret).unwrap(), "old");
     }
 
     #[test]
@@ -250,9 +351,17 @@ mod tests {
         let dek = test_dek();
 
         let mut cred = create_test_credential(conn, &dek, "Test", "old_secret");
+        cred.rotate_every = Some(90);
+        update_credential(conn, &dek, &mut cred, Some("old_secret"), None, None).unwrap();
+        assert_eq!(cred.secret_changed_at, None);
+        assert_eq!(cred.rotation_due(), Some(cred.created_at + chrono::Duration::days(90)));
+
         update_credential(conn, &dek, &mut cred, Some("new_secret"), Some("new notes"), None).unwrap();
+        assert!(cred.secret_changed_at.is_some());
 
         let fetched = db::get_credential(conn, &cred.id).unwrap();
+        assert_eq!(fetched.rotate_every, Some(90));
+        assert_eq!(fetched.rotation_due(), cred.rotation_due());
         let decrypted = decrypt_credential(conn, &dek, &fetched, false).unwrap();
 
         assert_eq!(
@@ -265,6 +374,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_per_credential_keys() {
+        let dek = test_dek();
+        let blob = encrypt_credential_data(&dek, "a", "hunter2").unwrap();
+        assert_eq!(decrypt_credential_data(&dek, "a", &blob).unwrap(), "hunter2");
+        // Another credential's key, or the DEK itself, does not open it
+        assert!(decrypt_credential_data(&dek, "b", &blob).is_err());
+        assert!(decrypt_string(dek.as_ref(), &blob[CREDENTIAL_KEY_PREFIX.len()..].to_string()).is_err());
+
+        // Blobs from before per-credential keys still open with the DEK
+        let legacy = encrypt_string(dek.as_ref(), "old").unwrap();
+        assert_eq!(decrypt_credential_data(&dek, "a", &legacy).unwrap(), "old");
+    }
+
+    #[test]
+    fn test_fields_roundtrip() {
+        let db = setup_test_db();
+        let conn = db.conn();
+        let dek = test_dek();
+
+        let mut cred = create_test_credential(conn, &dek, "Postgres", "pw");
+        let fields = [("Host", "db.local"), ("Port", "5432"), ("Database", " ")];
+        cred.encrypted_fields = encrypt_fields(&dek, &cred.id, &fields).unwrap();
+        update_credential(conn, &dek, &mut cred, None, None, None).unwrap();
+
+        let fetched = db::get_credential(conn, &cred.id).unwrap();
+        let decrypted = decrypt_credential(conn, &dek, &fetched, false).unwrap();
+        let labels: Vec<&str> = decrypted.fields.iter().map(|(label, _)| label.as_str()).collect();
+        assert_eq!(labels, ["Host", "Port"]);
+        assert_eq!(decrypted.fields[1].1.expose_secret(), "5432");
+        assert!(encrypt_fields(&dek, &cred.id, &fields[2..]).unwrap().is_none());
+    }
+
     #[test]
     fn test_delete_credential() {
         let db = setup_test_db();
@@ -0,0 +1,275 @@
+//! Duplicate credential detection.
+//!
+//! Credentials are grouped by site and username. A group whose members all
+//! share the same decrypted secret is an exact duplicate; a group whose
+//! secrets differ is a near duplicate (usually a stale copy of an account).
+//!
+//! The site of a credential is the main label of its URL host
+//! (`accounts.google.com` → `google`), falling back to its normalized name.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Local};
+use secrecy::ExposeSecret;
+
+use crate::crypto::DataEncryptionKey;
+use crate::db::{self, Credential};
+
+use super::credential::{decrypt_credential, update_credential};
+use super::import::domain_from_url;
+use super::VaultResult;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateKind {
+    /// Same site, username and secret
+    Exact,
+    /// Same site and username, different secrets
+    Near,
+}
+
+impl DuplicateKind {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Exact => "Exact duplicate",
+            Self::Near => "Near duplicate",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DuplicateEntry {
+    pub id: String,
+    pub name: String,
+    pub username: Option<String>,
+    pub url: Option<String>,
+    pub updated_at: DateTime<Local>,
+}
+
+impl DuplicateEntry {
+    fn from_credential(cred: &Credential) -> Self {
+        Self {
+            id: cred.id.clone(),
+            name: cred.name.clone(),
+            username: cred.username.clone(),
+            url: cred.url.clone(),
+            updated_at: cred.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DuplicateGroup {
+    pub kind: DuplicateKind,
+    /// Most recently updated first
+    pub entries: Vec<DuplicateEntry>,
+}
+
+/// Main label of a host: `accounts.google.com` → `google`
+fn site_label(host: &str) -> String {
+    let mut labels = host.rsplit('.');
+    let last = labels.next().unwrap_or(host);
+    labels.next().unwrap_or(last).to_string()
+}
+
+/// Key identifying the site a credential belongs to
+fn site_key(cred: &Credential) -> String {
+    if let Some(host) = cred.url.as_deref().and_then(domain_from_url) {
+        return site_label(&host);
+    }
+
+    let name = cred.name.trim().to_lowercase();
+    let name = name.strip_prefix("www.").unwrap_or(&name);
+    if name.contains('.') && !name.contains(' ') {
+        return site_label(name);
+    }
+    name.chars().filter(|c| c.is_alphanumeric()).collect()
+}
+
+fn username_key(cred: &Credential) -> String {
+    cred.username.as_deref().unwrap_or("").trim().to_lowercase()
+}
+
+/// Scan all credentials and return groups of duplicates
+pub fn find_duplicates(conn: &rusqlite::Connection, dek: &DataEncryptionKey) -> VaultResult<Vec<DuplicateGroup>> {
+    let credentials = db::get_all_credentials(conn)?;
+
+    let mut groups: HashMap<(String, String), Vec<&Credential>> = HashMap::new();
+    let mut order = Vec::new();
+    for cred in &credentials {
+        let key = (site_key(cred), username_key(cred));
+        if key.0.is_empty() {
+            continue;
+        }
+        groups.entry(key.clone()).or_insert_with(|| {
+            order.push(key);
+            Vec::new()
+        }).push(cred);
+    }
+
+    let mut result = Vec::new();
+    for key in order {
+        let members = &groups[&key];
+        if members.len() < 2 {
+            continue;
+        }
+        result.push(build_group(dek, members)?);
+    }
+    Ok(result)
+}
+
+fn build_group(dek: &DataEncryptionKey, members: &[&Credential]) -> VaultResult<DuplicateGroup> {
+    let mut secrets = Vec::with_capacity(members.len());
+    for cred in members {
+        let secret = super::credential::decrypt_secret(dek, &cred.id, &cred.encrypted_secret)?;
+        secrets.push(secret);
+    }
+
+    let kind = if secrets.windows(2).all(|w| w[0] == w[1]) {
+        DuplicateKind::Exact
+    } else {
+        DuplicateKind::Near
+    };
+
+    let mut entries: Vec<_> = members.iter().map(|c| DuplicateEntry::from_credential(c)).collect();
+    entries.sort_by_key(|e| std::cmp::Reverse(e.updated_at));
+    Ok(DuplicateGroup { kind, entries })
+}
+
+fn append_notes(existing: Option<String>, other: Option<String>) -> Option<String> {
+    match (existing, other) {
+        (Some(a), Some(b)) if a.contains(&b) => Some(a),
+        (Some(a), Some(b)) => Some(format!("{}\n\n{}", a, b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Fold `others` into `keep_id` and delete them
+///
+/// The kept credential retains its secret; missing fields (username, URL,
+/// TOTP) are filled from the others, tags are unioned and notes appended.
+pub fn merge_duplicates(
+    conn: &rusqlite::Connection,
+    dek: &DataEncryptionKey,
+    keep_id: &str,
+    others: &[String],
+) -> VaultResult<()> {
+    let mut keep = db::get_credential(conn, keep_id)?;
+    let decrypted = decrypt_credential(conn, dek, &keep, false)?;
+    let mut notes = decrypted.notes.map(|n| n.expose_secret().to_string());
+    let mut totp = decrypted.totp_secret.map(|t| t.expose_secret().to_string());
+
+    for id in others.iter().filter(|id| id.as_str() != keep_id) {
+        let other = db::get_credential(conn, id)?;
+        let other_decrypted = decrypt_credential(conn, dek, &other, false)?;
+
+        keep.username = keep.username.or(other.username);
+        keep.url = keep.url.or(other.url);
+        for tag in other.tags {
+            if !keep.tags.contains(&tag) {
+                keep.tags.push(tag);
+            }
+        }
+        notes = append_notes(notes, other_decrypted.notes.map(|n| n.expose_secret().to_string()));
+        totp = totp.or(other_decrypted.totp_secret.map(|t| t.expose_secret().to_string()));
+    }
+
+    update_credential(conn, dek, &mut keep, None, notes.as_deref(), totp.as_deref())?;
+    for id in others.iter().filter(|id| id.as_str() != keep_id) {
+        db::delete_credential(conn, id)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{CredentialType, Database};
+    use crate::vault::credential::create_credential;
+
+    fn add(
+        conn: &rusqlite::Connection,
+        dek: &DataEncryptionKey,
+        name: &str,
+        url: Option<&str>,
+        username: &str,
+        secret: &str,
+    ) -> Credential {
+        create_credential(
+            conn,
+            dek,
+            name.to_string(),
+            CredentialType::Password,
+            secret,
+            Some(username.to_string()),
+            url.map(String::from),
+            vec![name.to_lowercase()],
+            Some(&format!("{} notes", name)),
+            None,
+            &[],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_site_key() {
+        let db = Database::open_in_memory().unwrap();
+        let dek = DataEncryptionKey::generate();
+        let conn = db.conn();
+
+        let a = add(conn, &dek, "x", Some("https://accounts.google.com/signin"), "u", "p");
+        let b = add(conn, &dek, "Google", None, "u", "p");
+        let c = add(conn, &dek, "www.Google.com", None, "u", "p");
+        let d = add(conn, &dek, "My Bank", None, "u", "p");
+
+        assert_eq!(site_key(&a), "google");
+        assert_eq!(site_key(&b), "google");
+        assert_eq!(site_key(&c), "google");
+        assert_eq!(site_key(&d), "mybank");
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let db = Database::open_in_memory().unwrap();
+        let dek = DataEncryptionKey::generate();
+        let conn = db.conn();
+
+        add(conn, &dek, "GitHub", Some("https://github.com"), "alice", "same");
+        add(conn, &dek, "github.com", None, "Alice", "same");
+        add(conn, &dek, "Google", Some("https://google.com"), "bob", "old");
+        add(conn, &dek, "Google", None, "bob", "new");
+        add(conn, &dek, "Google", None, "carol", "new");
+
+        let groups = find_duplicates(conn, &dek).unwrap();
+        assert_eq!(groups.len(), 2);
+
+        let github = groups.iter().find(|g| g.entries[0].name.to_lowercase().contains("github")).unwrap();
+        assert_eq!(github.kind, DuplicateKind::Exact);
+        assert_eq!(github.entries.len(), 2);
+
+        let google = groups.iter().find(|g| g.entries[0].name == "Google").unwrap();
+        assert_eq!(google.kind, DuplicateKind::Near);
+        assert_eq!(google.entries.len(), 2);
+    }
+
+    #[test]
+    fn test_merge_duplicates() {
+        let db = Database::open_in_memory().unwrap();
+        let dek = DataEncryptionKey::generate();
+        let conn = db.conn();
+
+        let keep = add(conn, &dek, "Google", None, "bob", "new");
+        let other = add(conn, &dek, "google.com", Some("https://google.com"), "bob", "old");
+
+        merge_duplicates(conn, &dek, &keep.id, &[keep.id.clone(), other.id.clone()]).unwrap();
+
+        assert!(db::get_credential(conn, &other.id).is_err());
+        let merged = db::get_credential(conn, &keep.id).unwrap();
+        let decrypted = decrypt_credential(conn, &dek, &merged, false).unwrap();
+
+        assert_eq!(merged.url.as_deref(), Some("https://google.com"));
+        assert_eq!(merged.tags, vec!["google".to_string(), "google.com".to_string()]);
+        assert_eq!(decrypted.secret.unwrap().expose_secret(), "new");
+        assert_eq!(decrypted.notes.unwrap().expose_secret(), "Google notes\n\ngoogle.com notes");
+        assert!(find_duplicates(conn, &dek).unwrap().is_empty());
+    }
+}
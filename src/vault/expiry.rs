@@ -0,0 +1,165 @@
+//! Expiry dates and rotation reminders
+//!
+//! A credential expires at its certificate's notAfter, else at its own
+//! `expires_at`. Certificates live in encrypted data, so finding what expires
+//! soon means decrypting them; other credentials are only decrypted for the
+//! Expires field API keys had before the date became a column.
+
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use rusqlite::Connection;
+use zeroize::Zeroizing;
+
+use crate::crypto::DataEncryptionKey;
+use crate::db::{Credential, CredentialType};
+
+use super::credential::{decrypt_fields, decrypt_secret};
+use super::{certificate, search, VaultResult};
+
+/// Label of the API key field that held the expiry date before `expires_at`
+pub const EXPIRES_FIELD: &str = "Expires";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpiryStatus {
+    Valid,
+    ExpiringSoon,
+    Expired,
+}
+
+impl ExpiryStatus {
+    /// Expiring soon means within `warning_days` of `now`
+    pub fn of(expires: DateTime<Utc>, now: DateTime<Utc>, warning_days: u64) -> Self {
+        if expires <= now {
+            Self::Expired
+        } else if expires - now <= chrono::Duration::days(warning_days as i64) {
+            Self::ExpiringSoon
+        } else {
+            Self::Valid
+        }
+    }
+}
+
+/// A `YYYY-MM-DD` date, taken as the start of that day in UTC
+pub fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    let date = NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").ok()?;
+    Some(date.and_hms_opt(0, 0, 0)?.and_utc())
+}
+
+/// The `YYYY-MM-DD` form `parse_date` reads back
+pub fn format_date(date: DateTime<Local>) -> String {
+    date.with_timezone(&Utc).format("%Y-%m-%d").to_string()
+}
+
+/// When a credential expires: its certificate's notAfter, else `expires_at`,
+/// else the legacy Expires field
+///
+/// The certificate is looked for in the Certificate field, then the secret.
+pub fn expiry_date(
+    expires_at: Option<DateTime<Local>>,
+    fields: &[(String, String)],
+    secret: Option<&str>,
+) -> Option<DateTime<Utc>> {
+    let field = |name: &str| fields.iter().find(|(label, _)| label == name).map(|(_, value)| value.as_str());
+    field("Certificate")
+        .and_then(certificate::parse_pem)
+        .or_else(|| secret.and_then(certificate::parse_pem))
+        .map(|cert| cert.not_after)
+        .or_else(|| expires_at.map(|date| date.with_timezone(&Utc)))
+        .or_else(|| field(EXPIRES_FIELD).and_then(parse_date))
+}
+
+/// Credentials that have expired or expire within `warning_days`, soonest first
+pub fn find_expiring(
+    conn: &Connection,
+    dek: &DataEncryptionKey,
+    warning_days: u64,
+) -> VaultResult<Vec<(Credential, DateTime<Utc>)>> {
+    let now = Utc::now();
+    let mut expiring = Vec::new();
+    for cred in search::get_all(conn)? {
+        let is_certificate = cred.credential_type == CredentialType::Certificate;
+        let expires = if !is_certificate && cred.expires_at.is_some() {
+            expiry_date(cred.expires_at, &[], None)
+        } else if is_certificate || cred.encrypted_fields.is_some() {
+            let fields = decrypt_fields(dek, &cred.id, cred.encrypted_fields.as_ref())?;
+            let secret = if is_certificate { Some(Zeroizing::new(decrypt_secret(dek, &cred.id, &cred.encrypted_secret)?)) } else { None };
+            expiry_date(cred.expires_at, &fields, secret.as_deref().map(String::as_str))
+        } else {
+            None
+        };
+        let Some(expires) = expires else { continue };
+        if ExpiryStatus::of(expires, now, warning_days) != ExpiryStatus::Valid {
+            expiring.push((cred, expires));
+        }
+    }
+    expiring.sort_by_key(|(_, expires)| *expires);
+    Ok(expiring)
+}
+
+/// Credentials whose secret is due for rotation, most overdue first
+pub fn find_rotation_due(conn: &Connection) -> VaultResult<Vec<(Credential, DateTime<Local>)>> {
+    let now = Local::now();
+    let mut due: Vec<_> = search::get_all(conn)?
+        .into_iter()
+        .filter_map(|cred| cred.rotation_due().filter(|due| *due <= now).map(|due| (cred, due)))
+        .collect();
+    due.sort_by_key(|(_, due)| *due);
+    Ok(due)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{self, Database};
+    use crate::vault::credential::create_credential;
+
+    #[test]
+    fn test_find_expiring() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let dek = DataEncryptionKey::generate();
+
+        let in_days = |days: i64| Local::now() + chrono::Duration::days(days);
+        for (name, days) in [("soon", 10), ("later", 90)] {
+            let cred = create_credential(conn, &dek, name.into(), CredentialType::Password, "k", None, None, vec![], None, None, &[])
+                .unwrap();
+            db::set_reminders(conn, &cred.id, Some(in_days(days)), None).unwrap();
+        }
+        // Dates from before `expires_at` existed are still read from the field
+        let expired = format_date(in_days(-3));
+        let fields = [(EXPIRES_FIELD, expired.as_str())];
+        create_credential(conn, &dek, "past".into(), CredentialType::ApiKey, "k", None, None, vec![], None, None, &fields)
+            .unwrap();
+        create_credential(conn, &dek, "none".into(), CredentialType::ApiKey, "k", None, None, vec![], None, None, &[])
+            .unwrap();
+
+        let names: Vec<String> = find_expiring(conn, &dek, 30).unwrap().into_iter().map(|(c, _)| c.name).collect();
+        assert_eq!(names, ["past", "soon"]);
+        assert_eq!(find_expiring(conn, &dek, 0).unwrap().len(), 1);
+
+        assert_eq!(parse_date("2025-13-01"), None);
+        let now = parse_date("2025-01-01").unwrap();
+        assert_eq!(ExpiryStatus::of(parse_date("2025-01-20").unwrap(), now, 30), ExpiryStatus::ExpiringSoon);
+        assert_eq!(ExpiryStatus::of(parse_date("2025-03-01").unwrap(), now, 30), ExpiryStatus::Valid);
+        assert_eq!(format_date(now.with_timezone(&Local)), "2025-01-01");
+    }
+
+    #[test]
+    fn test_find_rotation_due() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let dek = DataEncryptionKey::generate();
+
+        for (name, changed_days_ago) in [("stale", 40), ("fresh", 10)] {
+            let mut cred = create_credential(conn, &dek, name.into(), CredentialType::Password, "pw", None, None, vec![], None, None, &[])
+                .unwrap();
+            cred.rotate_every = Some(30);
+            cred.secret_changed_at = Some(Local::now() - chrono::Duration::days(changed_days_ago));
+            db::update_credential(conn, &cred).unwrap();
+        }
+        create_credential(conn, &dek, "never".into(), CredentialType::Password, "pw", None, None, vec![], None, None, &[])
+            .unwrap();
+
+        let names: Vec<String> = find_rotation_due(conn).unwrap().into_iter().map(|(c, _)| c.name).collect();
+        assert_eq!(names, ["stale"]);
+    }
+}
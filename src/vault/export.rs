@@ -1,20 +1,29 @@
 //! Export credentials in different formats with optional encryption.
 //!
 //! Encryption options:
-//! - GPG (AES-256-GCM): `gpg -d export.gpg`
-//! - age (ChaCha20-Poly1305): `age -d export.age`
+//! - GPG (AES-256-GCM): `gpg -d export.gpg`, to a passphrase or to key IDs
+//!   from the keyring
+//! - age (ChaCha20-Poly1305): `age -d export.age`, written with the `age`
+//!   crate to a passphrase or to recipients' public keys
 //! - Plaintext: No encryption (dangerous!)
+//!
+//! The bundle format is written by `super::bundle` with the vault's own crypto.
+//! An output path of `-` writes the export to stdout instead of a file.
 
-use std::io::Write;
+use std::fmt;
+use std::io::{self, Write};
 use std::path::Path;
 use std::process::{Command, Stdio};
 
 use chrono::Local;
-use serde::Serialize;
+use secrecy::SecretString;
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
 use crate::db::models::{Credential, CredentialType};
 
-use super::{VaultError, VaultResult};
+use super::sealed::{self, SEALED_EXTENSION};
+use super::{backup, VaultError, VaultResult};
 
 /// Export format for the credential data
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -23,6 +32,19 @@ pub enum ExportFormat {
     Json,
     /// Human-readable plain text
     Text,
+    /// Encrypted whole-vault bundle (see `super::bundle`)
+    Bundle,
+    /// TOTP settings only, as an encrypted Aegis vault (see `super::aegis`)
+    Aegis,
+    /// One GPG file per credential in a password-store (see `super::pass`)
+    Pass,
+}
+
+impl ExportFormat {
+    /// Formats that carry their own encryption and are never wrapped in GPG or age
+    pub fn is_self_encrypted(&self) -> bool {
+        matches!(self, ExportFormat::Bundle | ExportFormat::Aegis | ExportFormat::Pass)
+    }
 }
 
 /// Encryption method for export
@@ -34,6 +56,8 @@ pub enum ExportEncryption {
     Gpg,
     /// age encryption (ChaCha20-Poly1305)
     Age,
+    /// Built-in Argon2id + AES-256-GCM, no external tools (see `super::sealed`)
+    Internal,
 }
 
 impl ExportEncryption {
@@ -42,6 +66,7 @@ impl ExportEncryption {
             Self::None => "",
             Self::Gpg => ".gpg",
             Self::Age => ".age",
+            Self::Internal => SEALED_EXTENSION,
         }
     }
 
@@ -50,14 +75,16 @@ impl ExportEncryption {
             Self::None => "None (plaintext)",
             Self::Gpg => "GPG (AES-256)",
             Self::Age => "age (ChaCha20-Poly1305)",
+            Self::Internal => "Built-in (Argon2id + AES-256-GCM)",
         }
     }
 }
 
-/// Decrypted credential for export (secrets in plaintext)
-#[derive(Debug, Clone, Serialize)]
+/// Decrypted credential for export (secrets in plaintext, wiped on drop)
+#[derive(Debug, Clone, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
 pub struct ExportCredential {
     pub name: String,
+    #[zeroize(skip)]
     pub credential_type: CredentialType,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub username: Option<String>,
@@ -66,42 +93,94 @@ pub struct ExportCredential {
     pub notes: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub url: Option<String>,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub tags: Vec<String>,
+    /// Type-specific fields as label/value pairs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<(String, String)>,
+    /// Expiry date as `YYYY-MM-DD`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    /// Rotation interval in days
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[zeroize(skip)]
+    pub rotate_every: Option<u32>,
+    /// Password generation rule, e.g. `16 nosymbols`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password_rule: Option<String>,
 }
 
 impl ExportCredential {
-    fn format_to_text(&self) -> String {
-        let mut output = format!("Name: {}\n", self.name);
+    fn write_text(&self, out: &mut impl fmt::Write) -> fmt::Result {
+        writeln!(out, "Name: {}", self.name)?;
 
         if self.credential_type != CredentialType::Password {
-            output.push_str(&format!("Type: {}\n", self.credential_type.display_name()));
+            writeln!(out, "Type: {}", self.credential_type.display_name())?;
         }
 
         if let Some(username) = &self.username {
-            output.push_str(&format!("Username: {}\n", username));
+            writeln!(out, "Username: {}", username)?;
         }
 
-        output.push_str(&format!("Secret: {}\n", self.secret));
+        writeln!(out, "Secret: {}", self.secret)?;
+
+        for (label, value) in &self.fields {
+            writeln!(out, "{}: {}", label, value)?;
+        }
 
         if let Some(url) = &self.url {
-            output.push_str(&format!("URL: {}\n", url));
+            writeln!(out, "URL: {}", url)?;
+        }
+
+        if let Some(expires_at) = &self.expires_at {
+            writeln!(out, "Expires: {}", expires_at)?;
+        }
+
+        if let Some(days) = self.rotate_every {
+            writeln!(out, "Rotate every: {} days", days)?;
+        }
+
+        if let Some(rule) = &self.password_rule {
+            writeln!(out, "Password rule: {}", rule)?;
         }
 
         if !self.tags.is_empty() {
-            output.push_str(&format!("Tags: {}\n", self.tags.join(", ")));
+            writeln!(out, "Tags: {}", self.tags.join(", "))?;
         }
 
         if let Some(notes) = &self.notes {
-            output.push_str(&format!("Notes: {}\n", notes));
+            writeln!(out, "Notes: {}", notes)?;
         }
 
-        output
+        Ok(())
+    }
+}
+
+/// Counts the bytes written to it, so the real output buffer can be sized
+/// up front; growing it would leave unzeroized copies of the secrets behind
+#[derive(Default)]
+struct Measure(usize);
+
+impl fmt::Write for Measure {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+impl io::Write for Measure {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
     }
 }
 
 /// Full export container
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExportData {
     pub exported_at: String,
     pub version: u32,
@@ -119,20 +198,35 @@ impl ExportData {
         }
     }
 
-    pub fn to_json(&self) -> VaultResult<String> {
-        serde_json::to_string_pretty(self)
+    pub fn to_json(&self) -> VaultResult<Zeroizing<String>> {
+        let json_error = |e: serde_json::Error| VaultError::OperationFailed(format!("JSON serialization failed: {}", e));
+        let mut measure = Measure::default();
+        serde_json::to_writer_pretty(&mut measure, self).map_err(json_error)?;
+        let mut out = Zeroizing::new(Vec::with_capacity(measure.0));
+        serde_json::to_writer_pretty(&mut *out, self).map_err(json_error)?;
+        String::from_utf8(std::mem::take(&mut *out))
+            .map(Zeroizing::new)
             .map_err(|e| VaultError::OperationFailed(format!("JSON serialization failed: {}", e)))
     }
 
-    pub fn to_text(&self) -> String {
-        let header = format!(
-            "# Vault Export - {}\n# {} credentials\n\n",
-            self.exported_at, self.credential_count
-        );
-
-        let credentials: Vec<_> = self.credentials.iter().map(|c| c.format_to_text()).collect();
+    pub fn to_text(&self) -> Zeroizing<String> {
+        let mut measure = Measure::default();
+        // Neither writer can fail
+        let _ = self.write_text(&mut measure);
+        let mut text = Zeroizing::new(String::with_capacity(measure.0));
+        let _ = self.write_text(&mut *text);
+        text
+    }
 
-        header + &credentials.join("\n---\n\n")
+    fn write_text(&self, out: &mut impl fmt::Write) -> fmt::Result {
+        write!(out, "# Vault Export - {}\n# {} credentials\n\n", self.exported_at, self.credential_count)?;
+        for (i, credential) in self.credentials.iter().enumerate() {
+            if i > 0 {
+                out.write_str("\n---\n\n")?;
+            }
+            credential.write_text(out)?;
+        }
+        Ok(())
     }
 }
 
@@ -146,14 +240,37 @@ pub fn gpg_available() -> bool {
         .unwrap_or(false)
 }
 
-pub fn age_available() -> bool {
-    Command::new("age")
-        .arg("--version")
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status()
-        .map(|s| s.success())
-        .unwrap_or(false)
+/// Output path that sends an export to stdout instead of a file
+pub const STDOUT_PATH: &str = "-";
+
+pub fn is_stdout(path: &Path) -> bool {
+    path == Path::new(STDOUT_PATH)
+}
+
+/// Open `path` for writing, or stdout for `-`; files are made owner-only
+/// before anything is written to them
+fn open_output(path: &Path) -> VaultResult<Box<dyn Write>> {
+    if is_stdout(path) {
+        return Ok(Box::new(io::stdout().lock()));
+    }
+    ensure_parent_dir(path)?;
+    let file = std::fs::File::create(path).map_err(|e| VaultError::IoError(e.to_string()))?;
+    backup::restrict_permissions(path);
+    Ok(Box::new(file))
+}
+
+/// Write `bytes` to `path`, or to stdout for `-`
+pub(super) fn write_output(path: &Path, bytes: &[u8]) -> VaultResult<()> {
+    let mut out = open_output(path)?;
+    out.write_all(bytes).and_then(|_| out.flush()).map_err(|e| VaultError::IoError(e.to_string()))
+}
+
+/// gpg writes straight to our stdout when exporting to `-`
+fn gpg_stdout(path: &Path) -> Stdio {
+    match is_stdout(path) {
+        true => Stdio::inherit(),
+        false => Stdio::null(),
+    }
 }
 
 fn ensure_parent_dir(output_path: &Path) -> VaultResult<()> {
@@ -169,35 +286,74 @@ fn ensure_parent_dir(output_path: &Path) -> VaultResult<()> {
         .map_err(|e| VaultError::IoError(format!("Failed to create directory: {}", e)))
 }
 
-fn require_passphrase<'a>(passphrase: Option<&'a str>, method: &str) -> VaultResult<&'a str> {
-    passphrase.ok_or_else(|| {
-        VaultError::OperationFailed(format!("Passphrase required for {} encryption", method))
-    })
+/// What an encrypted export is locked with
+#[derive(Debug, Clone, Copy)]
+pub enum ExportKey<'a> {
+    /// Anyone with the passphrase can decrypt
+    Passphrase(&'a str),
+    /// Only the holders of these public keys can decrypt: GPG key IDs, or
+    /// age recipients (`age1...`)
+    Recipients(&'a [String]),
+}
+
+fn require_passphrase<'a>(key: Option<ExportKey<'a>>, method: &str) -> VaultResult<&'a str> {
+    match key {
+        Some(ExportKey::Passphrase(passphrase)) => Ok(passphrase),
+        Some(ExportKey::Recipients(_)) => {
+            Err(VaultError::OperationFailed(format!("{} encryption takes a passphrase, not recipients", method)))
+        }
+        None => Err(VaultError::OperationFailed(format!("Passphrase required for {} encryption", method))),
+    }
 }
 
 pub fn export_to_file(
     data: &ExportData,
     format: ExportFormat,
     encryption: ExportEncryption,
-    passphrase: Option<&str>,
+    key: Option<ExportKey>,
     output_path: &Path,
 ) -> VaultResult<()> {
-    ensure_parent_dir(output_path)?;
+    if !is_stdout(output_path) {
+        ensure_parent_dir(output_path)?;
+    }
 
     let content = match format {
         ExportFormat::Json => data.to_json()?,
         ExportFormat::Text => data.to_text(),
+        ExportFormat::Bundle => {
+            return Err(VaultError::OperationFailed("Vault bundles are written from the database, not decrypted data".into()))
+        }
+        ExportFormat::Aegis => {
+            return Err(VaultError::OperationFailed("Aegis vaults are written from TOTP secrets, not export data".into()))
+        }
+        ExportFormat::Pass => {
+            return Err(VaultError::OperationFailed("pass stores are written one credential at a time, not from export data".into()))
+        }
     };
 
     match encryption {
-        ExportEncryption::None => {
-            std::fs::write(output_path, content).map_err(|e| VaultError::IoError(e.to_string()))
-        }
-        ExportEncryption::Gpg => {
-            encrypt_with_gpg(&content, require_passphrase(passphrase, "GPG")?, output_path)
-        }
+        ExportEncryption::None => write_output(output_path, content.as_bytes()),
+        ExportEncryption::Gpg => match key {
+            Some(ExportKey::Recipients([])) => {
+                Err(VaultError::OperationFailed("At least one GPG key ID is required".into()))
+            }
+            Some(ExportKey::Recipients(recipients)) => {
+                require_gpg()?;
+                encrypt_to_gpg_keys(recipients, &content, output_path)?;
+                if !is_stdout(output_path) {
+                    backup::restrict_permissions(output_path);
+                }
+                Ok(())
+            }
+            key => encrypt_with_gpg(&content, require_passphrase(key, "GPG")?, output_path),
+        },
         ExportEncryption::Age => {
-            encrypt_with_age(&content, require_passphrase(passphrase, "age")?, output_path)
+            let key = key.ok_or_else(|| VaultError::OperationFailed("Passphrase or recipients required for age encryption".into()))?;
+            encrypt_with_age(&content, key, output_path)
+        }
+        ExportEncryption::Internal => {
+            let sealed = sealed::seal(&content, require_passphrase(key, "built-in")?)?;
+            write_output(output_path, sealed.as_bytes())
         }
     }
 }
@@ -208,13 +364,16 @@ fn write_gpg_stdin(stdin: &mut std::process::ChildStdin, passphrase: &str, conte
     stdin.write_all(content.as_bytes()).map_err(|e| VaultError::IoError(e.to_string()))
 }
 
+pub(super) fn require_gpg() -> VaultResult<()> {
+    match gpg_available() {
+        true => Ok(()),
+        false => Err(VaultError::OperationFailed("gpg is not installed. Install it with: pacman -S gnupg".into())),
+    }
+}
+
 /// Encrypt data using GPG symmetric encryption (AES-256)
 fn encrypt_with_gpg(content: &str, passphrase: &str, output_path: &Path) -> VaultResult<()> {
-    if !gpg_available() {
-        return Err(VaultError::OperationFailed(
-            "gpg is not installed. Install it with: pacman -S gnupg".into(),
-        ));
-    }
+    require_gpg()?;
 
     let mut child = Command::new("gpg")
         .args([
@@ -223,10 +382,10 @@ fn encrypt_with_gpg(content: &str, passphrase: &str, output_path: &Path) -> Vaul
             "--batch",
             "--yes",
             "--passphrase-fd", "0",
-            "--output", output_path.to_str().unwrap_or("-"),
+            "--output", output_path.to_str().unwrap_or(STDOUT_PATH),
         ])
         .stdin(Stdio::piped())
-        .stdout(Stdio::null())
+        .stdout(gpg_stdout(output_path))
         .stderr(Stdio::piped())
         .spawn()
         .map_err(|e| VaultError::IoError(format!("Failed to spawn gpg: {}", e)))?;
@@ -245,46 +404,77 @@ fn encrypt_with_gpg(content: &str, passphrase: &str, output_path: &Path) -> Vaul
     Ok(())
 }
 
-/// Encrypt data using age (ChaCha20-Poly1305)
-fn encrypt_with_age(content: &str, passphrase: &str, output_path: &Path) -> VaultResult<()> {
-    if !age_available() {
-        return Err(VaultError::OperationFailed(
-            "age is not installed. Install it with: pacman -S age".into(),
-        ));
+/// Encrypt `content` to the public keys of `recipients`, as pass does
+pub(super) fn encrypt_to_gpg_keys(recipients: &[String], content: &str, path: &Path) -> VaultResult<()> {
+    let mut args = vec!["--encrypt", "--batch", "--yes", "--quiet", "--compress-algo=none", "--no-encrypt-to"];
+    // The keys were picked for this export, signed or not
+    args.extend(["--trust-model", "always"]);
+    for id in recipients {
+        args.extend(["--recipient", id.as_str()]);
     }
+    args.extend(["--output", path.to_str().unwrap_or(STDOUT_PATH)]);
 
-    let mut child = Command::new("age")
-        .args([
-            "--passphrase",
-            "--output", output_path.to_str().unwrap_or("-"),
-        ])
+    let mut child = Command::new("gpg")
+        .args(&args)
         .stdin(Stdio::piped())
-        .stdout(Stdio::null())
+        .stdout(gpg_stdout(path))
         .stderr(Stdio::piped())
-        .env("AGE_PASSPHRASE", passphrase)
         .spawn()
-        .map_err(|e| VaultError::IoError(format!("Failed to spawn age: {}", e)))?;
-
-    let stdin = child.stdin.as_mut()
-        .ok_or_else(|| VaultError::IoError("Failed to open age stdin".into()))?;
-    stdin.write_all(content.as_bytes()).map_err(|e| VaultError::IoError(e.to_string()))?;
+        .map_err(|e| VaultError::IoError(format!("Failed to spawn gpg: {}", e)))?;
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| VaultError::IoError("Failed to open gpg stdin".into()))?
+        .write_all(content.as_bytes())
+        .map_err(|e| VaultError::IoError(e.to_string()))?;
 
     let output = child.wait_with_output().map_err(|e| VaultError::IoError(e.to_string()))?;
-
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(VaultError::OperationFailed(format!("age encryption failed: {}", stderr)));
+        return Err(VaultError::OperationFailed(format!("gpg encryption failed: {}", stderr.trim())));
     }
-
     Ok(())
 }
 
+/// Parse age public keys (`age1...`)
+pub fn parse_age_recipients(recipients: &[String]) -> VaultResult<Vec<age::x25519::Recipient>> {
+    if recipients.is_empty() {
+        return Err(VaultError::OperationFailed("At least one age recipient is required".into()));
+    }
+    recipients
+        .iter()
+        .map(|r| {
+            r.trim()
+                .parse()
+                .map_err(|e| VaultError::OperationFailed(format!("Invalid age recipient {}: {}", r.trim(), e)))
+        })
+        .collect()
+}
+
+/// Encrypt data using age (ChaCha20-Poly1305)
+fn encrypt_with_age(content: &str, key: ExportKey, output_path: &Path) -> VaultResult<()> {
+    let age_error = |e: &dyn fmt::Display| VaultError::OperationFailed(format!("age encryption failed: {}", e));
+    let encryptor = match key {
+        ExportKey::Passphrase(passphrase) => age::Encryptor::with_user_passphrase(SecretString::from(passphrase)),
+        ExportKey::Recipients(recipients) => {
+            let recipients = parse_age_recipients(recipients)?;
+            age::Encryptor::with_recipients(recipients.iter().map(|r| r as &dyn age::Recipient))
+                .map_err(|e| age_error(&e))?
+        }
+    };
+
+    let mut writer = encryptor.wrap_output(open_output(output_path)?).map_err(|e| age_error(&e))?;
+    writer.write_all(content.as_bytes()).map_err(|e| age_error(&e))?;
+    writer.finish().and_then(|mut out| out.flush()).map_err(|e| age_error(&e))
+}
+
 /// Helper to convert a Credential (with encrypted fields) to ExportCredential
-/// The caller is responsible for decrypting the secret and notes before calling this
+/// The caller is responsible for decrypting the secret, notes and fields before calling this
 pub fn credential_to_export(
     cred: &Credential,
     decrypted_secret: String,
     decrypted_notes: Option<String>,
+    decrypted_fields: Vec<(String, String)>,
 ) -> ExportCredential {
     ExportCredential {
         name: cred.name.clone(),
@@ -294,6 +484,10 @@ pub fn credential_to_export(
         notes: decrypted_notes,
         url: cred.url.clone(),
         tags: cred.tags.clone(),
+        fields: decrypted_fields,
+        expires_at: cred.expires_at.map(super::expiry::format_date),
+        rotate_every: cred.rotate_every,
+        password_rule: cred.password_rule.clone(),
     }
 }
 
@@ -311,6 +505,10 @@ mod tests {
             notes: Some("Main account".into()),
             url: Some("https://github.com".into()),
             tags: vec!["dev".into(), "api".into()],
+            fields: vec![],
+            expires_at: None,
+            rotate_every: None,
+            password_rule: None,
         }
     }
 
@@ -323,6 +521,10 @@ mod tests {
             notes: None,
             url: None,
             tags: vec![],
+            fields: vec![],
+            expires_at: None,
+            rotate_every: None,
+            password_rule: None,
         }
     }
 
@@ -363,6 +565,16 @@ mod tests {
         assert!(!next_entry.contains("Type:"));
     }
 
+    #[test]
+    fn test_export_sized_exactly() {
+        // Buffers that never grew left no stray copies of the secrets
+        let data = sample_export_data();
+        let text = data.to_text();
+        assert_eq!(text.capacity(), text.len());
+        let json = data.to_json().unwrap();
+        assert_eq!(json.capacity(), json.len());
+    }
+
     #[test]
     fn test_plaintext_export() {
         let dir = TempDir::new().unwrap();
@@ -403,7 +615,7 @@ mod tests {
             &data,
             ExportFormat::Json,
             ExportEncryption::Gpg,
-            Some("testpassword"),
+            Some(ExportKey::Passphrase("testpassword")),
             &path,
         ).unwrap();
 
@@ -412,13 +624,16 @@ mod tests {
         assert!(!String::from_utf8_lossy(&content).contains("GitHub Token"));
     }
 
+    fn age_decrypt(path: &Path, identity: &dyn age::Identity) -> String {
+        let file = std::fs::File::open(path).unwrap();
+        let mut reader = age::Decryptor::new(file).unwrap().decrypt(std::iter::once(identity)).unwrap();
+        let mut plaintext = String::new();
+        io::Read::read_to_string(&mut reader, &mut plaintext).unwrap();
+        plaintext
+    }
+
     #[test]
     fn test_age_export() {
-        if !age_available() {
-            eprintln!("Skipping age test - age not installed");
-            return;
-        }
-
         let dir = TempDir::new().unwrap();
         let path = dir.path().join("export.json.age");
 
@@ -427,13 +642,46 @@ mod tests {
             &data,
             ExportFormat::Json,
             ExportEncryption::Age,
-            Some("testpassword"),
+            Some(ExportKey::Passphrase("testpassword")),
             &path,
         ).unwrap();
 
-        assert!(path.exists());
+        assert!(std::fs::read(&path).unwrap().starts_with(b"age-encryption.org/v1"));
+        let identity = age::scrypt::Identity::new(SecretString::from("testpassword"));
+        assert_eq!(age_decrypt(&path, &identity), *data.to_json().unwrap());
+    }
+
+    #[test]
+    fn test_age_export_to_recipients() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("export.txt.age");
+        let identity = age::x25519::Identity::generate();
+        let recipients = [identity.to_public().to_string()];
+
+        let data = sample_export_data();
+        let key = Some(ExportKey::Recipients(&recipients));
+        export_to_file(&data, ExportFormat::Text, ExportEncryption::Age, key, &path).unwrap();
+        assert_eq!(age_decrypt(&path, &identity), *data.to_text());
+
+        assert!(parse_age_recipients(&["age1nope".into()]).is_err());
+        assert!(parse_age_recipients(&[]).is_err());
+        let key = Some(ExportKey::Recipients(&recipients));
+        assert!(export_to_file(&data, ExportFormat::Json, ExportEncryption::Gpg, key, &path).is_err());
+    }
+
+    #[test]
+    fn test_internal_export() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("export.json.sealed");
+
+        let data = sample_export_data();
+        export_to_file(&data, ExportFormat::Json, ExportEncryption::Internal, Some(ExportKey::Passphrase("testpassword")), &path).unwrap();
+
         let content = std::fs::read_to_string(&path).unwrap();
-        assert!(content.starts_with("age-encryption.org"));
+        assert!(!content.contains("GitHub Token"));
+        let sealed = sealed::SealedExport::parse(&content).unwrap();
+        assert_eq!(sealed.open("testpassword").unwrap(), data.to_json().unwrap());
+        assert!(sealed.open("wrong").is_err());
     }
 
     #[test]
@@ -0,0 +1,124 @@
+//! FIDO2 hmac-secret through the libfido2 tools (`fido2-token`, `fido2-cred`
+//! and `fido2-assert`)
+//!
+//! Enrolling makes a credential with the hmac-secret extension on the
+//! security key. Unlocking asks the key for an assertion over a stored salt;
+//! the secret it answers with, only after a tap, decrypts a copy of the
+//! master key, so the password need not be typed.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use zeroize::Zeroizing;
+
+use super::{VaultError, VaultResult};
+
+/// Relying party the credential is made for
+const RP_ID: &str = "vault";
+
+/// hmac-secret takes a 32-byte salt and answers with 32 bytes
+pub const SALT_LEN: usize = 32;
+const SECRET_LEN: usize = 32;
+
+pub fn generate_salt() -> [u8; SALT_LEN] {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    salt
+}
+
+/// The first security key plugged in, e.g. `/dev/hidraw4`
+pub fn first_device() -> VaultResult<String> {
+    let output = run("fido2-token", &["-L"], None)?;
+    parse_device_list(&output).ok_or_else(|| VaultError::SecurityKeyUnavailable("no security key found".into()))
+}
+
+/// Make a hmac-secret credential on `device` and return its ID (base64)
+pub fn make_credential(device: &str) -> VaultResult<String> {
+    let user_id = STANDARD.encode(generate_salt());
+    let input = format!("{}\n{}\nvault\n{}\n", client_data_hash(), RP_ID, user_id);
+    let output = run("fido2-cred", &["-M", "-h", device], Some(&input))?;
+    // Client data hash, RP ID, format, authenticator data, credential ID, ...
+    output_line(&output, 4).map(String::from).ok_or_else(unexpected_output)
+}
+
+/// Ask `device` for the hmac-secret of `credential_id` over `salt`; the key
+/// waits for a tap
+pub fn hmac_secret(device: &str, credential_id: &str, salt: &[u8]) -> VaultResult<Zeroizing<Vec<u8>>> {
+    let input = format!("{}\n{}\n{}\n{}\n", client_data_hash(), RP_ID, credential_id, STANDARD.encode(salt));
+    let output = Zeroizing::new(run("fido2-assert", &["-G", "-h", device], Some(&input))?);
+    // Client data hash, RP ID, authenticator data, signature, secret
+    let secret = output_line(&output, 4).ok_or_else(unexpected_output)?;
+    let secret = Zeroizing::new(STANDARD.decode(secret).map_err(|_| unexpected_output())?);
+    if secret.len() != SECRET_LEN {
+        return Err(unexpected_output());
+    }
+    Ok(secret)
+}
+
+/// The tools insist on a client data hash; nothing checks the signature, so
+/// any random one does
+fn client_data_hash() -> String {
+    STANDARD.encode(generate_salt())
+}
+
+fn run(program: &str, args: &[&str], input: Option<&str>) -> VaultResult<String> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| VaultError::SecurityKeyUnavailable(format!("cannot run {}: {}", program, e)))?;
+
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin
+            .write_all(input.as_bytes())
+            .map_err(|e| VaultError::SecurityKeyUnavailable(format!("{}: {}", program, e)))?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| VaultError::SecurityKeyUnavailable(format!("{}: {}", program, e)))?;
+    if !output.status.success() {
+        return Err(VaultError::SecurityKeyUnavailable(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// `fido2-token -L` lists `path: vendor=..., product=... (name)` per key
+fn parse_device_list(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find_map(|line| line.split_once(": ").map(|(path, _)| path.trim().to_string()))
+        .filter(|path| !path.is_empty())
+}
+
+fn output_line(output: &str, index: usize) -> Option<&str> {
+    output.lines().nth(index).map(str::trim).filter(|line| !line.is_empty())
+}
+
+fn unexpected_output() -> VaultError {
+    VaultError::SecurityKeyUnavailable("unexpected output from the libfido2 tools".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_device_list() {
+        let output = "/dev/hidraw4: vendor=0x1050, product=0x0407 (Yubico YubiKey OTP+FIDO+CCID)\n\
+                      /dev/hidraw7: vendor=0x20a0, product=0x42b1 (Nitrokey 3)\n";
+        assert_eq!(parse_device_list(output).as_deref(), Some("/dev/hidraw4"));
+        assert_eq!(parse_device_list(""), None);
+    }
+
+    #[test]
+    fn test_output_line() {
+        let output = "hash\nvault\nauthdata\nsig\nc2VjcmV0\n";
+        assert_eq!(output_line(output, 4), Some("c2VjcmV0"));
+        assert_eq!(output_line(output, 5), None);
+    }
+}
@@ -0,0 +1,995 @@
+//! Import credentials from browser password exports.
+//!
+//! Supported formats:
+//! - Chrome / Chromium: `name,url,username,password[,note]`
+//! - Firefox: `url,username,password,httpRealm,formActionOrigin,guid,...`
+//! - This vault's own JSON export: plain, sealed with the built-in
+//!   encryption (see `super::sealed`), or encrypted with gpg or age, which
+//!   are recognised by their headers and opened with the export passphrase
+//! - A credential shared from another vault (see `super::share`), as the
+//!   armored block or a PNG of its QR code, opened with its one-time passphrase
+//!
+//! Entries are deduplicated by URL + username, and names are derived
+//! from the site domain. Imports are planned first so the caller can
+//! preview what will be created, skipped, or merged.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use secrecy::{ExposeSecret, SecretString};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::crypto::DataEncryptionKey;
+use crate::db::{self, Credential, CredentialType};
+use crate::qr;
+
+use super::credential::{create_credential, decrypt_credential, update_credential};
+use super::export::{require_gpg, ExportCredential, ExportData};
+use super::sealed::SealedExport;
+use super::share;
+use super::{VaultError, VaultResult};
+
+/// Source of a CSV password export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// Chrome, Chromium, Edge, Brave
+    Chrome,
+    /// Firefox
+    Firefox,
+}
+
+impl ImportFormat {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Chrome => "Chrome CSV",
+            Self::Firefox => "Firefox CSV",
+        }
+    }
+
+    /// Guess the format from the CSV header row
+    pub fn detect(header: &[String]) -> Option<Self> {
+        let has = |col: &str| header.iter().any(|h| h.eq_ignore_ascii_case(col));
+
+        if has("url") && has("password") && (has("guid") || has("httpRealm")) {
+            return Some(Self::Firefox);
+        }
+        if has("url") && has("password") && has("name") {
+            return Some(Self::Chrome);
+        }
+        None
+    }
+}
+
+/// Plaintext credential parsed from an import file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportCredential {
+    pub name: String,
+    pub credential_type: CredentialType,
+    pub username: Option<String>,
+    pub secret: String,
+    pub notes: Option<String>,
+    pub url: Option<String>,
+    pub tags: Vec<String>,
+    /// Type-specific fields, kept when the entry is stored as a new credential
+    pub fields: Vec<(String, String)>,
+}
+
+impl ImportCredential {
+    /// Key used to detect duplicates: normalized URL + username
+    pub fn dedup_key(&self) -> (String, String) {
+        dedup_key(self.url.as_deref(), self.username.as_deref())
+    }
+}
+
+/// Why an imported entry collides with an existing credential
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictReason {
+    /// Same URL and same username
+    UrlAndUsername,
+    /// Same name and same username
+    NameAndUsername,
+    /// Same URL, different username
+    Url,
+}
+
+impl ConflictReason {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::UrlAndUsername => "same URL + username",
+            Self::NameAndUsername => "same name + username",
+            Self::Url => "same URL",
+        }
+    }
+
+    fn default_resolution(&self) -> ConflictResolution {
+        match self {
+            Self::UrlAndUsername | Self::NameAndUsername => ConflictResolution::Merge,
+            Self::Url => ConflictResolution::KeepBoth,
+        }
+    }
+}
+
+/// How a conflicting entry should be applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictResolution {
+    /// Leave the existing credential untouched
+    Skip,
+    /// Replace the existing credential's fields with the imported ones
+    Overwrite,
+    /// Store the imported entry as a separate credential
+    KeepBoth,
+    /// Take the imported secret and fill in fields the existing one lacks
+    Merge,
+}
+
+impl ConflictResolution {
+    pub const ALL: [Self; 4] = [Self::Skip, Self::Overwrite, Self::KeepBoth, Self::Merge];
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Skip => "Skip",
+            Self::Overwrite => "Overwrite",
+            Self::KeepBoth => "Keep both",
+            Self::Merge => "Merge",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let idx = Self::ALL.iter().position(|r| *r == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+}
+
+/// A collision between an imported entry and a credential already in the vault
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportConflict {
+    pub existing_id: String,
+    pub existing_name: String,
+    pub existing_username: Option<String>,
+    pub reason: ConflictReason,
+    pub resolution: ConflictResolution,
+}
+
+impl ImportConflict {
+    fn new(existing: &Credential, reason: ConflictReason) -> Self {
+        Self {
+            existing_id: existing.id.clone(),
+            existing_name: existing.name.clone(),
+            existing_username: existing.username.clone(),
+            reason,
+            resolution: reason.default_resolution(),
+        }
+    }
+}
+
+/// What to do with a single imported entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportAction {
+    /// Store as a new credential
+    Create,
+    /// Identical entry already exists
+    Skip,
+    /// Collides with an existing credential; applied per its resolution
+    Conflict(ImportConflict),
+}
+
+/// Outcome (or preview) of an import run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub created: usize,
+    pub skipped: usize,
+    pub merged: usize,
+    pub overwritten: usize,
+    pub conflicts: usize,
+}
+
+/// Imported entries paired with the action that will be applied to each
+#[derive(Debug, Clone, Default)]
+pub struct ImportPlan {
+    pub items: Vec<(ImportCredential, ImportAction)>,
+}
+
+impl ImportPlan {
+    pub fn summary(&self) -> ImportSummary {
+        let mut summary = ImportSummary::default();
+        for (_, action) in &self.items {
+            match action {
+                ImportAction::Create => summary.created += 1,
+                ImportAction::Skip => summary.skipped += 1,
+                ImportAction::Conflict(c) => {
+                    summary.conflicts += 1;
+                    match c.resolution {
+                        ConflictResolution::Skip => summary.skipped += 1,
+                        ConflictResolution::Overwrite => summary.overwritten += 1,
+                        ConflictResolution::KeepBoth => summary.created += 1,
+                        ConflictResolution::Merge => summary.merged += 1,
+                    }
+                }
+            }
+        }
+        summary
+    }
+
+    /// Indices of items that need a resolution
+    pub fn conflict_indices(&self) -> Vec<usize> {
+        self.items
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, action))| matches!(action, ImportAction::Conflict(_)))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    pub fn set_resolution(&mut self, index: usize, resolution: ConflictResolution) {
+        if let Some((_, ImportAction::Conflict(c))) = self.items.get_mut(index) {
+            c.resolution = resolution;
+        }
+    }
+
+    pub fn set_all_resolutions(&mut self, resolution: ConflictResolution) {
+        for (_, action) in &mut self.items {
+            if let ImportAction::Conflict(c) = action {
+                c.resolution = resolution;
+            }
+        }
+    }
+}
+
+/// Column positions resolved from the CSV header
+struct Columns {
+    name: Option<usize>,
+    url: Option<usize>,
+    username: Option<usize>,
+    password: usize,
+    note: Option<usize>,
+}
+
+impl Columns {
+    fn from_header(header: &[String]) -> VaultResult<Self> {
+        let find = |col: &str| header.iter().position(|h| h.eq_ignore_ascii_case(col));
+
+        let password = find("password").ok_or_else(|| {
+            VaultError::OperationFailed("CSV is missing a password column".into())
+        })?;
+
+        Ok(Self {
+            name: find("name"),
+            url: find("url"),
+            username: find("username"),
+            password,
+            note: find("note").or_else(|| find("notes")),
+        })
+    }
+}
+
+/// Split CSV content into records, honouring quoted fields (RFC 4180)
+pub fn parse_csv(content: &str) -> VaultResult<Vec<Vec<String>>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = content.trim_start_matches('\u{feff}').chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' => in_quotes = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                push_record(&mut records, std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(VaultError::OperationFailed("CSV has an unterminated quoted field".into()));
+    }
+
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        push_record(&mut records, record);
+    }
+
+    Ok(records)
+}
+
+fn push_record(records: &mut Vec<Vec<String>>, record: Vec<String>) {
+    let is_blank = record.len() == 1 && record[0].is_empty();
+    if !is_blank {
+        records.push(record);
+    }
+}
+
+/// Parse a browser CSV export into credentials, deduplicated by URL + username.
+/// The format is detected from the header unless given explicitly.
+pub fn parse_browser_csv(
+    content: &str,
+    format: Option<ImportFormat>,
+) -> VaultResult<(ImportFormat, Vec<ImportCredential>)> {
+    let mut records = parse_csv(content)?.into_iter();
+
+    let header = records
+        .next()
+        .ok_or_else(|| VaultError::OperationFailed("CSV file is empty".into()))?;
+    let format = format.or_else(|| ImportFormat::detect(&header)).ok_or_else(|| {
+        VaultError::OperationFailed("Unrecognized CSV header (expected Chrome or Firefox export)".into())
+    })?;
+    let columns = Columns::from_header(&header)?;
+
+    let credentials = records.filter_map(|r| record_to_credential(&r, &columns)).collect();
+    Ok((format, dedup_credentials(credentials)))
+}
+
+/// Parse a JSON export written by this vault
+pub fn parse_vault_export(content: &str) -> VaultResult<Vec<ImportCredential>> {
+    let data: ExportData = serde_json::from_str(content)
+        .map_err(|e| VaultError::OperationFailed(format!("Not a vault JSON export: {}", e)))?;
+
+    let credentials = data.credentials.into_iter().map(from_export).collect();
+    Ok(dedup_credentials(credentials))
+}
+
+/// Export entries wipe themselves on drop, so the strings are moved out
+fn from_export(mut c: ExportCredential) -> ImportCredential {
+    ImportCredential {
+        name: std::mem::take(&mut c.name),
+        credential_type: c.credential_type,
+        username: c.username.take(),
+        secret: std::mem::take(&mut c.secret),
+        notes: c.notes.take(),
+        url: c.url.take(),
+        tags: std::mem::take(&mut c.tags),
+        fields: std::mem::take(&mut c.fields),
+    }
+}
+
+/// Read an import file: an encrypted export or shared credential (opened
+/// with `passphrase`), a vault JSON export, or a browser CSV
+pub fn read_import_file(
+    path: &Path,
+    format: Option<ImportFormat>,
+    passphrase: &str,
+) -> VaultResult<Vec<ImportCredential>> {
+    let bytes = Zeroizing::new(
+        std::fs::read(path).map_err(|e| VaultError::IoError(format!("Failed to read {}: {}", path.display(), e)))?,
+    );
+    let bytes = if bytes.starts_with(PNG_SIGNATURE) {
+        // A screenshot of a shared credential's QR code
+        Zeroizing::new(qr::read_file(path).map_err(|e| VaultError::OperationFailed(e.to_string()))?.into_bytes())
+    } else if is_age(&bytes) {
+        decrypt_age(&bytes, passphrase)?
+    } else if is_gpg(&bytes) {
+        decrypt_gpg(path, passphrase)?
+    } else {
+        bytes
+    };
+    let content = into_text(bytes)?;
+    if share::is_share(&content) {
+        return Ok(vec![from_export(share::open_share(&content, passphrase)?)]);
+    }
+    let content = match SealedExport::parse(&content) {
+        Some(sealed) => sealed.open(passphrase)?,
+        None => content,
+    };
+
+    if content.trim_start().starts_with('{') {
+        return parse_vault_export(&content);
+    }
+    Ok(parse_browser_csv(&content, format)?.1)
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Binary age files, as exports are written
+fn is_age(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"age-encryption.org/v1\n")
+}
+
+/// OpenPGP messages, armored or starting with a passphrase (symmetric) or
+/// public-key encrypted session key packet
+fn is_gpg(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"-----BEGIN PGP MESSAGE-----")
+        || bytes.first().is_some_and(|&b| {
+            let tag = match b & 0xc0 {
+                0xc0 => b & 0x3f,
+                0x80 => (b >> 2) & 0x0f,
+                _ => return false,
+            };
+            matches!(tag, 1 | 3)
+        })
+}
+
+fn decrypt_age(bytes: &[u8], passphrase: &str) -> VaultResult<Zeroizing<Vec<u8>>> {
+    let invalid = |e: age::DecryptError| VaultError::OperationFailed(format!("age decryption failed: {}", e));
+    let decryptor = age::Decryptor::new(bytes).map_err(invalid)?;
+    if !decryptor.is_scrypt() {
+        return Err(VaultError::OperationFailed(
+            "File is encrypted to age public keys; decrypt it with `age -d -i <key file>` and import the result".into(),
+        ));
+    }
+
+    let identity = age::scrypt::Identity::new(SecretString::from(passphrase));
+    let mut reader = decryptor.decrypt(std::iter::once(&identity as &dyn age::Identity)).map_err(|e| match e {
+        age::DecryptError::DecryptionFailed | age::DecryptError::NoMatchingKeys => VaultError::InvalidPassword,
+        e => invalid(e),
+    })?;
+    let mut plaintext = Zeroizing::new(Vec::new());
+    reader.read_to_end(&mut plaintext).map_err(|e| VaultError::IoError(e.to_string()))?;
+    Ok(plaintext)
+}
+
+/// Decrypt with gpg, `passphrase` being the symmetric passphrase or the one
+/// for the secret key; keys gpg-agent already holds need none
+fn decrypt_gpg(path: &Path, passphrase: &str) -> VaultResult<Zeroizing<Vec<u8>>> {
+    require_gpg()?;
+
+    let mut child = Command::new("gpg")
+        .args(["--decrypt", "--batch", "--quiet", "--no-symkey-cache", "--pinentry-mode", "loopback"])
+        .args(["--passphrase-fd", "0"])
+        .arg(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| VaultError::IoError(format!("Failed to spawn gpg: {}", e)))?;
+
+    let mut stdin = child.stdin.take().ok_or_else(|| VaultError::IoError("Failed to open gpg stdin".into()))?;
+    stdin.write_all(passphrase.as_bytes()).map_err(|e| VaultError::IoError(e.to_string()))?;
+    stdin.write_all(b"\n").map_err(|e| VaultError::IoError(e.to_string()))?;
+    drop(stdin);
+
+    let output = child.wait_with_output().map_err(|e| VaultError::IoError(e.to_string()))?;
+    let plaintext = Zeroizing::new(output.stdout);
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("Bad session key") || stderr.contains("Bad passphrase") {
+            return Err(VaultError::InvalidPassword);
+        }
+        return Err(VaultError::OperationFailed(format!("gpg decryption failed: {}", stderr.trim())));
+    }
+    Ok(plaintext)
+}
+
+fn into_text(mut bytes: Zeroizing<Vec<u8>>) -> VaultResult<Zeroizing<String>> {
+    String::from_utf8(std::mem::take(&mut *bytes)).map(Zeroizing::new).map_err(|e| {
+        e.into_bytes().zeroize();
+        VaultError::OperationFailed("Import file is not UTF-8 text".into())
+    })
+}
+
+fn column(record: &[String], idx: Option<usize>) -> Option<String> {
+    let value = record.get(idx?)?.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn record_to_credential(record: &[String], columns: &Columns) -> Option<ImportCredential> {
+    let secret = record.get(columns.password)?.clone();
+    if secret.is_empty() {
+        return None;
+    }
+
+    let url = column(record, columns.url);
+    // Firefox stores its own account sync entries with chrome:// URLs
+    if url.as_deref().is_some_and(|u| u.starts_with("chrome://")) {
+        return None;
+    }
+
+    let name = url
+        .as_deref()
+        .and_then(domain_from_url)
+        .or_else(|| column(record, columns.name))
+        .or_else(|| url.clone())
+        .unwrap_or_else(|| "Imported".to_string());
+
+    Some(ImportCredential {
+        name,
+        credential_type: CredentialType::Password,
+        username: column(record, columns.username),
+        secret,
+        notes: column(record, columns.note),
+        url,
+        tags: Vec::new(),
+        fields: Vec::new(),
+    })
+}
+
+/// Extract the host from a URL, dropping scheme, credentials, port, path and `www.`
+pub fn domain_from_url(url: &str) -> Option<String> {
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = without_scheme.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit_once('@').map_or(authority, |(_, h)| h);
+    let host = host.split(':').next()?.to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+
+    (!host.is_empty()).then(|| host.to_string())
+}
+
+fn dedup_key(url: Option<&str>, username: Option<&str>) -> (String, String) {
+    let url = url.unwrap_or("").trim().trim_end_matches('/').to_lowercase();
+    let username = username.unwrap_or("").trim().to_string();
+    (url, username)
+}
+
+/// Drop entries whose URL + username already appeared earlier in the list
+pub fn dedup_credentials(credentials: Vec<ImportCredential>) -> Vec<ImportCredential> {
+    let mut seen = HashSet::new();
+    credentials.into_iter().filter(|c| seen.insert(c.dedup_key())).collect()
+}
+
+/// Lookup tables over existing credentials used for collision detection
+struct ExistingIndex<'a> {
+    by_url_username: HashMap<(String, String), &'a Credential>,
+    by_name_username: HashMap<(String, String), &'a Credential>,
+    by_url: HashMap<String, &'a Credential>,
+}
+
+impl<'a> ExistingIndex<'a> {
+    fn new(existing: &'a [Credential]) -> Self {
+        let mut index = Self {
+            by_url_username: HashMap::new(),
+            by_name_username: HashMap::new(),
+            by_url: HashMap::new(),
+        };
+
+        for cred in existing {
+            let name_key = (cred.name.to_lowercase(), cred.username.clone().unwrap_or_default());
+            index.by_name_username.entry(name_key).or_insert(cred);
+
+            if cred.url.is_none() {
+                continue;
+            }
+            let key = dedup_key(cred.url.as_deref(), cred.username.as_deref());
+            index.by_url.entry(key.0.clone()).or_insert(cred);
+            index.by_url_username.entry(key).or_insert(cred);
+        }
+
+        index
+    }
+
+    fn find(&self, cred: &ImportCredential) -> Option<(&'a Credential, ConflictReason)> {
+        let key = cred.dedup_key();
+        let name_key = (cred.name.to_lowercase(), cred.username.clone().unwrap_or_default());
+
+        if let Some(found) = cred.url.as_ref().and(self.by_url_username.get(&key)) {
+            return Some((found, ConflictReason::UrlAndUsername));
+        }
+        if let Some(found) = self.by_name_username.get(&name_key) {
+            return Some((found, ConflictReason::NameAndUsername));
+        }
+        cred.url
+            .as_ref()
+            .and(self.by_url.get(&key.0))
+            .map(|found| (*found, ConflictReason::Url))
+    }
+}
+
+fn has_same_secret(dek: &DataEncryptionKey, existing: &Credential, secret: &str) -> VaultResult<bool> {
+    let current = super::credential::decrypt_secret(dek, &existing.id, &existing.encrypted_secret)?;
+    Ok(current == secret)
+}
+
+fn classify(
+    dek: &DataEncryptionKey,
+    index: &ExistingIndex,
+    planned: &mut HashSet<(String, String)>,
+    cred: &ImportCredential,
+) -> VaultResult<ImportAction> {
+    let Some((found, reason)) = index.find(cred) else {
+        let is_new = cred.url.is_none() || planned.insert(cred.dedup_key());
+        return Ok(if is_new { ImportAction::Create } else { ImportAction::Skip });
+    };
+
+    // Same account with the same password is a plain duplicate
+    if reason != ConflictReason::Url && has_same_secret(dek, found, &cred.secret)? {
+        return Ok(ImportAction::Skip);
+    }
+
+    Ok(ImportAction::Conflict(ImportConflict::new(found, reason)))
+}
+
+/// Decide per entry whether to create or skip it, or flag a conflict with the vault contents
+pub fn plan_import(
+    conn: &rusqlite::Connection,
+    dek: &DataEncryptionKey,
+    credentials: Vec<ImportCredential>,
+) -> VaultResult<ImportPlan> {
+    let existing = db::get_all_credentials(conn)?;
+    let index = ExistingIndex::new(&existing);
+    let mut planned = HashSet::new();
+    let mut plan = ImportPlan::default();
+
+    for cred in credentials {
+        let action = classify(dek, &index, &mut planned, &cred)?;
+        plan.items.push((cred, action));
+    }
+
+    Ok(plan)
+}
+
+fn store_new(conn: &rusqlite::Connection, dek: &DataEncryptionKey, cred: ImportCredential) -> VaultResult<()> {
+    create_credential(
+        conn,
+        dek,
+        cred.name,
+        cred.credential_type,
+        &cred.secret,
+        cred.username,
+        cred.url,
+        cred.tags,
+        cred.notes.as_deref(),
+        None,
+        &cred.fields.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect::<Vec<_>>(),
+    )?;
+    Ok(())
+}
+
+/// Replace the secret of an existing credential, filling in fields it lacks
+fn merge_into(
+    conn: &rusqlite::Connection,
+    dek: &DataEncryptionKey,
+    id: &str,
+    cred: ImportCredential,
+) -> VaultResult<()> {
+    let mut existing = db::get_credential(conn, id)?;
+    let decrypted = decrypt_credential(conn, dek, &existing, false)?;
+
+    let notes = decrypted.notes.map(|n| n.expose_secret().to_string()).or(cred.notes);
+    let totp = decrypted.totp_secret.map(|t| t.expose_secret().to_string());
+
+    existing.username = existing.username.or(cred.username);
+    existing.url = existing.url.or(cred.url);
+    for tag in cred.tags {
+        if !existing.tags.contains(&tag) {
+            existing.tags.push(tag);
+        }
+    }
+
+    update_credential(conn, dek, &mut existing, Some(&cred.secret), notes.as_deref(), totp.as_deref())
+}
+
+/// Replace an existing credential's fields with the imported ones, keeping its TOTP secret
+fn overwrite_existing(
+    conn: &rusqlite::Connection,
+    dek: &DataEncryptionKey,
+    id: &str,
+    cred: ImportCredential,
+) -> VaultResult<()> {
+    let mut existing = db::get_credential(conn, id)?;
+    let decrypted = decrypt_credential(conn, dek, &existing, false)?;
+    let totp = decrypted.totp_secret.map(|t| t.expose_secret().to_string());
+
+    existing.name = cred.name;
+    existing.credential_type = cred.credential_type;
+    existing.username = cred.username;
+    existing.url = cred.url;
+    existing.tags = cred.tags;
+
+    update_credential(conn, dek, &mut existing, Some(&cred.secret), cred.notes.as_deref(), totp.as_deref())
+}
+
+fn resolve_conflict(
+    conn: &rusqlite::Connection,
+    dek: &DataEncryptionKey,
+    conflict: ImportConflict,
+    cred: ImportCredential,
+) -> VaultResult<()> {
+    match conflict.resolution {
+        ConflictResolution::Skip => Ok(()),
+        ConflictResolution::Overwrite => overwrite_existing(conn, dek, &conflict.existing_id, cred),
+        ConflictResolution::KeepBoth => store_new(conn, dek, cred),
+        ConflictResolution::Merge => merge_into(conn, dek, &conflict.existing_id, cred),
+    }
+}
+
+/// Execute a previously computed plan, in one transaction
+pub fn apply_import(
+    conn: &rusqlite::Connection,
+    dek: &DataEncryptionKey,
+    plan: ImportPlan,
+) -> VaultResult<ImportSummary> {
+    let summary = plan.summary();
+
+    // One commit for the lot; a failing row leaves the vault as it was
+    db::in_transaction(conn, || -> VaultResult<()> {
+        for (cred, action) in plan.items {
+            match action {
+                ImportAction::Create => store_new(conn, dek, cred)?,
+                ImportAction::Skip => {}
+                ImportAction::Conflict(conflict) => resolve_conflict(conn, dek, conflict, cred)?,
+            }
+        }
+        Ok(())
+    })?;
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    const CHROME_CSV: &str = "name,url,username,password,note\n\
+        github.com,https://github.com/login,alice,hunter2,\n\
+        www.example.com,https://www.example.com/,bob,\"pa,ss\"\"word\",\"multi\nline\"\n\
+        github.com,https://github.com/login/,alice,duplicate,\n";
+
+    /// Plan and apply an import in one step
+    fn import_credentials(
+        conn: &rusqlite::Connection,
+        dek: &DataEncryptionKey,
+        credentials: Vec<ImportCredential>,
+    ) -> VaultResult<ImportSummary> {
+        let plan = plan_import(conn, dek, credentials)?;
+        apply_import(conn, dek, plan)
+    }
+
+    const FIREFOX_CSV: &str = "\"url\",\"username\",\"password\",\"httpRealm\",\"formActionOrigin\",\"guid\",\"timeCreated\",\"timeLastUsed\",\"timePasswordChanged\"\r\n\
+        \"https://accounts.google.com\",\"carol@gmail.com\",\"s3cret\",,\"https://accounts.google.com\",\"{abc}\",\"1\",\"1\",\"1\"\r\n\
+        \"chrome://FirefoxAccounts\",\"sync\",\"token\",,,\"{def}\",\"1\",\"1\",\"1\"\r\n";
+
+    #[test]
+    fn test_parse_csv_quoted_fields() {
+        let records = parse_csv("a,\"b,c\",\"d\"\"e\"\n\"multi\nline\",x,\n").unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0], vec!["a", "b,c", "d\"e"]);
+        assert_eq!(records[1], vec!["multi\nline", "x", ""]);
+    }
+
+    #[test]
+    fn test_parse_csv_unterminated_quote() {
+        assert!(parse_csv("a,\"b\n").is_err());
+    }
+
+    #[test]
+    fn test_domain_from_url() {
+        assert_eq!(domain_from_url("https://www.GitHub.com/login"), Some("github.com".into()));
+        assert_eq!(domain_from_url("http://user@host.io:8080/x?y"), Some("host.io".into()));
+        assert_eq!(domain_from_url("example.org"), Some("example.org".into()));
+        assert_eq!(domain_from_url("https://"), None);
+    }
+
+    #[test]
+    fn test_parse_chrome_csv() {
+        let (format, creds) = parse_browser_csv(CHROME_CSV, None).unwrap();
+
+        assert_eq!(format, ImportFormat::Chrome);
+        assert_eq!(creds.len(), 2);
+        assert_eq!(creds[0].name, "github.com");
+        assert_eq!(creds[0].secret, "hunter2");
+        assert_eq!(creds[1].name, "example.com");
+        assert_eq!(creds[1].secret, "pa,ss\"word");
+        assert_eq!(creds[1].notes.as_deref(), Some("multi\nline"));
+    }
+
+    #[test]
+    fn test_parse_firefox_csv() {
+        let (format, creds) = parse_browser_csv(FIREFOX_CSV, None).unwrap();
+
+        assert_eq!(format, ImportFormat::Firefox);
+        assert_eq!(creds.len(), 1);
+        assert_eq!(creds[0].name, "accounts.google.com");
+        assert_eq!(creds[0].username.as_deref(), Some("carol@gmail.com"));
+        assert_eq!(creds[0].secret, "s3cret");
+    }
+
+    #[test]
+    fn test_unknown_header_rejected() {
+        assert!(parse_browser_csv("foo,bar\n1,2\n", None).is_err());
+    }
+
+    #[test]
+    fn test_import_skips_existing() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let dek = DataEncryptionKey::generate();
+
+        let (_, creds) = parse_browser_csv(CHROME_CSV, None).unwrap();
+        let first = import_credentials(conn, &dek, creds.clone()).unwrap();
+        assert_eq!(first, ImportSummary { created: 2, ..Default::default() });
+
+        let second = import_credentials(conn, &dek, creds).unwrap();
+        assert_eq!(second, ImportSummary { skipped: 2, ..Default::default() });
+        assert_eq!(db::get_all_credentials(conn).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_import_merges_changed_secret() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let dek = DataEncryptionKey::generate();
+
+        let (_, creds) = parse_browser_csv(CHROME_CSV, None).unwrap();
+        import_credentials(conn, &dek, creds).unwrap();
+
+        let updated = "name,url,username,password\ngithub.com,https://github.com/login,alice,rotated\n";
+        let (_, creds) = parse_browser_csv(updated, None).unwrap();
+        let plan = plan_import(conn, &dek, creds).unwrap();
+        assert_eq!(plan.summary(), ImportSummary { merged: 1, conflicts: 1, ..Default::default() });
+
+        apply_import(conn, &dek, plan).unwrap();
+        let github = db::get_all_credentials(conn)
+            .unwrap()
+            .into_iter()
+            .find(|c| c.name == "github.com")
+            .unwrap();
+        let decrypted = decrypt_credential(conn, &dek, &github, false).unwrap();
+        assert_eq!(decrypted.secret.unwrap().expose_secret(), "rotated");
+    }
+
+    fn setup_with_github() -> (Database, DataEncryptionKey) {
+        let db = Database::open_in_memory().unwrap();
+        let dek = DataEncryptionKey::generate();
+        let (_, creds) = parse_browser_csv(CHROME_CSV, None).unwrap();
+        import_credentials(db.conn(), &dek, creds).unwrap();
+        (db, dek)
+    }
+
+    fn find_by_name(conn: &rusqlite::Connection, name: &str) -> Vec<Credential> {
+        db::get_all_credentials(conn).unwrap().into_iter().filter(|c| c.name == name).collect()
+    }
+
+    #[test]
+    fn test_conflict_reasons() {
+        let (db, dek) = setup_with_github();
+        let csv = "name,url,username,password\n\
+            github.com,https://github.com/other,alice,changed\n\
+            github,https://github.com/login,bob,other\n";
+        let (_, creds) = parse_browser_csv(csv, None).unwrap();
+        let plan = plan_import(db.conn(), &dek, creds).unwrap();
+
+        let reasons: Vec<_> = plan
+            .items
+            .iter()
+            .map(|(_, a)| match a {
+                ImportAction::Conflict(c) => Some((c.reason, c.resolution)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(reasons, vec![
+            Some((ConflictReason::NameAndUsername, ConflictResolution::Merge)),
+            Some((ConflictReason::Url, ConflictResolution::KeepBoth)),
+        ]);
+        assert_eq!(plan.conflict_indices(), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_conflict_resolutions() {
+        let (db, dek) = setup_with_github();
+        let conn = db.conn();
+        let csv = "name,url,username,password\ngithub.com,https://github.com/login,alice,new\n";
+
+        let (_, creds) = parse_browser_csv(csv, None).unwrap();
+        let mut plan = plan_import(conn, &dek, creds.clone()).unwrap();
+        plan.set_all_resolutions(ConflictResolution::KeepBoth);
+        apply_import(conn, &dek, plan).unwrap();
+        assert_eq!(find_by_name(conn, "github.com").len(), 2);
+
+        let (db, dek) = setup_with_github();
+        let conn = db.conn();
+        let mut plan = plan_import(conn, &dek, creds.clone()).unwrap();
+        plan.set_resolution(0, ConflictResolution::Skip);
+        assert_eq!(plan.summary().skipped, 1);
+        apply_import(conn, &dek, plan).unwrap();
+        let github = &find_by_name(conn, "github.com")[0];
+        let decrypted = decrypt_credential(conn, &dek, github, false).unwrap();
+        assert_eq!(decrypted.secret.unwrap().expose_secret(), "hunter2");
+
+        let mut renamed = creds;
+        renamed[0].name = "GitHub".into();
+        let mut plan = plan_import(conn, &dek, renamed).unwrap();
+        plan.set_resolution(0, ConflictResolution::Overwrite);
+        assert_eq!(apply_import(conn, &dek, plan).unwrap().overwritten, 1);
+        assert!(find_by_name(conn, "github.com").is_empty());
+        assert_eq!(find_by_name(conn, "GitHub").len(), 1);
+    }
+
+    #[test]
+    fn test_failed_import_rolls_back() {
+        let (db, dek) = setup_with_github();
+        let conn = db.conn();
+        let csv = "name,url,username,password\n\
+            new.com,https://new.com,carol,fresh\n\
+            github.com,https://github.com/login,alice,new\n";
+        let (_, creds) = parse_browser_csv(csv, None).unwrap();
+        let mut plan = plan_import(conn, &dek, creds).unwrap();
+        plan.set_resolution(1, ConflictResolution::Overwrite);
+
+        // The credential to overwrite is gone by the time the plan runs
+        let github = find_by_name(conn, "github.com")[0].id.clone();
+        conn.execute("DELETE FROM credentials WHERE id = ?1", [&github]).unwrap();
+        assert!(apply_import(conn, &dek, plan).is_err());
+        assert!(find_by_name(conn, "new.com").is_empty());
+        assert!(conn.is_autocommit());
+    }
+
+    #[test]
+    fn test_vault_export_round_trip() {
+        use super::super::export::ExportCredential;
+
+        let exported = ExportCredential {
+            name: "Prod DB".into(),
+            credential_type: CredentialType::Database,
+            username: Some("admin".into()),
+            secret: "hunter2".into(),
+            notes: None,
+            url: None,
+            tags: vec!["work".into()],
+            fields: vec![("Host".into(), "db.local".into())],
+            expires_at: Some("2030-01-01".into()),
+            rotate_every: None,
+            password_rule: None,
+        };
+        let json = ExportData::new(vec![exported]).to_json().unwrap();
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("export.json");
+        std::fs::write(&path, json.as_bytes()).unwrap();
+        let creds = read_import_file(&path, None, "").unwrap();
+        assert_eq!(creds.len(), 1);
+        assert_eq!((creds[0].name.as_str(), creds[0].secret.as_str()), ("Prod DB", "hunter2"));
+        assert_eq!(creds[0].credential_type, CredentialType::Database);
+        assert_eq!(creds[0].fields, [("Host".to_string(), "db.local".to_string())]);
+
+        let db = Database::open_in_memory().unwrap();
+        let dek = DataEncryptionKey::generate();
+        import_credentials(db.conn(), &dek, creds).unwrap();
+        let stored = &db::get_all_credentials(db.conn()).unwrap()[0];
+        let fields = super::super::credential::decrypt_fields(&dek, &stored.id, stored.encrypted_fields.as_ref()).unwrap();
+        assert_eq!(fields, [("Host".to_string(), "db.local".to_string())]);
+
+        assert!(parse_vault_export("{\"credentials\": 3}").is_err());
+    }
+
+    #[test]
+    fn test_encrypted_export_round_trip() {
+        use super::super::export::{export_to_file, gpg_available, ExportEncryption, ExportFormat, ExportKey};
+
+        let data = ExportData::new(Vec::new());
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut methods = vec![ExportEncryption::Age, ExportEncryption::Internal];
+        if gpg_available() {
+            methods.push(ExportEncryption::Gpg);
+        }
+        for encryption in methods {
+            let path = dir.path().join(format!("export.json{}", encryption.file_extension()));
+            let key = Some(ExportKey::Passphrase("export pass"));
+            export_to_file(&data, ExportFormat::Json, encryption, key, &path).unwrap();
+
+            assert!(read_import_file(&path, None, "export pass").unwrap().is_empty());
+            assert!(matches!(read_import_file(&path, None, "wrong"), Err(VaultError::InvalidPassword)), "{:?}", encryption);
+        }
+    }
+
+    #[test]
+    fn test_detect_encrypted_files() {
+        assert!(is_age(b"age-encryption.org/v1\n-> scrypt"));
+        // Old and new format symmetric key packets, and a public-key one
+        assert!(is_gpg(&[0x8c, 0x0d]) && is_gpg(&[0xc3, 0x2e]) && is_gpg(&[0x85, 0x01]));
+        assert!(is_gpg(b"-----BEGIN PGP MESSAGE-----\n"));
+        assert!(!is_gpg(b"name,url") && !is_gpg("\u{feff}name".as_bytes()) && !is_age(b"{}"));
+    }
+
+    #[test]
+    fn test_explicit_format_overrides_detection() {
+        let (format, _) = parse_browser_csv(CHROME_CSV, Some(ImportFormat::Firefox)).unwrap();
+        assert_eq!(format, ImportFormat::Firefox);
+    }
+}
@@ -0,0 +1,212 @@
+//! In-memory search index
+//!
+//! Every credential's metadata, with its name, username and tags lowercased
+//! once, so live search filters in memory on each keystroke instead of
+//! reading (and, with encrypted metadata, decrypting) the whole table again.
+//! The app updates entries as it creates, edits and deletes credentials.
+//! Anything else that writes to the database - a sync, a tag rename, another
+//! process - moves the connection's change counters, and the next lookup
+//! loads the index afresh.
+
+use rusqlite::Connection;
+
+use crate::db::{self, Credential, CredentialType, DbError};
+
+use super::search::{has_tag, split_tag_filter, TagMatch};
+use super::VaultResult;
+
+/// A credential with its searchable fields lowercased
+pub struct IndexEntry {
+    credential: Credential,
+    name: String,
+    username: Option<String>,
+    tags: Vec<String>,
+}
+
+impl IndexEntry {
+    fn new(credential: Credential) -> Self {
+        Self {
+            name: credential.name.to_lowercase(),
+            username: credential.username.as_deref().map(str::to_lowercase),
+            tags: credential.tags.iter().map(|t| t.to_lowercase()).collect(),
+            credential,
+        }
+    }
+
+    pub fn credential(&self) -> &Credential {
+        &self.credential
+    }
+
+    /// Whether the name, username or a tag contains `needle`, which must be lowercase
+    pub fn contains(&self, needle: &str) -> bool {
+        self.name.contains(needle)
+            || self.username.as_ref().is_some_and(|u| u.contains(needle))
+            || self.tags.iter().any(|t| t.contains(needle))
+    }
+
+    /// `tag` or a tag nested under it, ignoring ASCII case like the tag query did
+    fn has_tag_ignoring_case(&self, tag: &str) -> bool {
+        let tag = tag.to_ascii_lowercase();
+        self.credential.tags.iter().map(|t| t.to_ascii_lowercase()).any(|t| {
+            t.strip_prefix(&tag).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+    }
+}
+
+/// The database's change counters when the index last matched it
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Stamp {
+    /// Rows changed through this connection
+    own: u64,
+    /// Bumped when another connection commits
+    others: i64,
+}
+
+impl Stamp {
+    fn of(conn: &Connection) -> VaultResult<Self> {
+        let others = conn.query_row("PRAGMA data_version", [], |row| row.get(0))?;
+        Ok(Self { own: conn.total_changes(), others })
+    }
+}
+
+#[derive(Default)]
+pub struct SearchIndex {
+    /// In the database's name order, edited and new credentials in place or last
+    entries: Vec<IndexEntry>,
+    /// None until loaded, and after `clear`
+    stamp: Option<Stamp>,
+}
+
+impl SearchIndex {
+    /// Load every credential unless the index already matches the database
+    pub fn sync(&mut self, conn: &Connection) -> VaultResult<()> {
+        let stamp = Stamp::of(conn)?;
+        if self.stamp == Some(stamp) {
+            return Ok(());
+        }
+        self.entries = db::get_all_credentials(conn)?.into_iter().map(IndexEntry::new).collect();
+        self.stamp = Some(Stamp::of(conn)?);
+        Ok(())
+    }
+
+    /// Read `ids` again after the app changed them, dropping deleted ones,
+    /// and take the database as matching otherwise
+    pub fn refresh<'a>(&mut self, conn: &Connection, ids: impl IntoIterator<Item = &'a str>) -> VaultResult<()> {
+        if self.stamp.is_none() {
+            return Ok(());
+        }
+        for id in ids {
+            let position = self.entries.iter().position(|e| e.credential.id == id);
+            match (db::get_credential(conn, id), position) {
+                (Ok(cred), Some(i)) => self.entries[i] = IndexEntry::new(cred),
+                (Ok(cred), None) => self.entries.push(IndexEntry::new(cred)),
+                (Err(DbError::NotFound(_)), Some(i)) => {
+                    self.entries.remove(i);
+                }
+                (Err(DbError::NotFound(_)), None) => {}
+                (Err(e), _) => return Err(e.into()),
+            }
+        }
+        self.stamp = Some(Stamp::of(conn)?);
+        Ok(())
+    }
+
+    /// Forget everything, e.g. on lock
+    pub fn clear(&mut self) {
+        self.entries = Vec::new();
+        self.stamp = None;
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &IndexEntry> {
+        self.entries.iter()
+    }
+
+    /// Entries matching the tag filter whose type is any of `types`, as
+    /// `search::filter_credentials` picks them from the database
+    pub fn filter<'a>(
+        &'a self,
+        tags: &[String],
+        tag_match: TagMatch,
+        types: &'a [CredentialType],
+    ) -> impl Iterator<Item = &'a IndexEntry> {
+        let (included, excluded) = split_tag_filter(tags);
+        self.entries.iter().filter(move |e| {
+            let included_ok = match tag_match {
+                _ if included.is_empty() => true,
+                TagMatch::All => included.iter().all(|t| e.has_tag_ignoring_case(t)),
+                TagMatch::Any => included.iter().any(|t| has_tag(&e.credential, t)),
+            };
+            included_ok
+                && !excluded.iter().any(|t| has_tag(&e.credential, t))
+                && (types.is_empty() || types.contains(&e.credential.credential_type))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::vault::search::filter_credentials;
+
+    fn insert(conn: &Connection, name: &str, tags: &[&str]) -> Credential {
+        let mut cred = Credential::new(name.to_string(), CredentialType::Password, "enc".to_string());
+        cred.username = Some(format!("{}@Example.com", name));
+        cred.tags = tags.iter().map(|t| t.to_string()).collect();
+        db::create_credential(conn, &cred).unwrap();
+        cred
+    }
+
+    fn names<'a>(entries: impl Iterator<Item = &'a IndexEntry>) -> Vec<&'a str> {
+        entries.map(|e| e.credential().name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_filter_matches_database() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        insert(conn, "AWS", &["Work/cloud", "prod"]);
+        insert(conn, "GitHub", &["work"]);
+        insert(conn, "Bank", &["personal"]);
+        let mut index = SearchIndex::default();
+        index.sync(conn).unwrap();
+
+        for tags in [vec![], vec!["work".to_string()], vec!["-prod".to_string()], vec!["work".into(), "prod".into()]] {
+            for tag_match in [TagMatch::All, TagMatch::Any] {
+                let expected: Vec<String> =
+                    filter_credentials(conn, &tags, tag_match, &[]).unwrap().into_iter().map(|c| c.name).collect();
+                assert_eq!(names(index.filter(&tags, tag_match, &[])), expected, "{:?} {:?}", tags, tag_match);
+            }
+        }
+
+        let found: Vec<_> = index.entries().filter(|e| e.contains("example")).collect();
+        assert_eq!(found.len(), 3);
+        assert_eq!(names(index.entries().filter(|e| e.contains("work/c"))), vec!["AWS"]);
+    }
+
+    #[test]
+    fn test_incremental_and_stale() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let aws = insert(conn, "AWS", &[]);
+        let mut index = SearchIndex::default();
+        index.sync(conn).unwrap();
+
+        // Changes the app reports are picked up one by one
+        let github = insert(conn, "GitHub", &[]);
+        db::delete_credential(conn, &aws.id).unwrap();
+        index.refresh(conn, [github.id.as_str(), aws.id.as_str()]).unwrap();
+        assert_eq!(names(index.entries()), vec!["GitHub"]);
+
+        // Anything else is noticed by the next sync
+        conn.execute("UPDATE credentials SET name = 'Renamed'", []).unwrap();
+        assert_eq!(names(index.entries()), vec!["GitHub"]);
+        index.sync(conn).unwrap();
+        assert_eq!(names(index.entries()), vec!["Renamed"]);
+
+        index.clear();
+        assert_eq!(index.entries().count(), 0);
+        index.sync(conn).unwrap();
+        assert_eq!(index.entries().count(), 1);
+    }
+}
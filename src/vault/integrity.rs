@@ -0,0 +1,190 @@
+//! Integrity check over the whole database
+//!
+//! SQLite checks its own pages and indexes with `PRAGMA integrity_check`.
+//! On top of that every encrypted column of every credential, trashed
+//! credential and revision has to decrypt under the DEK, and every audit
+//! entry has to carry a valid HMAC. Damaged rows come back as report groups.
+
+use rusqlite::Connection;
+
+use crate::crypto::{DataEncryptionKey, DerivedKey};
+use crate::db;
+
+use super::audit;
+use super::credential::decrypt_credential_data;
+use super::report::{Report, ReportEntry, ReportGroup, ReportKind};
+use super::rotation::{encrypted_columns, TABLES};
+use super::VaultResult;
+
+/// What `check` found
+#[derive(Debug, Clone)]
+pub struct IntegrityCheck {
+    /// Problems SQLite reported; empty when the file is sound
+    pub sqlite: Vec<String>,
+    /// Credential, trash and revision rows checked
+    pub records: usize,
+    pub audit_entries: usize,
+    /// Damaged rows by table, only the tables that have any
+    pub damaged: Vec<ReportGroup>,
+}
+
+impl IntegrityCheck {
+    pub fn is_ok(&self) -> bool {
+        self.sqlite.is_empty() && self.damaged.is_empty()
+    }
+
+    /// One line, e.g. "SQLite ok; 1 of 12 records damaged; 40 audit entries verify"
+    pub fn summary(&self) -> String {
+        let sqlite = match self.sqlite.len() {
+            0 => "SQLite ok".to_string(),
+            n => format!("SQLite found {} problem(s)", n),
+        };
+        let (audit, records): (Vec<&ReportGroup>, Vec<&ReportGroup>) =
+            self.damaged.iter().partition(|g| g.title.as_deref() == Some(AUDIT_GROUP));
+        let count = |groups: Vec<&ReportGroup>| -> usize { groups.iter().map(|g| g.entries.len()).sum() };
+        let records = match count(records) {
+            0 => format!("{} records decrypt", self.records),
+            n => format!("{} of {} records damaged", n, self.records),
+        };
+        let audit = match count(audit) {
+            0 => format!("{} audit entries verify", self.audit_entries),
+            n => format!("{} of {} audit entries fail", n, self.audit_entries),
+        };
+        format!("{}; {}; {}", sqlite, records, audit)
+    }
+
+    /// The problems as a report, SQLite's first
+    pub fn into_report(self) -> Report {
+        let mut groups = Vec::new();
+        if !self.sqlite.is_empty() {
+            let entries = self
+                .sqlite
+                .into_iter()
+                .map(|message| ReportEntry { id: String::new(), name: message, username: None, detail: "sqlite".to_string() })
+                .collect();
+            groups.push(ReportGroup { title: Some("SQLite".to_string()), entries });
+        }
+        groups.extend(self.damaged);
+        Report { kind: ReportKind::Integrity, groups }
+    }
+}
+
+const AUDIT_GROUP: &str = "Audit log";
+
+/// Run every check; only a failing query aborts, damage is reported
+pub fn check(conn: &Connection, dek: &DataEncryptionKey, audit_key: &DerivedKey) -> VaultResult<IntegrityCheck> {
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    let sqlite: Vec<String> = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?
+        .into_iter()
+        .filter(|message| message != "ok")
+        .collect();
+
+    let mut records = 0;
+    let mut damaged = Vec::new();
+    let columns = encrypted_columns(conn);
+    for ((table, key), title) in TABLES.into_iter().zip(["Credentials", "Trash", "Revisions"]) {
+        let (checked, entries) = check_table(conn, dek, table, key, &columns)?;
+        records += checked;
+        if !entries.is_empty() {
+            damaged.push(ReportGroup { title: Some(title.to_string()), entries });
+        }
+    }
+
+    let logs = db::get_all_audit_logs(conn)?;
+    let audit_entries = logs.len();
+    let entries: Vec<ReportEntry> = logs
+        .into_iter()
+        .filter(|log| !audit::verify_log(audit_key, log))
+        .map(|log| ReportEntry {
+            id: log.credential_id.clone().unwrap_or_default(),
+            name: log.credential_name.clone().unwrap_or_else(|| log.action.as_str().to_string()),
+            username: log.username.clone(),
+            detail: format!("entry {}: bad HMAC", log.id),
+        })
+        .collect();
+    if !entries.is_empty() {
+        damaged.push(ReportGroup { title: Some(AUDIT_GROUP.to_string()), entries });
+    }
+
+    Ok(IntegrityCheck { sqlite, records, audit_entries, damaged })
+}
+
+/// Rows of `table` with columns that do not decrypt, and how many rows there are
+fn check_table(
+    conn: &Connection,
+    dek: &DataEncryptionKey,
+    table: &str,
+    key: &str,
+    columns: &[&str],
+) -> VaultResult<(usize, Vec<ReportEntry>)> {
+    let sql = format!(
+        "SELECT {}, id, vault_open(id, name), vault_open(id, username), {} FROM {}",
+        key,
+        columns.join(", "),
+        table
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let mut rows = stmt.query([])?;
+
+    let mut checked = 0;
+    let mut entries = Vec::new();
+    while let Some(row) = rows.next()? {
+        checked += 1;
+        let id: String = row.get(1)?;
+        let mut failed = Vec::new();
+        for (i, column) in columns.iter().enumerate() {
+            let blob: Option<String> = row.get(4 + i)?;
+            if blob.is_some_and(|blob| decrypt_credential_data(dek, &id, &blob).is_err()) {
+                failed.push(column.trim_start_matches("encrypted_").replace('_', " "));
+            }
+        }
+        if failed.is_empty() {
+            continue;
+        }
+
+        let mut detail = format!("{} do not decrypt", failed.join(", "));
+        if key != "id" {
+            detail = format!("{} {}: {}", key, row.get::<_, i64>(0)?, detail);
+        }
+        entries.push(ReportEntry { id, name: row.get(2)?, username: row.get(3)?, detail });
+    }
+    Ok((checked, entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{KeyHierarchy, MasterKey};
+    use crate::db::{AuditAction, CredentialType, Database};
+    use crate::vault::credential::create_credential;
+
+    #[test]
+    fn test_check() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let keys = KeyHierarchy::new(MasterKey::from_bytes([1u8; 32])).unwrap();
+        let audit_key = keys.derive_audit_key().unwrap();
+
+        let good = create_credential(conn, keys.dek(), "Good".into(), CredentialType::Password, "pw", None, None, vec![], Some("notes"), None, &[]).unwrap();
+        let bad = create_credential(conn, keys.dek(), "Bad".into(), CredentialType::Password, "pw", None, None, vec![], Some("notes"), None, &[]).unwrap();
+        audit::log_action(conn, &audit_key, AuditAction::Create, Some(&good.id), Some("Good"), None, None).unwrap();
+
+        let clean = check(conn, keys.dek(), &audit_key).unwrap();
+        assert!(clean.is_ok());
+        assert_eq!(clean.summary(), "SQLite ok; 2 records decrypt; 1 audit entries verify");
+
+        conn.execute("UPDATE credentials SET encrypted_notes = ?1 WHERE id = ?2", [&good.encrypted_secret, &bad.id]).unwrap();
+        conn.execute("UPDATE audit_log SET details = 'edited'", []).unwrap();
+        let damaged = check(conn, keys.dek(), &audit_key).unwrap();
+        assert!(!damaged.is_ok());
+        assert_eq!(damaged.summary(), "SQLite ok; 1 of 2 records damaged; 1 of 1 audit entries fail");
+
+        let report = damaged.into_report();
+        assert_eq!(report.groups.len(), 2);
+        assert_eq!(report.groups[0].entries[0].name, "Bad");
+        assert_eq!(report.groups[0].entries[0].detail, "notes do not decrypt");
+        assert_eq!(report.groups[1].title.as_deref(), Some("Audit log"));
+    }
+}
@@ -0,0 +1,84 @@
+//! Session keys in the Linux kernel keyring through the system `keyctl`
+//!
+//! The user keyring lives in kernel memory, never on disk, and is gone after
+//! a reboot. A random session key kept there decrypts a copy of the master
+//! key stored in the vault, so the vault reopens without the password until
+//! the machine restarts or the session is forgotten. Any process running as
+//! the same user can read the keyring, which is why this is off by default.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use rand::rngs::OsRng;
+use rand::RngCore;
+use zeroize::Zeroizing;
+
+use super::{VaultError, VaultResult};
+
+pub const SESSION_KEY_LEN: usize = 32;
+
+pub fn generate_session_key() -> Zeroizing<[u8; SESSION_KEY_LEN]> {
+    let mut key = Zeroizing::new([0u8; SESSION_KEY_LEN]);
+    OsRng.fill_bytes(key.as_mut());
+    key
+}
+
+/// A fresh name for a session key, e.g. `vault-session:3f9c...`
+pub fn generate_description() -> String {
+    let mut id = [0u8; 8];
+    OsRng.fill_bytes(&mut id);
+    format!("vault-session:{}", hex::encode(id))
+}
+
+/// Add `key` to the user keyring under `description`
+pub fn store(description: &str, key: &[u8]) -> VaultResult<()> {
+    let mut child = Command::new("keyctl")
+        .args(["padd", "user", description, "@u"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| VaultError::KeyringUnavailable(format!("cannot run keyctl: {}", e)))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(key).map_err(|e| VaultError::KeyringUnavailable(e.to_string()))?;
+    }
+    let output = child.wait_with_output().map_err(|e| VaultError::KeyringUnavailable(e.to_string()))?;
+    if !output.status.success() {
+        return Err(VaultError::KeyringUnavailable(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+    Ok(())
+}
+
+/// The key stored under `description`, or None when the keyring lost it,
+/// e.g. after a reboot
+pub fn load(description: &str) -> Option<Zeroizing<Vec<u8>>> {
+    let id = key_id(description)?;
+    let output = Command::new("keyctl").args(["pipe", &id]).stdin(Stdio::null()).output().ok()?;
+    let key = Zeroizing::new(output.stdout);
+    (output.status.success() && key.len() == SESSION_KEY_LEN).then_some(key)
+}
+
+/// Drop the key stored under `description`; nothing to drop is fine
+pub fn remove(description: &str) -> VaultResult<()> {
+    let Some(id) = key_id(description) else { return Ok(()) };
+    let output = Command::new("keyctl")
+        .args(["unlink", &id, "@u"])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| VaultError::KeyringUnavailable(format!("cannot run keyctl: {}", e)))?;
+    if !output.status.success() {
+        return Err(VaultError::KeyringUnavailable(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+    Ok(())
+}
+
+fn key_id(description: &str) -> Option<String> {
+    let output = Command::new("keyctl")
+        .args(["search", "@u", "user", description])
+        .stdin(Stdio::null())
+        .output()
+        .ok()?;
+    let id = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !id.is_empty()).then_some(id)
+}
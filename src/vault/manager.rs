@@ -4,16 +4,28 @@
 //!
 //! Uses a wrapped DEK (Data Encryption Key) model so password changes do not
 //! require re-encrypting stored data.
+//!
+//! The database file itself may also be encrypted (SQLCipher) under the
+//! master password. It then has to be opened with the password before any
+//! metadata can be read, so it cannot be unlocked without the password.
 
 use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
+use zeroize::{Zeroize, Zeroizing};
+
 use crate::crypto::{
-    derive_master_key, verify_master_key, DataEncryptionKey, KdfParams, KeyHierarchy, MasterKey,
+    derive_master_key, mix_keyfile, mix_token_response, mix_tpm_secret, verify_master_key, DataEncryptionKey, KdfParams, KeyHierarchy, MasterKey,
 };
-use crate::db::{Database, DatabaseConfig};
+use crate::db::{self, Database, DatabaseConfig};
+
+use crate::crypto::encryption::{decrypt_bytes, encrypt_bytes};
 
-use super::{VaultError, VaultResult};
+use super::credential::{decrypt_credential_data, encrypt_credential_data};
+use super::{backup, fido2, keyring, metadata, rotation, tpm, yubikey, VaultError, VaultResult};
+
+/// Metadata key of the command-mode history, also the id its key derives from
+const COMMAND_HISTORY_KEY: &str = "command_history";
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum VaultState {
@@ -25,6 +37,10 @@ pub enum VaultState {
 #[derive(Debug, Clone)]
 pub struct VaultConfig {
     pub path: PathBuf,
+    /// Keyfile mixed into the master key, for vaults that use one
+    pub keyfile: Option<PathBuf>,
+    /// Argon2 parameters for new password hashes; existing ones keep theirs
+    pub kdf: KdfParams,
 }
 
 impl Default for VaultConfig {
@@ -34,7 +50,7 @@ impl Default for VaultConfig {
             .join("vault")
             .join("vault.db");
 
-        Self { path }
+        Self { path, keyfile: None, kdf: KdfParams::default() }
     }
 }
 
@@ -42,8 +58,30 @@ impl VaultConfig {
     pub fn with_path(path: impl Into<PathBuf>) -> Self {
         Self {
             path: path.into(),
+            keyfile: None,
+            kdf: KdfParams::default(),
         }
     }
+
+    pub fn with_keyfile(mut self, keyfile: Option<PathBuf>) -> Self {
+        self.keyfile = keyfile;
+        self
+    }
+
+    pub fn with_kdf(mut self, kdf: KdfParams) -> Self {
+        self.kdf = kdf;
+        self
+    }
+}
+
+/// Hardware the master key is bound to besides the password; a vault uses
+/// at most one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Factor {
+    /// A YubiKey's challenge-response answer
+    Token,
+    /// A secret sealed to this machine's TPM
+    Tpm,
 }
 
 pub struct Vault {
@@ -51,6 +89,10 @@ pub struct Vault {
     db: Option<Database>,
     key_hierarchy: Option<KeyHierarchy>,
     password_hash: Option<String>,
+    /// The YubiKey's answer or the unsealed TPM secret (or the recovery code
+    /// for either) while unlocked, so rewrapping the DEK does not need the
+    /// hardware again
+    hardware_secret: Option<(Factor, Zeroizing<Vec<u8>>)>,
     last_activity: Instant,
 }
 
@@ -61,10 +103,15 @@ impl Vault {
             db: None,
             key_hierarchy: None,
             password_hash: None,
+            hardware_secret: None,
             last_activity: Instant::now(),
         }
     }
 
+    pub fn config(&self) -> &VaultConfig {
+        &self.config
+    }
+
     pub fn state(&self) -> VaultState {
         if self.key_hierarchy.is_some() {
             return VaultState::Unlocked;
@@ -84,13 +131,23 @@ impl Vault {
             return Err(VaultError::AlreadyExists);
         }
 
+        // Read the keyfile before anything lands on disk
+        let keyfile = match &self.config.keyfile {
+            Some(_) => Some(self.read_keyfile()?),
+            None => None,
+        };
         self.create_parent_directory()?;
-        let (master_key, password_hash) = self.derive_new_master_key(password)?;
+        let (password_key, password_hash) = self.derive_new_master_key(password)?;
+        let master_key = match &keyfile {
+            Some(contents) => mix_keyfile(&password_key, contents),
+            None => password_key,
+        };
         let key_hierarchy = self.create_key_hierarchy(master_key)?;
         let db = self.open_database()?;
 
         Self::store_password_hash(db.conn(), &password_hash)?;
         Self::store_wrapped_dek(db.conn(), key_hierarchy.wrapped_dek())?;
+        Self::store_keyfile_flag(db.conn(), keyfile.is_some())?;
 
         self.db = Some(db);
         self.key_hierarchy = Some(key_hierarchy);
@@ -101,16 +158,59 @@ impl Vault {
     }
 
     pub fn unlock(&mut self, password: &str) -> VaultResult<()> {
+        self.unlock_with_factors(password, None)
+    }
+
+    /// Unlock a YubiKey or TPM vault with the recovery code written down at
+    /// setup in place of the token or the machine
+    pub fn unlock_with_recovery_code(&mut self, password: &str, code: &str) -> VaultResult<()> {
+        self.unlock_with_factors(password, Some(code))
+    }
+
+    fn unlock_with_factors(&mut self, password: &str, recovery: Option<&str>) -> VaultResult<()> {
+        if !self.config.path.exists() {
+            return Err(VaultError::NotFound);
+        }
+
+        let db = self.open_database_with_password(password)?;
+        let stored_hash = Self::load_password_hash(db.conn())?;
+        let password_key = Self::verify_password_and_get_key(password, &stored_hash)?;
+        let uses_keyfile = Self::uses_keyfile(db.conn());
+        let hardware_secret = Self::unlock_hardware_secret(db.conn(), recovery)?;
+        let master_key = self.master_key_for(password_key, uses_keyfile, hardware_secret.as_ref().map(|(f, s)| (*f, s.as_slice())))?;
+        let wrapped_dek = Self::load_wrapped_dek(db.conn())?;
+        let key_hierarchy = Self::reconstruct_key_hierarchy(master_key, wrapped_dek).map_err(|e| {
+            match (hardware_secret.as_ref().map(|(factor, _)| *factor), uses_keyfile) {
+                (Some(Factor::Token), _) => VaultError::InvalidToken,
+                (Some(Factor::Tpm), _) => VaultError::InvalidTpmSecret,
+                (None, true) => VaultError::InvalidKeyfile,
+                (None, false) => e,
+            }
+        })?;
+
+        metadata::bind(&db, key_hierarchy.dek())?;
+        self.db = Some(db);
+        self.key_hierarchy = Some(key_hierarchy);
+        self.password_hash = Some(stored_hash);
+        self.hardware_secret = hardware_secret;
+        self.update_activity();
+
+        Ok(())
+    }
+
+    /// Unlock with a master key handed over by the agent instead of a password
+    pub fn unlock_with_key(&mut self, master_key: MasterKey) -> VaultResult<()> {
         if !self.config.path.exists() {
             return Err(VaultError::NotFound);
         }
 
         let db = self.open_database()?;
         let stored_hash = Self::load_password_hash(db.conn())?;
-        let master_key = Self::verify_password_and_get_key(password, &stored_hash)?;
         let wrapped_dek = Self::load_wrapped_dek(db.conn())?;
-        let key_hierarchy = Self::reconstruct_key_hierarchy(master_key, wrapped_dek)?;
+        let key_hierarchy = KeyHierarchy::from_wrapped_dek(master_key, wrapped_dek)
+            .map_err(|_| VaultError::InvalidPassword)?;
 
+        metadata::bind(&db, key_hierarchy.dek())?;
         self.db = Some(db);
         self.key_hierarchy = Some(key_hierarchy);
         self.password_hash = Some(stored_hash);
@@ -119,10 +219,52 @@ impl Vault {
         Ok(())
     }
 
+    /// Unlock with a tap on the enrolled FIDO2 security key instead of the
+    /// password; its hmac-secret decrypts the stored copy of the master key
+    pub fn unlock_with_fido2(&mut self) -> VaultResult<()> {
+        if !self.config.path.exists() {
+            return Err(VaultError::NotFound);
+        }
+
+        let db = self.open_database()?;
+        let (credential_id, salt, wrapped_key) = Self::load_fido2(db.conn()).ok_or(VaultError::Fido2NotEnrolled)?;
+        let device = fido2::first_device()?;
+        let secret = fido2::hmac_secret(&device, &credential_id, &salt)?;
+        let master_key = Self::decrypt_master_key(&secret, &wrapped_key).ok_or(VaultError::InvalidSecurityKey)?;
+
+        self.unlock_with_key(master_key)
+    }
+
+    /// Unlock with the session key the kernel keyring kept since an earlier
+    /// unlock; gone after a reboot or `forget_session`
+    pub fn unlock_with_session(&mut self) -> VaultResult<()> {
+        if !self.config.path.exists() {
+            return Err(VaultError::NotFound);
+        }
+
+        let db = self.open_database()?;
+        let (description, wrapped_key) = Self::load_session(db.conn()).ok_or(VaultError::NoSession)?;
+        let session_key = keyring::load(&description).ok_or(VaultError::NoSession)?;
+        let master_key = Self::decrypt_master_key(&session_key, &wrapped_key).ok_or(VaultError::NoSession)?;
+
+        self.unlock_with_key(master_key)
+    }
+
+    /// Rebuild the keys after the stored wrapped DEK changed, e.g. on a bundle import
+    pub fn reload_keys(&mut self) -> VaultResult<()> {
+        let master_key = self.keys()?.master_key().clone();
+        let wrapped_dek = Self::load_wrapped_dek(self.db()?.conn())?;
+        let key_hierarchy = Self::reconstruct_key_hierarchy(master_key, wrapped_dek)?;
+        metadata::bind(self.db()?, key_hierarchy.dek())?;
+        self.key_hierarchy = Some(key_hierarchy);
+        Ok(())
+    }
+
     pub fn lock(&mut self) {
         self.db = None;
         self.key_hierarchy = None;
         self.password_hash = None;
+        self.hardware_secret = None;
     }
 
     pub fn time_since_activity(&self) -> Duration {
@@ -153,12 +295,23 @@ impl Vault {
 
     pub fn change_password(&mut self, old_password: &str, new_password: &str) -> VaultResult<()> {
         self.verify_current_password(old_password)?;
-        let (new_master_key, new_hash) = self.derive_new_master_key(new_password)?;
+        let (new_password_key, new_hash) = self.derive_new_master_key(new_password)?;
+        let uses_keyfile = Self::uses_keyfile(self.db()?.conn());
+        let new_master_key = self.master_key_for(new_password_key, uses_keyfile, self.cached_hardware_secret())?;
         let new_wrapped_dek = self.rewrap_dek(new_master_key)?;
 
         let db = self.db.as_ref().ok_or(VaultError::Locked)?;
-        Self::store_password_hash(db.conn(), &new_hash)?;
-        Self::store_wrapped_dek(db.conn(), &new_wrapped_dek)?;
+        let rekey = self.is_database_encrypted() && old_password != new_password;
+        if rekey {
+            db.rekey(new_password)?;
+        }
+        let stored = Self::store_password_hash(db.conn(), &new_hash).and_then(|_| Self::store_wrapped_dek(db.conn(), &new_wrapped_dek));
+        if let Err(e) = stored {
+            if rekey {
+                let _ = db.rekey(old_password);
+            }
+            return Err(e);
+        }
 
         self.password_hash = Some(new_hash);
         self.update_activity();
@@ -166,8 +319,370 @@ impl Vault {
         Ok(())
     }
 
-    pub fn record_failed_unlock(&self) -> VaultResult<()> {
+    /// Replace the DEK with a fresh one: every credential, trashed credential
+    /// and revision is re-encrypted and the audit log signed again, all in
+    /// one transaction, then the new DEK is wrapped with the same master key.
+    /// On failure the database rolls back and the old DEK stays. Returns the
+    /// number of records re-encrypted.
+    pub fn rotate_dek(&mut self, progress: &mut dyn FnMut(usize, usize)) -> VaultResult<usize> {
+        let keys = self.keys()?;
+        let new_keys = keys.with_fresh_dek().map_err(|e| VaultError::CryptoError(e.to_string()))?;
+        let old_audit = keys.derive_audit_key().map_err(|e| VaultError::CryptoError(e.to_string()))?;
+        let new_audit = new_keys.derive_audit_key().map_err(|e| VaultError::CryptoError(e.to_string()))?;
+        let history = self.command_history()?;
+        let db = self.db()?;
+        let conn = db.conn();
+
+        conn.execute_batch("BEGIN")?;
+        let result = (|| -> VaultResult<usize> {
+            let rows = rotation::reencrypt_all(conn, keys.dek(), new_keys.dek(), progress)?;
+            rotation::resign_audit_log(conn, &old_audit, &new_audit)?;
+            rotation::reencrypt_audit_log(conn, keys.dek(), new_keys.dek())?;
+            if !history.is_empty() {
+                Self::store_command_history(conn, new_keys.dek(), &history)?;
+            }
+            Self::store_wrapped_dek(conn, new_keys.wrapped_dek())?;
+            // The search index is keyed from the DEK too
+            metadata::bind(db, new_keys.dek())?;
+            if metadata::is_encrypted(conn) {
+                db::update_search_index(conn, true)?;
+            }
+            Ok(rows)
+        })();
+
+        match result {
+            Ok(rows) => {
+                conn.execute_batch("COMMIT")?;
+                self.key_hierarchy = Some(new_keys);
+                Ok(rows)
+            }
+            Err(e) => {
+                let _ = conn.execute_batch("ROLLBACK");
+                metadata::bind(db, keys.dek())?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Whether names, usernames, URLs and tags are stored encrypted (vault format 2)
+    pub fn is_metadata_encrypted(&self) -> VaultResult<bool> {
+        Ok(metadata::is_encrypted(self.db()?.conn()))
+    }
+
+    /// Encrypt the metadata of every record, or store it in plaintext again
+    pub fn set_metadata_encryption(&mut self, password: &str, encrypt: bool) -> VaultResult<()> {
+        self.verify_current_password(password)?;
+        metadata::set_encrypted(self.db()?, self.dek()?, encrypt)?;
+        self.update_activity();
+        Ok(())
+    }
+
+    /// Commands entered in command mode, oldest first
+    pub fn command_history(&self) -> VaultResult<Vec<String>> {
+        let Some(blob) = Self::get_metadata_value(self.db()?.conn(), COMMAND_HISTORY_KEY) else {
+            return Ok(Vec::new());
+        };
+        let text = decrypt_credential_data(self.dek()?, COMMAND_HISTORY_KEY, &blob)?;
+        Ok(text.lines().map(String::from).collect())
+    }
+
+    /// Replace the command history; it is encrypted under the DEK, since
+    /// commands name tags and credentials
+    pub fn save_command_history(&self, entries: &[String]) -> VaultResult<()> {
+        Self::store_command_history(self.db()?.conn(), self.dek()?, entries)
+    }
+
+    fn store_command_history(conn: &rusqlite::Connection, dek: &DataEncryptionKey, entries: &[String]) -> VaultResult<()> {
+        let blob = encrypt_credential_data(dek, COMMAND_HISTORY_KEY, &entries.join("\n"))?;
+        conn.execute("INSERT OR REPLACE INTO metadata (key, value) VALUES (?1, ?2)", [COMMAND_HISTORY_KEY, &blob])?;
+        Ok(())
+    }
+
+    /// Whether the database file is encrypted as a whole
+    pub fn is_database_encrypted(&self) -> bool {
+        db::is_encrypted_file(&self.config.path)
+    }
+
+    /// Encrypt the whole database file under the master password, or turn it
+    /// back into plain SQLite. The file is rewritten through a converted
+    /// copy, so a failure leaves the old one in place. A security key or
+    /// keyring session cannot open an encrypted file, so encrypting drops them.
+    pub fn set_database_encryption(&mut self, password: &str, encrypt: bool) -> VaultResult<()> {
+        self.verify_current_password(password)?;
+        if encrypt == self.is_database_encrypted() {
+            return Ok(());
+        }
+        if !db::ENCRYPTION_SUPPORTED {
+            return Err(db::DbError::EncryptionUnsupported.into());
+        }
+        if encrypt {
+            if self.has_duress_password()? {
+                return Err(VaultError::OperationFailed(
+                    "The duress password is checked before unlocking, which an encrypted database does not allow; :duress off first".into(),
+                ));
+            }
+            Self::store_fido2(self.db()?.conn(), None)?;
+            self.forget_session()?;
+        }
+
+        let mut name = self.config.path.file_name().unwrap_or_default().to_os_string();
+        name.push(".converting");
+        let converted = self.config.path.with_file_name(name);
+        let _ = std::fs::remove_file(&converted);
+        let key = if encrypt { password } else { "" };
+        if let Err(e) = self.db()?.export_to(&converted, key) {
+            let _ = std::fs::remove_file(&converted);
+            return Err(e.into());
+        }
+
+        self.db = None;
+        let swapped = backup::replace_database(&self.config.path, &converted);
+        let _ = std::fs::remove_file(&converted);
+        let db = self.open_database_with_password(password)?;
+        metadata::bind(&db, self.dek()?)?;
+        self.db = Some(db);
+        self.update_activity();
+        swapped
+    }
+
+    /// Argon2 parameters for the next password hash
+    pub fn set_kdf_params(&mut self, kdf: KdfParams) {
+        self.config.kdf = kdf;
+    }
+
+    /// The Argon2 parameters the master password is hashed with now
+    pub fn kdf_params(&self) -> VaultResult<KdfParams> {
+        let hash = self.password_hash.as_ref().ok_or(VaultError::Locked)?;
+        KdfParams::from_hash(hash).map_err(|e| VaultError::CryptoError(e.to_string()))
+    }
+
+    /// Hash the password again with `kdf` and rewrap the DEK under the new
+    /// key; the parameters travel in the stored hash
+    pub fn change_kdf(&mut self, password: &str, kdf: KdfParams) -> VaultResult<()> {
+        self.config.kdf = kdf;
+        self.change_password(password, password)
+    }
+
+    /// Where to find the keyfile from now on, for a vault that already uses one
+    pub fn set_keyfile_path(&mut self, keyfile: Option<PathBuf>) {
+        self.config.keyfile = keyfile;
+    }
+
+    /// Whether the open vault needs a keyfile to unlock
+    pub fn has_keyfile(&self) -> VaultResult<bool> {
+        Ok(Self::uses_keyfile(self.db()?.conn()))
+    }
+
+    /// Start or stop requiring a keyfile; the DEK is rewrapped like on a
+    /// password change, so nothing is re-encrypted
+    pub fn change_keyfile(&mut self, password: &str, keyfile: Option<PathBuf>) -> VaultResult<()> {
+        let hash = self.password_hash.as_ref().ok_or(VaultError::Locked)?;
+        let password_key = Self::verify_password_and_get_key(password, hash)?;
+
+        let previous = std::mem::replace(&mut self.config.keyfile, keyfile);
+        let master_key = match self.master_key_for(password_key, self.config.keyfile.is_some(), self.cached_hardware_secret()) {
+            Ok(key) => key,
+            Err(e) => {
+                self.config.keyfile = previous;
+                return Err(e);
+            }
+        };
+        let new_wrapped_dek = self.rewrap_dek(master_key)?;
+
+        let db = self.db.as_ref().ok_or(VaultError::Locked)?;
+        Self::store_wrapped_dek(db.conn(), &new_wrapped_dek)?;
+        Self::store_keyfile_flag(db.conn(), self.config.keyfile.is_some())?;
+        self.update_activity();
+
+        Ok(())
+    }
+
+    /// The YubiKey slot unlocking needs, if any
+    pub fn token_slot(&self) -> VaultResult<Option<u8>> {
+        Ok(Self::load_token(self.db()?.conn()).map(|(slot, _)| slot))
+    }
+
+    /// The recovery code that stands in for the YubiKey or the TPM, while unlocked
+    pub fn recovery_code(&self) -> Option<Zeroizing<String>> {
+        self.hardware_secret.as_ref().map(|(factor, secret)| match factor {
+            Factor::Token => yubikey::format_recovery_code(secret),
+            Factor::Tpm => tpm::format_recovery_code(secret),
+        })
+    }
+
+    /// Start requiring the YubiKey in `slot`, or stop with None; a fresh
+    /// challenge is stored and the DEK rewrapped. Returns the recovery code
+    /// for the new challenge
+    pub fn change_token(&mut self, password: &str, slot: Option<u8>) -> VaultResult<Option<Zeroizing<String>>> {
+        let hash = self.password_hash.as_ref().ok_or(VaultError::Locked)?;
+        let password_key = Self::verify_password_and_get_key(password, hash)?;
+        if self.has_tpm()? {
+            return Err(VaultError::OperationFailed("The vault is bound to the TPM; :tpm off first".into()));
+        }
+
+        let challenge = yubikey::generate_challenge();
+        let response = match slot {
+            Some(slot) => Some((Factor::Token, yubikey::challenge_response(slot, &challenge)?)),
+            None => None,
+        };
+        let uses_keyfile = self.has_keyfile()?;
+        let master_key = self.master_key_for(password_key, uses_keyfile, response.as_ref().map(|(f, s)| (*f, s.as_slice())))?;
+        let new_wrapped_dek = self.rewrap_dek(master_key)?;
+
+        let db = self.db.as_ref().ok_or(VaultError::Locked)?;
+        Self::store_wrapped_dek(db.conn(), &new_wrapped_dek)?;
+        Self::store_token(db.conn(), slot.map(|slot| (slot, &challenge[..])))?;
+        self.hardware_secret = response;
+        self.update_activity();
+
+        Ok(self.recovery_code())
+    }
+
+    /// Whether unlocking needs this machine's TPM
+    pub fn has_tpm(&self) -> VaultResult<bool> {
+        Ok(Self::load_tpm(self.db()?.conn()).is_some())
+    }
+
+    /// Bind the vault to this machine's TPM, or release it; a fresh secret is
+    /// sealed against the current PCRs and the DEK rewrapped. Returns the
+    /// recovery code for the new secret
+    pub fn change_tpm(&mut self, password: &str, enable: bool) -> VaultResult<Option<Zeroizing<String>>> {
+        let hash = self.password_hash.as_ref().ok_or(VaultError::Locked)?;
+        let password_key = Self::verify_password_and_get_key(password, hash)?;
+        if self.token_slot()?.is_some() {
+            return Err(VaultError::OperationFailed("The vault already needs a YubiKey; :yubikey off first".into()));
+        }
+
+        let secret = tpm::generate_secret();
+        let sealed = match enable {
+            true => {
+                let sealed = tpm::seal(&secret)?;
+                // Make sure the policy lets it out again before relying on it
+                if tpm::unseal(&sealed)?.as_slice() != secret.as_slice() {
+                    return Err(VaultError::TpmUnavailable("the TPM returned a different secret".into()));
+                }
+                Some(sealed)
+            }
+            false => None,
+        };
+        let hardware_secret = enable.then_some((Factor::Tpm, secret));
+        let uses_keyfile = self.has_keyfile()?;
+        let master_key = self.master_key_for(password_key, uses_keyfile, hardware_secret.as_ref().map(|(f, s)| (*f, s.as_slice())))?;
+        let new_wrapped_dek = self.rewrap_dek(master_key)?;
+
+        let db = self.db.as_ref().ok_or(VaultError::Locked)?;
+        Self::store_wrapped_dek(db.conn(), &new_wrapped_dek)?;
+        Self::store_tpm(db.conn(), sealed.as_ref())?;
+        self.hardware_secret = hardware_secret;
+        self.update_activity();
+
+        Ok(self.recovery_code())
+    }
+
+    /// Whether a FIDO2 security key can unlock the vault, locked or not
+    pub fn has_fido2(&self) -> bool {
+        let db = match &self.db {
+            Some(db) => Self::load_fido2(db.conn()),
+            None if self.config.path.exists() => self.open_database().ok().and_then(|db| Self::load_fido2(db.conn())),
+            None => None,
+        };
+        db.is_some()
+    }
+
+    /// Make a hmac-secret credential on the security key plugged in and store
+    /// the master key encrypted under its secret; the key wants two taps
+    pub fn enroll_fido2(&mut self, password: &str) -> VaultResult<()> {
+        self.verify_current_password(password)?;
+        self.ensure_plain_database("A security key")?;
+        let device = fido2::first_device()?;
+        let credential_id = fido2::make_credential(&device)?;
+        let salt = fido2::generate_salt();
+        let secret = fido2::hmac_secret(&device, &credential_id, &salt)?;
+
+        let master_key = self.keys()?.master_key();
+        let wrapped_key = encrypt_bytes(&secret, master_key.as_bytes()).map_err(|e| VaultError::CryptoError(e.to_string()))?;
+        Self::store_fido2(self.db()?.conn(), Some((&credential_id, &salt, &wrapped_key)))?;
+        self.update_activity();
+
+        Ok(())
+    }
+
+    pub fn remove_fido2(&mut self) -> VaultResult<()> {
+        Self::store_fido2(self.db()?.conn(), None)
+    }
+
+    /// Whether the vault holds a session the keyring may reopen
+    pub fn has_session(&self) -> VaultResult<bool> {
+        Ok(Self::load_session(self.db()?.conn()).is_some())
+    }
+
+    /// Keep a fresh session key in the kernel keyring and a copy of the
+    /// master key encrypted under it, replacing any earlier session
+    pub fn remember_session(&mut self) -> VaultResult<()> {
+        self.ensure_plain_database("A keyring session")?;
+        self.forget_session()?;
+        let description = keyring::generate_description();
+        let session_key = keyring::generate_session_key();
+        let master_key = self.keys()?.master_key();
+        let wrapped_key = encrypt_bytes(session_key.as_ref(), master_key.as_bytes()).map_err(|e| VaultError::CryptoError(e.to_string()))?;
+
+        keyring::store(&description, session_key.as_ref())?;
+        Self::store_session(self.db()?.conn(), Some((&description, &wrapped_key)))
+    }
+
+    /// Drop the session from the keyring and the vault; returns whether there was one
+    ///
+    /// Works while locked too, as on the duress path: the session rows are
+    /// plain metadata, so the database is opened just to read them.
+    pub fn forget_session(&mut self) -> VaultResult<bool> {
+        let opened = match self.db {
+            Some(_) => None,
+            None => Some(self.open_database()?),
+        };
+        let db = match &opened {
+            Some(db) => db,
+            None => self.db()?,
+        };
+        let Some((description, _)) = Self::load_session(db.conn()) else { return Ok(false) };
+        Self::store_session(db.conn(), None)?;
+        keyring::remove(&description)?;
+        Ok(true)
+    }
+
+    /// Set a duress password that wipes the vault when typed at unlock, or
+    /// remove it with None; it must differ from the master password
+    pub fn set_duress_password(&mut self, duress: Option<&str>) -> VaultResult<()> {
+        let conn = self.db()?.conn();
+        let Some(duress) = duress else {
+            conn.execute("DELETE FROM metadata WHERE key = 'duress_hash'", [])?;
+            return Ok(());
+        };
+        self.ensure_plain_database("A duress password")?;
+        if self.verify_current_password(duress).is_ok() {
+            return Err(VaultError::OperationFailed("The duress password must differ from the master password".into()));
+        }
+        let (_, hash) = self.derive_new_master_key(duress)?;
+        conn.execute("INSERT OR REPLACE INTO metadata (key, value) VALUES ('duress_hash', ?1)", [&hash])?;
+        Ok(())
+    }
+
+    pub fn has_duress_password(&self) -> VaultResult<bool> {
+        Ok(Self::get_metadata_value(self.db()?.conn(), "duress_hash").is_some())
+    }
+
+    /// Whether `password` is the duress password, checked without unlocking
+    pub fn is_duress_password(&self, password: &str) -> bool {
         if !self.config.path.exists() {
+            return false;
+        }
+        let Ok(db) = self.open_database() else { return false };
+        Self::get_metadata_value(db.conn(), "duress_hash")
+            .is_some_and(|hash| verify_master_key(password.as_bytes(), &hash).is_ok())
+    }
+
+    pub fn record_failed_unlock(&self) -> VaultResult<()> {
+        // An encrypted database cannot be written without the password
+        if !self.config.path.exists() || self.is_database_encrypted() {
             return Ok(());
         }
 
@@ -201,8 +716,7 @@ impl Vault {
     }
 
     fn derive_new_master_key(&self, password: &str) -> VaultResult<(MasterKey, String)> {
-        let params = KdfParams::default();
-        derive_master_key(password.as_bytes(), &params)
+        derive_master_key(password.as_bytes(), &self.config.kdf)
             .map_err(|e| VaultError::CryptoError(e.to_string()))
     }
 
@@ -211,10 +725,25 @@ impl Vault {
     }
 
     fn open_database(&self) -> VaultResult<Database> {
+        if self.is_database_encrypted() {
+            return Err(VaultError::DatabaseEncrypted);
+        }
         let db_config = DatabaseConfig::with_path(&self.config.path);
         Database::open(db_config).map_err(Into::into)
     }
 
+    /// Open the database, giving SQLCipher `password` if the file is encrypted
+    fn open_database_with_password(&self, password: &str) -> VaultResult<Database> {
+        if !self.is_database_encrypted() {
+            return self.open_database();
+        }
+        let db_config = DatabaseConfig::with_path(&self.config.path);
+        Database::open_with_key(db_config, Some(password)).map_err(|e| match e {
+            db::DbError::EncryptionUnsupported => e.into(),
+            _ => VaultError::InvalidPassword,
+        })
+    }
+
     fn verify_password_and_get_key(password: &str, stored_hash: &str) -> VaultResult<MasterKey> {
         verify_master_key(password.as_bytes(), stored_hash)
             .map_err(|_| VaultError::InvalidPassword)
@@ -228,19 +757,175 @@ impl Vault {
             .map_err(|e| VaultError::CryptoError(e.to_string()))
     }
 
+    /// Refuse what needs to read the database before the password is typed
+    fn ensure_plain_database(&self, what: &str) -> VaultResult<()> {
+        match self.is_database_encrypted() {
+            true => Err(VaultError::OperationFailed(format!("{} cannot work with an encrypted database; :encrypt-db off first", what))),
+            false => Ok(()),
+        }
+    }
+
     fn verify_current_password(&self, password: &str) -> VaultResult<()> {
         let hash = self.password_hash.as_ref().ok_or(VaultError::Locked)?;
         verify_master_key(password.as_bytes(), hash).map_err(|_| VaultError::InvalidPassword)?;
         Ok(())
     }
 
+    /// Swap in a new master key; the FIDO2 copy of the old one is dropped,
+    /// as only a tap could encrypt the new one, and so is the keyring session
     fn rewrap_dek(&mut self, new_master_key: MasterKey) -> VaultResult<String> {
+        Self::store_fido2(self.db()?.conn(), None)?;
+        self.forget_session()?;
         let key_hierarchy = self.key_hierarchy.as_mut().ok_or(VaultError::Locked)?;
         key_hierarchy
             .change_master_key(new_master_key)
             .map_err(|e| VaultError::CryptoError(e.to_string()))
     }
 
+    /// A master key stored encrypted under `key`, if `key` opens it
+    fn decrypt_master_key(key: &[u8], wrapped_key: &str) -> Option<MasterKey> {
+        let decrypted = Zeroizing::new(decrypt_bytes(key, &wrapped_key.to_string()).ok()?);
+        let mut key_bytes: [u8; 32] = decrypted.as_slice().try_into().ok()?;
+        let master_key = MasterKey::from_bytes(key_bytes);
+        key_bytes.zeroize();
+        Some(master_key)
+    }
+
+    /// The master key from a password-derived key, mixing in the keyfile and
+    /// the token's response or the TPM secret when the vault uses them
+    fn master_key_for(&self, password_key: MasterKey, uses_keyfile: bool, hardware_secret: Option<(Factor, &[u8])>) -> VaultResult<MasterKey> {
+        let key = match uses_keyfile {
+            true => mix_keyfile(&password_key, &self.read_keyfile()?),
+            false => password_key,
+        };
+        Ok(match hardware_secret {
+            Some((Factor::Token, response)) => mix_token_response(&key, response),
+            Some((Factor::Tpm, secret)) => mix_tpm_secret(&key, secret),
+            None => key,
+        })
+    }
+
+    fn cached_hardware_secret(&self) -> Option<(Factor, &[u8])> {
+        self.hardware_secret.as_ref().map(|(factor, secret)| (*factor, secret.as_slice()))
+    }
+
+    /// Ask the YubiKey or the TPM for the secret the vault is bound to, or
+    /// read it from `recovery` instead
+    fn unlock_hardware_secret(conn: &rusqlite::Connection, recovery: Option<&str>) -> VaultResult<Option<(Factor, Zeroizing<Vec<u8>>)>> {
+        let not_a_code = || VaultError::OperationFailed("Not a recovery code".into());
+        if let Some((slot, challenge)) = Self::load_token(conn) {
+            let response = match recovery {
+                Some(code) => yubikey::parse_recovery_code(code).ok_or_else(not_a_code)?,
+                None => yubikey::challenge_response(slot, &challenge)?,
+            };
+            return Ok(Some((Factor::Token, response)));
+        }
+        if let Some(sealed) = Self::load_tpm(conn) {
+            let secret = match recovery {
+                Some(code) => tpm::parse_recovery_code(code).ok_or_else(not_a_code)?,
+                None => tpm::unseal(&sealed)?,
+            };
+            return Ok(Some((Factor::Tpm, secret)));
+        }
+        Ok(None)
+    }
+
+    fn read_keyfile(&self) -> VaultResult<Zeroizing<Vec<u8>>> {
+        let path = self.config.keyfile.as_ref().ok_or(VaultError::KeyfileRequired)?;
+        let contents = Zeroizing::new(
+            std::fs::read(path).map_err(|e| VaultError::IoError(format!("Cannot read keyfile {}: {}", path.display(), e)))?,
+        );
+        if contents.is_empty() {
+            return Err(VaultError::OperationFailed(format!("Keyfile {} is empty", path.display())));
+        }
+        Ok(contents)
+    }
+
+    fn uses_keyfile(conn: &rusqlite::Connection) -> bool {
+        Self::get_metadata_value(conn, "keyfile").is_some_and(|v| v == "1")
+    }
+
+    fn store_keyfile_flag(conn: &rusqlite::Connection, uses_keyfile: bool) -> VaultResult<()> {
+        match uses_keyfile {
+            true => conn.execute("INSERT OR REPLACE INTO metadata (key, value) VALUES ('keyfile', '1')", [])?,
+            false => conn.execute("DELETE FROM metadata WHERE key = 'keyfile'", [])?,
+        };
+        Ok(())
+    }
+
+    /// The YubiKey slot and stored challenge, for vaults that need the token
+    fn load_token(conn: &rusqlite::Connection) -> Option<(u8, Vec<u8>)> {
+        let slot = Self::get_metadata_value(conn, "yubikey_slot")?.parse().ok()?;
+        let challenge = hex::decode(Self::get_metadata_value(conn, "yubikey_challenge")?).ok()?;
+        Some((slot, challenge))
+    }
+
+    fn store_token(conn: &rusqlite::Connection, token: Option<(u8, &[u8])>) -> VaultResult<()> {
+        conn.execute("DELETE FROM metadata WHERE key IN ('yubikey_slot', 'yubikey_challenge')", [])?;
+        if let Some((slot, challenge)) = token {
+            conn.execute(
+                "INSERT INTO metadata (key, value) VALUES ('yubikey_slot', ?1), ('yubikey_challenge', ?2)",
+                [slot.to_string(), hex::encode(challenge)],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// The secret sealed to the TPM, for vaults bound to a machine
+    fn load_tpm(conn: &rusqlite::Connection) -> Option<tpm::SealedSecret> {
+        let public = hex::decode(Self::get_metadata_value(conn, "tpm_public")?).ok()?;
+        let private = hex::decode(Self::get_metadata_value(conn, "tpm_private")?).ok()?;
+        Some(tpm::SealedSecret { public, private })
+    }
+
+    fn store_tpm(conn: &rusqlite::Connection, sealed: Option<&tpm::SealedSecret>) -> VaultResult<()> {
+        conn.execute("DELETE FROM metadata WHERE key IN ('tpm_public', 'tpm_private')", [])?;
+        if let Some(sealed) = sealed {
+            conn.execute(
+                "INSERT INTO metadata (key, value) VALUES ('tpm_public', ?1), ('tpm_private', ?2)",
+                [hex::encode(&sealed.public), hex::encode(&sealed.private)],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Credential ID, salt and encrypted master key, for vaults a security key unlocks
+    fn load_fido2(conn: &rusqlite::Connection) -> Option<(String, Vec<u8>, String)> {
+        let credential_id = Self::get_metadata_value(conn, "fido2_credential")?;
+        let salt = hex::decode(Self::get_metadata_value(conn, "fido2_salt")?).ok()?;
+        let wrapped_key = Self::get_metadata_value(conn, "fido2_master_key")?;
+        Some((credential_id, salt, wrapped_key))
+    }
+
+    fn store_fido2(conn: &rusqlite::Connection, fido2: Option<(&str, &[u8], &str)>) -> VaultResult<()> {
+        conn.execute("DELETE FROM metadata WHERE key IN ('fido2_credential', 'fido2_salt', 'fido2_master_key')", [])?;
+        if let Some((credential_id, salt, wrapped_key)) = fido2 {
+            conn.execute(
+                "INSERT INTO metadata (key, value) VALUES ('fido2_credential', ?1), ('fido2_salt', ?2), ('fido2_master_key', ?3)",
+                [credential_id, &hex::encode(salt), wrapped_key],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Keyring description and encrypted master key of a remembered session
+    fn load_session(conn: &rusqlite::Connection) -> Option<(String, String)> {
+        let description = Self::get_metadata_value(conn, "session_keyring")?;
+        let wrapped_key = Self::get_metadata_value(conn, "session_master_key")?;
+        Some((description, wrapped_key))
+    }
+
+    fn store_session(conn: &rusqlite::Connection, session: Option<(&str, &str)>) -> VaultResult<()> {
+        conn.execute("DELETE FROM metadata WHERE key IN ('session_keyring', 'session_master_key')", [])?;
+        if let Some((description, wrapped_key)) = session {
+            conn.execute(
+                "INSERT INTO metadata (key, value) VALUES ('session_keyring', ?1), ('session_master_key', ?2)",
+                [description, wrapped_key],
+            )?;
+        }
+        Ok(())
+    }
+
     fn store_password_hash(conn: &rusqlite::Connection, hash: &str) -> VaultResult<()> {
         conn.execute(
             "INSERT OR REPLACE INTO metadata (key, value) VALUES ('password_hash', ?1)",
@@ -340,6 +1025,8 @@ mod tests {
     use super::*;
     use tempfile::TempDir;
 
+    use crate::db::CredentialType;
+
     fn temp_vault() -> (TempDir, VaultConfig) {
         let dir = TempDir::new().unwrap();
         let path = dir.path().join("test_vault.db");
@@ -381,6 +1068,21 @@ mod tests {
         assert!(matches!(result, Err(VaultError::InvalidPassword)));
     }
 
+    #[test]
+    fn test_unlock_with_key() {
+        let (_dir, config) = temp_vault();
+        let mut vault = create_initialized_vault(config, "test_password_123");
+        let master_key = vault.keys().unwrap().master_key().clone();
+        vault.lock();
+
+        vault.unlock_with_key(MasterKey::from_bytes([0u8; 32])).unwrap_err();
+        assert!(!vault.is_unlocked());
+
+        vault.unlock_with_key(master_key).unwrap();
+        assert!(vault.is_unlocked());
+        vault.verify_password("test_password_123").unwrap();
+    }
+
     #[test]
     fn test_change_password() {
         let (_dir, config) = temp_vault();
@@ -401,6 +1103,330 @@ mod tests {
         assert_eq!(&dek_before, vault.dek().unwrap().as_bytes());
     }
 
+    #[test]
+    fn test_database_encryption() {
+        let (_dir, config) = temp_vault();
+        let mut vault = create_initialized_vault(config.clone(), "password");
+        let dek = *vault.dek().unwrap().as_bytes();
+        let cred = crate::vault::credential::create_credential(
+            vault.db().unwrap().conn(), vault.dek().unwrap(), "Landscape".into(), CredentialType::Password, "secret", None, None, vec![], None, None, &[],
+        ).unwrap();
+
+        if !db::ENCRYPTION_SUPPORTED {
+            assert!(vault.set_database_encryption("password", true).is_err());
+            assert!(!vault.is_database_encrypted());
+            return;
+        }
+        assert!(matches!(vault.set_database_encryption("wrong", true), Err(VaultError::InvalidPassword)));
+        vault.set_database_encryption("password", true).unwrap();
+        assert!(vault.is_database_encrypted());
+        assert!(!std::fs::read(&config.path).unwrap().windows(9).any(|w| w == b"Landscape"));
+        assert!(vault.set_duress_password(Some("duress")).is_err());
+        vault.lock();
+
+        assert!(matches!(vault.unlock("wrong"), Err(VaultError::InvalidPassword)));
+        assert!(matches!(vault.unlock_with_session(), Err(VaultError::DatabaseEncrypted)));
+        vault.unlock("password").unwrap();
+        assert_eq!(&dek, vault.dek().unwrap().as_bytes());
+        assert!(crate::db::get_credential(vault.db().unwrap().conn(), &cred.id).is_ok());
+
+        // The password change re-encrypts the file under the new password
+        vault.change_password("password", "new password").unwrap();
+        vault.lock();
+        assert!(matches!(vault.unlock("password"), Err(VaultError::InvalidPassword)));
+        vault.unlock("new password").unwrap();
+
+        vault.set_database_encryption("new password", false).unwrap();
+        assert!(!vault.is_database_encrypted());
+        vault.lock();
+        Vault::new(config).unlock("new password").unwrap();
+    }
+
+    #[test]
+    fn test_keyfile() {
+        let (dir, config) = temp_vault();
+        let keyfile = dir.path().join("usb.key");
+        std::fs::write(&keyfile, b"random keyfile bytes").unwrap();
+
+        let mut vault = create_initialized_vault(config.clone().with_keyfile(Some(keyfile.clone())), "password");
+        assert!(vault.has_keyfile().unwrap());
+        let dek = *vault.dek().unwrap().as_bytes();
+        vault.lock();
+
+        let mut without = Vault::new(config.clone());
+        assert!(matches!(without.unlock("password"), Err(VaultError::KeyfileRequired)));
+        assert!(matches!(without.unlock("wrong"), Err(VaultError::InvalidPassword)));
+
+        let other = dir.path().join("other.key");
+        std::fs::write(&other, b"some other file").unwrap();
+        let mut wrong = Vault::new(config.clone().with_keyfile(Some(other)));
+        assert!(matches!(wrong.unlock("password"), Err(VaultError::InvalidKeyfile)));
+
+        vault.unlock("password").unwrap();
+        assert_eq!(&dek, vault.dek().unwrap().as_bytes());
+        vault.change_password("password", "new password").unwrap();
+        vault.lock();
+        vault.unlock("new password").unwrap();
+        assert_eq!(&dek, vault.dek().unwrap().as_bytes());
+    }
+
+    #[test]
+    fn test_change_keyfile() {
+        let (dir, config) = temp_vault();
+        let keyfile = dir.path().join("usb.key");
+        std::fs::write(&keyfile, b"random keyfile bytes").unwrap();
+
+        let mut vault = create_initialized_vault(config.clone(), "password");
+        assert!(vault.change_keyfile("wrong", Some(keyfile.clone())).is_err());
+        assert!(vault.change_keyfile("password", Some(dir.path().join("missing"))).is_err());
+        assert!(!vault.has_keyfile().unwrap());
+
+        vault.change_keyfile("password", Some(keyfile.clone())).unwrap();
+        vault.lock();
+        assert!(Vault::new(config.clone()).unlock("password").is_err());
+        vault.unlock("password").unwrap();
+
+        vault.change_keyfile("password", None).unwrap();
+        vault.lock();
+        Vault::new(config).unlock("password").unwrap();
+    }
+
+    #[test]
+    fn test_recovery_code_unlock() {
+        let (_dir, config) = temp_vault();
+        let mut vault = create_initialized_vault(config.clone(), "password");
+        let dek = *vault.dek().unwrap().as_bytes();
+
+        // What enabling the token does, with a made-up response for the hardware
+        let response = Zeroizing::new(vec![5u8; 20]);
+        let password_key = Vault::verify_password_and_get_key("password", vault.password_hash.as_ref().unwrap()).unwrap();
+        let master_key = vault.master_key_for(password_key, false, Some((Factor::Token, &response))).unwrap();
+        let wrapped_dek = vault.rewrap_dek(master_key).unwrap();
+        Vault::store_wrapped_dek(vault.db().unwrap().conn(), &wrapped_dek).unwrap();
+        Vault::store_token(vault.db().unwrap().conn(), Some((2, &yubikey::generate_challenge()))).unwrap();
+        assert_eq!(vault.token_slot().unwrap(), Some(2));
+        vault.lock();
+
+        let code = yubikey::format_recovery_code(&response);
+        assert!(vault.unlock("password").is_err());
+        assert!(matches!(vault.unlock_with_recovery_code("password", "not a code"), Err(VaultError::OperationFailed(_))));
+        assert!(matches!(
+            vault.unlock_with_recovery_code("password", &yubikey::format_recovery_code(&[6u8; 20])),
+            Err(VaultError::InvalidToken)
+        ));
+        assert!(matches!(vault.unlock_with_recovery_code("wrong", &code), Err(VaultError::InvalidPassword)));
+
+        vault.unlock_with_recovery_code("password", &code).unwrap();
+        assert_eq!(&dek, vault.dek().unwrap().as_bytes());
+        assert_eq!(vault.recovery_code().unwrap().as_str(), code.as_str());
+
+        // The cached response keeps the token mixed in across a password change
+        vault.change_password("password", "new password").unwrap();
+        vault.lock();
+        vault.unlock_with_recovery_code("new password", &code).unwrap();
+
+        vault.change_token("new password", None).unwrap();
+        assert_eq!(vault.token_slot().unwrap(), None);
+        vault.lock();
+        Vault::new(config).unlock("new password").unwrap();
+    }
+
+    #[test]
+    fn test_tpm_recovery_code_unlock() {
+        let (_dir, config) = temp_vault();
+        let mut vault = create_initialized_vault(config.clone(), "password");
+        let dek = *vault.dek().unwrap().as_bytes();
+
+        // What binding to the TPM does, with made-up sealed blobs for the hardware
+        let secret = tpm::generate_secret();
+        let password_key = Vault::verify_password_and_get_key("password", vault.password_hash.as_ref().unwrap()).unwrap();
+        let master_key = vault.master_key_for(password_key, false, Some((Factor::Tpm, &secret))).unwrap();
+        let wrapped_dek = vault.rewrap_dek(master_key).unwrap();
+        Vault::store_wrapped_dek(vault.db().unwrap().conn(), &wrapped_dek).unwrap();
+        let sealed = tpm::SealedSecret { public: vec![1, 2], private: vec![3, 4] };
+        Vault::store_tpm(vault.db().unwrap().conn(), Some(&sealed)).unwrap();
+        assert!(vault.has_tpm().unwrap());
+        assert!(matches!(vault.change_token("password", Some(2)), Err(VaultError::OperationFailed(_))));
+        vault.lock();
+
+        let code = tpm::format_recovery_code(&secret);
+        // A YubiKey's code is the wrong length for the TPM secret
+        assert!(matches!(
+            vault.unlock_with_recovery_code("password", &yubikey::format_recovery_code(&[5u8; 20])),
+            Err(VaultError::OperationFailed(_))
+        ));
+        assert!(matches!(
+            vault.unlock_with_recovery_code("password", &tpm::format_recovery_code(&[6u8; 32])),
+            Err(VaultError::InvalidTpmSecret)
+        ));
+
+        vault.unlock_with_recovery_code("password", &code).unwrap();
+        assert_eq!(&dek, vault.dek().unwrap().as_bytes());
+        assert_eq!(vault.recovery_code().unwrap().as_str(), code.as_str());
+
+        vault.change_tpm("password", false).unwrap();
+        assert!(!vault.has_tpm().unwrap());
+        vault.lock();
+        Vault::new(config).unlock("password").unwrap();
+    }
+
+    #[test]
+    fn test_fido2_copy_dropped_with_master_key() {
+        let (_dir, config) = temp_vault();
+        let mut vault = create_initialized_vault(config, "password");
+        assert!(!vault.has_fido2());
+        assert!(vault.enroll_fido2("wrong").is_err());
+
+        // What enrolling stores, with a made-up secret for the hardware
+        let secret = [9u8; 32];
+        let wrapped_key = encrypt_bytes(&secret, vault.keys().unwrap().master_key().as_bytes()).unwrap();
+        Vault::store_fido2(vault.db().unwrap().conn(), Some(("credential", &fido2::generate_salt(), &wrapped_key))).unwrap();
+        assert!(vault.has_fido2());
+        vault.lock();
+        assert!(vault.has_fido2());
+
+        vault.unlock("password").unwrap();
+        vault.change_password("password", "new password").unwrap();
+        assert!(!vault.has_fido2());
+    }
+
+    #[test]
+    fn test_change_kdf() {
+        let (_dir, config) = temp_vault();
+        let mut vault = create_initialized_vault(config.clone(), "password");
+        let dek = *vault.dek().unwrap().as_bytes();
+        assert_eq!(vault.kdf_params().unwrap().memory_cost, KdfParams::default().memory_cost);
+
+        let raised = KdfParams { memory_cost: 32 * 1024, time_cost: 3, ..KdfParams::default() };
+        assert!(vault.change_kdf("wrong", raised.clone()).is_err());
+        vault.change_kdf("password", raised).unwrap();
+        assert_eq!(vault.kdf_params().unwrap().memory_cost, 32 * 1024);
+        vault.lock();
+
+        // The parameters come from the stored hash, not the config
+        let mut reopened = Vault::new(config);
+        reopened.unlock("password").unwrap();
+        assert_eq!(reopened.kdf_params().unwrap().time_cost, 3);
+        assert_eq!(&dek, reopened.dek().unwrap().as_bytes());
+    }
+
+    #[test]
+    fn test_rotate_dek() {
+        use crate::crypto::encrypt_string;
+        use crate::vault::credential::decrypt_credential_data;
+
+        let (_dir, config) = temp_vault();
+        let mut vault = create_initialized_vault(config.clone(), "password");
+        let dek = *vault.dek().unwrap().as_bytes();
+        let secret = encrypt_string(vault.dek().unwrap().as_ref(), "hunter2").unwrap();
+        let mut cred = crate::db::Credential::new("Bank".into(), crate::db::CredentialType::Password, secret);
+        cred.encrypted_notes = Some(encrypt_string(vault.dek().unwrap().as_ref(), "pin").unwrap());
+        crate::db::create_credential(vault.db().unwrap().conn(), &cred).unwrap();
+        let history = vec!["filter tag=bank".to_string(), "stats".to_string()];
+        vault.save_command_history(&history).unwrap();
+
+        assert_eq!(vault.rotate_dek(&mut |_, _| {}).unwrap(), 1);
+        assert_ne!(&dek, vault.dek().unwrap().as_bytes());
+        vault.lock();
+
+        let mut reopened = Vault::new(config);
+        reopened.unlock("password").unwrap();
+        let stored = crate::db::get_credential(reopened.db().unwrap().conn(), &cred.id).unwrap();
+        // Rotation also moves blobs from before per-credential keys onto them
+        assert!(stored.encrypted_secret.starts_with("k1:"));
+        assert_eq!(decrypt_credential_data(reopened.dek().unwrap(), &cred.id, &stored.encrypted_secret).unwrap(), "hunter2");
+        assert_eq!(decrypt_credential_data(reopened.dek().unwrap(), &cred.id, stored.encrypted_notes.as_ref().unwrap()).unwrap(), "pin");
+        assert_eq!(reopened.command_history().unwrap(), history);
+    }
+
+    #[test]
+    fn test_encrypted_metadata_survives_rotation() {
+        let (_dir, config) = temp_vault();
+        let mut vault = create_initialized_vault(config.clone(), "password");
+        let dek = vault.dek().unwrap().clone();
+        let conn = vault.db().unwrap().conn();
+        crate::vault::credential::create_credential(conn, &dek, "Bank".into(), crate::db::CredentialType::Password, "pw", None, None, vec![], None, None, &[]).unwrap();
+
+        assert!(matches!(vault.set_metadata_encryption("wrong", true), Err(VaultError::InvalidPassword)));
+        vault.set_metadata_encryption("password", true).unwrap();
+        assert!(vault.is_metadata_encrypted().unwrap());
+        vault.rotate_dek(&mut |_, _| {}).unwrap();
+        vault.lock();
+
+        let mut reopened = Vault::new(config);
+        reopened.unlock("password").unwrap();
+        let conn = reopened.db().unwrap().conn();
+        assert_eq!(crate::db::get_all_credentials(conn).unwrap()[0].name, "Bank");
+        assert_eq!(crate::db::search_credentials(conn, "ban").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_rotate_dek_rolls_back() {
+        use crate::crypto::encrypt_string;
+
+        let (_dir, config) = temp_vault();
+        let mut vault = create_initialized_vault(config, "password");
+        let dek = *vault.dek().unwrap().as_bytes();
+        let good = crate::db::Credential::new("Good".into(), crate::db::CredentialType::Password, encrypt_string(vault.dek().unwrap().as_ref(), "a").unwrap());
+        let bad = crate::db::Credential::new("Bad".into(), crate::db::CredentialType::Password, "not ciphertext".into());
+        crate::db::create_credential(vault.db().unwrap().conn(), &good).unwrap();
+        crate::db::create_credential(vault.db().unwrap().conn(), &bad).unwrap();
+
+        assert!(vault.rotate_dek(&mut |_, _| {}).is_err());
+        assert_eq!(&dek, vault.dek().unwrap().as_bytes());
+        let stored = crate::db::get_credential(vault.db().unwrap().conn(), &good.id).unwrap();
+        assert_eq!(stored.encrypted_secret, good.encrypted_secret);
+    }
+
+    #[test]
+    fn test_duress_password() {
+        let (_dir, config) = temp_vault();
+        let mut vault = create_initialized_vault(config, "password");
+        assert!(!vault.is_duress_password("password"));
+        assert!(vault.set_duress_password(Some("password")).is_err());
+
+        vault.set_duress_password(Some("duress")).unwrap();
+        assert!(vault.has_duress_password().unwrap());
+        vault.lock();
+        assert!(vault.is_duress_password("duress"));
+        assert!(!vault.is_duress_password("password"));
+
+        vault.unlock("password").unwrap();
+        vault.set_duress_password(None).unwrap();
+        assert!(!vault.is_duress_password("duress"));
+    }
+
+    #[test]
+    fn test_session_dropped_with_master_key() {
+        let (_dir, config) = temp_vault();
+        let mut vault = create_initialized_vault(config, "password");
+        assert!(!vault.has_session().unwrap());
+
+        let wrapped_key = encrypt_bytes(&[3u8; 32], vault.keys().unwrap().master_key().as_bytes()).unwrap();
+        Vault::store_session(vault.db().unwrap().conn(), Some(("vault-session:test", &wrapped_key))).unwrap();
+        assert!(vault.has_session().unwrap());
+        let master_key = Vault::decrypt_master_key(&[3u8; 32], &wrapped_key).unwrap();
+        assert_eq!(master_key.as_bytes(), vault.keys().unwrap().master_key().as_bytes());
+        assert!(Vault::decrypt_master_key(&[4u8; 32], &wrapped_key).is_none());
+
+        vault.change_password("password", "new password").unwrap();
+        assert!(!vault.has_session().unwrap());
+    }
+
+    #[test]
+    fn test_forget_session_while_locked() {
+        let (_dir, config) = temp_vault();
+        let mut vault = create_initialized_vault(config, "password");
+        let wrapped_key = encrypt_bytes(&[3u8; 32], vault.keys().unwrap().master_key().as_bytes()).unwrap();
+        Vault::store_session(vault.db().unwrap().conn(), Some(("vault-session:test", &wrapped_key))).unwrap();
+
+        // The duress path wipes without unlocking
+        vault.lock();
+        assert!(vault.forget_session().unwrap());
+        vault.unlock("password").unwrap();
+        assert!(!vault.has_session().unwrap());
+    }
+
     #[test]
     fn test_credentials_accessible_after_password_change() {
         use crate::crypto::{decrypt_string, encrypt_string};
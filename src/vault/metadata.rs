@@ -0,0 +1,209 @@
+//! Encrypted credential metadata (vault format 2)
+//!
+//! Format 1 keeps names, usernames, URLs and tags in plaintext so SQLite can
+//! sort and search them, which hands anyone with the database file a map of
+//! every account. Format 2 encrypts those columns with each credential's own
+//! key, like its secret, in the credentials, trash and revisions tables and
+//! the audit log. Queries read and write them through the SQL functions of
+//! `db::functions`, keyed here once the vault is unlocked.
+//!
+//! Search goes through a table of keyed hashes of every word prefix up to
+//! `MAX_PREFIX` characters. It shows how many distinct prefixes a credential
+//! has and which credentials share one, but not the words themselves.
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use hmac::{Hmac, Mac};
+use rusqlite::Connection;
+use sha2::Sha256;
+
+use crate::crypto::{derive_search_key, DataEncryptionKey, DerivedKey};
+use crate::db::{self, functions::{ColumnCipher, Plaintext}, Database};
+
+use super::credential::{decrypt_credential_data, encrypt_credential_data};
+use super::{VaultError, VaultResult};
+
+/// Metadata key holding the vault format; missing means format 1
+const FORMAT_KEY: &str = "vault_format";
+
+/// Longest word prefix indexed; longer search words match on this many characters
+const MAX_PREFIX: usize = 16;
+
+/// Bytes of HMAC kept per token
+const TOKEN_LEN: usize = 16;
+
+/// Keyed `ColumnCipher` of a format 2 vault
+struct MetadataCipher {
+    dek: DataEncryptionKey,
+    search_key: DerivedKey,
+}
+
+impl ColumnCipher for MetadataCipher {
+    fn seal(&self, id: &str, value: &str) -> Result<String, String> {
+        encrypt_credential_data(&self.dek, id, value).map_err(|e| e.to_string())
+    }
+
+    /// Values that do not decrypt are shown as stored rather than failing the query
+    fn open(&self, id: &str, value: &str) -> String {
+        decrypt_credential_data(&self.dek, id, value).unwrap_or_else(|_| value.to_string())
+    }
+
+    fn tokens(&self, text: &str) -> Vec<String> {
+        let mut prefixes = BTreeSet::new();
+        for word in words(text) {
+            let chars: Vec<char> = word.chars().take(MAX_PREFIX).collect();
+            prefixes.extend((1..=chars.len()).map(|n| chars[..n].iter().collect::<String>()));
+        }
+        prefixes.iter().map(|prefix| self.hash(prefix)).collect()
+    }
+
+    fn token(&self, term: &str) -> Option<String> {
+        let prefix: String = term.to_lowercase().chars().take(MAX_PREFIX).collect();
+        Some(self.hash(&prefix))
+    }
+}
+
+impl MetadataCipher {
+    fn hash(&self, prefix: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.search_key.as_bytes()).expect("HMAC can take key of any size");
+        mac.update(prefix.as_bytes());
+        hex::encode(&mac.finalize().into_bytes()[..TOKEN_LEN])
+    }
+}
+
+/// Lowercased words of `text`, split on anything not alphanumeric
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).map(str::to_lowercase)
+}
+
+/// Whether the vault stores its metadata encrypted (format 2)
+pub fn is_encrypted(conn: &Connection) -> bool {
+    conn.query_row("SELECT value FROM metadata WHERE key = ?1", [FORMAT_KEY], |row| row.get::<_, String>(0))
+        .is_ok_and(|format| format == "2")
+}
+
+/// Key the metadata SQL functions of `db` with `dek` if the vault is in
+/// format 2, indexing credentials a sync merge left without search tokens
+pub fn bind(db: &Database, dek: &DataEncryptionKey) -> VaultResult<()> {
+    if !is_encrypted(db.conn()) {
+        db.set_column_cipher(Arc::new(Plaintext));
+        return Ok(());
+    }
+    let search_key = derive_search_key(dek).map_err(|e| VaultError::CryptoError(e.to_string()))?;
+    db.set_column_cipher(Arc::new(MetadataCipher { dek: dek.clone(), search_key }));
+    db::update_search_index(db.conn(), false)?;
+    Ok(())
+}
+
+/// Move the vault to format 2, encrypting the metadata of every record, or
+/// back to format 1, all in one transaction. Freed pages are overwritten
+/// and the full-text index rebuilt so the old values do not linger.
+pub fn set_encrypted(db: &Database, dek: &DataEncryptionKey, encrypt: bool) -> VaultResult<()> {
+    let conn = db.conn();
+    if encrypt == is_encrypted(conn) {
+        return Ok(());
+    }
+
+    conn.execute_batch("PRAGMA secure_delete = ON")?;
+    conn.execute_batch("BEGIN")?;
+    let result = (|| -> VaultResult<()> {
+        if encrypt {
+            conn.execute("INSERT OR REPLACE INTO metadata (key, value) VALUES (?1, '2')", [FORMAT_KEY])?;
+            bind(db, dek)?;
+            convert(conn, "vault_seal")?;
+            db::update_search_index(conn, true)?;
+        } else {
+            convert(conn, "vault_open")?;
+            conn.execute("DELETE FROM search_tokens", [])?;
+            conn.execute("DELETE FROM metadata WHERE key = ?1", [FORMAT_KEY])?;
+            bind(db, dek)?;
+        }
+        conn.execute("INSERT INTO credentials_fts(credentials_fts) VALUES ('rebuild')", [])?;
+        Ok(())
+    })();
+
+    match result {
+        Ok(()) => conn.execute_batch("COMMIT")?,
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK");
+            bind(db, dek)?;
+            return Err(e);
+        }
+    }
+    let _ = conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE)");
+    Ok(())
+}
+
+/// Pass every metadata column through the SQL function `function`
+fn convert(conn: &Connection, function: &str) -> VaultResult<()> {
+    for table in ["credentials", "trash", "revisions"] {
+        conn.execute(
+            &format!(
+                "UPDATE {table} SET name = {f}(id, name), username = {f}(id, username), url = {f}(id, url), tags = {f}(id, tags)",
+                f = function
+            ),
+            [],
+        )?;
+    }
+    conn.execute(
+        &format!(
+            "UPDATE audit_log SET credential_name = {f}(COALESCE(credential_id, ''), credential_name), username = {f}(COALESCE(credential_id, ''), username)",
+            f = function
+        ),
+        [],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    use crate::crypto::{KeyHierarchy, MasterKey};
+    use crate::db::{AuditAction, CredentialType, Database, DatabaseConfig};
+    use crate::vault::{audit, credential::create_credential};
+
+    #[test]
+    fn test_encrypted_metadata() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("vault.db");
+        let db = Database::open(DatabaseConfig::with_path(&path)).unwrap();
+        let conn = db.conn();
+        let keys = KeyHierarchy::new(MasterKey::from_bytes([1u8; 32])).unwrap();
+        let audit_key = keys.derive_audit_key().unwrap();
+
+        let tags = vec!["work/aws".to_string()];
+        let bank = create_credential(conn, keys.dek(), "Zebra Bank".into(), CredentialType::Password, "pw", Some("alice".into()), None, vec![], None, None, &[]).unwrap();
+        let aws = create_credential(conn, keys.dek(), "AWS Console".into(), CredentialType::Password, "pw", None, Some("https://console.aws.amazon.com".into()), tags, None, None, &[]).unwrap();
+        audit::log_action(conn, &audit_key, AuditAction::Create, Some(&bank.id), Some("Zebra Bank"), Some("alice"), None).unwrap();
+        db::delete_credential(conn, &bank.id).unwrap();
+
+        set_encrypted(&db, keys.dek(), true).unwrap();
+        assert!(is_encrypted(conn));
+        let raw: String = conn.query_row("SELECT name FROM credentials WHERE id = ?1", [&aws.id], |row| row.get(0)).unwrap();
+        assert!(raw.starts_with("k1:"));
+        let file = std::fs::read(&path).unwrap();
+        assert!(!file.windows(5).any(|w| w == b"Zebra" || w == b"alice" || w == b"AWS C"));
+
+        let names: Vec<String> = db::get_all_credentials(conn).unwrap().into_iter().map(|c| c.name).collect();
+        assert_eq!(names, vec!["AWS Console"]);
+        assert_eq!(db::get_credentials_by_tag(conn, &["work".to_string()]).unwrap().len(), 1);
+        assert_eq!(db::search_credentials(conn, "amazon").unwrap()[0].id, aws.id);
+        assert_eq!(db::search_credentials(conn, "con AW").unwrap().len(), 1);
+        assert!(db::search_credentials(conn, "zebra").unwrap().is_empty());
+        assert_eq!(db::get_trash(conn).unwrap()[0].credential.name, "Zebra Bank");
+        assert!(audit::verify_all_logs(conn, &audit_key).unwrap().iter().all(|(_, ok)| *ok));
+
+        db::restore_from_trash(conn, &bank.id).unwrap();
+        assert_eq!(db::search_credentials(conn, "ALI").unwrap()[0].name, "Zebra Bank");
+
+        set_encrypted(&db, keys.dek(), false).unwrap();
+        assert!(!is_encrypted(conn));
+        let raw: String = conn.query_row("SELECT name FROM credentials WHERE id = ?1", [&aws.id], |row| row.get(0)).unwrap();
+        assert_eq!(raw, "AWS Console");
+        assert_eq!(db::search_credentials(conn, "Zebra").unwrap().len(), 1);
+        assert!(audit::verify_all_logs(conn, &audit_key).unwrap().iter().all(|(_, ok)| *ok));
+    }
+}
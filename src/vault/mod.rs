@@ -2,11 +2,40 @@
 //!
 //! Secure credential storage with encryption and key management.
 
+pub mod aegis;
+pub mod api_tokens;
 pub mod audit;
+pub mod auto_export;
+pub mod backup;
+pub mod bundle;
+pub mod cache;
+pub mod certificate;
 pub mod credential;
 pub mod manager;
+pub mod metadata;
+pub mod pass;
 pub mod search;
+pub mod sealed;
+pub mod share;
+pub mod ssh;
 pub mod export;
+pub mod history;
+pub mod import;
+pub mod index;
+pub mod integrity;
+pub mod keyring;
+pub mod duplicates;
+pub mod expiry;
+pub mod fido2;
+pub mod report;
+pub mod rotation;
+mod scratch;
+pub mod stats;
+pub mod sync;
+pub mod tags;
+pub mod tpm;
+pub mod wipe;
+pub mod yubikey;
 
 use thiserror::Error;
 
@@ -25,6 +54,42 @@ pub enum VaultError {
     #[error("Invalid password")]
     InvalidPassword,
 
+    #[error("This vault needs its keyfile; set keyfile to its path")]
+    KeyfileRequired,
+
+    #[error("Keyfile does not match this vault")]
+    InvalidKeyfile,
+
+    #[error("YubiKey unavailable: {0}")]
+    TokenUnavailable(String),
+
+    #[error("YubiKey or recovery code does not match this vault")]
+    InvalidToken,
+
+    #[error("TPM unavailable: {0}")]
+    TpmUnavailable(String),
+
+    #[error("TPM secret or recovery code does not match this vault")]
+    InvalidTpmSecret,
+
+    #[error("Security key unavailable: {0}")]
+    SecurityKeyUnavailable(String),
+
+    #[error("Security key does not match this vault")]
+    InvalidSecurityKey,
+
+    #[error("No security key is set up for this vault; :fido2 on adds one")]
+    Fido2NotEnrolled,
+
+    #[error("Keyring unavailable: {0}")]
+    KeyringUnavailable(String),
+
+    #[error("The database is encrypted; only the master password opens it")]
+    DatabaseEncrypted,
+
+    #[error("No session to reopen the vault with")]
+    NoSession,
+
     #[error("Database error: {0}")]
     DatabaseError(#[from] crate::db::DbError),
 
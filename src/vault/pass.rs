@@ -0,0 +1,249 @@
+//! password-store (`pass`) export
+//!
+//! Writes each credential as its own gpg-encrypted file in a pass store, laid
+//! out the way `pass insert -m` would leave it: the secret first, then
+//! `key: value` metadata lines, an `otpauth://` line for pass-otp, and the
+//! notes after a blank line. The first tag becomes the folder, so `work/cloud`
+//! puts GitHub at `work/cloud/GitHub.gpg`.
+//!
+//! Files are encrypted to the key IDs in the nearest `.gpg-id`, as pass does,
+//! so `pass show`, browser extensions and other pass tooling read them
+//! directly. The store must already be set up with `pass init`; entries
+//! with the same name as an exported credential are replaced.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use zeroize::Zeroizing;
+
+use crate::crypto::totp::TotpSecret;
+use crate::crypto::DataEncryptionKey;
+use crate::db::Credential;
+
+use super::credential::{decrypt_credential_data, decrypt_fields, decrypt_notes, decrypt_totp_secret};
+use super::export::{encrypt_to_gpg_keys, require_gpg};
+use super::{backup, expiry, VaultError, VaultResult};
+
+/// Key IDs a store's files are encrypted to
+const GPG_ID: &str = ".gpg-id";
+
+/// One credential as a pass entry
+pub struct PassEntry {
+    /// Path in the store without the `.gpg` extension, e.g. `work/GitHub`
+    pub name: String,
+    /// Decrypted file contents
+    content: Zeroizing<String>,
+}
+
+/// Where pass keeps its store: `$PASSWORD_STORE_DIR`, or `~/.password-store`
+pub fn default_store_dir() -> PathBuf {
+    match std::env::var_os("PASSWORD_STORE_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => dirs::home_dir().unwrap_or_default().join(".password-store"),
+    }
+}
+
+/// An entry for every credential, named after its first tag and name;
+/// names that would collide get ` (2)`, ` (3)`... appended
+pub fn pass_entries<'a>(
+    dek: &DataEncryptionKey,
+    credentials: impl IntoIterator<Item = &'a Credential>,
+) -> VaultResult<Vec<PassEntry>> {
+    let mut taken = HashSet::new();
+    let mut entries = Vec::new();
+    for cred in credentials {
+        let base = entry_name(cred);
+        let name = (1..)
+            .map(|n| match n {
+                1 => base.clone(),
+                n => format!("{} ({})", base, n),
+            })
+            .find(|name| taken.insert(name.to_lowercase()))
+            .unwrap_or(base);
+        entries.push(PassEntry { name, content: entry_content(dek, cred)? });
+    }
+    Ok(entries)
+}
+
+/// Encrypt each entry into `store`, returning how many were written
+pub fn write_pass_store(entries: &[PassEntry], store: &Path) -> VaultResult<usize> {
+    require_gpg()?;
+    if !store.join(GPG_ID).is_file() {
+        return Err(VaultError::OperationFailed(format!(
+            "{} is not a password store; set it up with `pass init <gpg-id>` first",
+            store.display()
+        )));
+    }
+
+    for entry in entries {
+        let path = store.join(format!("{}.gpg", entry.name));
+        let dir = path.parent().unwrap_or(store);
+        std::fs::create_dir_all(dir).map_err(|e| VaultError::IoError(format!("Failed to create directory: {}", e)))?;
+        encrypt_to_gpg_keys(&recipients(store, dir)?, &entry.content, &path)?;
+        backup::restrict_permissions(&path);
+    }
+    Ok(entries.len())
+}
+
+/// `folder/name` from the first tag and the credential name, with anything
+/// that would step outside the store or hide the file made safe
+fn entry_name(cred: &Credential) -> String {
+    let folder = cred.tags.first().map(|tag| tag.split('/').map(path_segment).filter(|s| !s.is_empty()));
+    let mut parts: Vec<String> = folder.into_iter().flatten().collect();
+    parts.push(match path_segment(&cred.name.replace('/', "-")) {
+        name if name.is_empty() => "unnamed".to_string(),
+        name => name,
+    });
+    parts.join("/")
+}
+
+fn path_segment(segment: &str) -> String {
+    let segment = segment.trim().replace(['\\', '\0'], "-");
+    match segment.strip_prefix('.') {
+        Some(rest) => format!("_{}", rest),
+        None => segment,
+    }
+}
+
+/// The file as pass tooling reads it: secret, metadata, otpauth URI, notes
+fn entry_content(dek: &DataEncryptionKey, cred: &Credential) -> VaultResult<Zeroizing<String>> {
+    let secret = Zeroizing::new(decrypt_credential_data(dek, &cred.id, &cred.encrypted_secret)?);
+    let notes = decrypt_notes(dek, &cred.id, cred.encrypted_notes.as_ref())?.map(Zeroizing::new);
+    let fields = Zeroizing::new(decrypt_fields(dek, &cred.id, cred.encrypted_fields.as_ref())?);
+    let totp = decrypt_totp_secret(dek, &cred.id, cred.encrypted_totp_secret.as_ref())?.map(Zeroizing::new);
+    let otpauth = totp
+        .as_deref()
+        .and_then(|input| TotpSecret::from_user_input(input, cred.username.as_deref().unwrap_or(&cred.name), &cred.name).ok())
+        .and_then(|totp| totp.to_uri().ok())
+        .map(Zeroizing::new);
+
+    let mut lines: Vec<(&str, &str)> = Vec::new();
+    if let Some(username) = &cred.username {
+        lines.push(("login", username.as_str()));
+    }
+    if let Some(url) = &cred.url {
+        lines.push(("url", url.as_str()));
+    }
+    lines.extend(fields.iter().map(|(label, value)| (label.as_str(), value.as_str())));
+    let expires = cred.expires_at.map(expiry::format_date);
+    if let Some(expires) = &expires {
+        lines.push(("expires", expires.as_str()));
+    }
+    let tags = cred.tags.join(", ");
+    if !tags.is_empty() {
+        lines.push(("tags", tags.as_str()));
+    }
+
+    let first_line = secret.trim_end_matches('\n');
+    let notes = notes.as_deref().map(|n| n.trim_end()).filter(|n| !n.is_empty());
+    let size = first_line.len()
+        + lines.iter().map(|(k, v)| k.len() + v.len() + 3).sum::<usize>()
+        + otpauth.as_ref().map_or(0, |uri| uri.len() + 1)
+        + notes.map_or(0, |n| n.len() + 2)
+        + 1;
+    // Sized up front so it never reallocates and leaves copies behind
+    let mut content = Zeroizing::new(String::with_capacity(size));
+    content.push_str(first_line);
+    content.push('\n');
+    for (key, value) in lines {
+        content.push_str(key);
+        content.push_str(": ");
+        content.push_str(value);
+        content.push('\n');
+    }
+    if let Some(uri) = &otpauth {
+        content.push_str(uri);
+        content.push('\n');
+    }
+    if let Some(notes) = notes {
+        content.push('\n');
+        content.push_str(notes);
+        content.push('\n');
+    }
+    Ok(content)
+}
+
+/// Key IDs from the `.gpg-id` nearest to `dir`, which pass lets subfolders
+/// override
+fn recipients(store: &Path, dir: &Path) -> VaultResult<Vec<String>> {
+    let gpg_id = dir
+        .ancestors()
+        .take_while(|d| d.starts_with(store))
+        .map(|d| d.join(GPG_ID))
+        .find(|p| p.is_file())
+        .unwrap_or_else(|| store.join(GPG_ID));
+    let ids = std::fs::read_to_string(&gpg_id).map_err(|e| VaultError::IoError(e.to_string()))?;
+    let ids: Vec<String> = ids
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|id| !id.is_empty())
+        .map(String::from)
+        .collect();
+    if ids.is_empty() {
+        return Err(VaultError::OperationFailed(format!("{} lists no GPG keys", gpg_id.display())));
+    }
+    Ok(ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    use crate::crypto::encrypt_string;
+    use crate::db::CredentialType;
+
+    fn credential(dek: &DataEncryptionKey, name: &str, tags: &[&str]) -> Credential {
+        let mut cred = Credential::new(name.into(), CredentialType::Password, encrypt_string(dek.as_bytes(), "hunter2").unwrap());
+        cred.tags = tags.iter().map(|t| t.to_string()).collect();
+        cred
+    }
+
+    #[test]
+    fn test_entry_layout() {
+        let dek = DataEncryptionKey::generate();
+        let mut github = credential(&dek, "GitHub", &["work/cloud", "dev"]);
+        github.username = Some("alice".into());
+        github.url = Some("https://github.com".into());
+        github.encrypted_totp_secret = Some(encrypt_string(dek.as_bytes(), "JBSWY3DPEHPK3PXP").unwrap());
+        github.encrypted_notes = Some(encrypt_string(dek.as_bytes(), "Main account\n").unwrap());
+        let credentials = [
+            github,
+            credential(&dek, "a/b", &[]),
+            credential(&dek, "A/B", &["../.."]),
+            credential(&dek, "a-b", &[]),
+        ];
+
+        let entries = pass_entries(&dek, &credentials).unwrap();
+        let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["work/cloud/GitHub", "a-b", "_./_./A-B", "a-b (2)"]);
+
+        let lines: Vec<_> = entries[0].content.lines().collect();
+        assert_eq!(lines[..4], ["hunter2", "login: alice", "url: https://github.com", "tags: work/cloud, dev"]);
+        assert!(lines[4].starts_with("otpauth://totp/GitHub:alice?secret=JBSWY3DPEHPK3PXP"));
+        assert_eq!(lines[5..], ["", "Main account"]);
+        assert_eq!(entries[0].content.capacity(), entries[0].content.len());
+        assert_eq!(entries[1].content.as_str(), "hunter2\n");
+    }
+
+    #[test]
+    fn test_recipients_nearest_gpg_id() {
+        let store = TempDir::new().unwrap();
+        let work = store.path().join("work/cloud");
+        std::fs::create_dir_all(&work).unwrap();
+        std::fs::write(store.path().join(GPG_ID), "alice@example.com\n\n").unwrap();
+        std::fs::write(store.path().join("work").join(GPG_ID), "ABCD1234 # team key\nbob@example.com\n").unwrap();
+
+        assert_eq!(recipients(store.path(), store.path()).unwrap(), ["alice@example.com"]);
+        assert_eq!(recipients(store.path(), &work).unwrap(), ["ABCD1234", "bob@example.com"]);
+
+        std::fs::write(store.path().join(GPG_ID), "# nobody\n").unwrap();
+        assert!(recipients(store.path(), store.path()).is_err());
+    }
+
+    #[test]
+    fn test_store_must_be_initialized() {
+        let store = TempDir::new().unwrap();
+        assert!(write_pass_store(&[], store.path()).is_err());
+    }
+}
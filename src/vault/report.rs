@@ -0,0 +1,247 @@
+//! Security reports over the whole vault
+//!
+//! A report is a list of groups of credentials; reports that are a plain
+//! list have a single group without a title.
+
+use std::collections::HashMap;
+
+use chrono::Local;
+use rusqlite::Connection;
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::crypto::{strength_label, DataEncryptionKey};
+use crate::db::{Credential, CredentialType};
+
+use super::credential::{decrypt_secret, secret_strength};
+use super::{search, VaultResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportKind {
+    /// Secrets unchanged for longer than the configured age
+    Age,
+    /// Groups of credentials sharing the same secret
+    Reuse,
+    /// Secrets scoring below the configured strength
+    Weak,
+    /// Rows that fail `:check`; not one of the `:report` names
+    Integrity,
+}
+
+impl ReportKind {
+    pub const ALL: [Self; 3] = [Self::Age, Self::Reuse, Self::Weak];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Age => "age",
+            Self::Reuse => "reuse",
+            Self::Weak => "weak",
+            Self::Integrity => "check",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::Age => "Password age",
+            Self::Reuse => "Reused passwords",
+            Self::Weak => "Weak passwords",
+            Self::Integrity => "Integrity check",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.name() == name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReportEntry {
+    pub id: String,
+    pub name: String,
+    pub username: Option<String>,
+    /// Why the credential is listed, e.g. how old its secret is
+    pub detail: String,
+}
+
+impl ReportEntry {
+    fn new(cred: &Credential, detail: String) -> Self {
+        Self {
+            id: cred.id.clone(),
+            name: cred.name.clone(),
+            username: cred.username.clone(),
+            detail,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReportGroup {
+    pub title: Option<String>,
+    pub entries: Vec<ReportEntry>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub kind: ReportKind,
+    pub groups: Vec<ReportGroup>,
+}
+
+impl Report {
+    pub fn entry_count(&self) -> usize {
+        self.groups.iter().map(|g| g.entries.len()).sum()
+    }
+}
+
+/// Credentials whose secret is older than `max_age_days`, stalest first
+///
+/// A secret's age counts from its last change, or from when the credential
+/// was created if it never changed. Notes have no secret worth rotating.
+pub fn password_age(conn: &Connection, max_age_days: u64) -> VaultResult<Report> {
+    let now = Local::now();
+    let mut stale: Vec<(Credential, i64)> = search::get_all(conn)?
+        .into_iter()
+        .filter(|c| c.credential_type != CredentialType::Note)
+        .map(|c| {
+            let age = (now - c.secret_set_at()).num_days();
+            (c, age)
+        })
+        .filter(|(_, age)| *age > max_age_days as i64)
+        .collect();
+    stale.sort_by_key(|(_, age)| std::cmp::Reverse(*age));
+
+    let entries = stale.iter().map(|(c, age)| ReportEntry::new(c, format!("{} days", age))).collect();
+    Ok(Report { kind: ReportKind::Age, groups: vec![ReportGroup { title: None, entries }] })
+}
+
+/// Credentials that share a secret, largest groups first
+///
+/// Secrets are compared by SHA-256 so only one is decrypted at a time.
+pub fn password_reuse(conn: &Connection, dek: &DataEncryptionKey) -> VaultResult<Report> {
+    let mut by_hash: HashMap<[u8; 32], Vec<Credential>> = HashMap::new();
+    for cred in search::get_all(conn)? {
+        if cred.credential_type == CredentialType::Note {
+            continue;
+        }
+        let secret = Zeroizing::new(decrypt_secret(dek, &cred.id, &cred.encrypted_secret)?);
+        if secret.is_empty() {
+            continue;
+        }
+        by_hash.entry(Sha256::digest(secret.as_bytes()).into()).or_default().push(cred);
+    }
+
+    let mut clusters: Vec<Vec<Credential>> = by_hash.into_values().filter(|c| c.len() > 1).collect();
+    // Name order within and between equal-sized groups keeps the report stable
+    for cluster in &mut clusters {
+        cluster.sort_by_key(|c| c.name.to_lowercase());
+    }
+    clusters.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a[0].name.to_lowercase().cmp(&b[0].name.to_lowercase())));
+
+    let groups = clusters
+        .iter()
+        .map(|cluster| ReportGroup {
+            title: Some("Same password".to_string()),
+            entries: cluster
+                .iter()
+                .map(|c| ReportEntry::new(c, format!("set {}", c.secret_set_at().format("%Y-%m-%d"))))
+                .collect(),
+        })
+        .collect();
+    Ok(Report { kind: ReportKind::Reuse, groups })
+}
+
+/// Credentials whose secret scores below `min_strength`, weakest first
+///
+/// Keys and certificates are left out: their strength isn't a password's.
+pub fn weak_passwords(conn: &Connection, dek: &DataEncryptionKey, min_strength: u32) -> VaultResult<Report> {
+    let mut weak = Vec::new();
+    for cred in search::get_all(conn)? {
+        if !has_password(cred.credential_type) {
+            continue;
+        }
+        let score = secret_strength(dek, &cred)?;
+        if score < min_strength {
+            weak.push((cred, score));
+        }
+    }
+    weak.sort_by_key(|(_, score)| *score);
+
+    let entries = weak
+        .iter()
+        .map(|(c, score)| ReportEntry::new(c, format!("{} ({})", strength_label(*score), score)))
+        .collect();
+    Ok(Report { kind: ReportKind::Weak, groups: vec![ReportGroup { title: None, entries }] })
+}
+
+/// Whether a type's secret is a password worth scoring; keys and notes are not
+pub fn has_password(credential_type: CredentialType) -> bool {
+    !matches!(credential_type, CredentialType::Note | CredentialType::SshKey | CredentialType::Certificate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{self, Database};
+    use crate::vault::credential::create_credential;
+
+    #[test]
+    fn test_password_age() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let dek = DataEncryptionKey::generate();
+
+        for (name, days_ago) in [("recent", 30), ("old", 400), ("ancient", 900)] {
+            let mut cred = create_credential(conn, &dek, name.into(), CredentialType::Password, "pw", None, None, vec![], None, None, &[])
+                .unwrap();
+            cred.secret_changed_at = Some(Local::now() - chrono::Duration::days(days_ago));
+            db::update_credential(conn, &cred).unwrap();
+        }
+
+        let report = password_age(conn, 365).unwrap();
+        let names: Vec<&str> = report.groups[0].entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["ancient", "old"]);
+        assert_eq!(report.groups[0].entries[1].detail, "400 days");
+        assert_eq!(ReportKind::from_name("age"), Some(ReportKind::Age));
+    }
+
+    #[test]
+    fn test_password_reuse() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let dek = DataEncryptionKey::generate();
+
+        let secrets = [("a", "hunter2"), ("b", "unique"), ("c", "hunter2"), ("d", "shared"), ("e", "hunter2"), ("f", "shared")];
+        for (name, secret) in secrets {
+            create_credential(conn, &dek, name.into(), CredentialType::Password, secret, None, None, vec![], None, None, &[])
+                .unwrap();
+        }
+        create_credential(conn, &dek, "note".into(), CredentialType::Note, "shared", None, None, vec![], None, None, &[])
+            .unwrap();
+
+        let report = password_reuse(conn, &dek).unwrap();
+        let groups: Vec<Vec<&str>> = report
+            .groups
+            .iter()
+            .map(|g| g.entries.iter().map(|e| e.name.as_str()).collect())
+            .collect();
+        assert_eq!(groups, [vec!["a", "c", "e"], vec!["d", "f"]]);
+    }
+
+    #[test]
+    fn test_weak_passwords() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let dek = DataEncryptionKey::generate();
+
+        let secrets = [("short", "abc"), ("strong", "MyP@ssw0rd!2026XyZ"), ("lower", "abcdefgh"), ("empty", "")];
+        for (name, secret) in secrets {
+            create_credential(conn, &dek, name.into(), CredentialType::Password, secret, None, None, vec![], None, None, &[])
+                .unwrap();
+        }
+        create_credential(conn, &dek, "key".into(), CredentialType::SshKey, "abc", None, None, vec![], None, None, &[])
+            .unwrap();
+
+        let report = weak_passwords(conn, &dek, 41).unwrap();
+        let names: Vec<&str> = report.groups[0].entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["empty", "short", "lower"]);
+    }
+}
@@ -0,0 +1,169 @@
+//! DEK rotation: re-encrypt everything under a fresh data encryption key
+//!
+//! Credentials, trashed credentials and revisions all carry the same four
+//! encrypted columns; each is decrypted with the credential's key under the
+//! old DEK and encrypted again with its key under the new one, which also
+//! moves blobs from before per-credential keys onto them. In a vault with
+//! encrypted metadata the name, username, URL and tags columns go the same
+//! way, as do the names in the audit log. The audit log is signed with a key
+//! derived from the DEK, so entries that still verify are signed again too.
+//! Callers run this inside a transaction so a failure leaves the old DEK in
+//! charge.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use zeroize::Zeroizing;
+
+use crate::crypto::{DataEncryptionKey, DerivedKey};
+use crate::db;
+
+use super::{audit, metadata};
+use super::credential::{decrypt_credential_data, encrypt_credential_data};
+use super::VaultResult;
+
+/// Tables with encrypted credential columns and the column keying their rows
+pub(super) const TABLES: [(&str, &str); 3] = [("credentials", "id"), ("trash", "id"), ("revisions", "revision")];
+
+/// Columns holding ciphertext under the DEK; all but the secret may be NULL
+const ENCRYPTED_COLUMNS: [&str; 4] = ["encrypted_secret", "encrypted_notes", "encrypted_totp_secret", "encrypted_fields"];
+
+/// Columns also encrypted when the vault encrypts its metadata
+const METADATA_COLUMNS: [&str; 4] = ["name", "username", "url", "tags"];
+
+/// Re-encrypt every row from `old` to `new`, calling `progress(done, total)`
+/// after each; returns the number of rows
+pub fn reencrypt_all(
+    conn: &Connection,
+    old: &DataEncryptionKey,
+    new: &DataEncryptionKey,
+    progress: &mut dyn FnMut(usize, usize),
+) -> VaultResult<usize> {
+    let mut rows = Vec::new();
+    for (table, key) in TABLES {
+        let mut stmt = conn.prepare(&format!("SELECT {}, id FROM {}", key, table))?;
+        let keys = stmt.query_map([], |row| Ok((row.get::<_, rusqlite::types::Value>(0)?, row.get::<_, String>(1)?)))?;
+        for row in keys {
+            let (row_key, id) = row?;
+            rows.push((table, key, row_key, id));
+        }
+    }
+
+    let columns = encrypted_columns(conn);
+    let total = rows.len();
+    for (done, (table, key, row_key, id)) in rows.into_iter().enumerate() {
+        reencrypt_row(conn, (table, key, &row_key), &id, &columns, old, new)?;
+        progress(done + 1, total);
+    }
+    Ok(total)
+}
+
+/// Columns of `TABLES` holding ciphertext in this vault
+pub(super) fn encrypted_columns(conn: &Connection) -> Vec<&'static str> {
+    let mut columns = ENCRYPTED_COLUMNS.to_vec();
+    if metadata::is_encrypted(conn) {
+        columns.extend(METADATA_COLUMNS);
+    }
+    columns
+}
+
+/// Re-encrypt the row of `table` whose `key` column is `row_key`, holding
+/// data of credential `id`
+fn reencrypt_row(
+    conn: &Connection,
+    (table, key, row_key): (&str, &str, &rusqlite::types::Value),
+    id: &str,
+    columns: &[&str],
+    old: &DataEncryptionKey,
+    new: &DataEncryptionKey,
+) -> VaultResult<()> {
+    for column in columns {
+        let select = format!("SELECT {} FROM {} WHERE {} = ?1", column, table, key);
+        let blob: Option<String> = conn.query_row(&select, [row_key], |row| row.get(0)).optional()?.flatten();
+        let Some(blob) = blob else { continue };
+
+        let plain = Zeroizing::new(decrypt_credential_data(old, id, &blob)?);
+        let fresh = encrypt_credential_data(new, id, &plain)?;
+        let update = format!("UPDATE {} SET {} = ?1 WHERE {} = ?2", table, column, key);
+        conn.execute(&update, params![fresh, row_key])?;
+    }
+    Ok(())
+}
+
+/// Sign the audit entries that verify under `old` again with `new`; ones
+/// that do not were tampered with and stay unverifiable. Returns how many
+/// were signed again.
+pub fn resign_audit_log(conn: &Connection, old: &DerivedKey, new: &DerivedKey) -> VaultResult<usize> {
+    let mut resigned = 0;
+    for mut log in db::get_all_audit_logs(conn)? {
+        if !audit::verify_log(old, &log) {
+            continue;
+        }
+        log.hmac = audit::sign_log(new, &log);
+        conn.execute("UPDATE audit_log SET hmac = ?1 WHERE id = ?2", params![log.hmac, log.id])?;
+        resigned += 1;
+    }
+    Ok(resigned)
+}
+
+/// Move the encrypted credential names and usernames of the audit log from
+/// `old` to `new`; a no-op unless the vault encrypts its metadata. Run after
+/// `resign_audit_log`, which reads them under `old`.
+pub fn reencrypt_audit_log(conn: &Connection, old: &DataEncryptionKey, new: &DataEncryptionKey) -> VaultResult<()> {
+    if !metadata::is_encrypted(conn) {
+        return Ok(());
+    }
+    let mut stmt = conn.prepare("SELECT id, COALESCE(credential_id, ''), credential_name, username FROM audit_log")?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get(2)?, row.get(3)?)))?
+        .collect::<rusqlite::Result<Vec<(i64, String, Option<String>, Option<String>)>>>()?;
+
+    let convert = |id: &str, blob: Option<String>| -> VaultResult<Option<String>> {
+        blob.map(|blob| encrypt_credential_data(new, id, &Zeroizing::new(decrypt_credential_data(old, id, &blob)?))).transpose()
+    };
+    for (row, id, name, username) in rows {
+        conn.execute(
+            "UPDATE audit_log SET credential_name = ?1, username = ?2 WHERE id = ?3",
+            params![convert(&id, name)?, convert(&id, username)?, row],
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::ExposeSecret;
+
+    use crate::crypto::{KeyHierarchy, MasterKey};
+    use crate::db::{AuditAction, CredentialType, Database};
+    use crate::vault::credential::{create_credential, decrypt_credential};
+
+    #[test]
+    fn test_reencrypt_all() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let old_keys = KeyHierarchy::new(MasterKey::from_bytes([1u8; 32])).unwrap();
+        let new_keys = old_keys.with_fresh_dek().unwrap();
+
+        let cred = create_credential(conn, old_keys.dek(), "Bank".into(), CredentialType::Password, "hunter2", None, None, vec![], Some("pin 1234"), None, &[]).unwrap();
+        audit::log_action(conn, &old_keys.derive_audit_key().unwrap(), AuditAction::Create, Some(&cred.id), Some("Bank"), None, None).unwrap();
+        conn.execute("UPDATE audit_log SET hmac = 'forged' WHERE id = (SELECT MAX(id) FROM audit_log)", []).unwrap();
+        audit::log_action(conn, &old_keys.derive_audit_key().unwrap(), AuditAction::Read, Some(&cred.id), Some("Bank"), None, None).unwrap();
+
+        let mut calls = Vec::new();
+        let rows = reencrypt_all(conn, old_keys.dek(), new_keys.dek(), &mut |done, total| calls.push((done, total))).unwrap();
+        assert_eq!(rows, 1);
+        assert_eq!(calls, vec![(1, 1)]);
+
+        let stored = db::get_credential(conn, &cred.id).unwrap();
+        assert!(decrypt_credential_data(old_keys.dek(), &cred.id, &stored.encrypted_secret).is_err());
+        let decrypted = decrypt_credential(conn, new_keys.dek(), &stored, false).unwrap();
+        assert_eq!(decrypted.secret.unwrap().expose_secret(), "hunter2");
+        assert_eq!(decrypted.notes.unwrap().expose_secret(), "pin 1234");
+
+        let new_audit = new_keys.derive_audit_key().unwrap();
+        assert_eq!(resign_audit_log(conn, &old_keys.derive_audit_key().unwrap(), &new_audit).unwrap(), 1);
+        let verified: Vec<bool> = audit::verify_all_logs(conn, &new_audit).unwrap().into_iter().map(|(_, ok)| ok).collect();
+        assert_eq!(verified.iter().filter(|ok| **ok).count(), 1);
+        assert_eq!(verified.len(), 2);
+    }
+}
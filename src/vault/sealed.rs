@@ -0,0 +1,153 @@
+//! Built-in export encryption
+//!
+//! Seals a JSON or text export behind a passphrase without calling out to
+//! gpg or age: the key comes from Argon2id over the passphrase, as for the
+//! master key, and the export is encrypted with AES-256-GCM. The result is a
+//! small JSON envelope carrying the KDF settings, salt, nonce and tag next to
+//! the base64 ciphertext, which import recognises and opens again.
+
+use aes_gcm::aead::AeadInPlace;
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce, Tag};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::crypto::{derive_key_with_salt, generate_salt, KdfParams};
+
+use super::{VaultError, VaultResult};
+
+pub const SEALED_FORMAT: &str = "vault-export";
+pub const SEALED_VERSION: u32 = 1;
+pub const SEALED_EXTENSION: &str = ".sealed";
+
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+/// On-disk envelope; nothing about the export is readable without the passphrase
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SealedExport {
+    pub format: String,
+    pub version: u32,
+    pub kdf: KdfParams,
+    /// Salt for deriving the passphrase key
+    pub salt: String,
+    pub nonce: String,
+    pub tag: String,
+    /// The export, AES-256-GCM encrypted, base64
+    pub payload: String,
+}
+
+fn crypto_err(e: crate::crypto::CryptoError) -> VaultError {
+    VaultError::CryptoError(e.to_string())
+}
+
+/// Encrypt `content` behind `passphrase`, returning the envelope as JSON
+pub fn seal(content: &str, passphrase: &str) -> VaultResult<String> {
+    seal_with(content, passphrase, KdfParams::default())
+}
+
+fn seal_with(content: &str, passphrase: &str, kdf: KdfParams) -> VaultResult<String> {
+    let sealed = SealedExport::seal(content, passphrase, kdf)?;
+    serde_json::to_string_pretty(&sealed)
+        .map_err(|e| VaultError::OperationFailed(format!("JSON serialization failed: {}", e)))
+}
+
+impl SealedExport {
+    /// Encrypt `content` behind `passphrase`, deriving the key with `kdf`
+    pub fn seal(content: &str, passphrase: &str, kdf: KdfParams) -> VaultResult<Self> {
+        if passphrase.is_empty() {
+            return Err(VaultError::OperationFailed("Passphrase required for built-in encryption".into()));
+        }
+
+        let salt = generate_salt();
+        let key = derive_key_with_salt(passphrase.as_bytes(), &salt, &kdf).map_err(crypto_err)?;
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        let mut ciphertext = content.as_bytes().to_vec();
+        let tag = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key.as_bytes()))
+            .encrypt_in_place_detached(Nonce::from_slice(&nonce), b"", &mut ciphertext)
+            .map_err(|_| VaultError::CryptoError("Encryption failed".into()))?;
+
+        Ok(Self {
+            format: SEALED_FORMAT.to_string(),
+            version: SEALED_VERSION,
+            kdf,
+            salt,
+            nonce: STANDARD.encode(nonce),
+            tag: STANDARD.encode(tag),
+            payload: STANDARD.encode(ciphertext),
+        })
+    }
+
+    /// Parse an envelope, or `None` if `content` isn't one
+    pub fn parse(content: &str) -> Option<Self> {
+        serde_json::from_str::<Self>(content).ok().filter(|s| s.format == SEALED_FORMAT)
+    }
+
+    /// Derive the key from `passphrase` and decrypt the export
+    pub fn open(&self, passphrase: &str) -> VaultResult<Zeroizing<String>> {
+        if self.version > SEALED_VERSION {
+            return Err(VaultError::OperationFailed(format!(
+                "Export version {} is newer than this vault supports ({})",
+                self.version, SEALED_VERSION
+            )));
+        }
+
+        let damaged = |detail: &str| VaultError::OperationFailed(format!("Export is damaged: {}", detail));
+        let decode = |field: &str| STANDARD.decode(field).map_err(|_| damaged("invalid base64"));
+        let nonce: [u8; NONCE_LEN] = decode(&self.nonce)?.try_into().map_err(|_| damaged("bad nonce"))?;
+        let tag: [u8; TAG_LEN] = decode(&self.tag)?.try_into().map_err(|_| damaged("bad tag"))?;
+        let mut plaintext = decode(&self.payload)?;
+
+        // The settings come from the file; don't let a crafted one exhaust memory
+        self.kdf.check_bounds().map_err(crypto_err)?;
+        let key = derive_key_with_salt(passphrase.as_bytes(), &self.salt, &self.kdf).map_err(crypto_err)?;
+        // A wrong passphrase and a tampered file look the same from here
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key.as_bytes()))
+            .decrypt_in_place_detached(Nonce::from_slice(&nonce), b"", &mut plaintext, Tag::from_slice(&tag))
+            .map_err(|_| VaultError::InvalidPassword)?;
+        String::from_utf8(plaintext).map(Zeroizing::new).map_err(|e| {
+            e.into_bytes().zeroize();
+            damaged("not UTF-8")
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_round_trip() {
+        let sealed = seal_with("{\"secret\": \"hunter2\"}", "export pass", KdfParams::testing()).unwrap();
+        assert!(!sealed.contains("hunter2"));
+
+        let envelope = SealedExport::parse(&sealed).unwrap();
+        assert_eq!(envelope.open("export pass").unwrap().as_str(), "{\"secret\": \"hunter2\"}");
+        assert!(matches!(envelope.open("wrong pass"), Err(VaultError::InvalidPassword)));
+
+        assert!(seal_with("x", "", KdfParams::testing()).is_err());
+        assert!(SealedExport::parse("{\"format\": \"vault-bundle\"}").is_none());
+    }
+
+    #[test]
+    fn test_tampered_payload_rejected() {
+        let sealed = seal_with("hello", "export pass", KdfParams::testing()).unwrap();
+        let mut envelope = SealedExport::parse(&sealed).unwrap();
+        let mut payload = STANDARD.decode(&envelope.payload).unwrap();
+        payload[0] ^= 1;
+        envelope.payload = STANDARD.encode(payload);
+        assert!(envelope.open("export pass").is_err());
+    }
+
+    #[test]
+    fn test_oversized_kdf_rejected() {
+        let sealed = seal_with("hello", "export pass", KdfParams::testing()).unwrap();
+        let mut envelope = SealedExport::parse(&sealed).unwrap();
+        envelope.kdf.memory_cost = u32::MAX;
+        assert!(matches!(envelope.open("export pass"), Err(VaultError::CryptoError(_))));
+    }
+}
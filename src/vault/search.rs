@@ -10,7 +10,6 @@ pub fn get_all(conn: &rusqlite::Connection) -> VaultResult<Vec<Credential>> {
     db::get_all_credentials(conn).map_err(Into::into)
 }
 
-#[allow(dead_code)]
 pub fn search_credentials(conn: &rusqlite::Connection, query: &str) -> VaultResult<Vec<Credential>> {
     let trimmed = query.trim();
     if trimmed.is_empty() {
@@ -23,11 +22,210 @@ pub fn filter_by_tags(conn: &rusqlite::Connection, tags: &[String]) -> VaultResu
     db::get_credentials_by_tag(conn, tags).map_err(Into::into)
 }
 
-// TODO: wire up filter by type
-#[allow(dead_code)]
-pub fn filter_by_type(conn: &rusqlite::Connection, cred_type: CredentialType) -> VaultResult<Vec<Credential>> {
-    let all = get_all(conn)?;
-    Ok(all.into_iter().filter(|c| c.credential_type == cred_type).collect())
+/// How the included tags of a tag filter combine
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TagMatch {
+    /// Credentials carrying every included tag
+    #[default]
+    All,
+    /// Credentials carrying at least one of them
+    Any,
+}
+
+impl TagMatch {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::All => "all",
+            Self::Any => "any",
+        }
+    }
+
+    pub fn toggled(&self) -> Self {
+        match self {
+            Self::All => Self::Any,
+            Self::Any => Self::All,
+        }
+    }
+}
+
+/// Whether the credential carries `tag` or a tag nested under it
+pub fn has_tag(cred: &Credential, tag: &str) -> bool {
+    cred.tags.iter().any(|t| t.strip_prefix(tag).is_some_and(|rest| rest.is_empty() || rest.starts_with('/')))
+}
+
+/// Split a tag filter into included tags and the `-tag` exclusions
+pub fn split_tag_filter(tags: &[String]) -> (Vec<String>, Vec<String>) {
+    let (excluded, included): (Vec<&String>, Vec<&String>) = tags.iter().partition(|t| t.starts_with('-'));
+    (
+        included.into_iter().cloned().collect(),
+        excluded.into_iter().map(|t| t[1..].to_string()).collect(),
+    )
+}
+
+/// How the search query matches credentials
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchMode {
+    /// Case-insensitive substring of the name, username or a tag
+    #[default]
+    Substring,
+    /// Characters in order with gaps allowed, best matches first
+    Fuzzy,
+}
+
+impl SearchMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Substring => "substring",
+            Self::Fuzzy => "fuzzy",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "substring" => Some(Self::Substring),
+            "fuzzy" => Some(Self::Fuzzy),
+            _ => None,
+        }
+    }
+}
+
+/// Split a `~query` into fuzzy mode and the query; otherwise use `default`
+pub fn parse_query(query: &str, default: SearchMode) -> (SearchMode, &str) {
+    match query.strip_prefix('~') {
+        Some(rest) => (SearchMode::Fuzzy, rest.trim()),
+        None => (default, query.trim()),
+    }
+}
+
+const SCORE_MATCH: i64 = 16;
+const BONUS_BOUNDARY: i64 = 8;
+const BONUS_CONSECUTIVE: i64 = 8;
+const PENALTY_GAP: i64 = 1;
+
+/// Skim-style fuzzy score of `pattern` against `text`, or None when the
+/// pattern is not a subsequence of the text
+///
+/// Matches score more at word starts and in runs, and lose a little for
+/// each skipped character, so `ghtok` prefers "GitHub Token".
+pub fn fuzzy_score(pattern: &str, text: &str) -> Option<i64> {
+    let pattern: Vec<char> = pattern.chars().filter(|c| !c.is_whitespace()).flat_map(char::to_lowercase).collect();
+    if pattern.is_empty() {
+        return Some(0);
+    }
+    let original: Vec<char> = text.chars().collect();
+    let lower: Vec<char> = original.iter().map(|c| c.to_lowercase().next().unwrap_or(*c)).collect();
+    if pattern.len() > lower.len() {
+        return None;
+    }
+
+    let bonus: Vec<i64> = (0..original.len()).map(|j| boundary_bonus(&original, j)).collect();
+
+    // best[j]: best score with the current pattern char matched at text[j]
+    let mut best: Vec<Option<i64>> = vec![None; lower.len()];
+    for (i, &pc) in pattern.iter().enumerate() {
+        let mut next: Vec<Option<i64>> = vec![None; lower.len()];
+        // Best score of the previous pattern char matched before j, less gaps
+        let mut carried: Option<i64> = None;
+        for j in 0..lower.len() {
+            if j > 0 {
+                carried = carried.map(|s| s - PENALTY_GAP).max(best[j - 1]);
+            }
+            if lower[j] != pc {
+                continue;
+            }
+            let base = SCORE_MATCH + bonus[j];
+            next[j] = if i == 0 {
+                Some(base)
+            } else {
+                let consecutive = j.checked_sub(1).and_then(|k| best[k]).map(|s| s + base + BONUS_CONSECUTIVE);
+                carried.map(|s| s + base).max(consecutive)
+            };
+        }
+        best = next;
+    }
+    best.into_iter().flatten().max()
+}
+
+/// Bonus for matching at the start of the text, after a separator, or at a camelCase hump
+fn boundary_bonus(chars: &[char], j: usize) -> i64 {
+    let Some(&prev) = j.checked_sub(1).and_then(|k| chars.get(k)) else {
+        return BONUS_BOUNDARY;
+    };
+    let current = chars[j];
+    if !prev.is_alphanumeric() || (prev.is_lowercase() && current.is_uppercase()) {
+        BONUS_BOUNDARY
+    } else {
+        0
+    }
+}
+
+/// Character positions in `text` matched by `query`, for highlighting
+///
+/// Substring mode marks every occurrence; fuzzy mode marks the leftmost
+/// subsequence, and nothing when the query does not match.
+pub fn match_indices(text: &str, mode: SearchMode, query: &str) -> Vec<usize> {
+    let lower: Vec<char> = text.chars().map(|c| c.to_lowercase().next().unwrap_or(c)).collect();
+    let needle: Vec<char> = query.chars().filter(|c| mode == SearchMode::Substring || !c.is_whitespace()).flat_map(char::to_lowercase).collect();
+    if needle.is_empty() || needle.len() > lower.len() {
+        return Vec::new();
+    }
+
+    match mode {
+        SearchMode::Substring => {
+            let mut indices = Vec::new();
+            let mut start = 0;
+            while start + needle.len() <= lower.len() {
+                if lower[start..start + needle.len()] == needle[..] {
+                    indices.extend(start..start + needle.len());
+                    start += needle.len();
+                } else {
+                    start += 1;
+                }
+            }
+            indices
+        }
+        SearchMode::Fuzzy => {
+            let mut pending = needle.iter().peekable();
+            let indices: Vec<usize> = lower
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| pending.next_if(|n| n == c).is_some())
+                .map(|(i, _)| i)
+                .collect();
+            if indices.len() == needle.len() { indices } else { Vec::new() }
+        }
+    }
+}
+
+/// Best fuzzy score over a credential's name, username and tags, names weighted highest
+pub fn credential_fuzzy_score(cred: &Credential, pattern: &str) -> Option<i64> {
+    let name = fuzzy_score(pattern, &cred.name);
+    let username = cred.username.as_deref().and_then(|u| fuzzy_score(pattern, u)).map(|s| s - BONUS_BOUNDARY);
+    let tags = cred.tags.iter().filter_map(|t| fuzzy_score(pattern, t)).max().map(|s| s - BONUS_BOUNDARY);
+    name.max(username).max(tags)
+}
+
+/// Credentials matching the tag filter whose type is any of `types`
+///
+/// `tags` holds included tags, combined by `tag_match`, and `-tag`
+/// exclusions that always apply. An empty list leaves that side unfiltered.
+pub fn filter_credentials(
+    conn: &rusqlite::Connection,
+    tags: &[String],
+    tag_match: TagMatch,
+    types: &[CredentialType],
+) -> VaultResult<Vec<Credential>> {
+    let (included, excluded) = split_tag_filter(tags);
+    let base = match tag_match {
+        TagMatch::All if !included.is_empty() => filter_by_tags(conn, &included)?,
+        _ => get_all(conn)?,
+    };
+    Ok(base
+        .into_iter()
+        .filter(|c| tag_match == TagMatch::All || included.is_empty() || included.iter().any(|t| has_tag(c, t)))
+        .filter(|c| !excluded.iter().any(|t| has_tag(c, t)))
+        .filter(|c| types.is_empty() || types.contains(&c.credential_type))
+        .collect())
 }
 
 #[cfg(test)]
@@ -53,7 +251,7 @@ mod tests {
             ("AWS Prod", CredentialType::ApiKey, vec!["cloud", "prod"]),
             ("AWS Staging", CredentialType::ApiKey, vec!["cloud", "staging"]),
             ("GitHub Token", CredentialType::ApiKey, vec!["dev"]),
-            ("Gmail", CredentialType::Password, vec!["personal"]),
+            ("Gmail", CredentialType::Password, vec!["personal/mail"]),
         ];
 
         for (name, ctype, tags) in creds {
@@ -82,11 +280,41 @@ mod tests {
         let db = Database::open_in_memory().unwrap();
         setup_test_data(db.conn());
 
-        let results = filter_by_type(db.conn(), CredentialType::ApiKey).unwrap();
+        let results = filter_credentials(db.conn(), &[], TagMatch::All, &[CredentialType::ApiKey]).unwrap();
         assert_eq!(results.len(), 3);
 
-        let results = filter_by_type(db.conn(), CredentialType::Password).unwrap();
+        let results = filter_credentials(db.conn(), &[], TagMatch::All, &[CredentialType::Password]).unwrap();
         assert_eq!(results.len(), 1);
+
+        let results = filter_credentials(db.conn(), &[], TagMatch::All, &[CredentialType::ApiKey, CredentialType::Password]).unwrap();
+        assert_eq!(results.len(), 4);
+        assert_eq!(filter_credentials(db.conn(), &[], TagMatch::All, &[]).unwrap().len(), 4);
+
+        let tags = vec!["cloud".to_string()];
+        let results = filter_credentials(db.conn(), &tags, TagMatch::All, &[CredentialType::ApiKey]).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(filter_credentials(db.conn(), &tags, TagMatch::All, &[CredentialType::Password]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert!(fuzzy_score("ghtok", "GitHub Token").is_some());
+        assert!(fuzzy_score("ghtok", "Gmail").is_none());
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+
+        // Runs and word starts beat scattered matches
+        assert!(fuzzy_score("tok", "Token") > fuzzy_score("tok", "the outlook"));
+        assert!(fuzzy_score("gh", "GitHub") > fuzzy_score("gh", "Gmail high"));
+        assert_eq!(parse_query("~gh ", SearchMode::Substring), (SearchMode::Fuzzy, "gh"));
+        assert_eq!(parse_query("gh", SearchMode::Fuzzy), (SearchMode::Fuzzy, "gh"));
+    }
+
+    #[test]
+    fn test_match_indices() {
+        assert_eq!(match_indices("AWS aws", SearchMode::Substring, "aws"), [0, 1, 2, 4, 5, 6]);
+        assert_eq!(match_indices("GitHub Token", SearchMode::Fuzzy, "ghtok"), [0, 3, 7, 8, 9]);
+        assert!(match_indices("Gmail", SearchMode::Fuzzy, "ghtok").is_empty());
+        assert!(match_indices("Gmail", SearchMode::Substring, "").is_empty());
     }
 
     #[test]
@@ -96,5 +324,32 @@ mod tests {
 
         let results = filter_by_tags(db.conn(), &["cloud".to_string()]).unwrap();
         assert_eq!(results.len(), 2);
+
+        // Parent tags match nested ones, but not tags that merely share a prefix
+        let results = filter_by_tags(db.conn(), &["personal".to_string()]).unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(filter_by_tags(db.conn(), &["person".to_string()]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_tag_filter_logic() {
+        let db = Database::open_in_memory().unwrap();
+        setup_test_data(db.conn());
+        let filter = |tags: &[&str], tag_match| {
+            let tags: Vec<String> = tags.iter().map(|t| t.to_string()).collect();
+            let mut names: Vec<String> = filter_credentials(db.conn(), &tags, tag_match, &[])
+                .unwrap()
+                .into_iter()
+                .map(|c| c.name)
+                .collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(filter(&["cloud", "prod"], TagMatch::All), ["AWS Prod"]);
+        assert_eq!(filter(&["prod", "dev"], TagMatch::Any), ["AWS Prod", "GitHub Token"]);
+        assert_eq!(filter(&["cloud", "-staging"], TagMatch::All), ["AWS Prod"]);
+        assert_eq!(filter(&["-cloud", "-personal"], TagMatch::Any), ["GitHub Token"]);
+        assert_eq!(filter(&["-personal/mail"], TagMatch::All).len(), 3);
     }
 }
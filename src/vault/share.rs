@@ -0,0 +1,144 @@
+//! Sharing a single credential with another vault
+//!
+//! The credential is written as compact JSON in the export format and
+//! sealed the way built-in export encryption seals a file, under a key
+//! Argon2id derives from a one-time passphrase. The envelope is then
+//! ASCII-armored so it can be pasted, saved to a file or shown as a QR code:
+//!
+//! ```text
+//! -----BEGIN VAULT SHARE-----
+//! <base64 of the sealed envelope>
+//! -----END VAULT SHARE-----
+//! ```
+//!
+//! The passphrase is generated fresh for every share and shown once, to be
+//! passed on separately; import opens the block with it on the other side.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use zeroize::Zeroizing;
+
+use crate::crypto::{generate_passphrase, KdfParams, PassphraseOptions, Wordlist};
+
+use super::export::ExportCredential;
+use super::sealed::SealedExport;
+use super::{VaultError, VaultResult};
+
+const BEGIN: &str = "-----BEGIN VAULT SHARE-----";
+const END: &str = "-----END VAULT SHARE-----";
+/// Extension for a share saved to a file
+pub const SHARE_EXTENSION: &str = ".share";
+/// Words in the one-time passphrase, about 61 bits from the built-in list
+const PASSPHRASE_WORDS: usize = 8;
+/// Base64 characters per armored line
+const LINE_WIDTH: usize = 64;
+
+/// A credential ready to hand over
+pub struct Share {
+    /// The encrypted credential, armored
+    pub armored: String,
+    /// Needed to open it; never stored
+    pub passphrase: Zeroizing<String>,
+}
+
+/// Encrypt `credential` under a new one-time passphrase
+pub fn share_credential(credential: &ExportCredential) -> VaultResult<Share> {
+    let options = PassphraseOptions { words: PASSPHRASE_WORDS, ..PassphraseOptions::default() };
+    let passphrase = generate_passphrase(&options, &Wordlist::builtin())
+        .map_err(|e| VaultError::OperationFailed(e.to_string()))?;
+    let passphrase = Zeroizing::new(passphrase);
+    let armored = seal(credential, &passphrase, KdfParams::default())?;
+    Ok(Share { armored, passphrase })
+}
+
+fn seal(credential: &ExportCredential, passphrase: &str, kdf: KdfParams) -> VaultResult<String> {
+    let to_json = |e: serde_json::Error| VaultError::OperationFailed(format!("JSON serialization failed: {}", e));
+    let json = Zeroizing::new(serde_json::to_string(credential).map_err(to_json)?);
+    let envelope = SealedExport::seal(&json, passphrase, kdf)?;
+    // Compact, unlike a sealed export file, to keep the QR code small
+    let encoded = STANDARD.encode(serde_json::to_string(&envelope).map_err(to_json)?);
+
+    let mut armored = String::from(BEGIN);
+    for line in encoded.as_bytes().chunks(LINE_WIDTH) {
+        armored.push('\n');
+        armored.push_str(std::str::from_utf8(line).unwrap_or_default());
+    }
+    armored.push('\n');
+    armored.push_str(END);
+    armored.push('\n');
+    Ok(armored)
+}
+
+/// Whether `text` holds an armored share
+pub fn is_share(text: &str) -> bool {
+    text.contains(BEGIN)
+}
+
+/// Decrypt the share in `text` with its passphrase
+pub fn open_share(text: &str, passphrase: &str) -> VaultResult<ExportCredential> {
+    let damaged = |detail: &str| VaultError::OperationFailed(format!("Share is damaged: {}", detail));
+    let body = text
+        .split_once(BEGIN)
+        .and_then(|(_, rest)| rest.split_once(END))
+        .map(|(body, _)| body)
+        .ok_or_else(|| damaged("no END line"))?;
+    let encoded: String = body.chars().filter(|c| !c.is_whitespace()).collect();
+    let payload = STANDARD.decode(encoded).map_err(|_| damaged("invalid base64"))?;
+    let envelope = std::str::from_utf8(&payload)
+        .ok()
+        .and_then(SealedExport::parse)
+        .ok_or_else(|| damaged("not a sealed credential"))?;
+
+    let json = envelope.open(passphrase)?;
+    serde_json::from_str(&json).map_err(|_| damaged("not a credential"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::CredentialType;
+
+    fn credential() -> ExportCredential {
+        ExportCredential {
+            name: "Wi-Fi".into(),
+            credential_type: CredentialType::Password,
+            username: None,
+            secret: "correct horse".into(),
+            notes: Some("Guest network".into()),
+            url: None,
+            tags: vec!["home".into()],
+            fields: Vec::new(),
+            expires_at: None,
+            rotate_every: None,
+            password_rule: None,
+        }
+    }
+
+    #[test]
+    fn test_share_round_trip() {
+        let armored = seal(&credential(), "one time pass", KdfParams::testing()).unwrap();
+        assert!(armored.starts_with(BEGIN) && armored.trim_end().ends_with(END));
+        assert!(armored.lines().all(|line| line.len() <= LINE_WIDTH.max(BEGIN.len())));
+        assert!(!armored.contains("correct"));
+        assert!(qrcode::QrCode::with_error_correction_level(&armored, qrcode::EcLevel::L).is_ok());
+
+        // Pasted with extra indentation and text around it
+        let pasted = format!("Here you go:\n  {}\nbye", armored.replace('\n', "\n  "));
+        assert!(is_share(&pasted));
+        let opened = open_share(&pasted, "one time pass").unwrap();
+        assert_eq!((opened.name.as_str(), opened.secret.as_str()), ("Wi-Fi", "correct horse"));
+        assert_eq!(opened.notes.as_deref(), Some("Guest network"));
+
+        assert!(matches!(open_share(&armored, "wrong"), Err(VaultError::InvalidPassword)));
+        assert!(open_share(BEGIN, "one time pass").is_err());
+    }
+
+    #[test]
+    fn test_one_time_passphrase() {
+        let first = share_credential(&credential()).unwrap();
+        let second = share_credential(&credential()).unwrap();
+        assert_eq!(first.passphrase.split('-').count(), PASSPHRASE_WORDS);
+        assert_ne!(first.passphrase, second.passphrase);
+        assert_eq!(open_share(&first.armored, &first.passphrase).unwrap().secret, "correct horse");
+    }
+}
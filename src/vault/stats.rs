@@ -0,0 +1,108 @@
+//! Vault statistics for `:stats`
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use chrono::{DateTime, Local};
+use rusqlite::Connection;
+
+use crate::crypto::DataEncryptionKey;
+use crate::db::{self, CredentialType};
+
+use super::credential::secret_strength;
+use super::report::has_password;
+use super::{search, VaultResult};
+
+/// A credential's name and when it was created
+pub type Dated = (String, DateTime<Local>);
+
+#[derive(Debug, Clone, Default)]
+pub struct VaultStats {
+    pub total: usize,
+    /// Credentials per type, most common first, only types in use
+    pub by_type: Vec<(CredentialType, usize)>,
+    /// Credentials per tag, most used first
+    pub by_tag: Vec<(String, usize)>,
+    pub untagged: usize,
+    pub trashed: usize,
+    pub audit_entries: usize,
+    pub oldest: Option<Dated>,
+    pub newest: Option<Dated>,
+    /// Average strength score (0-100) over the passwords scored
+    pub average_strength: Option<u32>,
+    pub scored: usize,
+    /// Size of the database file in bytes
+    pub file_size: u64,
+}
+
+/// Gather the statistics; only passwords are decrypted, one at a time, to score them
+pub fn collect(conn: &Connection, dek: &DataEncryptionKey, file_size: u64) -> VaultResult<VaultStats> {
+    let credentials = search::get_all(conn)?;
+
+    let mut types: HashMap<CredentialType, usize> = HashMap::new();
+    let mut tags: HashMap<&str, usize> = HashMap::new();
+    let mut untagged = 0;
+    let mut strengths = Vec::new();
+    for cred in &credentials {
+        *types.entry(cred.credential_type).or_insert(0) += 1;
+        for tag in &cred.tags {
+            *tags.entry(tag).or_insert(0) += 1;
+        }
+        if cred.tags.is_empty() {
+            untagged += 1;
+        }
+        if has_password(cred.credential_type) {
+            strengths.push(secret_strength(dek, cred)?);
+        }
+    }
+
+    let mut by_type: Vec<_> = types.into_iter().collect();
+    by_type.sort_by_key(|(kind, count)| (Reverse(*count), kind.display_name()));
+    let mut by_tag: Vec<_> = tags.into_iter().map(|(tag, count)| (tag.to_string(), count)).collect();
+    by_tag.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let dated = |c: &db::Credential| (c.name.clone(), c.created_at);
+    let scored = strengths.len();
+    Ok(VaultStats {
+        total: credentials.len(),
+        by_type,
+        by_tag,
+        untagged,
+        trashed: db::get_trash(conn)?.len(),
+        audit_entries: db::count_audit_logs(conn)?,
+        oldest: credentials.iter().min_by_key(|c| c.created_at).map(dated),
+        newest: credentials.iter().max_by_key(|c| c.created_at).map(dated),
+        average_strength: (scored > 0).then(|| strengths.iter().sum::<u32>() / scored as u32),
+        scored,
+        file_size,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::vault::credential::create_credential;
+
+    #[test]
+    fn test_collect() {
+        let db = Database::open_in_memory().unwrap();
+        let conn = db.conn();
+        let dek = DataEncryptionKey::generate();
+        let work = vec!["work".to_string()];
+
+        create_credential(conn, &dek, "GitHub".into(), CredentialType::Password, "password", None, None, work.clone(), None, None, &[]).unwrap();
+        create_credential(conn, &dek, "AWS".into(), CredentialType::ApiKey, "AKIA-long-random-key-1234", None, None, work, None, None, &[]).unwrap();
+        create_credential(conn, &dek, "Recipe".into(), CredentialType::Note, "flour", None, None, vec![], None, None, &[]).unwrap();
+
+        let stats = collect(conn, &dek, 4096).unwrap();
+        assert_eq!(stats.total, 3);
+        assert_eq!(stats.by_tag, vec![("work".to_string(), 2)]);
+        assert_eq!(stats.untagged, 1);
+        assert_eq!(stats.by_type.len(), 3);
+        assert_eq!(stats.scored, 2);
+        assert!(stats.average_strength.is_some());
+        assert!(stats.oldest.unwrap().1 <= stats.newest.unwrap().1);
+        assert_eq!(stats.file_size, 4096);
+    }
+}
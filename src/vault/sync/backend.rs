@@ -0,0 +1,226 @@
+//! Remote storage backends for vault sync
+//!
+//! A backend stores one opaque file: the vault database snapshot. WebDAV and
+//! S3 are spoken through the system `curl`, with credentials passed on its
+//! stdin so they never show up in the process list. The folder backend
+//! covers mounted or otherwise synchronised directories.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::vault::{VaultError, VaultResult};
+
+/// Environment variable holding the WebDAV password
+pub const WEBDAV_PASSWORD_ENV: &str = "VAULT_SYNC_PASSWORD";
+
+/// Version of the remote copy as reported by the backend
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteVersion {
+    /// Opaque tag that changes whenever the remote copy does (ETag, mtime)
+    pub tag: String,
+    pub modified: Option<DateTime<Utc>>,
+}
+
+pub trait SyncBackend {
+    /// Human-readable location for messages
+    fn describe(&self) -> String;
+
+    /// Version of the remote copy, or None if there is none yet
+    fn stat(&self) -> VaultResult<Option<RemoteVersion>>;
+
+    fn download(&self, dest: &Path) -> VaultResult<()>;
+
+    fn upload(&self, src: &Path) -> VaultResult<()>;
+}
+
+/// Persisted backend settings; secrets come from the environment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum BackendConfig {
+    Folder { path: PathBuf },
+    WebDav { url: String, user: Option<String> },
+    S3 { url: String, region: String },
+}
+
+impl BackendConfig {
+    pub fn open(&self) -> VaultResult<Box<dyn SyncBackend>> {
+        Ok(match self {
+            Self::Folder { path } => Box::new(FolderBackend { path: path.clone() }),
+            Self::WebDav { url, user } => {
+                let auth = match user {
+                    Some(user) => {
+                        let password = env_secret(WEBDAV_PASSWORD_ENV)?;
+                        Some(format!("user = {}\n", curl_quote(&format!("{}:{}", user, password))))
+                    }
+                    None => None,
+                };
+                Box::new(CurlBackend { url: url.clone(), config: auth.unwrap_or_default() })
+            }
+            Self::S3 { url, region } => {
+                let key = env_secret("AWS_ACCESS_KEY_ID")?;
+                let secret = env_secret("AWS_SECRET_ACCESS_KEY")?;
+                let mut config = format!("user = {}\n", curl_quote(&format!("{}:{}", key, secret)));
+                config.push_str(&format!("aws-sigv4 = {}\n", curl_quote(&format!("aws:amz:{}:s3", region))));
+                Box::new(CurlBackend { url: url.clone(), config })
+            }
+        })
+    }
+}
+
+fn env_secret(name: &str) -> VaultResult<String> {
+    std::env::var(name).map_err(|_| VaultError::OperationFailed(format!("{} is not set", name)))
+}
+
+/// A file in a local directory (mounted share, Syncthing folder, USB drive)
+pub struct FolderBackend {
+    pub path: PathBuf,
+}
+
+impl SyncBackend for FolderBackend {
+    fn describe(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn stat(&self) -> VaultResult<Option<RemoteVersion>> {
+        let meta = match std::fs::metadata(&self.path) {
+            Ok(meta) => meta,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(VaultError::IoError(e.to_string())),
+        };
+        let modified = meta.modified().ok().map(DateTime::<Utc>::from);
+        let stamp = modified.map(|m| m.timestamp_nanos_opt().unwrap_or_default()).unwrap_or_default();
+        Ok(Some(RemoteVersion { tag: format!("{}-{}", stamp, meta.len()), modified }))
+    }
+
+    fn download(&self, dest: &Path) -> VaultResult<()> {
+        std::fs::copy(&self.path, dest).map(|_| ()).map_err(|e| VaultError::IoError(e.to_string()))
+    }
+
+    fn upload(&self, src: &Path) -> VaultResult<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| VaultError::IoError(e.to_string()))?;
+        }
+        // Copy next to the target and rename so readers never see half a file
+        let partial = self.path.with_extension("partial");
+        std::fs::copy(src, &partial).map_err(|e| VaultError::IoError(e.to_string()))?;
+        std::fs::rename(&partial, &self.path).map_err(|e| VaultError::IoError(e.to_string()))
+    }
+}
+
+/// An object behind a URL: WebDAV with basic auth, or S3 with SigV4 signing
+pub struct CurlBackend {
+    url: String,
+    /// curl config lines (credentials, signing) fed through stdin
+    config: String,
+}
+
+impl CurlBackend {
+    fn run(&self, args: &[&str]) -> VaultResult<Output> {
+        let mut child = Command::new("curl")
+            .args(["--silent", "--show-error", "--config", "-"])
+            .args(args)
+            .arg(&self.url)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| VaultError::IoError(format!("Failed to spawn curl: {}", e)))?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(self.config.as_bytes()).map_err(|e| VaultError::IoError(e.to_string()))?;
+        }
+        child.wait_with_output().map_err(|e| VaultError::IoError(e.to_string()))
+    }
+
+    fn run_checked(&self, args: &[&str]) -> VaultResult<()> {
+        let output = self.run(args)?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(VaultError::OperationFailed(format!("curl failed for {}: {}", self.url, stderr.trim())));
+        }
+        Ok(())
+    }
+}
+
+impl SyncBackend for CurlBackend {
+    fn describe(&self) -> String {
+        self.url.clone()
+    }
+
+    fn stat(&self) -> VaultResult<Option<RemoteVersion>> {
+        let output = self.run(&["--head", "--write-out", "\n%{http_code}"])?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(VaultError::OperationFailed(format!("curl failed for {}: {}", self.url, stderr.trim())));
+        }
+        parse_head(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    fn download(&self, dest: &Path) -> VaultResult<()> {
+        let dest = dest.to_str().ok_or_else(|| VaultError::IoError("non UTF-8 path".into()))?;
+        self.run_checked(&["--fail", "--output", dest])
+    }
+
+    fn upload(&self, src: &Path) -> VaultResult<()> {
+        let src = src.to_str().ok_or_else(|| VaultError::IoError("non UTF-8 path".into()))?;
+        self.run_checked(&["--fail", "--upload-file", src])
+    }
+}
+
+/// Parse `curl --head --write-out '\n%{http_code}'` output
+fn parse_head(output: &str) -> VaultResult<Option<RemoteVersion>> {
+    let (headers, status) = output.trim_end().rsplit_once('\n').unwrap_or(("", output.trim()));
+    match status.trim() {
+        "404" => return Ok(None),
+        code if code.starts_with('2') => {}
+        code => return Err(VaultError::OperationFailed(format!("remote returned HTTP {}", code))),
+    }
+
+    let header = |name: &str| {
+        headers.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.trim().eq_ignore_ascii_case(name).then(|| value.trim().to_string())
+        })
+    };
+    let modified = header("last-modified")
+        .and_then(|v| DateTime::parse_from_rfc2822(&v).ok())
+        .map(|d| d.with_timezone(&Utc));
+    let tag = header("etag")
+        .or_else(|| modified.map(|m| m.to_rfc3339()))
+        .ok_or_else(|| VaultError::OperationFailed("remote sent neither ETag nor Last-Modified".into()))?;
+
+    Ok(Some(RemoteVersion { tag, modified }))
+}
+
+/// Quote a value for a curl config file
+fn curl_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_head() {
+        let ok = "HTTP/1.1 200 OK\r\nETag: \"abc\"\r\nLast-Modified: Tue, 15 Oct 2024 10:00:00 GMT\r\n\r\n\n200";
+        let version = parse_head(ok).unwrap().unwrap();
+        assert_eq!(version.tag, "\"abc\"");
+        assert_eq!(version.modified.unwrap().to_rfc3339(), "2024-10-15T10:00:00+00:00");
+
+        let no_etag = "HTTP/2 200\r\nlast-modified: Tue, 15 Oct 2024 10:00:00 GMT\r\n\r\n\n200";
+        assert_eq!(parse_head(no_etag).unwrap().unwrap().tag, "2024-10-15T10:00:00+00:00");
+
+        assert_eq!(parse_head("HTTP/1.1 404 Not Found\r\n\r\n\n404").unwrap(), None);
+        assert!(parse_head("HTTP/1.1 401 Unauthorized\r\n\r\n\n401").is_err());
+    }
+
+    #[test]
+    fn test_curl_quote() {
+        assert_eq!(curl_quote(r#"me:p"a\ss"#), r#""me:p\"a\\ss""#);
+    }
+}
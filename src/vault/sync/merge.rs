@@ -0,0 +1,141 @@
+//! Record-level merge of two copies of the same vault
+//!
+//! Credentials are compared by ID: the copy with the later `updated_at`
+//! wins, and a tombstone wins over any edit made before the deletion. Both
+//! copies must share the data encryption key, which holds as long as they
+//! carry the same wrapped DEK, and the same vault format, as records are
+//! copied as stored. Audit logs are not merged; each device keeps its own.
+//! Merged credentials in a vault with encrypted metadata get their search
+//! tokens on its next unlock.
+
+use std::collections::HashMap;
+
+use rusqlite::Connection;
+
+use crate::db;
+
+use crate::vault::{metadata, VaultResult};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MergeReport {
+    pub added: usize,
+    pub updated: usize,
+    pub deleted: usize,
+}
+
+impl MergeReport {
+    pub fn is_empty(&self) -> bool {
+        self.added + self.updated + self.deleted == 0
+    }
+}
+
+fn wrapped_dek(conn: &Connection) -> Option<String> {
+    conn.query_row("SELECT value FROM metadata WHERE key = 'wrapped_dek'", [], |row| row.get(0)).ok()
+}
+
+/// Whether both databases are encrypted with the same key material and format
+pub fn can_merge(local: &Connection, remote: &Connection) -> bool {
+    matches!((wrapped_dek(local), wrapped_dek(remote)), (Some(a), Some(b)) if a == b)
+        && metadata::is_encrypted(local) == metadata::is_encrypted(remote)
+}
+
+/// Fold the credentials and tombstones of `remote` into `local`
+pub fn merge_into(local: &Connection, remote: &Connection) -> VaultResult<MergeReport> {
+    let mut report = MergeReport::default();
+    let existing: HashMap<_, _> = db::get_all_credentials(local)?
+        .into_iter()
+        .map(|c| (c.id.clone(), c.updated_at))
+        .collect();
+    let local_tombstones: HashMap<_, _> = db::get_tombstones(local)?.into_iter().collect();
+
+    db::in_transaction(local, || -> VaultResult<()> {
+        for cred in db::get_all_credentials(remote)? {
+            match existing.get(&cred.id) {
+                Some(updated_at) if cred.updated_at > *updated_at => {
+                    db::replace_credential(local, &cred)?;
+                    report.updated += 1;
+                }
+                Some(_) => {}
+                None if local_tombstones.get(&cred.id).is_some_and(|t| *t >= cred.updated_at) => {}
+                None => {
+                    db::replace_credential(local, &cred)?;
+                    report.added += 1;
+                }
+            }
+        }
+
+        for (id, deleted_at) in db::get_tombstones(remote)? {
+            match existing.get(&id) {
+                Some(updated_at) if *updated_at <= deleted_at => {
+                    db::delete_credential(local, &id)?;
+                    report.deleted += 1;
+                }
+                Some(_) => continue,
+                None => {}
+            }
+            db::record_tombstone(local, &id, deleted_at)?;
+        }
+        Ok(())
+    })?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Local};
+
+    use crate::db::{Credential, CredentialType, Database};
+
+    fn cred(id: &str, name: &str, minutes_ago: i64) -> Credential {
+        let mut cred = Credential::new(name.to_string(), CredentialType::Password, "enc".to_string());
+        cred.id = id.to_string();
+        cred.updated_at = Local::now() - Duration::minutes(minutes_ago);
+        cred
+    }
+
+    #[test]
+    fn test_merge_records() {
+        let local = Database::open_in_memory().unwrap();
+        let remote = Database::open_in_memory().unwrap();
+        let (l, r) = (local.conn(), remote.conn());
+
+        // Edited on both sides: the later edit wins
+        db::create_credential(l, &cred("a", "local-old", 10)).unwrap();
+        db::create_credential(r, &cred("a", "remote-new", 5)).unwrap();
+        db::create_credential(l, &cred("b", "local-new", 1)).unwrap();
+        db::create_credential(r, &cred("b", "remote-old", 20)).unwrap();
+
+        // Only on the remote side
+        db::create_credential(r, &cred("c", "added", 3)).unwrap();
+
+        // Deleted remotely after the last local edit
+        db::create_credential(l, &cred("d", "doomed", 30)).unwrap();
+        db::record_tombstone(r, "d", Local::now() - Duration::minutes(2)).unwrap();
+
+        // Deleted locally, but edited remotely afterwards: the edit survives
+        db::create_credential(r, &cred("e", "revived", 1)).unwrap();
+        db::record_tombstone(l, "e", Local::now() - Duration::minutes(5)).unwrap();
+
+        // Deleted locally after the last remote edit: stays deleted
+        db::create_credential(r, &cred("f", "stale", 60)).unwrap();
+        db::record_tombstone(l, "f", Local::now() - Duration::minutes(5)).unwrap();
+
+        let report = merge_into(l, r).unwrap();
+        assert_eq!(report, MergeReport { added: 2, updated: 1, deleted: 1 });
+
+        let names: HashMap<_, _> = db::get_all_credentials(l).unwrap().into_iter().map(|c| (c.id, c.name)).collect();
+        assert_eq!(names["a"], "remote-new");
+        assert_eq!(names["b"], "local-new");
+        assert_eq!(names["c"], "added");
+        assert_eq!(names["e"], "revived");
+        assert!(!names.contains_key("d"));
+        assert!(!names.contains_key("f"));
+
+        let tombstones: Vec<_> = db::get_tombstones(l).unwrap().into_iter().map(|(id, _)| id).collect();
+        assert!(tombstones.contains(&"d".to_string()));
+        assert!(!tombstones.contains(&"e".to_string()));
+
+        assert!(merge_into(l, r).unwrap().is_empty());
+    }
+}
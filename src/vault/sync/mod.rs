@@ -0,0 +1,392 @@
+//! Mirror the vault database to remote storage
+//!
+//! The whole database is synced as one file. A state file next to the vault
+//! remembers the backend, the hash of the last synced local snapshot and the
+//! remote version tag, which tells which side changed since. If only one
+//! side changed it wins. If both did, copies of the same vault are merged
+//! credential by credential (see [`merge`]); unrelated copies fall back to
+//! the more recently modified one winning (last writer wins), with the
+//! overwritten copy kept as a snapshot in `sync-snapshots/` next to the vault.
+
+pub mod backend;
+pub mod merge;
+
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Local, Utc};
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::db::{self, Database, DatabaseConfig};
+
+use super::backup;
+use super::{VaultError, VaultResult};
+
+pub use backend::{BackendConfig, RemoteVersion, SyncBackend};
+pub use merge::MergeReport;
+
+/// What a sync run did
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncOutcome {
+    UpToDate,
+    Uploaded,
+    Downloaded,
+    /// Both changed; remote records were merged in and the result uploaded
+    Merged(MergeReport),
+    /// Both changed; the local copy won and the remote one was saved here
+    LocalWon { snapshot: PathBuf },
+    /// Both changed; the remote copy won and the local one was saved here
+    RemoteWon { snapshot: PathBuf },
+}
+
+/// Sync bookkeeping stored in `<vault>.sync.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncState {
+    pub backend: BackendConfig,
+    pub local_hash: Option<String>,
+    pub remote_tag: Option<String>,
+}
+
+impl SyncState {
+    pub fn new(backend: BackendConfig) -> Self {
+        Self { backend, local_hash: None, remote_tag: None }
+    }
+
+    pub fn path_for(vault_path: &Path) -> PathBuf {
+        let mut name = vault_path.file_name().unwrap_or_default().to_os_string();
+        name.push(".sync.json");
+        vault_path.with_file_name(name)
+    }
+
+    pub fn load(vault_path: &Path) -> VaultResult<Option<Self>> {
+        let content = match std::fs::read_to_string(Self::path_for(vault_path)) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(VaultError::IoError(e.to_string())),
+        };
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| VaultError::OperationFailed(format!("Invalid sync state: {}", e)))
+    }
+
+    pub fn save(&self, vault_path: &Path) -> VaultResult<()> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| VaultError::OperationFailed(e.to_string()))?;
+        std::fs::write(Self::path_for(vault_path), json).map_err(|e| VaultError::IoError(e.to_string()))
+    }
+}
+
+/// Scratch file next to the vault, removed when dropped
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(vault_path: &Path, suffix: &str) -> Self {
+        let mut name = std::ffi::OsString::from(".");
+        name.push(vault_path.file_name().unwrap_or_default());
+        name.push(format!(".sync-{}", suffix));
+        let path = vault_path.with_file_name(name);
+        let _ = std::fs::remove_file(&path);
+        Self(path)
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn io_err(e: std::io::Error) -> VaultError {
+    VaultError::IoError(e.to_string())
+}
+
+/// Write a consistent copy of the database (including any WAL content) to `dest`
+fn snapshot_database(vault_path: &Path, dest: &Path) -> VaultResult<()> {
+    // Without the password an encrypted database can only be copied as it
+    // is, which misses changes still in the WAL of a vault open elsewhere
+    if db::is_encrypted_file(vault_path) {
+        if wal_in_use(vault_path) {
+            return Err(VaultError::OperationFailed("The encrypted vault is open elsewhere; close it and sync again".into()));
+        }
+        std::fs::copy(vault_path, dest).map_err(io_err)?;
+        return Ok(());
+    }
+    let conn = Connection::open_with_flags(vault_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let _ = std::fs::remove_file(dest);
+    let dest = dest.to_str().ok_or_else(|| VaultError::IoError("non UTF-8 path".into()))?;
+    conn.execute("VACUUM INTO ?1", [dest])?;
+    Ok(())
+}
+
+fn hash_file(path: &Path) -> VaultResult<String> {
+    let bytes = std::fs::read(path).map_err(io_err)?;
+    Ok(hex::encode(Sha256::digest(&bytes)))
+}
+
+fn wal_in_use(vault_path: &Path) -> bool {
+    let mut wal = vault_path.as_os_str().to_os_string();
+    wal.push("-wal");
+    std::fs::metadata(PathBuf::from(wal)).is_ok_and(|m| m.len() > 0)
+}
+
+/// Last write to the vault, counting the WAL file
+fn local_modified(vault_path: &Path) -> Option<DateTime<Utc>> {
+    let mut wal = vault_path.as_os_str().to_os_string();
+    wal.push("-wal");
+    [vault_path.to_path_buf(), PathBuf::from(wal)]
+        .iter()
+        .filter_map(|p| std::fs::metadata(p).and_then(|m| m.modified()).ok())
+        .max()
+        .map(DateTime::<Utc>::from)
+}
+
+/// Where copies a sync overwrote are kept, next to the vault
+pub fn snapshot_dir(vault_path: &Path) -> PathBuf {
+    vault_path.with_file_name("sync-snapshots")
+}
+
+fn snapshot_path(vault_path: &Path, side: &str) -> VaultResult<PathBuf> {
+    let dir = snapshot_dir(vault_path);
+    std::fs::create_dir_all(&dir).map_err(io_err)?;
+    let stem = vault_path.file_stem().unwrap_or_default().to_string_lossy();
+    let stamp = Local::now().format("%Y%m%d-%H%M%S");
+    Ok(dir.join(format!("{}-{}-{}.db", stem, side, stamp)))
+}
+
+/// Sync the vault at `vault_path` with `backend`, updating `state`
+///
+/// The vault must not be open elsewhere while its file may be replaced.
+pub fn sync(vault_path: &Path, backend: &dyn SyncBackend, state: &mut SyncState) -> VaultResult<SyncOutcome> {
+    if !vault_path.exists() {
+        return Err(VaultError::NotFound);
+    }
+
+    let local = TempFile::new(vault_path, "local");
+    snapshot_database(vault_path, &local.0)?;
+    let local_hash = hash_file(&local.0)?;
+    let remote = backend.stat()?;
+
+    let local_changed = state.local_hash.as_deref() != Some(local_hash.as_str());
+    let remote_changed = remote.as_ref().map(|r| &r.tag) != state.remote_tag.as_ref();
+
+    let outcome = match &remote {
+        None => upload(backend, &local.0, state)?,
+        Some(_) if !local_changed && !remote_changed => SyncOutcome::UpToDate,
+        Some(_) if !remote_changed => upload(backend, &local.0, state)?,
+        Some(_) if !local_changed => {
+            download(vault_path, backend)?;
+            SyncOutcome::Downloaded
+        }
+        Some(version) => match merge_remote(vault_path, backend)? {
+            Some(report) => {
+                snapshot_database(vault_path, &local.0)?;
+                upload(backend, &local.0, state)?;
+                SyncOutcome::Merged(report)
+            }
+            None => resolve_conflict(vault_path, backend, version, &local.0, state)?,
+        },
+    };
+
+    if matches!(outcome, SyncOutcome::Downloaded | SyncOutcome::RemoteWon { .. }) {
+        state.remote_tag = remote.map(|r| r.tag);
+        snapshot_database(vault_path, &local.0)?;
+    }
+    state.local_hash = Some(hash_file(&local.0)?);
+    Ok(outcome)
+}
+
+fn upload(backend: &dyn SyncBackend, local: &Path, state: &mut SyncState) -> VaultResult<SyncOutcome> {
+    backend.upload(local)?;
+    state.remote_tag = backend.stat()?.map(|r| r.tag);
+    Ok(SyncOutcome::Uploaded)
+}
+
+fn download(vault_path: &Path, backend: &dyn SyncBackend) -> VaultResult<()> {
+    let incoming = TempFile::new(vault_path, "remote");
+    backend.download(&incoming.0)?;
+    backup::validate_vault_file(&incoming.0)?;
+    backup::replace_database(vault_path, &incoming.0)
+}
+
+/// Merge the remote records into the local vault, if both share their keys
+fn merge_remote(vault_path: &Path, backend: &dyn SyncBackend) -> VaultResult<Option<MergeReport>> {
+    let incoming = TempFile::new(vault_path, "remote");
+    backend.download(&incoming.0)?;
+    backup::validate_vault_file(&incoming.0)?;
+    // Records in encrypted databases are out of reach without the password
+    if db::is_encrypted_file(&incoming.0) || db::is_encrypted_file(vault_path) {
+        return Ok(None);
+    }
+
+    let remote = Database::open(DatabaseConfig::with_path(&incoming.0))?;
+    let local = Database::open(DatabaseConfig::with_path(vault_path))?;
+    if !merge::can_merge(local.conn(), remote.conn()) {
+        return Ok(None);
+    }
+    merge::merge_into(local.conn(), remote.conn()).map(Some)
+}
+
+/// Both sides changed: the newer copy wins, the other is kept as a snapshot
+fn resolve_conflict(
+    vault_path: &Path,
+    backend: &dyn SyncBackend,
+    remote: &RemoteVersion,
+    local: &Path,
+    state: &mut SyncState,
+) -> VaultResult<SyncOutcome> {
+    let local_time = local_modified(vault_path).unwrap_or_else(|| SystemTime::UNIX_EPOCH.into());
+    let remote_newer = remote.modified.is_some_and(|m| m > local_time);
+
+    if remote_newer {
+        let snapshot = snapshot_path(vault_path, "local")?;
+        std::fs::copy(local, &snapshot).map_err(io_err)?;
+        download(vault_path, backend)?;
+        return Ok(SyncOutcome::RemoteWon { snapshot });
+    }
+
+    let snapshot = snapshot_path(vault_path, "remote")?;
+    backend.download(&snapshot)?;
+    upload(backend, local, state)?;
+    Ok(SyncOutcome::LocalWon { snapshot })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use backend::FolderBackend;
+    use tempfile::TempDir;
+
+    use crate::db::CredentialType;
+    use crate::vault::credential::create_credential;
+    use crate::vault::{Vault, VaultConfig};
+
+    fn add(vault: &Vault, name: &str) {
+        let conn = vault.db().unwrap().conn();
+        create_credential(conn, vault.dek().unwrap(), name.to_string(), CredentialType::Password, "s", None, None, vec![], None, None, &[]).unwrap();
+    }
+
+    fn names(vault: &mut Vault) -> Vec<String> {
+        vault.unlock("correct horse").unwrap();
+        let mut names: Vec<_> = crate::db::get_all_credentials(vault.db().unwrap().conn())
+            .unwrap()
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        vault.lock();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_sync_between_two_devices() {
+        let dir = TempDir::new().unwrap();
+        let remote = BackendConfig::Folder { path: dir.path().join("remote").join("vault.db") };
+        let backend = remote.open().unwrap();
+
+        let path_a = dir.path().join("a").join("vault.db");
+        let mut vault_a = Vault::new(VaultConfig::with_path(&path_a));
+        vault_a.initialize("correct horse").unwrap();
+        add(&vault_a, "github");
+        vault_a.lock();
+
+        let mut state_a = SyncState::new(remote.clone());
+        assert_eq!(sync(&path_a, backend.as_ref(), &mut state_a).unwrap(), SyncOutcome::Uploaded);
+        assert_eq!(sync(&path_a, backend.as_ref(), &mut state_a).unwrap(), SyncOutcome::UpToDate);
+
+        // A second device starting from a copy of the same vault
+        let path_b = dir.path().join("b").join("vault.db");
+        std::fs::create_dir_all(path_b.parent().unwrap()).unwrap();
+        let backend_b = FolderBackend { path: dir.path().join("remote").join("vault.db") };
+        let mut state_b = SyncState::new(remote.clone());
+        std::fs::copy(&path_a, &path_b).unwrap();
+        let mut vault_b = Vault::new(VaultConfig::with_path(&path_b));
+        assert_eq!(names(&mut vault_b), vec!["github"]);
+
+        let first = sync(&path_b, &backend_b, &mut state_b).unwrap();
+        assert_eq!(first, SyncOutcome::Merged(MergeReport::default()));
+        assert_eq!(sync(&path_b, &backend_b, &mut state_b).unwrap(), SyncOutcome::UpToDate);
+
+        // A change on B travels to A
+        vault_b.unlock("correct horse").unwrap();
+        add(&vault_b, "gitlab");
+        vault_b.lock();
+        assert_eq!(sync(&path_b, &backend_b, &mut state_b).unwrap(), SyncOutcome::Uploaded);
+        assert_eq!(sync(&path_a, backend.as_ref(), &mut state_a).unwrap(), SyncOutcome::Downloaded);
+        assert_eq!(names(&mut vault_a), vec!["github", "gitlab"]);
+
+        // Concurrent changes on both devices are merged record by record
+        vault_b.unlock("correct horse").unwrap();
+        add(&vault_b, "from-b");
+        let gitlab = crate::db::get_all_credentials(vault_b.db().unwrap().conn())
+            .unwrap()
+            .into_iter()
+            .find(|c| c.name == "gitlab")
+            .unwrap();
+        crate::db::delete_credential(vault_b.db().unwrap().conn(), &gitlab.id).unwrap();
+        vault_b.lock();
+        assert_eq!(sync(&path_b, &backend_b, &mut state_b).unwrap(), SyncOutcome::Uploaded);
+
+        vault_a.unlock("correct horse").unwrap();
+        add(&vault_a, "from-a");
+        vault_a.lock();
+        let report = MergeReport { added: 1, updated: 0, deleted: 1 };
+        assert_eq!(sync(&path_a, backend.as_ref(), &mut state_a).unwrap(), SyncOutcome::Merged(report));
+        assert_eq!(names(&mut vault_a), vec!["from-a", "from-b", "github"]);
+
+        assert_eq!(sync(&path_b, &backend_b, &mut state_b).unwrap(), SyncOutcome::Downloaded);
+        assert_eq!(names(&mut vault_b), vec!["from-a", "from-b", "github"]);
+
+        state_a.save(&path_a).unwrap();
+        assert_eq!(SyncState::load(&path_a).unwrap(), Some(state_a));
+    }
+
+    #[test]
+    fn test_unrelated_vaults_last_writer_wins() {
+        let dir = TempDir::new().unwrap();
+        let backend = FolderBackend { path: dir.path().join("remote.db") };
+        let config = BackendConfig::Folder { path: backend.path.clone() };
+
+        let path_a = dir.path().join("a").join("vault.db");
+        let mut vault_a = Vault::new(VaultConfig::with_path(&path_a));
+        vault_a.initialize("correct horse").unwrap();
+        add(&vault_a, "from-a");
+        vault_a.lock();
+        sync(&path_a, &backend, &mut SyncState::new(config.clone())).unwrap();
+
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let path_b = dir.path().join("b").join("vault.db");
+        let mut vault_b = Vault::new(VaultConfig::with_path(&path_b));
+        vault_b.initialize("correct horse").unwrap();
+        add(&vault_b, "from-b");
+        vault_b.lock();
+
+        let SyncOutcome::LocalWon { snapshot } = sync(&path_b, &backend, &mut SyncState::new(config)).unwrap() else {
+            panic!("expected the newer local copy to win");
+        };
+        let mut kept = Vault::new(VaultConfig::with_path(&snapshot));
+        assert_eq!(names(&mut kept), vec!["from-a"]);
+        assert_eq!(names(&mut vault_b), vec!["from-b"]);
+    }
+
+    #[test]
+    fn test_rejects_invalid_remote() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("vault.db");
+        Vault::new(VaultConfig::with_path(&path)).initialize("correct horse").unwrap();
+
+        let remote = dir.path().join("remote.db");
+        std::fs::write(&remote, "not a database").unwrap();
+        let backend = FolderBackend { path: remote };
+        let mut state = SyncState::new(BackendConfig::Folder { path: backend.path.clone() });
+        state.local_hash = Some({
+            let tmp = TempFile::new(&path, "test");
+            snapshot_database(&path, &tmp.0).unwrap();
+            hash_file(&tmp.0).unwrap()
+        });
+
+        assert!(sync(&path, &backend, &mut state).is_err());
+        let mut vault = Vault::new(VaultConfig::with_path(&path));
+        assert!(vault.unlock("correct horse").is_ok());
+    }
+}
@@ -0,0 +1,120 @@
+//! Vault-wide tag edits: rename, merge and delete
+
+use rusqlite::Connection;
+
+use crate::crypto::DerivedKey;
+use crate::db::{self, AuditAction};
+
+use super::audit;
+use super::VaultResult;
+
+/// Replace every tag in `from` with `into`, or drop them when `into` is `None`,
+/// on all credentials; nested tags move or go with their parent
+///
+/// Runs in one transaction and writes an audit entry per changed credential.
+/// Returns the number of credentials changed.
+pub fn rewrite_tags(
+    conn: &Connection,
+    audit_key: &DerivedKey,
+    from: &[String],
+    into: Option<&str>,
+    details: &str,
+) -> VaultResult<usize> {
+    let mut changed = 0;
+
+    db::in_transaction(conn, || -> VaultResult<()> {
+        for mut cred in db::get_all_credentials(conn)? {
+            if !retag(&mut cred.tags, from, into) {
+                continue;
+            }
+            db::update_credential(conn, &cred)?;
+            audit::log_action(
+                conn,
+                audit_key,
+                AuditAction::Update,
+                Some(&cred.id),
+                Some(&cred.name),
+                cred.username.as_deref(),
+                Some(details),
+            )?;
+            changed += 1;
+        }
+        Ok(())
+    })?;
+    Ok(changed)
+}
+
+/// Rewrite one tag list in place, keeping order and dropping duplicates
+pub fn retag(tags: &mut Vec<String>, from: &[String], into: Option<&str>) -> bool {
+    if !tags.iter().any(|t| matched_suffix(t, from).is_some()) {
+        return false;
+    }
+    let mut rewritten: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags.drain(..) {
+        let tag = match (matched_suffix(&tag, from), into) {
+            (None, _) => tag,
+            (Some(suffix), Some(into)) => format!("{}{}", into, suffix),
+            (Some(_), None) => continue,
+        };
+        if !rewritten.contains(&tag) {
+            rewritten.push(tag);
+        }
+    }
+    *tags = rewritten;
+    true
+}
+
+/// What follows the matching tag in `from`: `""` for the tag itself, `/child` for a nested one
+fn matched_suffix<'a>(tag: &'a str, from: &[String]) -> Option<&'a str> {
+    from.iter().find_map(|f| {
+        let suffix = tag.strip_prefix(f.as_str())?;
+        (suffix.is_empty() || suffix.starts_with('/')).then_some(suffix)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{KeyHierarchy, MasterKey};
+    use crate::db::{Credential, CredentialType, Database};
+
+    fn tags(list: &[&str]) -> Vec<String> {
+        list.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_retag() {
+        let mut list = tags(&["work", "dev", "api"]);
+        assert!(retag(&mut list, &tags(&["dev"]), Some("api")));
+        assert_eq!(list, tags(&["work", "api"]));
+
+        assert!(retag(&mut list, &tags(&["work"]), None));
+        assert_eq!(list, tags(&["api"]));
+
+        assert!(!retag(&mut list, &tags(&["missing"]), Some("x")));
+
+        let mut nested = tags(&["work/aws", "workshop", "work"]);
+        assert!(retag(&mut nested, &tags(&["work"]), Some("job")));
+        assert_eq!(nested, tags(&["job/aws", "workshop", "job"]));
+        assert!(retag(&mut nested, &tags(&["job"]), None));
+        assert_eq!(nested, tags(&["workshop"]));
+    }
+
+    #[test]
+    fn test_rewrite_tags_audits_changes() {
+        let db = Database::open_in_memory().unwrap();
+        let keys = KeyHierarchy::new(MasterKey::from_bytes([0x42u8; 32])).unwrap();
+        let audit_key = keys.derive_audit_key().unwrap();
+
+        let mut tagged = Credential::new("GitHub".into(), CredentialType::Password, "enc1".into());
+        tagged.tags = tags(&["dev", "git"]);
+        let untagged = Credential::new("Bank".into(), CredentialType::Password, "enc2".into());
+        db::create_credential(db.conn(), &tagged).unwrap();
+        db::create_credential(db.conn(), &untagged).unwrap();
+
+        let changed = rewrite_tags(db.conn(), &audit_key, &tags(&["dev"]), Some("code"), "Renamed tag").unwrap();
+        assert_eq!(changed, 1);
+        assert_eq!(db::get_credential(db.conn(), &tagged.id).unwrap().tags, tags(&["code", "git"]));
+        assert_eq!(audit::get_recent_logs(db.conn(), 10).unwrap().len(), 1);
+    }
+}
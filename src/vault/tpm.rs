@@ -0,0 +1,129 @@
+//! TPM2 binding through the system `tpm2-tools`
+//!
+//! A random secret is sealed to the TPM under a policy on PCRs 0, 2, 4 and 7
+//! (firmware, option ROMs, boot loader and Secure Boot state) and mixed into
+//! the master key, so the wrapped DEK only opens on the enrolled machine
+//! booted the same way. The vault stores the sealed blobs, which nothing but
+//! that TPM can unseal. Like the YubiKey's answer, the secret doubles as a
+//! recovery code for a new machine or a firmware update that moves the PCRs.
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use rand::rngs::OsRng;
+use rand::RngCore;
+use zeroize::Zeroizing;
+
+use super::scratch::ScratchDir;
+use super::yubikey;
+use super::{VaultError, VaultResult};
+
+/// PCRs the secret is sealed against
+const PCRS: &str = "sha256:0,2,4,7";
+
+pub const SECRET_LEN: usize = 32;
+
+/// The sealed secret as the TPM hands it back: public and private halves
+pub struct SealedSecret {
+    pub public: Vec<u8>,
+    pub private: Vec<u8>,
+}
+
+pub fn generate_secret() -> Zeroizing<Vec<u8>> {
+    let mut secret = Zeroizing::new(vec![0u8; SECRET_LEN]);
+    OsRng.fill_bytes(&mut secret);
+    secret
+}
+
+/// Seal `secret` to this machine's TPM under the current PCR values. The
+/// object can only be unsealed through the PCR policy, never with an empty
+/// password.
+pub fn seal(secret: &[u8]) -> VaultResult<SealedSecret> {
+    let dir = ScratchDir::create("vault-tpm")?;
+    create_primary(&dir.path)?;
+    run(&dir.path, &["tpm2_pcrread", "-Q", "-o", "pcr.bin", PCRS], None)?;
+    run(&dir.path, &["tpm2_createpolicy", "-Q", "--policy-pcr", "-l", PCRS, "-f", "pcr.bin", "-L", "policy.digest"], None)?;
+    run(
+        &dir.path,
+        &[
+            "tpm2_create", "-Q", "-C", "primary.ctx", "-L", "policy.digest",
+            "-a", "fixedtpm|fixedparent|adminwithpolicy|noda",
+            "-i", "-", "-u", "seal.pub", "-r", "seal.priv",
+        ],
+        Some(secret),
+    )?;
+
+    Ok(SealedSecret { public: read(&dir.path.join("seal.pub"))?, private: read(&dir.path.join("seal.priv"))? })
+}
+
+/// Unseal the secret; fails on another machine or once the PCRs moved
+pub fn unseal(sealed: &SealedSecret) -> VaultResult<Zeroizing<Vec<u8>>> {
+    let dir = ScratchDir::create("vault-tpm")?;
+    std::fs::write(dir.path.join("seal.pub"), &sealed.public).map_err(|e| VaultError::IoError(e.to_string()))?;
+    std::fs::write(dir.path.join("seal.priv"), &sealed.private).map_err(|e| VaultError::IoError(e.to_string()))?;
+    create_primary(&dir.path)?;
+    run(&dir.path, &["tpm2_load", "-Q", "-C", "primary.ctx", "-u", "seal.pub", "-r", "seal.priv", "-c", "seal.ctx"], None)?;
+    let secret = run(&dir.path, &["tpm2_unseal", "-c", "seal.ctx", "-p", &format!("pcr:{}", PCRS)], None)?;
+
+    if secret.len() != SECRET_LEN {
+        return Err(VaultError::TpmUnavailable("unexpected tpm2_unseal output".into()));
+    }
+    Ok(secret)
+}
+
+/// The secret as a recovery code, in groups of eight hex digits
+pub fn format_recovery_code(secret: &[u8]) -> Zeroizing<String> {
+    yubikey::format_recovery_code(secret)
+}
+
+/// Read a recovery code back, ignoring case, spaces and dashes
+pub fn parse_recovery_code(code: &str) -> Option<Zeroizing<Vec<u8>>> {
+    yubikey::parse_hex_code(code, SECRET_LEN)
+}
+
+/// The storage primary key is derived afresh from the owner seed each time,
+/// so it never needs to be persisted in the TPM
+fn create_primary(dir: &Path) -> VaultResult<()> {
+    run(dir, &["tpm2_createprimary", "-Q", "-C", "o", "-c", "primary.ctx"], None).map(|_| ())
+}
+
+/// Run a tpm2-tools command in `dir`, feeding `input` on stdin; returns stdout
+fn run(dir: &Path, args: &[&str], input: Option<&[u8]>) -> VaultResult<Zeroizing<Vec<u8>>> {
+    let mut child = Command::new(args[0])
+        .args(&args[1..])
+        .current_dir(dir)
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| VaultError::TpmUnavailable(format!("cannot run {}: {}", args[0], e)))?;
+
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin.write_all(input).map_err(|e| VaultError::TpmUnavailable(format!("{}: {}", args[0], e)))?;
+    }
+    let output = child.wait_with_output().map_err(|e| VaultError::TpmUnavailable(format!("{}: {}", args[0], e)))?;
+    if !output.status.success() {
+        return Err(VaultError::TpmUnavailable(format!("{}: {}", args[0], String::from_utf8_lossy(&output.stderr).trim())));
+    }
+    Ok(Zeroizing::new(output.stdout))
+}
+
+fn read(path: &Path) -> VaultResult<Vec<u8>> {
+    std::fs::read(path).map_err(|e| VaultError::IoError(format!("Failed to read sealed secret: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recovery_code_round_trip() {
+        let secret = generate_secret();
+        let code = format_recovery_code(&secret);
+        assert_eq!(code.split('-').count(), 8);
+        assert_eq!(parse_recovery_code(&code).unwrap().as_slice(), secret.as_slice());
+        // A YubiKey recovery code is too short
+        assert!(parse_recovery_code("00010203-04050607-08090a0b-0c0d0e0f-10111213").is_none());
+    }
+}
@@ -0,0 +1,116 @@
+//! Emergency wipe: overwrite files with random bytes before deleting them
+//!
+//! On SSDs and copy-on-write filesystems an overwrite may land elsewhere
+//! than the old blocks, so this is best effort; the vault contents are
+//! encrypted either way.
+
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+use super::sync::snapshot_dir;
+
+const CHUNK: usize = 64 * 1024;
+
+/// The vault file and the SQLite files that sit next to it
+pub fn database_files(vault_path: &Path) -> Vec<PathBuf> {
+    let mut files = vec![vault_path.to_path_buf()];
+    for suffix in ["-wal", "-shm", "-journal"] {
+        let mut side = vault_path.as_os_str().to_os_string();
+        side.push(suffix);
+        files.push(PathBuf::from(side));
+    }
+    files
+}
+
+/// The full copies of the vault sync kept in `sync-snapshots/`
+pub fn sync_snapshots(vault_path: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(snapshot_dir(vault_path)) else { return Vec::new() };
+    entries
+        .filter_map(|e| Some(e.ok()?.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "db"))
+        .collect()
+}
+
+/// Overwrite `path` with random bytes, flush it to disk and delete it; a
+/// missing file is fine
+pub fn shred_file(path: &Path) -> io::Result<()> {
+    let mut file = match OpenOptions::new().write(true).open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    let mut remaining = file.metadata()?.len() as usize;
+    let mut chunk = vec![0u8; CHUNK];
+    while remaining > 0 {
+        let len = remaining.min(CHUNK);
+        OsRng.fill_bytes(&mut chunk[..len]);
+        file.write_all(&chunk[..len])?;
+        remaining -= len;
+    }
+    file.sync_all()?;
+    drop(file);
+    std::fs::remove_file(path)
+}
+
+/// Shred every file in `paths`, carrying on past failures; returns the
+/// files that could not be shredded
+pub fn shred_all(paths: &[PathBuf]) -> Vec<(PathBuf, io::Error)> {
+    paths
+        .iter()
+        .filter_map(|path| shred_file(path).err().map(|e| (path.clone(), e)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    use crate::vault::auto_export::list_exports;
+
+    #[test]
+    fn test_shred_all() {
+        let dir = TempDir::new().unwrap();
+        let vault = dir.path().join("vault.db");
+        std::fs::write(&vault, vec![7u8; CHUNK + 10]).unwrap();
+        std::fs::write(dir.path().join("vault.db-wal"), b"wal").unwrap();
+        let keep = dir.path().join("notes.txt");
+        std::fs::write(&keep, b"not ours").unwrap();
+        let exports = dir.path().join("exports");
+        std::fs::create_dir(&exports).unwrap();
+        let export = exports.join("vault-export-20240101-000000.json.age");
+        std::fs::write(&export, b"age").unwrap();
+
+        let mut paths = database_files(&vault);
+        paths.extend(list_exports(&exports).unwrap().into_iter().map(|e| e.path));
+        let failures = shred_all(&paths);
+        assert!(failures.is_empty());
+        assert!(!vault.exists());
+        assert!(!dir.path().join("vault.db-wal").exists());
+        assert!(!export.exists());
+        assert!(keep.exists());
+    }
+
+    #[test]
+    fn test_shred_sync_snapshots() {
+        let dir = TempDir::new().unwrap();
+        let vault = dir.path().join("vault.db");
+        assert!(sync_snapshots(&vault).is_empty());
+
+        let snapshots = snapshot_dir(&vault);
+        std::fs::create_dir(&snapshots).unwrap();
+        let snapshot = snapshots.join("vault-local-20240101-000000.db");
+        std::fs::write(&snapshot, b"SQLite format 3").unwrap();
+        std::fs::write(snapshots.join("README"), b"not ours").unwrap();
+
+        let found = sync_snapshots(&vault);
+        assert_eq!(found, std::slice::from_ref(&snapshot));
+        assert!(shred_all(&found).is_empty());
+        assert!(!snapshot.exists());
+    }
+}
@@ -0,0 +1,85 @@
+//! YubiKey HMAC-SHA1 challenge-response through the system `ykchalresp`
+//!
+//! The vault stores a random challenge; the token's answer is mixed into the
+//! master key, so unlocking needs the token as well as the password. The
+//! answer never changes for a given challenge and slot, which makes it
+//! usable as a recovery code: written down at setup, it stands in for a lost
+//! or broken token.
+
+use std::process::{Command, Stdio};
+
+use rand::rngs::OsRng;
+use rand::RngCore;
+use zeroize::Zeroizing;
+
+use super::{VaultError, VaultResult};
+
+/// ykchalresp takes up to 64 bytes; 32 is plenty
+pub const CHALLENGE_LEN: usize = 32;
+
+/// HMAC-SHA1 output
+const RESPONSE_LEN: usize = 20;
+
+pub fn generate_challenge() -> [u8; CHALLENGE_LEN] {
+    let mut challenge = [0u8; CHALLENGE_LEN];
+    OsRng.fill_bytes(&mut challenge);
+    challenge
+}
+
+/// Ask the token in `slot` (1 or 2) to answer `challenge`; a token set to
+/// require touch blinks until tapped
+pub fn challenge_response(slot: u8, challenge: &[u8]) -> VaultResult<Zeroizing<Vec<u8>>> {
+    let output = Command::new("ykchalresp")
+        .arg(format!("-{}", slot))
+        .arg("-x")
+        .arg(hex::encode(challenge))
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| VaultError::TokenUnavailable(format!("cannot run ykchalresp: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(VaultError::TokenUnavailable(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+    let text = Zeroizing::new(String::from_utf8_lossy(&output.stdout).trim().to_string());
+    let response = Zeroizing::new(hex::decode(text.as_str()).map_err(|_| VaultError::TokenUnavailable("unexpected ykchalresp output".into()))?);
+    if response.len() != RESPONSE_LEN {
+        return Err(VaultError::TokenUnavailable("unexpected ykchalresp output".into()));
+    }
+    Ok(response)
+}
+
+/// The response as a recovery code, in groups of eight hex digits
+pub fn format_recovery_code(response: &[u8]) -> Zeroizing<String> {
+    let hex = Zeroizing::new(hex::encode(response));
+    let groups: Vec<&str> = hex.as_bytes().chunks(8).map(|c| std::str::from_utf8(c).unwrap_or_default()).collect();
+    Zeroizing::new(groups.join("-"))
+}
+
+/// Read a recovery code back, ignoring case, spaces and dashes
+pub fn parse_recovery_code(code: &str) -> Option<Zeroizing<Vec<u8>>> {
+    parse_hex_code(code, RESPONSE_LEN)
+}
+
+/// `len` bytes from a grouped hex code like the recovery code
+pub(super) fn parse_hex_code(code: &str, len: usize) -> Option<Zeroizing<Vec<u8>>> {
+    let hex: Zeroizing<String> = Zeroizing::new(code.chars().filter(|c| !c.is_whitespace() && *c != '-').collect());
+    let bytes = Zeroizing::new(hex::decode(hex.as_str()).ok()?);
+    (bytes.len() == len).then_some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recovery_code_round_trip() {
+        let response: Vec<u8> = (0..RESPONSE_LEN as u8).collect();
+        let code = format_recovery_code(&response);
+        assert_eq!(code.as_str(), "00010203-04050607-08090a0b-0c0d0e0f-10111213");
+
+        assert_eq!(parse_recovery_code(&code).unwrap().as_slice(), response.as_slice());
+        assert_eq!(parse_recovery_code(" 00010203 04050607 08090A0B 0C0D0E0F 10111213 ").unwrap().as_slice(), response.as_slice());
+        assert!(parse_recovery_code("00010203").is_none());
+        assert!(parse_recovery_code("not a code").is_none());
+    }
+}